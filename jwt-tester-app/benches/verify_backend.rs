@@ -0,0 +1,141 @@
+//! Signature checks per second with jsonwebtoken (ring, the default backend) against aws-lc-rs
+//! with the key parsed once (the `aws-lc` feature), on the test fixture keys.
+//!
+//! cargo bench --features aws-lc --bench verify_backend
+//!
+//! `VERIFY_BENCH_ITERATIONS` sets the tokens checked per case (default 2000).
+
+use aws_lc_rs::signature::{self, ParsedPublicKey, VerificationAlgorithm};
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+struct Case {
+    alg: Algorithm,
+    private: &'static str,
+    public: &'static str,
+    aws_lc: &'static dyn VerificationAlgorithm,
+}
+
+const CASES: [Case; 5] = [
+    Case {
+        alg: Algorithm::RS256,
+        private: "rsa_private.pem",
+        public: "rsa_public.pem",
+        aws_lc: &signature::RSA_PKCS1_2048_8192_SHA256,
+    },
+    Case {
+        alg: Algorithm::PS256,
+        private: "rsa_private.pem",
+        public: "rsa_public.pem",
+        aws_lc: &signature::RSA_PSS_2048_8192_SHA256,
+    },
+    Case {
+        alg: Algorithm::ES256,
+        private: "ec256_private.pem",
+        public: "ec256_public.pem",
+        aws_lc: &signature::ECDSA_P256_SHA256_FIXED,
+    },
+    Case {
+        alg: Algorithm::ES384,
+        private: "ec384_private.pem",
+        public: "ec384_public.pem",
+        aws_lc: &signature::ECDSA_P384_SHA384_FIXED,
+    },
+    Case {
+        alg: Algorithm::EdDSA,
+        private: "ed25519_private.pem",
+        public: "ed25519_public.pem",
+        aws_lc: &signature::ED25519,
+    },
+];
+
+fn fixture(name: &str) -> Vec<u8> {
+    let path = format!("{}/tests/fixtures/{name}", env!("CARGO_MANIFEST_DIR"));
+    std::fs::read(&path).unwrap_or_else(|err| panic!("read {path}: {err}"))
+}
+
+fn pem_body(pem: &[u8]) -> Vec<u8> {
+    let body: String = std::str::from_utf8(pem)
+        .expect("PEM is UTF-8")
+        .lines()
+        .filter(|line| !line.starts_with("-----"))
+        .collect();
+    STANDARD.decode(body).expect("PEM body is base64")
+}
+
+fn signing_key(alg: Algorithm, pem: &[u8]) -> EncodingKey {
+    match alg {
+        Algorithm::ES256 | Algorithm::ES384 => EncodingKey::from_ec_pem(pem),
+        Algorithm::EdDSA => EncodingKey::from_ed_pem(pem),
+        _ => EncodingKey::from_rsa_pem(pem),
+    }
+    .expect("private key fixture")
+}
+
+fn decoding_key(alg: Algorithm, pem: &[u8]) -> DecodingKey {
+    match alg {
+        Algorithm::ES256 | Algorithm::ES384 => DecodingKey::from_ec_pem(pem),
+        Algorithm::EdDSA => DecodingKey::from_ed_pem(pem),
+        _ => DecodingKey::from_rsa_pem(pem),
+    }
+    .expect("public key fixture")
+}
+
+fn per_op(iterations: usize, mut check: impl FnMut() -> bool) -> Duration {
+    for _ in 0..iterations / 10 {
+        assert!(check());
+    }
+    let started = Instant::now();
+    for _ in 0..iterations {
+        assert!(black_box(check()));
+    }
+    started.elapsed() / iterations as u32
+}
+
+fn main() {
+    let iterations = std::env::var("VERIFY_BENCH_ITERATIONS")
+        .ok()
+        .and_then(|raw| raw.parse().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(2000);
+    let claims = serde_json::json!({ "sub": "bench", "iat": 1_700_000_000 });
+
+    println!("{iterations} checks per case, time per check");
+    println!(
+        "{:<6} {:>14} {:>14} {:>8}",
+        "alg", "jsonwebtoken", "aws-lc-rs", "speedup"
+    );
+    for case in &CASES {
+        let token = jsonwebtoken::encode(
+            &Header::new(case.alg),
+            &claims,
+            &signing_key(case.alg, &fixture(case.private)),
+        )
+        .expect("sign");
+        let (message, signature) = token.rsplit_once('.').expect("compact JWS");
+        let public = fixture(case.public);
+
+        let key = decoding_key(case.alg, &public);
+        let ring = per_op(iterations, || {
+            jsonwebtoken::crypto::verify(signature, message.as_bytes(), &key, case.alg)
+                .expect("verify")
+        });
+
+        let parsed = ParsedPublicKey::new(case.aws_lc, pem_body(&public)).expect("aws-lc key");
+        let aws_lc = per_op(iterations, || {
+            let signature = URL_SAFE_NO_PAD.decode(signature).expect("signature");
+            parsed.verify_sig(message.as_bytes(), &signature).is_ok()
+        });
+
+        println!(
+            "{:<6} {:>12.1}µs {:>12.1}µs {:>7.2}x",
+            format!("{:?}", case.alg),
+            ring.as_secs_f64() * 1e6,
+            aws_lc.as_secs_f64() * 1e6,
+            ring.as_secs_f64() / aws_lc.as_secs_f64()
+        );
+    }
+}
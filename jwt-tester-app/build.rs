@@ -0,0 +1,83 @@
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "grpc")]
+    grpc::generate();
+}
+
+/// Service stubs for `proto/jwt_tester/v1/jwt_tester.proto`. The messages are written by hand
+/// with `prost` derives in `src/commands/serve/grpc/pb.rs`, so building needs no `protoc`; a test
+/// there checks them against the `.proto` file.
+#[cfg(feature = "grpc")]
+mod grpc {
+    use tonic_build::manual::{Builder, Method, Service};
+
+    const PACKAGE: &str = "jwt_tester.v1";
+
+    fn method(name: &str, route: &str, input: &str, output: &str) -> Method {
+        Method::builder()
+            .name(name)
+            .route_name(route)
+            .input_type(format!("super::{input}"))
+            .output_type(format!("super::{output}"))
+            .codec_path("tonic::codec::ProstCodec")
+            .build()
+    }
+
+    fn services() -> Vec<Service> {
+        let service = |name: &str, methods: Vec<Method>| {
+            methods
+                .into_iter()
+                .fold(Service::builder().name(name).package(PACKAGE), |s, m| {
+                    s.method(m)
+                })
+                .build()
+        };
+        let stream = Method::builder()
+            .name("verify_stream")
+            .route_name("VerifyStream")
+            .input_type("super::VerifyRequest")
+            .output_type("super::VerifyReply")
+            .codec_path("tonic::codec::ProstCodec")
+            .client_streaming()
+            .server_streaming()
+            .build();
+        vec![
+            service(
+                "Encode",
+                vec![method("encode", "Encode", "EncodeRequest", "EncodeReply")],
+            ),
+            service(
+                "Verify",
+                vec![
+                    method("verify", "Verify", "VerifyRequest", "VerifyReply"),
+                    stream,
+                ],
+            ),
+            service(
+                "Inspect",
+                vec![method(
+                    "inspect",
+                    "Inspect",
+                    "InspectRequest",
+                    "InspectReply",
+                )],
+            ),
+            service(
+                "Vault",
+                vec![method("query", "Query", "VaultRequest", "VaultReply")],
+            ),
+        ]
+    }
+
+    pub fn generate() {
+        let out_dir = std::path::PathBuf::from(std::env::var("OUT_DIR").expect("OUT_DIR"));
+        Builder::new().build_client(false).compile(&services());
+        // Clients are only compiled into the tests.
+        let clients = out_dir.join("clients");
+        std::fs::create_dir_all(&clients).expect("create OUT_DIR/clients");
+        Builder::new()
+            .build_server(false)
+            .out_dir(&clients)
+            .compile(&services());
+    }
+}
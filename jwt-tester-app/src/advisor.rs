@@ -0,0 +1,420 @@
+use crate::error::{AppError, AppResult};
+use crate::jwt_ops;
+use crate::vault::{KeyEntry, KeyUsage, ProjectEntry};
+use jsonwebtoken::Algorithm;
+use serde::Serialize;
+use serde_json::Value;
+
+#[cfg(feature = "keygen")]
+use crate::vault::LatencyStats;
+
+/// What `advise` reads from the sample token. Nothing is verified.
+#[derive(Debug)]
+pub struct Sample {
+    /// `None` for an unsecured (`alg: none`) token.
+    pub alg: Option<Algorithm>,
+    pub header: Value,
+    pub claims: Value,
+    pub token_bytes: usize,
+}
+
+impl Sample {
+    pub fn parse(token: &str) -> AppResult<Self> {
+        let token = token.trim();
+        // Unsecured tokens may have only two segments, so decode them directly.
+        let segments: Vec<&str> = token.split('.').collect();
+        if segments.len() < 2 {
+            return Err(AppError::invalid_token(
+                "token must have 3 dot-separated segments",
+            ));
+        }
+        let header = jwt_ops::decode_segment_json(segments[0], "header")?;
+        let claims = jwt_ops::decode_payload_json(segments[1], &header)?;
+        let alg = match header.get("alg").and_then(Value::as_str) {
+            None | Some("none") => None,
+            Some(name) => Some(name.parse::<Algorithm>().map_err(|_| {
+                AppError::invalid_token(format!("unsupported alg '{name}' in token header"))
+            })?),
+        };
+        Ok(Sample {
+            alg,
+            header,
+            claims,
+            token_bytes: token.len(),
+        })
+    }
+
+    fn kid(&self) -> Option<&str> {
+        self.header.get("kid").and_then(Value::as_str)
+    }
+
+    /// `exp - iat` in seconds, when the token carries both.
+    pub fn lifetime_secs(&self) -> Option<i64> {
+        let exp = self.claims.get("exp").and_then(Value::as_i64)?;
+        let iat = self.claims.get("iat").and_then(Value::as_i64)?;
+        Some(exp.saturating_sub(iat).max(0))
+    }
+
+    fn audiences(&self) -> usize {
+        match self.claims.get("aud") {
+            Some(Value::String(_)) => 1,
+            Some(Value::Array(items)) => items.len(),
+            _ => 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    High,
+    Medium,
+    Low,
+}
+
+impl Priority {
+    pub fn label(self) -> &'static str {
+        match self {
+            Priority::High => "HIGH",
+            Priority::Medium => "MEDIUM",
+            Priority::Low => "LOW",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Finding {
+    pub id: &'static str,
+    pub priority: Priority,
+    pub message: String,
+}
+
+fn finding(id: &'static str, priority: Priority, message: impl Into<String>) -> Finding {
+    Finding {
+        id,
+        priority,
+        message: message.into(),
+    }
+}
+
+/// Where to migrate from `current`: a shared HMAC secret to RS256 (verifiers only get the public
+/// key), RSA to ES256 (smaller signatures, faster signing). EC and EdDSA are left alone.
+pub fn default_target(current: Option<Algorithm>) -> Option<Algorithm> {
+    match current {
+        None | Some(Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512) => {
+            Some(Algorithm::RS256)
+        }
+        Some(
+            Algorithm::RS256
+            | Algorithm::RS384
+            | Algorithm::RS512
+            | Algorithm::PS256
+            | Algorithm::PS384
+            | Algorithm::PS512,
+        ) => Some(Algorithm::ES256),
+        Some(Algorithm::ES256 | Algorithm::ES384 | Algorithm::EdDSA) => None,
+    }
+}
+
+pub fn alg_name(alg: Option<Algorithm>) -> String {
+    alg.map_or_else(|| "none".to_string(), |alg| format!("{alg:?}"))
+}
+
+/// Vault key kind (`hmac`, `rsa`, `ec`, `eddsa`) that signs with `alg`.
+pub fn key_kind(alg: Algorithm) -> &'static str {
+    match alg {
+        Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => "hmac",
+        Algorithm::ES256 | Algorithm::ES384 => "ec",
+        Algorithm::EdDSA => "eddsa",
+        _ => "rsa",
+    }
+}
+
+fn is_hmac(alg: Option<Algorithm>) -> bool {
+    alg.is_some_and(|alg| key_kind(alg) == "hmac")
+}
+
+/// Findings about the sample token and the project's keys, most pressing first.
+pub fn findings(
+    sample: &Sample,
+    project: &ProjectEntry,
+    keys: &[KeyEntry],
+    target: Option<Algorithm>,
+) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let current = alg_name(sample.alg);
+    if sample.alg.is_none() {
+        findings.push(finding(
+            "unsecured",
+            Priority::High,
+            "token is unsecured (alg none); sign it before planning anything else",
+        ));
+    }
+    if is_hmac(sample.alg) {
+        let audiences = sample.audiences();
+        let (priority, spread) = if audiences > 1 {
+            (
+                Priority::High,
+                format!("the token is accepted by {audiences} audiences, so each of them"),
+            )
+        } else {
+            (Priority::Medium, "every verifier".to_string())
+        };
+        findings.push(finding(
+            "shared-secret",
+            priority,
+            format!(
+                "{current} uses one shared secret: {spread} holds the key and can mint tokens; \
+                 an asymmetric algorithm gives verifiers only the public key"
+            ),
+        ));
+    }
+    if let (Some(alg), Some(target)) = (sample.alg, target) {
+        if key_kind(alg) == "rsa" && key_kind(target) != "rsa" {
+            findings.push(finding(
+                "signature-size",
+                Priority::Low,
+                format!(
+                    "{current} signatures are 256+ bytes; {target:?} keeps tokens smaller and \
+                     signs faster"
+                ),
+            ));
+        }
+    }
+    if sample.kid().is_none() {
+        findings.push(finding(
+            "no-kid",
+            Priority::Medium,
+            "token has no kid; add one so verifiers can pick the right key while old and new \
+             keys are both live",
+        ));
+    }
+    let unnamed: Vec<&str> = keys
+        .iter()
+        .filter(|key| key.kid.is_none())
+        .map(|key| key.name.as_str())
+        .collect();
+    if !unnamed.is_empty() {
+        findings.push(finding(
+            "keys-without-kid",
+            Priority::Low,
+            format!("project keys without a kid: {}", unnamed.join(", ")),
+        ));
+    }
+    let signers = keys
+        .iter()
+        .filter(|key| key.usage.allows(KeyUsage::Sign))
+        .count();
+    if signers > 1 && project.default_key_id.is_none() {
+        findings.push(finding(
+            "no-default-key",
+            Priority::Low,
+            format!(
+                "{signers} keys may sign and the project has no default key; set one so the \
+                 switch-over is a single command"
+            ),
+        ));
+    }
+    if let Some(target) = target {
+        let ready = keys
+            .iter()
+            .any(|key| key.kind == key_kind(target) && key.usage.allows(KeyUsage::Sign));
+        if !ready {
+            findings.push(finding(
+                "no-target-key",
+                Priority::Medium,
+                format!(
+                    "project has no {} key that may sign {target:?} yet",
+                    key_kind(target)
+                ),
+            ));
+        }
+    } else {
+        findings.push(finding(
+            "no-migration",
+            Priority::Low,
+            format!("{current} is already an asymmetric algorithm with compact signatures"),
+        ));
+    }
+    findings.sort_by_key(|f| f.priority as u8);
+    findings
+}
+
+/// Ordered migration steps from the sample's algorithm to `target`, with the commands that
+/// perform them where this tool can.
+pub fn checklist(sample: &Sample, project: &ProjectEntry, target: Algorithm) -> Vec<String> {
+    let kind = key_kind(target);
+    let name = format!("signing-{}", alg_name(Some(target)).to_lowercase());
+    let curve = match target {
+        Algorithm::ES384 => " --ec-curve P-384",
+        _ => "",
+    };
+    let overlap = sample.lifetime_secs().map_or_else(
+        || "the longest token lifetime you issue".to_string(),
+        |secs| humantime::format_duration(std::time::Duration::from_secs(secs as u64)).to_string(),
+    );
+    let mut steps = vec![
+        format!(
+            "Generate the new key: jwt-tester vault key generate --project {} --name {name} \
+             --kind {kind}{curve} --kid {name}",
+            project.name
+        ),
+        "Publish its public_jwk to every verifier in a JWKS next to the current key".to_string(),
+        format!(
+            "Allow {target:?} in every verifier's algorithm allow-list and select keys by kid; \
+             check with jwt-tester verify --project {} --try-all-keys",
+            project.name
+        ),
+        format!(
+            "Switch issuers to the new key: jwt-tester vault project set-default-key --project \
+             {} --key-name {name}",
+            project.name
+        ),
+        format!("Keep accepting the old key for {overlap} so tokens already issued expire"),
+    ];
+    if is_hmac(sample.alg) {
+        steps.push(
+            "Delete the old shared secret from every service and remove HS* from verifier \
+             allow-lists"
+                .to_string(),
+        );
+    } else {
+        steps.push(format!(
+            "Remove the old key from the JWKS and drop {} from verifier allow-lists",
+            alg_name(sample.alg)
+        ));
+    }
+    steps
+}
+
+/// Token size and signing/verification cost of one algorithm, measured with a throwaway key.
+#[cfg(feature = "keygen")]
+#[derive(Debug, Serialize)]
+pub struct Estimate {
+    pub alg: String,
+    pub token_bytes: usize,
+    pub signature_bytes: usize,
+    pub sign: LatencyStats,
+    pub verify: LatencyStats,
+}
+
+/// Signs the sample's claims with a freshly generated `alg` key `iterations` times and verifies
+/// each result. The sample's header is reused with only `alg` changed.
+#[cfg(feature = "keygen")]
+pub fn estimate(sample: &Sample, alg: Algorithm, iterations: usize) -> AppResult<Estimate> {
+    use crate::keygen::{self, KeyGenSpec};
+    use std::time::Instant;
+
+    if iterations == 0 {
+        return Err(AppError::invalid_claims("iterations must be at least 1"));
+    }
+    let material = keygen::generate_key_pair(KeyGenSpec::for_alg(alg))?.material;
+    let (encoding, decoding) = crate::key_resolver::key_pair_from_material(alg, &material)?;
+    let mut header = jsonwebtoken::Header::new(alg);
+    header.kid = sample.kid().map(str::to_string);
+    header.typ = sample
+        .header
+        .get("typ")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+
+    let mut sign = Vec::with_capacity(iterations);
+    let mut verify = Vec::with_capacity(iterations);
+    let mut token = String::new();
+    for _ in 0..iterations {
+        let started = Instant::now();
+        token = jwt_ops::encode_token(&header, &sample.claims, &encoding)?;
+        sign.push(started.elapsed());
+        let started = Instant::now();
+        if !jwt_ops::signature_valid(&token, &decoding)? {
+            return Err(AppError::internal(format!(
+                "a {alg:?} token signed for the estimate did not verify"
+            )));
+        }
+        verify.push(started.elapsed());
+    }
+    let signature_bytes = token
+        .rsplit_once('.')
+        .map_or(0, |(_, signature)| signature.len());
+    Ok(Estimate {
+        alg: alg_name(Some(alg)),
+        token_bytes: token.len(),
+        signature_bytes,
+        sign: LatencyStats::from_samples(&mut sign),
+        verify: LatencyStats::from_samples(&mut verify),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use base64::Engine;
+    use serde_json::json;
+
+    fn sample(header: Value, claims: Value) -> Sample {
+        let token = format!(
+            "{}.{}.sig",
+            URL_SAFE_NO_PAD.encode(header.to_string()),
+            URL_SAFE_NO_PAD.encode(claims.to_string())
+        );
+        Sample::parse(&token).expect("sample")
+    }
+
+    fn project() -> ProjectEntry {
+        ProjectEntry {
+            id: "p1".to_string(),
+            name: "payments".to_string(),
+            created_at: 0,
+            default_key_id: None,
+            description: None,
+            tags: Vec::new(),
+            policy: Default::default(),
+            exportable: true,
+            wasm_module: None,
+        }
+    }
+
+    #[test]
+    fn shared_hmac_secret_across_audiences_is_high_priority() {
+        let sample = sample(
+            json!({ "alg": "HS256", "typ": "JWT" }),
+            json!({ "aud": ["orders", "billing"], "iat": 0, "exp": 900 }),
+        );
+        let target = default_target(sample.alg);
+        assert_eq!(target, Some(Algorithm::RS256));
+        let findings = findings(&sample, &project(), &[], target);
+        assert_eq!(findings[0].id, "shared-secret");
+        assert_eq!(findings[0].priority, Priority::High);
+        assert!(findings.iter().any(|f| f.id == "no-kid"));
+        assert!(findings.iter().any(|f| f.id == "no-target-key"));
+
+        let steps = checklist(&sample, &project(), Algorithm::RS256);
+        assert!(steps[0].contains("--project payments --name signing-rs256 --kind rsa"));
+        assert!(steps.iter().any(|s| s.contains("for 15m so tokens")));
+        assert!(steps.last().unwrap().contains("shared secret"));
+    }
+
+    #[test]
+    fn ec_tokens_need_no_migration() {
+        let sample = sample(json!({ "alg": "ES256", "kid": "k1" }), json!({}));
+        assert_eq!(default_target(sample.alg), None);
+        let findings = findings(&sample, &project(), &[], None);
+        let ids: Vec<&str> = findings.iter().map(|f| f.id).collect();
+        assert_eq!(ids, ["no-migration"]);
+    }
+
+    #[cfg(feature = "keygen")]
+    #[test]
+    fn estimate_measures_the_target_algorithm() {
+        let sample = sample(
+            json!({ "alg": "HS256", "kid": "k1" }),
+            json!({ "sub": "u" }),
+        );
+        let estimate = estimate(&sample, Algorithm::ES256, 3).expect("estimate");
+        assert_eq!(estimate.alg, "ES256");
+        // 64 raw signature bytes, base64url without padding.
+        assert_eq!(estimate.signature_bytes, 86);
+        assert!(estimate.sign.min_ms <= estimate.sign.max_ms);
+    }
+}
@@ -0,0 +1,421 @@
+//! Scrubs identifying values out of token headers and claims for sharing (`anonymize`). Each value
+//! is replaced by a fake of the same shape: letters stay letters, digits stay digits, punctuation
+//! and `scheme://` prefixes are kept, and hex ids stay hex. Fakes come from a keyed hash of the
+//! original, so a value that appears twice gets the same fake both times. Timestamps can be moved
+//! by one whole-day offset, which keeps their order, gaps and time of day.
+
+use crate::date_utils::MILLIS_THRESHOLD;
+use rand::RngCore;
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+
+/// Claims (matched on the last `/` or `:` segment of the name, ignoring case) whose whole value is
+/// identifying. Names ending in `_id`, `Id`, `_name`, `Name` or `email` count too.
+const IDENTIFYING: &[&str] = &[
+    "iss",
+    "aud",
+    "sub",
+    "jti",
+    "sid",
+    "oid",
+    "tid",
+    "uid",
+    "upn",
+    "unique_name",
+    "name",
+    "given_name",
+    "family_name",
+    "middle_name",
+    "nickname",
+    "preferred_username",
+    "username",
+    "phone_number",
+    "address",
+    "birthdate",
+    "picture",
+    "profile",
+    "website",
+    "login_hint",
+    "azp",
+    "appid",
+];
+
+/// NumericDate claims; names ending in `_at` count too.
+const TIME_CLAIMS: &[&str] = &["exp", "nbf", "iat", "auth_time"];
+
+/// Header parameters that carry or point at key material and certificates; removed outright.
+const KEY_HEADER_PARAMS: &[&str] = &["jwk", "jku", "x5c", "x5u", "x5t", "x5t#S256"];
+
+const MAX_SHIFT_DAYS: i64 = 365;
+
+pub struct Anonymizer {
+    key: [u8; 32],
+    shift_secs: i64,
+    extra: Vec<String>,
+    keep: Vec<String>,
+}
+
+/// What one anonymization changed, as dotted claim paths (`address.locality`, `emails[0]`).
+#[derive(Debug, Default)]
+pub struct Changes {
+    pub replaced: Vec<String>,
+    pub shifted: Vec<String>,
+}
+
+impl Anonymizer {
+    /// With a `seed`, the same seed gives the same fakes and time shift on every run; without one
+    /// a random key is used.
+    pub fn new(seed: Option<&str>) -> Self {
+        let key = match seed {
+            Some(seed) => Sha256::digest(seed.as_bytes()).into(),
+            None => {
+                let mut key = [0u8; 32];
+                rand::thread_rng().fill_bytes(&mut key);
+                key
+            }
+        };
+        Anonymizer {
+            key,
+            shift_secs: 0,
+            extra: Vec::new(),
+            keep: Vec::new(),
+        }
+    }
+
+    /// Also replaces the claims named in `extra` and leaves those in `keep` (names or dotted
+    /// paths) untouched.
+    pub fn with_claims(mut self, extra: &[String], keep: &[String]) -> Self {
+        self.extra = extra.to_vec();
+        self.keep = keep.to_vec();
+        self
+    }
+
+    /// Moves every timestamp 1 to 365 whole days into the past, by an offset derived from the key.
+    pub fn shift_times(mut self) -> Self {
+        let days = {
+            let mut bytes = self.stream("time-shift");
+            let high = bytes.next().unwrap_or(0);
+            i64::from(u16::from_be_bytes([high, bytes.next().unwrap_or(0)]))
+        };
+        self.shift_secs = -(1 + days % MAX_SHIFT_DAYS) * 86_400;
+        self
+    }
+
+    /// Seconds added to every timestamp (negative: into the past; zero unless shifting).
+    pub fn shift_secs(&self) -> i64 {
+        self.shift_secs
+    }
+
+    pub fn claims(&self, claims: &Value) -> (Value, Changes) {
+        let mut changes = Changes::default();
+        let value = self.walk(claims, "", false, &mut changes);
+        (value, changes)
+    }
+
+    /// Fakes `kid` and drops the parameters in `KEY_HEADER_PARAMS`, returning their names.
+    pub fn header(&self, header: &Value) -> (Value, Vec<String>) {
+        let Some(obj) = header.as_object() else {
+            return (header.clone(), Vec::new());
+        };
+        let mut removed = Vec::new();
+        let mut out = Map::new();
+        for (name, value) in obj {
+            if KEY_HEADER_PARAMS.contains(&name.as_str()) {
+                removed.push(name.clone());
+                continue;
+            }
+            let value = match (name.as_str(), value) {
+                ("kid", Value::String(kid)) => Value::String(self.fake_string(kid)),
+                _ => value.clone(),
+            };
+            out.insert(name.clone(), value);
+        }
+        (Value::Object(out), removed)
+    }
+
+    fn walk(&self, value: &Value, path: &str, identifying: bool, changes: &mut Changes) -> Value {
+        match value {
+            Value::Object(obj) => Value::Object(
+                obj.iter()
+                    .map(|(name, child)| {
+                        let path = if path.is_empty() {
+                            name.clone()
+                        } else {
+                            format!("{path}.{name}")
+                        };
+                        let child = if self.keeps(name, &path) {
+                            child.clone()
+                        } else if self.shift_secs != 0 && is_time_claim(name) && child.is_i64() {
+                            self.shift(child, &path, changes)
+                        } else {
+                            let identifying = identifying || self.is_identifying(name);
+                            self.walk(child, &path, identifying, changes)
+                        };
+                        (name.clone(), child)
+                    })
+                    .collect(),
+            ),
+            Value::Array(items) => Value::Array(
+                items
+                    .iter()
+                    .enumerate()
+                    .map(|(i, item)| self.walk(item, &format!("{path}[{i}]"), identifying, changes))
+                    .collect(),
+            ),
+            Value::String(text) if identifying || looks_like_email(text) => {
+                changes.replaced.push(path.to_string());
+                Value::String(self.fake_string(text))
+            }
+            Value::Number(number) if identifying => {
+                let fake = match (number.as_u64(), number.as_i64()) {
+                    (Some(n), _) => self
+                        .fake_integer(&n.to_string())
+                        .parse::<u64>()
+                        .map(Value::from),
+                    (None, Some(n)) => self
+                        .fake_integer(&n.unsigned_abs().to_string())
+                        .parse::<i64>()
+                        .map(|n| Value::from(-n)),
+                    // Fractions are left alone; ids are integers.
+                    (None, None) => return value.clone(),
+                };
+                match fake {
+                    Ok(fake) => {
+                        changes.replaced.push(path.to_string());
+                        fake
+                    }
+                    Err(_) => value.clone(),
+                }
+            }
+            _ => value.clone(),
+        }
+    }
+
+    fn shift(&self, value: &Value, path: &str, changes: &mut Changes) -> Value {
+        let Some(ts) = value.as_i64() else {
+            return value.clone();
+        };
+        let shift = if ts.unsigned_abs() as f64 >= MILLIS_THRESHOLD {
+            self.shift_secs * 1000
+        } else {
+            self.shift_secs
+        };
+        changes.shifted.push(path.to_string());
+        Value::from(ts.saturating_add(shift))
+    }
+
+    fn keeps(&self, name: &str, path: &str) -> bool {
+        self.keep.iter().any(|keep| keep == name || keep == path)
+    }
+
+    fn is_identifying(&self, name: &str) -> bool {
+        if self.extra.iter().any(|extra| extra == name) {
+            return true;
+        }
+        let last = name.rsplit(['/', ':']).next().unwrap_or(name);
+        let lower = last.to_ascii_lowercase();
+        IDENTIFYING.contains(&lower.as_str())
+            || lower.ends_with("_id")
+            || lower.ends_with("_name")
+            || lower.ends_with("email")
+            || last.ends_with("Id")
+            || last.ends_with("Name")
+    }
+
+    /// Same length in characters and same character classes; lowercase stays lowercase, and a
+    /// value made of hex digits (with at least one digit) stays hex.
+    fn fake_string(&self, text: &str) -> String {
+        let (prefix, rest) = match text.find("://") {
+            Some(at) => text.split_at(at + 3),
+            None => ("", text),
+        };
+        let hex = rest.chars().any(|c| c.is_ascii_digit())
+            && rest
+                .chars()
+                .all(|c| c.is_ascii_hexdigit() || !c.is_ascii_alphanumeric());
+        let letters = if hex { 6 } else { 26 };
+        let mut stream = self.stream(text);
+        let mut fake = prefix.to_string();
+        for c in rest.chars() {
+            let n = stream.next().unwrap_or(0);
+            fake.push(if c.is_ascii_digit() {
+                char::from(b'0' + n % 10)
+            } else if c.is_uppercase() {
+                char::from(b'A' + n % letters)
+            } else if c.is_alphabetic() {
+                char::from(b'a' + n % letters)
+            } else {
+                c
+            });
+        }
+        fake
+    }
+
+    /// The same number of digits, without a leading zero, and small enough for the integer type
+    /// the original came in.
+    fn fake_integer(&self, digits: &str) -> String {
+        let mut fake = self.fake_string(digits);
+        if fake.len() > 1 && fake.starts_with('0') {
+            fake.replace_range(..1, "1");
+        }
+        if fake.len() >= 19 {
+            fake.replace_range(..2, "10");
+        }
+        fake
+    }
+
+    /// Bytes derived from the key and `input`: SHA-256 over the key, a block counter and the input.
+    fn stream<'a>(&'a self, input: &'a str) -> impl Iterator<Item = u8> + 'a {
+        (0u32..).flat_map(move |block| {
+            let mut hasher = Sha256::new();
+            hasher.update(self.key);
+            hasher.update(block.to_be_bytes());
+            hasher.update(input.as_bytes());
+            hasher.finalize().to_vec()
+        })
+    }
+}
+
+fn is_time_claim(name: &str) -> bool {
+    TIME_CLAIMS.contains(&name) || name.ends_with("_at")
+}
+
+fn looks_like_email(text: &str) -> bool {
+    match text.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !local.contains(char::is_whitespace)
+                && domain.contains('.')
+                && !domain.contains(['@', ' '])
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Anonymizer;
+    use serde_json::json;
+
+    #[test]
+    fn fakes_keep_shape_and_repeat_for_the_same_value() {
+        let anonymizer = Anonymizer::new(Some("seed"));
+        let claims = json!({
+            "sub": "248289761001",
+            "email": "Jane.Doe@example.com",
+            "oid": "5f0c2a8e-91b3-4c7d-a6e2-0b9d8c7f6e5d",
+            "iss": "https://issuer.example.com",
+            "scope": "openid email",
+            "address": { "locality": "Zürich", "postal_code": 8001 },
+            "https://example.com/user_id": "u-42",
+            "collaborators": ["jane.doe@example.com", "ops"],
+            "alias": "Jane.Doe@example.com",
+        });
+        let (out, changes) = anonymizer.claims(&claims);
+
+        let sub = out["sub"].as_str().expect("sub");
+        assert_eq!(sub.len(), 12);
+        assert!(sub.chars().all(|c| c.is_ascii_digit()));
+        assert_ne!(sub, "248289761001");
+
+        let email = out["email"].as_str().expect("email");
+        assert_eq!(email.len(), "Jane.Doe@example.com".len());
+        assert_eq!(&email[4..5], ".");
+        assert_eq!(&email[8..9], "@");
+        assert!(email[..1].chars().all(|c| c.is_ascii_uppercase()));
+        // Emails are replaced wherever they appear, and the same value gets the same fake.
+        assert_eq!(out["alias"], out["email"]);
+        assert_ne!(out["collaborators"][0], claims["collaborators"][0]);
+        assert_eq!(out["collaborators"][1], "ops");
+
+        let oid = out["oid"].as_str().expect("oid");
+        assert_eq!(oid.len(), 36);
+        assert!(oid.chars().all(|c| c.is_ascii_hexdigit() || c == '-'));
+        assert_eq!(oid.matches('-').count(), 4);
+
+        // URLs keep their scheme and get a fake host of the same shape.
+        let iss = out["iss"].as_str().expect("iss");
+        assert_ne!(iss, "https://issuer.example.com");
+        assert_eq!(iss.len(), "https://issuer.example.com".len());
+        assert!(iss.starts_with("https://") && iss.matches('.').count() == 2);
+        assert_eq!(out["scope"], claims["scope"]);
+        assert_eq!(
+            out["address"]["locality"]
+                .as_str()
+                .map(|s| s.chars().count()),
+            Some(6)
+        );
+        let postal = out["address"]["postal_code"].as_u64().expect("postal code");
+        assert!((1000..10000).contains(&postal));
+        assert_ne!(
+            out["https://example.com/user_id"],
+            claims["https://example.com/user_id"]
+        );
+        assert!(changes
+            .replaced
+            .contains(&"address.postal_code".to_string()));
+        assert!(changes.replaced.contains(&"collaborators[0]".to_string()));
+
+        let (again, _) = Anonymizer::new(Some("seed")).claims(&claims);
+        assert_eq!(again, out);
+        let (other, _) = Anonymizer::new(Some("other")).claims(&claims);
+        assert_ne!(other["sub"], out["sub"]);
+    }
+
+    #[test]
+    fn timestamps_move_together_and_kept_claims_stay() {
+        let anonymizer = Anonymizer::new(Some("seed"))
+            .with_claims(
+                &["tenant".to_string()],
+                &["sub".to_string(), "iss".to_string()],
+            )
+            .shift_times();
+        let claims = json!({
+            "sub": "alice",
+            "iss": "https://login.acme.internal/tenant-42",
+            "aud": ["api://orders-prod", "billing"],
+            "tenant": "acme",
+            "iat": 1_700_000_000,
+            "exp": 1_700_003_600,
+            "updated_at": 1_699_000_000,
+            "login_ms_at": 1_700_000_000_000_i64,
+        });
+        let (out, changes) = anonymizer.claims(&claims);
+        let shift = anonymizer.shift_secs();
+        assert!(shift < 0 && shift % 86_400 == 0 && shift >= -365 * 86_400);
+        assert_eq!(out["iat"], 1_700_000_000 + shift);
+        assert_eq!(
+            out["exp"].as_i64().unwrap() - out["iat"].as_i64().unwrap(),
+            3600
+        );
+        assert_eq!(out["updated_at"], 1_699_000_000 + shift);
+        assert_eq!(out["login_ms_at"], 1_700_000_000_000_i64 + shift * 1000);
+        assert_eq!(changes.shifted.len(), 4);
+        assert_eq!(out["sub"], "alice");
+        assert_eq!(out["iss"], claims["iss"]);
+        assert_ne!(out["tenant"], "acme");
+        let aud = out["aud"].as_array().expect("aud");
+        assert!(aud[0].as_str().is_some_and(|aud| aud.starts_with("api://")));
+        assert_ne!(aud[0], claims["aud"][0]);
+        assert_ne!(aud[1], "billing");
+
+        let (unshifted, changes) = Anonymizer::new(Some("seed")).claims(&claims);
+        assert_eq!(unshifted["exp"], claims["exp"]);
+        assert!(changes.shifted.is_empty());
+    }
+
+    #[test]
+    fn header_loses_key_material_and_kid_is_faked() {
+        let anonymizer = Anonymizer::new(Some("seed"));
+        let header =
+            json!({ "alg": "RS256", "kid": "prod-key-2024", "x5c": ["MII..."], "typ": "JWT" });
+        let (out, removed) = anonymizer.header(&header);
+        assert_eq!(removed, ["x5c"]);
+        assert_eq!(out["alg"], "RS256");
+        assert_eq!(out["typ"], "JWT");
+        let kid = out["kid"].as_str().expect("kid");
+        assert_eq!(kid.len(), "prod-key-2024".len());
+        assert_ne!(kid, "prod-key-2024");
+        assert_eq!(out.as_object().map(|obj| obj.len()), Some(3));
+    }
+}
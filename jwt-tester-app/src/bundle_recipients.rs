@@ -0,0 +1,154 @@
+use anyhow::Context;
+#[cfg(feature = "vault")]
+use std::io::Read;
+use std::io::Write;
+use std::process::{Command, Stdio};
+#[cfg(feature = "vault")]
+use std::str::FromStr;
+
+const GPG_ENV: &str = "JWT_TESTER_GPG";
+
+#[cfg(feature = "vault")]
+pub fn age_encrypt(recipients: &[String], plaintext: &[u8]) -> anyhow::Result<Vec<u8>> {
+    if recipients.is_empty() {
+        anyhow::bail!("at least one age recipient is required");
+    }
+    let mut parsed = Vec::with_capacity(recipients.len());
+    for recipient in recipients {
+        let recipient = age::x25519::Recipient::from_str(recipient.trim())
+            .map_err(|e| anyhow::anyhow!("invalid age recipient '{recipient}': {e}"))?;
+        parsed.push(recipient);
+    }
+
+    let encryptor =
+        age::Encryptor::with_recipients(parsed.iter().map(|r| r as &dyn age::Recipient))
+            .map_err(|e| anyhow::anyhow!("prepare age encryption: {e}"))?;
+    let mut ciphertext = Vec::new();
+    let mut writer = encryptor
+        .wrap_output(&mut ciphertext)
+        .context("start age encryption")?;
+    writer.write_all(plaintext).context("age encrypt")?;
+    writer.finish().context("finish age encryption")?;
+    Ok(ciphertext)
+}
+
+/// Decrypts with identities from an age identity file (`AGE-SECRET-KEY-1...` lines).
+#[cfg(feature = "vault")]
+pub fn age_decrypt(identity_file: &str, ciphertext: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let identities = age::IdentityFile::from_buffer(identity_file.as_bytes())
+        .context("parse age identity file")?
+        .into_identities()
+        .map_err(|e| anyhow::anyhow!("load age identities: {e}"))?;
+    if identities.is_empty() {
+        anyhow::bail!("age identity file contains no identities");
+    }
+
+    let decryptor = age::Decryptor::new_buffered(ciphertext)
+        .map_err(|e| anyhow::anyhow!("invalid age payload: {e}"))?;
+    let mut reader = decryptor
+        .decrypt(identities.iter().map(|i| i.as_ref() as &dyn age::Identity))
+        .map_err(|e| anyhow::anyhow!("age decrypt: {e}"))?;
+    let mut plaintext = Vec::new();
+    reader
+        .read_to_end(&mut plaintext)
+        .context("read age plaintext")?;
+    Ok(plaintext)
+}
+
+#[cfg(not(feature = "vault"))]
+pub fn age_encrypt(_: &[String], _: &[u8]) -> anyhow::Result<Vec<u8>> {
+    anyhow::bail!("age recipients require a build with the `vault` feature")
+}
+
+#[cfg(not(feature = "vault"))]
+pub fn age_decrypt(_: &str, _: &[u8]) -> anyhow::Result<Vec<u8>> {
+    anyhow::bail!("age identities require a build with the `vault` feature")
+}
+
+/// Encrypts via the local `gpg` binary (override with `JWT_TESTER_GPG`).
+pub fn gpg_encrypt(recipients: &[String], plaintext: &[u8]) -> anyhow::Result<Vec<u8>> {
+    if recipients.is_empty() {
+        anyhow::bail!("at least one gpg recipient is required");
+    }
+    let mut args = vec![
+        "--batch".to_string(),
+        "--yes".to_string(),
+        "--trust-model".to_string(),
+        "always".to_string(),
+        "--encrypt".to_string(),
+    ];
+    for recipient in recipients {
+        args.push("--recipient".to_string());
+        args.push(recipient.trim().to_string());
+    }
+    run_gpg(&args, plaintext)
+}
+
+pub fn gpg_decrypt(ciphertext: &[u8]) -> anyhow::Result<Vec<u8>> {
+    run_gpg(
+        &["--batch".to_string(), "--decrypt".to_string()],
+        ciphertext,
+    )
+}
+
+fn run_gpg(args: &[String], input: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let program = std::env::var_os(GPG_ENV).unwrap_or_else(|| "gpg".into());
+    let mut child = Command::new(&program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("failed to run {program:?} (set {GPG_ENV} to override)"))?;
+
+    {
+        let mut stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("gpg stdin unavailable"))?;
+        stdin.write_all(input).context("write gpg input")?;
+    }
+
+    let output = child.wait_with_output().context("wait for gpg")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("gpg failed ({}): {}", output.status, stderr.trim());
+    }
+    Ok(output.stdout)
+}
+
+#[cfg(all(test, feature = "vault"))]
+mod tests {
+    use super::{age_decrypt, age_encrypt};
+    use age::secrecy::ExposeSecret;
+
+    #[test]
+    fn age_roundtrip_with_multiple_recipients() {
+        let alice = age::x25519::Identity::generate();
+        let bob = age::x25519::Identity::generate();
+        let recipients = vec![alice.to_public().to_string(), bob.to_public().to_string()];
+
+        let ciphertext = age_encrypt(&recipients, b"snapshot").expect("encrypt");
+        for identity in [&alice, &bob] {
+            let identity_file = format!("{}\n", identity.to_string().expose_secret());
+            let plaintext = age_decrypt(&identity_file, &ciphertext).expect("decrypt");
+            assert_eq!(plaintext, b"snapshot");
+        }
+    }
+
+    #[test]
+    fn age_decrypt_rejects_other_identity() {
+        let alice = age::x25519::Identity::generate();
+        let mallory = age::x25519::Identity::generate();
+        let ciphertext =
+            age_encrypt(&[alice.to_public().to_string()], b"snapshot").expect("encrypt");
+        let identity_file = mallory.to_string().expose_secret().to_string();
+        assert!(age_decrypt(&identity_file, &ciphertext).is_err());
+    }
+
+    #[test]
+    fn age_encrypt_rejects_invalid_recipient() {
+        let err = age_encrypt(&["not-a-recipient".to_string()], b"x").unwrap_err();
+        assert!(err.to_string().contains("invalid age recipient"));
+    }
+}
@@ -0,0 +1,682 @@
+use crate::claims::UNNAMESPACED_CLAIMS;
+use crate::cli::{ClaimProfile, JwtAlg};
+use crate::date_utils::MILLIS_THRESHOLD;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+/// Claims from other specs that are common enough to not be reported as typos.
+const EXTRA_KNOWN_CLAIMS: &[&str] = &[
+    "act",
+    "may_act",
+    "roles",
+    "groups",
+    "entitlements",
+    "events",
+    "toe",
+    "txn",
+    "htm",
+    "htu",
+    "ath",
+];
+
+/// Spellings that mean a registered claim, compared lowercased without `_` and `-`.
+const SYNONYMS: &[(&str, &str)] = &[
+    ("issuer", "iss"),
+    ("subject", "sub"),
+    ("audience", "aud"),
+    ("audiences", "aud"),
+    ("expires", "exp"),
+    ("expiry", "exp"),
+    ("expiration", "exp"),
+    ("expiresat", "exp"),
+    ("expirationtime", "exp"),
+    ("notbefore", "nbf"),
+    ("issuedat", "iat"),
+    ("jwtid", "jti"),
+];
+
+/// JSON type RFC 7519 and OpenID Connect Core give a claim.
+#[derive(Debug, Clone, Copy)]
+enum Expected {
+    String,
+    StringOrStrings,
+    Strings,
+    NumericDate,
+    Bool,
+    Object,
+}
+
+impl Expected {
+    fn matches(self, value: &Value) -> bool {
+        match self {
+            Expected::String => value.is_string(),
+            Expected::StringOrStrings => value.is_string() || is_strings(value),
+            Expected::Strings => is_strings(value),
+            Expected::NumericDate => value.is_number(),
+            Expected::Bool => value.is_boolean(),
+            Expected::Object => value.is_object(),
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            Expected::String => "a string",
+            Expected::StringOrStrings => "a string or an array of strings",
+            Expected::Strings => "an array of strings",
+            Expected::NumericDate => "a number (NumericDate, seconds since the epoch)",
+            Expected::Bool => "a boolean",
+            Expected::Object => "an object",
+        }
+    }
+}
+
+const CLAIM_TYPES: &[(&str, Expected)] = &[
+    ("iss", Expected::String),
+    ("sub", Expected::String),
+    ("aud", Expected::StringOrStrings),
+    ("exp", Expected::NumericDate),
+    ("nbf", Expected::NumericDate),
+    ("iat", Expected::NumericDate),
+    ("jti", Expected::String),
+    ("auth_time", Expected::NumericDate),
+    ("updated_at", Expected::NumericDate),
+    ("nonce", Expected::String),
+    ("azp", Expected::String),
+    ("acr", Expected::String),
+    ("amr", Expected::Strings),
+    ("at_hash", Expected::String),
+    ("c_hash", Expected::String),
+    ("sid", Expected::String),
+    ("scope", Expected::String),
+    ("client_id", Expected::String),
+    ("email", Expected::String),
+    ("email_verified", Expected::Bool),
+    ("phone_number_verified", Expected::Bool),
+    ("address", Expected::Object),
+    ("cnf", Expected::Object),
+    ("act", Expected::Object),
+];
+
+fn is_strings(value: &Value) -> bool {
+    value
+        .as_array()
+        .is_some_and(|items| items.iter().all(Value::is_string))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A problem with one claim (or, without `claim`, with the object as a whole).
+#[derive(Debug, Clone, Serialize)]
+pub struct Issue {
+    pub rule: &'static str,
+    pub severity: Severity,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub claim: Option<String>,
+    pub message: String,
+    /// Whether the issue is about the value rather than the name; picks the reported position.
+    #[serde(skip)]
+    pub on_value: bool,
+}
+
+impl Issue {
+    fn name(rule: &'static str, severity: Severity, claim: &str, message: String) -> Self {
+        Issue {
+            rule,
+            severity,
+            claim: Some(claim.to_string()),
+            message,
+            on_value: false,
+        }
+    }
+
+    fn value(rule: &'static str, severity: Severity, claim: &str, message: String) -> Self {
+        Issue {
+            on_value: true,
+            ..Issue::name(rule, severity, claim, message)
+        }
+    }
+}
+
+/// An [`Issue`] placed in the source text (1-based line and column, in characters).
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostic {
+    pub line: usize,
+    pub column: usize,
+    #[serde(flatten)]
+    pub issue: Issue,
+}
+
+/// Claim names that look like a misspelled, miscased or spelled-out registered claim
+/// (`Iss`, `expr`, `audience`). URL-style names and known claims are never reported.
+pub fn name_issues(claims: &Map<String, Value>) -> Vec<Issue> {
+    claims
+        .keys()
+        .filter_map(|name| {
+            let intended = near_miss(name)?;
+            let message = if claims.contains_key(intended) {
+                format!("'{name}' looks like '{intended}', which is also present; one is likely a leftover")
+            } else {
+                format!("'{name}' is not a registered claim; did you mean '{intended}'?")
+            };
+            Some(Issue::name("claim-name", Severity::Warning, name, message))
+        })
+        .collect()
+}
+
+/// Registered claims whose value has the wrong JSON type, e.g. a numeric `aud` or a string `exp`.
+pub fn type_issues(claims: &Map<String, Value>) -> Vec<Issue> {
+    CLAIM_TYPES
+        .iter()
+        .filter_map(|(name, expected)| {
+            let value = claims.get(*name)?;
+            if expected.matches(value) {
+                return None;
+            }
+            Some(Issue::value(
+                "claim-type",
+                Severity::Error,
+                name,
+                format!(
+                    "'{name}' must be {}, got {}",
+                    expected.describe(),
+                    json_type(value)
+                ),
+            ))
+        })
+        .collect()
+}
+
+/// The `warnings` `verify` and `decode` report: near-miss claim names and wrong value types.
+/// A token that got this far was accepted, so type problems are only warnings here.
+pub fn claim_warnings(claims: &Value) -> Vec<Issue> {
+    let Some(claims) = claims.as_object() else {
+        return Vec::new();
+    };
+    let mut issues = name_issues(claims);
+    issues.extend(type_issues(claims));
+    for issue in &mut issues {
+        issue.severity = Severity::Warning;
+    }
+    issues
+}
+
+fn near_miss(name: &str) -> Option<&'static str> {
+    if name.contains(':') || name.contains('/') || is_known(name) {
+        return None;
+    }
+    let folded: String = name
+        .chars()
+        .filter(|c| *c != '_' && *c != '-')
+        .collect::<String>()
+        .to_lowercase();
+    if let Some((_, claim)) = SYNONYMS.iter().find(|(synonym, _)| *synonym == folded) {
+        return Some(claim);
+    }
+    let lowered = name.to_lowercase();
+    known_claims()
+        .filter(|claim| {
+            // Short names differ from each other by one letter already (iss/iat, sub/sid).
+            let limit = if claim.len() <= 4 { 1 } else { 2 };
+            lowered == **claim || edit_distance(&lowered, claim) <= limit
+        })
+        .min_by_key(|claim| edit_distance(&lowered, claim))
+}
+
+fn is_known(name: &str) -> bool {
+    known_claims().any(|claim| claim == name)
+}
+
+fn known_claims() -> impl Iterator<Item = &'static str> {
+    UNNAMESPACED_CLAIMS
+        .iter()
+        .chain(EXTRA_KNOWN_CLAIMS)
+        .copied()
+}
+
+/// Optimal string alignment distance: Levenshtein plus swapping two neighbors (`jit` → `jti`).
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = best;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+fn json_type(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+/// What `lint-claims` checks beyond names and types.
+#[derive(Debug, Clone, Copy)]
+pub struct LintOptions {
+    /// Signing algorithm; sets the expected `at_hash`/`c_hash` length.
+    pub alg: Option<JwtAlg>,
+    pub profile: Option<ClaimProfile>,
+    pub now: i64,
+}
+
+/// Lints a claims JSON document. A syntax error is the only diagnostic when the text does not
+/// parse; otherwise every issue is placed on the claim's name or value.
+pub fn lint_source(source: &str, options: LintOptions) -> Vec<Diagnostic> {
+    let claims: Value = match serde_json::from_str(source) {
+        Ok(claims) => claims,
+        Err(err) => {
+            let message = err.to_string();
+            let message = message
+                .rsplit_once(" at line ")
+                .map_or(message.as_str(), |(message, _)| message)
+                .to_string();
+            return vec![Diagnostic {
+                line: err.line().max(1),
+                column: err.column().max(1),
+                issue: Issue {
+                    rule: "syntax",
+                    severity: Severity::Error,
+                    claim: None,
+                    message,
+                    on_value: false,
+                },
+            }];
+        }
+    };
+    let mut positions = locate_members(source);
+    let mut issues = std::mem::take(&mut positions.duplicates);
+    let place = |issue: Issue| {
+        let (line, column) = issue
+            .claim
+            .as_deref()
+            .and_then(|claim| positions.members.get(claim))
+            .map_or(positions.start, |member| {
+                if issue.on_value {
+                    member.value
+                } else {
+                    member.name
+                }
+            });
+        Diagnostic {
+            line,
+            column,
+            issue,
+        }
+    };
+    let Some(claims) = claims.as_object() else {
+        return vec![place(Issue {
+            rule: "syntax",
+            severity: Severity::Error,
+            claim: None,
+            message: format!("claims must be a JSON object, got {}", json_type(&claims)),
+            on_value: false,
+        })];
+    };
+
+    issues.extend(type_issues(claims));
+    issues.extend(name_issues(claims));
+    issues.extend(time_issues(claims, options.now));
+    if let Some(profile) = options.profile {
+        issues.extend(profile_issues(claims, profile));
+    }
+    if let Some(alg) = options.alg {
+        issues.extend(hash_issues(claims, alg));
+    }
+    let mut diagnostics: Vec<Diagnostic> = issues.into_iter().map(place).collect();
+    diagnostics.sort_by_key(|d| (d.line, d.column));
+    diagnostics
+}
+
+fn time_issues(claims: &Map<String, Value>, now: i64) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    let time = |name: &str| claims.get(name).and_then(Value::as_f64);
+    for name in ["exp", "nbf", "iat", "auth_time"] {
+        if time(name).is_some_and(|value| value.abs() >= MILLIS_THRESHOLD) {
+            issues.push(Issue::value(
+                "timestamp-unit",
+                Severity::Warning,
+                name,
+                format!("'{name}' looks like milliseconds; NumericDate values are seconds"),
+            ));
+        }
+    }
+    let now = now as f64;
+    if let Some(exp) = time("exp").filter(|exp| *exp < MILLIS_THRESHOLD) {
+        if exp <= now {
+            issues.push(Issue::value(
+                "expired",
+                Severity::Error,
+                "exp",
+                "'exp' is in the past; a token with these claims is expired when issued".into(),
+            ));
+        }
+        for name in ["nbf", "iat"] {
+            if time(name).is_some_and(|value| value > exp) {
+                issues.push(Issue::value(
+                    "time-order",
+                    Severity::Error,
+                    name,
+                    format!("'{name}' is after 'exp'"),
+                ));
+            }
+        }
+    }
+    if time("iat").is_some_and(|iat| iat < MILLIS_THRESHOLD && iat > now + 60.0) {
+        issues.push(Issue::value(
+            "time-order",
+            Severity::Warning,
+            "iat",
+            "'iat' is in the future".into(),
+        ));
+    }
+    issues
+}
+
+fn profile_issues(claims: &Map<String, Value>, profile: ClaimProfile) -> Vec<Issue> {
+    let (label, required): (&str, &[&str]) = match profile {
+        // OpenID Connect Core 1.0 §2.
+        ClaimProfile::Oidc => ("ID token", &["iss", "sub", "aud", "exp", "iat"]),
+        // RFC 9068 §2.2.
+        ClaimProfile::AccessToken => (
+            "JWT access token",
+            &["iss", "exp", "aud", "sub", "client_id", "iat", "jti"],
+        ),
+    };
+    let mut issues: Vec<Issue> = required
+        .iter()
+        .filter(|name| !claims.contains_key(**name))
+        .map(|name| Issue {
+            rule: "required",
+            severity: Severity::Error,
+            claim: None,
+            message: format!("{label} is missing required claim '{name}'"),
+            on_value: false,
+        })
+        .collect();
+    let audiences = claims.get("aud").and_then(Value::as_array).map(Vec::len);
+    if profile == ClaimProfile::Oidc
+        && audiences.is_some_and(|count| count > 1)
+        && !claims.contains_key("azp")
+    {
+        issues.push(Issue::value(
+            "azp",
+            Severity::Warning,
+            "aud",
+            "ID token with several audiences should carry 'azp'".into(),
+        ));
+    }
+    issues
+}
+
+/// `at_hash` and `c_hash` are the left half of the alg's hash, base64url without padding.
+fn hash_issues(claims: &Map<String, Value>, alg: JwtAlg) -> Vec<Issue> {
+    let expected_len = match alg {
+        JwtAlg::HS256 | JwtAlg::RS256 | JwtAlg::PS256 | JwtAlg::ES256 => 22,
+        JwtAlg::HS384 | JwtAlg::RS384 | JwtAlg::PS384 | JwtAlg::ES384 => 32,
+        // Ed25519 uses SHA-512 (OpenID Connect Core errata).
+        JwtAlg::HS512 | JwtAlg::RS512 | JwtAlg::PS512 | JwtAlg::EdDSA => 43,
+    };
+    ["at_hash", "c_hash"]
+        .into_iter()
+        .filter_map(|name| {
+            let value = claims.get(name)?.as_str()?;
+            (value.len() != expected_len).then(|| {
+                Issue::value(
+                    "hash-length",
+                    Severity::Error,
+                    name,
+                    format!(
+                        "'{name}' has {} characters; {alg:?} hashes give {expected_len}",
+                        value.len()
+                    ),
+                )
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Member {
+    name: (usize, usize),
+    value: (usize, usize),
+}
+
+/// Where the top-level object and its members start, plus duplicate names (RFC 7519 §4 lets
+/// parsers reject them; serde keeps the last one).
+struct Positions {
+    start: (usize, usize),
+    members: HashMap<String, Member>,
+    duplicates: Vec<Issue>,
+}
+
+/// Walks JSON that already parsed, recording the position of each top-level member.
+fn locate_members(source: &str) -> Positions {
+    let mut scanner = Scanner::new(source);
+    scanner.skip_whitespace();
+    let mut positions = Positions {
+        start: scanner.position(),
+        members: HashMap::new(),
+        duplicates: Vec::new(),
+    };
+    if scanner.peek() != Some('{') {
+        return positions;
+    }
+    scanner.next();
+    loop {
+        scanner.skip_whitespace();
+        match scanner.peek() {
+            Some('"') => {}
+            _ => return positions,
+        }
+        let name_at = scanner.position();
+        let raw = scanner.string();
+        let name: String = serde_json::from_str(raw).unwrap_or_default();
+        scanner.skip_whitespace();
+        scanner.next(); // ':'
+        scanner.skip_whitespace();
+        let value_at = scanner.position();
+        scanner.value();
+        let member = Member {
+            name: name_at,
+            value: value_at,
+        };
+        if positions.members.insert(name.clone(), member).is_some() {
+            positions.duplicates.push(Issue::name(
+                "duplicate",
+                Severity::Error,
+                &name,
+                format!("'{name}' appears more than once; only the last value is used"),
+            ));
+        }
+        scanner.skip_whitespace();
+        if scanner.next() != Some(',') {
+            return positions;
+        }
+    }
+}
+
+struct Scanner<'a> {
+    source: &'a str,
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Scanner<'a> {
+    fn new(source: &'a str) -> Self {
+        Scanner {
+            source,
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn position(&self) -> (usize, usize) {
+        (self.line, self.column)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.source[self.offset..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.offset += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.next();
+        }
+    }
+
+    /// Consumes a string literal and returns it with its quotes.
+    fn string(&mut self) -> &'a str {
+        let start = self.offset;
+        self.next();
+        while let Some(c) = self.next() {
+            match c {
+                '\\' => {
+                    self.next();
+                }
+                '"' => break,
+                _ => {}
+            }
+        }
+        &self.source[start..self.offset]
+    }
+
+    fn value(&mut self) {
+        let mut depth = 0usize;
+        while let Some(c) = self.peek() {
+            match c {
+                '"' => {
+                    self.string();
+                    if depth == 0 {
+                        return;
+                    }
+                    continue;
+                }
+                '{' | '[' => depth += 1,
+                '}' | ']' if depth == 0 => return,
+                '}' | ']' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        self.next();
+                        return;
+                    }
+                }
+                ',' if depth == 0 => return,
+                _ => {}
+            }
+            self.next();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const NOW: i64 = 1_700_000_000;
+
+    fn lint(source: &str, profile: Option<ClaimProfile>) -> Vec<(usize, usize, &'static str)> {
+        let options = LintOptions {
+            alg: Some(JwtAlg::RS256),
+            profile,
+            now: NOW,
+        };
+        lint_source(source, options)
+            .into_iter()
+            .map(|d| (d.line, d.column, d.issue.rule))
+            .collect()
+    }
+
+    #[test]
+    fn diagnostics_point_at_names_and_values() {
+        let source = "{\n  \"iss\": \"https://issuer\",\n  \"aud\": 42,\n  \"expr\": 1800000000,\n  \"exp\": 1600000000,\n  \"at_hash\": \"short\"\n}";
+        assert_eq!(
+            lint(source, None),
+            vec![
+                (3, 10, "claim-type"),
+                (4, 3, "claim-name"),
+                (5, 10, "expired"),
+                (6, 14, "hash-length"),
+            ]
+        );
+        assert_eq!(
+            lint(
+                "{\"sub\": \"a\",\n \"sub\": \"b\"}",
+                Some(ClaimProfile::Oidc)
+            )[..2],
+            [(1, 1, "required"), (1, 1, "required")]
+        );
+        assert_eq!(lint("{\"sub\": }", None), vec![(1, 9, "syntax")]);
+        assert_eq!(lint("[1]", None), vec![(1, 1, "syntax")]);
+    }
+
+    #[test]
+    fn name_issues_catch_case_typos_and_spelled_out_names() {
+        let claims = json!({
+            "Iss": "a", "expires": 1, "audience": "b", "jit": "c", "expr": 2,
+            "sub": "ok", "nonce": "ok", "https://example.com/roles": [], "tenant": "t",
+        });
+        let mut flagged: Vec<(String, String)> = name_issues(claims.as_object().unwrap())
+            .into_iter()
+            .map(|issue| (issue.claim.unwrap(), issue.message))
+            .collect();
+        flagged.sort();
+        let names: Vec<&str> = flagged.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["Iss", "audience", "expires", "expr", "jit"]);
+        assert!(flagged[0].1.contains("did you mean 'iss'"));
+        assert!(flagged[4].1.contains("'jti'"));
+    }
+
+    #[test]
+    fn type_issues_follow_rfc7519() {
+        let claims = json!({
+            "aud": ["a", 1], "exp": "1700000000", "iss": "ok", "email_verified": "true",
+        });
+        let flagged: Vec<String> = type_issues(claims.as_object().unwrap())
+            .into_iter()
+            .filter_map(|issue| issue.claim)
+            .collect();
+        assert_eq!(flagged, ["aud", "exp", "email_verified"]);
+        assert!(type_issues(json!({ "aud": ["a"], "exp": 1.5 }).as_object().unwrap()).is_empty());
+    }
+}
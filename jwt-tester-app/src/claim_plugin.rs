@@ -0,0 +1,253 @@
+//! Per-project WASM plugins: `encode --project` passes the claims through the module's
+//! `jwt_transform` export before signing, and `verify --project` asks its `jwt_validate` export
+//! for extra violations after the built-in checks.
+//!
+//! A module exports `memory`, `alloc(len: i32) -> i32` and at least one hook. Each hook takes
+//! `(ptr: i32, len: i32)` pointing at UTF-8 JSON written into a buffer from `alloc`, and returns
+//! its JSON answer packed as `(ptr << 32) | len`:
+//!
+//! - `jwt_transform`: claims object in, claims object out.
+//! - `jwt_validate`: `{"header": .., "claims": ..}` in, array of violation messages out (empty
+//!   when the token passes).
+//!
+//! Modules get no imports (no WASI, files or network), a fuel budget and a memory cap per call.
+//! The runtime (wasmtime) is only in builds with the `wasm` feature; `.wat` text modules load too.
+
+use crate::error::{AppError, AppResult};
+use serde_json::{json, Value};
+
+pub const TRANSFORM_EXPORT: &str = "jwt_transform";
+pub const VALIDATE_EXPORT: &str = "jwt_validate";
+
+/// The hooks `path` exports. Fails when the module does not load or exports neither hook.
+pub fn hooks(path: &str) -> AppResult<Vec<&'static str>> {
+    let hooks = exported_hooks(path)?;
+    if hooks.is_empty() {
+        return Err(AppError::invalid_key(format!(
+            "wasm module {path} exports neither {TRANSFORM_EXPORT} nor {VALIDATE_EXPORT}"
+        )));
+    }
+    Ok(hooks)
+}
+
+/// The claims `jwt_transform` returns; unchanged when the module has no such export.
+pub fn transform_claims(path: &str, claims: Value) -> AppResult<Value> {
+    match call(path, TRANSFORM_EXPORT, &claims)? {
+        None => Ok(claims),
+        Some(out @ Value::Object(_)) => Ok(out),
+        Some(_) => Err(AppError::internal(format!(
+            "wasm module {path}: {TRANSFORM_EXPORT} must return a JSON object"
+        ))),
+    }
+}
+
+/// The violations `jwt_validate` reports; none when the module has no such export.
+pub fn validate(path: &str, header: &Value, claims: &Value) -> AppResult<Vec<String>> {
+    let input = json!({ "header": header, "claims": claims });
+    let Some(out) = call(path, VALIDATE_EXPORT, &input)? else {
+        return Ok(Vec::new());
+    };
+    let malformed = || {
+        AppError::internal(format!(
+            "wasm module {path}: {VALIDATE_EXPORT} must return an array of strings"
+        ))
+    };
+    out.as_array()
+        .ok_or_else(malformed)?
+        .iter()
+        .map(|v| v.as_str().map(str::to_string).ok_or_else(malformed))
+        .collect()
+}
+
+fn call(path: &str, export: &str, input: &Value) -> AppResult<Option<Value>> {
+    let Some(out) = runtime::call(path, export, input.to_string().as_bytes())? else {
+        return Ok(None);
+    };
+    serde_json::from_slice(&out).map(Some).map_err(|e| {
+        AppError::internal(format!(
+            "wasm module {path}: {export} returned invalid JSON: {e}"
+        ))
+    })
+}
+
+#[cfg(feature = "wasm")]
+fn exported_hooks(path: &str) -> AppResult<Vec<&'static str>> {
+    let plugin = runtime::Plugin::load(path)?;
+    Ok([TRANSFORM_EXPORT, VALIDATE_EXPORT]
+        .into_iter()
+        .filter(|name| plugin.exports(name))
+        .collect())
+}
+
+#[cfg(not(feature = "wasm"))]
+fn exported_hooks(path: &str) -> AppResult<Vec<&'static str>> {
+    Err(runtime::unavailable(path))
+}
+
+#[cfg(feature = "wasm")]
+mod runtime {
+    use crate::error::{AppError, AppResult};
+    use wasmtime::{
+        Config, Engine, Instance, Memory, Module, Store, StoreLimits, StoreLimitsBuilder,
+    };
+
+    /// Instructions (roughly) one hook call may run before it is stopped.
+    const FUEL: u64 = 50_000_000;
+    const MAX_MEMORY_BYTES: usize = 64 * 1024 * 1024;
+
+    pub(super) struct Plugin {
+        path: String,
+        module: Module,
+        engine: Engine,
+    }
+
+    impl Plugin {
+        pub(super) fn load(path: &str) -> AppResult<Self> {
+            let mut config = Config::new();
+            config.consume_fuel(true);
+            let engine = Engine::new(&config)
+                .map_err(|e| AppError::internal(format!("failed to start wasm runtime: {e}")))?;
+            let module = Module::from_file(&engine, path).map_err(|e| {
+                AppError::invalid_key(format!("failed to load wasm module {path}: {e:#}"))
+            })?;
+            Ok(Self {
+                path: path.to_string(),
+                module,
+                engine,
+            })
+        }
+
+        pub(super) fn exports(&self, name: &str) -> bool {
+            self.module.get_export(name).is_some()
+        }
+
+        fn fail(&self, what: impl std::fmt::Display) -> AppError {
+            AppError::internal(format!("wasm module {}: {what}", self.path))
+        }
+
+        fn run(&self, export: &str, input: &[u8]) -> AppResult<Vec<u8>> {
+            let limits = StoreLimitsBuilder::new()
+                .memory_size(MAX_MEMORY_BYTES)
+                .build();
+            let mut store: Store<StoreLimits> = Store::new(&self.engine, limits);
+            store.limiter(|limits| limits);
+            store.set_fuel(FUEL).map_err(|e| self.fail(e))?;
+            let instance = Instance::new(&mut store, &self.module, &[])
+                .map_err(|e| self.fail(format_args!("failed to instantiate: {e:#}")))?;
+            let memory: Memory = instance
+                .get_memory(&mut store, "memory")
+                .ok_or_else(|| self.fail("does not export `memory`"))?;
+            let alloc = instance
+                .get_typed_func::<i32, i32>(&mut store, "alloc")
+                .map_err(|e| self.fail(format_args!("`alloc(i32) -> i32`: {e:#}")))?;
+            let hook = instance
+                .get_typed_func::<(i32, i32), i64>(&mut store, export)
+                .map_err(|e| self.fail(format_args!("`{export}(i32, i32) -> i64`: {e:#}")))?;
+
+            let len = i32::try_from(input.len()).map_err(|_| self.fail("input is too large"))?;
+            let ptr = alloc
+                .call(&mut store, len)
+                .map_err(|e| self.fail(format_args!("alloc failed: {e:#}")))?;
+            memory
+                .write(&mut store, ptr as u32 as usize, input)
+                .map_err(|_| self.fail("alloc returned a buffer outside memory"))?;
+            let packed = hook
+                .call(&mut store, (ptr, len))
+                .map_err(|e| self.fail(format_args!("{export} failed: {e:#}")))?;
+
+            let (out_ptr, out_len) = ((packed as u64 >> 32) as usize, packed as u32 as usize);
+            memory
+                .data(&store)
+                .get(out_ptr..out_ptr + out_len)
+                .map(<[u8]>::to_vec)
+                .ok_or_else(|| self.fail(format_args!("{export} returned a range outside memory")))
+        }
+    }
+
+    pub(super) fn call(path: &str, export: &str, input: &[u8]) -> AppResult<Option<Vec<u8>>> {
+        let plugin = Plugin::load(path)?;
+        if !plugin.exports(export) {
+            return Ok(None);
+        }
+        plugin.run(export, input).map(Some)
+    }
+}
+
+#[cfg(not(feature = "wasm"))]
+mod runtime {
+    use crate::error::{AppError, AppResult};
+
+    pub(super) fn unavailable(path: &str) -> AppError {
+        AppError::invalid_key(format!(
+            "wasm module {path} requires a build with the `wasm` feature"
+        ))
+    }
+
+    pub(super) fn call(path: &str, _export: &str, _input: &[u8]) -> AppResult<Option<Vec<u8>>> {
+        Err(unavailable(path))
+    }
+}
+
+#[cfg(all(test, feature = "wasm"))]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// A module whose hooks answer with fixed JSON kept in a data segment.
+    fn module(dir: &tempfile::TempDir, exports: &str) -> String {
+        let path: PathBuf = dir.path().join("plugin.wat");
+        let wat = format!(
+            r#"(module
+                (memory (export "memory") 1)
+                (data (i32.const 0) "{{\"sub\":\"svc\",\"tenant\":\"acme\"}}")
+                (data (i32.const 64) "[\"tenant acme is suspended\"]")
+                (func (export "alloc") (param i32) (result i32) i32.const 1024)
+                {exports})"#
+        );
+        std::fs::write(&path, wat).expect("write module");
+        path.to_string_lossy().into_owned()
+    }
+
+    const TRANSFORM: &str = r#"(func (export "jwt_transform") (param i32 i32) (result i64)
+        i64.const 29)"#;
+    const VALIDATE: &str = r#"(func (export "jwt_validate") (param i32 i32) (result i64)
+        i64.const 274877906972)"#;
+    const SPIN: &str = r#"(func (export "jwt_validate") (param i32 i32) (result i64)
+        (loop $again (br $again)) i64.const 0)"#;
+
+    #[test]
+    fn runs_the_hooks_a_module_exports() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = module(&dir, &format!("{TRANSFORM} {VALIDATE}"));
+        assert_eq!(hooks(&path).unwrap(), [TRANSFORM_EXPORT, VALIDATE_EXPORT]);
+        assert_eq!(
+            transform_claims(&path, json!({ "sub": "user" })).unwrap(),
+            json!({ "sub": "svc", "tenant": "acme" })
+        );
+        assert_eq!(
+            validate(&path, &json!({}), &json!({})).unwrap(),
+            ["tenant acme is suspended"]
+        );
+    }
+
+    #[test]
+    fn missing_hooks_pass_through_and_runaway_modules_stop() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = module(&dir, SPIN);
+        assert_eq!(hooks(&path).unwrap(), [VALIDATE_EXPORT]);
+        let claims = json!({ "sub": "user" });
+        assert_eq!(transform_claims(&path, claims.clone()).unwrap(), claims);
+        let err = validate(&path, &json!({}), &claims).unwrap_err();
+        assert!(
+            err.message.contains("jwt_validate failed"),
+            "{}",
+            err.message
+        );
+
+        let path = module(&dir, "");
+        assert!(hooks(&path)
+            .unwrap_err()
+            .message
+            .contains("exports neither"));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::date_utils::parse_datetime;
 use crate::error::{AppError, AppResult};
 use humantime::parse_duration;
 use serde_json::{json, Map, Value};
@@ -13,8 +14,19 @@ pub struct StandardClaims {
     pub nbf: Option<String>,
     pub exp: Option<String>,
     pub no_iat: bool,
+    /// OpenID Connect authentication context: `acr` and `amr` (RFC 8176 values) and
+    /// `auth_time` (a time spec, like `iat`).
+    pub acr: Option<String>,
+    pub amr: Vec<String>,
+    pub auth_time: Option<String>,
 }
 
+/// Authentication Method Reference values registered by RFC 8176.
+pub const AMR_VALUES: &[&str] = &[
+    "face", "fpt", "geo", "hwk", "iris", "kba", "mca", "mfa", "otp", "pin", "pwd", "rba", "retina",
+    "sc", "sms", "swk", "tel", "user", "vbm", "wia",
+];
+
 pub fn build_claims(
     base: Value,
     claim_files: Vec<Value>,
@@ -100,9 +112,74 @@ fn apply_standard_claims(obj: &mut Map<String, Value>, standard: StandardClaims)
         obj.insert("exp".to_string(), json!(ts));
     }
 
+    if let Some(acr) = standard.acr {
+        if acr.trim().is_empty() || acr.chars().any(char::is_whitespace) {
+            return Err(AppError::invalid_claims(format!(
+                "acr must be a single value without whitespace, got {acr:?}"
+            )));
+        }
+        obj.insert("acr".to_string(), Value::String(acr));
+    }
+    if !standard.amr.is_empty() {
+        if let Some(unknown) = standard
+            .amr
+            .iter()
+            .find(|amr| !AMR_VALUES.contains(&amr.as_str()))
+        {
+            return Err(AppError::invalid_claims(format!(
+                "unknown amr value '{unknown}' (RFC 8176 values: {}); use --claim for others",
+                AMR_VALUES.join(", ")
+            )));
+        }
+        // amr is always an array, even with one method.
+        obj.insert("amr".to_string(), json!(standard.amr));
+    }
+    if let Some(auth_time) = standard.auth_time {
+        let ts = parse_time(&auth_time, now)?;
+        if ts > now {
+            return Err(AppError::invalid_claims(format!(
+                "auth_time {ts} is in the future; authentication happens before issuance"
+            )));
+        }
+        obj.insert("auth_time".to_string(), json!(ts));
+    }
+
     Ok(())
 }
 
+/// The `aud` values for `encode`: the `--aud` values, then each line of `listed` (blank lines
+/// and `#` comments skipped) expanded through `pattern`. Duplicates are dropped, keeping the first
+/// occurrence, so the order is stable for the same inputs.
+pub fn build_audiences(
+    aud: &[String],
+    listed: Option<String>,
+    pattern: Option<&str>,
+) -> AppResult<Vec<String>> {
+    if let Some(pattern) = pattern {
+        if pattern.matches("%s").count() != 1 {
+            return Err(AppError::invalid_claims(format!(
+                "--aud-pattern must contain %s exactly once, got '{pattern}'"
+            )));
+        }
+    }
+    let listed = listed.unwrap_or_default();
+    let expanded = listed
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|entry| match pattern {
+            Some(pattern) => pattern.replace("%s", entry),
+            None => entry.to_string(),
+        });
+    let mut audiences: Vec<String> = Vec::new();
+    for value in aud.iter().cloned().chain(expanded) {
+        if !audiences.contains(&value) {
+            audiences.push(value);
+        }
+    }
+    Ok(audiences)
+}
+
 pub fn parse_claim_kv(input: &str) -> AppResult<(String, Value)> {
     let mut parts = input.splitn(2, '=');
     let key = parts.next().unwrap_or("").trim();
@@ -132,6 +209,9 @@ pub fn parse_time(spec: &str, now: i64) -> AppResult<i64> {
     if let Ok(val) = raw.parse::<i64>() {
         return Ok(val);
     }
+    if let Some(ts) = parse_datetime(raw, now)? {
+        return Ok(ts);
+    }
     let mut sign = 1i64;
     let mut text = raw.to_string();
     if text.to_lowercase().contains("ago") {
@@ -167,6 +247,107 @@ pub fn now_epoch() -> i64 {
         .as_secs() as i64
 }
 
+/// Registered JWT and OpenID Connect claims; these keep their short names under a namespace.
+pub const UNNAMESPACED_CLAIMS: &[&str] = &[
+    "iss",
+    "sub",
+    "aud",
+    "exp",
+    "nbf",
+    "iat",
+    "jti",
+    "azp",
+    "nonce",
+    "auth_time",
+    "acr",
+    "amr",
+    "at_hash",
+    "c_hash",
+    "sid",
+    "scope",
+    "client_id",
+    "cnf",
+    "name",
+    "given_name",
+    "family_name",
+    "middle_name",
+    "nickname",
+    "preferred_username",
+    "profile",
+    "picture",
+    "website",
+    "email",
+    "email_verified",
+    "gender",
+    "birthdate",
+    "zoneinfo",
+    "locale",
+    "phone_number",
+    "phone_number_verified",
+    "address",
+    "updated_at",
+];
+
+/// Checks a `--claim-namespace` value is an http(s) URL and makes it end in `/`.
+pub fn normalize_namespace(raw: &str) -> AppResult<String> {
+    let raw = raw.trim();
+    let host = raw
+        .strip_prefix("https://")
+        .or_else(|| raw.strip_prefix("http://"))
+        .unwrap_or_default();
+    if host.is_empty() || host.starts_with('/') {
+        return Err(AppError::invalid_claims(format!(
+            "claim namespace '{raw}' must be an http(s) URL"
+        )));
+    }
+    if raw.ends_with('/') {
+        Ok(raw.to_string())
+    } else {
+        Ok(format!("{raw}/"))
+    }
+}
+
+/// Prefixes the key of a `k=v` claim with `namespace`, Auth0-style: registered claims and keys
+/// that already are URIs are left alone.
+pub fn namespace_claim_kv(kv: &str, namespace: &str) -> String {
+    let key = kv.split('=').next().unwrap_or_default().trim();
+    if key.is_empty() || key.contains(':') || UNNAMESPACED_CLAIMS.contains(&key) {
+        return kv.to_string();
+    }
+    format!("{namespace}{}", kv.trim_start())
+}
+
+/// Groups URL-keyed claims (`https://example.com/roles`) by namespace, keyed by the short name.
+pub fn group_namespaced_claims(payload: &Value) -> Map<String, Value> {
+    let mut groups = Map::new();
+    let Some(obj) = payload.as_object() else {
+        return groups;
+    };
+    for (key, value) in obj {
+        let Some(rest) = key
+            .strip_prefix("https://")
+            .or_else(|| key.strip_prefix("http://"))
+        else {
+            continue;
+        };
+        let Some(idx) = rest.rfind('/') else {
+            continue;
+        };
+        let split = key.len() - rest.len() + idx + 1;
+        let (namespace, name) = key.split_at(split);
+        if name.is_empty() {
+            continue;
+        }
+        let group = groups
+            .entry(namespace.to_string())
+            .or_insert_with(|| Value::Object(Map::new()));
+        if let Some(group) = group.as_object_mut() {
+            group.insert(name.to_string(), value.clone());
+        }
+    }
+    groups
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -196,6 +377,10 @@ mod tests {
         assert_eq!(parse_time("-30m", now).unwrap(), now - 1800);
         assert_eq!(parse_time("2 days", now).unwrap(), now + 172_800);
         assert_eq!(parse_time("2 days ago", now).unwrap(), now - 172_800);
+        assert_eq!(
+            parse_time("2025-06-30T12:00:00Z", now).unwrap(),
+            1_751_284_800
+        );
     }
 
     #[test]
@@ -209,6 +394,7 @@ mod tests {
             nbf: Some("1700000100".to_string()),
             exp: Some("1700000200".to_string()),
             no_iat: false,
+            ..StandardClaims::default()
         };
         let claims =
             build_claims(json!({}), Vec::new(), standard, Vec::new(), false).expect("claims");
@@ -238,4 +424,97 @@ mod tests {
             .expect("claims");
         assert!(claims.get("iat").is_none());
     }
+
+    #[test]
+    fn audiences_are_expanded_deduplicated_and_ordered() {
+        let listed = "# mesh services\npayments\n\n  ledger  \npayments\napi\n".to_string();
+        let audiences = build_audiences(
+            &["spiffe://cluster/ns/api".to_string(), "web".to_string()],
+            Some(listed),
+            Some("spiffe://cluster/ns/%s"),
+        )
+        .expect("audiences");
+        assert_eq!(
+            audiences,
+            [
+                "spiffe://cluster/ns/api",
+                "web",
+                "spiffe://cluster/ns/payments",
+                "spiffe://cluster/ns/ledger",
+            ]
+        );
+        assert_eq!(
+            build_audiences(&[], Some("a\nb\na".to_string()), None).expect("plain"),
+            ["a", "b"]
+        );
+        assert!(build_audiences(&[], Some("a".to_string()), Some("spiffe://x")).is_err());
+        assert!(build_audiences(&[], Some("a".to_string()), Some("%s/%s")).is_err());
+    }
+
+    #[test]
+    fn authentication_context_claims_are_validated() {
+        let standard = StandardClaims {
+            acr: Some("urn:mace:incommon:iap:silver".to_string()),
+            amr: vec!["pwd".to_string()],
+            auth_time: Some("1700000000".to_string()),
+            ..StandardClaims::default()
+        };
+        let claims =
+            build_claims(json!({}), Vec::new(), standard, Vec::new(), false).expect("claims");
+        assert_eq!(claims["acr"], "urn:mace:incommon:iap:silver");
+        assert_eq!(claims["amr"], json!(["pwd"]));
+        assert_eq!(claims["auth_time"], 1_700_000_000);
+
+        for standard in [
+            StandardClaims {
+                amr: vec!["pwd".to_string(), "password".to_string()],
+                ..StandardClaims::default()
+            },
+            StandardClaims {
+                acr: Some("level 2".to_string()),
+                ..StandardClaims::default()
+            },
+            StandardClaims {
+                auth_time: Some("+5m".to_string()),
+                ..StandardClaims::default()
+            },
+        ] {
+            let err = build_claims(json!({}), Vec::new(), standard, Vec::new(), false)
+                .expect_err("invalid");
+            assert_eq!(err.exit_code(), 12);
+        }
+    }
+
+    #[test]
+    fn claim_namespace_prefixes_custom_claims_and_groups_them_back() {
+        let ns = normalize_namespace("https://example.com").unwrap();
+        assert_eq!(ns, "https://example.com/");
+        assert!(normalize_namespace("example.com").is_err());
+        assert!(normalize_namespace("https:///x").is_err());
+
+        assert_eq!(
+            namespace_claim_kv("roles=[\"admin\"]", &ns),
+            "https://example.com/roles=[\"admin\"]"
+        );
+        assert_eq!(namespace_claim_kv("email=a@b.c", &ns), "email=a@b.c");
+        assert_eq!(
+            namespace_claim_kv("urn:x:tier=gold", &ns),
+            "urn:x:tier=gold"
+        );
+
+        let payload = json!({
+            "sub": "user",
+            "https://example.com/roles": ["admin"],
+            "https://example.com/app/tier": "gold",
+            "https://other.example/": 1,
+        });
+        let groups = group_namespaced_claims(&payload);
+        assert_eq!(
+            Value::Object(groups),
+            json!({
+                "https://example.com/": { "roles": ["admin"] },
+                "https://example.com/app/": { "tier": "gold" },
+            })
+        );
+    }
 }
@@ -1,26 +1,67 @@
-use super::crypto::{EncodeArgs, VerifyArgs, VerifyCommonArgs};
+use super::crypto::{EncodeArgs, JwtAlg, VerifyArgs, VerifyCommonArgs, WebhookArgs};
 use super::vault::VaultArgs;
 use clap::{Parser, Subcommand, ValueEnum};
+#[cfg(feature = "ui")]
 use std::net::IpAddr;
 use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Parser, Debug)]
 #[command(name = "jwt-tester")]
 #[command(about = "JWT CLI + local UI (MVP)", long_about = None)]
 #[command(version)]
+#[command(arg_required_else_help = true)]
 pub struct App {
     /// Output machine-readable JSON
     #[arg(long)]
     pub json: bool,
 
+    /// Output compact JSON, one object per result, one line each (batch and loop commands stream)
+    #[arg(long, conflicts_with = "json")]
+    pub json_lines: bool,
+
     /// Disable ANSI color output
     #[arg(long)]
     pub no_color: bool,
 
+    /// Color theme for text output (colors are only used on terminals unless CLICOLOR_FORCE is set)
+    #[arg(long, value_enum, default_value_t = Theme::Default)]
+    pub theme: Theme,
+
     /// Suppress non-essential output
     #[arg(long)]
     pub quiet: bool,
 
+    /// Render successful output with a template instead (minijinja; inline, @file, or env:NAME);
+    /// a template that fails to parse or render exits 17
+    #[arg(long)]
+    pub template: Option<String>,
+
+    /// Forbid network access; remote fetches fail with exit code 15
+    #[arg(long)]
+    pub offline: bool,
+
+    /// Always write a JSON result summary (status, error, timing, key source) to this path
+    #[arg(long)]
+    pub summary_file: Option<PathBuf>,
+
+    /// Also write each JSON result to this file (one line per result)
+    #[arg(long)]
+    pub output_file: Option<PathBuf>,
+
+    /// Also POST each JSON result to this URL
+    #[arg(long)]
+    pub output_webhook: Option<String>,
+
+    /// Also send each JSON result to the local syslog (Unix)
+    #[arg(long)]
+    pub output_syslog: bool,
+
+    /// Show a desktop notification when a long operation finishes (RSA key generation, vault
+    /// export/import, replay, monitor --once)
+    #[arg(long)]
+    pub notify_done: bool,
+
     /// Verbose diagnostics (no secrets)
     #[arg(long, short = 'v')]
     pub verbose: bool,
@@ -33,8 +74,37 @@ pub struct App {
     #[arg(long)]
     pub data_dir: Option<PathBuf>,
 
+    /// Load an export bundle into the in-memory vault before the command runs ('@file', '-',
+    /// 'fd:N' or 'env:NAME'). Implies --no-persist: nothing touches disk or the keychain
+    #[arg(long, value_name = "BUNDLE")]
+    pub vault_seed: Option<String>,
+
+    /// Passphrase for --vault-seed: literal, prompt[:LABEL], '@file', or 'env:NAME'
+    #[arg(long, requires = "vault_seed")]
+    pub vault_seed_passphrase: Option<String>,
+
+    /// age identity for a recipient-encrypted --vault-seed bundle: '@file' or 'env:NAME'
+    #[arg(long, requires = "vault_seed")]
+    pub vault_seed_identity: Option<String>,
+
+    /// Serve JSON-RPC 2.0 over stdin/stdout (one request per line) instead of running a command,
+    /// so editor plugins can keep one process and one open vault
+    #[arg(long)]
+    pub rpc: bool,
+
+    // Required unless `--rpc` is given; `workspace::parse_app` checks that.
     #[command(subcommand)]
-    pub command: Command,
+    pub command: Option<Command>,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Theme {
+    /// Standard ANSI colors
+    Default,
+    /// Bright ANSI colors for dark backgrounds
+    Bright,
+    /// No colors (same as --no-color)
+    None,
 }
 
 #[derive(Subcommand, Debug)]
@@ -53,16 +123,66 @@ pub enum Command {
     Verify(VerifyArgs),
 
     /// Encode a JWT using a key from the vault or direct input.
-    Encode(EncodeArgs),
+    Encode(Box<EncodeArgs>),
 
     /// Inspect a JWT with human-friendly summaries.
     Inspect(InspectArgs),
 
+    /// Summarize scopes, roles, groups, and permissions in a JWT, or diff two tokens (UNVERIFIED).
+    Analyze(AnalyzeArgs),
+
+    /// Replace identifying claims with consistent fakes of the same shape, for sharing tokens in
+    /// bug reports; the signature is replaced or re-signed with a throwaway key.
+    Anonymize(AnonymizeArgs),
+
+    /// Score a JWT against the JWT best current practices (RFC 8725) (UNVERIFIED).
+    Lint(LintArgs),
+
+    /// Check a claims JSON file before encoding, with line/column diagnostics.
+    LintClaims(LintClaimsArgs),
+
+    /// Recommend an algorithm migration for a project from a sample token, with token size and
+    /// signing cost estimates and a step-by-step checklist (UNVERIFIED).
+    Advise(AdviseArgs),
+
     /// Split JWT segments (decoded header/payload + signature bytes).
     Split(SplitArgs),
 
+    /// Show or clear recorded encode/verify operations (enable with JWT_TESTER_HISTORY=<N>).
+    History(HistoryArgs),
+
+    /// Check JWT-signed webhook deliveries against their request body.
+    Webhook(WebhookArgs),
+
+    /// Request tokens from an OAuth token endpoint and decode them.
+    FetchToken(FetchTokenArgs),
+
+    /// Watch tokens and alert before they expire.
+    Monitor(MonitorArgs),
+
+    /// Re-run a UI session recorded with `ui --record` through the CLI and compare the outcomes.
+    Replay(ReplayArgs),
+
+    /// List or run built-in recipes against a throwaway in-memory vault, showing each command.
+    Examples(ExamplesArgs),
+
+    /// Link the current directory to a vault project by writing a `.jwt-tester` file.
+    Init(InitArgs),
+
+    /// Run a local fake identity provider that mints tokens from vault project keys.
+    #[cfg(feature = "ui")]
+    Issuer(IssuerArgs),
+
+    /// Serve encode, verify, inspect and vault queries to other programs over gRPC.
+    #[cfg(feature = "ui")]
+    Serve(ServeArgs),
+
     /// Generate shell completion scripts.
     Completion(CompletionArgs),
+
+    /// Download the latest signed release and replace this binary.
+    #[cfg(feature = "self-update")]
+    SelfUpdate(SelfUpdateArgs),
 }
 
 #[cfg(feature = "ui")]
@@ -88,9 +208,166 @@ pub struct UiArgs {
     #[arg(long)]
     pub dev: bool,
 
+    /// Port for the Vite dev server (default: 5173 when free, otherwise any free port)
+    #[arg(long, requires = "dev")]
+    pub dev_port: Option<u16>,
+
+    /// Open the UI in the default browser once it is ready ($BROWSER overrides the launcher)
+    #[arg(long)]
+    pub open: bool,
+
+    /// Print a QR code of the UI URL to stderr, e.g. to open it on a phone with --allow-remote
+    #[arg(long)]
+    pub qr: bool,
+
+    /// Start with this .jwt/.jwks file loaded (a path, file:// URL or jwt-tester://open?path=...); implies --open
+    #[arg(long, value_name = "FILE")]
+    pub open_file: Option<String>,
+
     /// Path to the npm executable (override PATH).
     #[arg(long)]
     pub npm: Option<PathBuf>,
+
+    /// Append every /api/jwt/* request and response (secrets redacted) to this session file
+    #[arg(long, value_name = "FILE")]
+    pub record: Option<PathBuf>,
+
+    /// Let other local origins call /api/jwt/inspect (CORS, no CSRF token); every other endpoint stays locked down
+    #[arg(long)]
+    pub allow_cors_inspect: bool,
+}
+
+#[cfg(feature = "ui")]
+#[derive(Parser, Debug, Clone)]
+pub struct ServeArgs {
+    /// Serve the gRPC API in proto/jwt_tester/v1/jwt_tester.proto (needs the `grpc` feature)
+    #[arg(long, required = true)]
+    pub grpc: bool,
+
+    /// Host to bind to (default: 127.0.0.1)
+    #[arg(long, default_value = "127.0.0.1")]
+    pub host: IpAddr,
+
+    /// Port to bind to (0 = ephemeral)
+    #[arg(long, default_value_t = 0)]
+    pub port: u16,
+
+    /// Dangerous: allow binding to non-localhost addresses.
+    #[arg(long)]
+    pub allow_remote: bool,
+}
+
+#[cfg(feature = "ui")]
+#[derive(Parser, Debug)]
+pub struct IssuerArgs {
+    #[command(subcommand)]
+    pub cmd: IssuerCmd,
+}
+
+#[cfg(feature = "ui")]
+#[derive(Subcommand, Debug)]
+pub enum IssuerCmd {
+    /// Serve discovery, JWKS and a client_credentials `/token` endpoint until ctrl+c.
+    Serve(IssuerServeArgs),
+}
+
+#[cfg(feature = "ui")]
+#[derive(Parser, Debug, Clone)]
+pub struct IssuerServeArgs {
+    /// Project (name or id) whose keys sign tokens and are published in the JWKS
+    #[arg(long)]
+    pub project: String,
+
+    /// Signing key id (default: the project's default or only key)
+    #[arg(long, conflicts_with = "key_name")]
+    pub key_id: Option<String>,
+
+    /// Signing key name
+    #[arg(long)]
+    pub key_name: Option<String>,
+
+    /// Signing algorithm (default: from the key: HS256, RS256, ES256/ES384 or EdDSA)
+    #[arg(long, value_enum)]
+    pub alg: Option<JwtAlg>,
+
+    /// `iss` claim and discovery issuer (default: the server's base URL)
+    #[arg(long)]
+    pub issuer: Option<String>,
+
+    /// Client registrations: JSON object keyed by client_id (inline, @file, env:NAME). Without
+    /// it any client_id is accepted.
+    #[arg(long)]
+    pub clients: Option<String>,
+
+    /// Token lifetime for clients that do not set their own
+    #[arg(long, default_value = "1h")]
+    pub expires_in: String,
+
+    /// Host to bind to (default: 127.0.0.1)
+    #[arg(long, default_value = "127.0.0.1")]
+    pub host: IpAddr,
+
+    /// Port to bind to (0 = ephemeral)
+    #[arg(long, default_value_t = 0)]
+    pub port: u16,
+
+    /// Dangerous: allow binding to non-localhost addresses.
+    #[arg(long)]
+    pub allow_remote: bool,
+
+    /// Skew the issuer's clock for iat/exp (e.g. -2h, +30m)
+    #[arg(long, allow_hyphen_values = true)]
+    pub clock_offset: Option<String>,
+
+    /// Answer every token request with this OAuth error
+    #[arg(long, value_enum)]
+    pub force_error: Option<OAuthErrorCode>,
+
+    /// Wait this long before answering any request except /admin (e.g. 3s)
+    #[arg(long, value_parser = humantime::parse_duration)]
+    pub delay: Option<Duration>,
+
+    /// Bearer token for the /admin endpoints (raw, @file, env:NAME; default: generated and printed)
+    #[arg(long)]
+    pub admin_token: Option<String>,
+}
+
+/// Token endpoint error codes (RFC 6749 §5.2, plus the §4.1.2.1 server errors).
+#[cfg(feature = "ui")]
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OAuthErrorCode {
+    #[value(name = "invalid_request")]
+    InvalidRequest,
+    #[value(name = "invalid_client")]
+    InvalidClient,
+    #[value(name = "invalid_grant")]
+    InvalidGrant,
+    #[value(name = "unauthorized_client")]
+    UnauthorizedClient,
+    #[value(name = "unsupported_grant_type")]
+    UnsupportedGrantType,
+    #[value(name = "invalid_scope")]
+    InvalidScope,
+    #[value(name = "server_error")]
+    ServerError,
+    #[value(name = "temporarily_unavailable")]
+    TemporarilyUnavailable,
+}
+
+#[cfg(feature = "ui")]
+impl OAuthErrorCode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OAuthErrorCode::InvalidRequest => "invalid_request",
+            OAuthErrorCode::InvalidClient => "invalid_client",
+            OAuthErrorCode::InvalidGrant => "invalid_grant",
+            OAuthErrorCode::UnauthorizedClient => "unauthorized_client",
+            OAuthErrorCode::UnsupportedGrantType => "unsupported_grant_type",
+            OAuthErrorCode::InvalidScope => "invalid_scope",
+            OAuthErrorCode::ServerError => "server_error",
+            OAuthErrorCode::TemporarilyUnavailable => "temporarily_unavailable",
+        }
+    }
 }
 
 #[derive(Parser, Debug)]
@@ -106,8 +383,23 @@ pub struct DecodeArgs {
     #[arg(long)]
     pub out: Option<PathBuf>,
 
+    /// Read the token from a QR code in an image (@image.png); needs zbarimg from zbar
+    #[arg(long, value_name = "@IMAGE", conflicts_with = "token")]
+    pub from_qr: Option<String>,
+
+    /// The input is a JSON document (e.g. a token endpoint response); decode the string at this
+    /// JSONPath ($.access_token, $.data.tokens[0])
+    #[arg(long, value_name = "PATH", conflicts_with = "from_qr")]
+    pub token_path: Option<String>,
+
+    /// (Linux) Once the token is read, drop filesystem, network and process access (seccomp)
+    /// before parsing it; decode only, no verification
+    #[arg(long, conflicts_with_all = ["out", "from_qr"])]
+    pub sandbox: bool,
+
     /// The JWT to decode, or '-' to read from stdin.
-    pub token: String,
+    #[arg(required_unless_present = "from_qr")]
+    pub token: Option<String>,
 }
 
 #[derive(Parser, Debug)]
@@ -120,8 +412,54 @@ pub struct InspectArgs {
     #[arg(long)]
     pub show_segments: bool,
 
+    /// Break the payload size down per claim, with a deflate estimate and trimming suggestions
+    #[arg(long)]
+    pub sizes: bool,
+
+    /// Warn when the token is longer than this many bytes (e.g. 4096 for a cookie); implies --sizes
+    #[arg(long)]
+    pub budget: Option<usize>,
+
+    /// Check the token fits: cookie (4096 B), header (8192 B), or cookie:<bytes>/header:<bytes>; repeatable
+    #[arg(long)]
+    pub fits_in: Vec<String>,
+
+    /// Text before the token (default: "Bearer " in a header, nothing in a cookie)
+    #[arg(long)]
+    pub prefix: Option<String>,
+
+    /// Cookie name counted by --fits-in cookie
+    #[arg(long, default_value = "token")]
+    pub cookie_name: String,
+
+    /// Header name counted by --fits-in header
+    #[arg(long, default_value = "Authorization")]
+    pub header_name: String,
+
+    /// Download the header's jku/x5u (allow-listed URLs only) and check the signature against them
+    #[arg(long, requires = "allow_url")]
+    pub fetch_header_urls: bool,
+
+    /// URL prefix --fetch-header-urls may download from (same scheme, host and port); repeatable
+    #[arg(long)]
+    pub allow_url: Vec<String>,
+
+    /// Make double-clicking .jwt/.jwks files (and jwt-tester:// links) open them in the local UI
+    #[arg(long, conflicts_with = "token")]
+    pub register_file_association: bool,
+
+    /// With --register-file-association: print what would be written and run, change nothing
+    #[arg(long, requires = "register_file_association")]
+    pub dry_run: bool,
+
+    /// (Linux) Once the token is read, drop filesystem, network and process access (seccomp)
+    /// before parsing it
+    #[arg(long, conflicts_with_all = ["fetch_header_urls", "register_file_association"])]
+    pub sandbox: bool,
+
     /// The JWT to inspect, or '-' to read from stdin.
-    pub token: String,
+    #[arg(required_unless_present = "register_file_association")]
+    pub token: Option<String>,
 }
 
 #[derive(ValueEnum, Debug, Clone, Copy)]
@@ -132,6 +470,110 @@ pub enum SplitFormat {
     Json,
 }
 
+#[derive(Parser, Debug)]
+pub struct AnalyzeArgs {
+    /// Compare against this token and list added/removed permissions
+    #[arg(long)]
+    pub diff: Option<String>,
+
+    /// The JWT to analyze, or '-' to read from stdin.
+    pub token: String,
+}
+
+#[derive(Parser, Debug)]
+pub struct AnonymizeArgs {
+    /// Derive the fakes and time shift from this seed, so separate runs give the same values
+    #[arg(long)]
+    pub seed: Option<String>,
+
+    /// Also replace this claim (e.g. tenant); repeatable
+    #[arg(long = "claim", value_name = "NAME")]
+    pub claims: Vec<String>,
+
+    /// Leave this claim (name or dotted path, e.g. iss or address.country) as it is; repeatable
+    #[arg(long, value_name = "NAME")]
+    pub keep: Vec<String>,
+
+    /// Move exp/nbf/iat and other timestamps 1-365 days back (one offset for all, so gaps are
+    /// kept; the result may be expired)
+    #[arg(long)]
+    pub shift_times: bool,
+
+    /// Re-sign with a throwaway key generated for the header's alg (printed with the result)
+    #[arg(long)]
+    pub resign: bool,
+
+    /// The JWTs to anonymize (shared values get the same fakes in all of them), or '-' for stdin.
+    #[arg(required = true)]
+    pub tokens: Vec<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct LintArgs {
+    /// Apply the JWT best current practices (RFC 8725)
+    #[arg(long)]
+    pub rfc8725: bool,
+
+    /// Algorithm verifiers pin; a different header alg fails (omit to only warn about pinning)
+    #[arg(long, value_enum)]
+    pub expect_alg: Option<JwtAlg>,
+
+    /// Warn when exp is further than this from iat (or from now without iat)
+    #[arg(long, default_value = "24h", value_parser = humantime::parse_duration)]
+    pub max_lifetime: Duration,
+
+    /// Issuer and audiences are separate parties; symmetric (HS*) algorithms fail
+    #[arg(long)]
+    pub cross_boundary: bool,
+
+    /// Also exit 12 when the score is below this
+    #[arg(long)]
+    pub min_score: Option<u32>,
+
+    /// The JWT to lint, or '-' to read from stdin.
+    pub token: String,
+}
+
+#[derive(Parser, Debug)]
+pub struct AdviseArgs {
+    /// Project name or id whose keys the plan covers
+    #[arg(long)]
+    pub project: String,
+
+    /// Algorithm to migrate to (default: RS256 from HS*, ES256 from RS*/PS*)
+    #[arg(long, value_enum)]
+    pub target_alg: Option<JwtAlg>,
+
+    /// Sign/verify round trips timed per algorithm for the estimates
+    #[arg(long, default_value_t = 100)]
+    pub iterations: usize,
+
+    /// A sample JWT the project issues, or '-' to read from stdin.
+    pub token: String,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClaimProfile {
+    /// OpenID Connect ID token
+    Oidc,
+    /// OAuth JWT access token (RFC 9068)
+    AccessToken,
+}
+
+#[derive(Parser, Debug)]
+pub struct LintClaimsArgs {
+    /// Signing algorithm the claims are meant for (checks at_hash/c_hash lengths)
+    #[arg(long, value_enum)]
+    pub alg: Option<JwtAlg>,
+
+    /// Also require the claims this token type must carry
+    #[arg(long, value_enum)]
+    pub profile: Option<ClaimProfile>,
+
+    /// Claims JSON string, '@file', or '-' to read from stdin.
+    pub claims: String,
+}
+
 #[derive(Parser, Debug)]
 pub struct SplitArgs {
     /// Output format
@@ -142,6 +584,218 @@ pub struct SplitArgs {
     pub token: String,
 }
 
+#[derive(Parser, Debug)]
+pub struct HistoryArgs {
+    #[command(subcommand)]
+    pub cmd: HistoryCmd,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum HistoryCmd {
+    /// List recorded operations, newest first
+    List {
+        /// Show at most this many entries.
+        #[arg(long)]
+        limit: Option<usize>,
+    },
+    /// Delete all recorded operations
+    Clear,
+}
+
+#[derive(Parser, Debug)]
+pub struct InitArgs {
+    /// Project (name or id) that commands run in this directory tree use by default
+    #[arg(long)]
+    pub project: String,
+
+    /// Default `encode --alg`
+    #[arg(long, value_enum)]
+    pub alg: Option<JwtAlg>,
+
+    /// Default `encode` claims (JSON or @file; relative paths are resolved from this directory)
+    #[arg(long)]
+    pub claims: Option<String>,
+
+    /// Replace an existing `.jwt-tester` file
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct ReplayArgs {
+    /// Session file written by `jwt-tester ui --record`
+    pub session: PathBuf,
+
+    /// Replay only this entry (1-based)
+    #[arg(long)]
+    pub entry: Option<usize>,
+}
+
+#[derive(Parser, Debug)]
+pub struct ExamplesArgs {
+    #[command(subcommand)]
+    pub cmd: ExamplesCmd,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ExamplesCmd {
+    /// List the recipes and their parameters
+    List,
+    /// Run recipes (all of them when none is named); exits 12 if a step's exit code is unexpected
+    Run {
+        /// Recipe names
+        names: Vec<String>,
+        /// Override a recipe parameter (NAME=VALUE); repeatable
+        #[arg(long, value_name = "NAME=VALUE")]
+        set: Vec<String>,
+    },
+}
+
+#[derive(Parser, Debug)]
+pub struct MonitorArgs {
+    /// Tokens to watch (raw, @file, env:NAME); inputs are re-read on every check
+    pub tokens: Vec<String>,
+
+    /// Also watch every token stored in this vault project (name or id)
+    #[arg(long)]
+    pub project: Option<String>,
+
+    /// Alert when a token expires within this window
+    #[arg(long, default_value = "1h", value_parser = humantime::parse_duration)]
+    pub warn_before: Duration,
+
+    /// Time between checks
+    #[arg(long, default_value = "5m", value_parser = humantime::parse_duration)]
+    pub interval: Duration,
+
+    /// Check once and exit (12 if any token is expiring, expired, or unreadable)
+    #[arg(long)]
+    pub once: bool,
+
+    /// Shell command to run for each alert (token details in JWT_TESTER_MONITOR_* env vars)
+    #[arg(long)]
+    pub exec: Option<String>,
+
+    /// URL to POST each alert to as JSON
+    #[arg(long)]
+    pub webhook: Option<String>,
+
+    /// Show a desktop notification for each alert
+    #[arg(long)]
+    pub notify: bool,
+}
+
+#[cfg(feature = "self-update")]
+#[derive(Parser, Debug)]
+pub struct SelfUpdateArgs {
+    /// Only report whether a newer release exists
+    #[arg(long)]
+    pub check: bool,
+
+    /// Install this release tag instead of the latest one (e.g. v0.3.0)
+    #[arg(long)]
+    pub tag: Option<String>,
+
+    /// Reinstall even when the release is not newer
+    #[arg(long)]
+    pub force: bool,
+
+    /// Minisign public key the release must be signed with (inline, @file, env:NAME)
+    #[arg(long)]
+    pub public_key: Option<String>,
+
+    /// GitHub repository to take releases from
+    #[arg(long, default_value = "jMerta/jwt-tester")]
+    pub repo: String,
+
+    /// GitHub API base URL (for mirrors or GitHub Enterprise)
+    #[arg(long, default_value = "https://api.github.com")]
+    pub api_url: String,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GrantType {
+    #[value(name = "client-credentials")]
+    ClientCredentials,
+    #[value(name = "password")]
+    Password,
+    #[value(name = "device-code")]
+    DeviceCode,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientAuth {
+    /// HTTP Basic (client_secret_basic)
+    #[value(name = "basic")]
+    Basic,
+    /// Form parameters (client_secret_post)
+    #[value(name = "post")]
+    Post,
+}
+
+#[derive(Parser, Debug)]
+pub struct FetchTokenArgs {
+    /// Token endpoint URL
+    #[arg(long)]
+    pub token_url: String,
+
+    /// Grant type to request
+    #[arg(long, value_enum, default_value_t = GrantType::ClientCredentials)]
+    pub grant: GrantType,
+
+    /// OAuth client_id
+    #[arg(long)]
+    pub client_id: String,
+
+    /// Client secret (raw, @file, env:NAME, prompt[:LABEL]); omit for public clients
+    #[arg(long)]
+    pub client_secret: Option<String>,
+
+    /// How to send the client secret
+    #[arg(long, value_enum, default_value_t = ClientAuth::Basic)]
+    pub client_auth: ClientAuth,
+
+    /// Requested scope (space-separated)
+    #[arg(long)]
+    pub scope: Option<String>,
+
+    /// audience parameter expected by some providers
+    #[arg(long)]
+    pub audience: Option<String>,
+
+    /// Resource owner username (password grant)
+    #[arg(long, required_if_eq("grant", "password"))]
+    pub username: Option<String>,
+
+    /// Resource owner password (password grant; raw, @file, env:NAME, prompt[:LABEL])
+    #[arg(long, required_if_eq("grant", "password"))]
+    pub password: Option<String>,
+
+    /// Device authorization endpoint URL (device-code grant)
+    #[arg(long, required_if_eq("grant", "device-code"))]
+    pub device_url: Option<String>,
+
+    /// Extra form parameter (k=v); repeatable
+    #[arg(long)]
+    pub param: Vec<String>,
+
+    /// Store the returned tokens in this vault project (name or id)
+    #[arg(long)]
+    pub project: Option<String>,
+
+    /// Vault token name prefix (default: the client id, or the --refresh token's prefix)
+    #[arg(long, requires = "project")]
+    pub name: Option<String>,
+
+    /// Run a refresh_token grant with this stored token (name, or prefix of '<prefix>-refresh')
+    #[arg(long, requires = "project", conflicts_with = "grant")]
+    pub refresh: Option<String>,
+
+    /// After a rotation, replay the old refresh token and report whether the IdP rejects it
+    #[arg(long, requires = "refresh")]
+    pub check_rotation: bool,
+}
+
 #[derive(Parser, Debug)]
 pub struct CompletionArgs {
     /// Shell type
@@ -173,14 +827,14 @@ mod tests {
     fn parse_split_args_and_decode_date() {
         let app = App::try_parse_from(["jwt-tester", "split", "--format", "json", "tok"])
             .expect("parse split");
-        match app.command {
+        match app.command.expect("command") {
             Command::Split(args) => assert!(matches!(args.format, SplitFormat::Json)),
             _ => panic!("expected split command"),
         }
 
         let app = App::try_parse_from(["jwt-tester", "decode", "--date", "local", "tok"])
             .expect("parse decode");
-        match app.command {
+        match app.command.expect("command") {
             Command::Decode(args) => assert_eq!(args.date.as_deref(), Some("local")),
             _ => panic!("expected decode command"),
         }
@@ -189,7 +843,7 @@ mod tests {
     #[test]
     fn parse_completion_shell() {
         let app = App::try_parse_from(["jwt-tester", "completion", "bash"]).expect("parse");
-        match app.command {
+        match app.command.expect("command") {
             Command::Completion(args) => assert!(matches!(args.shell, CompletionShell::Bash)),
             _ => panic!("expected completion command"),
         }
@@ -207,7 +861,7 @@ mod tests {
             "C:\\npm.cmd",
         ])
         .expect("parse ui");
-        match app.command {
+        match app.command.expect("command") {
             Command::Ui(args) => {
                 assert!(args.build);
                 assert!(args.dev);
@@ -216,4 +870,16 @@ mod tests {
             _ => panic!("expected ui command"),
         }
     }
+
+    #[cfg(feature = "ui")]
+    #[test]
+    fn parse_ui_dev_port_requires_dev() {
+        let app = App::try_parse_from(["jwt-tester", "ui", "--dev", "--dev-port", "5200"])
+            .expect("parse ui");
+        match app.command.expect("command") {
+            Command::Ui(args) => assert_eq!(args.dev_port, Some(5200)),
+            _ => panic!("expected ui command"),
+        }
+        assert!(App::try_parse_from(["jwt-tester", "ui", "--dev-port", "5200"]).is_err());
+    }
 }
@@ -1,6 +1,7 @@
-use clap::{Args, Parser, ValueEnum};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use jsonwebtoken::Algorithm;
 use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(ValueEnum, Debug, Clone, Copy)]
 pub enum JwtAlg {
@@ -57,28 +58,154 @@ pub enum KeyFormat {
     Der,
 }
 
+/// The unit of `exp`, `nbf` and `iat`. RFC 7519 says seconds, but some stacks emit milliseconds.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampUnit {
+    /// Seconds, as the token says
+    #[value(name = "s")]
+    Seconds,
+    /// Milliseconds: divide by 1000 before validating
+    #[value(name = "ms")]
+    Millis,
+    /// Milliseconds only when the value is too large to be seconds
+    #[value(name = "auto")]
+    Auto,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayloadZip {
+    /// Raw DEFLATE (RFC 1951), the only JOSE `zip` value
+    #[value(name = "DEF", alias = "def")]
+    Def,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncodePreset {
+    /// OAuth authorization request object (RFC 9101, JAR)
+    #[value(name = "request-object")]
+    RequestObject,
+    /// ACME JWS (RFC 8555): nonce and url headers, jwk or kid, no typ
+    Acme,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyProfile {
+    /// OAuth authorization request object (RFC 9101, JAR)
+    #[value(name = "request-object")]
+    RequestObject,
+}
+
 #[derive(Parser, Debug)]
 pub struct VerifyArgs {
     #[command(flatten)]
     pub verify: VerifyCommonArgs,
 
+    /// Per-issuer rules (YAML, @file): the token's iss picks the tenant whose JWKS or project,
+    /// audiences and algorithms are used
+    #[arg(
+        long,
+        value_name = "@FILE",
+        conflicts_with_all = ["secret", "key", "jwks", "project", "iss"]
+    )]
+    pub tenant_config: Option<String>,
+
+    /// Apply token-type rules after verification (request-object)
+    #[arg(long, value_enum)]
+    pub profile: Option<VerifyProfile>,
+
+    /// Expected client_id for --profile request-object
+    #[arg(long, requires = "profile")]
+    pub client_id: Option<String>,
+
+    /// Only show these claims (comma-separated; dotted paths reach nested claims)
+    #[arg(long, value_delimiter = ',')]
+    pub print_claims: Vec<String>,
+
+    /// Evaluate exp/nbf across a time range (START..END:STEP, e.g. now-1h..now+2h:5m) and report
+    /// where validity changes
+    #[arg(long, value_name = "RANGE")]
+    pub sweep: Option<String>,
+
+    /// When the signature is valid but claims fail, still print the claims with the violations
+    /// (valid: false); the exit code stays 12
+    #[arg(long)]
+    pub continue_on_claim_failure: bool,
+
+    /// The input is a JSON document (e.g. a token endpoint response); verify the string at this
+    /// JSONPath ($.access_token, $.data.tokens[0])
+    #[arg(long, value_name = "PATH")]
+    pub token_path: Option<String>,
+
+    /// Rhai script of custom rules run after verification: `rule(condition, "message")` over
+    /// `claims`, `header` and `now`; every failed rule is reported (exit 12)
+    #[arg(long, value_name = "FILE")]
+    pub script: Option<PathBuf>,
+
     /// Token to verify, or '-' to read from stdin
     pub token: String,
 }
 
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BodyHashAlg {
+    #[value(name = "sha256")]
+    Sha256,
+    #[value(name = "sha384")]
+    Sha384,
+    #[value(name = "sha512")]
+    Sha512,
+}
+
+#[derive(Parser, Debug)]
+pub struct WebhookArgs {
+    #[command(subcommand)]
+    pub cmd: WebhookCmd,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum WebhookCmd {
+    /// Verify a JWT signature header and the body hash claim it carries
+    Verify(WebhookVerifyArgs),
+}
+
+#[derive(Parser, Debug)]
+pub struct WebhookVerifyArgs {
+    #[command(flatten)]
+    pub verify: VerifyCommonArgs,
+
+    /// Request body file as received (byte-exact), or '-' for stdin
+    #[arg(long)]
+    pub body: PathBuf,
+
+    /// Signature header: the JWT or a full 'Name: value' header line (raw, @file, env:NAME)
+    #[arg(long)]
+    pub signature: String,
+
+    /// Claim holding the body hash
+    #[arg(long, default_value = "request_body_sha256")]
+    pub hash_claim: String,
+
+    /// Hash algorithm used for the body
+    #[arg(long, value_enum, default_value_t = BodyHashAlg::Sha256)]
+    pub hash_alg: BodyHashAlg,
+
+    /// Reject signatures whose iat is older than this (e.g. 5m)
+    #[arg(long, value_parser = humantime::parse_duration)]
+    pub max_age: Option<Duration>,
+}
+
 #[derive(Args, Debug, Clone)]
 pub struct VerifyCommonArgs {
-    /// HMAC secret (raw, @file, -, env:NAME, b64:BASE64, or prompt[:LABEL])
+    /// HMAC secret (raw, @file, -, env:NAME, fd:N, b64:BASE64, or prompt[:LABEL])
     #[arg(long)]
     pub secret: Option<String>,
 
-    /// Public key (PEM/DER) for RS*/PS*/ES*/EdDSA (supports @file, -, env:NAME, b64:BASE64, prompt[:LABEL])
+    /// Public key (PEM/DER) for RS*/PS*/ES*/EdDSA (supports @file, -, env:NAME, fd:N, b64:BASE64, prompt[:LABEL])
     #[arg(long)]
     pub key: Option<String>,
 
-    /// JWKS (JSON)
+    /// JWKS (JSON, @file, env:NAME, or an http(s) URL); repeat to try sources in order
     #[arg(long)]
-    pub jwks: Option<String>,
+    pub jwks: Vec<String>,
 
     /// Key format override (pem|der)
     #[arg(long, value_enum)]
@@ -104,10 +231,18 @@ pub struct VerifyCommonArgs {
     #[arg(long)]
     pub key_name: Option<String>,
 
+    /// Optional key fingerprint to use (sha256:<hex> or a unique prefix, within the project)
+    #[arg(long)]
+    pub key_fingerprint: Option<String>,
+
     /// Try all keys in the project if the selected/default key fails with InvalidSignature.
     #[arg(long)]
     pub try_all_keys: bool,
 
+    /// Re-fetch the project's stored JWKS from their source URLs when the kid is not found
+    #[arg(long)]
+    pub auto_refresh: bool,
+
     /// Ignore token expiration (exp) during verification
     #[arg(long)]
     pub ignore_exp: bool,
@@ -116,6 +251,14 @@ pub struct VerifyCommonArgs {
     #[arg(long, default_value_t = 30)]
     pub leeway_secs: u64,
 
+    /// Unit of exp/nbf/iat; `auto` normalizes millisecond values to seconds with a warning
+    #[arg(long, value_enum, default_value_t = TimestampUnit::Auto)]
+    pub timestamps: TimestampUnit,
+
+    /// Remember that a JWKS URL lacks a kid for this long (0 disables the negative cache)
+    #[arg(long, default_value = "5m", value_parser = humantime::parse_duration)]
+    pub jwks_negative_ttl: Duration,
+
     /// Issuer validation (iss)
     #[arg(long)]
     pub iss: Option<String>,
@@ -132,6 +275,18 @@ pub struct VerifyCommonArgs {
     #[arg(long)]
     pub require: Vec<String>,
 
+    /// Require the acr claim to be one of these values; repeatable
+    #[arg(long)]
+    pub require_acr: Vec<String>,
+
+    /// Reject tokens whose auth_time is older than this (e.g. 5m); requires auth_time
+    #[arg(long, value_parser = humantime::parse_duration)]
+    pub max_auth_age: Option<Duration>,
+
+    /// Reject tokens on the project's revocation list (`vault token revoke`); exits 16
+    #[arg(long, requires = "project")]
+    pub check_revocation: bool,
+
     /// Print validation details
     #[arg(long)]
     pub explain: bool,
@@ -143,11 +298,11 @@ pub struct VerifyCommonArgs {
 
 #[derive(Parser, Debug)]
 pub struct EncodeArgs {
-    /// HMAC secret (raw, @file, -, env:NAME, b64:BASE64, or prompt[:LABEL])
+    /// HMAC secret (raw, @file, -, env:NAME, fd:N, b64:BASE64, or prompt[:LABEL])
     #[arg(long)]
     pub secret: Option<String>,
 
-    /// Private key (PEM/DER) for RS256/ES256/EdDSA (supports @file, -, env:NAME, b64:BASE64, prompt[:LABEL])
+    /// Private key (PEM/DER) for RS256/ES256/EdDSA (supports @file, -, env:NAME, fd:N, b64:BASE64, prompt[:LABEL])
     #[arg(long)]
     pub key: Option<String>,
 
@@ -167,6 +322,10 @@ pub struct EncodeArgs {
     #[arg(long)]
     pub key_name: Option<String>,
 
+    /// Optional key fingerprint to use (sha256:<hex> or a unique prefix, within the project)
+    #[arg(long)]
+    pub key_fingerprint: Option<String>,
+
     /// Algorithm to sign with
     #[arg(long, value_enum)]
     pub alg: JwtAlg,
@@ -179,6 +338,11 @@ pub struct EncodeArgs {
     #[arg(long)]
     pub header: Option<String>,
 
+    /// Start from a captured token (literal, @file or -): copy its payload and header, except
+    /// what ties the header to the original key (kid, jku, jwk, x5*), then apply the overrides
+    #[arg(long, value_name = "TOKEN", conflicts_with_all = ["claims", "header"])]
+    pub mimic: Option<String>,
+
     /// Optional kid to place in the header
     #[arg(long)]
     pub kid: Option<String>,
@@ -191,6 +355,14 @@ pub struct EncodeArgs {
     #[arg(long)]
     pub no_typ: bool,
 
+    /// Extra protected header parameter (k=v, JSON values allowed); repeatable
+    #[arg(long)]
+    pub header_param: Vec<String>,
+
+    /// Deflate the payload and set the zip header (DEF); meant for interop tests, RFC 8725 advises against it
+    #[arg(long, value_enum)]
+    pub zip: Option<PayloadZip>,
+
     /// Standard claims
     #[arg(long)]
     pub iss: Option<String>,
@@ -198,6 +370,16 @@ pub struct EncodeArgs {
     pub sub: Option<String>,
     #[arg(long)]
     pub aud: Vec<String>,
+
+    /// Audiences one per line ('#' comments and blank lines skipped); @file, - or env:NAME
+    #[arg(long, value_name = "@FILE")]
+    pub aud_from_file: Option<String>,
+
+    /// Expand each --aud-from-file entry with this pattern, where %s is the entry
+    /// (e.g. spiffe://cluster/ns/%s)
+    #[arg(long, requires = "aud_from_file")]
+    pub aud_pattern: Option<String>,
+
     #[arg(long)]
     pub jti: Option<String>,
 
@@ -217,10 +399,26 @@ pub struct EncodeArgs {
     #[arg(long, num_args = 0..=1, default_missing_value = "+30m")]
     pub exp: Option<String>,
 
+    /// Authentication context class reference (acr), e.g. a URI or level name
+    #[arg(long)]
+    pub acr: Option<String>,
+
+    /// Authentication method reference (amr) from RFC 8176 (pwd, otp, mfa, hwk, ...); repeatable
+    #[arg(long)]
+    pub amr: Vec<String>,
+
+    /// Time the user authenticated (auth_time; seconds, date or duration, e.g. --auth-time=-10m)
+    #[arg(long)]
+    pub auth_time: Option<String>,
+
     /// Custom claim (k=v); repeatable
     #[arg(long)]
     pub claim: Vec<String>,
 
+    /// Prefix custom --claim keys with this namespace URL (e.g. https://example.com/)
+    #[arg(long, value_name = "URL")]
+    pub claim_namespace: Option<String>,
+
     /// JSON claim file to merge; repeatable
     #[arg(long)]
     pub claim_file: Vec<String>,
@@ -232,6 +430,52 @@ pub struct EncodeArgs {
     /// Write token to file
     #[arg(long)]
     pub out: Option<PathBuf>,
+
+    /// Print the token as a terminal QR code (read back with `decode --from-qr`)
+    #[arg(long, conflicts_with = "armor")]
+    pub qr: bool,
+
+    /// Print the token as a wrapped text block with per-line checksums, for retyping
+    #[arg(long)]
+    pub armor: bool,
+
+    #[command(flatten)]
+    pub preset: PresetArgs,
+}
+
+#[derive(Args, Debug, Clone, Default)]
+pub struct PresetArgs {
+    /// Fill header and claims for a token type (request-object, acme)
+    #[arg(long, value_enum)]
+    pub preset: Option<EncodePreset>,
+
+    /// OAuth client_id; also the default iss (request-object)
+    #[arg(long, requires = "preset")]
+    pub client_id: Option<String>,
+
+    /// OAuth redirect_uri (request-object)
+    #[arg(long, requires = "preset")]
+    pub redirect_uri: Option<String>,
+
+    /// OAuth scope, space-separated (request-object)
+    #[arg(long, requires = "preset")]
+    pub scope: Option<String>,
+
+    /// OAuth response_type (request-object; default: code)
+    #[arg(long, requires = "preset")]
+    pub response_type: Option<String>,
+
+    /// OAuth state (request-object)
+    #[arg(long, requires = "preset")]
+    pub state: Option<String>,
+
+    /// OpenID Connect nonce (request-object)
+    #[arg(long, requires = "preset")]
+    pub nonce: Option<String>,
+
+    /// Fetch a fresh nonce from this newNonce URL unless one is set (acme)
+    #[arg(long, requires = "preset")]
+    pub nonce_url: Option<String>,
 }
 
 #[cfg(test)]
@@ -2,8 +2,22 @@ mod app;
 mod crypto;
 mod vault;
 
+#[cfg(feature = "self-update")]
+pub use app::SelfUpdateArgs;
 pub use app::{
-    App, Command, CompletionArgs, CompletionShell, DecodeArgs, InspectArgs, SplitArgs, SplitFormat,
+    AdviseArgs, AnalyzeArgs, AnonymizeArgs, App, ClaimProfile, ClientAuth, Command, CompletionArgs,
+    CompletionShell, DecodeArgs, ExamplesArgs, ExamplesCmd, FetchTokenArgs, GrantType, HistoryArgs,
+    HistoryCmd, InitArgs, InspectArgs, LintArgs, LintClaimsArgs, MonitorArgs, ReplayArgs,
+    SplitArgs, SplitFormat, Theme,
+};
+#[cfg(feature = "ui")]
+pub use app::{IssuerCmd, IssuerServeArgs, OAuthErrorCode, ServeArgs};
+pub use crypto::{
+    BodyHashAlg, EncodeArgs, EncodePreset, JwtAlg, KeyFormat, PayloadZip, PresetArgs,
+    TimestampUnit, VerifyArgs, VerifyCommonArgs, VerifyProfile, WebhookArgs, WebhookCmd,
+    WebhookVerifyArgs,
+};
+pub use vault::{
+    AlgFamily, BackupCmd, JwksCmd, KeyCmd, ListFormat, ListFormatArgs, OnConflict, ProjectCmd,
+    TokenCmd, VaultArgs, VaultCmd, VaultWebhookCmd,
 };
-pub use crypto::{EncodeArgs, JwtAlg, KeyFormat, VerifyArgs, VerifyCommonArgs};
-pub use vault::{KeyCmd, ProjectCmd, TokenCmd, VaultArgs, VaultCmd};
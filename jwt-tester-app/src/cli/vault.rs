@@ -1,8 +1,13 @@
-use clap::{Parser, Subcommand};
+use super::crypto::JwtAlg;
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
 pub struct VaultArgs {
+    /// Answer yes to confirmation prompts (project delete, key delete --all, import --replace,
+    /// backup restore); required when not running in a terminal
+    #[arg(long, short = 'y', global = true)]
+    pub yes: bool,
     #[command(subcommand)]
     pub cmd: VaultCmd,
 }
@@ -15,26 +20,98 @@ pub enum VaultCmd {
     Key(KeyCmd),
     #[command(subcommand)]
     Token(TokenCmd),
+    /// Store JWKS documents for kid-based verification with --project
+    #[command(subcommand)]
+    Jwks(JwksCmd),
+    /// POST HMAC-signed notifications when keys are added, rotated or deleted and tokens are
+    /// added
+    #[command(subcommand)]
+    Webhook(VaultWebhookCmd),
+    /// Manage automatic pre-change backups
+    #[command(subcommand)]
+    Backup(BackupCmd),
+    /// Find projects, keys, tokens and JWKS by name, kid, description or tag across all projects
+    Search {
+        /// Case-insensitive text to look for.
+        query: String,
+    },
+    /// Check that every stored key and token has a readable keychain entry and that key
+    /// material parses as its declared kind
+    VerifyIntegrity,
+    /// Show totals per project (keys by kind, tokens, JWKS, oldest/newest entry) and storage sizes
+    Stats,
+    /// List audit entries (uses of --include-non-exportable), newest first
+    Audit {
+        #[command(flatten)]
+        listing: ListFormatArgs,
+    },
+    /// Move the vault database, backups and a file keychain kept in the data dir to another
+    /// directory, verifying the copy before the original is removed
+    Relocate {
+        /// Target directory; must be empty or not exist yet
+        #[arg(long)]
+        to: PathBuf,
+        /// Copy and verify, but leave the original data directory in place
+        #[arg(long)]
+        keep_source: bool,
+    },
+    /// Measure set/get/delete latency of the active keychain backend with throwaway entries
+    BenchKeychain {
+        /// Set/get/delete round trips to time
+        #[arg(long, default_value_t = 20)]
+        iterations: usize,
+    },
     /// Export the vault to an encrypted bundle
     Export {
         /// Output path for the bundle (omit to print to stdout)
         #[arg(long)]
         out: Option<PathBuf>,
-        /// Passphrase (supports prompt[:LABEL], '-', '@file', or 'env:NAME')
+        /// Passphrase (supports prompt[:LABEL], '-', '@file', 'fd:N', or 'env:NAME')
+        #[arg(
+            long,
+            required_unless_present_any = ["recipient", "gpg_recipient"],
+            conflicts_with_all = ["recipient", "gpg_recipient"]
+        )]
+        passphrase: Option<String>,
+        /// Encrypt to an age recipient (age1...); repeatable
+        #[arg(long = "recipient", conflicts_with = "gpg_recipient")]
+        recipient: Vec<String>,
+        /// Encrypt to a gpg recipient (key id, fingerprint, or email) via the local gpg; repeatable
+        #[arg(long = "gpg-recipient")]
+        gpg_recipient: Vec<String>,
+        /// Export a single project (name or id) instead of the whole vault
         #[arg(long)]
-        passphrase: String,
+        project: Option<String>,
+        /// Also export keys of non-exportable projects; recorded in the audit log
+        #[arg(long)]
+        include_non_exportable: bool,
     },
     /// Import an encrypted bundle into the vault
     Import {
         /// Bundle JSON string, '-', '@file', or 'env:NAME'
         #[arg(long)]
         bundle: String,
-        /// Passphrase (supports prompt[:LABEL], '-', '@file', or 'env:NAME')
+        /// Passphrase for passphrase-encrypted bundles (supports prompt[:LABEL], '-', '@file', 'fd:N', or 'env:NAME')
+        #[arg(long)]
+        passphrase: Option<String>,
+        /// age identity file for recipient-encrypted bundles ('@file', '-', or 'env:NAME')
         #[arg(long)]
-        passphrase: String,
+        identity: Option<String>,
         /// Replace existing vault contents before import
         #[arg(long)]
         replace: bool,
+        /// List bundle contents from its manifest without importing
+        #[arg(long, conflicts_with_all = ["replace", "merge"])]
+        list: bool,
+        /// Merge into the existing vault (projects by name, keys by kid/name, tokens by name)
+        #[arg(long, conflicts_with = "replace")]
+        merge: bool,
+        /// How to resolve key/token conflicts during --merge
+        #[arg(long, value_enum, default_value = "skip", requires = "merge")]
+        on_conflict: OnConflict,
+        /// Report what --merge would change without writing anything
+        #[arg(long, requires = "merge")]
+        dry_run: bool,
     },
 }
 
@@ -48,11 +125,16 @@ pub enum ProjectCmd {
         /// Optional tags; repeatable
         #[arg(long)]
         tag: Vec<String>,
+        /// Keep the project's key material in this vault (see set-exportable)
+        #[arg(long)]
+        non_exportable: bool,
     },
     List {
         /// Include tags/description in text output.
         #[arg(long)]
         details: bool,
+        #[command(flatten)]
+        listing: ListFormatArgs,
     },
     Delete {
         /// Project id (positional). Use --name to delete by project name.
@@ -61,6 +143,19 @@ pub enum ProjectCmd {
         #[arg(long)]
         name: Option<String>,
     },
+    /// Show a project's settings, keys, tokens and JWKS in one view
+    Show {
+        /// Project name or id.
+        project: String,
+    },
+    /// Copy a project's settings, keys and JWKS into a new project (tokens are not copied)
+    Clone {
+        /// Project name or id to copy.
+        source: String,
+        /// Name of the new project.
+        #[arg(long = "as", value_name = "NAME")]
+        as_name: String,
+    },
     SetDefaultKey {
         /// Project name or id.
         #[arg(long)]
@@ -73,6 +168,50 @@ pub enum ProjectCmd {
         #[arg(long)]
         clear: bool,
     },
+    /// Allow or forbid the project's key material leaving the vault: when false, exports skip
+    /// its keys and `key generate` refuses --reveal/--out without --include-non-exportable
+    SetExportable {
+        /// Project name or id.
+        #[arg(long)]
+        project: String,
+        #[arg(long, action = clap::ArgAction::Set, value_name = "BOOL")]
+        exportable: bool,
+    },
+    /// Run a WASM module on the project's tokens: its `jwt_transform` export rewrites claims on
+    /// `encode --project`, its `jwt_validate` export adds checks to `verify --project`
+    SetWasmModule {
+        /// Project name or id.
+        #[arg(long)]
+        project: String,
+        /// Module file (.wasm, or .wat text); needs a build with the `wasm` feature
+        #[arg(long, value_name = "FILE", required_unless_present = "clear")]
+        path: Option<PathBuf>,
+        /// Stop running the project's module.
+        #[arg(long, conflicts_with = "path")]
+        clear: bool,
+    },
+    /// Require header parameters, claims or issuers on tokens of one algorithm family; enforced
+    /// by `verify --project`
+    SetPolicy {
+        /// Project name or id.
+        #[arg(long)]
+        project: String,
+        /// Algorithm family the rules apply to
+        #[arg(long, value_enum)]
+        family: AlgFamily,
+        /// Header parameter tokens must carry (kid, x5t, ...); repeatable
+        #[arg(long, value_name = "NAME")]
+        require_header: Vec<String>,
+        /// Claim tokens must carry; repeatable
+        #[arg(long, value_name = "NAME")]
+        require_claim: Vec<String>,
+        /// Accepted `iss` value; repeatable. Without it any issuer passes
+        #[arg(long, value_name = "ISS")]
+        allow_issuer: Vec<String>,
+        /// Remove the family's rules.
+        #[arg(long, conflicts_with_all = ["require_header", "require_claim", "allow_issuer"])]
+        clear: bool,
+    },
 }
 
 #[derive(Subcommand, Debug)]
@@ -83,7 +222,8 @@ pub enum KeyCmd {
         project: String,
         #[arg(long)]
         name: Option<String>,
-        /// Kind is stored for UX; should match algorithm family (hmac|rsa|ec|eddsa|jwks)
+        /// Algorithm family the material must parse as (hmac|rsa|ec|eddsa), or platform for a
+        /// cng:<NAME> / secure-enclave:<LABEL> reference to an existing keystore key
         #[arg(long, default_value = "hmac")]
         kind: String,
         /// Optional key id hint (kid) for selection
@@ -95,9 +235,47 @@ pub enum KeyCmd {
         /// Optional tags; repeatable
         #[arg(long)]
         tag: Vec<String>,
-        /// Key material: literal string, prompt[:LABEL], '-', '@file', or 'env:NAME'
+        /// Key material: literal string, prompt[:LABEL], '-', '@file', 'fd:N', or 'env:NAME'
+        #[arg(
+            long,
+            required_unless_present = "from_env",
+            conflicts_with = "from_env"
+        )]
+        secret: Option<String>,
+        /// Read the material from this environment variable; the key is named after it unless
+        /// --name is given
+        #[arg(long, value_name = "VAR")]
+        from_env: Option<String>,
+        /// Accept a public key for rsa/ec/eddsa (verification only; it cannot sign)
+        #[arg(long)]
+        allow_public: bool,
+        /// What the key may be used for (default: verify for a public key, otherwise both)
+        #[arg(long, value_parser = ["sign", "verify", "both"])]
+        usage: Option<String>,
+        /// Reject HMAC secrets shorter than this many bytes
+        #[arg(long)]
+        min_hmac_bytes: Option<usize>,
+    },
+    /// Store one key per environment variable (or dotenv entry) whose name matches --pattern
+    Import {
+        /// Project name or id.
+        #[arg(long)]
+        project: String,
+        /// Read variables from this dotenv file
+        #[arg(long, required_unless_present = "env", conflicts_with = "env")]
+        dotenv: Option<PathBuf>,
+        /// Read variables from the process environment
+        #[arg(long)]
+        env: bool,
+        /// Variable names to import; '*' matches any run of characters, '?' one character
+        #[arg(long, default_value = "*")]
+        pattern: String,
+        /// Algorithm family the material must parse as (hmac|rsa|ec|eddsa)
+        #[arg(long, default_value = "hmac")]
+        kind: String,
+        /// Reject HMAC secrets shorter than this many bytes
         #[arg(long)]
-        secret: String,
+        min_hmac_bytes: Option<usize>,
     },
     /// Generate key material and store it in the vault
     Generate {
@@ -124,15 +302,69 @@ pub enum KeyCmd {
         /// RSA key size (2048, 3072, 4096)
         #[arg(long, value_name = "BITS")]
         rsa_bits: Option<usize>,
-        /// EC curve (P-256 or P-384)
+        /// EC curve (P-256, P-384, or secp256k1)
         #[arg(long, value_name = "CURVE")]
         ec_curve: Option<String>,
+        /// EdDSA curve (Ed25519 or Ed448)
+        #[arg(long, value_name = "CURVE")]
+        ed_curve: Option<String>,
+        /// RSA key use: sig (default) or enc (RSA-OAEP)
+        #[arg(long, value_name = "USE")]
+        key_use: Option<String>,
         /// Include generated material in output
         #[arg(long)]
         reveal: bool,
         /// Write generated material to a file
         #[arg(long)]
         out: Option<PathBuf>,
+        /// Allow --reveal/--out in a non-exportable project; recorded in the audit log
+        #[arg(long)]
+        include_non_exportable: bool,
+        /// What the key may be used for
+        #[arg(long, default_value = "both", value_parser = ["sign", "verify", "both"])]
+        usage: String,
+    },
+    /// Create a non-exportable key in the platform keystore (Windows CNG or the macOS Secure
+    /// Enclave) and store a sign-only `platform` key that refers to it
+    CreatePlatform {
+        /// Project name or id.
+        #[arg(long)]
+        project: String,
+        /// Vault key name (default: the keystore name)
+        #[arg(long)]
+        name: Option<String>,
+        /// Keystore key to create: cng:<NAME> or secure-enclave:<LABEL>
+        #[arg(long = "ref", value_name = "REF")]
+        key_ref: String,
+        /// Algorithm the key signs: es256 creates a P-256 key, rs256/ps256 an RSA-2048 CNG key
+        #[arg(long, value_enum, default_value = "es256")]
+        alg: JwtAlg,
+        /// Optional key id hint (kid) for selection
+        #[arg(long)]
+        kid: Option<String>,
+        /// Optional description/notes
+        #[arg(long)]
+        description: Option<String>,
+        /// Optional tags; repeatable
+        #[arg(long)]
+        tag: Vec<String>,
+    },
+    /// Restrict a key to signing (encode, issuer) or verification (verify, decode), or lift the
+    /// restriction with `both`
+    SetUsage {
+        /// Project name or id.
+        #[arg(long)]
+        project: String,
+        #[arg(
+            long,
+            required_unless_present = "key_name",
+            conflicts_with = "key_name"
+        )]
+        key_id: Option<String>,
+        #[arg(long)]
+        key_name: Option<String>,
+        #[arg(value_parser = ["sign", "verify", "both"])]
+        usage: String,
     },
     List {
         /// Project name or id.
@@ -141,16 +373,24 @@ pub enum KeyCmd {
         /// Include tags/description in text output.
         #[arg(long)]
         details: bool,
+        #[command(flatten)]
+        listing: ListFormatArgs,
     },
     Delete {
         /// Key id (positional). Use --project + --name to delete by name.
         id: Option<String>,
-        /// Project name or id (required with --name).
+        /// Project name or id (required with --name or --all).
         #[arg(long)]
         project: Option<String>,
         /// Key name (requires --project).
         #[arg(long)]
         name: Option<String>,
+        /// Delete every key in --project (narrow it with --kind)
+        #[arg(long, requires = "project", conflicts_with_all = ["id", "name"])]
+        all: bool,
+        /// Only delete keys of this kind (with --all)
+        #[arg(long, requires = "all")]
+        kind: Option<String>,
     },
 }
 
@@ -173,6 +413,8 @@ pub enum TokenCmd {
         /// Include created timestamp in text output.
         #[arg(long)]
         details: bool,
+        #[command(flatten)]
+        listing: ListFormatArgs,
     },
     Delete {
         /// Token id (positional). Use --project + --name to delete by name.
@@ -184,4 +426,195 @@ pub enum TokenCmd {
         #[arg(long)]
         name: Option<String>,
     },
+    /// Add a token to its project's revocation list, checked by `verify --check-revocation`
+    Revoke {
+        /// Stored token id (revokes its jti and hash), or a jti to revoke (requires --project).
+        target: String,
+        /// Project name or id.
+        #[arg(long)]
+        project: Option<String>,
+    },
+    /// List a project's revoked jtis and token hashes
+    Revoked {
+        /// Project name or id.
+        #[arg(long)]
+        project: String,
+        #[command(flatten)]
+        listing: ListFormatArgs,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum JwksCmd {
+    Add {
+        /// Project name or id.
+        #[arg(long)]
+        project: String,
+        #[arg(long)]
+        name: Option<String>,
+        /// URL to re-fetch on refresh (defaults to the JWKS argument when that is a URL)
+        #[arg(long)]
+        source_url: Option<String>,
+        /// JWKS document: inline JSON, '@file', 'env:NAME', or an http(s) URL to snapshot
+        jwks: String,
+    },
+    /// Re-fetch stored JWKS documents from their source URLs
+    Refresh {
+        /// Project name or id.
+        #[arg(long)]
+        project: String,
+        /// Only refresh the JWKS with this name.
+        #[arg(long)]
+        name: Option<String>,
+    },
+    List {
+        /// Project name or id.
+        #[arg(long)]
+        project: String,
+        #[command(flatten)]
+        listing: ListFormatArgs,
+    },
+    /// Print a stored JWKS document
+    Show {
+        /// JWKS id (positional). Use --project + --name to select by name.
+        id: Option<String>,
+        /// Project name or id (required with --name).
+        #[arg(long)]
+        project: Option<String>,
+        /// JWKS name (requires --project).
+        #[arg(long)]
+        name: Option<String>,
+    },
+    Delete {
+        /// JWKS id (positional). Use --project + --name to delete by name.
+        id: Option<String>,
+        /// Project name or id (required with --name).
+        #[arg(long)]
+        project: Option<String>,
+        /// JWKS name (requires --project).
+        #[arg(long)]
+        name: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum VaultWebhookCmd {
+    /// Register a URL; without --secret a signing secret is generated and shown once
+    Add {
+        /// Project name or id.
+        #[arg(long)]
+        project: String,
+        /// http(s) URL that receives the POSTs.
+        #[arg(long)]
+        url: String,
+        /// Only send this event (repeatable; default: all events)
+        #[arg(
+            long = "event",
+            value_parser = ["key.added", "key.rotated", "key.deleted", "token.added"]
+        )]
+        events: Vec<String>,
+        /// HMAC-SHA256 signing secret (supports '-', '@file', 'fd:N', or 'env:NAME')
+        #[arg(long)]
+        secret: Option<String>,
+    },
+    List {
+        /// Project name or id.
+        #[arg(long)]
+        project: String,
+        #[command(flatten)]
+        listing: ListFormatArgs,
+    },
+    Delete {
+        /// Webhook id (from `vault webhook list`).
+        id: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum BackupCmd {
+    /// List backups, newest first
+    List,
+    /// Take a backup now
+    Create {
+        /// Label stored with the backup.
+        #[arg(long, default_value = "manual")]
+        reason: String,
+    },
+    /// Replace the vault with a backup (the current state is backed up first)
+    Restore {
+        /// Backup id from `vault backup list`.
+        id: String,
+    },
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlgFamily {
+    #[value(name = "hs")]
+    Hs,
+    #[value(name = "rs")]
+    Rs,
+    #[value(name = "ps")]
+    Ps,
+    #[value(name = "es")]
+    Es,
+    #[value(name = "eddsa")]
+    EdDsa,
+}
+
+impl AlgFamily {
+    /// The key project policies are stored under.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AlgFamily::Hs => "HS",
+            AlgFamily::Rs => "RS",
+            AlgFamily::Ps => "PS",
+            AlgFamily::Es => "ES",
+            AlgFamily::EdDsa => "EdDSA",
+        }
+    }
+
+    pub fn of(alg: jsonwebtoken::Algorithm) -> Self {
+        use jsonwebtoken::Algorithm;
+        match alg {
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => AlgFamily::Hs,
+            Algorithm::RS256 | Algorithm::RS384 | Algorithm::RS512 => AlgFamily::Rs,
+            Algorithm::PS256 | Algorithm::PS384 | Algorithm::PS512 => AlgFamily::Ps,
+            Algorithm::ES256 | Algorithm::ES384 => AlgFamily::Es,
+            Algorithm::EdDSA => AlgFamily::EdDsa,
+        }
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnConflict {
+    #[value(name = "skip")]
+    Skip,
+    #[value(name = "overwrite")]
+    Overwrite,
+    #[value(name = "rename")]
+    Rename,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListFormat {
+    /// Aligned columns with a header row
+    #[value(name = "table")]
+    Table,
+    /// Comma-separated values with a header row
+    #[value(name = "csv")]
+    Csv,
+    /// One JSON object per line
+    #[value(name = "json-lines")]
+    JsonLines,
+}
+
+#[derive(Args, Debug, Clone, Default)]
+pub struct ListFormatArgs {
+    /// Render the listing as a table, CSV, or JSON lines (text output only)
+    #[arg(long, value_enum)]
+    pub format: Option<ListFormat>,
+
+    /// Columns to include, comma-separated (default depends on the listing)
+    #[arg(long, value_delimiter = ',', requires = "format")]
+    pub columns: Vec<String>,
 }
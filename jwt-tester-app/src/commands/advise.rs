@@ -0,0 +1,138 @@
+use crate::advisor::{self, Finding, Priority, Sample};
+use crate::cli::AdviseArgs;
+use crate::commands::vault::resolve_project_selector;
+use crate::error::{AppError, AppResult};
+use crate::io_utils::read_token_input;
+use crate::output::{emit_err, emit_ok, paint, CommandOutput, OutputConfig, Tone};
+use crate::vault::{Vault, VaultConfig};
+use jsonwebtoken::Algorithm;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+
+/// Reads the sample token and the project's keys, then reports findings, size/CPU estimates for
+/// the current and target algorithms, and the migration checklist. Nothing in the vault changes.
+pub fn run(
+    no_persist: bool,
+    data_dir: Option<PathBuf>,
+    args: AdviseArgs,
+    cfg: OutputConfig,
+) -> i32 {
+    let result = (|| -> AppResult<CommandOutput> {
+        let sample = Sample::parse(&read_token_input(&args.token)?)?;
+        let vault = Vault::open(VaultConfig {
+            no_persist,
+            data_dir,
+        })
+        .map_err(|e| AppError::invalid_key(e.to_string()))?;
+        let project = resolve_project_selector(&vault, &args.project)?;
+        let keys = vault
+            .list_keys(Some(&project.id))
+            .map_err(|e| AppError::invalid_key(e.to_string()))?;
+
+        let target = match args.target_alg.map(Algorithm::from) {
+            Some(target) if Some(target) == sample.alg => {
+                return Err(AppError::invalid_claims(format!(
+                    "the sample token already uses {target:?}; pick another --target-alg"
+                )));
+            }
+            Some(target) => Some(target),
+            None => advisor::default_target(sample.alg),
+        };
+        let findings = advisor::findings(&sample, &project, &keys, target);
+        let checklist = target
+            .map(|target| advisor::checklist(&sample, &project, target))
+            .unwrap_or_default();
+        let estimates = estimates(&sample, target, args.iterations)?;
+
+        let data = json!({
+            "project": project.name,
+            "current_alg": advisor::alg_name(sample.alg),
+            "target_alg": target.map(|alg| advisor::alg_name(Some(alg))),
+            "token_bytes": sample.token_bytes,
+            "lifetime_secs": sample.lifetime_secs(),
+            "findings": findings,
+            "estimates": estimates,
+            "checklist": checklist,
+        });
+        let text = format_report(cfg, &data, &findings, &checklist);
+        Ok(CommandOutput::new(data, text))
+    })();
+
+    match result.and_then(|out| emit_ok(cfg, out)) {
+        Ok(()) => 0,
+        Err(err) => {
+            let code = err.exit_code();
+            emit_err(cfg, err);
+            code
+        }
+    }
+}
+
+/// One estimate per algorithm (current, then target), each with a fresh throwaway key.
+#[cfg(feature = "keygen")]
+fn estimates(sample: &Sample, target: Option<Algorithm>, iterations: usize) -> AppResult<Value> {
+    let mut estimates = Vec::new();
+    for alg in [sample.alg, target].into_iter().flatten() {
+        estimates.push(advisor::estimate(sample, alg, iterations)?);
+    }
+    Ok(json!(estimates))
+}
+
+#[cfg(not(feature = "keygen"))]
+fn estimates(_: &Sample, _: Option<Algorithm>, _: usize) -> AppResult<Value> {
+    Ok(json!([]))
+}
+
+fn format_report(
+    cfg: OutputConfig,
+    data: &Value,
+    findings: &[Finding],
+    checklist: &[String],
+) -> String {
+    let current = data["current_alg"].as_str().unwrap_or_default();
+    let mut lines = vec![match data["target_alg"].as_str() {
+        Some(target) => format!(
+            "project {}: {current} -> {target}",
+            data["project"].as_str().unwrap_or_default()
+        ),
+        None => format!(
+            "project {}: keep {current}",
+            data["project"].as_str().unwrap_or_default()
+        ),
+    }];
+    for finding in findings {
+        let tone = match finding.priority {
+            Priority::High => Tone::Error,
+            Priority::Medium | Priority::Low => Tone::Warning,
+        };
+        lines.push(format!(
+            "{} {}: {}",
+            paint(cfg, tone, finding.priority.label()),
+            finding.id,
+            finding.message
+        ));
+    }
+    if let Some(estimates) = data["estimates"].as_array().filter(|e| !e.is_empty()) {
+        lines.push(format!(
+            "{:<8}{:>10}{:>10}{:>14}{:>14}",
+            "alg", "token B", "sig B", "sign p50 ms", "verify p50 ms"
+        ));
+        for estimate in estimates {
+            lines.push(format!(
+                "{:<8}{:>10}{:>10}{:>14.3}{:>14.3}",
+                estimate["alg"].as_str().unwrap_or_default(),
+                estimate["token_bytes"].as_u64().unwrap_or_default(),
+                estimate["signature_bytes"].as_u64().unwrap_or_default(),
+                estimate["sign"]["p50_ms"].as_f64().unwrap_or_default(),
+                estimate["verify"]["p50_ms"].as_f64().unwrap_or_default()
+            ));
+        }
+    }
+    if !checklist.is_empty() {
+        lines.push("checklist:".to_string());
+        for (i, step) in checklist.iter().enumerate() {
+            lines.push(format!("  [ ] {}. {step}", i + 1));
+        }
+    }
+    lines.join("\n")
+}
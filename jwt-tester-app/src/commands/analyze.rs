@@ -0,0 +1,225 @@
+use crate::cli::AnalyzeArgs;
+use crate::error::AppResult;
+use crate::io_utils::read_token_input;
+use crate::jwt_ops;
+use crate::output::{emit_err, emit_ok, CommandOutput, OutputConfig};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeSet;
+
+/// One normalized permission: what kind of grant it is and its value. Client roles are
+/// `<client>/<role>` so roles of different clients never collide.
+type Grant = (&'static str, String);
+
+const KINDS: [(&str, &str); 5] = [
+    ("scope", "scopes"),
+    ("role", "roles"),
+    ("client-role", "client roles"),
+    ("group", "groups"),
+    ("permission", "permissions"),
+];
+
+pub fn run(args: AnalyzeArgs, cfg: OutputConfig) -> i32 {
+    let result = (|| -> AppResult<CommandOutput> {
+        let left = analyze_input(&args.token)?;
+        let Some(other) = &args.diff else {
+            let text = format!("UNVERIFIED\n{}", format_analysis(&left));
+            return Ok(CommandOutput::new(left.to_json(), text));
+        };
+
+        let right = analyze_input(other)?;
+        let added: Vec<&Grant> = right.grants.difference(&left.grants).collect();
+        let removed: Vec<&Grant> = left.grants.difference(&right.grants).collect();
+        let unchanged = left.grants.intersection(&right.grants).count();
+        let data = json!({
+            "left": left.to_json(),
+            "right": right.to_json(),
+            "diff": {
+                "added": added.iter().map(|grant| grant_json(grant)).collect::<Vec<_>>(),
+                "removed": removed.iter().map(|grant| grant_json(grant)).collect::<Vec<_>>(),
+                "unchanged": unchanged,
+            },
+        });
+        let mut lines = vec!["UNVERIFIED".to_string()];
+        lines.extend(
+            removed
+                .iter()
+                .map(|(kind, value)| format!("- {kind} {value}")),
+        );
+        lines.extend(
+            added
+                .iter()
+                .map(|(kind, value)| format!("+ {kind} {value}")),
+        );
+        if added.is_empty() && removed.is_empty() {
+            lines.push(format!("no differences ({unchanged} shared)"));
+        } else {
+            lines.push(format!("{unchanged} unchanged"));
+        }
+        Ok(CommandOutput::new(data, lines.join("\n")))
+    })();
+
+    match result.and_then(|out| emit_ok(cfg, out)) {
+        Ok(()) => 0,
+        Err(err) => {
+            let code = err.exit_code();
+            emit_err(cfg, err);
+            code
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Analysis {
+    grants: BTreeSet<Grant>,
+    sources: Vec<String>,
+}
+
+impl Analysis {
+    fn add(&mut self, source: &str, kind: &'static str, values: Vec<String>) {
+        if values.is_empty() {
+            return;
+        }
+        if !self.sources.iter().any(|known| known == source) {
+            self.sources.push(source.to_string());
+        }
+        self.grants
+            .extend(values.into_iter().map(|value| (kind, value)));
+    }
+
+    fn values(&self, kind: &str) -> Vec<&str> {
+        self.grants
+            .iter()
+            .filter(|(grant_kind, _)| *grant_kind == kind)
+            .map(|(_, value)| value.as_str())
+            .collect()
+    }
+
+    fn to_json(&self) -> Value {
+        let mut client_roles: Map<String, Value> = Map::new();
+        for role in self.values("client-role") {
+            let (client, name) = role.split_once('/').unwrap_or(("", role));
+            let entry = client_roles
+                .entry(client.to_string())
+                .or_insert_with(|| json!([]));
+            if let Some(list) = entry.as_array_mut() {
+                list.push(json!(name));
+            }
+        }
+        json!({
+            "sources": self.sources,
+            "scopes": self.values("scope"),
+            "roles": self.values("role"),
+            "client_roles": client_roles,
+            "groups": self.values("group"),
+            "permissions": self.values("permission"),
+        })
+    }
+}
+
+fn analyze_input(spec: &str) -> AppResult<Analysis> {
+    let token = read_token_input(spec)?;
+    let decoded = jwt_ops::decode_unverified(&token)?;
+    Ok(analyze_claims(&decoded.payload_json))
+}
+
+/// Reads the authorization claim layouts used by common IdPs: OAuth `scope` strings, `scp`
+/// (Azure AD strings, Okta arrays), plain `roles`, Keycloak `realm_access`/`resource_access`,
+/// `groups`/`cognito:groups`, and Auth0 RBAC `permissions`.
+fn analyze_claims(claims: &Value) -> Analysis {
+    let mut analysis = Analysis::default();
+    for (claim, kind) in [
+        ("scope", "scope"),
+        ("scp", "scope"),
+        ("roles", "role"),
+        ("groups", "group"),
+        ("cognito:groups", "group"),
+        ("permissions", "permission"),
+    ] {
+        if let Some(value) = claims.get(claim) {
+            analysis.add(claim, kind, string_list(value));
+        }
+    }
+    if let Some(roles) = claims.pointer("/realm_access/roles") {
+        analysis.add("realm_access.roles", "role", string_list(roles));
+    }
+    if let Some(clients) = claims.get("resource_access").and_then(Value::as_object) {
+        for (client, access) in clients {
+            let roles = access.get("roles").map(string_list).unwrap_or_default();
+            let roles = roles
+                .into_iter()
+                .map(|role| format!("{client}/{role}"))
+                .collect();
+            analysis.add("resource_access", "client-role", roles);
+        }
+    }
+    analysis
+}
+
+/// Space-separated strings and arrays of strings are both common; anything else is ignored.
+fn string_list(value: &Value) -> Vec<String> {
+    match value {
+        Value::String(s) => s.split_whitespace().map(str::to_string).collect(),
+        Value::Array(items) => items
+            .iter()
+            .filter_map(Value::as_str)
+            .map(str::to_string)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn grant_json((kind, value): &Grant) -> Value {
+    json!({ "kind": kind, "value": value })
+}
+
+fn format_analysis(analysis: &Analysis) -> String {
+    if analysis.grants.is_empty() {
+        return "no scope, role, group, or permission claims found".to_string();
+    }
+    let mut lines = Vec::new();
+    for (kind, label) in KINDS {
+        let values = analysis.values(kind);
+        if !values.is_empty() {
+            lines.push(format!("{:<13} {}", format!("{label}:"), values.join(" ")));
+        }
+    }
+    lines.push(format!(
+        "{:<13} {}",
+        "sources:",
+        analysis.sources.join(", ")
+    ));
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{analyze_claims, format_analysis};
+    use serde_json::json;
+
+    #[test]
+    fn analyze_claims_normalizes_idp_layouts() {
+        let claims = json!({
+            "scope": "openid profile",
+            "scp": ["api.read"],
+            "realm_access": { "roles": ["admin", "user"] },
+            "resource_access": { "account": { "roles": ["manage-account"] } },
+            "cognito:groups": ["ops"],
+            "permissions": ["read:users"],
+            "roles": 42,
+        });
+        let analysis = analyze_claims(&claims);
+        let data = analysis.to_json();
+        assert_eq!(data["scopes"], json!(["api.read", "openid", "profile"]));
+        assert_eq!(data["roles"], json!(["admin", "user"]));
+        assert_eq!(data["client_roles"]["account"], json!(["manage-account"]));
+        assert_eq!(data["groups"], json!(["ops"]));
+        assert_eq!(data["permissions"], json!(["read:users"]));
+        assert!(!data["sources"]
+            .as_array()
+            .unwrap()
+            .contains(&json!("roles")));
+
+        let text = format_analysis(&analysis);
+        assert!(text.contains("client roles: account/manage-account"));
+    }
+}
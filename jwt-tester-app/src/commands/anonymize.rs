@@ -0,0 +1,223 @@
+use crate::anonymize::Anonymizer;
+use crate::cli::AnonymizeArgs;
+use crate::error::{AppError, AppResult};
+use crate::io_utils::read_token_input;
+use crate::jwt_ops::{self, JoseKind};
+use crate::output::{emit_err, emit_ok, CommandOutput, OutputConfig};
+use crate::signer::SigningKey;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use jsonwebtoken::Algorithm;
+use rand::RngCore;
+use serde_json::{json, Value};
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+pub fn run(args: AnonymizeArgs, cfg: OutputConfig) -> i32 {
+    match execute(&args).and_then(|out| emit_ok(cfg, out)) {
+        Ok(()) => 0,
+        Err(err) => {
+            let code = err.exit_code();
+            emit_err(cfg, err);
+            code
+        }
+    }
+}
+
+fn execute(args: &AnonymizeArgs) -> AppResult<CommandOutput> {
+    let mut anonymizer =
+        Anonymizer::new(args.seed.as_deref()).with_claims(&args.claims, &args.keep);
+    if args.shift_times {
+        anonymizer = anonymizer.shift_times();
+    }
+    // One throwaway key per alg, shared by every token that uses it.
+    let mut keys: BTreeMap<String, (SigningKey, Value)> = BTreeMap::new();
+    let mut results = Vec::new();
+    let mut lines = Vec::new();
+
+    for spec in &args.tokens {
+        let token = read_token_input(spec)?;
+        let token = token.trim();
+        if jwt_ops::classify_compact(token)? == JoseKind::Jwe {
+            return Err(AppError::invalid_token(
+                "encrypted tokens (JWE) cannot be anonymized; decrypt them first",
+            ));
+        }
+        let decoded = jwt_ops::decode_unverified(token)?;
+        let (header, removed) = anonymizer.header(&decoded.header_json);
+        let (claims, changes) = anonymizer.claims(&decoded.payload_json);
+        let alg_name = header
+            .get("alg")
+            .and_then(Value::as_str)
+            .unwrap_or("none")
+            .to_string();
+
+        let (anonymized, signature) = if alg_name == "none" {
+            (
+                jwt_ops::compact_with_signature(&header, &claims, &[])?,
+                "none",
+            )
+        } else if args.resign {
+            let alg = Algorithm::from_str(&alg_name)
+                .map_err(|_| AppError::invalid_token(format!("unsupported alg '{alg_name}'")))?;
+            let (key, _) = match keys.entry(alg_name.clone()) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => entry.insert(throwaway_key(alg)?),
+            };
+            (
+                jwt_ops::encode_token_with_header_json(alg, &header, &claims, key)?,
+                "resigned",
+            )
+        } else {
+            let length = token
+                .rsplit_once('.')
+                .and_then(|(_, signature)| URL_SAFE_NO_PAD.decode(signature).ok())
+                .map_or(0, |bytes| bytes.len());
+            let mut random = vec![0u8; length];
+            rand::thread_rng().fill_bytes(&mut random);
+            (
+                jwt_ops::compact_with_signature(&header, &claims, &random)?,
+                "random",
+            )
+        };
+
+        lines.push(anonymized.clone());
+        if !changes.replaced.is_empty() {
+            lines.push(format!("  replaced: {}", changes.replaced.join(", ")));
+        }
+        if !changes.shifted.is_empty() {
+            lines.push(format!(
+                "  shifted {} days back: {}",
+                -anonymizer.shift_secs() / 86_400,
+                changes.shifted.join(", ")
+            ));
+        }
+        if !removed.is_empty() {
+            lines.push(format!("  removed from header: {}", removed.join(", ")));
+        }
+        if signature == "random" {
+            lines.push("  signature: random bytes; it will not verify (use --resign)".to_string());
+        }
+        results.push(json!({
+            "token": anonymized,
+            "replaced": changes.replaced,
+            "shifted": changes.shifted,
+            "removed_header_params": removed,
+            "signature": signature,
+        }));
+    }
+
+    let keys: Vec<Value> = keys.into_values().map(|(_, details)| details).collect();
+    for key in &keys {
+        let alg = key["alg"].as_str().unwrap_or_default();
+        match (key["secret"].as_str(), key["public_key"].as_str()) {
+            (Some(secret), _) => lines.push(format!("throwaway {alg} secret: {secret}")),
+            (None, Some(pem)) => {
+                lines.push(format!("throwaway {alg} public key:"));
+                lines.push(pem.trim_end().to_string());
+            }
+            (None, None) => {}
+        }
+    }
+    let mut data = json!({
+        "tokens": results,
+        "time_shift_secs": anonymizer.shift_secs(),
+    });
+    if args.resign {
+        data["keys"] = json!(keys);
+    }
+    Ok(CommandOutput::new(data, lines.join("\n")))
+}
+
+/// A fresh key for `alg` and what a reader needs to verify with it: the secret for HMAC (it is
+/// throwaway), otherwise the public key as PEM and JWK.
+#[cfg(feature = "keygen")]
+fn throwaway_key(alg: Algorithm) -> AppResult<(SigningKey, Value)> {
+    use crate::keygen::{self, KeyGenSpec};
+
+    let generated = keygen::generate_key_pair(KeyGenSpec::for_alg(alg))?;
+    let (encoding, _) = crate::key_resolver::key_pair_from_material(alg, &generated.material)?;
+    let details = match generated.public {
+        Some(public) => json!({
+            "alg": format!("{alg:?}"),
+            "public_key": public.pem,
+            "jwk": public.jwk,
+        }),
+        None => json!({ "alg": format!("{alg:?}"), "secret": generated.material }),
+    };
+    Ok((SigningKey::Local(encoding), details))
+}
+
+#[cfg(not(feature = "keygen"))]
+fn throwaway_key(_alg: Algorithm) -> AppResult<(SigningKey, Value)> {
+    Err(AppError::internal(
+        "anonymize --resign requires a build with the `keygen` feature",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::execute;
+    use crate::cli::AnonymizeArgs;
+    use crate::jwt_ops;
+    use jsonwebtoken::{Algorithm, EncodingKey, Header};
+    use serde_json::json;
+
+    fn args(tokens: Vec<String>, resign: bool) -> AnonymizeArgs {
+        AnonymizeArgs {
+            seed: Some("report-1".to_string()),
+            claims: Vec::new(),
+            keep: Vec::new(),
+            shift_times: true,
+            resign,
+            tokens,
+        }
+    }
+
+    fn token(claims: serde_json::Value) -> String {
+        let mut header = Header::new(Algorithm::HS256);
+        header.kid = Some("prod-2024".to_string());
+        jsonwebtoken::encode(&header, &claims, &EncodingKey::from_secret(b"secret")).expect("sign")
+    }
+
+    #[test]
+    fn tokens_share_fakes_and_keep_their_signature_length() {
+        let access = token(json!({ "sub": "alice", "iat": 1_700_000_000, "exp": 1_700_000_600 }));
+        let id = token(json!({ "sub": "alice", "email": "alice@example.com" }));
+        let out = execute(&args(vec![access.clone(), id], false)).expect("anonymize");
+        let tokens = out.data["tokens"].as_array().expect("tokens");
+
+        let first = tokens[0]["token"].as_str().expect("token");
+        let second = tokens[1]["token"].as_str().expect("token");
+        let a = jwt_ops::decode_unverified(first).expect("decode");
+        let b = jwt_ops::decode_unverified(second).expect("decode");
+        assert_eq!(a.payload_json["sub"], b.payload_json["sub"]);
+        assert_ne!(a.payload_json["sub"], "alice");
+        assert_eq!(a.header_json["kid"], b.header_json["kid"]);
+        assert_ne!(a.header_json["kid"], "prod-2024");
+        assert_eq!(
+            a.payload_json["exp"].as_i64().unwrap() - a.payload_json["iat"].as_i64().unwrap(),
+            600
+        );
+        assert_eq!(tokens[0]["signature"], "random");
+        assert_eq!(first.len(), access.len());
+        assert!(out.data.get("keys").is_none());
+    }
+
+    #[cfg(feature = "keygen")]
+    #[test]
+    fn resigned_tokens_verify_with_the_printed_key() {
+        use crate::jwt_ops::VerifyKey;
+
+        let out = execute(&args(vec![token(json!({ "sub": "alice" }))], true)).expect("anonymize");
+        let token = out.data["tokens"][0]["token"].as_str().expect("token");
+        let secret = out.data["keys"][0]["secret"].as_str().expect("secret");
+        assert_eq!(out.data["keys"][0]["alg"], "HS256");
+        let key = VerifyKey::from_secret(secret.as_bytes());
+        assert!(jwt_ops::signature_valid(token, &key).expect("check"));
+        assert!(
+            !jwt_ops::signature_valid(token, &VerifyKey::from_secret(b"secret")).expect("check")
+        );
+    }
+}
@@ -1,10 +1,15 @@
+use crate::claim_lint::claim_warnings;
+use crate::claims::group_namespaced_claims;
 use crate::cli::{DecodeArgs, VerifyCommonArgs};
-use crate::commands::verify::verify_token_with_args;
-use crate::date_utils::{extract_dates, parse_date_mode};
+use crate::commands::vault_access::VaultAccess;
+use crate::commands::verify::verify_token_in;
+use crate::date_utils::{extract_dates, normalize_timestamps, parse_date_mode, timestamp_warning};
 use crate::error::{AppError, AppResult};
-use crate::io_utils::read_input;
+use crate::io_utils::{extract_token, read_token_at_path, read_token_input};
 use crate::jwt_ops;
-use crate::output::{emit_err, emit_ok, CommandOutput, OutputConfig};
+use crate::output::{emit_err, emit_ok, paint, CommandOutput, OutputConfig, Tone};
+use crate::qr;
+use crate::sandbox;
 use serde_json::json;
 use std::path::PathBuf;
 
@@ -14,53 +19,17 @@ pub fn run(
     args: DecodeArgs,
     cfg: OutputConfig,
 ) -> i32 {
-    let result = (|| -> AppResult<CommandOutput> {
-        let token = read_input(&args.token)?;
-        let decoded = jwt_ops::decode_unverified(&token)?;
-        let date_mode = parse_date_mode(args.date)?;
-        let dates = extract_dates(&decoded.payload_json, date_mode)?;
-        let mut data = json!({
-            "header": decoded.header_json,
-            "payload": decoded.payload_json,
-            "dates": dates.json,
-        });
-
-        let mut text = String::new();
-        let verify_requested = has_verify_request(&args.verify);
-        if verify_requested {
-            let verify_outcome =
-                verify_token_with_args(no_persist, data_dir.clone(), &args.verify, &token)?;
-            data["verified"] = json!(true);
-            data["verification"] = verify_outcome.data.clone();
-            text.push_str("VERIFIED\n");
-        } else {
-            text.push_str("UNVERIFIED\n");
-        }
-        text.push_str("Header:\n");
-        text.push_str(&serde_json::to_string_pretty(&data["header"]).unwrap_or_default());
-        text.push_str("\nPayload:\n");
-        text.push_str(&serde_json::to_string_pretty(&data["payload"]).unwrap_or_default());
-        if !dates.lines.is_empty() {
-            text.push_str("\nDates:\n");
-            text.push_str(&dates.lines.join("\n"));
-        }
-
-        if let Some(path) = &args.out {
-            let body = json!({ "ok": true, "data": data });
-            let json_text = serde_json::to_string_pretty(&body)
-                .map_err(|e| AppError::internal(format!("serialize output: {e}")))?;
-            std::fs::write(path, json_text.as_bytes())
-                .map_err(|e| AppError::internal(format!("failed to write {path:?}: {e}")))?;
-        }
-
-        Ok(CommandOutput::new(data, text))
-    })();
-
-    match result {
-        Ok(out) => {
-            emit_ok(cfg, out);
-            0
-        }
+    let access = VaultAccess::Open {
+        no_persist,
+        data_dir: &data_dir,
+    };
+    let result = if args.sandbox {
+        execute_sandboxed(access, &args, cfg)
+    } else {
+        execute(access, &args, cfg)
+    };
+    match result.and_then(|out| emit_ok(cfg, out)) {
+        Ok(()) => 0,
         Err(err) => {
             let code = err.exit_code();
             emit_err(cfg, err);
@@ -69,10 +38,132 @@ pub fn run(
     }
 }
 
+/// Decodes (and, with key options, verifies) without printing; shared with `--rpc`.
+pub(crate) fn execute(
+    access: VaultAccess<'_>,
+    args: &DecodeArgs,
+    cfg: OutputConfig,
+) -> AppResult<CommandOutput> {
+    let token = read_token(args)?;
+    decode_token(access, args, &token, cfg)
+}
+
+/// `--sandbox`: the token is read first, then decoded with filesystem, network and process access
+/// gone. Verifying needs key material from files, the vault or the network, so it is refused.
+fn execute_sandboxed(
+    access: VaultAccess<'_>,
+    args: &DecodeArgs,
+    cfg: OutputConfig,
+) -> AppResult<CommandOutput> {
+    if has_verify_request(&args.verify) {
+        return Err(AppError::internal(
+            "--sandbox only decodes; drop the key and claim check options to use it",
+        ));
+    }
+    sandbox::check_output(cfg)?;
+    let token = read_token(args)?;
+    sandbox::enter()?;
+    decode_token(access, args, &token, cfg)
+}
+
+fn read_token(args: &DecodeArgs) -> AppResult<String> {
+    match (&args.from_qr, &args.token) {
+        (Some(image), _) => extract_token(&qr::scan_image(image)?),
+        (None, Some(spec)) => match &args.token_path {
+            Some(path) => read_token_at_path(spec, path),
+            None => read_token_input(spec),
+        },
+        (None, None) => Err(AppError::invalid_token("a token or --from-qr is required")),
+    }
+}
+
+fn decode_token(
+    access: VaultAccess<'_>,
+    args: &DecodeArgs,
+    token: &str,
+    cfg: OutputConfig,
+) -> AppResult<CommandOutput> {
+    let decoded = jwt_ops::decode_unverified(token)?;
+    let date_mode = parse_date_mode(args.date.clone())?;
+    // The payload is shown as sent; only the date read-out uses normalized seconds.
+    let mut seconds = decoded.payload_json.clone();
+    let normalized = normalize_timestamps(&mut seconds, args.verify.timestamps);
+    let dates = extract_dates(&seconds, date_mode)?;
+    let mut data = json!({
+        "header": decoded.header_json,
+        "payload": decoded.payload_json,
+        "dates": dates.json,
+    });
+    let namespaced = group_namespaced_claims(&decoded.payload_json);
+    if !namespaced.is_empty() {
+        data["namespaced_claims"] = serde_json::Value::Object(namespaced);
+    }
+    if decoded.compressed {
+        data["compressed"] = json!(true);
+    }
+    let warning = timestamp_warning(&normalized);
+    let claim_issues = claim_warnings(&decoded.payload_json);
+    data["warnings"] = json!(claim_issues);
+    if !normalized.is_empty() {
+        data["normalized_timestamps"] = serde_json::Value::Object(normalized);
+    }
+
+    let mut text = String::new();
+    let verify_requested = has_verify_request(&args.verify);
+    if verify_requested {
+        let verify_outcome = verify_token_in(access, &args.verify, token)?;
+        data["verified"] = json!(true);
+        data["verification"] = verify_outcome.data.clone();
+        text.push_str(&paint(cfg, Tone::Valid, "VERIFIED"));
+    } else {
+        text.push_str(&paint(cfg, Tone::Warning, "UNVERIFIED"));
+    }
+    text.push('\n');
+    if decoded.compressed {
+        text.push_str(&paint(
+            cfg,
+            Tone::Warning,
+            "compressed payload (zip DEF), shown inflated",
+        ));
+        text.push('\n');
+    }
+    if let Some(warning) = &warning {
+        text.push_str(&paint(cfg, Tone::Warning, warning));
+        text.push('\n');
+    }
+    for claim_issue in &claim_issues {
+        let line = format!("warning: {}", claim_issue.message);
+        text.push_str(&paint(cfg, Tone::Warning, &line));
+        text.push('\n');
+    }
+    text.push_str("Header:\n");
+    text.push_str(&serde_json::to_string_pretty(&data["header"]).unwrap_or_default());
+    text.push_str("\nPayload:\n");
+    text.push_str(&serde_json::to_string_pretty(&data["payload"]).unwrap_or_default());
+    if let Some(namespaced) = data.get("namespaced_claims") {
+        text.push_str("\nNamespaced claims:\n");
+        text.push_str(&serde_json::to_string_pretty(namespaced).unwrap_or_default());
+    }
+    if !dates.lines.is_empty() {
+        text.push_str("\nDates:\n");
+        text.push_str(&dates.styled_lines(cfg).join("\n"));
+    }
+
+    if let Some(path) = &args.out {
+        let body = json!({ "ok": true, "data": data });
+        let json_text = serde_json::to_string_pretty(&body)
+            .map_err(|e| AppError::internal(format!("serialize output: {e}")))?;
+        std::fs::write(path, json_text.as_bytes())
+            .map_err(|e| AppError::internal(format!("failed to write {path:?}: {e}")))?;
+    }
+
+    Ok(CommandOutput::new(data, text))
+}
+
 fn has_verify_request(args: &VerifyCommonArgs) -> bool {
     args.secret.is_some()
         || args.key.is_some()
-        || args.jwks.is_some()
+        || !args.jwks.is_empty()
         || args.project.is_some()
         || args.alg.is_some()
         || args.try_all_keys
@@ -82,13 +173,16 @@ fn has_verify_request(args: &VerifyCommonArgs) -> bool {
         || args.sub.is_some()
         || !args.aud.is_empty()
         || !args.require.is_empty()
+        || !args.require_acr.is_empty()
+        || args.max_auth_age.is_some()
+        || args.check_revocation
         || args.explain
 }
 
 #[cfg(test)]
 mod tests {
     use super::has_verify_request;
-    use crate::cli::{JwtAlg, VerifyCommonArgs};
+    use crate::cli::{JwtAlg, TimestampUnit, VerifyCommonArgs};
     use crate::commands::decode::run;
     use crate::jwt_ops;
     use crate::output::{OutputConfig, OutputMode};
@@ -100,20 +194,27 @@ mod tests {
         VerifyCommonArgs {
             secret: None,
             key: None,
-            jwks: None,
+            jwks: Vec::new(),
             key_format: None,
             kid: None,
             allow_single_jwk: false,
             project: None,
             key_id: None,
             key_name: None,
+            key_fingerprint: None,
             try_all_keys: false,
+            auto_refresh: false,
             ignore_exp: false,
             leeway_secs: 30,
+            jwks_negative_ttl: std::time::Duration::from_secs(300),
+            timestamps: TimestampUnit::Auto,
             iss: None,
             sub: None,
             aud: Vec::new(),
             require: Vec::new(),
+            require_acr: Vec::new(),
+            max_auth_age: None,
+            check_revocation: false,
             explain: false,
             alg: None,
         }
@@ -136,7 +237,7 @@ mod tests {
         assert!(has_verify_request(&args));
 
         let mut args = base_args();
-        args.jwks = Some("jwks".to_string());
+        args.jwks = vec!["jwks".to_string()];
         assert!(has_verify_request(&args));
 
         let mut args = base_args();
@@ -198,32 +299,46 @@ mod tests {
             verify: VerifyCommonArgs {
                 secret: Some("secret".to_string()),
                 key: None,
-                jwks: None,
+                jwks: Vec::new(),
                 key_format: None,
                 kid: None,
                 allow_single_jwk: false,
                 project: None,
                 key_id: None,
                 key_name: None,
+                key_fingerprint: None,
                 try_all_keys: false,
+                auto_refresh: false,
                 ignore_exp: true,
                 leeway_secs: 30,
+                jwks_negative_ttl: std::time::Duration::from_secs(300),
+                timestamps: TimestampUnit::Auto,
                 iss: None,
                 sub: None,
                 aud: Vec::new(),
                 require: Vec::new(),
+                require_acr: Vec::new(),
+                max_auth_age: None,
+                check_revocation: false,
                 explain: true,
                 alg: Some(JwtAlg::HS256),
             },
             out: Some(out_path.clone()),
-            token,
+            from_qr: None,
+            token_path: None,
+            sandbox: false,
+            token: Some(token),
         };
 
         let cfg = OutputConfig {
             mode: OutputMode::Json,
             quiet: true,
             no_color: true,
+            theme: crate::cli::Theme::Default,
             verbose: false,
+            template: None,
+            summary: None,
+            sinks: None,
         };
         let code = run(true, None, args, cfg);
         assert_eq!(code, 0);
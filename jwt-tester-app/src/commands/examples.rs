@@ -0,0 +1,626 @@
+use crate::cli::{App, Command, ExamplesArgs, ExamplesCmd};
+use crate::commands::replay::display_command;
+use crate::commands::vault_access::VaultAccess;
+use crate::commands::{decode, encode, vault, verify};
+use crate::error::{AppError, AppResult};
+use crate::output::{emit_err, emit_ok, paint, CommandOutput, OutputConfig, Tone};
+use crate::vault::{Vault, VaultConfig};
+use crate::workspace;
+use clap::FromArgMatches;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+
+/// A built-in walkthrough: commands run in order against one throwaway in-memory vault.
+struct Recipe {
+    name: &'static str,
+    title: &'static str,
+    summary: &'static str,
+    params: &'static [Param],
+    steps: &'static [Step],
+}
+
+struct Param {
+    name: &'static str,
+    default: &'static str,
+    help: &'static str,
+}
+
+/// One command. `{name}` in an argument is replaced by a parameter or an earlier capture.
+struct Step {
+    note: &'static str,
+    args: &'static [&'static str],
+    /// Saves the value at this JSON pointer of the step's data under a name for later steps.
+    capture: Option<(&'static str, &'static str)>,
+    /// The exit code the command line would give; anything else fails the recipe.
+    expect: i32,
+}
+
+const fn step(note: &'static str, args: &'static [&'static str]) -> Step {
+    Step {
+        note,
+        args,
+        capture: None,
+        expect: 0,
+    }
+}
+
+impl Step {
+    const fn capture(self, name: &'static str, pointer: &'static str) -> Step {
+        Step {
+            capture: Some((name, pointer)),
+            ..self
+        }
+    }
+
+    const fn expect(self, code: i32) -> Step {
+        Step {
+            expect: code,
+            ..self
+        }
+    }
+}
+
+const RECIPES: &[Recipe] = &[
+    Recipe {
+        name: "rs256-rotation",
+        title: "RS256 with rotated keys",
+        summary: "Tokens signed before and after a key rotation both verify against the project, \
+                  because the kid header picks the right key.",
+        params: &[Param {
+            name: "bits",
+            default: "2048",
+            help: "RSA key size",
+        }],
+        steps: &[
+            step("A project for the API", &["vault", "project", "add", "api"]),
+            step(
+                "The key in use before the rotation",
+                &[
+                    "vault",
+                    "key",
+                    "generate",
+                    "--project",
+                    "api",
+                    "--name",
+                    "key-1",
+                    "--kind",
+                    "rsa",
+                    "--rsa-bits",
+                    "{bits}",
+                    "--kid",
+                    "key-1",
+                ],
+            ),
+            step(
+                "A token issued before the rotation",
+                &[
+                    "encode",
+                    "--project",
+                    "api",
+                    "--key-name",
+                    "key-1",
+                    "--kid",
+                    "key-1",
+                    "--alg",
+                    "rs256",
+                    "--exp=+1h",
+                    r#"{"sub":"alice"}"#,
+                ],
+            )
+            .capture("old_token", "/token"),
+            step(
+                "Rotate: generate the next key and make it the default",
+                &[
+                    "vault",
+                    "key",
+                    "generate",
+                    "--project",
+                    "api",
+                    "--name",
+                    "key-2",
+                    "--kind",
+                    "rsa",
+                    "--rsa-bits",
+                    "{bits}",
+                    "--kid",
+                    "key-2",
+                ],
+            ),
+            step(
+                "",
+                &[
+                    "vault",
+                    "project",
+                    "set-default-key",
+                    "--project",
+                    "api",
+                    "--key-name",
+                    "key-2",
+                ],
+            ),
+            step(
+                "A token issued after the rotation",
+                &[
+                    "encode",
+                    "--project",
+                    "api",
+                    "--key-name",
+                    "key-2",
+                    "--kid",
+                    "key-2",
+                    "--alg",
+                    "rs256",
+                    "--exp=+1h",
+                    r#"{"sub":"alice"}"#,
+                ],
+            )
+            .capture("new_token", "/token"),
+            step(
+                "Both tokens verify; the kid header selects the key",
+                &["verify", "--project", "api", "{old_token}"],
+            ),
+            step("", &["verify", "--project", "api", "{new_token}"]),
+            step(
+                "Pinning the new key rejects the old token (signature mismatch)",
+                &[
+                    "verify",
+                    "--project",
+                    "api",
+                    "--key-name",
+                    "key-2",
+                    "{old_token}",
+                ],
+            )
+            .expect(11),
+        ],
+    },
+    Recipe {
+        name: "expired-token",
+        title: "Expired token reproduction",
+        summary:
+            "Mints a token that is already expired and shows how verify reports it, and how to \
+                  look past the expiry.",
+        params: &[
+            Param {
+                name: "age",
+                default: "1h",
+                help: "how long ago the token expired",
+            },
+            Param {
+                name: "leeway",
+                default: "30",
+                help: "clock-skew leeway in seconds",
+            },
+        ],
+        steps: &[
+            step(
+                "A project with an HMAC key",
+                &["vault", "project", "add", "demo"],
+            ),
+            step(
+                "",
+                &[
+                    "vault",
+                    "key",
+                    "generate",
+                    "--project",
+                    "demo",
+                    "--name",
+                    "signing",
+                    "--kind",
+                    "hmac",
+                ],
+            ),
+            step(
+                "A token that expired {age} ago",
+                &[
+                    "encode",
+                    "--project",
+                    "demo",
+                    "--alg",
+                    "hs256",
+                    "--exp=-{age}",
+                    r#"{"sub":"alice"}"#,
+                ],
+            )
+            .capture("token", "/token"),
+            step(
+                "Verification fails on exp (exit 12)",
+                &[
+                    "verify",
+                    "--project",
+                    "demo",
+                    "--leeway-secs",
+                    "{leeway}",
+                    "{token}",
+                ],
+            )
+            .expect(12),
+            step(
+                "The signature and the other claims are fine",
+                &["verify", "--project", "demo", "--ignore-exp", "{token}"],
+            ),
+            step(
+                "The claims, without a separate decode (still exit 12)",
+                &[
+                    "verify",
+                    "--project",
+                    "demo",
+                    "--continue-on-claim-failure",
+                    "{token}",
+                ],
+            )
+            .expect(12),
+        ],
+    },
+    Recipe {
+        name: "jwks-kid-mismatch",
+        title: "JWKS kid mismatch",
+        summary:
+            "A verifier that only knows the issuer's JWKS accepts tokens whose kid it lists and \
+                  rejects a token carrying a kid it has never seen.",
+        params: &[Param {
+            name: "stale_kid",
+            default: "idp-2024",
+            help: "kid the mismatched token carries",
+        }],
+        steps: &[
+            step(
+                "The issuer and its EC signing key",
+                &["vault", "project", "add", "idp"],
+            ),
+            step(
+                "",
+                &[
+                    "vault",
+                    "key",
+                    "generate",
+                    "--project",
+                    "idp",
+                    "--name",
+                    "signing",
+                    "--kind",
+                    "ec",
+                    "--kid",
+                    "idp-2025",
+                ],
+            )
+            .capture("jwk", "/public_jwk"),
+            step(
+                "The verifier stores only the issuer's public JWKS",
+                &["vault", "project", "add", "api"],
+            ),
+            step(
+                "",
+                &[
+                    "vault",
+                    "jwks",
+                    "add",
+                    "--project",
+                    "api",
+                    "--name",
+                    "idp",
+                    r#"{"keys":[{jwk}]}"#,
+                ],
+            ),
+            step(
+                "A token with the published kid verifies",
+                &[
+                    "encode",
+                    "--project",
+                    "idp",
+                    "--kid",
+                    "idp-2025",
+                    "--alg",
+                    "es256",
+                    "--exp=+1h",
+                ],
+            )
+            .capture("token", "/token"),
+            step("", &["verify", "--project", "api", "{token}"]),
+            step(
+                "A token with kid {stale_kid} does not: no stored key or JWKS lists it (exit 13)",
+                &[
+                    "encode",
+                    "--project",
+                    "idp",
+                    "--kid",
+                    "{stale_kid}",
+                    "--alg",
+                    "es256",
+                    "--exp=+1h",
+                ],
+            )
+            .capture("stale_token", "/token"),
+            step("", &["verify", "--project", "api", "{stale_token}"]).expect(13),
+        ],
+    },
+];
+
+pub fn run(args: ExamplesArgs, cfg: OutputConfig) -> i32 {
+    let result = match args.cmd {
+        ExamplesCmd::List => Ok((list(), 0)),
+        ExamplesCmd::Run { names, set } => run_recipes(&names, &set, cfg),
+    };
+    match result.and_then(|(out, code)| emit_ok(cfg, out).map(|()| code)) {
+        Ok(code) => code,
+        Err(err) => {
+            let code = err.exit_code();
+            emit_err(cfg, err);
+            code
+        }
+    }
+}
+
+fn list() -> CommandOutput {
+    let mut lines = Vec::new();
+    let recipes: Vec<Value> = RECIPES
+        .iter()
+        .map(|recipe| {
+            lines.push(format!("{}: {}", recipe.name, recipe.title));
+            for param in recipe.params {
+                lines.push(format!(
+                    "  --set {}=VALUE  {} (default {})",
+                    param.name, param.help, param.default
+                ));
+            }
+            json!({
+                "name": recipe.name,
+                "title": recipe.title,
+                "summary": recipe.summary,
+                "params": recipe.params.iter().map(|param| json!({
+                    "name": param.name,
+                    "default": param.default,
+                    "help": param.help,
+                })).collect::<Vec<_>>(),
+                "steps": recipe.steps.len(),
+            })
+        })
+        .collect();
+    lines.push("run one with: jwt-tester examples run <NAME> [--set NAME=VALUE ...]".to_string());
+    CommandOutput::new(json!({ "recipes": recipes }), lines.join("\n"))
+}
+
+/// Runs the named recipes (all of them when none is named). Exits 12 when any step's exit code
+/// differs from the recipe's expectation, like `replay` does on a mismatch.
+fn run_recipes(
+    names: &[String],
+    set: &[String],
+    cfg: OutputConfig,
+) -> AppResult<(CommandOutput, i32)> {
+    let recipes: Vec<&Recipe> = if names.is_empty() {
+        RECIPES.iter().collect()
+    } else {
+        names
+            .iter()
+            .map(|name| find_recipe(name))
+            .collect::<AppResult<_>>()?
+    };
+    let overrides = parse_overrides(set, &recipes)?;
+    let mut clap = workspace::cli_command(None);
+    let mut reports = Vec::new();
+    let mut sections = Vec::new();
+    for recipe in recipes {
+        let (report, text) = run_recipe(recipe, &overrides, &mut clap, cfg)?;
+        reports.push(report);
+        sections.push(text);
+    }
+    let failed = reports.iter().filter(|r| r["passed"] != true).count();
+    let data = json!({
+        "recipes": reports,
+        "passed": reports.len() - failed,
+        "failed": failed,
+    });
+    let code = if failed > 0 { 12 } else { 0 };
+    Ok((CommandOutput::new(data, sections.join("\n\n")), code))
+}
+
+fn find_recipe(name: &str) -> AppResult<&'static Recipe> {
+    RECIPES
+        .iter()
+        .find(|recipe| recipe.name == name)
+        .ok_or_else(|| {
+            let available: Vec<&str> = RECIPES.iter().map(|recipe| recipe.name).collect();
+            AppError::not_found(format!(
+                "unknown example '{name}' (available: {})",
+                available.join(", ")
+            ))
+        })
+}
+
+/// `--set NAME=VALUE` pairs; each name must be a parameter of at least one selected recipe.
+fn parse_overrides(set: &[String], recipes: &[&Recipe]) -> AppResult<BTreeMap<String, String>> {
+    let mut overrides = BTreeMap::new();
+    for raw in set {
+        let (name, value) = raw.split_once('=').ok_or_else(|| {
+            AppError::invalid_key(format!("--set expects NAME=VALUE, got '{raw}'"))
+        })?;
+        let known = recipes
+            .iter()
+            .any(|recipe| recipe.params.iter().any(|param| param.name == name));
+        if !known {
+            return Err(AppError::invalid_key(format!(
+                "no selected example has a parameter '{name}'"
+            )));
+        }
+        overrides.insert(name.to_string(), value.to_string());
+    }
+    Ok(overrides)
+}
+
+fn run_recipe(
+    recipe: &Recipe,
+    overrides: &BTreeMap<String, String>,
+    clap: &mut clap::Command,
+    cfg: OutputConfig,
+) -> AppResult<(Value, String)> {
+    let vault = Vault::open(VaultConfig {
+        no_persist: true,
+        data_dir: None,
+    })
+    .map_err(|e| AppError::invalid_key(e.to_string()))?;
+    let mut vars: BTreeMap<String, String> = recipe
+        .params
+        .iter()
+        .map(|param| {
+            let value = overrides
+                .get(param.name)
+                .map_or(param.default, String::as_str);
+            (param.name.to_string(), value.to_string())
+        })
+        .collect();
+    let params: Map<String, Value> = vars
+        .iter()
+        .map(|(name, value)| (name.clone(), json!(value)))
+        .collect();
+
+    let mut lines = vec![
+        format!("== {}: {} ==", recipe.name, recipe.title),
+        recipe.summary.to_string(),
+    ];
+    let mut steps = Vec::new();
+    let mut passed = true;
+    for step in recipe.steps {
+        let args: Vec<String> = step.args.iter().map(|arg| substitute(arg, &vars)).collect();
+        let command = display_command(&args);
+        if !step.note.is_empty() {
+            lines.push(String::new());
+            lines.push(format!("# {}", substitute(step.note, &vars)));
+        }
+        lines.push(format!("$ {command}"));
+        let mut report = json!({ "command": command, "expected_exit_code": step.expect });
+        let exit_code = match execute_step(&vault, clap, &args, cfg) {
+            Ok(out) => {
+                let code = verify::exit_code(&out);
+                if let Some((name, pointer)) = step.capture {
+                    let value = match out.data.pointer(pointer) {
+                        Some(Value::String(s)) => s.clone(),
+                        Some(value) => value.to_string(),
+                        None => {
+                            return Err(AppError::internal(format!(
+                                "example {}: no {pointer} in the output of `{command}`",
+                                recipe.name
+                            )))
+                        }
+                    };
+                    vars.insert(name.to_string(), value);
+                }
+                if !out.text.is_empty() {
+                    lines.push(out.text);
+                }
+                report["data"] = out.data;
+                code
+            }
+            Err(err) => {
+                lines.push(paint(cfg, Tone::Error, &format!("error: {err}")));
+                report["error"] = json!({ "code": err.code(), "message": err.message });
+                err.exit_code()
+            }
+        };
+        report["exit_code"] = json!(exit_code);
+        report["passed"] = json!(exit_code == step.expect);
+        if exit_code != step.expect {
+            passed = false;
+            lines.push(paint(
+                cfg,
+                Tone::Error,
+                &format!("exit {exit_code}, expected {}", step.expect),
+            ));
+        } else if exit_code != 0 {
+            lines.push(format!("exit {exit_code} (expected)"));
+        }
+        steps.push(report);
+    }
+    let verdict = if passed {
+        paint(cfg, Tone::Valid, &format!("PASS {}", recipe.name))
+    } else {
+        paint(cfg, Tone::Error, &format!("FAIL {}", recipe.name))
+    };
+    lines.push(String::new());
+    lines.push(verdict);
+    let report = json!({
+        "name": recipe.name,
+        "title": recipe.title,
+        "params": params,
+        "passed": passed,
+        "steps": steps,
+    });
+    Ok((report, lines.join("\n")))
+}
+
+fn substitute(template: &str, vars: &BTreeMap<String, String>) -> String {
+    vars.iter()
+        .fold(template.to_string(), |text, (name, value)| {
+            text.replace(&format!("{{{name}}}"), value)
+        })
+}
+
+/// Parses `args` like the command line (without workspace defaults) and runs the command
+/// in-process, so every step shares the recipe's in-memory vault.
+fn execute_step(
+    vault: &Vault,
+    clap: &mut clap::Command,
+    args: &[String],
+    cfg: OutputConfig,
+) -> AppResult<CommandOutput> {
+    let argv = std::iter::once("jwt-tester").chain(args.iter().map(String::as_str));
+    let app = clap
+        .try_get_matches_from_mut(argv)
+        .and_then(|mut matches| App::from_arg_matches_mut(&mut matches))
+        .map_err(|err| AppError::internal(format!("example command does not parse: {err}")))?;
+    let access = VaultAccess::Shared(vault);
+    match app.command {
+        Some(Command::Vault(args)) => vault::execute(vault, args),
+        Some(Command::Encode(args)) => encode::execute(access, &args),
+        Some(Command::Verify(args)) => verify::execute(access, &args, cfg),
+        Some(Command::Decode(args)) => decode::execute(access, &args, cfg),
+        _ => Err(AppError::internal(
+            "examples only run vault, encode, verify and decode commands",
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recipes_reference_only_known_variables() {
+        for recipe in RECIPES {
+            let mut known: Vec<&str> = recipe.params.iter().map(|param| param.name).collect();
+            for step in recipe.steps {
+                for text in step.args.iter().chain(std::iter::once(&step.note)) {
+                    let mut rest = *text;
+                    while let Some(start) = rest.find('{') {
+                        let Some(len) = rest[start..].find('}') else {
+                            break;
+                        };
+                        let name = &rest[start + 1..start + len];
+                        // JSON literals contain braces too; only identifiers are placeholders.
+                        if name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                            assert!(
+                                known.contains(&name),
+                                "{}: '{name}' is used before it is set",
+                                recipe.name
+                            );
+                        }
+                        rest = &rest[start + 1..];
+                    }
+                }
+                if let Some((name, _)) = step.capture {
+                    known.push(name);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn overrides_must_name_a_parameter() {
+        let recipe = find_recipe("expired-token").expect("recipe");
+        let overrides = parse_overrides(&["age=2h".to_string()], &[recipe]).expect("age");
+        assert_eq!(overrides["age"], "2h");
+        assert!(parse_overrides(&["bits=4096".to_string()], &[recipe]).is_err());
+        assert!(parse_overrides(&["age".to_string()], &[recipe]).is_err());
+        assert!(find_recipe("nope").is_err());
+    }
+}
@@ -0,0 +1,489 @@
+use crate::cli::{ClientAuth, FetchTokenArgs, GrantType};
+use crate::commands::vault::resolve_project_selector;
+use crate::error::{AppError, AppResult, ErrorKind};
+use crate::http::{self, JsonResponse};
+use crate::io_utils::read_input;
+use crate::jwt_ops;
+use crate::output::{emit_err, emit_ok, CommandOutput, OutputConfig};
+use crate::vault::{TokenEntryInput, Vault, VaultConfig};
+use serde_json::{json, Map, Value};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+const DEVICE_CODE_GRANT: &str = "urn:ietf:params:oauth:grant-type:device_code";
+const TOKEN_FIELDS: [(&str, &str); 3] = [
+    ("access_token", "access"),
+    ("refresh_token", "refresh"),
+    ("id_token", "id"),
+];
+
+pub fn run(
+    no_persist: bool,
+    data_dir: Option<PathBuf>,
+    args: FetchTokenArgs,
+    cfg: OutputConfig,
+) -> i32 {
+    let result = (|| -> AppResult<CommandOutput> {
+        // Open the vault first so a bad --project fails before any request is sent.
+        let vault = match &args.project {
+            Some(_) => Some(
+                Vault::open(VaultConfig {
+                    no_persist,
+                    data_dir,
+                })
+                .map_err(|e| AppError::invalid_key(e.to_string()))?,
+            ),
+            None => None,
+        };
+        let project = match (&vault, &args.project) {
+            (Some(vault), Some(selector)) => Some(resolve_project_selector(vault, selector)?),
+            _ => None,
+        };
+
+        let client = ClientCredentials::from_args(&args)?;
+        let stored_refresh = match (&vault, &project, &args.refresh) {
+            (Some(vault), Some(project), Some(name)) => {
+                Some(load_refresh_token(vault, &project.id, name)?)
+            }
+            _ => None,
+        };
+        let response = if let Some(stored) = &stored_refresh {
+            refresh_grant(&args, &client, &stored.token)?
+        } else {
+            match args.grant {
+                GrantType::ClientCredentials => {
+                    let mut form = base_form(&args, "client_credentials")?;
+                    request_token(&args.token_url, &mut form, &client)?
+                }
+                GrantType::Password => {
+                    let mut form = base_form(&args, "password")?;
+                    let username = args.username.clone().unwrap_or_default();
+                    let password = read_input(args.password.as_deref().unwrap_or_default())?;
+                    form.push(("username".to_string(), username));
+                    form.push(("password".to_string(), password));
+                    request_token(&args.token_url, &mut form, &client)?
+                }
+                GrantType::DeviceCode => device_code_flow(&args, &client)?,
+            }
+        };
+
+        let mut data = summarize_response(&response);
+        let mut text = format_tokens(&data);
+        if let Some(stored) = &stored_refresh {
+            let report = rotation_report(&args, &client, stored, &response)?;
+            text.push_str(&format!("\n{}", report.summary()));
+            data["refresh"] = report.to_json(&stored.name);
+        }
+        if let (Some(vault), Some(project)) = (&vault, &project) {
+            let prefix = args
+                .name
+                .as_deref()
+                .or(stored_refresh.as_ref().map(|stored| stored.prefix.as_str()))
+                .unwrap_or(&args.client_id);
+            let stored = store_tokens(vault, &project.id, prefix, &response)?;
+            for entry in &stored {
+                text.push_str(&format!(
+                    "\nstored {} in project {}",
+                    entry["name"].as_str().unwrap_or_default(),
+                    project.name
+                ));
+            }
+            data["stored"] = json!(stored);
+        }
+        Ok(CommandOutput::new(data, text))
+    })();
+
+    match result.and_then(|out| emit_ok(cfg, out)) {
+        Ok(()) => 0,
+        Err(err) => {
+            let code = err.exit_code();
+            emit_err(cfg, err);
+            code
+        }
+    }
+}
+
+struct ClientCredentials {
+    client_id: String,
+    secret: Option<String>,
+    auth: ClientAuth,
+}
+
+impl ClientCredentials {
+    fn from_args(args: &FetchTokenArgs) -> AppResult<Self> {
+        Ok(Self {
+            client_id: args.client_id.clone(),
+            secret: args.client_secret.as_deref().map(read_input).transpose()?,
+            auth: args.client_auth,
+        })
+    }
+
+    /// Adds the credentials to the form unless they go in a Basic header, which is returned.
+    fn apply<'a>(&'a self, form: &mut Vec<(String, String)>) -> Option<(&'a str, &'a str)> {
+        match (&self.secret, self.auth) {
+            (Some(secret), ClientAuth::Basic) => Some((self.client_id.as_str(), secret.as_str())),
+            (secret, _) => {
+                form.push(("client_id".to_string(), self.client_id.clone()));
+                if let Some(secret) = secret {
+                    form.push(("client_secret".to_string(), secret.clone()));
+                }
+                None
+            }
+        }
+    }
+}
+
+fn base_form(args: &FetchTokenArgs, grant_type: &str) -> AppResult<Vec<(String, String)>> {
+    let mut form = vec![("grant_type".to_string(), grant_type.to_string())];
+    if let Some(scope) = &args.scope {
+        form.push(("scope".to_string(), scope.clone()));
+    }
+    if let Some(audience) = &args.audience {
+        form.push(("audience".to_string(), audience.clone()));
+    }
+    for param in &args.param {
+        let (name, value) = param.split_once('=').ok_or_else(|| {
+            AppError::invalid_claims(format!("invalid --param '{param}'; expected k=v"))
+        })?;
+        form.push((name.to_string(), value.to_string()));
+    }
+    Ok(form)
+}
+
+fn post(
+    url: &str,
+    form: &mut Vec<(String, String)>,
+    client: &ClientCredentials,
+) -> AppResult<JsonResponse> {
+    let basic = client.apply(form);
+    let pairs: Vec<(&str, &str)> = form
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.as_str()))
+        .collect();
+    http::post_form(url, &pairs, basic)
+}
+
+fn request_token(
+    url: &str,
+    form: &mut Vec<(String, String)>,
+    client: &ClientCredentials,
+) -> AppResult<Map<String, Value>> {
+    let response = post(url, form, client)?;
+    token_response(url, response)
+}
+
+fn token_response(url: &str, response: JsonResponse) -> AppResult<Map<String, Value>> {
+    match response.body {
+        Value::Object(body) if response.is_success() && body.contains_key("access_token") => {
+            Ok(body)
+        }
+        body => Err(endpoint_error(url, response.status, &body)),
+    }
+}
+
+/// Maps an OAuth error response (RFC 6749 §5.2) to an error that keeps the raw body.
+fn endpoint_error(url: &str, status: u16, body: &Value) -> AppError {
+    let message = match body.get("error").and_then(Value::as_str) {
+        Some(code) => match body.get("error_description").and_then(Value::as_str) {
+            Some(description) => format!("{url} returned {status}: {code} ({description})"),
+            None => format!("{url} returned {status}: {code}"),
+        },
+        None => format!("{url} returned {status} without a token response"),
+    };
+    AppError::invalid_key(message).with_details(json!({ "status": status, "body": body }))
+}
+
+/// RFC 8628: request a device code, show the user code, then poll the token endpoint.
+fn device_code_flow(
+    args: &FetchTokenArgs,
+    client: &ClientCredentials,
+) -> AppResult<Map<String, Value>> {
+    let device_url = args.device_url.as_deref().unwrap_or_default();
+    let mut form = base_form(args, DEVICE_CODE_GRANT)?;
+    form.retain(|(name, _)| name != "grant_type");
+    let response = post(device_url, &mut form, client)?;
+    let authorization = match response.body {
+        Value::Object(body) if response.is_success() && body.contains_key("device_code") => body,
+        body => return Err(endpoint_error(device_url, response.status, &body)),
+    };
+    let field = |name: &str| authorization.get(name).and_then(Value::as_str);
+    let device_code = field("device_code").unwrap_or_default().to_string();
+    match field("verification_uri_complete") {
+        Some(uri) => eprintln!("Open {uri} to approve the request."),
+        None => eprintln!(
+            "Open {} and enter code {}",
+            field("verification_uri").unwrap_or("the verification page"),
+            field("user_code").unwrap_or_default()
+        ),
+    }
+
+    let mut interval = authorization
+        .get("interval")
+        .and_then(Value::as_u64)
+        .unwrap_or(5);
+    let expires_in = authorization
+        .get("expires_in")
+        .and_then(Value::as_u64)
+        .unwrap_or(600);
+    let deadline = Instant::now() + Duration::from_secs(expires_in);
+    while Instant::now() < deadline {
+        std::thread::sleep(Duration::from_secs(interval));
+        let mut form = vec![
+            ("grant_type".to_string(), DEVICE_CODE_GRANT.to_string()),
+            ("device_code".to_string(), device_code.clone()),
+        ];
+        let response = post(&args.token_url, &mut form, client)?;
+        match response.body.get("error").and_then(Value::as_str) {
+            Some("authorization_pending") => continue,
+            Some("slow_down") => interval += 5,
+            _ => return token_response(&args.token_url, response),
+        }
+    }
+    Err(AppError::invalid_key(
+        "device code expired before the request was approved",
+    ))
+}
+
+struct StoredRefresh {
+    name: String,
+    prefix: String,
+    token: String,
+}
+
+/// Finds a stored refresh token by exact name, then as the prefix of `<prefix>-refresh`.
+fn load_refresh_token(vault: &Vault, project_id: &str, name: &str) -> AppResult<StoredRefresh> {
+    let tokens = vault
+        .list_tokens(Some(project_id))
+        .map_err(|e| AppError::invalid_key(e.to_string()))?;
+    let suffixed = format!("{name}-refresh");
+    let entry = tokens
+        .iter()
+        .find(|entry| entry.name == name)
+        .or_else(|| tokens.iter().find(|entry| entry.name == suffixed))
+        .ok_or_else(|| AppError::not_found(format!("refresh token not found: {name}")))?;
+    let token = vault
+        .get_token_material(&entry.id)
+        .map_err(|e| AppError::invalid_key(e.to_string()))?;
+    let prefix = entry
+        .name
+        .strip_suffix("-refresh")
+        .unwrap_or(&entry.name)
+        .to_string();
+    Ok(StoredRefresh {
+        name: entry.name.clone(),
+        prefix,
+        token,
+    })
+}
+
+fn refresh_grant(
+    args: &FetchTokenArgs,
+    client: &ClientCredentials,
+    refresh_token: &str,
+) -> AppResult<Map<String, Value>> {
+    let mut form = base_form(args, "refresh_token")?;
+    form.push(("refresh_token".to_string(), refresh_token.to_string()));
+    request_token(&args.token_url, &mut form, client)
+}
+
+struct RotationReport {
+    rotated: bool,
+    old_token_rejected: Option<bool>,
+}
+
+impl RotationReport {
+    fn summary(&self) -> String {
+        match (self.rotated, self.old_token_rejected) {
+            (false, _) => "refresh token was not rotated".to_string(),
+            (true, None) => "refresh token was rotated".to_string(),
+            (true, Some(true)) => "refresh token was rotated; the old one is rejected".to_string(),
+            (true, Some(false)) => {
+                "refresh token was rotated, but the old one is still accepted".to_string()
+            }
+        }
+    }
+
+    fn to_json(&self, source: &str) -> Value {
+        json!({
+            "source": source,
+            "rotated": self.rotated,
+            "old_token_rejected": self.old_token_rejected,
+        })
+    }
+}
+
+/// Compares the returned refresh token with the one used. With `--check-rotation`, a rotated
+/// token is replayed once to see whether the IdP invalidated it.
+fn rotation_report(
+    args: &FetchTokenArgs,
+    client: &ClientCredentials,
+    stored: &StoredRefresh,
+    response: &Map<String, Value>,
+) -> AppResult<RotationReport> {
+    let rotated = response
+        .get("refresh_token")
+        .and_then(Value::as_str)
+        .is_some_and(|token| token != stored.token);
+    if rotated {
+        warn!(
+            "the IdP returned a new refresh token; {} was rotated",
+            stored.name
+        );
+    }
+    let old_token_rejected = if rotated && args.check_rotation {
+        match refresh_grant(args, client, &stored.token) {
+            Ok(_) => {
+                warn!(
+                    "the IdP still accepts the rotated refresh token {}",
+                    stored.name
+                );
+                Some(false)
+            }
+            // Rejections surface as InvalidKey; transport failures prove nothing.
+            Err(err) if err.kind == ErrorKind::InvalidKey => Some(true),
+            Err(err) => return Err(err),
+        }
+    } else {
+        None
+    };
+    Ok(RotationReport {
+        rotated,
+        old_token_rejected,
+    })
+}
+
+fn summarize_response(response: &Map<String, Value>) -> Value {
+    let mut tokens = Map::new();
+    for (field, _) in TOKEN_FIELDS {
+        if let Some(token) = response.get(field).and_then(Value::as_str) {
+            tokens.insert(field.to_string(), describe_token(token));
+        }
+    }
+    let extra: Map<String, Value> = response
+        .iter()
+        .filter(|(name, _)| !TOKEN_FIELDS.iter().any(|(field, _)| field == name))
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect();
+    json!({ "tokens": tokens, "response": extra })
+}
+
+/// Decodes JWTs (unverified); opaque tokens are reported as such.
+fn describe_token(token: &str) -> Value {
+    match jwt_ops::decode_unverified(token) {
+        Ok(decoded) => json!({
+            "token": token,
+            "jwt": true,
+            "header": decoded.header_json,
+            "payload": decoded.payload_json,
+        }),
+        Err(_) => json!({ "token": token, "jwt": false }),
+    }
+}
+
+fn format_tokens(data: &Value) -> String {
+    let mut lines = Vec::new();
+    if let Some(tokens) = data["tokens"].as_object() {
+        for (field, info) in tokens {
+            lines.push(format!(
+                "{field}: {}",
+                info["token"].as_str().unwrap_or_default()
+            ));
+            if info["jwt"] == true {
+                lines.push(format!("  header: {}", info["header"]));
+                lines.push(format!("  payload: {}", info["payload"]));
+            } else {
+                lines.push("  (opaque, not a JWT)".to_string());
+            }
+        }
+    }
+    if let Some(response) = data["response"].as_object() {
+        for (name, value) in response {
+            lines.push(format!("{name}: {value}"));
+        }
+    }
+    lines.join("\n")
+}
+
+/// Stores each returned token as `<prefix>-access|refresh|id`, replacing earlier fetches.
+fn store_tokens(
+    vault: &Vault,
+    project_id: &str,
+    prefix: &str,
+    response: &Map<String, Value>,
+) -> AppResult<Vec<Value>> {
+    let existing = vault
+        .list_tokens(Some(project_id))
+        .map_err(|e| AppError::invalid_key(e.to_string()))?;
+    let mut stored = Vec::new();
+    for (field, suffix) in TOKEN_FIELDS {
+        let Some(token) = response.get(field).and_then(Value::as_str) else {
+            continue;
+        };
+        let name = format!("{prefix}-{suffix}");
+        for old in existing.iter().filter(|entry| entry.name == name) {
+            vault
+                .delete_token(&old.id)
+                .map_err(|e| AppError::invalid_key(e.to_string()))?;
+        }
+        let entry = vault
+            .add_token(TokenEntryInput {
+                project_id: project_id.to_string(),
+                name,
+                token: token.to_string(),
+            })
+            .map_err(|e| AppError::invalid_key(e.to_string()))?;
+        stored.push(json!({ "field": field, "name": entry.name, "id": entry.id }));
+    }
+    Ok(stored)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{endpoint_error, summarize_response, ClientCredentials};
+    use crate::cli::ClientAuth;
+    use crate::error::ErrorKind;
+    use serde_json::json;
+
+    #[test]
+    fn client_credentials_go_to_header_or_form() {
+        let client = ClientCredentials {
+            client_id: "app".to_string(),
+            secret: Some("s3cret".to_string()),
+            auth: ClientAuth::Basic,
+        };
+        let mut form = Vec::new();
+        assert_eq!(client.apply(&mut form), Some(("app", "s3cret")));
+        assert!(form.is_empty());
+
+        let client = ClientCredentials {
+            auth: ClientAuth::Post,
+            ..client
+        };
+        assert_eq!(client.apply(&mut form), None);
+        assert_eq!(form.len(), 2);
+    }
+
+    #[test]
+    fn summarize_response_separates_tokens_from_metadata() {
+        let response = json!({
+            "access_token": "opaque-value",
+            "token_type": "Bearer",
+            "expires_in": 300
+        });
+        let summary = summarize_response(response.as_object().unwrap());
+        assert_eq!(summary["tokens"]["access_token"]["jwt"], false);
+        assert_eq!(summary["response"]["expires_in"], 300);
+    }
+
+    #[test]
+    fn endpoint_error_reports_oauth_error_codes() {
+        let err = endpoint_error(
+            "https://idp/token",
+            401,
+            &json!({ "error": "invalid_client", "error_description": "bad secret" }),
+        );
+        assert_eq!(err.kind, ErrorKind::InvalidKey);
+        assert!(err.to_string().contains("invalid_client (bad secret)"));
+    }
+}
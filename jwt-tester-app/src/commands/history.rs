@@ -0,0 +1,140 @@
+use crate::cli::{HistoryArgs, HistoryCmd};
+use crate::error::{AppError, AppResult};
+use crate::history::{history_limit, HISTORY_ENV};
+use crate::output::{emit_err, emit_ok, CommandOutput, OutputConfig};
+use crate::vault::{HistoryEntry, Vault, VaultConfig};
+use serde_json::json;
+use std::path::PathBuf;
+
+pub fn run(
+    no_persist: bool,
+    data_dir: Option<PathBuf>,
+    args: HistoryArgs,
+    cfg: OutputConfig,
+) -> i32 {
+    let result = (|| -> AppResult<CommandOutput> {
+        let vault = Vault::open(VaultConfig {
+            no_persist,
+            data_dir,
+        })
+        .map_err(|e| AppError::invalid_key(e.to_string()))?;
+
+        execute(&vault, args)
+    })();
+
+    match result.and_then(|out| emit_ok(cfg, out)) {
+        Ok(()) => 0,
+        Err(err) => {
+            let code = err.exit_code();
+            emit_err(cfg, err);
+            code
+        }
+    }
+}
+
+pub(crate) fn execute(vault: &Vault, args: HistoryArgs) -> AppResult<CommandOutput> {
+    let out = match args.cmd {
+        HistoryCmd::List { limit } => {
+            let entries = vault
+                .list_history(limit)
+                .map_err(|e| AppError::internal(e.to_string()))?;
+            let mut lines: Vec<String> = entries.iter().map(format_history_entry).collect();
+            if lines.is_empty() {
+                lines.push(match history_limit() {
+                    Some(_) => "no history recorded yet".to_string(),
+                    None => format!("history is disabled; set {HISTORY_ENV}=<N> to record"),
+                });
+            }
+            CommandOutput::new(
+                json!({ "enabled": history_limit().is_some(), "entries": entries }),
+                lines.join("\n"),
+            )
+        }
+        HistoryCmd::Clear => {
+            let removed = vault
+                .clear_history()
+                .map_err(|e| AppError::internal(e.to_string()))?;
+            CommandOutput::new(
+                json!({ "removed": removed }),
+                format!("removed {removed} history entries"),
+            )
+        }
+    };
+    Ok(out)
+}
+
+fn format_history_entry(entry: &HistoryEntry) -> String {
+    let params = entry
+        .params
+        .as_object()
+        .map(|map| {
+            map.iter()
+                .map(|(name, value)| match value {
+                    serde_json::Value::String(text) => format!("{name}={text}"),
+                    serde_json::Value::Bool(true) => name.clone(),
+                    other => format!("{name}={other}"),
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .unwrap_or_default();
+    format!(
+        "{}  {}  {}  {}\n    {}",
+        entry.created_at,
+        entry.operation,
+        if entry.ok { "ok" } else { "error" },
+        entry.result,
+        params
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::execute;
+    use crate::cli::{HistoryArgs, HistoryCmd};
+    use crate::vault::{HistoryInput, Vault, VaultConfig};
+    use serde_json::json;
+
+    #[test]
+    fn list_and_clear_history() {
+        let vault = Vault::open(VaultConfig {
+            no_persist: true,
+            data_dir: None,
+        })
+        .expect("vault");
+        for n in 0..3 {
+            vault
+                .record_history(
+                    HistoryInput {
+                        operation: "encode".to_string(),
+                        ok: true,
+                        params: json!({ "alg": "HS256", "sub": format!("user-{n}") }),
+                        result: format!("token-{n}"),
+                    },
+                    2,
+                )
+                .expect("record");
+        }
+
+        let listed = execute(
+            &vault,
+            HistoryArgs {
+                cmd: HistoryCmd::List { limit: None },
+            },
+        )
+        .expect("list");
+        let entries = listed.data["entries"].as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0]["result"], "token-2");
+        assert!(listed.text.contains("alg=HS256 sub=user-2"));
+
+        let cleared = execute(
+            &vault,
+            HistoryArgs {
+                cmd: HistoryCmd::Clear,
+            },
+        )
+        .expect("clear");
+        assert_eq!(cleared.data["removed"], 2);
+    }
+}
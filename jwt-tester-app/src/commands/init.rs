@@ -0,0 +1,65 @@
+use crate::cli::InitArgs;
+use crate::commands::vault::resolve_project_selector;
+use crate::error::{AppError, AppResult};
+use crate::output::{emit_err, emit_ok, CommandOutput, OutputConfig};
+use crate::vault::{Vault, VaultConfig};
+use crate::workspace::{Workspace, WORKSPACE_FILE};
+use clap::ValueEnum;
+use serde_json::json;
+use std::path::PathBuf;
+
+/// Writes `.jwt-tester` in the current directory. The project must exist in the vault, so a typo
+/// fails here instead of in every later command.
+pub fn run(no_persist: bool, data_dir: Option<PathBuf>, args: InitArgs, cfg: OutputConfig) -> i32 {
+    let result = (|| -> AppResult<CommandOutput> {
+        let dir = std::env::current_dir()
+            .map_err(|e| AppError::internal(format!("failed to read current directory: {e}")))?;
+        let path = dir.join(WORKSPACE_FILE);
+        if path.exists() && !args.force {
+            return Err(AppError::invalid_claims(format!(
+                "{} already exists; pass --force to replace it",
+                path.display()
+            )));
+        }
+        let vault = Vault::open(VaultConfig {
+            no_persist,
+            data_dir,
+        })
+        .map_err(|e| AppError::invalid_key(e.to_string()))?;
+        let project = resolve_project_selector(&vault, &args.project)?;
+
+        let workspace = Workspace {
+            project: project.name.clone(),
+            alg: args
+                .alg
+                .and_then(|alg| alg.to_possible_value())
+                .map(|value| value.get_name().to_string()),
+            claims: args.claims,
+        };
+        let body = serde_json::to_string_pretty(&workspace)
+            .map_err(|e| AppError::internal(format!("serialize workspace: {e}")))?;
+        std::fs::write(&path, format!("{body}\n"))
+            .map_err(|e| AppError::internal(format!("failed to write {}: {e}", path.display())))?;
+
+        let mut text = format!("linked {} to project {}", dir.display(), project.name);
+        if let Some(alg) = &workspace.alg {
+            text.push_str(&format!("\ndefault alg: {alg}"));
+        }
+        if let Some(claims) = &workspace.claims {
+            text.push_str(&format!("\ndefault claims: {claims}"));
+        }
+        Ok(CommandOutput::new(
+            json!({ "path": path, "workspace": workspace }),
+            text,
+        ))
+    })();
+
+    match result.and_then(|out| emit_ok(cfg, out)) {
+        Ok(()) => 0,
+        Err(err) => {
+            let code = err.exit_code();
+            emit_err(cfg, err);
+            code
+        }
+    }
+}
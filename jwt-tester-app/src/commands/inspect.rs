@@ -1,68 +1,38 @@
 use crate::cli::InspectArgs;
 use crate::date_utils::{extract_dates, parse_date_mode};
-use crate::error::AppResult;
-use crate::io_utils::read_input;
-use crate::jwt_ops;
-use crate::output::{emit_err, emit_ok, CommandOutput, OutputConfig};
-use serde_json::json;
+use crate::error::{AppError, AppResult};
+use crate::file_assoc;
+use crate::io_utils::read_token_input;
+use crate::jwt_ops::{self, JoseKind};
+use crate::output::{emit_err, emit_ok, paint, CommandOutput, OutputConfig, Tone};
+use crate::sandbox;
+use serde_json::{json, Value};
+
+mod fit;
+mod header_urls;
+mod size;
 
 pub fn run(args: InspectArgs, cfg: OutputConfig) -> i32 {
-    let result = (|| -> AppResult<CommandOutput> {
-        let token = read_input(&args.token)?;
-        let decoded = jwt_ops::decode_unverified(&token)?;
-        let header = jwt_ops::decode_header_only(&token)?;
-        let date_mode = parse_date_mode(args.date)?;
-        let dates = extract_dates(&decoded.payload_json, date_mode)?;
-
-        let segments: Vec<&str> = token.trim().split('.').collect();
-        let sizes = json!({
-            "token_len": token.trim().len(),
-            "header_len": segments.first().map(|s| s.len()).unwrap_or(0),
-            "payload_len": segments.get(1).map(|s| s.len()).unwrap_or(0),
-            "signature_len": segments.get(2).map(|s| s.len()).unwrap_or(0),
-        });
-
-        let data = json!({
-            "header": decoded.header_json,
-            "payload": decoded.payload_json,
-            "summary": {
-                "alg": format!("{:?}", header.alg),
-                "kid": header.kid,
-                "typ": header.typ,
-                "sizes": sizes,
-            },
-            "dates": dates.json,
-            "segments": if args.show_segments { Some(segments.clone()) } else { None },
-        });
-
-        let mut text = String::new();
-        text.push_str("UNVERIFIED\n");
-        text.push_str(&format!("alg: {:?}\n", header.alg));
-        if let Some(kid) = header.kid {
-            text.push_str(&format!("kid: {}\n", kid));
-        }
-        if let Some(typ) = header.typ {
-            text.push_str(&format!("typ: {}\n", typ));
-        }
-        text.push_str(&format!("token length: {}\n", token.trim().len()));
-        if args.show_segments {
-            text.push_str("segments:\n");
-            for (idx, seg) in segments.iter().enumerate() {
-                text.push_str(&format!("  [{}] {}\n", idx, seg));
+    if args.register_file_association {
+        return match register_file_association(args.dry_run).and_then(|out| emit_ok(cfg, out)) {
+            Ok(()) => 0,
+            Err(err) => {
+                let code = err.exit_code();
+                emit_err(cfg, err);
+                code
+            }
+        };
+    }
+    match execute_in(&args, cfg, args.sandbox)
+        .and_then(|(out, fits)| emit_ok(cfg, out).map(|()| fits))
+    {
+        Ok(fits) => {
+            // Same exit code as a failed claim check, so CI can gate on deployment limits.
+            if fits {
+                0
+            } else {
+                12
             }
-        }
-        if !dates.lines.is_empty() {
-            text.push_str("dates:\n");
-            text.push_str(&dates.lines.join("\n"));
-            text.push('\n');
-        }
-        Ok(CommandOutput::new(data, text))
-    })();
-
-    match result {
-        Ok(out) => {
-            emit_ok(cfg, out);
-            0
         }
         Err(err) => {
             let code = err.exit_code();
@@ -72,6 +42,372 @@ pub fn run(args: InspectArgs, cfg: OutputConfig) -> i32 {
     }
 }
 
+/// Inspects without printing; the flag is false when a `--fits-in` target is exceeded. Shared with
+/// `serve --grpc`.
+#[cfg(feature = "grpc")]
+pub(crate) fn execute(args: &InspectArgs, cfg: OutputConfig) -> AppResult<(CommandOutput, bool)> {
+    execute_in(args, cfg, false)
+}
+
+/// With `sandboxed` (`--sandbox`), the token is read first and then inspected with filesystem,
+/// network and process access gone.
+fn execute_in(
+    args: &InspectArgs,
+    cfg: OutputConfig,
+    sandboxed: bool,
+) -> AppResult<(CommandOutput, bool)> {
+    let targets = args
+        .fits_in
+        .iter()
+        .map(|raw| fit::parse_target(raw))
+        .collect::<AppResult<Vec<_>>>()?;
+    let allow = args
+        .allow_url
+        .iter()
+        .map(|raw| header_urls::parse_allow(raw))
+        .collect::<AppResult<Vec<_>>>()?;
+    if sandboxed {
+        if args.fetch_header_urls {
+            return Err(AppError::internal(
+                "--sandbox cannot be combined with --fetch-header-urls",
+            ));
+        }
+        sandbox::check_output(cfg)?;
+    }
+    let token = read_token_input(args.token.as_deref().unwrap_or("-"))?;
+    if sandboxed {
+        sandbox::enter()?;
+    }
+    let mut out = match jwt_ops::classify_compact(&token)? {
+        JoseKind::Jws => {
+            let mut out = inspect_jws(args, &token, cfg)?;
+            push_header_urls(&mut out, args, &allow, token.trim(), cfg);
+            out
+        }
+        JoseKind::Unsecured => inspect_unsecured(args, &token, cfg)?,
+        JoseKind::Jwe => inspect_jwe(args, &token, cfg)?,
+    };
+    let fits = push_fit_report(&mut out, args, &targets, token.trim(), cfg);
+    Ok((out, fits))
+}
+
+/// Points `.jwt`/`.jwks` files and `jwt-tester://` links at `ui --open-file` for this binary.
+fn register_file_association(dry_run: bool) -> AppResult<CommandOutput> {
+    if !cfg!(feature = "ui") {
+        return Err(AppError::internal(
+            "file associations open the local UI; this build has no `ui` feature",
+        ));
+    }
+    let exe = std::env::current_exe()
+        .and_then(|path| path.canonicalize())
+        .map_err(|e| AppError::internal(format!("failed to locate the running binary: {e}")))?;
+    let registration = file_assoc::plan(&exe)?;
+    let warnings = if dry_run {
+        Vec::new()
+    } else {
+        file_assoc::apply(&registration)?
+    };
+    let mut lines = vec![format!(
+        "{} .jwt, .jwks and {}:// for {} ({})",
+        if dry_run {
+            "would register"
+        } else {
+            "registered"
+        },
+        file_assoc::URL_SCHEME,
+        exe.display(),
+        registration.platform
+    )];
+    lines.extend(
+        registration
+            .files
+            .iter()
+            .map(|file| format!("  wrote {}", file.path.display())),
+    );
+    lines.extend(
+        registration
+            .commands
+            .iter()
+            .map(|command| format!("  ran {}", command.join(" "))),
+    );
+    lines.extend(warnings.iter().map(|warning| format!("warning: {warning}")));
+    let data = json!({
+        "registered": !dry_run,
+        "platform": registration.platform,
+        "executable": exe,
+        "files": registration.files,
+        "commands": registration.commands,
+        "warnings": warnings,
+    });
+    Ok(CommandOutput::new(data, lines.join("\n")))
+}
+
+fn inspect_jws(args: &InspectArgs, token: &str, cfg: OutputConfig) -> AppResult<CommandOutput> {
+    let decoded = jwt_ops::decode_unverified(token)?;
+    let header = jwt_ops::decode_header_only(token)?;
+    let date_mode = parse_date_mode(args.date.clone())?;
+    let dates = extract_dates(&decoded.payload_json, date_mode)?;
+
+    let segments: Vec<&str> = token.trim().split('.').collect();
+    let sizes = json!({
+        "token_len": token.trim().len(),
+        "header_len": segments.first().map(|s| s.len()).unwrap_or(0),
+        "payload_len": segments.get(1).map(|s| s.len()).unwrap_or(0),
+        "signature_len": segments.get(2).map(|s| s.len()).unwrap_or(0),
+    });
+
+    let mut data = json!({
+        "type": JoseKind::Jws.as_str(),
+        "header": decoded.header_json,
+        "payload": decoded.payload_json,
+        "summary": {
+            "alg": format!("{:?}", header.alg),
+            "kid": header.kid,
+            "typ": header.typ,
+            "compressed": decoded.compressed,
+            "sizes": sizes,
+        },
+        "dates": dates.json,
+        "segments": if args.show_segments { Some(segments.clone()) } else { None },
+    });
+
+    let mut text = String::new();
+    text.push_str(&paint(cfg, Tone::Warning, "UNVERIFIED"));
+    text.push('\n');
+    text.push_str(&format!("alg: {:?}\n", header.alg));
+    if let Some(kid) = header.kid {
+        text.push_str(&format!("kid: {}\n", kid));
+    }
+    if let Some(typ) = header.typ {
+        text.push_str(&format!("typ: {}\n", typ));
+    }
+    push_compressed(&mut text, decoded.compressed, cfg);
+    text.push_str(&format!("token length: {}\n", token.trim().len()));
+    push_segments(&mut text, args, &segments);
+    if !dates.lines.is_empty() {
+        text.push_str("dates:\n");
+        text.push_str(&dates.styled_lines(cfg).join("\n"));
+        text.push('\n');
+    }
+    push_size_report(
+        &mut data,
+        &mut text,
+        args,
+        &decoded.payload_json,
+        token,
+        cfg,
+    );
+    Ok(CommandOutput::new(data, text))
+}
+
+/// `alg: none` tokens carry no signature at all, so there is nothing to verify; the header and
+/// payload are shown as-is.
+fn inspect_unsecured(
+    args: &InspectArgs,
+    token: &str,
+    cfg: OutputConfig,
+) -> AppResult<CommandOutput> {
+    let segments: Vec<&str> = token.trim().split('.').collect();
+    let header_json = jwt_ops::decode_segment_json(segments[0], "header")?;
+    let payload_json = jwt_ops::decode_payload_json(segments[1], &header_json)?;
+    let compressed = header_json.get("zip").is_some();
+    let date_mode = parse_date_mode(args.date.clone())?;
+    let dates = extract_dates(&payload_json, date_mode)?;
+
+    let mut data = json!({
+        "type": JoseKind::Unsecured.as_str(),
+        "header": header_json,
+        "payload": payload_json,
+        "summary": {
+            "alg": header_json.get("alg").cloned().unwrap_or(Value::Null),
+            "kid": header_json.get("kid").cloned().unwrap_or(Value::Null),
+            "typ": header_json.get("typ").cloned().unwrap_or(Value::Null),
+            "compressed": compressed,
+            "sizes": {
+                "token_len": token.trim().len(),
+                "header_len": segments[0].len(),
+                "payload_len": segments[1].len(),
+                "signature_len": 0,
+            },
+        },
+        "dates": dates.json,
+        "segments": if args.show_segments { Some(segments.clone()) } else { None },
+    });
+
+    let mut text = String::new();
+    text.push_str(&paint(cfg, Tone::Warning, "UNSECURED (no signature)"));
+    text.push('\n');
+    push_header_field(&mut text, &header_json, "alg");
+    push_header_field(&mut text, &header_json, "kid");
+    push_header_field(&mut text, &header_json, "typ");
+    push_compressed(&mut text, compressed, cfg);
+    text.push_str(&format!("token length: {}\n", token.trim().len()));
+    push_segments(&mut text, args, &segments);
+    if !dates.lines.is_empty() {
+        text.push_str("dates:\n");
+        text.push_str(&dates.styled_lines(cfg).join("\n"));
+        text.push('\n');
+    }
+    push_size_report(&mut data, &mut text, args, &payload_json, token, cfg);
+    Ok(CommandOutput::new(data, text))
+}
+
+/// JWEs are never decrypted here; only the protected header and segment sizes are shown.
+fn inspect_jwe(args: &InspectArgs, token: &str, cfg: OutputConfig) -> AppResult<CommandOutput> {
+    let segments: Vec<&str> = token.trim().split('.').collect();
+    let header_json = jwt_ops::decode_segment_json(segments[0], "header")?;
+    let field = |name: &str| header_json.get(name).cloned().unwrap_or(Value::Null);
+
+    let data = json!({
+        "type": JoseKind::Jwe.as_str(),
+        "header": header_json,
+        "payload": Value::Null,
+        "summary": {
+            "alg": field("alg"),
+            "enc": field("enc"),
+            "kid": field("kid"),
+            "typ": field("typ"),
+            "cty": field("cty"),
+            "zip": field("zip"),
+            "sizes": {
+                "token_len": token.trim().len(),
+                "header_len": segments[0].len(),
+                "encrypted_key_len": segments[1].len(),
+                "iv_len": segments[2].len(),
+                "ciphertext_len": segments[3].len(),
+                "tag_len": segments[4].len(),
+            },
+        },
+        "dates": {},
+        "segments": if args.show_segments { Some(segments.clone()) } else { None },
+    });
+
+    let mut text = String::new();
+    text.push_str(&paint(cfg, Tone::Warning, "ENCRYPTED (JWE, not decrypted)"));
+    text.push('\n');
+    for name in ["alg", "enc", "kid", "typ", "cty", "zip"] {
+        push_header_field(&mut text, &header_json, name);
+    }
+    text.push_str(&format!("token length: {}\n", token.trim().len()));
+    text.push_str(&format!("ciphertext length: {}\n", segments[3].len()));
+    push_segments(&mut text, args, &segments);
+    Ok(CommandOutput::new(data, text))
+}
+
+/// `--sizes`/`--budget`: adds `size_report` to the JSON and a claim breakdown to the text.
+fn push_size_report(
+    data: &mut Value,
+    text: &mut String,
+    args: &InspectArgs,
+    payload: &Value,
+    token: &str,
+    cfg: OutputConfig,
+) {
+    if !args.sizes && args.budget.is_none() {
+        return;
+    }
+    let report = size::analyze(payload, token.trim().len(), args.budget);
+    data["size_report"] = report.to_json();
+    text.push_str(&report.text_lines(cfg).join("\n"));
+    text.push('\n');
+}
+
+/// `--fits-in`: adds `fits_in` to the JSON and one line per target to the text. Returns whether
+/// the token fits every target.
+fn push_fit_report(
+    out: &mut CommandOutput,
+    args: &InspectArgs,
+    targets: &[fit::FitTarget],
+    token: &str,
+    cfg: OutputConfig,
+) -> bool {
+    if targets.is_empty() {
+        return true;
+    }
+    let checks: Vec<fit::FitCheck> = targets
+        .iter()
+        .map(|target| {
+            fit::check(
+                *target,
+                token,
+                args.prefix.as_deref(),
+                &args.cookie_name,
+                &args.header_name,
+            )
+        })
+        .collect();
+    out.data["fits_in"] = Value::Array(checks.iter().map(fit::FitCheck::to_json).collect());
+    if !out.text.is_empty() && !out.text.ends_with('\n') {
+        out.text.push('\n');
+    }
+    out.text.push_str("fits in:\n");
+    for check in &checks {
+        out.text.push_str(&format!("  {}\n", check.text_line(cfg)));
+    }
+    checks.iter().all(fit::FitCheck::fits)
+}
+
+/// `jku`/`x5u`: adds `header_urls` to the JSON and a section to the text whenever the header has
+/// one, fetched or not, so the warning about trusting them is always next to the URL.
+fn push_header_urls(
+    out: &mut CommandOutput,
+    args: &InspectArgs,
+    allow: &[header_urls::AllowedUrl],
+    token: &str,
+    cfg: OutputConfig,
+) {
+    let report = header_urls::inspect(&out.data["header"], token, args.fetch_header_urls, allow);
+    if report.is_empty() {
+        return;
+    }
+    out.data["header_urls"] = json!({
+        "warning": header_urls::warning(),
+        "urls": report.iter().map(header_urls::HeaderUrl::to_json).collect::<Vec<_>>(),
+    });
+    if !out.text.is_empty() && !out.text.ends_with('\n') {
+        out.text.push('\n');
+    }
+    out.text.push_str("header URLs (untrusted):\n");
+    for entry in &report {
+        out.text.push_str(&entry.text_lines(cfg).join("\n"));
+        out.text.push('\n');
+    }
+    out.text.push_str(&paint(
+        cfg,
+        Tone::Warning,
+        &format!("  warning: {}", header_urls::warning()),
+    ));
+    out.text.push('\n');
+}
+
+fn push_compressed(text: &mut String, compressed: bool, cfg: OutputConfig) {
+    if compressed {
+        text.push_str(&paint(
+            cfg,
+            Tone::Warning,
+            "compressed: zip DEF (payload shown inflated)",
+        ));
+        text.push('\n');
+    }
+}
+
+fn push_header_field(text: &mut String, header: &Value, name: &str) {
+    match header.get(name) {
+        Some(Value::String(value)) => text.push_str(&format!("{name}: {value}\n")),
+        Some(value) if !value.is_null() => text.push_str(&format!("{name}: {value}\n")),
+        _ => {}
+    }
+}
+
+fn push_segments(text: &mut String, args: &InspectArgs, segments: &[&str]) {
+    if args.show_segments {
+        text.push_str("segments:\n");
+        for (idx, seg) in segments.iter().enumerate() {
+            text.push_str(&format!("  [{}] {}\n", idx, seg));
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::run;
@@ -86,7 +422,11 @@ mod tests {
             mode: OutputMode::Json,
             quiet: true,
             no_color: true,
+            theme: crate::cli::Theme::Default,
             verbose: false,
+            template: None,
+            summary: None,
+            sinks: None,
         }
     }
 
@@ -106,7 +446,18 @@ mod tests {
         let args = InspectArgs {
             date: Some("utc".to_string()),
             show_segments: true,
-            token,
+            sizes: true,
+            budget: Some(64),
+            fits_in: Vec::new(),
+            prefix: None,
+            cookie_name: "token".to_string(),
+            header_name: "Authorization".to_string(),
+            fetch_header_urls: false,
+            allow_url: Vec::new(),
+            register_file_association: false,
+            dry_run: false,
+            sandbox: false,
+            token: Some(token),
         };
         let code = run(args, cfg());
         assert_eq!(code, 0);
@@ -0,0 +1,176 @@
+use crate::error::{AppError, AppResult};
+use crate::output::{paint, OutputConfig, Tone};
+use serde_json::{json, Value};
+
+/// Browsers cap a cookie's name, `=` and value at 4096 bytes.
+const COOKIE_LIMIT: usize = 4096;
+/// nginx `large_client_header_buffers` default: one header line must fit in 8k.
+const HEADER_LIMIT: usize = 8192;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum FitKind {
+    Cookie,
+    Header,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct FitTarget {
+    kind: FitKind,
+    limit: usize,
+}
+
+/// `cookie`, `header`, or either with an explicit limit (`header:16384`).
+pub(super) fn parse_target(raw: &str) -> AppResult<FitTarget> {
+    let (name, limit) = match raw.trim().split_once(':') {
+        Some((name, limit)) => (name, Some(limit)),
+        None => (raw.trim(), None),
+    };
+    let kind = match name.to_lowercase().as_str() {
+        "cookie" => FitKind::Cookie,
+        "header" => FitKind::Header,
+        _ => {
+            return Err(AppError::invalid_claims(format!(
+                "invalid --fits-in value '{raw}'; expected cookie, header, cookie:<bytes> or header:<bytes>"
+            )))
+        }
+    };
+    let limit = match limit {
+        Some(limit) => limit.trim().parse().map_err(|_| {
+            AppError::invalid_claims(format!("invalid --fits-in byte limit in '{raw}'"))
+        })?,
+        None if kind == FitKind::Cookie => COOKIE_LIMIT,
+        None => HEADER_LIMIT,
+    };
+    Ok(FitTarget { kind, limit })
+}
+
+pub(super) struct FitCheck {
+    target: FitTarget,
+    /// The exact line the limit applies to, e.g. `Authorization: Bearer eyJ...`.
+    line_len: usize,
+    /// Bytes added by percent-encoding the prefix for a cookie value.
+    encoding_overhead: usize,
+}
+
+/// Cookies carry `<name>=<value>`; the value is percent-encoded where cookie-octets forbid a byte
+/// (RFC 6265), which matters for a prefix like `Bearer `. JWT characters never need encoding.
+/// Headers carry `<name>: <prefix><token>` unencoded.
+pub(super) fn check(
+    target: FitTarget,
+    token: &str,
+    prefix: Option<&str>,
+    cookie_name: &str,
+    header_name: &str,
+) -> FitCheck {
+    match target.kind {
+        FitKind::Cookie => {
+            let prefix = prefix.unwrap_or("");
+            let encoded: usize = prefix.bytes().map(cookie_octet_len).sum();
+            FitCheck {
+                target,
+                line_len: cookie_name.len() + 1 + encoded + token.len(),
+                encoding_overhead: encoded - prefix.len(),
+            }
+        }
+        FitKind::Header => FitCheck {
+            target,
+            line_len: header_name.len() + 2 + prefix.unwrap_or("Bearer ").len() + token.len(),
+            encoding_overhead: 0,
+        },
+    }
+}
+
+fn cookie_octet_len(byte: u8) -> usize {
+    match byte {
+        0x21 | 0x23..=0x2B | 0x2D..=0x3A | 0x3C..=0x5B | 0x5D..=0x7E => 1,
+        _ => 3,
+    }
+}
+
+impl FitCheck {
+    pub(super) fn fits(&self) -> bool {
+        self.line_len <= self.target.limit
+    }
+
+    fn label(&self) -> &'static str {
+        match self.target.kind {
+            FitKind::Cookie => "cookie",
+            FitKind::Header => "header",
+        }
+    }
+
+    pub(super) fn to_json(&self) -> Value {
+        json!({
+            "target": self.label(),
+            "limit": self.target.limit,
+            "bytes": self.line_len,
+            "encoding_overhead": self.encoding_overhead,
+            "fits": self.fits(),
+            "headroom": self.target.limit as i64 - self.line_len as i64,
+        })
+    }
+
+    pub(super) fn text_line(&self, cfg: OutputConfig) -> String {
+        let (tone, verdict) = if self.fits() {
+            (Tone::Valid, "fits")
+        } else {
+            (Tone::Error, "does not fit")
+        };
+        let mut line = format!(
+            "{} ({} bytes): {} of {} bytes, {}",
+            self.label(),
+            self.target.limit,
+            self.line_len,
+            self.target.limit,
+            verdict
+        );
+        if self.encoding_overhead > 0 {
+            line.push_str(&format!(
+                " (incl. {} bytes of URL encoding)",
+                self.encoding_overhead
+            ));
+        }
+        paint(cfg, tone, &line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_targets_with_default_and_explicit_limits() {
+        assert_eq!(parse_target("cookie").unwrap().limit, COOKIE_LIMIT);
+        assert_eq!(parse_target("Header").unwrap().limit, HEADER_LIMIT);
+        let target = parse_target("header:16384").unwrap();
+        assert_eq!((target.kind, target.limit), (FitKind::Header, 16384));
+        assert!(parse_target("url").is_err());
+        assert!(parse_target("cookie:lots").is_err());
+    }
+
+    #[test]
+    fn counts_names_prefixes_and_cookie_encoding() {
+        let token = "a".repeat(100);
+        let header = check(
+            parse_target("header:130").unwrap(),
+            &token,
+            None,
+            "session",
+            "Authorization",
+        );
+        assert_eq!(header.line_len, "Authorization: Bearer ".len() + 100);
+        assert!(header.fits());
+
+        let cookie = check(
+            parse_target("cookie:110").unwrap(),
+            &token,
+            Some("Bearer "),
+            "session",
+            "Authorization",
+        );
+        assert_eq!(cookie.encoding_overhead, 2);
+        assert_eq!(cookie.line_len, "session=Bearer%20".len() + 100);
+        assert!(!cookie.fits());
+        assert_eq!(cookie.to_json()["headroom"], -7);
+    }
+}
@@ -0,0 +1,212 @@
+use crate::output::{paint, OutputConfig, Tone};
+use serde_json::{json, Value};
+
+/// Claim names longer than this get a shortening suggestion (registered claims excepted).
+const LONG_CLAIM_NAME: usize = 12;
+/// A claim taking at least this share of a payload over `DOMINANT_MIN_PAYLOAD` bytes is flagged.
+const DOMINANT_SHARE: f64 = 0.25;
+const DOMINANT_MIN_PAYLOAD: usize = 512;
+const REGISTERED: [&str; 7] = ["iss", "sub", "aud", "exp", "nbf", "iat", "jti"];
+
+struct ClaimSize {
+    name: String,
+    /// `"name":value,` as compact JSON.
+    bytes: usize,
+    /// The same bytes after base64url encoding.
+    encoded_bytes: usize,
+}
+
+/// Per-claim sizes, largest first, plus advice on what to trim.
+pub(super) struct SizeReport {
+    claims: Vec<ClaimSize>,
+    payload_bytes: usize,
+    /// `None` in builds without the `deflate` feature.
+    deflated_payload_len: Option<usize>,
+    token_len: usize,
+    budget: Option<usize>,
+    suggestions: Vec<String>,
+}
+
+pub(super) fn analyze(payload: &Value, token_len: usize, budget: Option<usize>) -> SizeReport {
+    let payload_bytes = payload.to_string().len();
+    let mut claims: Vec<ClaimSize> = payload
+        .as_object()
+        .map(|object| {
+            object
+                .iter()
+                .map(|(name, value)| {
+                    let bytes = Value::String(name.clone()).to_string().len()
+                        + 1
+                        + value.to_string().len()
+                        + 1;
+                    ClaimSize {
+                        name: name.clone(),
+                        bytes,
+                        encoded_bytes: encoded_len(bytes),
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    claims.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.name.cmp(&b.name)));
+
+    let mut suggestions = Vec::new();
+    for claim in &claims {
+        let share = claim.bytes as f64 / payload_bytes.max(1) as f64;
+        if payload_bytes >= DOMINANT_MIN_PAYLOAD && share >= DOMINANT_SHARE {
+            suggestions.push(format!(
+                "'{}' is {:.0}% of the payload (~{} encoded bytes); move it server-side or reference it by id",
+                claim.name,
+                share * 100.0,
+                claim.encoded_bytes
+            ));
+        }
+    }
+    for claim in &claims {
+        let len = claim.name.chars().count();
+        if len > LONG_CLAIM_NAME && !REGISTERED.contains(&claim.name.as_str()) {
+            suggestions.push(format!(
+                "shorten claim name '{}' ({len} chars); a 3-char name saves ~{} encoded bytes",
+                claim.name,
+                encoded_len(len - 3)
+            ));
+        }
+    }
+
+    SizeReport {
+        claims,
+        payload_bytes,
+        deflated_payload_len: deflated_len(payload.to_string().as_bytes()).map(encoded_len),
+        token_len,
+        budget,
+        suggestions,
+    }
+}
+
+impl SizeReport {
+    pub(super) fn over_budget(&self) -> Option<usize> {
+        self.budget
+            .filter(|budget| self.token_len > *budget)
+            .map(|budget| self.token_len - budget)
+    }
+
+    pub(super) fn to_json(&self) -> Value {
+        let claims: Vec<Value> = self
+            .claims
+            .iter()
+            .map(|claim| {
+                json!({
+                    "claim": claim.name,
+                    "bytes": claim.bytes,
+                    "encoded_bytes": claim.encoded_bytes,
+                })
+            })
+            .collect();
+        json!({
+            "payload_bytes": self.payload_bytes,
+            "claims": claims,
+            "deflated_payload_len": self.deflated_payload_len,
+            "budget": self.budget.map(|budget| json!({
+                "limit": budget,
+                "token_len": self.token_len,
+                "over_by": self.over_budget().unwrap_or(0),
+            })),
+            "suggestions": self.suggestions,
+        })
+    }
+
+    pub(super) fn text_lines(&self, cfg: OutputConfig) -> Vec<String> {
+        let mut lines = vec![format!(
+            "claim sizes ({} payload bytes):",
+            self.payload_bytes
+        )];
+        let width = self
+            .claims
+            .iter()
+            .map(|claim| claim.name.chars().count())
+            .max()
+            .unwrap_or(0);
+        for claim in &self.claims {
+            lines.push(format!(
+                "  {:<width$}  {:>6} B  ~{} encoded",
+                claim.name, claim.bytes, claim.encoded_bytes
+            ));
+        }
+        if let Some(len) = self.deflated_payload_len {
+            lines.push(format!("deflated payload (zip DEF): ~{len} encoded bytes"));
+        }
+        if let Some(budget) = self.budget {
+            let line = format!("budget: {} of {budget} bytes", self.token_len);
+            match self.over_budget() {
+                Some(over) => {
+                    lines.push(paint(cfg, Tone::Error, &format!("{line} (over by {over})")))
+                }
+                None => lines.push(paint(cfg, Tone::Valid, &line)),
+            }
+        }
+        for suggestion in &self.suggestions {
+            lines.push(format!("suggestion: {suggestion}"));
+        }
+        lines
+    }
+}
+
+/// base64url without padding.
+fn encoded_len(bytes: usize) -> usize {
+    (bytes * 4).div_ceil(3)
+}
+
+/// Raw DEFLATE (RFC 1951), as the JOSE `zip: DEF` header uses.
+#[cfg(feature = "deflate")]
+fn deflated_len(bytes: &[u8]) -> Option<usize> {
+    use flate2::write::DeflateEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+    // Writing to a Vec cannot fail.
+    let _ = encoder.write_all(bytes);
+    Some(encoder.finish().map(|out| out.len()).unwrap_or(bytes.len()))
+}
+
+#[cfg(not(feature = "deflate"))]
+fn deflated_len(_bytes: &[u8]) -> Option<usize> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn claims_are_sized_largest_first_with_suggestions() {
+        let payload = json!({
+            "sub": "user",
+            "very_long_custom_claim_name": true,
+            "permissions": (0..80).map(|i| format!("perm:{i}")).collect::<Vec<_>>(),
+        });
+        let token_len = 1500;
+        let report = analyze(&payload, token_len, Some(1024));
+        assert_eq!(report.claims[0].name, "permissions");
+        assert_eq!(report.claims.last().unwrap().name, "sub");
+        assert_eq!(
+            report.claims.last().unwrap().bytes,
+            r#""sub":"user","#.len()
+        );
+        assert_eq!(report.over_budget(), Some(476));
+        #[cfg(feature = "deflate")]
+        assert!(report.deflated_payload_len.unwrap() < encoded_len(report.payload_bytes));
+
+        let data = report.to_json();
+        let suggestions = data["suggestions"].as_array().unwrap();
+        assert!(suggestions[0]
+            .as_str()
+            .unwrap()
+            .starts_with("'permissions' is"));
+        assert!(suggestions[1]
+            .as_str()
+            .unwrap()
+            .contains("very_long_custom_claim_name"));
+        assert_eq!(data["budget"]["over_by"], 476);
+    }
+}
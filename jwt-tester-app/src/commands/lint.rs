@@ -0,0 +1,368 @@
+use crate::claims::now_epoch;
+use crate::cli::LintArgs;
+use crate::error::{AppError, AppResult};
+use crate::io_utils::read_token_input;
+use crate::jwt_ops::{self, JoseKind};
+use crate::output::{emit_err, emit_ok, paint, CommandOutput, OutputConfig, Tone};
+use jsonwebtoken::Algorithm;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+/// Score lost per finding; `info` findings are advice only.
+const FAIL_PENALTY: u32 = 25;
+const WARN_PENALTY: u32 = 10;
+
+/// Rule ids in report order. Every rule that produced no finding is listed as passed.
+const RULES: [&str; 9] = [
+    "alg-none",
+    "alg-pinned",
+    "lifetime",
+    "iss",
+    "aud",
+    "symmetric",
+    "compression",
+    "untrusted-headers",
+    "explicit-typ",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Severity {
+    Fail,
+    Warn,
+    Info,
+}
+
+impl Severity {
+    fn penalty(self) -> u32 {
+        match self {
+            Severity::Fail => FAIL_PENALTY,
+            Severity::Warn => WARN_PENALTY,
+            Severity::Info => 0,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Severity::Fail => "FAIL",
+            Severity::Warn => "WARN",
+            Severity::Info => "INFO",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Finding {
+    rule: &'static str,
+    /// RFC 8725 section the rule comes from; lifetime limits are general practice.
+    section: Option<&'static str>,
+    severity: Severity,
+    message: String,
+}
+
+impl Finding {
+    fn new(
+        rule: &'static str,
+        section: Option<&'static str>,
+        severity: Severity,
+        message: impl Into<String>,
+    ) -> Self {
+        Finding {
+            rule,
+            section,
+            severity,
+            message: message.into(),
+        }
+    }
+}
+
+pub fn run(args: LintArgs, cfg: OutputConfig) -> i32 {
+    let result = (|| -> AppResult<(CommandOutput, bool)> {
+        if !args.rfc8725 {
+            return Err(AppError::invalid_token(
+                "no rule set selected; pass --rfc8725",
+            ));
+        }
+        let token = read_token_input(&args.token)?;
+        if jwt_ops::classify_compact(&token)? == JoseKind::Jwe {
+            return Err(AppError::invalid_token(
+                "lint checks signed tokens (JWS); this is a JWE",
+            ));
+        }
+        // Unsecured tokens may have only two segments, so decode them directly.
+        let segments: Vec<&str> = token.trim().split('.').collect();
+        let header = jwt_ops::decode_segment_json(segments[0], "header")?;
+        let claims = jwt_ops::decode_payload_json(segments[1], &header)?;
+        let findings = check_rfc8725(&args, &header, &claims, now_epoch());
+        let score = score(&findings);
+        let failed = findings.iter().any(|f| f.severity == Severity::Fail)
+            || args.min_score.is_some_and(|min| score < min);
+        let passed: Vec<&str> = RULES
+            .iter()
+            .copied()
+            .filter(|rule| !findings.iter().any(|f| f.rule == *rule))
+            .collect();
+        let data = json!({
+            "profile": "rfc8725",
+            "score": score,
+            "ok": !failed,
+            "findings": findings,
+            "passed": passed,
+        });
+        let text = format_report(cfg, score, &findings, &passed);
+        Ok((CommandOutput::new(data, text), failed))
+    })();
+
+    match result.and_then(|(out, failed)| emit_ok(cfg, out).map(|()| failed)) {
+        Ok(failed) => {
+            // Same exit code as a failed claim check, so CI can gate on the report.
+            if failed {
+                12
+            } else {
+                0
+            }
+        }
+        Err(err) => {
+            let code = err.exit_code();
+            emit_err(cfg, err);
+            code
+        }
+    }
+}
+
+/// Applies the JWT BCP (RFC 8725) to what a token alone can show. Verifier-side rules (pinning
+/// the algorithm, trust boundaries) rely on `--expect-alg` and `--cross-boundary`.
+fn check_rfc8725(args: &LintArgs, header: &Value, claims: &Value, now: i64) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let alg = header.get("alg").and_then(Value::as_str).unwrap_or("");
+
+    if alg.is_empty() || alg.eq_ignore_ascii_case("none") {
+        findings.push(Finding::new(
+            "alg-none",
+            Some("3.1"),
+            Severity::Fail,
+            "token is unsecured (alg none or missing); verifiers must reject it",
+        ));
+    }
+
+    match args.expect_alg.map(Algorithm::from) {
+        None => findings.push(Finding::new(
+            "alg-pinned",
+            Some("3.1"),
+            Severity::Warn,
+            "no --expect-alg given; verifiers must pin the algorithm instead of trusting the header",
+        )),
+        Some(expected) if format!("{expected:?}") != alg => findings.push(Finding::new(
+            "alg-pinned",
+            Some("3.1"),
+            Severity::Fail,
+            format!("header alg '{alg}' does not match the expected {expected:?}"),
+        )),
+        Some(_) => {}
+    }
+
+    match claims.get("exp").and_then(Value::as_i64) {
+        None => findings.push(Finding::new(
+            "lifetime",
+            None,
+            Severity::Fail,
+            "token has no exp; it never expires",
+        )),
+        Some(exp) => {
+            let start = claims.get("iat").and_then(Value::as_i64).unwrap_or(now);
+            let lifetime = exp.saturating_sub(start);
+            let max = args.max_lifetime.as_secs() as i64;
+            if lifetime > max {
+                findings.push(Finding::new(
+                    "lifetime",
+                    None,
+                    Severity::Warn,
+                    format!(
+                        "token is valid for {}, longer than {}",
+                        humantime::format_duration(std::time::Duration::from_secs(lifetime as u64)),
+                        humantime::format_duration(args.max_lifetime)
+                    ),
+                ));
+            }
+        }
+    }
+
+    if claims.get("iss").and_then(Value::as_str).is_none() {
+        findings.push(Finding::new(
+            "iss",
+            Some("3.8"),
+            Severity::Warn,
+            "no iss claim; verifiers cannot check who issued the token",
+        ));
+    }
+    let audiences = match claims.get("aud") {
+        Some(Value::String(aud)) => vec![aud.as_str()],
+        Some(Value::Array(items)) => items.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    };
+    if audiences.is_empty() {
+        findings.push(Finding::new(
+            "aud",
+            Some("3.9"),
+            Severity::Warn,
+            "no aud claim; the token can be replayed to any relying party",
+        ));
+    }
+
+    if alg.to_ascii_uppercase().starts_with("HS") {
+        if args.cross_boundary {
+            findings.push(Finding::new(
+                "symmetric",
+                Some("3.2"),
+                Severity::Fail,
+                format!(
+                    "{alg} shares one secret across a trust boundary; use an asymmetric algorithm"
+                ),
+            ));
+        } else if audiences.len() > 1 {
+            findings.push(Finding::new(
+                "symmetric",
+                Some("3.2"),
+                Severity::Warn,
+                format!(
+                    "{alg} with {} audiences: each one holds the secret and can mint tokens for the others",
+                    audiences.len()
+                ),
+            ));
+        }
+    }
+
+    if let Some(zip) = header.get("zip") {
+        findings.push(Finding::new(
+            "compression",
+            Some("3.6"),
+            Severity::Fail,
+            format!("header sets zip {zip}; compressed inputs leak plaintext length"),
+        ));
+    }
+
+    let embedded: Vec<&str> = ["jku", "x5u", "jwk", "x5c"]
+        .into_iter()
+        .filter(|name| header.get(*name).is_some())
+        .collect();
+    if !embedded.is_empty() {
+        findings.push(Finding::new(
+            "untrusted-headers",
+            Some("3.10"),
+            Severity::Warn,
+            format!(
+                "header carries {}; verifiers must not take keys or key URLs from the token",
+                embedded.join(", ")
+            ),
+        ));
+    }
+
+    match header.get("typ").and_then(Value::as_str) {
+        Some(typ) if !typ.eq_ignore_ascii_case("JWT") => {}
+        _ => findings.push(Finding::new(
+            "explicit-typ",
+            Some("3.11"),
+            Severity::Info,
+            "no specific typ (e.g. at+jwt); explicit typing keeps token kinds apart",
+        )),
+    }
+
+    findings
+}
+
+fn score(findings: &[Finding]) -> u32 {
+    let lost: u32 = findings.iter().map(|f| f.severity.penalty()).sum();
+    100u32.saturating_sub(lost)
+}
+
+fn format_report(cfg: OutputConfig, score: u32, findings: &[Finding], passed: &[&str]) -> String {
+    let mut lines = vec![format!("RFC 8725 score: {score}/100")];
+    for finding in findings {
+        let tone = match finding.severity {
+            Severity::Fail => Tone::Error,
+            Severity::Warn | Severity::Info => Tone::Warning,
+        };
+        let section = finding
+            .section
+            .map(|s| format!(" (§{s})"))
+            .unwrap_or_default();
+        lines.push(format!(
+            "{} {}{}: {}",
+            paint(cfg, tone, finding.severity.label()),
+            finding.rule,
+            section,
+            finding.message
+        ));
+    }
+    if !passed.is_empty() {
+        lines.push(format!("passed: {}", passed.join(", ")));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::JwtAlg;
+    use std::time::Duration;
+
+    fn args() -> LintArgs {
+        LintArgs {
+            rfc8725: true,
+            expect_alg: Some(JwtAlg::RS256),
+            max_lifetime: Duration::from_secs(3600),
+            cross_boundary: false,
+            min_score: None,
+            token: String::new(),
+        }
+    }
+
+    fn rules(findings: &[Finding]) -> Vec<&'static str> {
+        findings.iter().map(|f| f.rule).collect()
+    }
+
+    #[test]
+    fn clean_token_passes_every_rule() {
+        let header = json!({ "alg": "RS256", "typ": "at+jwt" });
+        let claims = json!({ "iss": "https://idp", "aud": "api", "iat": 1000, "exp": 1600 });
+        let findings = check_rfc8725(&args(), &header, &claims, 1000);
+        assert!(findings.is_empty(), "{findings:?}");
+        assert_eq!(score(&findings), 100);
+    }
+
+    #[test]
+    fn flags_none_long_lifetimes_and_compression() {
+        let header = json!({ "alg": "none", "zip": "DEF", "jku": "https://evil" });
+        let claims = json!({ "iat": 0, "exp": 86_400 });
+        let findings = check_rfc8725(&args(), &header, &claims, 0);
+        assert_eq!(
+            rules(&findings),
+            [
+                "alg-none",
+                "alg-pinned",
+                "lifetime",
+                "iss",
+                "aud",
+                "compression",
+                "untrusted-headers",
+                "explicit-typ"
+            ]
+        );
+        assert_eq!(score(&findings), 0);
+    }
+
+    #[test]
+    fn symmetric_algorithms_depend_on_trust_boundary() {
+        let header = json!({ "alg": "HS256", "typ": "at+jwt" });
+        let claims = json!({ "iss": "a", "aud": ["b", "c"], "exp": 10 });
+        let mut lint = args();
+        lint.expect_alg = Some(JwtAlg::HS256);
+        let findings = check_rfc8725(&lint, &header, &claims, 0);
+        assert_eq!(rules(&findings), ["symmetric"]);
+        assert_eq!(findings[0].severity, Severity::Warn);
+
+        lint.cross_boundary = true;
+        let findings = check_rfc8725(&lint, &header, &claims, 0);
+        assert_eq!(findings[0].severity, Severity::Fail);
+    }
+}
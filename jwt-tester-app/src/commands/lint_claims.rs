@@ -0,0 +1,110 @@
+use crate::claim_lint::{self, Diagnostic, LintOptions, Severity};
+use crate::claims::now_epoch;
+use crate::cli::LintClaimsArgs;
+use crate::error::{AppError, AppResult};
+use crate::io_utils::read_input;
+use crate::output::{emit_err, emit_ok, paint, CommandOutput, OutputConfig, Tone};
+use serde_json::json;
+use std::io::Read;
+
+pub fn run(args: LintClaimsArgs, cfg: OutputConfig) -> i32 {
+    let result = execute(&args, cfg).and_then(|out| {
+        let failed = out.data["ok"] == false;
+        emit_ok(cfg, out).map(|()| failed)
+    });
+    match result {
+        Ok(failed) => {
+            // Same exit code as `lint`, so an editor or CI can gate on errors.
+            if failed {
+                12
+            } else {
+                0
+            }
+        }
+        Err(err) => {
+            let code = err.exit_code();
+            emit_err(cfg, err);
+            code
+        }
+    }
+}
+
+/// Lints without printing; shared with `--rpc`. Errors in the claims are reported as
+/// diagnostics with `ok: false`, not as an `AppError`.
+pub(crate) fn execute(args: &LintClaimsArgs, cfg: OutputConfig) -> AppResult<CommandOutput> {
+    let (source, label) = read_source(&args.claims)?;
+    let diagnostics = claim_lint::lint_source(
+        &source,
+        LintOptions {
+            alg: args.alg,
+            profile: args.profile,
+            now: now_epoch(),
+        },
+    );
+    let count = |severity| {
+        diagnostics
+            .iter()
+            .filter(|d| d.issue.severity == severity)
+            .count()
+    };
+    let (errors, warnings) = (count(Severity::Error), count(Severity::Warning));
+    let data = json!({
+        "source": label,
+        "ok": errors == 0,
+        "errors": errors,
+        "warnings": warnings,
+        "diagnostics": diagnostics,
+    });
+    let text = format_report(cfg, &label, &diagnostics, errors, warnings);
+    Ok(CommandOutput::new(data, text))
+}
+
+/// Files and stdin are read untrimmed so line and column numbers match the editor's.
+fn read_source(spec: &str) -> AppResult<(String, String)> {
+    if let Some(path) = spec.strip_prefix('@') {
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| AppError::invalid_claims(format!("failed to read file {path}: {e}")))?;
+        return Ok((source, path.to_string()));
+    }
+    if spec == "-" {
+        let mut source = String::new();
+        std::io::stdin()
+            .read_to_string(&mut source)
+            .map_err(|e| AppError::invalid_claims(format!("failed to read stdin: {e}")))?;
+        return Ok((source, "<stdin>".to_string()));
+    }
+    Ok((read_input(spec)?, "<claims>".to_string()))
+}
+
+/// `file:line:column: severity: message [rule]`, the format editors and `grep -n` users know.
+fn format_report(
+    cfg: OutputConfig,
+    label: &str,
+    diagnostics: &[Diagnostic],
+    errors: usize,
+    warnings: usize,
+) -> String {
+    let mut lines: Vec<String> = diagnostics
+        .iter()
+        .map(|d| {
+            let (tone, severity) = match d.issue.severity {
+                Severity::Error => (Tone::Error, "error"),
+                Severity::Warning => (Tone::Warning, "warning"),
+            };
+            format!(
+                "{label}:{}:{}: {}: {} [{}]",
+                d.line,
+                d.column,
+                paint(cfg, tone, severity),
+                d.issue.message,
+                d.issue.rule
+            )
+        })
+        .collect();
+    if diagnostics.is_empty() {
+        lines.push(paint(cfg, Tone::Valid, "OK: no issues"));
+    } else {
+        lines.push(format!("{errors} error(s), {warnings} warning(s)"));
+    }
+    lines.join("\n")
+}
@@ -1,10 +1,28 @@
+pub mod advise;
+pub mod analyze;
+pub mod anonymize;
 pub mod completion;
 pub mod decode;
 pub mod encode;
+pub mod examples;
+pub mod fetch_token;
+pub mod history;
+pub mod init;
 pub mod inspect;
+pub mod lint;
+pub mod lint_claims;
+pub mod monitor;
+pub mod replay;
+pub mod rpc;
+#[cfg(feature = "self-update")]
+pub mod self_update;
+#[cfg(feature = "ui")]
+pub mod serve;
 pub mod split;
 pub mod vault;
+pub mod vault_access;
 pub mod verify;
+pub mod webhook;
 
-#[cfg(test)]
+#[cfg(all(test, feature = "vault"))]
 mod vault_tests;
@@ -0,0 +1,331 @@
+use crate::claims::now_epoch;
+use crate::cli::MonitorArgs;
+use crate::commands::vault::resolve_project_selector;
+use crate::date_utils::{format_timestamp, DateMode};
+use crate::error::{AppError, AppResult};
+use crate::history;
+use crate::http;
+use crate::io_utils::read_token_input;
+use crate::jwt_ops;
+use crate::notify;
+use crate::output::{emit_err, emit_ok, CommandOutput, OutputConfig};
+use crate::vault::{ProjectEntry, Vault, VaultConfig};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum TokenStatus {
+    Valid,
+    NoExpiry,
+    Expiring,
+    Expired,
+    Unreadable,
+}
+
+impl TokenStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            TokenStatus::Valid => "valid",
+            TokenStatus::NoExpiry => "no-expiry",
+            TokenStatus::Expiring => "expiring",
+            TokenStatus::Expired => "expired",
+            TokenStatus::Unreadable => "unreadable",
+        }
+    }
+
+    fn is_alert(self) -> bool {
+        matches!(
+            self,
+            TokenStatus::Expiring | TokenStatus::Expired | TokenStatus::Unreadable
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct TokenCheck {
+    source: String,
+    status: TokenStatus,
+    expires_at: Option<i64>,
+    expires_in: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+pub fn run(
+    no_persist: bool,
+    data_dir: Option<PathBuf>,
+    args: MonitorArgs,
+    cfg: OutputConfig,
+) -> i32 {
+    let result = (|| -> AppResult<i32> {
+        if args.tokens.is_empty() && args.project.is_none() {
+            return Err(AppError::invalid_token(
+                "nothing to monitor; pass token inputs and/or --project",
+            ));
+        }
+        let vault = match &args.project {
+            Some(_) => Some(
+                Vault::open(VaultConfig {
+                    no_persist,
+                    data_dir,
+                })
+                .map_err(|e| AppError::invalid_key(e.to_string()))?,
+            ),
+            None => None,
+        };
+        // An unknown project is a configuration error, so it is resolved once, up front.
+        let project = match (&vault, args.project.as_deref()) {
+            (Some(vault), Some(selector)) => {
+                Some((vault, resolve_project_selector(vault, selector)?))
+            }
+            _ => None,
+        };
+        let project = project.as_ref().map(|(vault, project)| (*vault, project));
+
+        if args.once {
+            let started = Instant::now();
+            let checked = check_once(&args, project, cfg);
+            notify::operation_finished("token check", started, checked.is_ok());
+            return checked;
+        }
+        watch(&args, project, cfg)
+    })();
+
+    match result {
+        Ok(code) => code,
+        Err(err) => {
+            let code = err.exit_code();
+            emit_err(cfg, err);
+            code
+        }
+    }
+}
+
+fn check_once(
+    args: &MonitorArgs,
+    project: Option<(&Vault, &ProjectEntry)>,
+    cfg: OutputConfig,
+) -> AppResult<i32> {
+    let checks = collect_checks(args, project)?;
+    let mut alerts = 0;
+    for check in checks.iter().filter(|check| check.status.is_alert()) {
+        alerts += 1;
+        deliver_alert(args, check);
+    }
+    let text = checks
+        .iter()
+        .map(format_check)
+        .collect::<Vec<_>>()
+        .join("\n");
+    emit_ok(
+        cfg,
+        CommandOutput::new(json!({ "alerts": alerts, "tokens": checks }), text)
+            .with_records("tokens"),
+    )?;
+    // Same exit code as a failed claim check, so scripts can gate on expiring tokens.
+    Ok(if alerts > 0 { 12 } else { 0 })
+}
+
+/// Runs until interrupted. Every status change is printed; changes into an alert state are
+/// delivered once, so a token that stays `expiring` does not alert on every interval. A check
+/// that fails (the vault is locked or briefly unreadable) is reported for that interval and the
+/// monitor keeps polling.
+fn watch(
+    args: &MonitorArgs,
+    project: Option<(&Vault, &ProjectEntry)>,
+    cfg: OutputConfig,
+) -> AppResult<i32> {
+    let mut last: HashMap<String, TokenStatus> = HashMap::new();
+    loop {
+        let checks = match collect_checks(args, project) {
+            Ok(checks) => checks,
+            Err(err) => {
+                emit_err(cfg, err);
+                Vec::new()
+            }
+        };
+        for check in checks {
+            if last.insert(check.source.clone(), check.status) == Some(check.status) {
+                continue;
+            }
+            if check.status.is_alert() {
+                deliver_alert(args, &check);
+            }
+            let text = format_check(&check);
+            emit_ok(cfg, CommandOutput::new(json!(check), text))?;
+        }
+        std::thread::sleep(args.interval);
+    }
+}
+
+fn collect_checks(
+    args: &MonitorArgs,
+    project: Option<(&Vault, &ProjectEntry)>,
+) -> AppResult<Vec<TokenCheck>> {
+    let now = now_epoch();
+    let warn_secs = i64::try_from(args.warn_before.as_secs()).unwrap_or(i64::MAX);
+    let mut checks: Vec<TokenCheck> = args
+        .tokens
+        .iter()
+        .map(|spec| {
+            let source = history::describe_token_spec(spec);
+            check_token(source, read_token_input(spec), now, warn_secs)
+        })
+        .collect();
+
+    if let Some((vault, project)) = project {
+        let entries = vault
+            .list_tokens(Some(&project.id))
+            .map_err(|e| AppError::invalid_key(e.to_string()))?;
+        for entry in entries {
+            let material = vault
+                .get_token_material(&entry.id)
+                .map_err(|e| AppError::invalid_key(e.to_string()));
+            let source = format!("vault:{}/{}", project.name, entry.name);
+            checks.push(check_token(source, material, now, warn_secs));
+        }
+    }
+    Ok(checks)
+}
+
+fn check_token(source: String, token: AppResult<String>, now: i64, warn_secs: i64) -> TokenCheck {
+    let exp = token
+        .and_then(|token| jwt_ops::decode_unverified(&token))
+        .and_then(|decoded| match decoded.payload_json.get("exp") {
+            None => Ok(None),
+            Some(value) => value
+                .as_i64()
+                .map(Some)
+                .ok_or_else(|| AppError::invalid_claims("exp is not an integer")),
+        });
+    match exp {
+        Ok(Some(exp)) => TokenCheck {
+            source,
+            status: classify(exp, now, warn_secs),
+            expires_at: Some(exp),
+            expires_in: Some(exp - now),
+            message: None,
+        },
+        Ok(None) => TokenCheck {
+            source,
+            status: TokenStatus::NoExpiry,
+            expires_at: None,
+            expires_in: None,
+            message: None,
+        },
+        Err(err) => TokenCheck {
+            source,
+            status: TokenStatus::Unreadable,
+            expires_at: None,
+            expires_in: None,
+            message: Some(err.to_string()),
+        },
+    }
+}
+
+fn classify(exp: i64, now: i64, warn_secs: i64) -> TokenStatus {
+    let remaining = exp - now;
+    if remaining <= 0 {
+        TokenStatus::Expired
+    } else if remaining <= warn_secs {
+        TokenStatus::Expiring
+    } else {
+        TokenStatus::Valid
+    }
+}
+
+fn format_check(check: &TokenCheck) -> String {
+    let detail = match (check.expires_at, check.expires_in, &check.message) {
+        (Some(at), Some(remaining), _) => {
+            let at = format_timestamp(at, DateMode::Utc).unwrap_or_else(|_| at.to_string());
+            let span = humantime::format_duration(Duration::from_secs(remaining.unsigned_abs()));
+            if remaining > 0 {
+                format!("expires {at} (in {span})")
+            } else {
+                format!("expired {at} ({span} ago)")
+            }
+        }
+        (_, _, Some(message)) => message.clone(),
+        _ => "no exp claim".to_string(),
+    };
+    format!(
+        "{:<10}  {}  {}",
+        check.status.as_str(),
+        check.source,
+        detail
+    )
+}
+
+/// Delivers an alert to every configured sink. Failures are logged and never stop the monitor.
+fn deliver_alert(args: &MonitorArgs, check: &TokenCheck) {
+    if let Some(command) = &args.exec {
+        match run_hook(command, check) {
+            Ok(status) if !status.success() => warn!("monitor hook exited with {status}"),
+            Ok(_) => {}
+            Err(err) => warn!("failed to run monitor hook: {err}"),
+        }
+    }
+    if let Some(url) = &args.webhook {
+        let event = json!({ "event": format!("token-{}", check.status.as_str()), "token": check });
+        if let Err(err) = http::post_json(url, &event) {
+            warn!("{err}");
+        }
+    }
+    if args.notify {
+        if let Err(err) = notify::desktop("jwt-tester", &format_check(check)) {
+            warn!("failed to show desktop notification: {err}");
+        }
+    }
+}
+
+fn run_hook(command: &str, check: &TokenCheck) -> std::io::Result<std::process::ExitStatus> {
+    let mut hook = if cfg!(windows) {
+        let mut hook = Command::new("cmd");
+        hook.args(["/C", command]);
+        hook
+    } else {
+        let mut hook = Command::new("sh");
+        hook.args(["-c", command]);
+        hook
+    };
+    let optional = |value: Option<i64>| value.map(|v| v.to_string()).unwrap_or_default();
+    hook.env("JWT_TESTER_MONITOR_SOURCE", &check.source)
+        .env("JWT_TESTER_MONITOR_STATUS", check.status.as_str())
+        .env("JWT_TESTER_MONITOR_EXPIRES_AT", optional(check.expires_at))
+        .env("JWT_TESTER_MONITOR_EXPIRES_IN", optional(check.expires_in))
+        // Keep stdout for the monitor's own (possibly JSON) output.
+        .stdout(Stdio::from(std::io::stderr()))
+        .status()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_token, classify, TokenStatus};
+    use crate::error::AppError;
+
+    #[test]
+    fn classify_uses_warning_window() {
+        assert_eq!(classify(1_000, 0, 3_600), TokenStatus::Expiring);
+        assert_eq!(classify(10_000, 0, 3_600), TokenStatus::Valid);
+        assert_eq!(classify(1_000, 1_000, 3_600), TokenStatus::Expired);
+    }
+
+    #[test]
+    fn check_token_reports_unreadable_inputs() {
+        let check = check_token(
+            "@missing".to_string(),
+            Err(AppError::invalid_token("failed to read")),
+            0,
+            60,
+        );
+        assert_eq!(check.status, TokenStatus::Unreadable);
+        assert!(check.status.is_alert());
+        assert_eq!(check.message.as_deref(), Some("failed to read"));
+    }
+}
@@ -0,0 +1,378 @@
+use crate::cli::ReplayArgs;
+use crate::error::{AppError, AppResult};
+use crate::notify;
+use crate::output::{
+    emit_err, emit_ok, emit_record, paint, CommandOutput, OutputConfig, Progress, Tone,
+    CANCELLED_EXIT_CODE,
+};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::Instant;
+
+/// Re-runs each recorded `/api/jwt/*` call as the equivalent CLI command (a child process of this
+/// binary against the same vault) and compares the outcome with what the UI got.
+pub fn run(
+    no_persist: bool,
+    data_dir: Option<PathBuf>,
+    args: ReplayArgs,
+    cfg: OutputConfig,
+) -> i32 {
+    let started = Instant::now();
+    let result = (|| -> AppResult<(CommandOutput, i32)> {
+        let entries = load_session(&args)?;
+        let exe = std::env::current_exe()
+            .map_err(|e| AppError::internal(format!("failed to locate jwt-tester: {e}")))?;
+        let mut global = vec!["--json".to_string()];
+        if no_persist {
+            global.push("--no-persist".to_string());
+        }
+        if let Some(dir) = &data_dir {
+            global.push(format!("--data-dir={}", dir.display()));
+        }
+
+        let progress = Progress::items(entries.len(), "replaying");
+        let mut results = Vec::new();
+        for (number, entry) in entries {
+            if progress.cancelled() {
+                break;
+            }
+            let endpoint = entry["endpoint"].as_str().unwrap_or_default();
+            let recorded = recorded_outcome(&entry);
+            let mut result = json!({
+                "entry": number,
+                "endpoint": endpoint,
+                "recorded": recorded,
+            });
+            match cli_args(endpoint, &entry["request"]) {
+                Ok(cli) => {
+                    let output = Command::new(&exe)
+                        .args(&global)
+                        .args(&cli)
+                        .output()
+                        .map_err(|e| {
+                            AppError::internal(format!("failed to run jwt-tester: {e}"))
+                        })?;
+                    if progress.cancelled() {
+                        // Ctrl+C reached the child too; its outcome says nothing about the entry.
+                        break;
+                    }
+                    let body: Value = serde_json::from_slice(&output.stdout).unwrap_or(Value::Null);
+                    let replayed = replayed_outcome(&body);
+                    result["command"] = json!(display_command(&cli));
+                    result["matched"] = json!(replayed == recorded);
+                    result["replayed"] = json!(replayed);
+                    result["exit_code"] = json!(output.status.code());
+                    if let Some(message) = body["error"]["message"].as_str() {
+                        result["message"] = json!(message);
+                    }
+                }
+                Err(err) => {
+                    result["matched"] = json!(false);
+                    result["replayed"] = Value::Null;
+                    result["message"] = json!(err.to_string());
+                }
+            }
+            emit_record(cfg, &result);
+            results.push(result);
+            progress.inc();
+        }
+        let cancelled = progress.cancelled();
+        drop(progress);
+
+        let mismatched = results.iter().filter(|r| r["matched"] != true).count();
+        let mut text = format_report(cfg, &results, mismatched);
+        if cancelled {
+            text.push_str("\ncancelled: remaining entries were not replayed");
+        }
+        let mut data = json!({
+            "session": args.session,
+            "replayed": results.len(),
+            "matched": results.len() - mismatched,
+            "mismatched": mismatched,
+            "cancelled": cancelled,
+            "entries": results,
+        });
+        if cfg.json_lines() {
+            // Already printed one per line as each entry finished.
+            data.as_object_mut().map(|data| data.remove("entries"));
+        }
+        // A mismatch is a failed check, like `lint` below its threshold.
+        let code = if cancelled {
+            CANCELLED_EXIT_CODE
+        } else if mismatched > 0 {
+            12
+        } else {
+            0
+        };
+        Ok((CommandOutput::new(data, text), code))
+    })();
+    notify::operation_finished("session replay", started, matches!(result, Ok((_, 0))));
+
+    match result.and_then(|(out, code)| emit_ok(cfg, out).map(|()| code)) {
+        Ok(code) => code,
+        Err(err) => {
+            let code = err.exit_code();
+            emit_err(cfg, err);
+            code
+        }
+    }
+}
+
+/// Numbered (1-based) session entries, narrowed to `--entry` when given.
+fn load_session(args: &ReplayArgs) -> AppResult<Vec<(usize, Value)>> {
+    let raw = std::fs::read_to_string(&args.session).map_err(|e| {
+        AppError::invalid_claims(format!(
+            "failed to read session file {}: {e}",
+            args.session.display()
+        ))
+    })?;
+    let mut entries = Vec::new();
+    for (index, line) in raw.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry: Value = serde_json::from_str(line).map_err(|e| {
+            AppError::invalid_claims(format!("session line {}: invalid JSON: {e}", index + 1))
+        })?;
+        entries.push((entries.len() + 1, entry));
+    }
+    if let Some(wanted) = args.entry {
+        let total = entries.len();
+        entries.retain(|(number, _)| *number == wanted);
+        if entries.is_empty() {
+            return Err(AppError::not_found(format!(
+                "entry {wanted} not found; the session has {total} entries"
+            )));
+        }
+    }
+    Ok(entries)
+}
+
+/// `ok`, or the error code the UI answered with.
+fn recorded_outcome(entry: &Value) -> String {
+    let status = entry["status"].as_u64().unwrap_or_default();
+    if (200..300).contains(&status) {
+        return "ok".to_string();
+    }
+    entry["response"]["code"]
+        .as_str()
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("HTTP {status}"))
+}
+
+fn replayed_outcome(body: &Value) -> String {
+    if body["ok"] == true {
+        return "ok".to_string();
+    }
+    body["error"]["code"]
+        .as_str()
+        .unwrap_or("NO_OUTPUT")
+        .to_string()
+}
+
+/// The CLI arguments that mirror one UI request. Values use `--flag=value` so relative times
+/// such as `-5m` are not taken for flags.
+fn cli_args(endpoint: &str, request: &Value) -> AppResult<Vec<String>> {
+    let mut args = vec![endpoint.to_string()];
+    let text = |args: &mut Vec<String>, field: &str| {
+        if let Some(value) = scalar(&request[field]) {
+            args.push(format!("--{}={value}", field.replace('_', "-")));
+        }
+    };
+    let switch = |args: &mut Vec<String>, field: &str| {
+        if request[field] == true {
+            args.push(format!("--{}", field.replace('_', "-")));
+        }
+    };
+    let list = |args: &mut Vec<String>, field: &str| {
+        for value in request[field].as_array().into_iter().flatten() {
+            if let Some(value) = scalar(value) {
+                args.push(format!("--{field}={value}"));
+            }
+        }
+    };
+    let token = || {
+        request["token"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| AppError::invalid_token("recorded request has no token"))
+    };
+
+    match endpoint {
+        "encode" => {
+            for field in ["project", "key_id", "key_name", "key_fingerprint"] {
+                text(&mut args, field);
+            }
+            let alg = request["alg"]
+                .as_str()
+                .ok_or_else(|| AppError::invalid_key("recorded request has no alg"))?;
+            args.push(format!("--alg={}", alg.trim().to_lowercase()));
+            for field in ["kid", "typ", "iss", "sub", "jti", "iat", "nbf", "exp"] {
+                text(&mut args, field);
+            }
+            list(&mut args, "aud");
+            switch(&mut args, "no_typ");
+            switch(&mut args, "no_iat");
+            if let Some(claims) = request["claims"].as_str().filter(|c| !c.trim().is_empty()) {
+                args.push("--".to_string());
+                args.push(claims.to_string());
+            }
+        }
+        "verify" => {
+            for field in ["project", "key_id", "key_name", "key_fingerprint"] {
+                text(&mut args, field);
+            }
+            match request["alg"].as_str().map(str::trim) {
+                None | Some("") => {}
+                Some(alg) if alg.eq_ignore_ascii_case("auto") => {}
+                Some(alg) => args.push(format!("--alg={}", alg.to_lowercase())),
+            }
+            for field in ["leeway_secs", "iss", "sub"] {
+                text(&mut args, field);
+            }
+            list(&mut args, "aud");
+            list(&mut args, "require");
+            for field in ["try_all_keys", "ignore_exp", "explain"] {
+                switch(&mut args, field);
+            }
+            args.push("--".to_string());
+            args.push(token()?);
+        }
+        "inspect" => {
+            text(&mut args, "date");
+            switch(&mut args, "show_segments");
+            args.push("--".to_string());
+            args.push(token()?);
+        }
+        other => {
+            return Err(AppError::invalid_claims(format!(
+                "cannot replay unknown endpoint '{other}'"
+            )))
+        }
+    }
+    Ok(args)
+}
+
+fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => Some(text.clone()),
+        Value::Number(number) => Some(number.to_string()),
+        Value::Bool(flag) => Some(flag.to_string()),
+        _ => None,
+    }
+}
+
+/// A copy-pasteable command line for the terminal.
+pub(crate) fn display_command(args: &[String]) -> String {
+    let mut line = "jwt-tester".to_string();
+    for arg in args {
+        line.push(' ');
+        line.push_str(&shell_quote(arg));
+    }
+    line
+}
+
+fn shell_quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=@+,%".contains(c));
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+fn format_report(cfg: OutputConfig, results: &[Value], mismatched: usize) -> String {
+    let mut lines = Vec::new();
+    for result in results {
+        let recorded = result["recorded"].as_str().unwrap_or_default();
+        let replayed = result["replayed"].as_str().unwrap_or("not replayed");
+        let (tone, verdict) = if result["matched"] == true {
+            (Tone::Valid, "match")
+        } else {
+            (Tone::Error, "MISMATCH")
+        };
+        lines.push(format!(
+            "#{} {}: UI {recorded}, CLI {replayed} [{}]",
+            result["entry"],
+            result["endpoint"].as_str().unwrap_or_default(),
+            paint(cfg, tone, verdict)
+        ));
+        if let Some(command) = result["command"].as_str() {
+            lines.push(format!("  {command}"));
+        }
+        if result["matched"] != true {
+            if let Some(message) = result["message"].as_str() {
+                lines.push(format!("  {message}"));
+            }
+        }
+    }
+    lines.push(format!(
+        "{} of {} entries matched",
+        results.len() - mismatched,
+        results.len()
+    ));
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_ui_requests_to_cli_arguments() {
+        let encode = json!({
+            "project": "demo",
+            "key_name": "signing",
+            "key_id": null,
+            "alg": "HS256",
+            "aud": ["api", "web"],
+            "exp": "-5m",
+            "no_typ": true,
+            "no_iat": false,
+            "claims": "{\"sub\":\"alice\"}",
+        });
+        assert_eq!(
+            cli_args("encode", &encode).unwrap(),
+            [
+                "encode",
+                "--project=demo",
+                "--key-name=signing",
+                "--alg=hs256",
+                "--exp=-5m",
+                "--aud=api",
+                "--aud=web",
+                "--no-typ",
+                "--",
+                "{\"sub\":\"alice\"}",
+            ]
+        );
+
+        let verify = json!({ "project": "demo", "alg": "auto", "token": "a.b.c", "leeway_secs": 0, "explain": true });
+        assert_eq!(
+            cli_args("verify", &verify).unwrap(),
+            [
+                "verify",
+                "--project=demo",
+                "--leeway-secs=0",
+                "--explain",
+                "--",
+                "a.b.c"
+            ]
+        );
+        assert!(cli_args("inspect", &json!({})).is_err());
+        assert!(cli_args("vault", &json!({})).is_err());
+    }
+
+    #[test]
+    fn quotes_display_commands_for_the_shell() {
+        let args = ["encode", "--iss=https://idp", "--", "{\"sub\":\"o'neil\"}"].map(String::from);
+        assert_eq!(
+            display_command(&args),
+            r#"jwt-tester encode --iss=https://idp -- '{"sub":"o'\''neil"}'"#
+        );
+    }
+}
@@ -0,0 +1,305 @@
+use crate::cli::{App, Command, JwksCmd, KeyCmd, ProjectCmd, TokenCmd, VaultCmd, VaultWebhookCmd};
+use crate::commands::vault_access::VaultAccess;
+use crate::commands::{decode, encode, lint_claims, vault, verify};
+use crate::error::{AppError, AppResult};
+use crate::output::{emit_err, OutputConfig, OutputMode};
+use crate::vault::{Vault, VaultConfig};
+use crate::workspace::{self, Loaded};
+use clap::FromArgMatches;
+use serde_json::{json, Value};
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+use tracing::warn;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// Every `AppError`; `error.data` carries its code and the exit code the CLI would have used.
+const COMMAND_FAILED: i64 = -32000;
+
+const METHODS: &str = "decode, verify, encode, lint-claims, vault";
+
+/// A JSON-RPC error object, before it is wrapped into a response.
+#[derive(Debug)]
+struct RpcError {
+    code: i64,
+    message: String,
+    data: Option<Value>,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        RpcError {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        let mut error = json!({ "code": self.code, "message": self.message });
+        if let Some(data) = &self.data {
+            error["data"] = data.clone();
+        }
+        error
+    }
+}
+
+impl From<AppError> for RpcError {
+    fn from(err: AppError) -> Self {
+        let mut data = json!({ "code": err.code(), "exit_code": err.exit_code() });
+        if let Some(details) = &err.details {
+            data["details"] = details.clone();
+        }
+        RpcError {
+            code: COMMAND_FAILED,
+            message: err.message,
+            data: Some(data),
+        }
+    }
+}
+
+/// Serves newline-delimited JSON-RPC 2.0 on stdin/stdout until stdin closes. Each request's
+/// `params` are the arguments of the matching subcommand, so requests accept exactly what the
+/// command line does, workspace defaults included. The vault is opened once up front.
+pub fn run(
+    no_persist: bool,
+    data_dir: Option<PathBuf>,
+    loaded: Option<&Loaded>,
+    cfg: OutputConfig,
+) -> i32 {
+    let vault = match Vault::open(VaultConfig {
+        no_persist,
+        data_dir,
+    }) {
+        Ok(vault) => vault,
+        Err(e) => {
+            let err = AppError::invalid_key(e.to_string());
+            let code = err.exit_code();
+            emit_err(cfg, err);
+            return code;
+        }
+    };
+    let server = Server {
+        vault,
+        loaded,
+        clap: workspace::cli_command(loaded),
+        // Results are returned as JSON data, so text rendering is never colored or templated.
+        cfg: OutputConfig {
+            mode: OutputMode::Json,
+            template: None,
+            summary: None,
+            sinks: None,
+            ..cfg
+        },
+    };
+    match server.serve(std::io::stdin().lock(), std::io::stdout().lock()) {
+        Ok(()) => 0,
+        // stdout is the response stream, so the failure can only be reported on stderr.
+        Err(err) => {
+            warn!("rpc: {err}");
+            err.exit_code()
+        }
+    }
+}
+
+struct Server<'a> {
+    vault: Vault,
+    loaded: Option<&'a Loaded>,
+    clap: clap::Command,
+    cfg: OutputConfig,
+}
+
+impl Server<'_> {
+    fn serve(mut self, input: impl BufRead, mut output: impl Write) -> AppResult<()> {
+        for line in input.lines() {
+            let line =
+                line.map_err(|e| AppError::internal(format!("failed to read stdin: {e}")))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let Some(response) = self.handle_line(&line) else {
+                continue;
+            };
+            writeln!(output, "{response}")
+                .and_then(|()| output.flush())
+                .map_err(|e| AppError::internal(format!("failed to write response: {e}")))?;
+        }
+        Ok(())
+    }
+
+    /// The response line for one request, or `None` for a notification (no `id`).
+    fn handle_line(&mut self, line: &str) -> Option<Value> {
+        let request: Value = match serde_json::from_str(line) {
+            Ok(request) => request,
+            Err(e) => {
+                let err = RpcError::new(PARSE_ERROR, format!("invalid JSON: {e}"));
+                return Some(response(Value::Null, Err(err)));
+            }
+        };
+        let id = request.get("id").cloned();
+        let result = self.dispatch(&request);
+        match id {
+            Some(id) => Some(response(id, result)),
+            None if request.is_object() => None,
+            None => Some(response(Value::Null, result)),
+        }
+    }
+
+    fn dispatch(&mut self, request: &Value) -> Result<Value, RpcError> {
+        let Some(method) = request.get("method").and_then(Value::as_str) else {
+            return Err(RpcError::new(
+                INVALID_REQUEST,
+                "expected an object with a string \"method\"",
+            ));
+        };
+        if request.get("jsonrpc").and_then(Value::as_str) != Some("2.0") {
+            return Err(RpcError::new(
+                INVALID_REQUEST,
+                "\"jsonrpc\" must be \"2.0\"",
+            ));
+        }
+        if !matches!(
+            method,
+            "decode" | "verify" | "encode" | "lint-claims" | "vault"
+        ) {
+            return Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("unknown method {method:?}; available: {METHODS}"),
+            ));
+        }
+        let args = params(request.get("params"))?;
+        let mut command = self.parse(method, &args)?;
+        if let Some(loaded) = self.loaded {
+            workspace::apply(&mut command, loaded);
+        }
+        self.execute(command)
+    }
+
+    fn parse(&mut self, method: &str, args: &[String]) -> Result<Command, RpcError> {
+        let argv = ["jwt-tester", method]
+            .into_iter()
+            .chain(args.iter().map(String::as_str));
+        let app = self
+            .clap
+            .try_get_matches_from_mut(argv)
+            .and_then(|mut matches| App::from_arg_matches_mut(&mut matches))
+            .map_err(|err| RpcError::new(INVALID_PARAMS, clap_message(&err)))?;
+        app.command
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, "missing command"))
+    }
+
+    fn execute(&self, command: Command) -> Result<Value, RpcError> {
+        let access = VaultAccess::Shared(&self.vault);
+        let out = match command {
+            Command::Decode(args) if args.sandbox => {
+                return Err(RpcError::new(
+                    INVALID_PARAMS,
+                    "--sandbox locks down the whole process and is not available over --rpc",
+                ))
+            }
+            Command::Decode(args) => decode::execute(access, &args, self.cfg)?,
+            Command::Verify(args) => verify::execute(access, &args, self.cfg)?,
+            Command::Encode(args) => encode::execute(access, &args)?,
+            Command::LintClaims(args) => lint_claims::execute(&args, self.cfg)?,
+            Command::Vault(args) if is_query(&args.cmd) => vault::execute(&self.vault, args)?,
+            Command::Vault(_) => {
+                return Err(RpcError::new(
+                    INVALID_PARAMS,
+                    "only vault queries are served over --rpc: project list|show, key list, \
+                     token list, jwks list|show, search and stats",
+                ))
+            }
+            _ => return Err(RpcError::new(METHOD_NOT_FOUND, "method was not checked")),
+        };
+        Ok(out.data)
+    }
+}
+
+fn response(id: Value, result: Result<Value, RpcError>) -> Value {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(err) => json!({ "jsonrpc": "2.0", "id": id, "error": err.to_json() }),
+    }
+}
+
+/// `params` is the argument list after the subcommand name, e.g. `["--secret", "env:S", token]`.
+fn params(params: Option<&Value>) -> Result<Vec<String>, RpcError> {
+    let invalid = || {
+        RpcError::new(
+            INVALID_PARAMS,
+            "params must be an array of command-line arguments (strings)",
+        )
+    };
+    let Some(params) = params else {
+        return Ok(Vec::new());
+    };
+    let args = params
+        .as_array()
+        .ok_or_else(invalid)?
+        .iter()
+        .map(|arg| arg.as_str().map(str::to_string).ok_or_else(invalid))
+        .collect::<Result<Vec<_>, _>>()?;
+    if let Some(arg) = args.iter().find(|arg| reads_stdin(arg)) {
+        return Err(RpcError::new(
+            INVALID_PARAMS,
+            format!(
+                "{arg:?} would read the terminal or stdin, which carries RPC requests; \
+                 pass the value itself, '@file', 'fd:N' (N > 2) or 'env:NAME'"
+            ),
+        ));
+    }
+    Ok(args)
+}
+
+/// Input specs that would block on, or consume, the request stream.
+pub(crate) fn reads_stdin(arg: &str) -> bool {
+    let value = arg
+        .strip_prefix("--")
+        .and_then(|flag| flag.split_once('='))
+        .map_or(arg, |(_, value)| value);
+    value == "-" || value == "fd:0" || value == "prompt" || value.starts_with("prompt:")
+}
+
+/// Read-only vault commands; anything that writes, prompts or exports stays on the CLI.
+pub(crate) fn is_query(cmd: &VaultCmd) -> bool {
+    matches!(
+        cmd,
+        VaultCmd::Project(ProjectCmd::List { .. } | ProjectCmd::Show { .. })
+            | VaultCmd::Key(KeyCmd::List { .. })
+            | VaultCmd::Token(TokenCmd::List { .. } | TokenCmd::Revoked { .. })
+            | VaultCmd::Jwks(JwksCmd::List { .. } | JwksCmd::Show { .. })
+            | VaultCmd::Webhook(VaultWebhookCmd::List { .. })
+            | VaultCmd::Search { .. }
+            | VaultCmd::Stats
+            | VaultCmd::Audit { .. }
+    )
+}
+
+/// Clap's rendered error without the usage block and trailing newline.
+pub(crate) fn clap_message(err: &clap::Error) -> String {
+    let rendered = err.render().to_string();
+    let first = rendered.lines().next().unwrap_or_default();
+    first.strip_prefix("error: ").unwrap_or(first).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn params_reject_inputs_that_read_stdin() {
+        assert!(params(Some(&json!(["--secret", "env:S", "tok"]))).is_ok());
+        assert!(params(None).unwrap().is_empty());
+        for arg in ["-", "--secret=-", "fd:0", "prompt", "prompt:Secret"] {
+            let err = params(Some(&json!(["--secret", arg]))).unwrap_err();
+            assert_eq!(err.code, INVALID_PARAMS, "{arg}");
+        }
+        assert!(params(Some(&json!(["fd:3"]))).is_ok());
+        assert_eq!(
+            params(Some(&json!({ "token": "x" }))).unwrap_err().code,
+            INVALID_PARAMS
+        );
+    }
+}
@@ -0,0 +1,358 @@
+use crate::cli::SelfUpdateArgs;
+use crate::error::{AppError, AppResult};
+use crate::http;
+use crate::io_utils::read_input;
+use crate::output::{emit_err, emit_ok, CommandOutput, OutputConfig};
+use minisign_verify::{PublicKey, Signature};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+
+const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Release signing key baked in by release builds; `--public-key` overrides it.
+const RELEASE_PUBLIC_KEY: Option<&str> = option_env!("JWT_TESTER_RELEASE_PUBKEY");
+const MAX_BINARY_BYTES: u64 = 256 * 1024 * 1024;
+const MAX_SIGNATURE_BYTES: u64 = 16 * 1024;
+
+#[derive(Debug)]
+struct Release {
+    tag: String,
+    binary_url: String,
+    signature_url: String,
+    asset: String,
+}
+
+pub fn run(args: SelfUpdateArgs, cfg: OutputConfig) -> i32 {
+    let result = self_update(&args);
+
+    match result.and_then(|out| emit_ok(cfg, out)) {
+        Ok(()) => 0,
+        Err(err) => {
+            let code = err.exit_code();
+            emit_err(cfg, err);
+            code
+        }
+    }
+}
+
+fn self_update(args: &SelfUpdateArgs) -> AppResult<CommandOutput> {
+    let exe = std::env::current_exe()
+        .and_then(|path| path.canonicalize())
+        .map_err(|e| AppError::internal(format!("failed to locate the running binary: {e}")))?;
+    let asset = asset_name(&exe)?;
+    let release = find_release(args, &asset)?;
+    let newer = is_newer(&release.tag, CURRENT_VERSION)?;
+
+    let mut data = json!({
+        "current": CURRENT_VERSION,
+        "latest": release.tag,
+        "asset": release.asset,
+        "update_available": newer,
+        "updated": false,
+    });
+    if args.check || (!newer && !args.force) {
+        let text = if newer {
+            format!("update available: {CURRENT_VERSION} -> {}", release.tag)
+        } else {
+            format!("jwt-tester {CURRENT_VERSION} is up to date")
+        };
+        return Ok(CommandOutput::new(data, text));
+    }
+
+    let public_key = load_public_key(args.public_key.as_deref())?;
+    let binary = http::download(&release.binary_url, MAX_BINARY_BYTES)?;
+    let signature = http::download(&release.signature_url, MAX_SIGNATURE_BYTES)?;
+    verify_signature(&public_key, &binary, &signature, &release)?;
+    replace_binary(&exe, &binary)?;
+
+    data["updated"] = json!(true);
+    data["path"] = json!(exe.display().to_string());
+    let text = format!(
+        "updated {} from {CURRENT_VERSION} to {}",
+        exe.display(),
+        release.tag
+    );
+    Ok(CommandOutput::new(data, text))
+}
+
+/// Release assets are raw binaries named `<binary>-<os>-<arch>[.exe]`, e.g.
+/// `jwt-tester-linux-x86_64`, next to a minisign signature `<asset>.minisig`.
+fn asset_name(exe: &Path) -> AppResult<String> {
+    let stem = exe
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .ok_or_else(|| AppError::internal(format!("unexpected binary path {exe:?}")))?;
+    Ok(format!(
+        "{stem}-{}-{}{}",
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        std::env::consts::EXE_SUFFIX
+    ))
+}
+
+fn find_release(args: &SelfUpdateArgs, asset: &str) -> AppResult<Release> {
+    let api = args.api_url.trim_end_matches('/');
+    let url = match &args.tag {
+        Some(tag) => format!("{api}/repos/{}/releases/tags/{tag}", args.repo),
+        None => format!("{api}/repos/{}/releases/latest", args.repo),
+    };
+    let body = http::get_json(&url, "application/vnd.github+json")?;
+    parse_release(&body, asset)
+}
+
+fn parse_release(body: &Value, asset: &str) -> AppResult<Release> {
+    let tag = body
+        .get("tag_name")
+        .and_then(Value::as_str)
+        .ok_or_else(|| AppError::internal("release response has no tag_name"))?;
+    let download_url = |name: &str| {
+        body.get("assets")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .find(|entry| entry.get("name").and_then(Value::as_str) == Some(name))
+            .and_then(|entry| entry.get("browser_download_url"))
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    };
+    let binary_url = download_url(asset)
+        .ok_or_else(|| AppError::internal(format!("release {tag} has no asset named {asset}")))?;
+    let signature_url = download_url(&format!("{asset}.minisig")).ok_or_else(|| {
+        AppError::invalid_signature(format!(
+            "release {tag} has no signature ({asset}.minisig); refusing to update"
+        ))
+    })?;
+    Ok(Release {
+        tag: tag.to_string(),
+        binary_url,
+        signature_url,
+        asset: asset.to_string(),
+    })
+}
+
+/// Compares `major.minor.patch`, ignoring a leading `v` and any pre-release/build suffix.
+fn is_newer(tag: &str, current: &str) -> AppResult<bool> {
+    Ok(parse_version(tag)? > parse_version(current)?)
+}
+
+fn parse_version(raw: &str) -> AppResult<(u64, u64, u64)> {
+    let core = raw
+        .trim()
+        .trim_start_matches('v')
+        .split(['-', '+'])
+        .next()
+        .unwrap_or_default();
+    let parts: Vec<u64> = core
+        .split('.')
+        .map(str::parse)
+        .collect::<Result<_, _>>()
+        .map_err(|_| AppError::internal(format!("unrecognized version {raw:?}")))?;
+    match parts.as_slice() {
+        [major, minor, patch] => Ok((*major, *minor, *patch)),
+        _ => Err(AppError::internal(format!("unrecognized version {raw:?}"))),
+    }
+}
+
+/// Accepts the base64 key line or a whole `minisign.pub` file (inline, @file, env:NAME).
+fn load_public_key(spec: Option<&str>) -> AppResult<PublicKey> {
+    let raw = match spec {
+        Some(spec) => read_input(spec)?,
+        None => RELEASE_PUBLIC_KEY.map(str::to_string).ok_or_else(|| {
+            AppError::invalid_key(
+                "this build has no release signing key; pass --public-key with the minisign key",
+            )
+        })?,
+    };
+    let line = raw
+        .lines()
+        .map(str::trim)
+        .rfind(|line| !line.is_empty() && !line.starts_with("untrusted comment:"))
+        .unwrap_or_default();
+    PublicKey::from_base64(line)
+        .map_err(|e| AppError::invalid_key(format!("invalid minisign public key: {e}")))
+}
+
+/// Only pre-hashed signatures (the minisign default) are accepted, and the signed trusted
+/// comment must name the release being installed (see `check_trusted_comment`).
+fn verify_signature(
+    public_key: &PublicKey,
+    binary: &[u8],
+    signature: &[u8],
+    release: &Release,
+) -> AppResult<()> {
+    let signature = std::str::from_utf8(signature)
+        .map_err(|_| AppError::invalid_signature("release signature is not a minisign file"))
+        .and_then(|text| {
+            Signature::decode(text)
+                .map_err(|e| AppError::invalid_signature(format!("invalid release signature: {e}")))
+        })?;
+    public_key
+        .verify(binary, &signature, false)
+        .map_err(|e| AppError::invalid_signature(format!("release signature check failed: {e}")))?;
+    check_trusted_comment(signature.trusted_comment(), release)
+}
+
+/// The trusted comment is covered by the signature, so it ties the binary to one tag and asset
+/// (`minisign -S -t "file:<asset> tag:<tag>"`). Without it a validly signed older binary could be
+/// served under a newer tag, or one platform's binary under another's name.
+fn check_trusted_comment(comment: &str, release: &Release) -> AppResult<()> {
+    let field = |name: &str| {
+        comment
+            .split_whitespace()
+            .find_map(|part| part.strip_prefix(name))
+    };
+    for (name, expected) in [("file:", &release.asset), ("tag:", &release.tag)] {
+        match field(name) {
+            Some(signed) if signed == expected => {}
+            Some(signed) => {
+                return Err(AppError::invalid_signature(format!(
+                    "release signature is for {name}{signed}, not {name}{expected}; \
+                     refusing to update"
+                )))
+            }
+            None => {
+                return Err(AppError::invalid_signature(format!(
+                    "release signature has no {name} in its trusted comment; refusing to update"
+                )))
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Swaps in the new binary, then runs it once; if it does not start, the previous binary is
+/// put back so a broken download never leaves the machine without a working tool.
+fn replace_binary(exe: &Path, binary: &[u8]) -> AppResult<()> {
+    let staged = sibling(exe, "update");
+    let backup = sibling(exe, "old");
+    let io_err = |what: &str, e: std::io::Error| AppError::internal(format!("{what}: {e}"));
+
+    std::fs::write(&staged, binary).map_err(|e| io_err("failed to stage the update", e))?;
+    make_executable(&staged).map_err(|e| io_err("failed to mark the update executable", e))?;
+    std::fs::copy(exe, &backup).map_err(|e| io_err("failed to back up the current binary", e))?;
+
+    let outcome = self_replace::self_replace(&staged)
+        .map_err(|e| io_err("failed to replace the binary", e))
+        .and_then(|()| smoke_test(exe));
+    let _ = std::fs::remove_file(&staged);
+    if let Err(err) = outcome {
+        // The running image is the old binary, so the path only holds the new file now and a
+        // rename puts the backup back (self_replace would resolve the path of the old image).
+        return match std::fs::rename(&backup, exe) {
+            Ok(()) => Err(AppError::internal(format!(
+                "{err}; restored the previous version"
+            ))),
+            Err(e) => Err(AppError::internal(format!(
+                "{err}; rollback failed ({e}), the previous binary is at {}",
+                backup.display()
+            ))),
+        };
+    }
+    let _ = std::fs::remove_file(&backup);
+    Ok(())
+}
+
+fn smoke_test(exe: &Path) -> AppResult<()> {
+    let output = std::process::Command::new(exe)
+        .arg("--version")
+        .output()
+        .map_err(|e| AppError::internal(format!("the new binary failed to start: {e}")))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(AppError::internal(format!(
+            "the new binary failed to start ({})",
+            output.status
+        )))
+    }
+}
+
+fn sibling(exe: &Path, suffix: &str) -> PathBuf {
+    let name = exe
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    exe.with_file_name(format!(".{name}.{suffix}"))
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_trusted_comment, is_newer, parse_release, verify_signature, Release};
+    use minisign_verify::PublicKey;
+    use serde_json::json;
+
+    fn release(tag: &str) -> Release {
+        Release {
+            tag: tag.to_string(),
+            binary_url: String::new(),
+            signature_url: String::new(),
+            asset: "jwt-tester-linux-x86_64".to_string(),
+        }
+    }
+
+    #[test]
+    fn is_newer_compares_semver_cores() {
+        assert!(is_newer("v0.2.0", "0.1.9").unwrap());
+        assert!(!is_newer("v0.1.0", "0.1.0").unwrap());
+        assert!(!is_newer("0.1.0-rc.1", "0.1.0").unwrap());
+        assert!(is_newer("nightly", "0.1.0").is_err());
+    }
+
+    #[test]
+    fn parse_release_requires_a_signature_asset() {
+        let body = json!({
+            "tag_name": "v1.0.0",
+            "assets": [
+                { "name": "jwt-tester-linux-x86_64", "browser_download_url": "https://x/bin" },
+                { "name": "jwt-tester-linux-x86_64.minisig", "browser_download_url": "https://x/sig" },
+            ],
+        });
+        let release = parse_release(&body, "jwt-tester-linux-x86_64").unwrap();
+        assert_eq!(release.signature_url, "https://x/sig");
+
+        let unsigned = json!({ "tag_name": "v1.0.0", "assets": [body["assets"][0]] });
+        let err = parse_release(&unsigned, "jwt-tester-linux-x86_64").unwrap_err();
+        assert_eq!(err.exit_code(), 11);
+    }
+
+    #[test]
+    fn trusted_comment_must_name_the_tag_and_asset() {
+        let comment = "timestamp:1700000000\tfile:jwt-tester-linux-x86_64\ttag:v1.2.0";
+        assert!(check_trusted_comment(comment, &release("v1.2.0")).is_ok());
+
+        // An older signed build replayed under a newer tag.
+        let err = check_trusted_comment(comment, &release("v9.0.0")).unwrap_err();
+        assert_eq!(err.exit_code(), 11);
+        assert!(err.to_string().contains("tag:v1.2.0"));
+
+        let other_asset = Release {
+            asset: "jwt-tester-macos-aarch64".to_string(),
+            ..release("v1.2.0")
+        };
+        assert!(check_trusted_comment(comment, &other_asset).is_err());
+        // minisign's default comment names the file but no tag.
+        let default_comment = "timestamp:1700000000\tfile:jwt-tester-linux-x86_64\thashed";
+        assert!(check_trusted_comment(default_comment, &release("v1.2.0")).is_err());
+    }
+
+    #[test]
+    fn verify_signature_rejects_malformed_signatures() {
+        let key =
+            PublicKey::from_base64("RWQBAgMEBQYHCOo0J0S6VVYGsS8Su3RfUlnHAKlCMHKD8jfcZSjF2uCR")
+                .expect("public key");
+        for signature in [&b"\xff\xfe"[..], b"not a minisign file"] {
+            let err = verify_signature(&key, b"binary", signature, &release("v1.2.0")).unwrap_err();
+            assert_eq!(err.exit_code(), 11);
+        }
+    }
+}
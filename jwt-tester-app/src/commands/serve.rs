@@ -0,0 +1,41 @@
+//! `serve --grpc`: encode, verify, inspect and vault queries as gRPC services, so test frameworks
+//! in any language can call jwt-tester without spawning it per token. The contract is
+//! `proto/jwt_tester/v1/jwt_tester.proto`; the server is only in builds with the `grpc` feature.
+
+use crate::cli::ServeArgs;
+use crate::error::AppResult;
+use crate::output::OutputConfig;
+use crate::ui::validate_bind_target;
+use crate::workspace::Loaded;
+use std::path::PathBuf;
+
+#[cfg(feature = "grpc")]
+mod grpc;
+
+/// Serves until ctrl+c. Requests see the workspace this was started in.
+pub async fn run(
+    no_persist: bool,
+    data_dir: Option<PathBuf>,
+    loaded: Option<Loaded>,
+    args: ServeArgs,
+    output: OutputConfig,
+) -> AppResult<()> {
+    validate_bind_target(args.host, args.allow_remote, "gRPC server")?;
+    serve_grpc(no_persist, data_dir, loaded, args, output).await
+}
+
+#[cfg(feature = "grpc")]
+use grpc::serve as serve_grpc;
+
+#[cfg(not(feature = "grpc"))]
+async fn serve_grpc(
+    _no_persist: bool,
+    _data_dir: Option<PathBuf>,
+    _loaded: Option<Loaded>,
+    _args: ServeArgs,
+    _output: OutputConfig,
+) -> AppResult<()> {
+    Err(crate::error::AppError::internal(
+        "serve --grpc requires a build with the `grpc` feature",
+    ))
+}
@@ -0,0 +1,531 @@
+//! The gRPC services. Like `--rpc`, each request's `args` are parsed as the matching subcommand's
+//! command line (workspace defaults included) and run against one vault opened at startup; the
+//! blocking command code runs on tokio's blocking pool.
+
+use crate::cli::{App, Command, ServeArgs};
+use crate::commands::rpc::{clap_message, is_query, reads_stdin};
+use crate::commands::vault_access::VaultAccess;
+use crate::commands::{encode, inspect, vault, verify};
+use crate::error::{AppError, AppResult, ErrorKind};
+use crate::output::{emit_ok, CommandOutput, OutputConfig, OutputMode};
+use crate::vault::{Vault, VaultConfig};
+use crate::workspace::{self, Loaded};
+use clap::FromArgMatches;
+use serde_json::json;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio_stream::wrappers::TcpListenerStream;
+use tokio_stream::{Stream, StreamExt};
+use tonic::metadata::MetadataValue;
+use tonic::{Request, Response, Status, Streaming};
+use tracing::{info, warn};
+
+mod pb;
+
+const PROTO: &str = "proto/jwt_tester/v1/jwt_tester.proto";
+const SERVICES: [&str; 4] = [
+    "jwt_tester.v1.Encode",
+    "jwt_tester.v1.Verify",
+    "jwt_tester.v1.Inspect",
+    "jwt_tester.v1.Vault",
+];
+/// `Error.code` for arguments the command line would have rejected.
+const USAGE_CODE: &str = "INVALID_ARGUMENTS";
+/// Clap's exit code for unusable arguments.
+const USAGE_EXIT: i32 = 2;
+
+pub async fn serve(
+    no_persist: bool,
+    data_dir: Option<PathBuf>,
+    loaded: Option<Loaded>,
+    args: ServeArgs,
+    output: OutputConfig,
+) -> AppResult<()> {
+    let vault = Vault::open(VaultConfig {
+        no_persist,
+        data_dir,
+    })
+    .map_err(|e| AppError::invalid_key(e.to_string()))?;
+    let listener = TcpListener::bind(SocketAddr::new(args.host, args.port))
+        .await
+        .map_err(|e| AppError::internal(format!("failed to bind gRPC server: {e}")))?;
+    let local_addr = listener
+        .local_addr()
+        .map_err(|e| AppError::internal(format!("failed to get gRPC server address: {e}")))?;
+    let url = format!("http://{local_addr}");
+
+    info!("gRPC server started at {url}");
+    let text = if output.quiet {
+        String::new()
+    } else {
+        format!("{url}\nservices: {}\nproto: {PROTO}", SERVICES.join(", "))
+    };
+    let data = json!({ "url": url, "services": SERVICES, "proto": PROTO });
+    emit_ok(output, CommandOutput::new(data, text))?;
+
+    let shutdown = async {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            warn!("failed to install ctrl+c handler: {err}");
+        } else {
+            info!("gRPC server shutdown requested (ctrl+c)");
+        }
+    };
+    serve_on(listener, Api::new(vault, loaded, output), shutdown).await
+}
+
+async fn serve_on(
+    listener: TcpListener,
+    api: Api,
+    shutdown: impl Future<Output = ()>,
+) -> AppResult<()> {
+    let services = Services(Arc::new(api));
+    tonic::transport::Server::builder()
+        .add_service(pb::encode_server::EncodeServer::new(services.clone()))
+        .add_service(pb::verify_server::VerifyServer::new(services.clone()))
+        .add_service(pb::inspect_server::InspectServer::new(services.clone()))
+        .add_service(pb::vault_server::VaultServer::new(services))
+        .serve_with_incoming_shutdown(TcpListenerStream::new(listener), shutdown)
+        .await
+        .map_err(|e| AppError::internal(format!("gRPC server failed: {e}")))
+}
+
+/// Why a call failed: arguments the command line would have rejected, or the command itself.
+enum Failure {
+    Usage(String),
+    Command(AppError),
+}
+
+impl From<AppError> for Failure {
+    fn from(err: AppError) -> Self {
+        Failure::Command(err)
+    }
+}
+
+impl Failure {
+    /// A status for the unary calls; `jwt-tester-code` and `jwt-tester-exit-code` metadata carry
+    /// what the CLI would have reported.
+    fn into_status(self) -> Status {
+        let (mut status, code, exit_code) = match self {
+            Failure::Usage(message) => (Status::invalid_argument(message), USAGE_CODE, USAGE_EXIT),
+            Failure::Command(err) => {
+                let grpc_code = match err.kind {
+                    ErrorKind::InvalidToken
+                    | ErrorKind::InvalidSignature
+                    | ErrorKind::InvalidClaims
+                    | ErrorKind::Revoked
+                    | ErrorKind::InvalidInput => tonic::Code::InvalidArgument,
+                    ErrorKind::InvalidKey => tonic::Code::FailedPrecondition,
+                    ErrorKind::NotFound => tonic::Code::NotFound,
+                    ErrorKind::Offline => tonic::Code::Unavailable,
+                    ErrorKind::Internal => tonic::Code::Internal,
+                };
+                let status = Status::new(grpc_code, err.message.clone());
+                (status, err.code(), err.exit_code())
+            }
+        };
+        let metadata = status.metadata_mut();
+        metadata.insert("jwt-tester-code", MetadataValue::from_static(code));
+        metadata.insert("jwt-tester-exit-code", MetadataValue::from(exit_code));
+        status
+    }
+
+    fn into_error(self) -> pb::Error {
+        match self {
+            Failure::Usage(message) => pb::Error {
+                code: USAGE_CODE.to_string(),
+                message,
+                exit_code: USAGE_EXIT,
+                details_json: String::new(),
+            },
+            Failure::Command(err) => pb::Error {
+                code: err.code().to_string(),
+                exit_code: err.exit_code(),
+                details_json: err
+                    .details
+                    .as_ref()
+                    .map(|details| details.to_string())
+                    .unwrap_or_default(),
+                message: err.message,
+            },
+        }
+    }
+}
+
+struct Api {
+    vault: Vault,
+    loaded: Option<Loaded>,
+    clap: clap::Command,
+    cfg: OutputConfig,
+}
+
+impl Api {
+    fn new(vault: Vault, loaded: Option<Loaded>, cfg: OutputConfig) -> Self {
+        Api {
+            vault,
+            clap: workspace::cli_command(loaded.as_ref()),
+            loaded,
+            // Results are returned as JSON data, so text rendering is never colored or templated.
+            cfg: OutputConfig {
+                mode: OutputMode::Json,
+                template: None,
+                summary: None,
+                sinks: None,
+                ..cfg
+            },
+        }
+    }
+
+    /// `jwt-tester <name> <args..> [input]`, parsed as the CLI would with workspace defaults.
+    fn command(
+        &self,
+        name: &str,
+        args: &[String],
+        input: Option<&str>,
+    ) -> Result<Command, Failure> {
+        let input = input.filter(|input| !input.is_empty());
+        let mut argv = args.iter().map(String::as_str).chain(input);
+        if let Some(arg) = argv.clone().find(|arg| reads_stdin(arg)) {
+            return Err(Failure::Usage(format!(
+                "{arg:?} would read the terminal or stdin, which a server cannot do; \
+                 pass the value itself, '@file' or 'env:NAME'"
+            )));
+        }
+        let app = self
+            .clap
+            .clone()
+            .try_get_matches_from(["jwt-tester", name].into_iter().chain(&mut argv))
+            .and_then(|mut matches| App::from_arg_matches_mut(&mut matches))
+            .map_err(|err| Failure::Usage(clap_message(&err)))?;
+        let mut command = app
+            .command
+            .ok_or_else(|| Failure::Usage("missing command".to_string()))?;
+        if let Some(loaded) = &self.loaded {
+            workspace::apply(&mut command, loaded);
+        }
+        Ok(command)
+    }
+
+    fn encode(&self, request: pb::EncodeRequest) -> Result<pb::EncodeReply, Failure> {
+        let Command::Encode(args) = self.command("encode", &request.args, Some(&request.claims))?
+        else {
+            return Err(AppError::internal("expected an encode command").into());
+        };
+        let out = encode::execute(VaultAccess::Shared(&self.vault), &args)?;
+        Ok(pb::EncodeReply {
+            token: out.data["token"].as_str().unwrap_or_default().to_string(),
+            data_json: out.data.to_string(),
+        })
+    }
+
+    /// Never fails: a token that does not verify is a reply with `error` set.
+    fn verify(&self, request: pb::VerifyRequest) -> pb::VerifyReply {
+        let verified = self
+            .command("verify", &request.args, Some(&request.token))
+            .and_then(|command| match command {
+                Command::Verify(args) => Ok(verify::execute(
+                    VaultAccess::Shared(&self.vault),
+                    &args,
+                    self.cfg,
+                )?),
+                _ => Err(AppError::internal("expected a verify command").into()),
+            });
+        match verified {
+            Ok(out) => pb::VerifyReply {
+                valid: out.data["valid"] == true,
+                data_json: out.data.to_string(),
+                error: None,
+            },
+            Err(failure) => pb::VerifyReply {
+                valid: false,
+                data_json: String::new(),
+                error: Some(failure.into_error()),
+            },
+        }
+    }
+
+    fn inspect(&self, request: pb::InspectRequest) -> Result<pb::InspectReply, Failure> {
+        let Command::Inspect(args) =
+            self.command("inspect", &request.args, Some(&request.token))?
+        else {
+            return Err(AppError::internal("expected an inspect command").into());
+        };
+        if args.register_file_association {
+            return Err(Failure::Usage(
+                "--register-file-association is only available on the command line".to_string(),
+            ));
+        }
+        if args.sandbox {
+            return Err(Failure::Usage(
+                "--sandbox locks down the whole process and is only available on the command line"
+                    .to_string(),
+            ));
+        }
+        let (out, fits) = inspect::execute(&args, self.cfg)?;
+        Ok(pb::InspectReply {
+            data_json: out.data.to_string(),
+            fits,
+        })
+    }
+
+    fn vault(&self, request: pb::VaultRequest) -> Result<pb::VaultReply, Failure> {
+        match self.command("vault", &request.args, None)? {
+            Command::Vault(args) if is_query(&args.cmd) => {
+                let out = vault::execute(&self.vault, args)?;
+                Ok(pb::VaultReply {
+                    data_json: out.data.to_string(),
+                })
+            }
+            Command::Vault(_) => Err(Failure::Usage(
+                "only vault queries are served over gRPC: project list|show, key list, \
+                 token list|revoked, jwks list|show, webhook list, search, stats and audit"
+                    .to_string(),
+            )),
+            _ => Err(AppError::internal("expected a vault command").into()),
+        }
+    }
+}
+
+/// Runs blocking command code for one call off the async workers.
+async fn blocking<T: Send + 'static>(
+    api: &Arc<Api>,
+    call: impl FnOnce(&Api) -> T + Send + 'static,
+) -> Result<T, Status> {
+    let api = Arc::clone(api);
+    tokio::task::spawn_blocking(move || call(&api))
+        .await
+        .map_err(|e| Status::internal(format!("request task failed: {e}")))
+}
+
+#[derive(Clone)]
+struct Services(Arc<Api>);
+
+#[tonic::async_trait]
+impl pb::encode_server::Encode for Services {
+    async fn encode(
+        &self,
+        request: Request<pb::EncodeRequest>,
+    ) -> Result<Response<pb::EncodeReply>, Status> {
+        let request = request.into_inner();
+        blocking(&self.0, move |api| api.encode(request))
+            .await?
+            .map(Response::new)
+            .map_err(Failure::into_status)
+    }
+}
+
+#[tonic::async_trait]
+impl pb::verify_server::Verify for Services {
+    async fn verify(
+        &self,
+        request: Request<pb::VerifyRequest>,
+    ) -> Result<Response<pb::VerifyReply>, Status> {
+        let request = request.into_inner();
+        blocking(&self.0, move |api| api.verify(request))
+            .await
+            .map(Response::new)
+    }
+
+    type VerifyStreamStream = Pin<Box<dyn Stream<Item = Result<pb::VerifyReply, Status>> + Send>>;
+
+    /// Verifies each request in turn and answers in the same order.
+    async fn verify_stream(
+        &self,
+        request: Request<Streaming<pb::VerifyRequest>>,
+    ) -> Result<Response<Self::VerifyStreamStream>, Status> {
+        let api = Arc::clone(&self.0);
+        let replies = request.into_inner().then(move |request| {
+            let api = Arc::clone(&api);
+            async move {
+                let request = request?;
+                blocking(&api, move |api| api.verify(request)).await
+            }
+        });
+        Ok(Response::new(Box::pin(replies)))
+    }
+}
+
+#[tonic::async_trait]
+impl pb::inspect_server::Inspect for Services {
+    async fn inspect(
+        &self,
+        request: Request<pb::InspectRequest>,
+    ) -> Result<Response<pb::InspectReply>, Status> {
+        let request = request.into_inner();
+        blocking(&self.0, move |api| api.inspect(request))
+            .await?
+            .map(Response::new)
+            .map_err(Failure::into_status)
+    }
+}
+
+#[tonic::async_trait]
+impl pb::vault_server::Vault for Services {
+    async fn query(
+        &self,
+        request: Request<pb::VaultRequest>,
+    ) -> Result<Response<pb::VaultReply>, Status> {
+        let request = request.into_inner();
+        blocking(&self.0, move |api| api.vault(request))
+            .await?
+            .map(Response::new)
+            .map_err(Failure::into_status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::pb::clients::encode_client::EncodeClient;
+    use super::pb::clients::inspect_client::InspectClient;
+    use super::pb::clients::vault_client::VaultClient;
+    use super::pb::clients::verify_client::VerifyClient;
+    use super::*;
+    use crate::cli::Theme;
+
+    const SECRET: &str = "grpc-test-secret-that-is-long-enough";
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    /// A server on an ephemeral port over an in-memory vault; it stops with the test runtime.
+    async fn start() -> String {
+        let vault = Vault::open(VaultConfig {
+            no_persist: true,
+            data_dir: None,
+        })
+        .expect("open vault");
+        let cfg = OutputConfig {
+            mode: OutputMode::Json,
+            quiet: true,
+            no_color: true,
+            theme: Theme::Default,
+            verbose: false,
+            template: None,
+            summary: None,
+            sinks: None,
+        };
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+        let url = format!("http://{}", listener.local_addr().expect("address"));
+        let api = Api::new(vault, None, cfg);
+        tokio::spawn(serve_on(listener, api, std::future::pending()));
+        url
+    }
+
+    async fn encode(url: &str, claims: &str) -> String {
+        let mut client = EncodeClient::connect(url.to_string())
+            .await
+            .expect("connect");
+        let reply = client
+            .encode(pb::EncodeRequest {
+                claims: claims.to_string(),
+                args: args(&["--alg", "hs256", "--secret", SECRET, "--exp", "+1h"]),
+            })
+            .await
+            .expect("encode")
+            .into_inner();
+        let data: serde_json::Value = serde_json::from_str(&reply.data_json).expect("data");
+        assert_eq!(data["token"], reply.token.as_str());
+        reply.token
+    }
+
+    #[tokio::test]
+    async fn encode_verify_and_stream_over_grpc() {
+        let url = start().await;
+        let token = encode(&url, r#"{"sub":"alice"}"#).await;
+
+        let mut verify = VerifyClient::connect(url.clone()).await.expect("connect");
+        let reply = verify
+            .verify(pb::VerifyRequest {
+                token: token.clone(),
+                args: args(&["--secret", SECRET]),
+            })
+            .await
+            .expect("verify")
+            .into_inner();
+        assert!(reply.valid, "{reply:?}");
+        assert!(reply.error.is_none());
+
+        let requests = [
+            (token.clone(), args(&["--secret", SECRET])),
+            (
+                token.clone(),
+                args(&["--secret", "some-other-secret-value"]),
+            ),
+            (token.clone(), args(&["--secret", "-"])),
+            (token, args(&["--secret", SECRET, "--no-such-flag"])),
+        ]
+        .map(|(token, args)| pb::VerifyRequest { token, args });
+        let mut replies = verify
+            .verify_stream(tokio_stream::iter(requests))
+            .await
+            .expect("verify stream")
+            .into_inner();
+        let mut codes = Vec::new();
+        while let Some(reply) = replies.message().await.expect("reply") {
+            codes.push(reply.error.map(|error| (error.code, error.exit_code)));
+        }
+        assert_eq!(
+            codes,
+            [
+                None,
+                Some(("INVALID_SIGNATURE".to_string(), 11)),
+                Some((USAGE_CODE.to_string(), USAGE_EXIT)),
+                Some((USAGE_CODE.to_string(), USAGE_EXIT)),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn inspect_and_vault_queries_map_failures_to_statuses() {
+        let url = start().await;
+        let token = encode(&url, r#"{"sub":"bob"}"#).await;
+
+        let mut inspect = InspectClient::connect(url.clone()).await.expect("connect");
+        let reply = inspect
+            .inspect(pb::InspectRequest {
+                token,
+                args: Vec::new(),
+            })
+            .await
+            .expect("inspect")
+            .into_inner();
+        let data: serde_json::Value = serde_json::from_str(&reply.data_json).expect("data");
+        assert_eq!(data["payload"]["sub"], "bob", "{data}");
+        assert!(reply.fits);
+        let status = inspect
+            .inspect(pb::InspectRequest {
+                token: "not-a-jwt".to_string(),
+                args: Vec::new(),
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        assert_eq!(status.metadata().get("jwt-tester-exit-code").unwrap(), "10");
+
+        let mut vault = VaultClient::connect(url).await.expect("connect");
+        let reply = vault
+            .query(pb::VaultRequest {
+                args: args(&["project", "list"]),
+            })
+            .await
+            .expect("project list")
+            .into_inner();
+        assert!(reply.data_json.starts_with('{'), "{}", reply.data_json);
+        let status = vault
+            .query(pb::VaultRequest {
+                args: args(&["project", "add", "demo"]),
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        assert!(status.message().contains("only vault queries"));
+        assert_eq!(
+            status.metadata().get("jwt-tester-code").unwrap(),
+            USAGE_CODE
+        );
+    }
+}
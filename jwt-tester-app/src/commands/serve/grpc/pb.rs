@@ -0,0 +1,180 @@
+//! Messages of `proto/jwt_tester/v1/jwt_tester.proto`, field for field, plus the service stubs
+//! `build.rs` generates for them.
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Error {
+    #[prost(string, tag = "1")]
+    pub code: String,
+    #[prost(string, tag = "2")]
+    pub message: String,
+    #[prost(int32, tag = "3")]
+    pub exit_code: i32,
+    #[prost(string, tag = "4")]
+    pub details_json: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct EncodeRequest {
+    #[prost(string, tag = "1")]
+    pub claims: String,
+    #[prost(string, repeated, tag = "2")]
+    pub args: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct EncodeReply {
+    #[prost(string, tag = "1")]
+    pub token: String,
+    #[prost(string, tag = "2")]
+    pub data_json: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct VerifyRequest {
+    #[prost(string, tag = "1")]
+    pub token: String,
+    #[prost(string, repeated, tag = "2")]
+    pub args: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct VerifyReply {
+    #[prost(bool, tag = "1")]
+    pub valid: bool,
+    #[prost(string, tag = "2")]
+    pub data_json: String,
+    #[prost(message, optional, tag = "3")]
+    pub error: Option<Error>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct InspectRequest {
+    #[prost(string, tag = "1")]
+    pub token: String,
+    #[prost(string, repeated, tag = "2")]
+    pub args: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct InspectReply {
+    #[prost(string, tag = "1")]
+    pub data_json: String,
+    #[prost(bool, tag = "2")]
+    pub fits: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct VaultRequest {
+    #[prost(string, repeated, tag = "1")]
+    pub args: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct VaultReply {
+    #[prost(string, tag = "1")]
+    pub data_json: String,
+}
+
+include!(concat!(env!("OUT_DIR"), "/jwt_tester.v1.Encode.rs"));
+include!(concat!(env!("OUT_DIR"), "/jwt_tester.v1.Verify.rs"));
+include!(concat!(env!("OUT_DIR"), "/jwt_tester.v1.Inspect.rs"));
+include!(concat!(env!("OUT_DIR"), "/jwt_tester.v1.Vault.rs"));
+
+#[cfg(test)]
+pub mod clients {
+    use super::*;
+
+    include!(concat!(env!("OUT_DIR"), "/clients/jwt_tester.v1.Encode.rs"));
+    include!(concat!(env!("OUT_DIR"), "/clients/jwt_tester.v1.Verify.rs"));
+    include!(concat!(
+        env!("OUT_DIR"),
+        "/clients/jwt_tester.v1.Inspect.rs"
+    ));
+    include!(concat!(env!("OUT_DIR"), "/clients/jwt_tester.v1.Vault.rs"));
+}
+
+#[cfg(test)]
+mod tests {
+    //! The messages above are written by hand, so this keeps them in step with the `.proto`:
+    //! every message, field name, type, label and tag must match, in order.
+
+    const PROTO: &str = include_str!("../../../../proto/jwt_tester/v1/jwt_tester.proto");
+    const MESSAGES: &str = include_str!("pb.rs");
+    const BUILD: &str = include_str!("../../../../build.rs");
+
+    /// `(message, field, prost attribute)` for every field of `proto`.
+    fn proto_fields() -> Vec<(String, String, String)> {
+        let mut fields = Vec::new();
+        let mut message = None;
+        for line in PROTO.lines() {
+            let line = line.split("//").next().unwrap_or_default().trim();
+            if let Some(name) = line.strip_prefix("message ") {
+                message = Some(name.trim_end_matches('{').trim().to_string());
+                continue;
+            }
+            if line == "}" {
+                message = None;
+            }
+            let (Some(message), Some(field)) = (&message, line.strip_suffix(';')) else {
+                continue;
+            };
+            let (decl, tag) = field.split_once('=').expect("field tag");
+            let tag = tag.trim();
+            let words: Vec<&str> = decl.split_whitespace().collect();
+            let (repeated, kind, name) = match words.as_slice() {
+                ["repeated", kind, name] => (true, *kind, *name),
+                [kind, name] => (false, *kind, *name),
+                other => panic!("unexpected field {other:?}"),
+            };
+            let scalar = matches!(kind, "string" | "bool" | "int32" | "int64" | "bytes");
+            let attr = match (scalar, repeated) {
+                (true, false) => format!("{kind}, tag = \"{tag}\""),
+                (true, true) => format!("{kind}, repeated, tag = \"{tag}\""),
+                (false, false) => format!("message, optional, tag = \"{tag}\""),
+                (false, true) => format!("message, repeated, tag = \"{tag}\""),
+            };
+            fields.push((message.clone(), name.to_string(), attr));
+        }
+        fields
+    }
+
+    /// The same triples, read from the `#[prost(...)]` attributes in this file.
+    fn rust_fields() -> Vec<(String, String, String)> {
+        let mut fields = Vec::new();
+        let (mut message, mut attr) = (String::new(), None);
+        for line in MESSAGES.lines().map(str::trim) {
+            if let Some(name) = line.strip_prefix("pub struct ") {
+                message = name.trim_end_matches(" {").to_string();
+            } else if let Some(rest) = line.strip_prefix("#[prost(") {
+                attr = Some(rest.trim_end_matches(")]").to_string());
+            } else if let (Some(field), Some(attr)) = (line.strip_prefix("pub "), attr.take()) {
+                let field = field.split(':').next().unwrap_or_default();
+                fields.push((message.clone(), field.to_string(), attr));
+            }
+        }
+        fields
+    }
+
+    #[test]
+    fn messages_match_the_proto_file() {
+        let expected = proto_fields();
+        assert!(!expected.is_empty());
+        assert_eq!(rust_fields(), expected);
+    }
+
+    #[test]
+    fn every_rpc_has_a_generated_route() {
+        let rpcs: Vec<&str> = PROTO
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("rpc "))
+            .map(|rpc| rpc.split('(').next().unwrap_or_default())
+            .collect();
+        assert_eq!(rpcs.len(), 5);
+        for rpc in rpcs {
+            assert!(
+                BUILD.contains(&format!("\"{rpc}\"")),
+                "build.rs has no route for rpc {rpc}"
+            );
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use crate::cli::{SplitArgs, SplitFormat};
 use crate::error::{AppError, AppResult};
-use crate::io_utils::read_input;
+use crate::io_utils::read_token_input;
 use crate::output::{emit_err, emit_ok, CommandOutput, OutputConfig};
 use base64::engine::general_purpose::URL_SAFE_NO_PAD;
 use base64::Engine;
@@ -8,7 +8,7 @@ use serde_json::json;
 
 pub fn run(args: SplitArgs, cfg: OutputConfig) -> i32 {
     let result = (|| -> AppResult<CommandOutput> {
-        let token = read_input(&args.token)?;
+        let token = read_token_input(&args.token)?;
         let parts: Vec<&str> = token.trim().split('.').collect();
         if parts.len() != 3 {
             return Err(AppError::invalid_token(
@@ -55,11 +55,8 @@ pub fn run(args: SplitArgs, cfg: OutputConfig) -> i32 {
         Ok(CommandOutput::new(data, text))
     })();
 
-    match result {
-        Ok(out) => {
-            emit_ok(cfg, out);
-            0
-        }
+    match result.and_then(|out| emit_ok(cfg, out)) {
+        Ok(()) => 0,
         Err(err) => {
             let code = err.exit_code();
             emit_err(cfg, err);
@@ -82,7 +79,11 @@ mod tests {
             mode: OutputMode::Json,
             quiet: true,
             no_color: true,
+            theme: crate::cli::Theme::Default,
             verbose: false,
+            template: None,
+            summary: None,
+            sinks: None,
         }
     }
 
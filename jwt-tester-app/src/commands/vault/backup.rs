@@ -0,0 +1,64 @@
+use crate::cli::BackupCmd;
+use crate::error::{AppError, AppResult};
+use crate::output::CommandOutput;
+use crate::prompt;
+use crate::vault::{BackupEntry, Vault};
+use serde_json::json;
+
+fn format_backup_entry(entry: &BackupEntry) -> String {
+    let mut line = format!(
+        "{}  created_at={}  reason={}",
+        entry.id, entry.created_at, entry.reason
+    );
+    if let Some(counts) = &entry.counts {
+        line.push_str(&format!(
+            "  projects={} keys={} tokens={} jwks={}",
+            counts.projects, counts.keys, counts.tokens, counts.jwks
+        ));
+    }
+    line
+}
+
+pub(super) fn execute(vault: &Vault, cmd: BackupCmd, yes: bool) -> AppResult<CommandOutput> {
+    let out = match cmd {
+        BackupCmd::List => {
+            let backups = vault
+                .list_backups()
+                .map_err(|e| AppError::invalid_key(e.to_string()))?;
+            let text = if backups.is_empty() {
+                "no backups".to_string()
+            } else {
+                backups
+                    .iter()
+                    .map(format_backup_entry)
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            };
+            CommandOutput::new(json!({ "backups": backups }), text)
+        }
+        BackupCmd::Create { reason } => {
+            let backup = vault
+                .create_backup(&reason)
+                .map_err(|e| AppError::invalid_key(e.to_string()))?;
+            let text = match &backup {
+                Some(entry) => format!("created backup: {}", format_backup_entry(entry)),
+                None => "nothing to back up".to_string(),
+            };
+            CommandOutput::new(json!({ "backup": backup }), text)
+        }
+        BackupCmd::Restore { id } => {
+            prompt::require_confirmation(
+                &format!("Replace the vault contents with backup {id}?"),
+                yes,
+            )?;
+            let restored = vault
+                .restore_backup(&id)
+                .map_err(|e| AppError::invalid_key(e.to_string()))?;
+            CommandOutput::new(
+                json!({ "restored": restored }),
+                format!("restored backup: {}", restored.id),
+            )
+        }
+    };
+    Ok(out)
+}
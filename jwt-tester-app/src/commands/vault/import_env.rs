@@ -0,0 +1,94 @@
+use super::resolve_project_selector;
+use crate::error::{AppError, AppResult};
+use crate::io_utils::{read_dotenv, wildcard_match};
+use crate::key_resolver::check_new_key_material;
+use crate::output::{CommandOutput, Progress};
+use crate::vault::{KeyEntryInput, Vault};
+use serde_json::json;
+use std::path::PathBuf;
+
+/// `vault key import`: one key per matching variable, named after it. Variables that are empty,
+/// already stored under that name, or fail the kind check are skipped and reported.
+pub(super) fn import_keys(
+    vault: &Vault,
+    project: &str,
+    dotenv: Option<PathBuf>,
+    pattern: &str,
+    kind: &str,
+    min_hmac_bytes: Option<usize>,
+) -> AppResult<CommandOutput> {
+    let p = resolve_project_selector(vault, project)?;
+    let (source, mut vars) = match &dotenv {
+        Some(path) => (path.display().to_string(), read_dotenv(path)?),
+        None => {
+            let mut vars: Vec<(String, String)> = std::env::vars().collect();
+            vars.sort();
+            ("environment".to_string(), vars)
+        }
+    };
+    vars.retain(|(name, _)| wildcard_match(pattern, name));
+    if vars.is_empty() {
+        return Err(AppError::not_found(format!(
+            "no variables in {source} match '{pattern}'"
+        )));
+    }
+    let existing: Vec<String> = vault
+        .list_keys(Some(&p.id))
+        .map_err(|e| AppError::invalid_key(e.to_string()))?
+        .into_iter()
+        .map(|k| k.name)
+        .collect();
+
+    let progress = Progress::items(vars.len(), "importing keys");
+    let mut imported = Vec::new();
+    let mut skipped = Vec::new();
+    let mut lines = Vec::new();
+    for (name, value) in vars {
+        // Keys stored so far stay imported; the rest can be picked up by running it again.
+        if progress.cancelled() {
+            break;
+        }
+        progress.inc();
+        let value = value.trim().to_string();
+        let reason = if value.is_empty() {
+            Some("empty value".to_string())
+        } else if existing.contains(&name) {
+            Some("a key with this name already exists".to_string())
+        } else {
+            check_new_key_material(kind, &value, false, min_hmac_bytes)
+                .err()
+                .map(|err| err.message)
+        };
+        if let Some(reason) = reason {
+            lines.push(format!("skipped {name}: {reason}"));
+            skipped.push(json!({ "name": name, "reason": reason }));
+            continue;
+        }
+        let k = vault
+            .add_key(KeyEntryInput {
+                project_id: p.id.clone(),
+                name,
+                kind: kind.to_string(),
+                secret: value,
+                kid: None,
+                description: Some(format!("imported from {source}")),
+                tags: Vec::new(),
+            })
+            .map_err(|e| AppError::invalid_key(e.to_string()))?;
+        lines.push(format!("imported key: {} ({})", k.name, k.id));
+        imported.push(k);
+    }
+    let cancelled = progress.cancelled();
+    drop(progress);
+    lines.push(format!(
+        "{} imported, {} skipped into project {}{}",
+        imported.len(),
+        skipped.len(),
+        p.name,
+        if cancelled { " (cancelled)" } else { "" }
+    ));
+    Ok(CommandOutput::new(
+        json!({ "imported": imported, "skipped": skipped, "cancelled": cancelled }),
+        lines.join("\n"),
+    ))
+}
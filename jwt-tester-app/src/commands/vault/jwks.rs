@@ -0,0 +1,178 @@
+use super::{format_tags, render_listing, resolve_project_selector};
+use crate::cli::JwksCmd;
+use crate::error::{AppError, AppResult};
+use crate::jwks;
+use crate::output::CommandOutput;
+use crate::vault::{JwksEntry, JwksEntryInput, Vault};
+use serde_json::json;
+
+const JWKS_COLUMNS: &[&str] = &["id", "name", "kids", "created_at"];
+
+/// Selects a stored JWKS by id, or by `--project` + `--name`.
+fn resolve_jwks_selector(
+    vault: &Vault,
+    id: Option<String>,
+    project: Option<String>,
+    name: Option<String>,
+) -> AppResult<JwksEntry> {
+    if id.is_some() && (project.is_some() || name.is_some()) {
+        return Err(AppError::invalid_key(
+            "provide either a JWKS id or --project/--name".to_string(),
+        ));
+    }
+    let entries = match (&id, &project) {
+        (Some(_), _) => vault.list_jwks(None),
+        (None, Some(project)) => {
+            let p = resolve_project_selector(vault, project)?;
+            vault.list_jwks(Some(&p.id))
+        }
+        (None, None) => {
+            return Err(AppError::invalid_key(
+                "provide a JWKS id or --project with --name".to_string(),
+            ))
+        }
+    }
+    .map_err(|e| AppError::invalid_key(e.to_string()))?;
+
+    if let Some(id) = id {
+        return entries
+            .into_iter()
+            .find(|j| j.id == id)
+            .ok_or_else(|| AppError::not_found(format!("JWKS not found: {id}")));
+    }
+    let name =
+        name.ok_or_else(|| AppError::invalid_key("provide --name (or select by id)".to_string()))?;
+    let matches: Vec<_> = entries.into_iter().filter(|j| j.name == name).collect();
+    if matches.len() > 1 {
+        return Err(AppError::invalid_key(format!(
+            "multiple JWKS named '{name}' found; use JWKS id"
+        )));
+    }
+    matches
+        .into_iter()
+        .next()
+        .ok_or_else(|| AppError::not_found("JWKS name not found in project".to_string()))
+}
+
+pub(super) fn execute(vault: &Vault, cmd: JwksCmd) -> AppResult<CommandOutput> {
+    let out = match cmd {
+        JwksCmd::Add {
+            project,
+            name,
+            source_url,
+            jwks: spec,
+        } => {
+            let p = resolve_project_selector(vault, &project)?;
+            let (document, set) = jwks::read_jwks_document(&spec)?;
+            let source_url = source_url.or_else(|| jwks::is_remote(&spec).then_some(spec));
+            let j = vault
+                .add_jwks(JwksEntryInput {
+                    project_id: p.id,
+                    name: name.unwrap_or_default(),
+                    document,
+                    source_url,
+                })
+                .map_err(|e| AppError::invalid_key(e.to_string()))?;
+            let text = format!(
+                "created jwks: {} ({}) with {} key(s)",
+                j.name,
+                j.id,
+                set.keys.len()
+            );
+            CommandOutput::new(json!({ "jwks": j }), text)
+        }
+        JwksCmd::List { project, listing } => {
+            let p = resolve_project_selector(vault, &project)?;
+            let entries = vault
+                .list_jwks(Some(&p.id))
+                .map_err(|e| AppError::invalid_key(e.to_string()))?;
+            if let Some(text) = render_listing(&listing, &entries, JWKS_COLUMNS)? {
+                return Ok(
+                    CommandOutput::new(json!({ "jwks": entries }), text).with_records("jwks")
+                );
+            }
+            let lines: Vec<String> = entries
+                .iter()
+                .map(|j| {
+                    let mut line = format!("{}  {}  kids={}", j.id, j.name, format_tags(&j.kids));
+                    let non_signing: Vec<String> = j
+                        .keys
+                        .iter()
+                        .filter(|key| !key.may_verify())
+                        .map(|key| key.kid.clone().unwrap_or_else(|| "(no kid)".to_string()))
+                        .collect();
+                    if !non_signing.is_empty() {
+                        line.push_str(&format!("  non-signing={}", non_signing.join(",")));
+                    }
+                    line
+                })
+                .collect();
+            CommandOutput::new(json!({ "jwks": entries }), lines.join("\n")).with_records("jwks")
+        }
+        JwksCmd::Refresh { project, name } => {
+            let p = resolve_project_selector(vault, &project)?;
+            let entries = vault
+                .list_jwks(Some(&p.id))
+                .map_err(|e| AppError::invalid_key(e.to_string()))?;
+            let entries: Vec<_> = entries
+                .into_iter()
+                .filter(|j| name.as_ref().is_none_or(|name| &j.name == name))
+                .collect();
+            if entries.is_empty() {
+                return Err(AppError::not_found(match name {
+                    Some(name) => format!("JWKS name not found in project: {name}"),
+                    None => format!("project {} has no stored JWKS", p.name),
+                }));
+            }
+            let mut results = Vec::new();
+            let mut lines = Vec::new();
+            for entry in entries {
+                let Some(url) = entry.source_url.clone() else {
+                    lines.push(format!("{}  skipped (no source URL)", entry.name));
+                    results.push(json!({ "jwks": entry, "refreshed": false }));
+                    continue;
+                };
+                let (document, _) = jwks::read_jwks_document(&url)?;
+                let updated = vault
+                    .update_jwks_document(&entry.id, &document)
+                    .map_err(|e| AppError::invalid_key(e.to_string()))?;
+                let changed = updated.kids != entry.kids;
+                lines.push(format!(
+                    "{}  kids={}{}",
+                    updated.name,
+                    format_tags(&updated.kids),
+                    if changed { "  (changed)" } else { "" }
+                ));
+                results.push(json!({
+                    "jwks": updated,
+                    "refreshed": true,
+                    "previous_kids": entry.kids,
+                    "changed": changed,
+                }));
+            }
+            CommandOutput::new(json!({ "results": results }), lines.join("\n"))
+        }
+        JwksCmd::Show { id, project, name } => {
+            let j = resolve_jwks_selector(vault, id, project, name)?;
+            let document = vault
+                .get_jwks_document(&j.id)
+                .map_err(|e| AppError::invalid_key(e.to_string()))?;
+            let parsed: serde_json::Value = serde_json::from_str(&document)
+                .map_err(|e| AppError::internal(format!("stored JWKS is invalid: {e}")))?;
+            let text = serde_json::to_string_pretty(&parsed)
+                .map_err(|e| AppError::internal(e.to_string()))?;
+            CommandOutput::new(json!({ "jwks": j, "document": parsed }), text)
+        }
+        JwksCmd::Delete { id, project, name } => {
+            let j = resolve_jwks_selector(vault, id, project, name)?;
+            vault
+                .delete_jwks(&j.id)
+                .map_err(|e| AppError::invalid_key(e.to_string()))?;
+            CommandOutput::new(
+                json!({ "deleted": j.id }),
+                format!("deleted jwks: {} ({})", j.name, j.id),
+            )
+        }
+    };
+    Ok(out)
+}
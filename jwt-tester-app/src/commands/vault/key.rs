@@ -0,0 +1,518 @@
+use super::{format_tags, import_env, opt_or_dash, render_listing, resolve_project_selector};
+use crate::cli::KeyCmd;
+use crate::error::{AppError, AppResult};
+use crate::io_utils::read_input;
+use crate::key_resolver::{
+    check_new_key_material, is_public_key_material, platform_key_ref, PLATFORM_KIND,
+};
+#[cfg(feature = "keygen")]
+use crate::keygen::{
+    generate_key_pair, parse_ec_curve, parse_ed_curve, parse_key_use, KeyGenSpec,
+    DEFAULT_HMAC_BYTES, DEFAULT_RSA_BITS,
+};
+use crate::output::CommandOutput;
+use crate::prompt;
+use crate::signer;
+use crate::vault::{KeyEntry, KeyEntryInput, KeyUsage, Vault};
+use serde_json::json;
+
+const KEY_COLUMNS: &[&str] = &[
+    "id",
+    "kind",
+    "name",
+    "kid",
+    "fingerprint",
+    "tags",
+    "description",
+    "created_at",
+];
+
+fn resolve_named_key(vault: &Vault, project_id: &str, name: &str) -> AppResult<KeyEntry> {
+    let keys = vault
+        .list_keys(Some(project_id))
+        .map_err(|e| AppError::invalid_key(e.to_string()))?;
+    let matches: Vec<_> = keys.into_iter().filter(|k| k.name == name).collect();
+    if matches.is_empty() {
+        return Err(AppError::not_found(
+            "key name not found in project".to_string(),
+        ));
+    }
+    if matches.len() > 1 {
+        return Err(AppError::invalid_key(format!(
+            "multiple keys named '{name}' found; use key id"
+        )));
+    }
+    Ok(matches.into_iter().next().expect("single match"))
+}
+
+/// Renders a listing for `--format`; `None` keeps the classic line output.
+fn parse_key_usage(raw: &str) -> AppResult<KeyUsage> {
+    KeyUsage::parse(raw).map_err(|e| AppError::invalid_key(e.to_string()))
+}
+
+fn usage_text(usage: KeyUsage) -> &'static str {
+    match usage {
+        KeyUsage::Both => "sign and verify",
+        KeyUsage::Sign => "sign only",
+        KeyUsage::Verify => "verify only",
+    }
+}
+
+#[cfg(feature = "keygen")]
+fn build_keygen_spec(
+    kind: &str,
+    hmac_bytes: Option<usize>,
+    rsa_bits: Option<usize>,
+    ec_curve: Option<String>,
+    ed_curve: Option<String>,
+    key_use: Option<String>,
+) -> AppResult<(KeyGenSpec, &'static str)> {
+    if ed_curve.is_some() && kind != "eddsa" {
+        return Err(AppError::invalid_key(
+            "--ed-curve is only valid for EdDSA keys".to_string(),
+        ));
+    }
+    if key_use.is_some() && kind != "rsa" {
+        return Err(AppError::invalid_key(
+            "--key-use is only valid for RSA keys".to_string(),
+        ));
+    }
+    match kind {
+        "hmac" => {
+            if rsa_bits.is_some() || ec_curve.is_some() {
+                return Err(AppError::invalid_key(
+                    "--rsa-bits/--ec-curve are only valid for RSA/EC keys".to_string(),
+                ));
+            }
+            Ok((
+                KeyGenSpec::Hmac {
+                    bytes: hmac_bytes.unwrap_or(DEFAULT_HMAC_BYTES),
+                },
+                "base64url",
+            ))
+        }
+        "rsa" => {
+            if hmac_bytes.is_some() || ec_curve.is_some() {
+                return Err(AppError::invalid_key(
+                    "--hmac-bytes/--ec-curve are only valid for HMAC/EC keys".to_string(),
+                ));
+            }
+            Ok((
+                KeyGenSpec::Rsa {
+                    bits: rsa_bits.unwrap_or(DEFAULT_RSA_BITS),
+                    key_use: parse_key_use(key_use.as_deref())?,
+                },
+                "pem",
+            ))
+        }
+        "ec" => {
+            if hmac_bytes.is_some() || rsa_bits.is_some() {
+                return Err(AppError::invalid_key(
+                    "--hmac-bytes/--rsa-bits are only valid for HMAC/RSA keys".to_string(),
+                ));
+            }
+            let curve = parse_ec_curve(ec_curve.as_deref())?;
+            Ok((KeyGenSpec::Ec { curve }, "pem"))
+        }
+        "eddsa" => {
+            if hmac_bytes.is_some() || rsa_bits.is_some() || ec_curve.is_some() {
+                return Err(AppError::invalid_key(
+                    "generation options are not valid for EdDSA keys".to_string(),
+                ));
+            }
+            Ok((
+                KeyGenSpec::EdDsa {
+                    curve: parse_ed_curve(ed_curve.as_deref())?,
+                },
+                "pem",
+            ))
+        }
+        "jwks" => Err(AppError::invalid_key(
+            "JWKS generation is not supported; paste JWKS JSON instead".to_string(),
+        )),
+        other => Err(AppError::invalid_key(format!(
+            "unsupported key kind '{other}' for generation"
+        ))),
+    }
+}
+
+/// Platform keys only hold a reference to the keystore key, not its public half, so they cannot
+/// verify.
+fn platform_usage_error() -> AppError {
+    AppError::invalid_key("platform keys can only sign; their usage must be sign")
+}
+
+pub(super) fn execute(vault: &Vault, cmd: KeyCmd, yes: bool) -> AppResult<CommandOutput> {
+    let out = match cmd {
+        KeyCmd::Add {
+            project,
+            name,
+            kind,
+            kid,
+            description,
+            tag,
+            secret,
+            from_env,
+            allow_public,
+            usage,
+            min_hmac_bytes,
+        } => {
+            let p = resolve_project_selector(vault, &project)?;
+            let (secret, name) = match (secret, from_env) {
+                (_, Some(var)) => {
+                    let secret = std::env::var(&var)
+                        .map_err(|_| AppError::invalid_key(format!("env var {var} not set")))?;
+                    (secret.trim().to_string(), name.unwrap_or(var))
+                }
+                (Some(secret), None) => (read_input(&secret)?, name.unwrap_or_default()),
+                (None, None) => {
+                    return Err(AppError::invalid_key("provide --secret or --from-env"))
+                }
+            };
+            check_new_key_material(&kind, &secret, allow_public, min_hmac_bytes)?;
+            let public = is_public_key_material(&secret);
+            let platform = kind.eq_ignore_ascii_case(PLATFORM_KIND);
+            let usage = match usage {
+                Some(usage) => parse_key_usage(&usage)?,
+                None if public => KeyUsage::Verify,
+                None if platform => KeyUsage::Sign,
+                None => KeyUsage::Both,
+            };
+            if public && usage.allows(KeyUsage::Sign) {
+                return Err(AppError::invalid_key(
+                    "a public key cannot sign; store it with --usage verify",
+                ));
+            }
+            if platform && usage != KeyUsage::Sign {
+                return Err(platform_usage_error());
+            }
+            let mut k = vault
+                .add_key(KeyEntryInput {
+                    project_id: p.id,
+                    name,
+                    kind,
+                    secret,
+                    kid,
+                    description,
+                    tags: tag,
+                })
+                .map_err(|e| AppError::invalid_key(e.to_string()))?;
+            if usage != KeyUsage::Both {
+                k = vault
+                    .set_key_usage(&k.id, usage)
+                    .map_err(|e| AppError::invalid_key(e.to_string()))?;
+            }
+            CommandOutput::new(
+                json!({ "key": k }),
+                format!("created key: {} ({})", k.name, k.id),
+            )
+        }
+        KeyCmd::Import {
+            project,
+            dotenv,
+            env: _,
+            pattern,
+            kind,
+            min_hmac_bytes,
+        } => import_env::import_keys(vault, &project, dotenv, &pattern, &kind, min_hmac_bytes)?,
+        #[cfg(not(feature = "keygen"))]
+        KeyCmd::Generate { .. } => {
+            return Err(AppError::invalid_key(
+                "vault key generate requires a build with the `keygen` feature".to_string(),
+            ));
+        }
+        #[cfg(feature = "keygen")]
+        KeyCmd::Generate {
+            project,
+            name,
+            kind,
+            kid,
+            description,
+            tag,
+            hmac_bytes,
+            rsa_bits,
+            ec_curve,
+            ed_curve,
+            key_use,
+            reveal,
+            out,
+            include_non_exportable,
+            usage,
+        } => {
+            let p = resolve_project_selector(vault, &project)?;
+            let usage = parse_key_usage(&usage)?;
+            let reveals = reveal || out.is_some();
+            if reveals && !p.exportable && !include_non_exportable {
+                return Err(AppError::invalid_key(format!(
+                    "project {} is non-exportable; --reveal and --out need --include-non-exportable",
+                    p.name
+                )));
+            }
+            let kind = kind.trim().to_ascii_lowercase();
+            if kind.is_empty() {
+                return Err(AppError::invalid_key("key kind is required".to_string()));
+            }
+            let (spec, format) =
+                build_keygen_spec(&kind, hmac_bytes, rsa_bits, ec_curve, ed_curve, key_use)?;
+            let generated = generate_key_pair(spec)?;
+            let secret = generated.material;
+            let mut k = vault
+                .add_key(KeyEntryInput {
+                    project_id: p.id.clone(),
+                    name: name.unwrap_or_default(),
+                    kind,
+                    secret: secret.clone(),
+                    kid,
+                    description,
+                    tags: tag,
+                })
+                .map_err(|e| AppError::invalid_key(e.to_string()))?;
+            if usage != KeyUsage::Both {
+                k = vault
+                    .set_key_usage(&k.id, usage)
+                    .map_err(|e| AppError::invalid_key(e.to_string()))?;
+            }
+            if reveals && !p.exportable {
+                vault
+                    .record_audit(
+                        "reveal.non_exportable",
+                        Some(&p.id),
+                        json!({
+                            "key": { "id": k.id, "name": k.name },
+                            "reveal": reveal,
+                            "out": out.as_ref().map(|path| path.display().to_string()),
+                        }),
+                    )
+                    .map_err(|e| AppError::internal(e.to_string()))?;
+            }
+
+            if let Some(path) = &out {
+                std::fs::write(path, secret.as_bytes()).map_err(|e| {
+                    AppError::internal(format!("failed to write {}: {e}", path.display()))
+                })?;
+            }
+
+            let mut data = json!({ "key": k.clone(), "format": format });
+            if let Some(obj) = data.as_object_mut() {
+                if reveal {
+                    obj.insert("material".to_string(), json!(secret.clone()));
+                }
+                if let Some(path) = &out {
+                    obj.insert("path".to_string(), json!(path.display().to_string()));
+                }
+                if let Some(public) = generated.public {
+                    let public = public.with_kid(k.kid.as_deref());
+                    obj.insert("public_pem".to_string(), json!(public.pem));
+                    obj.insert("public_jwk".to_string(), public.jwk);
+                }
+            }
+
+            let mut text = format!("generated key: {} ({})", k.name, k.id);
+            if let Some(path) = out {
+                text.push_str(&format!("\nmaterial written to {}", path.display()));
+            }
+            if reveal {
+                text.push_str("\n\n");
+                text.push_str(&secret);
+            }
+            CommandOutput::new(data, text)
+        }
+        KeyCmd::CreatePlatform {
+            project,
+            name,
+            key_ref,
+            alg,
+            kid,
+            description,
+            tag,
+        } => {
+            let p = resolve_project_selector(vault, &project)?;
+            let external = platform_key_ref(&key_ref)?;
+            signer::create_platform_key(&external, alg.into())?;
+            let label = external.label();
+            let default_name = label
+                .split_once(':')
+                .map(|(_, keystore_name)| keystore_name.to_string())
+                .unwrap_or_default();
+            let k = vault
+                .add_key(KeyEntryInput {
+                    project_id: p.id,
+                    name: name.unwrap_or(default_name),
+                    kind: PLATFORM_KIND.to_string(),
+                    secret: label.clone(),
+                    kid,
+                    description,
+                    tags: tag,
+                })
+                .and_then(|k| vault.set_key_usage(&k.id, KeyUsage::Sign))
+                .map_err(|e| {
+                    AppError::invalid_key(format!(
+                        "{label} was created, but storing it failed: {e}"
+                    ))
+                })?;
+            CommandOutput::new(
+                json!({ "key": k, "ref": label }),
+                format!("created platform key {label}: {} ({})", k.name, k.id),
+            )
+        }
+        KeyCmd::SetUsage {
+            project,
+            key_id,
+            key_name,
+            usage,
+        } => {
+            let p = resolve_project_selector(vault, &project)?;
+            let usage = parse_key_usage(&usage)?;
+            let key = if let Some(id) = key_id {
+                vault
+                    .list_keys(Some(&p.id))
+                    .map_err(|e| AppError::invalid_key(e.to_string()))?
+                    .into_iter()
+                    .find(|k| k.id == id)
+                    .ok_or_else(|| AppError::not_found("key id not found in project"))?
+            } else {
+                let name = key_name.unwrap_or_default();
+                vault
+                    .find_key_in_project(&p.id, &name)
+                    .map_err(|e| AppError::invalid_key(e.to_string()))?
+                    .ok_or_else(|| AppError::not_found("key name not found in project"))?
+            };
+            if key.kind.eq_ignore_ascii_case(PLATFORM_KIND) && usage != KeyUsage::Sign {
+                return Err(platform_usage_error());
+            }
+            if usage.allows(KeyUsage::Sign) {
+                let material = vault
+                    .get_key_material(&key.id)
+                    .map_err(|e| AppError::invalid_key(e.to_string()))?;
+                if is_public_key_material(&material) {
+                    return Err(AppError::invalid_key(format!(
+                        "key {} is a public key and cannot sign; its usage must be verify",
+                        key.name
+                    )));
+                }
+            }
+            let key = vault
+                .set_key_usage(&key.id, usage)
+                .map_err(|e| AppError::invalid_key(e.to_string()))?;
+            CommandOutput::new(
+                json!({ "key": key, "usage": usage }),
+                format!("key {} may now be used to {}", key.name, usage_text(usage)),
+            )
+        }
+        KeyCmd::List {
+            project,
+            details,
+            listing,
+        } => {
+            let p = resolve_project_selector(vault, &project)?;
+            let keys = vault
+                .list_keys(Some(&p.id))
+                .map_err(|e| AppError::invalid_key(e.to_string()))?;
+            if let Some(text) = render_listing(&listing, &keys, KEY_COLUMNS)? {
+                return Ok(CommandOutput::new(json!({ "keys": keys }), text).with_records("keys"));
+            }
+            let mut lines = Vec::new();
+            for k in &keys {
+                let line = if details {
+                    let kid = opt_or_dash(k.kid.as_deref());
+                    let fingerprint = opt_or_dash(k.fingerprint.as_deref());
+                    let tags = format_tags(&k.tags);
+                    let desc = opt_or_dash(k.description.as_deref());
+                    format!(
+                        "{}  {}  {}  kid={} fingerprint={} usage={} tags={} desc={}",
+                        k.id,
+                        k.kind,
+                        k.name,
+                        kid,
+                        fingerprint,
+                        k.usage.as_str(),
+                        tags,
+                        desc
+                    )
+                } else if k.usage != KeyUsage::Both {
+                    format!(
+                        "{}  {}  {}  ({}-only)",
+                        k.id,
+                        k.kind,
+                        k.name,
+                        k.usage.as_str()
+                    )
+                } else {
+                    format!("{}  {}  {}", k.id, k.kind, k.name)
+                };
+                lines.push(line);
+            }
+            CommandOutput::new(json!({ "keys": keys }), lines.join("\n")).with_records("keys")
+        }
+        KeyCmd::Delete {
+            project: Some(project),
+            all: true,
+            kind,
+            ..
+        } => {
+            let p = resolve_project_selector(vault, &project)?;
+            let keys: Vec<KeyEntry> = vault
+                .list_keys(Some(&p.id))
+                .map_err(|e| AppError::invalid_key(e.to_string()))?
+                .into_iter()
+                .filter(|k| {
+                    kind.as_deref()
+                        .is_none_or(|kind| k.kind.eq_ignore_ascii_case(kind))
+                })
+                .collect();
+            let what = match &kind {
+                Some(kind) => format!("{} {kind} keys", keys.len()),
+                None => format!("{} keys", keys.len()),
+            };
+            if keys.is_empty() {
+                return Ok(CommandOutput::new(
+                    json!({ "deleted": [] }),
+                    format!("no keys to delete in project {}", p.name),
+                ));
+            }
+            prompt::require_confirmation(&format!("Delete {what} from project {}?", p.name), yes)?;
+            let ids: Vec<&str> = keys.iter().map(|k| k.id.as_str()).collect();
+            vault
+                .delete_keys(&ids)
+                .map_err(|e| AppError::invalid_key(e.to_string()))?;
+            let mut lines = vec![format!("deleted {what} from project {}", p.name)];
+            lines.extend(
+                keys.iter()
+                    .map(|k| format!("  {}  {}  {}", k.id, k.kind, k.name)),
+            );
+            CommandOutput::new(json!({ "deleted": ids }), lines.join("\n"))
+        }
+        KeyCmd::Delete {
+            id, project, name, ..
+        } => {
+            if id.is_some() && (project.is_some() || name.is_some()) {
+                return Err(AppError::invalid_key(
+                    "provide either a key id or --project/--name".to_string(),
+                ));
+            }
+            if let Some(id) = id {
+                vault
+                    .delete_key(&id)
+                    .map_err(|e| AppError::invalid_key(e.to_string()))?;
+                CommandOutput::new(json!({ "deleted": id }), format!("deleted key: {id}"))
+            } else {
+                let project = project.ok_or_else(|| {
+                    AppError::invalid_key("provide --project with --name".to_string())
+                })?;
+                let name = name.ok_or_else(|| {
+                    AppError::invalid_key("provide --name (or delete by id)".to_string())
+                })?;
+                let p = resolve_project_selector(vault, &project)?;
+                let key = resolve_named_key(vault, &p.id, &name)?;
+                vault
+                    .delete_key(&key.id)
+                    .map_err(|e| AppError::invalid_key(e.to_string()))?;
+                CommandOutput::new(
+                    json!({ "deleted": key.id }),
+                    format!("deleted key: {} ({})", key.name, key.id),
+                )
+            }
+        }
+    };
+    Ok(out)
+}
@@ -0,0 +1,203 @@
+use super::{format_bytes, opt_or_dash, render_listing};
+use crate::cli::ListFormatArgs;
+use crate::error::{AppError, AppResult};
+use crate::key_resolver::validate_key_material;
+use crate::output::CommandOutput;
+use crate::prompt;
+use crate::vault::Vault;
+use serde_json::json;
+use std::path::Path;
+
+const AUDIT_COLUMNS: &[&str] = &["id", "created_at", "action", "project_id", "detail"];
+
+/// `vault relocate`: copies the data directory, runs the integrity check against the copy, and
+/// only then removes the original. A failed check removes the copy instead.
+pub(super) fn relocate_vault(
+    vault: &Vault,
+    to: &Path,
+    keep_source: bool,
+    yes: bool,
+) -> AppResult<CommandOutput> {
+    if !keep_source {
+        prompt::require_confirmation(
+            &format!(
+                "Move the vault to {}? The current files are removed once the copy is verified.",
+                to.display()
+            ),
+            yes,
+        )?;
+    }
+    let (target, mut report) = vault
+        .relocate(to)
+        .map_err(|e| AppError::invalid_key(e.to_string()))?;
+    if let Err(err) = verify_integrity(&target) {
+        report.discard_copy();
+        let mut failed = AppError::invalid_key(format!(
+            "relocation aborted, {} is unchanged: {}",
+            report.from.display(),
+            err.message
+        ));
+        failed.details = err.details;
+        return Err(failed);
+    }
+    if !keep_source {
+        report
+            .remove_source()
+            .map_err(|e| AppError::internal(format!("copy verified, but {e}")))?;
+    }
+
+    let mut lines = vec![format!(
+        "{} {} file(s) ({}) from {} to {}",
+        if keep_source { "copied" } else { "moved" },
+        report.copied.len(),
+        format_bytes(report.bytes),
+        report.from.display(),
+        report.to.display()
+    )];
+    match &report.keychain_dir {
+        Some(dir) => lines.push(format!(
+            "file keychain moved to {}; update JWT_TESTER_KEYCHAIN_DIR if it is set",
+            dir.display()
+        )),
+        None => lines.push(format!(
+            "keychain ({} backend) left in place",
+            report.keychain_backend
+        )),
+    }
+    lines.push(format!(
+        "integrity verified; pass --data-dir {} from now on",
+        report.to.display()
+    ));
+    Ok(CommandOutput::new(
+        json!({ "relocated": report }),
+        lines.join("\n"),
+    ))
+}
+
+/// `vault bench-keychain`: latency percentiles per operation, to compare backends.
+pub(super) fn bench_keychain(vault: &Vault, iterations: usize) -> AppResult<CommandOutput> {
+    let bench = vault
+        .bench_keychain(iterations)
+        .map_err(|e| AppError::invalid_key(e.to_string()))?;
+    let mut lines = vec![
+        format!(
+            "keychain backend: {} (service {}, {} iterations)",
+            bench.backend, bench.service, bench.iterations
+        ),
+        format!(
+            "{:<8}{:>10}{:>10}{:>10}{:>10}{:>10}",
+            "op", "p50 ms", "p90 ms", "p99 ms", "max ms", "mean ms"
+        ),
+    ];
+    for (op, stats) in [
+        ("set", &bench.set),
+        ("get", &bench.get),
+        ("delete", &bench.delete),
+    ] {
+        lines.push(format!(
+            "{op:<8}{:>10.2}{:>10.2}{:>10.2}{:>10.2}{:>10.2}",
+            stats.p50_ms, stats.p90_ms, stats.p99_ms, stats.max_ms, stats.mean_ms
+        ));
+    }
+    Ok(CommandOutput::new(
+        json!({ "bench": bench }),
+        lines.join("\n"),
+    ))
+}
+
+/// Reads every key and token secret back from the keychain and parses key material as its
+/// declared kind. Any problem fails the command; the full list is in `error.details`.
+pub(super) fn verify_integrity(vault: &Vault) -> AppResult<CommandOutput> {
+    let keys = vault
+        .list_keys(None)
+        .map_err(|e| AppError::invalid_key(e.to_string()))?;
+    let tokens = vault
+        .list_tokens(None)
+        .map_err(|e| AppError::invalid_key(e.to_string()))?;
+
+    let mut problems = Vec::new();
+    for key in &keys {
+        let problem = match vault.get_key_material(&key.id) {
+            Ok(material) => validate_key_material(&key.kind, &material)
+                .err()
+                .map(|err| ("invalid_material", err.message)),
+            Err(err) => Some(("missing_secret", err.to_string())),
+        };
+        if let Some((problem, error)) = problem {
+            problems.push(json!({
+                "type": "key",
+                "id": key.id,
+                "project_id": key.project_id,
+                "name": key.name,
+                "kind": key.kind,
+                "problem": problem,
+                "error": error,
+            }));
+        }
+    }
+    for token in &tokens {
+        if let Err(err) = vault.get_token_material(&token.id) {
+            problems.push(json!({
+                "type": "token",
+                "id": token.id,
+                "project_id": token.project_id,
+                "name": token.name,
+                "problem": "missing_secret",
+                "error": err.to_string(),
+            }));
+        }
+    }
+
+    let checked = json!({ "keys": keys.len(), "tokens": tokens.len() });
+    if problems.is_empty() {
+        return Ok(CommandOutput::new(
+            json!({ "ok": true, "checked": checked, "problems": problems }),
+            format!(
+                "vault integrity ok: {} keys, {} tokens checked",
+                keys.len(),
+                tokens.len()
+            ),
+        ));
+    }
+    let mut message = format!(
+        "vault integrity check found {} problem(s) in {} keys, {} tokens",
+        problems.len(),
+        keys.len(),
+        tokens.len()
+    );
+    for problem in &problems {
+        message.push_str(&format!(
+            "\n  {} {} ({}): {}: {}",
+            problem["type"].as_str().unwrap_or_default(),
+            problem["name"].as_str().unwrap_or_default(),
+            problem["id"].as_str().unwrap_or_default(),
+            problem["problem"].as_str().unwrap_or_default(),
+            problem["error"].as_str().unwrap_or_default(),
+        ));
+    }
+    Err(AppError::invalid_key(message)
+        .with_details(json!({ "checked": checked, "problems": problems })))
+}
+
+/// `vault audit`: every recorded audit entry, newest first.
+pub(super) fn list_audit(vault: &Vault, listing: &ListFormatArgs) -> AppResult<CommandOutput> {
+    let entries = vault
+        .list_audit()
+        .map_err(|e| AppError::invalid_key(e.to_string()))?;
+    if let Some(text) = render_listing(listing, &entries, AUDIT_COLUMNS)? {
+        return Ok(CommandOutput::new(json!({ "entries": entries }), text).with_records("entries"));
+    }
+    let lines: Vec<String> = entries
+        .iter()
+        .map(|entry| {
+            format!(
+                "{}  {}  project={}  {}",
+                entry.created_at,
+                entry.action,
+                opt_or_dash(entry.project_id.as_deref()),
+                entry.detail
+            )
+        })
+        .collect();
+    Ok(CommandOutput::new(json!({ "entries": entries }), lines.join("\n")).with_records("entries"))
+}
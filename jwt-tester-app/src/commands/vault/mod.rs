@@ -0,0 +1,378 @@
+mod backup;
+mod import_env;
+mod jwks;
+mod key;
+mod maintenance;
+mod project;
+mod search;
+mod show;
+mod stats;
+mod token;
+mod webhook;
+
+#[cfg(feature = "keygen")]
+use crate::cli::KeyCmd;
+use crate::cli::{ListFormatArgs, OnConflict, VaultArgs, VaultCmd};
+use crate::error::{AppError, AppResult};
+use crate::io_utils::read_input;
+#[cfg(feature = "keygen")]
+use crate::keygen::DEFAULT_RSA_BITS;
+use crate::notify;
+use crate::output::{
+    emit_err, emit_ok, CommandOutput, OutputConfig, Progress, Table, CANCELLED_EXIT_CODE,
+};
+use crate::prompt;
+use crate::vault::{
+    ConflictStrategy, MergeActionKind, MergeReport, ProjectEntry, Vault, VaultConfig,
+};
+use crate::vault_export::{
+    list_bundle, unlock_for_bundle, BundleListing, BundleProtection, ExportBundle,
+};
+use serde_json::json;
+use std::path::PathBuf;
+use std::time::Instant;
+use tracing::{debug, warn};
+
+pub(crate) fn resolve_project_selector(vault: &Vault, selector: &str) -> AppResult<ProjectEntry> {
+    if let Some(project) = vault
+        .find_project_by_name(selector)
+        .map_err(|e| AppError::invalid_key(e.to_string()))?
+    {
+        return Ok(project);
+    }
+    if let Some(project) = vault
+        .find_project_by_id(selector)
+        .map_err(|e| AppError::invalid_key(e.to_string()))?
+    {
+        return Ok(project);
+    }
+    Err(AppError::not_found(format!(
+        "project not found: {selector}"
+    )))
+}
+
+fn format_tags(tags: &[String]) -> String {
+    if tags.is_empty() {
+        "-".to_string()
+    } else {
+        tags.join(",")
+    }
+}
+
+/// Renders a listing for `--format`; `None` keeps the classic line output.
+fn render_listing<T: serde::Serialize>(
+    listing: &ListFormatArgs,
+    records: &[T],
+    columns: &[&str],
+) -> AppResult<Option<String>> {
+    let Some(format) = listing.format else {
+        return Ok(None);
+    };
+    let table = Table::new(records, columns, &listing.columns)?;
+    Ok(Some(table.render(format)))
+}
+
+fn opt_or_dash(value: Option<&str>) -> &str {
+    value.unwrap_or("-")
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 3] = ["KiB", "MiB", "GiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+fn format_merge_report(report: &MergeReport) -> String {
+    let mut lines = Vec::with_capacity(report.actions.len() + 1);
+    for action in &report.actions {
+        let verb = match action.action {
+            MergeActionKind::Add => "add",
+            MergeActionKind::Merge => "merge",
+            MergeActionKind::Skip => "skip",
+            MergeActionKind::Overwrite => "overwrite",
+            MergeActionKind::Rename => "rename",
+        };
+        let mut line = if action.entity == "project" {
+            format!("{verb} project {}", action.name)
+        } else {
+            format!(
+                "{verb} {} {}/{}",
+                action.entity, action.project, action.name
+            )
+        };
+        if let Some(renamed) = &action.renamed_to {
+            line.push_str(&format!(" -> {renamed}"));
+        }
+        if let Some(kid) = &action.renamed_kid {
+            line.push_str(&format!(" (kid -> {kid})"));
+        }
+        if let Some(conflict) = &action.conflict {
+            line.push_str(&format!(" ({conflict})"));
+        }
+        lines.push(line);
+    }
+    lines.push(format!(
+        "{}added={} skipped={} overwritten={} renamed={}",
+        if report.dry_run { "dry run: " } else { "" },
+        report.summary.added,
+        report.summary.skipped,
+        report.summary.overwritten,
+        report.summary.renamed
+    ));
+    lines.join("\n")
+}
+
+fn format_bundle_listing(listing: &BundleListing) -> String {
+    let manifest = &listing.manifest;
+    let mut lines = vec![
+        format!(
+            "bundle v{} ({}) exported_at={} tool_version={}{}",
+            listing.version,
+            listing.cipher,
+            manifest.exported_at,
+            manifest.tool_version,
+            if listing.legacy {
+                " (legacy, no manifest)"
+            } else {
+                ""
+            }
+        ),
+        format!(
+            "projects={} keys={} tokens={} jwks={}",
+            manifest.counts.projects,
+            manifest.counts.keys,
+            manifest.counts.tokens,
+            manifest.counts.jwks
+        ),
+    ];
+    for entry in &manifest.entries {
+        let project = entry
+            .project_id
+            .as_deref()
+            .map(|id| format!(" project_id={id}"))
+            .unwrap_or_default();
+        lines.push(format!(
+            "{}  {}  {}{}",
+            entry.kind, entry.id, entry.name, project
+        ));
+    }
+    lines.join("\n")
+}
+
+/// How `--notify-done` names the vault commands that can keep a user waiting.
+fn long_operation(cmd: &VaultCmd) -> Option<String> {
+    match cmd {
+        #[cfg(feature = "keygen")]
+        VaultCmd::Key(KeyCmd::Generate { kind, rsa_bits, .. })
+            if kind.trim().eq_ignore_ascii_case("rsa") =>
+        {
+            Some(format!(
+                "RSA-{} key generation",
+                rsa_bits.unwrap_or(DEFAULT_RSA_BITS)
+            ))
+        }
+        VaultCmd::Export { .. } => Some("vault export".to_string()),
+        VaultCmd::Import { .. } => Some("vault import".to_string()),
+        _ => None,
+    }
+}
+
+/// `--vault-seed`: decrypts the bundle into the in-memory vault that every `--no-persist` open in
+/// this process then shares.
+pub fn seed(bundle: &str, passphrase: Option<&str>, identity: Option<&str>) -> AppResult<()> {
+    let raw = read_input(bundle)?;
+    let parsed: ExportBundle = serde_json::from_str(&raw)
+        .map_err(|e| AppError::invalid_key(format!("invalid --vault-seed bundle JSON: {e}")))?;
+    let passphrase = passphrase.map(read_input).transpose()?;
+    let identity = identity.map(read_input).transpose()?;
+    let unlock = unlock_for_bundle(&parsed, passphrase, identity)
+        .map_err(|e| AppError::invalid_key(format!("--vault-seed: {e}")))?;
+    let vault = Vault::seed_memory(&parsed, &unlock)
+        .map_err(|e| AppError::invalid_key(format!("--vault-seed: {e}")))?;
+    if let Ok(stats) = vault.stats() {
+        let totals = stats.totals;
+        debug!(
+            "seeded in-memory vault: {} project(s), {} key(s), {} token(s), {} JWKS",
+            totals.projects, totals.keys, totals.tokens, totals.jwks
+        );
+    }
+    Ok(())
+}
+
+pub fn run(no_persist: bool, data_dir: Option<PathBuf>, args: VaultArgs, cfg: OutputConfig) -> i32 {
+    let started = Instant::now();
+    let operation = long_operation(&args.cmd);
+    let result = (|| -> AppResult<CommandOutput> {
+        let vault = Vault::open(VaultConfig {
+            no_persist,
+            data_dir,
+        })
+        .map_err(|e| AppError::invalid_key(e.to_string()))?;
+
+        execute(&vault, args)
+    })();
+    if let Some(operation) = &operation {
+        notify::operation_finished(operation, started, result.is_ok());
+    }
+
+    // Batch commands stopped with Ctrl+C still report what they finished.
+    let result = result.and_then(|out| {
+        let cancelled = out.data["cancelled"] == true;
+        emit_ok(cfg, out).map(|()| cancelled)
+    });
+    match result {
+        Ok(cancelled) => {
+            if cancelled {
+                CANCELLED_EXIT_CODE
+            } else {
+                0
+            }
+        }
+        Err(err) => {
+            let code = err.exit_code();
+            emit_err(cfg, err);
+            code
+        }
+    }
+}
+
+pub(crate) fn execute(vault: &Vault, args: VaultArgs) -> AppResult<CommandOutput> {
+    let yes = args.yes;
+    let out = match args.cmd {
+        VaultCmd::Project(cmd) => project::execute(vault, cmd, yes)?,
+        VaultCmd::Key(cmd) => key::execute(vault, cmd, yes)?,
+        VaultCmd::Token(cmd) => token::execute(vault, cmd)?,
+        VaultCmd::Jwks(cmd) => jwks::execute(vault, cmd)?,
+        VaultCmd::Webhook(cmd) => webhook::execute(vault, cmd)?,
+        VaultCmd::Backup(cmd) => backup::execute(vault, cmd, yes)?,
+        VaultCmd::Search { query } => search::search_vault(vault, &query)?,
+        VaultCmd::VerifyIntegrity => maintenance::verify_integrity(vault)?,
+        VaultCmd::Stats => stats::vault_stats(vault)?,
+        VaultCmd::Audit { listing } => maintenance::list_audit(vault, &listing)?,
+        VaultCmd::Relocate { to, keep_source } => {
+            maintenance::relocate_vault(vault, &to, keep_source, yes)?
+        }
+        VaultCmd::BenchKeychain { iterations } => maintenance::bench_keychain(vault, iterations)?,
+        VaultCmd::Export {
+            out,
+            passphrase,
+            recipient,
+            gpg_recipient,
+            project,
+            include_non_exportable,
+        } => {
+            let protection = if !recipient.is_empty() {
+                BundleProtection::Age(recipient)
+            } else if !gpg_recipient.is_empty() {
+                BundleProtection::Gpg(gpg_recipient)
+            } else {
+                let passphrase = passphrase.ok_or_else(|| {
+                    AppError::invalid_key("provide --passphrase, --recipient, or --gpg-recipient")
+                })?;
+                BundleProtection::Passphrase(read_input(&passphrase)?)
+            };
+            let progress = Progress::spinner("encrypting bundle");
+            let report = match &project {
+                Some(selector) => {
+                    let p = resolve_project_selector(vault, selector)?;
+                    vault.export_project_bundle_with(&p.id, &protection, include_non_exportable)
+                }
+                None => vault.export_bundle_with(&protection, include_non_exportable),
+            }
+            .map_err(|e| AppError::invalid_key(e.to_string()))?;
+            drop(progress);
+            let bundle = report.bundle;
+            let skipped = if include_non_exportable {
+                Vec::new()
+            } else {
+                report.non_exportable_keys
+            };
+            // The bundle itself may be stdout, so the notice goes to the log (stderr).
+            if !skipped.is_empty() {
+                warn!(
+                    "skipped {} key(s) of non-exportable projects; --include-non-exportable exports them",
+                    skipped.len()
+                );
+            }
+            let bundle_value = serde_json::to_value(&bundle)
+                .map_err(|e| AppError::internal(format!("serialize bundle: {e}")))?;
+            let bundle_json = serde_json::to_string_pretty(&bundle)
+                .map_err(|e| AppError::internal(format!("serialize bundle: {e}")))?;
+
+            if let Some(path) = out {
+                std::fs::write(&path, bundle_json.as_bytes())
+                    .map_err(|e| AppError::internal(format!("failed to write {path:?}: {e}")))?;
+                CommandOutput::new(
+                    json!({ "path": path, "skipped_keys": skipped }),
+                    format!("exported vault to {}", path.display()),
+                )
+            } else {
+                CommandOutput::new(
+                    json!({ "bundle": bundle_value, "skipped_keys": skipped }),
+                    bundle_json,
+                )
+            }
+        }
+        VaultCmd::Import {
+            bundle,
+            passphrase,
+            identity,
+            replace,
+            list,
+            merge,
+            on_conflict,
+            dry_run,
+        } => {
+            let raw = read_input(&bundle)?;
+            let parsed: ExportBundle = serde_json::from_str(&raw)
+                .map_err(|e| AppError::invalid_key(format!("invalid bundle JSON: {e}")))?;
+            let passphrase = passphrase.as_deref().map(read_input).transpose()?;
+            let identity = identity.as_deref().map(read_input).transpose()?;
+            let progress = Progress::spinner("decrypting bundle");
+            let unlock = unlock_for_bundle(&parsed, passphrase, identity)
+                .map_err(|e| AppError::invalid_key(e.to_string()))?;
+            drop(progress);
+            if list {
+                let listing = list_bundle(&parsed, &unlock)
+                    .map_err(|e| AppError::invalid_key(e.to_string()))?;
+                let text = format_bundle_listing(&listing);
+                let data = serde_json::to_value(&listing)
+                    .map_err(|e| AppError::internal(format!("serialize listing: {e}")))?;
+                return Ok(CommandOutput::new(data, text));
+            }
+            if merge {
+                let strategy = match on_conflict {
+                    OnConflict::Skip => ConflictStrategy::Skip,
+                    OnConflict::Overwrite => ConflictStrategy::Overwrite,
+                    OnConflict::Rename => ConflictStrategy::Rename,
+                };
+                let report = vault
+                    .merge_bundle(&parsed, &unlock, strategy, dry_run)
+                    .map_err(|e| AppError::invalid_key(e.to_string()))?;
+                let text = format_merge_report(&report);
+                let data = serde_json::to_value(&report)
+                    .map_err(|e| AppError::internal(format!("serialize merge report: {e}")))?;
+                return Ok(CommandOutput::new(data, text));
+            }
+            if replace {
+                prompt::require_confirmation(
+                    "Replace the whole vault with the bundle contents?",
+                    yes,
+                )?;
+            }
+            vault
+                .import_bundle_with(&parsed, &unlock, replace)
+                .map_err(|e| AppError::invalid_key(e.to_string()))?;
+            CommandOutput::new(json!({ "imported": true }), "imported vault".to_string())
+        }
+    };
+    Ok(out)
+}
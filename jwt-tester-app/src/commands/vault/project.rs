@@ -0,0 +1,311 @@
+use super::{format_tags, opt_or_dash, render_listing, resolve_project_selector, show};
+use crate::claim_plugin;
+use crate::cli::ProjectCmd;
+use crate::error::{AppError, AppResult};
+use crate::output::CommandOutput;
+use crate::prompt;
+use crate::vault::{FamilyPolicy, ProjectInput, Vault};
+use serde_json::json;
+
+const PROJECT_COLUMNS: &[&str] = &[
+    "id",
+    "name",
+    "default_key_id",
+    "tags",
+    "description",
+    "created_at",
+];
+
+pub(super) fn format_family_policy(family: &str, policy: &FamilyPolicy) -> String {
+    format!(
+        "{family}: headers={} claims={} issuers={}",
+        format_tags(&policy.required_headers),
+        format_tags(&policy.required_claims),
+        format_tags(&policy.allowed_issuers)
+    )
+}
+
+fn policy_names(values: Vec<String>) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    for value in values {
+        let value = value.trim();
+        if !value.is_empty() && !out.iter().any(|existing| existing == value) {
+            out.push(value.to_string());
+        }
+    }
+    out
+}
+
+pub(super) fn execute(vault: &Vault, cmd: ProjectCmd, yes: bool) -> AppResult<CommandOutput> {
+    let out = match cmd {
+        ProjectCmd::Add {
+            name,
+            description,
+            tag,
+            non_exportable,
+        } => {
+            let mut p = vault
+                .add_project(ProjectInput {
+                    name,
+                    description,
+                    tags: tag,
+                })
+                .map_err(|e| AppError::invalid_key(e.to_string()))?;
+            if non_exportable {
+                vault
+                    .set_project_exportable(&p.id, false)
+                    .map_err(|e| AppError::invalid_key(e.to_string()))?;
+                p.exportable = false;
+            }
+            CommandOutput::new(
+                json!({ "project": p }),
+                format!("created project: {} ({})", p.name, p.id),
+            )
+        }
+        ProjectCmd::List { details, listing } => {
+            let list = vault
+                .list_projects()
+                .map_err(|e| AppError::invalid_key(e.to_string()))?;
+            if let Some(text) = render_listing(&listing, &list, PROJECT_COLUMNS)? {
+                return Ok(
+                    CommandOutput::new(json!({ "projects": list }), text).with_records("projects")
+                );
+            }
+            let mut lines = Vec::new();
+            for p in &list {
+                let line = if details {
+                    let default = opt_or_dash(p.default_key_id.as_deref());
+                    let tags = format_tags(&p.tags);
+                    let desc = opt_or_dash(p.description.as_deref());
+                    format!(
+                        "{}  {}  default_key_id={} tags={} desc={}",
+                        p.id, p.name, default, tags, desc
+                    )
+                } else {
+                    let default = p
+                        .default_key_id
+                        .as_deref()
+                        .map(|id| format!(" default_key_id={id}"))
+                        .unwrap_or_default();
+                    format!("{}  {}{}", p.id, p.name, default)
+                };
+                lines.push(line);
+            }
+            CommandOutput::new(json!({ "projects": list }), lines.join("\n"))
+                .with_records("projects")
+        }
+        ProjectCmd::Delete { id, name } => {
+            if id.is_some() && name.is_some() {
+                return Err(AppError::invalid_key(
+                    "provide either a project id or --name".to_string(),
+                ));
+            }
+            let project = if let Some(name) = name {
+                vault
+                    .find_project_by_name(&name)
+                    .map_err(|e| AppError::invalid_key(e.to_string()))?
+                    .ok_or_else(|| AppError::not_found(format!("project not found: {name}")))?
+            } else if let Some(id) = id {
+                vault
+                    .find_project_by_id(&id)
+                    .map_err(|e| AppError::invalid_key(e.to_string()))?
+                    .ok_or_else(|| AppError::not_found(format!("project not found: {id}")))?
+            } else {
+                return Err(AppError::invalid_key(
+                    "provide a project id or --name".to_string(),
+                ));
+            };
+            prompt::require_confirmation(
+                &format!(
+                    "Delete project {} with all its keys, tokens and JWKS?",
+                    project.name
+                ),
+                yes,
+            )?;
+            let report = vault
+                .delete_project(&project.id)
+                .map_err(|e| AppError::invalid_key(e.to_string()))?;
+            let mut text = format!(
+                "deleted project: {} ({}) with {} keys, {} tokens, {} JWKS",
+                project.name, project.id, report.keys, report.tokens, report.jwks
+            );
+            for failure in &report.keychain_failures {
+                text.push_str(&format!(
+                    "\nwarning: keychain entry {} was not removed: {}",
+                    failure.account, failure.error
+                ));
+            }
+            CommandOutput::new(
+                json!({
+                    "deleted": project.id,
+                    "removed": {
+                        "keys": report.keys,
+                        "tokens": report.tokens,
+                        "jwks": report.jwks,
+                        "webhooks": report.webhooks,
+                    },
+                    "keychain_failures": report.keychain_failures,
+                }),
+                text,
+            )
+        }
+        ProjectCmd::Show { project } => show::show_project(vault, &project)?,
+        ProjectCmd::Clone { source, as_name } => {
+            let p = resolve_project_selector(vault, &source)?;
+            let report = vault
+                .clone_project(&p.id, &as_name)
+                .map_err(|e| AppError::invalid_key(e.to_string()))?;
+            CommandOutput::new(
+                json!({
+                    "source": p.id,
+                    "project": report.project,
+                    "copied": { "keys": report.keys, "jwks": report.jwks },
+                }),
+                format!(
+                    "cloned project {} as {} ({}) with {} keys, {} JWKS",
+                    p.name, report.project.name, report.project.id, report.keys, report.jwks
+                ),
+            )
+        }
+        ProjectCmd::SetDefaultKey {
+            project,
+            key_id,
+            key_name,
+            clear,
+        } => {
+            let p = resolve_project_selector(vault, &project)?;
+
+            if clear {
+                vault
+                    .set_default_key(&p.id, None)
+                    .map_err(|e| AppError::invalid_key(e.to_string()))?;
+                return Ok(CommandOutput::new(
+                    json!({ "project": p.id, "default_key_id": null }),
+                    format!("cleared default key for project {}", p.name),
+                ));
+            }
+
+            let keys = vault
+                .list_keys(Some(&p.id))
+                .map_err(|e| AppError::invalid_key(e.to_string()))?;
+            let key = if let Some(id) = key_id {
+                keys.into_iter()
+                    .find(|k| k.id == id)
+                    .ok_or_else(|| AppError::not_found("key id not found in project"))?
+            } else if let Some(name) = key_name {
+                vault
+                    .find_key_in_project(&p.id, &name)
+                    .map_err(|e| AppError::invalid_key(e.to_string()))?
+                    .ok_or_else(|| AppError::not_found("key name not found in project"))?
+            } else {
+                return Err(AppError::invalid_key(
+                    "provide --key-id or --key-name (or use --clear)",
+                ));
+            };
+
+            vault
+                .set_default_key(&p.id, Some(&key.id))
+                .map_err(|e| AppError::invalid_key(e.to_string()))?;
+            CommandOutput::new(
+                json!({ "project": p.id, "default_key_id": key.id }),
+                format!(
+                    "set default key for project {} to {} ({})",
+                    p.name, key.name, key.id
+                ),
+            )
+        }
+        ProjectCmd::SetExportable {
+            project,
+            exportable,
+        } => {
+            let p = resolve_project_selector(vault, &project)?;
+            vault
+                .set_project_exportable(&p.id, exportable)
+                .map_err(|e| AppError::invalid_key(e.to_string()))?;
+            let state = if exportable {
+                "exportable"
+            } else {
+                "non-exportable"
+            };
+            CommandOutput::new(
+                json!({ "project": p.id, "exportable": exportable }),
+                format!("project {} is now {state}", p.name),
+            )
+        }
+        ProjectCmd::SetWasmModule {
+            project,
+            path,
+            clear,
+        } => {
+            let p = resolve_project_selector(vault, &project)?;
+            let (module, hooks) = match path.filter(|_| !clear) {
+                Some(path) => {
+                    let path = std::fs::canonicalize(&path).map_err(|e| {
+                        AppError::invalid_key(format!("failed to read {}: {e}", path.display()))
+                    })?;
+                    let module = path.to_string_lossy().into_owned();
+                    let hooks = claim_plugin::hooks(&module)?;
+                    (Some(module), hooks)
+                }
+                None => (None, Vec::new()),
+            };
+            vault
+                .set_project_wasm_module(&p.id, module.as_deref())
+                .map_err(|e| AppError::invalid_key(e.to_string()))?;
+            let text = match &module {
+                Some(module) => format!(
+                    "project {} now runs {module} ({})",
+                    p.name,
+                    hooks.join(", ")
+                ),
+                None => format!("project {} no longer runs a wasm module", p.name),
+            };
+            CommandOutput::new(
+                json!({ "project": p.id, "wasm_module": module, "hooks": hooks }),
+                text,
+            )
+        }
+        ProjectCmd::SetPolicy {
+            project,
+            family,
+            require_header,
+            require_claim,
+            allow_issuer,
+            clear,
+        } => {
+            let p = resolve_project_selector(vault, &project)?;
+            let family = family.as_str();
+            let mut policy = p.policy.clone();
+            let text = if clear {
+                policy.remove(family);
+                format!("cleared {family} policy for project {}", p.name)
+            } else {
+                let rules = FamilyPolicy {
+                    required_headers: policy_names(require_header),
+                    required_claims: policy_names(require_claim),
+                    allowed_issuers: policy_names(allow_issuer),
+                };
+                if rules == FamilyPolicy::default() {
+                    return Err(AppError::invalid_key(
+                        "provide --require-header, --require-claim or --allow-issuer (or use --clear)",
+                    ));
+                }
+                let text = format!(
+                    "set policy for project {}: {}",
+                    p.name,
+                    format_family_policy(family, &rules)
+                );
+                policy.insert(family.to_string(), rules);
+                text
+            };
+            vault
+                .set_project_policy(&p.id, &policy)
+                .map_err(|e| AppError::invalid_key(e.to_string()))?;
+            CommandOutput::new(
+                json!({ "project": p.id, "family": family, "policy": policy.get(family) }),
+                text,
+            )
+        }
+    };
+    Ok(out)
+}
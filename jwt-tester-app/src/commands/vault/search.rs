@@ -0,0 +1,118 @@
+use crate::error::{AppError, AppResult};
+use crate::output::CommandOutput;
+use crate::vault::Vault;
+use serde_json::json;
+
+/// Case-insensitive substring search over the fields people remember: project names, key names,
+/// kids, descriptions and tags, token names, and JWKS names and kids.
+pub(super) fn search_vault(vault: &Vault, query: &str) -> AppResult<CommandOutput> {
+    let needle = query.trim().to_lowercase();
+    if needle.is_empty() {
+        return Err(AppError::invalid_key("search query is empty"));
+    }
+    let projects = vault
+        .list_projects()
+        .map_err(|e| AppError::invalid_key(e.to_string()))?;
+    let project_name = |id: &str| {
+        projects
+            .iter()
+            .find(|p| p.id == id)
+            .map(|p| p.name.clone())
+            .unwrap_or_default()
+    };
+    let first_match = |fields: Vec<(&'static str, Option<&str>)>| {
+        fields.into_iter().find_map(|(field, value)| {
+            value
+                .filter(|value| value.to_lowercase().contains(&needle))
+                .map(|value| (field, value.to_string()))
+        })
+    };
+
+    let mut results = Vec::new();
+    let mut push = |kind: &str, id: &str, name: &str, project_id: &str, found| {
+        if let Some((field, value)) = found {
+            results.push(json!({
+                "type": kind,
+                "id": id,
+                "name": name,
+                "project_id": project_id,
+                "project": project_name(project_id),
+                "field": field,
+                "value": value,
+            }));
+        }
+    };
+    for p in &projects {
+        push(
+            "project",
+            &p.id,
+            &p.name,
+            &p.id,
+            first_match(vec![("name", Some(&p.name))]),
+        );
+    }
+    for k in vault
+        .list_keys(None)
+        .map_err(|e| AppError::invalid_key(e.to_string()))?
+    {
+        let mut fields = vec![
+            ("name", Some(k.name.as_str())),
+            ("kid", k.kid.as_deref()),
+            ("description", k.description.as_deref()),
+        ];
+        fields.extend(k.tags.iter().map(|tag| ("tag", Some(tag.as_str()))));
+        push("key", &k.id, &k.name, &k.project_id, first_match(fields));
+    }
+    for t in vault
+        .list_tokens(None)
+        .map_err(|e| AppError::invalid_key(e.to_string()))?
+    {
+        push(
+            "token",
+            &t.id,
+            &t.name,
+            &t.project_id,
+            first_match(vec![("name", Some(&t.name))]),
+        );
+    }
+    for j in vault
+        .list_jwks(None)
+        .map_err(|e| AppError::invalid_key(e.to_string()))?
+    {
+        let mut fields = vec![("name", Some(j.name.as_str()))];
+        fields.extend(j.kids.iter().map(|kid| ("kid", Some(kid.as_str()))));
+        push("jwks", &j.id, &j.name, &j.project_id, first_match(fields));
+    }
+
+    let text = if results.is_empty() {
+        format!("no matches for '{query}'")
+    } else {
+        results
+            .iter()
+            .map(|r| {
+                let field = r["field"].as_str().unwrap_or_default();
+                let location = if r["type"] == "project" {
+                    String::new()
+                } else {
+                    format!("  project={}", r["project"].as_str().unwrap_or_default())
+                };
+                let matched = if field == "name" {
+                    String::new()
+                } else {
+                    format!("  {field}={}", r["value"].as_str().unwrap_or_default())
+                };
+                format!(
+                    "{:<7}  {}  {}{location}{matched}",
+                    r["type"].as_str().unwrap_or_default(),
+                    r["id"].as_str().unwrap_or_default(),
+                    r["name"].as_str().unwrap_or_default(),
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    Ok(CommandOutput::new(
+        json!({ "query": query, "results": results }),
+        text,
+    ))
+}
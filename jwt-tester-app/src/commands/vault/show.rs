@@ -0,0 +1,135 @@
+use super::project::format_family_policy;
+use super::{format_tags, opt_or_dash, resolve_project_selector};
+use crate::claims::now_epoch;
+use crate::date_utils::{format_timestamp, DateMode};
+use crate::error::{AppError, AppResult};
+use crate::jwt_ops;
+use crate::output::CommandOutput;
+use crate::vault::Vault;
+use serde_json::json;
+
+/// Everything `vault project show` reports. Stored tokens are decoded (not verified) for their
+/// `exp`; the statuses match `monitor`'s.
+pub(super) fn show_project(vault: &Vault, selector: &str) -> AppResult<CommandOutput> {
+    let project = resolve_project_selector(vault, selector)?;
+    let keys = vault
+        .list_keys(Some(&project.id))
+        .map_err(|e| AppError::invalid_key(e.to_string()))?;
+    let tokens = vault
+        .list_tokens(Some(&project.id))
+        .map_err(|e| AppError::invalid_key(e.to_string()))?;
+    let jwks = vault
+        .list_jwks(Some(&project.id))
+        .map_err(|e| AppError::invalid_key(e.to_string()))?;
+    let now = now_epoch();
+
+    let default_key = keys
+        .iter()
+        .find(|k| project.default_key_id.as_deref() == Some(k.id.as_str()));
+    let mut lines = vec![
+        format!("project: {} ({})", project.name, project.id),
+        format!(
+            "created: {}",
+            format_timestamp(project.created_at, DateMode::Utc)?
+        ),
+        format!(
+            "description: {}",
+            opt_or_dash(project.description.as_deref())
+        ),
+        format!("tags: {}", format_tags(&project.tags)),
+        format!(
+            "default key: {}",
+            default_key
+                .map(|k| format!("{} ({})", k.name, k.id))
+                .unwrap_or_else(|| "-".to_string())
+        ),
+        format!(
+            "exportable: {}",
+            if project.exportable { "yes" } else { "no" }
+        ),
+        format!(
+            "wasm module: {}",
+            opt_or_dash(project.wasm_module.as_deref())
+        ),
+    ];
+    if project.policy.is_empty() {
+        lines.push("policy: -".to_string());
+    } else {
+        lines.push("policy:".to_string());
+        for (family, rules) in &project.policy {
+            lines.push(format!("  {}", format_family_policy(family, rules)));
+        }
+    }
+
+    let mut key_rows = Vec::new();
+    lines.push(format!("keys ({}):", keys.len()));
+    for key in &keys {
+        let fingerprint = vault
+            .key_fingerprint(key)
+            .map_err(|e| AppError::invalid_key(e.to_string()))?;
+        let is_default = project.default_key_id.as_deref() == Some(key.id.as_str());
+        lines.push(format!(
+            "  {}  {}  {}  kid={} fingerprint={}{}",
+            key.id,
+            key.kind,
+            key.name,
+            opt_or_dash(key.kid.as_deref()),
+            opt_or_dash(fingerprint.as_deref()),
+            if is_default { "  (default)" } else { "" }
+        ));
+        let mut row = json!(key);
+        row["fingerprint"] = json!(fingerprint);
+        row["default"] = json!(is_default);
+        key_rows.push(row);
+    }
+
+    let mut token_rows = Vec::new();
+    lines.push(format!("tokens ({}):", tokens.len()));
+    for token in &tokens {
+        let (status, exp) = match vault
+            .get_token_material(&token.id)
+            .map_err(|e| AppError::invalid_key(e.to_string()))
+            .and_then(|material| jwt_ops::decode_unverified(&material))
+        {
+            Ok(decoded) => match decoded.payload_json.get("exp").and_then(|v| v.as_i64()) {
+                Some(exp) if exp <= now => ("expired", Some(exp)),
+                Some(exp) => ("valid", Some(exp)),
+                None => ("no-expiry", None),
+            },
+            Err(_) => ("unreadable", None),
+        };
+        let exp_text = match exp {
+            Some(exp) => format!("  exp={}", format_timestamp(exp, DateMode::Utc)?),
+            None => String::new(),
+        };
+        lines.push(format!(
+            "  {}  {}  {status}{exp_text}",
+            token.id, token.name
+        ));
+        let mut row = json!(token);
+        row["status"] = json!(status);
+        row["expires_at"] = json!(exp);
+        token_rows.push(row);
+    }
+
+    lines.push(format!("jwks ({}):", jwks.len()));
+    for entry in &jwks {
+        lines.push(format!(
+            "  {}  {}  kids={} source={}",
+            entry.id,
+            entry.name,
+            format_tags(&entry.kids),
+            opt_or_dash(entry.source_url.as_deref())
+        ));
+    }
+
+    Ok(CommandOutput::new(
+        json!({
+            "project": project,
+            "keys": key_rows,
+            "tokens": token_rows,
+            "jwks": jwks,
+        }),
+        lines.join("\n"),
+    ))
+}
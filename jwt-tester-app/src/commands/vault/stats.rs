@@ -0,0 +1,84 @@
+use super::format_bytes;
+use crate::date_utils::{format_timestamp, DateMode};
+use crate::error::{AppError, AppResult};
+use crate::output::CommandOutput;
+use crate::vault::Vault;
+use serde_json::json;
+
+/// `vault stats`: per-project counts and what the vault takes up on disk.
+pub(super) fn vault_stats(vault: &Vault) -> AppResult<CommandOutput> {
+    let stats = vault
+        .stats()
+        .map_err(|e| AppError::invalid_key(e.to_string()))?;
+    let date = |ts: Option<i64>| -> AppResult<String> {
+        ts.map_or(Ok("-".to_string()), |ts| {
+            format_timestamp(ts, DateMode::Utc)
+        })
+    };
+    let mut lines = vec![format!(
+        "{:<24}{:>6}{:>8}{:>6}  {:<22}{}",
+        "project", "keys", "tokens", "jwks", "oldest", "newest"
+    )];
+    for project in &stats.projects {
+        lines.push(format!(
+            "{:<24}{:>6}{:>8}{:>6}  {:<22}{}",
+            project.name,
+            project.keys,
+            project.tokens,
+            project.jwks,
+            date(project.oldest)?,
+            date(project.newest)?
+        ));
+        if !project.keys_by_kind.is_empty() {
+            lines.push(format!("  keys: {}", format_kinds(&project.keys_by_kind)));
+        }
+    }
+    let totals = &stats.totals;
+    let mut total = format!(
+        "total: {} project(s), {} key(s)",
+        totals.projects, totals.keys
+    );
+    if !totals.keys_by_kind.is_empty() {
+        total.push_str(&format!(" ({})", format_kinds(&totals.keys_by_kind)));
+    }
+    total.push_str(&format!(
+        ", {} token(s), {} JWKS",
+        totals.tokens, totals.jwks
+    ));
+    lines.push(total);
+    match &stats.storage {
+        Some(storage) => {
+            lines.push(format!(
+                "database: {} ({})",
+                storage.db_path.display(),
+                format_bytes(storage.db_bytes)
+            ));
+            lines.push(match (&storage.keychain_dir, storage.keychain_bytes) {
+                (Some(dir), Some(bytes)) => format!(
+                    "keychain: {} backend, {} ({})",
+                    storage.keychain_backend,
+                    dir.display(),
+                    format_bytes(bytes)
+                ),
+                _ => format!(
+                    "keychain: {} backend (size not available)",
+                    storage.keychain_backend
+                ),
+            });
+            lines.push(format!("backups: {}", format_bytes(storage.backups_bytes)));
+        }
+        None => lines.push("storage: in memory (--no-persist)".to_string()),
+    }
+    Ok(CommandOutput::new(
+        json!({ "stats": stats }),
+        lines.join("\n"),
+    ))
+}
+
+fn format_kinds(kinds: &std::collections::BTreeMap<String, usize>) -> String {
+    kinds
+        .iter()
+        .map(|(kind, count)| format!("{kind}={count}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
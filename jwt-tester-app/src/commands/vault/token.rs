@@ -0,0 +1,198 @@
+use super::{opt_or_dash, render_listing, resolve_project_selector};
+use crate::cli::TokenCmd;
+use crate::error::{AppError, AppResult};
+use crate::io_utils::read_input;
+use crate::jwt_ops;
+use crate::output::CommandOutput;
+use crate::vault::{ProjectEntry, RevocationInput, TokenEntry, TokenEntryInput, Vault};
+use serde_json::json;
+
+const TOKEN_COLUMNS: &[&str] = &["id", "name", "created_at"];
+
+const REVOCATION_COLUMNS: &[&str] = &["id", "jti", "token_hash", "token_id", "created_at"];
+
+fn resolve_named_token(vault: &Vault, project_id: &str, name: &str) -> AppResult<TokenEntry> {
+    let tokens = vault
+        .list_tokens(Some(project_id))
+        .map_err(|e| AppError::invalid_key(e.to_string()))?;
+    let matches: Vec<_> = tokens
+        .into_iter()
+        .filter(|token| token.name == name)
+        .collect();
+    if matches.is_empty() {
+        return Err(AppError::not_found(
+            "token name not found in project".to_string(),
+        ));
+    }
+    if matches.len() > 1 {
+        return Err(AppError::invalid_key(format!(
+            "multiple tokens named '{name}' found; use token id"
+        )));
+    }
+    Ok(matches.into_iter().next().expect("single match"))
+}
+
+/// `vault token revoke`: a stored token id revokes that token's hash and `jti`; anything else is
+/// taken as a `jti` in the `--project` project.
+fn revocation_input(
+    vault: &Vault,
+    target: &str,
+    scope: Option<ProjectEntry>,
+) -> AppResult<RevocationInput> {
+    let stored = vault
+        .list_tokens(scope.as_ref().map(|p| p.id.as_str()))
+        .map_err(|e| AppError::invalid_key(e.to_string()))?
+        .into_iter()
+        .find(|t| t.id == target);
+    if let Some(stored) = stored {
+        let material = vault
+            .get_token_material(&stored.id)
+            .map_err(|e| AppError::invalid_key(e.to_string()))?;
+        let jti = jwt_ops::decode_unverified(&material)
+            .ok()
+            .and_then(|decoded| {
+                decoded
+                    .payload_json
+                    .get("jti")
+                    .and_then(serde_json::Value::as_str)
+                    .map(str::to_string)
+            });
+        return Ok(RevocationInput {
+            project_id: stored.project_id,
+            jti,
+            token: Some(material),
+            token_id: Some(stored.id),
+        });
+    }
+    let project = scope.ok_or_else(|| {
+        AppError::not_found(format!(
+            "no stored token with id {target}; pass --project to revoke it as a jti"
+        ))
+    })?;
+    Ok(RevocationInput {
+        project_id: project.id,
+        jti: Some(target.to_string()),
+        token: None,
+        token_id: None,
+    })
+}
+
+pub(super) fn execute(vault: &Vault, cmd: TokenCmd) -> AppResult<CommandOutput> {
+    let out = match cmd {
+        TokenCmd::Add {
+            project,
+            name,
+            token,
+        } => {
+            let p = resolve_project_selector(vault, &project)?;
+            let token = read_input(&token)?;
+            let t = vault
+                .add_token(TokenEntryInput {
+                    project_id: p.id,
+                    name,
+                    token,
+                })
+                .map_err(|e| AppError::invalid_key(e.to_string()))?;
+            CommandOutput::new(
+                json!({ "token": t }),
+                format!("created token: {} ({})", t.name, t.id),
+            )
+        }
+        TokenCmd::List {
+            project,
+            details,
+            listing,
+        } => {
+            let p = resolve_project_selector(vault, &project)?;
+            let tokens = vault
+                .list_tokens(Some(&p.id))
+                .map_err(|e| AppError::invalid_key(e.to_string()))?;
+            if let Some(text) = render_listing(&listing, &tokens, TOKEN_COLUMNS)? {
+                return Ok(
+                    CommandOutput::new(json!({ "tokens": tokens }), text).with_records("tokens")
+                );
+            }
+            let mut lines = Vec::new();
+            for t in &tokens {
+                let line = if details {
+                    format!("{}  {}  created_at={}", t.id, t.name, t.created_at)
+                } else {
+                    format!("{}  {}", t.id, t.name)
+                };
+                lines.push(line);
+            }
+            CommandOutput::new(json!({ "tokens": tokens }), lines.join("\n")).with_records("tokens")
+        }
+        TokenCmd::Delete { id, project, name } => {
+            if id.is_some() && (project.is_some() || name.is_some()) {
+                return Err(AppError::invalid_key(
+                    "provide either a token id or --project/--name".to_string(),
+                ));
+            }
+            if let Some(id) = id {
+                vault
+                    .delete_token(&id)
+                    .map_err(|e| AppError::invalid_key(e.to_string()))?;
+                CommandOutput::new(json!({ "deleted": id }), format!("deleted token: {id}"))
+            } else {
+                let project = project.ok_or_else(|| {
+                    AppError::invalid_key("provide --project with --name".to_string())
+                })?;
+                let name = name.ok_or_else(|| {
+                    AppError::invalid_key("provide --name (or delete by id)".to_string())
+                })?;
+                let p = resolve_project_selector(vault, &project)?;
+                let token = resolve_named_token(vault, &p.id, &name)?;
+                vault
+                    .delete_token(&token.id)
+                    .map_err(|e| AppError::invalid_key(e.to_string()))?;
+                CommandOutput::new(
+                    json!({ "deleted": token.id }),
+                    format!("deleted token: {} ({})", token.name, token.id),
+                )
+            }
+        }
+        TokenCmd::Revoke { target, project } => {
+            let scope = project
+                .as_deref()
+                .map(|selector| resolve_project_selector(vault, selector))
+                .transpose()?;
+            let input = revocation_input(vault, &target, scope)?;
+            let revocation = vault
+                .revoke(input)
+                .map_err(|e| AppError::invalid_key(e.to_string()))?;
+            let text = format!(
+                "revoked: jti={} sha256={}",
+                opt_or_dash(revocation.jti.as_deref()),
+                opt_or_dash(revocation.token_hash.as_deref())
+            );
+            CommandOutput::new(json!({ "revocation": revocation }), text)
+        }
+        TokenCmd::Revoked { project, listing } => {
+            let p = resolve_project_selector(vault, &project)?;
+            let revocations = vault
+                .list_revocations(&p.id)
+                .map_err(|e| AppError::invalid_key(e.to_string()))?;
+            if let Some(text) = render_listing(&listing, &revocations, REVOCATION_COLUMNS)? {
+                return Ok(
+                    CommandOutput::new(json!({ "revocations": revocations }), text)
+                        .with_records("revocations"),
+                );
+            }
+            let lines: Vec<String> = revocations
+                .iter()
+                .map(|r| {
+                    format!(
+                        "{}  jti={}  sha256={}",
+                        r.id,
+                        opt_or_dash(r.jti.as_deref()),
+                        opt_or_dash(r.token_hash.as_deref())
+                    )
+                })
+                .collect();
+            CommandOutput::new(json!({ "revocations": revocations }), lines.join("\n"))
+                .with_records("revocations")
+        }
+    };
+    Ok(out)
+}
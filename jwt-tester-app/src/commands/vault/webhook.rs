@@ -0,0 +1,93 @@
+use super::{render_listing, resolve_project_selector};
+use crate::cli::VaultWebhookCmd;
+use crate::error::{AppError, AppResult};
+use crate::io_utils::read_input;
+use crate::output::CommandOutput;
+use crate::vault::{Vault, WebhookEntryInput};
+use rand::RngCore;
+use serde_json::json;
+
+const WEBHOOK_COLUMNS: &[&str] = &["id", "url", "events", "created_at"];
+
+/// Bytes of randomness in a generated `vault webhook add` secret.
+const WEBHOOK_SECRET_BYTES: usize = 32;
+
+fn format_webhook_events(events: &[String]) -> String {
+    if events.is_empty() {
+        "all".to_string()
+    } else {
+        events.join(",")
+    }
+}
+
+pub(super) fn execute(vault: &Vault, cmd: VaultWebhookCmd) -> AppResult<CommandOutput> {
+    let out = match cmd {
+        VaultWebhookCmd::Add {
+            project,
+            url,
+            events,
+            secret,
+        } => {
+            let p = resolve_project_selector(vault, &project)?;
+            let (secret, generated) = match secret {
+                Some(spec) => (read_input(&spec)?, false),
+                None => {
+                    let mut raw = [0u8; WEBHOOK_SECRET_BYTES];
+                    rand::rngs::OsRng.fill_bytes(&mut raw);
+                    (hex::encode(raw), true)
+                }
+            };
+            let hook = vault
+                .add_webhook(WebhookEntryInput {
+                    project_id: p.id,
+                    url,
+                    events,
+                    secret: secret.clone(),
+                })
+                .map_err(|e| AppError::invalid_key(e.to_string()))?;
+            let mut text = format!(
+                "created webhook: {} -> {} (events={})",
+                hook.id,
+                hook.url,
+                format_webhook_events(&hook.events)
+            );
+            let mut data = json!({ "webhook": hook });
+            if generated {
+                text.push_str(&format!("\nsigning secret (shown once): {secret}"));
+                data["secret"] = json!(secret);
+            }
+            CommandOutput::new(data, text)
+        }
+        VaultWebhookCmd::List { project, listing } => {
+            let p = resolve_project_selector(vault, &project)?;
+            let hooks = vault
+                .list_webhooks(Some(&p.id))
+                .map_err(|e| AppError::invalid_key(e.to_string()))?;
+            if let Some(text) = render_listing(&listing, &hooks, WEBHOOK_COLUMNS)? {
+                return Ok(
+                    CommandOutput::new(json!({ "webhooks": hooks }), text).with_records("webhooks")
+                );
+            }
+            let lines: Vec<String> = hooks
+                .iter()
+                .map(|w| {
+                    format!(
+                        "{}  {}  events={}",
+                        w.id,
+                        w.url,
+                        format_webhook_events(&w.events)
+                    )
+                })
+                .collect();
+            CommandOutput::new(json!({ "webhooks": hooks }), lines.join("\n"))
+                .with_records("webhooks")
+        }
+        VaultWebhookCmd::Delete { id } => {
+            vault
+                .delete_webhook(&id)
+                .map_err(|e| AppError::not_found(e.to_string()))?;
+            CommandOutput::new(json!({ "deleted": id }), format!("deleted webhook: {id}"))
+        }
+    };
+    Ok(out)
+}
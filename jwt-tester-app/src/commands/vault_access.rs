@@ -0,0 +1,79 @@
+use crate::cli::VerifyCommonArgs;
+use crate::encode_request::EncodeRequest;
+use crate::error::{AppError, AppResult};
+use crate::key_resolver::{resolve_encoding_key, resolve_encoding_key_with_vault};
+use crate::signer::SigningKey;
+use crate::vault::{Vault, VaultConfig};
+use crate::verify_service::{self, Verified};
+use std::path::PathBuf;
+
+/// How a command reaches the vault: opened for this one invocation (only if a key has to come
+/// from it), or the vault `--rpc` keeps open across requests.
+#[derive(Clone, Copy)]
+pub enum VaultAccess<'a> {
+    Open {
+        no_persist: bool,
+        data_dir: &'a Option<PathBuf>,
+    },
+    Shared(&'a Vault),
+}
+
+impl VaultAccess<'_> {
+    pub fn verify(self, args: &VerifyCommonArgs, token: &str) -> AppResult<Verified> {
+        match self {
+            VaultAccess::Open {
+                no_persist,
+                data_dir,
+            } => verify_service::verify(no_persist, data_dir.clone(), args, token),
+            VaultAccess::Shared(vault) => verify_service::verify_with_vault(vault, args, token),
+        }
+    }
+
+    /// See [`verify_service::verify_untimed`].
+    pub fn verify_untimed(self, args: &VerifyCommonArgs, token: &str) -> AppResult<Verified> {
+        match self {
+            VaultAccess::Open {
+                no_persist,
+                data_dir,
+            } => verify_service::verify_untimed(no_persist, data_dir.clone(), args, token),
+            VaultAccess::Shared(vault) => {
+                verify_service::verify_untimed_with_vault(vault, args, token)
+            }
+        }
+    }
+
+    pub fn encoding_key(self, request: &EncodeRequest) -> AppResult<(SigningKey, String)> {
+        match self {
+            VaultAccess::Open {
+                no_persist,
+                data_dir,
+            } => resolve_encoding_key(no_persist, data_dir.clone(), request),
+            VaultAccess::Shared(vault) => resolve_encoding_key_with_vault(vault, request),
+        }
+    }
+
+    /// The WASM module `project` transforms claims with, if any. An unknown project has none;
+    /// key resolution reports it when its keys are needed.
+    pub fn project_wasm_module(self, project: &str) -> AppResult<Option<String>> {
+        let find = |vault: &Vault| {
+            vault
+                .find_project_by_name(project)
+                .map(|found| found.and_then(|p| p.wasm_module))
+                .map_err(|e| AppError::invalid_key(e.to_string()))
+        };
+        match self {
+            VaultAccess::Open {
+                no_persist,
+                data_dir,
+            } => {
+                let vault = Vault::open(VaultConfig {
+                    no_persist,
+                    data_dir: data_dir.clone(),
+                })
+                .map_err(|e| AppError::invalid_key(e.to_string()))?;
+                find(&vault)
+            }
+            VaultAccess::Shared(vault) => find(vault),
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use super::vault::execute;
-use crate::cli::{KeyCmd, ProjectCmd, TokenCmd, VaultArgs, VaultCmd};
+use crate::cli::{KeyCmd, ListFormatArgs, OnConflict, ProjectCmd, TokenCmd, VaultArgs, VaultCmd};
 use crate::error::ErrorKind;
-use crate::vault::{Vault, VaultConfig};
+use crate::vault::{KeyEntryInput, ProjectInput, TokenEntryInput, Vault, VaultConfig};
 
 fn memory_vault() -> Vault {
     Vault::open(VaultConfig {
@@ -17,10 +17,12 @@ fn execute_project_add_list_delete() {
     let add = execute(
         &vault,
         VaultArgs {
+            yes: false,
             cmd: VaultCmd::Project(ProjectCmd::Add {
                 name: "alpha".to_string(),
                 description: Some("notes".to_string()),
                 tag: vec!["one".to_string()],
+                non_exportable: false,
             }),
         },
     )
@@ -30,7 +32,11 @@ fn execute_project_add_list_delete() {
     let list = execute(
         &vault,
         VaultArgs {
-            cmd: VaultCmd::Project(ProjectCmd::List { details: false }),
+            yes: false,
+            cmd: VaultCmd::Project(ProjectCmd::List {
+                details: false,
+                listing: ListFormatArgs::default(),
+            }),
         },
     )
     .expect("list projects");
@@ -39,6 +45,7 @@ fn execute_project_add_list_delete() {
     let delete = execute(
         &vault,
         VaultArgs {
+            yes: true,
             cmd: VaultCmd::Project(ProjectCmd::Delete {
                 id: Some(project_id.to_string()),
                 name: None,
@@ -51,7 +58,11 @@ fn execute_project_add_list_delete() {
     let list = execute(
         &vault,
         VaultArgs {
-            cmd: VaultCmd::Project(ProjectCmd::List { details: false }),
+            yes: false,
+            cmd: VaultCmd::Project(ProjectCmd::List {
+                details: false,
+                listing: ListFormatArgs::default(),
+            }),
         },
     )
     .expect("list projects");
@@ -64,10 +75,12 @@ fn execute_set_default_key_variants() {
     execute(
         &vault,
         VaultArgs {
+            yes: false,
             cmd: VaultCmd::Project(ProjectCmd::Add {
                 name: "alpha".to_string(),
                 description: None,
                 tag: Vec::new(),
+                non_exportable: false,
             }),
         },
     )
@@ -76,6 +89,7 @@ fn execute_set_default_key_variants() {
     let key_out = execute(
         &vault,
         VaultArgs {
+            yes: false,
             cmd: VaultCmd::Key(KeyCmd::Add {
                 project: "alpha".to_string(),
                 name: Some("primary".to_string()),
@@ -83,7 +97,11 @@ fn execute_set_default_key_variants() {
                 kid: Some("kid1".to_string()),
                 description: None,
                 tag: Vec::new(),
-                secret: "secret".to_string(),
+                secret: Some("secret".to_string()),
+                from_env: None,
+                allow_public: false,
+                usage: None,
+                min_hmac_bytes: None,
             }),
         },
     )
@@ -93,6 +111,7 @@ fn execute_set_default_key_variants() {
     let set_default = execute(
         &vault,
         VaultArgs {
+            yes: false,
             cmd: VaultCmd::Project(ProjectCmd::SetDefaultKey {
                 project: "alpha".to_string(),
                 key_id: Some(key_id.to_string()),
@@ -107,6 +126,7 @@ fn execute_set_default_key_variants() {
     let clear = execute(
         &vault,
         VaultArgs {
+            yes: false,
             cmd: VaultCmd::Project(ProjectCmd::SetDefaultKey {
                 project: "alpha".to_string(),
                 key_id: None,
@@ -121,6 +141,7 @@ fn execute_set_default_key_variants() {
     let key_out = execute(
         &vault,
         VaultArgs {
+            yes: false,
             cmd: VaultCmd::Key(KeyCmd::Add {
                 project: "alpha".to_string(),
                 name: Some("named".to_string()),
@@ -128,7 +149,11 @@ fn execute_set_default_key_variants() {
                 kid: None,
                 description: None,
                 tag: Vec::new(),
-                secret: "secret".to_string(),
+                secret: Some("secret".to_string()),
+                from_env: None,
+                allow_public: false,
+                usage: None,
+                min_hmac_bytes: None,
             }),
         },
     )
@@ -138,6 +163,7 @@ fn execute_set_default_key_variants() {
     let set_by_name = execute(
         &vault,
         VaultArgs {
+            yes: false,
             cmd: VaultCmd::Project(ProjectCmd::SetDefaultKey {
                 project: "alpha".to_string(),
                 key_id: None,
@@ -152,6 +178,7 @@ fn execute_set_default_key_variants() {
     let err = execute(
         &vault,
         VaultArgs {
+            yes: false,
             cmd: VaultCmd::Project(ProjectCmd::SetDefaultKey {
                 project: "alpha".to_string(),
                 key_id: None,
@@ -170,10 +197,12 @@ fn execute_key_token_export_import() {
     execute(
         &vault,
         VaultArgs {
+            yes: false,
             cmd: VaultCmd::Project(ProjectCmd::Add {
                 name: "alpha".to_string(),
                 description: None,
                 tag: Vec::new(),
+                non_exportable: false,
             }),
         },
     )
@@ -182,6 +211,7 @@ fn execute_key_token_export_import() {
     let key = execute(
         &vault,
         VaultArgs {
+            yes: false,
             cmd: VaultCmd::Key(KeyCmd::Add {
                 project: "alpha".to_string(),
                 name: None,
@@ -189,7 +219,11 @@ fn execute_key_token_export_import() {
                 kid: None,
                 description: None,
                 tag: Vec::new(),
-                secret: "secret".to_string(),
+                secret: Some("secret".to_string()),
+                from_env: None,
+                allow_public: false,
+                usage: None,
+                min_hmac_bytes: None,
             }),
         },
     )
@@ -199,9 +233,11 @@ fn execute_key_token_export_import() {
     let list_keys = execute(
         &vault,
         VaultArgs {
+            yes: false,
             cmd: VaultCmd::Key(KeyCmd::List {
                 project: "alpha".to_string(),
                 details: false,
+                listing: ListFormatArgs::default(),
             }),
         },
     )
@@ -211,6 +247,7 @@ fn execute_key_token_export_import() {
     let token = execute(
         &vault,
         VaultArgs {
+            yes: false,
             cmd: VaultCmd::Token(TokenCmd::Add {
                 project: "alpha".to_string(),
                 name: "t1".to_string(),
@@ -224,9 +261,11 @@ fn execute_key_token_export_import() {
     let list_tokens = execute(
         &vault,
         VaultArgs {
+            yes: false,
             cmd: VaultCmd::Token(TokenCmd::List {
                 project: "alpha".to_string(),
                 details: false,
+                listing: ListFormatArgs::default(),
             }),
         },
     )
@@ -236,9 +275,14 @@ fn execute_key_token_export_import() {
     let export = execute(
         &vault,
         VaultArgs {
+            yes: false,
             cmd: VaultCmd::Export {
                 out: None,
-                passphrase: "passphrase".to_string(),
+                passphrase: Some("passphrase".to_string()),
+                recipient: Vec::new(),
+                gpg_recipient: Vec::new(),
+                project: None,
+                include_non_exportable: false,
             },
         },
     )
@@ -247,10 +291,16 @@ fn execute_key_token_export_import() {
     let import = execute(
         &vault,
         VaultArgs {
+            yes: true,
             cmd: VaultCmd::Import {
                 bundle: export.text.clone(),
-                passphrase: "passphrase".to_string(),
+                passphrase: Some("passphrase".to_string()),
+                identity: None,
                 replace: true,
+                list: false,
+                merge: false,
+                on_conflict: OnConflict::Skip,
+                dry_run: false,
             },
         },
     )
@@ -260,6 +310,7 @@ fn execute_key_token_export_import() {
     let delete_token = execute(
         &vault,
         VaultArgs {
+            yes: false,
             cmd: VaultCmd::Token(TokenCmd::Delete {
                 id: Some(token_id.to_string()),
                 project: None,
@@ -273,10 +324,13 @@ fn execute_key_token_export_import() {
     let delete_key = execute(
         &vault,
         VaultArgs {
+            yes: false,
             cmd: VaultCmd::Key(KeyCmd::Delete {
                 id: Some(key_id.to_string()),
                 project: None,
                 name: None,
+                all: false,
+                kind: None,
             }),
         },
     )
@@ -290,10 +344,12 @@ fn execute_project_delete_by_name() {
     let add = execute(
         &vault,
         VaultArgs {
+            yes: false,
             cmd: VaultCmd::Project(ProjectCmd::Add {
                 name: "alpha".to_string(),
                 description: None,
                 tag: Vec::new(),
+                non_exportable: false,
             }),
         },
     )
@@ -303,6 +359,7 @@ fn execute_project_delete_by_name() {
     let deleted = execute(
         &vault,
         VaultArgs {
+            yes: true,
             cmd: VaultCmd::Project(ProjectCmd::Delete {
                 id: None,
                 name: Some("alpha".to_string()),
@@ -319,10 +376,12 @@ fn execute_project_list_details_includes_tags() {
     execute(
         &vault,
         VaultArgs {
+            yes: false,
             cmd: VaultCmd::Project(ProjectCmd::Add {
                 name: "alpha".to_string(),
                 description: Some("notes".to_string()),
                 tag: vec!["one".to_string(), "two".to_string()],
+                non_exportable: false,
             }),
         },
     )
@@ -331,7 +390,11 @@ fn execute_project_list_details_includes_tags() {
     let list = execute(
         &vault,
         VaultArgs {
-            cmd: VaultCmd::Project(ProjectCmd::List { details: true }),
+            yes: false,
+            cmd: VaultCmd::Project(ProjectCmd::List {
+                details: true,
+                listing: ListFormatArgs::default(),
+            }),
         },
     )
     .expect("list details");
@@ -345,10 +408,12 @@ fn execute_key_list_accepts_project_id() {
     let project = execute(
         &vault,
         VaultArgs {
+            yes: false,
             cmd: VaultCmd::Project(ProjectCmd::Add {
                 name: "alpha".to_string(),
                 description: None,
                 tag: Vec::new(),
+                non_exportable: false,
             }),
         },
     )
@@ -358,6 +423,7 @@ fn execute_key_list_accepts_project_id() {
     execute(
         &vault,
         VaultArgs {
+            yes: false,
             cmd: VaultCmd::Key(KeyCmd::Add {
                 project: "alpha".to_string(),
                 name: Some("primary".to_string()),
@@ -365,7 +431,11 @@ fn execute_key_list_accepts_project_id() {
                 kid: None,
                 description: None,
                 tag: Vec::new(),
-                secret: "secret".to_string(),
+                secret: Some("secret".to_string()),
+                from_env: None,
+                allow_public: false,
+                usage: None,
+                min_hmac_bytes: None,
             }),
         },
     )
@@ -374,9 +444,11 @@ fn execute_key_list_accepts_project_id() {
     let list = execute(
         &vault,
         VaultArgs {
+            yes: false,
             cmd: VaultCmd::Key(KeyCmd::List {
                 project: project_id.to_string(),
                 details: false,
+                listing: ListFormatArgs::default(),
             }),
         },
     )
@@ -390,10 +462,12 @@ fn execute_key_delete_by_name() {
     execute(
         &vault,
         VaultArgs {
+            yes: false,
             cmd: VaultCmd::Project(ProjectCmd::Add {
                 name: "alpha".to_string(),
                 description: None,
                 tag: Vec::new(),
+                non_exportable: false,
             }),
         },
     )
@@ -402,6 +476,7 @@ fn execute_key_delete_by_name() {
     let key = execute(
         &vault,
         VaultArgs {
+            yes: false,
             cmd: VaultCmd::Key(KeyCmd::Add {
                 project: "alpha".to_string(),
                 name: Some("primary".to_string()),
@@ -409,7 +484,11 @@ fn execute_key_delete_by_name() {
                 kid: None,
                 description: None,
                 tag: Vec::new(),
-                secret: "secret".to_string(),
+                secret: Some("secret".to_string()),
+                from_env: None,
+                allow_public: false,
+                usage: None,
+                min_hmac_bytes: None,
             }),
         },
     )
@@ -419,10 +498,13 @@ fn execute_key_delete_by_name() {
     let deleted = execute(
         &vault,
         VaultArgs {
+            yes: false,
             cmd: VaultCmd::Key(KeyCmd::Delete {
                 id: None,
                 project: Some("alpha".to_string()),
                 name: Some("primary".to_string()),
+                all: false,
+                kind: None,
             }),
         },
     )
@@ -436,10 +518,12 @@ fn execute_token_delete_by_name() {
     execute(
         &vault,
         VaultArgs {
+            yes: false,
             cmd: VaultCmd::Project(ProjectCmd::Add {
                 name: "alpha".to_string(),
                 description: None,
                 tag: Vec::new(),
+                non_exportable: false,
             }),
         },
     )
@@ -448,6 +532,7 @@ fn execute_token_delete_by_name() {
     let token = execute(
         &vault,
         VaultArgs {
+            yes: false,
             cmd: VaultCmd::Token(TokenCmd::Add {
                 project: "alpha".to_string(),
                 name: "t1".to_string(),
@@ -461,6 +546,7 @@ fn execute_token_delete_by_name() {
     let deleted = execute(
         &vault,
         VaultArgs {
+            yes: false,
             cmd: VaultCmd::Token(TokenCmd::Delete {
                 id: None,
                 project: Some("alpha".to_string()),
@@ -471,3 +557,350 @@ fn execute_token_delete_by_name() {
     .expect("delete token by name");
     assert_eq!(deleted.data["deleted"], token_id);
 }
+
+#[test]
+fn execute_export_import_with_age_recipient() {
+    use age::secrecy::ExposeSecret;
+
+    let vault = memory_vault();
+    execute(
+        &vault,
+        VaultArgs {
+            yes: false,
+            cmd: VaultCmd::Project(ProjectCmd::Add {
+                name: "alpha".to_string(),
+                description: None,
+                tag: Vec::new(),
+                non_exportable: false,
+            }),
+        },
+    )
+    .expect("add project");
+
+    let identity = age::x25519::Identity::generate();
+    let export = execute(
+        &vault,
+        VaultArgs {
+            yes: false,
+            cmd: VaultCmd::Export {
+                out: None,
+                passphrase: None,
+                recipient: vec![identity.to_public().to_string()],
+                gpg_recipient: Vec::new(),
+                project: None,
+                include_non_exportable: false,
+            },
+        },
+    )
+    .expect("export vault");
+    assert_eq!(export.data["bundle"]["cipher"], "age");
+
+    let missing_identity = execute(
+        &vault,
+        VaultArgs {
+            yes: false,
+            cmd: VaultCmd::Import {
+                bundle: export.text.clone(),
+                passphrase: Some("ignored".to_string()),
+                identity: None,
+                replace: true,
+                list: false,
+                merge: false,
+                on_conflict: OnConflict::Skip,
+                dry_run: false,
+            },
+        },
+    )
+    .expect_err("identity required");
+    assert_eq!(missing_identity.kind, ErrorKind::InvalidKey);
+
+    let listing = execute(
+        &vault,
+        VaultArgs {
+            yes: false,
+            cmd: VaultCmd::Import {
+                bundle: export.text.clone(),
+                passphrase: None,
+                identity: Some(identity.to_string().expose_secret().to_string()),
+                replace: false,
+                list: true,
+                merge: false,
+                on_conflict: OnConflict::Skip,
+                dry_run: false,
+            },
+        },
+    )
+    .expect("list bundle");
+    assert_eq!(listing.data["manifest"]["counts"]["projects"], 1);
+    assert_eq!(listing.data["manifest"]["entries"][0]["name"], "alpha");
+    assert!(listing.text.contains("project  "));
+
+    let target = memory_vault();
+    execute(
+        &target,
+        VaultArgs {
+            yes: false,
+            cmd: VaultCmd::Import {
+                bundle: export.text.clone(),
+                passphrase: None,
+                identity: Some(identity.to_string().expose_secret().to_string()),
+                replace: false,
+                list: false,
+                merge: false,
+                on_conflict: OnConflict::Skip,
+                dry_run: false,
+            },
+        },
+    )
+    .expect("import vault");
+    assert_eq!(target.list_projects().expect("projects").len(), 1);
+}
+
+#[test]
+fn execute_verify_integrity_flags_material_of_the_wrong_kind() {
+    let vault = memory_vault();
+    let project = vault
+        .add_project(ProjectInput {
+            name: "alpha".to_string(),
+            description: None,
+            tags: Vec::new(),
+        })
+        .expect("project");
+    for (name, kind, secret) in [("ok", "hmac", "secret"), ("broken", "rsa", "not a pem")] {
+        vault
+            .add_key(KeyEntryInput {
+                project_id: project.id.clone(),
+                name: name.to_string(),
+                kind: kind.to_string(),
+                secret: secret.to_string(),
+                kid: None,
+                description: None,
+                tags: Vec::new(),
+            })
+            .expect("add key");
+    }
+
+    let Err(err) = execute(
+        &vault,
+        VaultArgs {
+            yes: false,
+            cmd: VaultCmd::VerifyIntegrity,
+        },
+    ) else {
+        panic!("expected integrity problems");
+    };
+    assert_eq!(err.kind, ErrorKind::InvalidKey);
+    let details = err.details.expect("details");
+    assert_eq!(details["checked"]["keys"], 2);
+    let problems = details["problems"].as_array().expect("problems");
+    assert_eq!(problems.len(), 1);
+    assert_eq!(problems[0]["name"], "broken");
+    assert_eq!(problems[0]["problem"], "invalid_material");
+}
+
+#[test]
+fn execute_project_clone_and_bulk_key_delete() {
+    let vault = memory_vault();
+    let project = vault
+        .add_project(ProjectInput {
+            name: "alpha".to_string(),
+            description: Some("staging".to_string()),
+            tags: vec!["env".to_string()],
+        })
+        .expect("add project");
+    for (name, kind) in [("h1", "hmac"), ("h2", "hmac"), ("r1", "rsa")] {
+        let key = vault
+            .add_key(KeyEntryInput {
+                project_id: project.id.clone(),
+                name: name.to_string(),
+                kind: kind.to_string(),
+                secret: format!("{name}-material"),
+                kid: Some(format!("kid-{name}")),
+                description: None,
+                tags: Vec::new(),
+            })
+            .expect("add key");
+        if name == "r1" {
+            vault
+                .set_default_key(&project.id, Some(&key.id))
+                .expect("set default");
+        }
+    }
+
+    let cloned = execute(
+        &vault,
+        VaultArgs {
+            yes: false,
+            cmd: VaultCmd::Project(ProjectCmd::Clone {
+                source: "alpha".to_string(),
+                as_name: "alpha-copy".to_string(),
+            }),
+        },
+    )
+    .expect("clone project");
+    assert_eq!(cloned.data["copied"]["keys"], 3);
+    assert_eq!(cloned.data["project"]["tags"][0], "env");
+    let copy_id = cloned.data["project"]["id"].as_str().unwrap().to_string();
+    let copies = vault.list_keys(Some(&copy_id)).expect("list copies");
+    let default_copy = copies.iter().find(|k| k.name == "r1").unwrap();
+    assert_eq!(
+        cloned.data["project"]["default_key_id"],
+        default_copy.id.as_str()
+    );
+    assert_eq!(
+        vault.get_key_material(&default_copy.id).unwrap(),
+        "r1-material"
+    );
+
+    let clone_again = execute(
+        &vault,
+        VaultArgs {
+            yes: false,
+            cmd: VaultCmd::Project(ProjectCmd::Clone {
+                source: "alpha".to_string(),
+                as_name: "alpha-copy".to_string(),
+            }),
+        },
+    );
+    assert!(clone_again.is_err());
+
+    let deleted = execute(
+        &vault,
+        VaultArgs {
+            yes: true,
+            cmd: VaultCmd::Key(KeyCmd::Delete {
+                id: None,
+                project: Some("alpha-copy".to_string()),
+                name: None,
+                all: true,
+                kind: Some("hmac".to_string()),
+            }),
+        },
+    )
+    .expect("bulk delete");
+    assert_eq!(deleted.data["deleted"].as_array().unwrap().len(), 2);
+    let left = vault.list_keys(Some(&copy_id)).expect("list copies");
+    assert_eq!(left.len(), 1);
+    assert_eq!(left[0].name, "r1");
+    assert_eq!(vault.list_keys(Some(&project.id)).unwrap().len(), 3);
+}
+
+#[test]
+fn execute_project_show_aggregates_keys_and_tokens() {
+    let vault = memory_vault();
+    let project = vault
+        .add_project(ProjectInput {
+            name: "alpha".to_string(),
+            description: Some("staging".to_string()),
+            tags: Vec::new(),
+        })
+        .expect("add project");
+    let key = vault
+        .add_key(KeyEntryInput {
+            project_id: project.id.clone(),
+            name: "primary".to_string(),
+            kind: "hmac".to_string(),
+            secret: "secret".to_string(),
+            kid: Some("k1".to_string()),
+            description: None,
+            tags: Vec::new(),
+        })
+        .expect("add key");
+    vault
+        .set_default_key(&project.id, Some(&key.id))
+        .expect("set default");
+    let header = jsonwebtoken::Header::new(jsonwebtoken::Algorithm::HS256);
+    let signing_key = jsonwebtoken::EncodingKey::from_secret(b"secret");
+    for (name, claims) in [
+        ("old", serde_json::json!({ "exp": 1000 })),
+        ("forever", serde_json::json!({ "sub": "svc" })),
+    ] {
+        vault
+            .add_token(TokenEntryInput {
+                project_id: project.id.clone(),
+                name: name.to_string(),
+                token: crate::jwt_ops::encode_token(&header, &claims, &signing_key).unwrap(),
+            })
+            .expect("add token");
+    }
+
+    let show = execute(
+        &vault,
+        VaultArgs {
+            yes: false,
+            cmd: VaultCmd::Project(ProjectCmd::Show {
+                project: "alpha".to_string(),
+            }),
+        },
+    )
+    .expect("show project");
+    assert_eq!(show.data["project"]["description"], "staging");
+    assert_eq!(show.data["keys"][0]["default"], true);
+    assert_eq!(show.data["keys"][0]["kid"], "k1");
+    let statuses: Vec<&str> = show.data["tokens"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["status"].as_str().unwrap())
+        .collect();
+    assert!(statuses.contains(&"expired") && statuses.contains(&"no-expiry"));
+    assert!(show.text.contains("default key: primary"));
+}
+
+#[test]
+fn execute_search_matches_across_projects() {
+    let vault = memory_vault();
+    for name in ["billing-prod", "auth-prod"] {
+        let project = vault
+            .add_project(ProjectInput {
+                name: name.to_string(),
+                description: None,
+                tags: Vec::new(),
+            })
+            .expect("add project");
+        vault
+            .add_key(KeyEntryInput {
+                project_id: project.id,
+                name: "signing".to_string(),
+                kind: "hmac".to_string(),
+                secret: "secret".to_string(),
+                kid: Some(format!("{name}-2024")),
+                description: None,
+                tags: vec!["Rotation".to_string()],
+            })
+            .expect("add key");
+    }
+
+    let search = |query: &str| {
+        execute(
+            &vault,
+            VaultArgs {
+                yes: false,
+                cmd: VaultCmd::Search {
+                    query: query.to_string(),
+                },
+            },
+        )
+        .expect("search")
+    };
+    let found = search("AUTH-PROD-2024");
+    let results = found.data["results"].as_array().unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["type"], "key");
+    assert_eq!(results[0]["field"], "kid");
+    assert_eq!(results[0]["project"], "auth-prod");
+
+    let found = search("prod");
+    let types: Vec<&str> = found.data["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| r["type"].as_str().unwrap())
+        .collect();
+    assert_eq!(types, ["project", "project", "key", "key"]);
+    assert_eq!(search("rotation").data["results"][0]["field"], "tag");
+    assert!(search("nothing").data["results"]
+        .as_array()
+        .unwrap()
+        .is_empty());
+}
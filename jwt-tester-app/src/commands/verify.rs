@@ -1,11 +1,22 @@
-use crate::cli::{JwtAlg, VerifyArgs, VerifyCommonArgs};
-use crate::error::{AppError, AppResult, ErrorKind};
-use crate::io_utils::read_input;
-use crate::jwt_ops::{self, VerifyOptions};
-use crate::key_resolver::{resolve_verification_key, KeySource};
-use crate::output::{emit_err, emit_ok, CommandOutput, OutputConfig};
-use serde_json::json;
-use std::path::PathBuf;
+use crate::claim_lint::claim_warnings;
+use crate::claims::now_epoch;
+use crate::cli::{VerifyArgs, VerifyCommonArgs, VerifyProfile};
+use crate::commands::vault_access::VaultAccess;
+use crate::date_utils::{format_timestamp, normalize_timestamps, timestamp_warning, DateMode};
+use crate::error::{AppError, AppResult};
+use crate::history;
+use crate::io_utils::{read_token_at_path, read_token_input};
+use crate::jwt_ops;
+use crate::output::{emit_err, emit_ok, paint, CommandOutput, OutputConfig, OutputMode, Tone};
+use crate::presets;
+use crate::tenants;
+use crate::verify_script;
+use clap::ValueEnum;
+use serde_json::{json, Map, Value};
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+mod sweep;
 
 pub fn run(
     no_persist: bool,
@@ -13,18 +24,45 @@ pub fn run(
     args: VerifyArgs,
     cfg: OutputConfig,
 ) -> i32 {
-    let result = (|| -> AppResult<CommandOutput> {
-        let token = read_input(&args.token)?;
-        let outcome = verify_token_with_args(no_persist, data_dir, &args.verify, &token)?;
-        Ok(CommandOutput::new(outcome.data, outcome.text))
-    })();
+    let access = VaultAccess::Open {
+        no_persist,
+        data_dir: &data_dir,
+    };
+    let result = execute(access, &args, cfg);
+    let (ok, summary) = match &result {
+        Ok(out) if out.data["valid"] == false => (false, "claims invalid".to_string()),
+        Ok(_) => (true, "valid".to_string()),
+        Err(err) => (false, err.to_string()),
+    };
+    history::record_cli(
+        no_persist,
+        data_dir,
+        "verify",
+        history_params(&args),
+        ok,
+        &summary,
+    );
 
+    let result = result.and_then(|out| {
+        let code = exit_code(&out);
+        emit_ok(cfg, out).map(|()| code)
+    });
     match result {
-        Ok(out) => {
-            emit_ok(cfg, out);
-            0
-        }
-        Err(err) => {
+        Ok(code) => code,
+        Err(mut err) => {
+            if args.verify.explain && matches!(cfg.mode, OutputMode::Text) {
+                if let Some(checklist) = claim_checklist(&err, "checks") {
+                    err.message = format!("{}\n{checklist}", err.message);
+                }
+                let explained = err
+                    .details
+                    .as_ref()
+                    .map(|details| explain_lines(&details["explain"]))
+                    .unwrap_or_default();
+                if !explained.is_empty() {
+                    err.message = format!("{}\n{}", err.message, explained.join("\n"));
+                }
+            }
             let code = err.exit_code();
             emit_err(cfg, err);
             code
@@ -32,153 +70,369 @@ pub fn run(
     }
 }
 
-pub struct VerifyOutcome {
-    pub data: serde_json::Value,
-    pub text: String,
+/// Exit code for a successful `execute`: `--continue-on-claim-failure` prints the claims but still
+/// fails like the claims error would.
+pub(crate) fn exit_code(out: &CommandOutput) -> i32 {
+    if out.data["valid"] == false {
+        AppError::invalid_claims("").exit_code()
+    } else {
+        0
+    }
 }
 
-pub fn verify_token_with_args(
-    no_persist: bool,
-    data_dir: Option<PathBuf>,
-    args: &VerifyCommonArgs,
-    token: &str,
-) -> AppResult<VerifyOutcome> {
-    let resolved = resolve_alg(args.alg, token)?;
-    let key_source = resolve_verification_key(no_persist, data_dir, args, token, resolved.alg)?;
-    let verify_opts = VerifyOptions {
-        alg: resolved.alg,
-        leeway_secs: args.leeway_secs,
-        ignore_exp: args.ignore_exp,
-        iss: args.iss.clone(),
-        sub: args.sub.clone(),
-        aud: args.aud.clone(),
-        require: args.require.clone(),
-    };
+/// Claim checks from a claims failure's `details[key]` (`checks` under `--explain`, or
+/// `violations`), one `PASS`/`FAIL` line each.
+fn claim_checklist(err: &AppError, key: &str) -> Option<String> {
+    let checks = err.details.as_ref()?.get(key)?.as_array()?;
+    let lines: Vec<String> = checks
+        .iter()
+        .map(|check| {
+            let label = if check["ok"] == true { "PASS" } else { "FAIL" };
+            format!(
+                "  {label} {}: {}",
+                check["claim"].as_str().unwrap_or_default(),
+                check["message"].as_str().unwrap_or_default()
+            )
+        })
+        .collect();
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
 
-    let data = match key_source {
-        KeySource::Single(key, label) => {
-            let token_data = jwt_ops::verify_token(token, &key, verify_opts)?;
-            let mut info = json!({
-                "valid": true,
-                "claims": token_data.claims,
-            });
-            if args.explain {
-                info["explain"] = build_verify_explain(args, &label, resolved);
-            }
-            info
+/// `--explain` text: the key decisions and timed steps of a success's `explain` or a failure's
+/// `details.explain`.
+fn explain_lines(explain: &Value) -> Vec<String> {
+    let text = |value: &Value| value.as_str().unwrap_or_default().to_string();
+    let mut lines = Vec::new();
+    if let Some(decisions) = explain["decisions"].as_array().filter(|d| !d.is_empty()) {
+        lines.push("key decisions:".to_string());
+        for decision in decisions {
+            let source = text(&decision["source"]);
+            let what = match decision["candidate"].as_str() {
+                Some(candidate) => format!("{source} '{candidate}'"),
+                None => source,
+            };
+            lines.push(format!(
+                "  {:<7} {what}: {}",
+                text(&decision["decision"]),
+                text(&decision["reason"])
+            ));
         }
-        KeySource::Multiple(keys, label) => {
-            let mut last_sig_err: Option<AppError> = None;
-            for key in keys {
-                match jwt_ops::verify_token(token, &key, verify_opts.clone()) {
-                    Ok(token_data) => {
-                        let mut info = json!({
-                            "valid": true,
-                            "claims": token_data.claims,
-                        });
-                        if args.explain {
-                            info["explain"] = build_verify_explain(args, &label, resolved);
-                        }
-                        return Ok(VerifyOutcome {
-                            data: info,
-                            text: "OK".to_string(),
-                        });
-                    }
-                    Err(err) => {
-                        if matches!(err.kind, ErrorKind::InvalidSignature) {
-                            last_sig_err = Some(err);
-                            continue;
-                        }
-                        return Err(err);
-                    }
-                }
-            }
-
-            if let Some(err) = last_sig_err {
-                return Err(err);
+    }
+    if let Some(steps) = explain["steps"].as_array().filter(|s| !s.is_empty()) {
+        lines.push("steps:".to_string());
+        for step in steps {
+            let outcome = if step["ok"] == true { "ok" } else { "failed" };
+            let mut line = format!(
+                "  {:<14} {outcome:<6} {}us",
+                text(&step["step"]),
+                step["elapsed_us"].as_u64().unwrap_or_default()
+            );
+            if let Some(error) = step["error"].as_str() {
+                line = format!("{line}: {error}");
             }
+            lines.push(line);
+        }
+    }
+    lines
+}
 
-            return Err(AppError::invalid_signature(
-                "signature invalid for all candidate keys",
-            ));
+/// Verifies without printing or recording history; shared with `--rpc`.
+pub(crate) fn execute(
+    access: VaultAccess<'_>,
+    args: &VerifyArgs,
+    cfg: OutputConfig,
+) -> AppResult<CommandOutput> {
+    let sweep_range = args
+        .sweep
+        .as_deref()
+        .map(|raw| sweep::parse_range(raw, now_epoch()))
+        .transpose()?;
+    let token = match &args.token_path {
+        Some(path) => read_token_at_path(&args.token, path)?,
+        None => read_token_input(&args.token)?,
+    };
+    let tenant = match &args.tenant_config {
+        Some(spec) => Some(tenants::load(spec)?.route(&token)?.clone()),
+        None => None,
+    };
+    let routed;
+    let common = match &tenant {
+        Some(tenant) => {
+            routed = tenants::apply(tenant, &args.verify, &token)?;
+            &routed
         }
+        None => &args.verify,
+    };
+    let verified = match sweep_range {
+        Some(_) => access
+            .verify_untimed(common, &token)
+            .map(|verified| VerifyOutcome {
+                data: verified.to_json(),
+                text: "OK (signature and claims; exp/nbf swept below)".to_string(),
+            }),
+        None => verify_token_in(access, common, &token),
     };
+    let mut outcome = match verified {
+        Ok(outcome) => outcome,
+        Err(err) if args.continue_on_claim_failure => claim_failure_outcome(err, &token, common)?,
+        Err(err) => return Err(err),
+    };
+    if let Some(tenant) = &tenant {
+        outcome.data["tenant"] = tenant.to_json();
+        outcome.text = format!("{} (tenant {})", outcome.text, tenant.label());
+    }
+    let tone = if outcome.data["valid"] == true {
+        Tone::Valid
+    } else {
+        Tone::Error
+    };
+    if let Some(profile) = args.profile {
+        let header = jwt_ops::decode_header_only(&token)?;
+        presets::check_profile(
+            profile,
+            &header,
+            &outcome.data["claims"],
+            args.client_id.as_deref(),
+        )?;
+        outcome.data["profile"] = json!(profile_name(profile));
+    }
+    let script_line = match &args.script {
+        Some(path) => Some(run_script(path, &token, &mut outcome)?),
+        None => None,
+    };
+    outcome.text = paint(cfg, tone, &outcome.text);
+    if let Some(line) = script_line {
+        outcome.text = format!("{}\n{line}", outcome.text);
+    }
+    if let Some(normalized) = outcome.data["normalized_timestamps"].as_object() {
+        if let Some(warning) = timestamp_warning(normalized) {
+            outcome.text = format!("{}\n{}", outcome.text, paint(cfg, Tone::Warning, &warning));
+        }
+    }
+    let warnings = claim_warnings(&outcome.data["claims"]);
+    for warning in &warnings {
+        let line = format!("warning: {}", warning.message);
+        outcome.text = format!("{}\n{}", outcome.text, paint(cfg, Tone::Warning, &line));
+    }
+    outcome.data["warnings"] = json!(warnings);
+    if !args.print_claims.is_empty() {
+        let selected = select_claims(&outcome.data["claims"], &args.print_claims);
+        outcome.text = format!("{}\n{}", outcome.text, format_claims_table(&selected));
+        outcome.data["selected_claims"] = Value::Object(selected);
+    }
+    if let Some(range) = sweep_range {
+        let report = sweep::evaluate(
+            range,
+            &outcome.data["claims"],
+            common.leeway_secs,
+            common.ignore_exp,
+        );
+        outcome.data["sweep"] = report.to_json();
+        outcome.text = format!("{}\n{}", outcome.text, report.text_lines(cfg).join("\n"));
+    }
+    Ok(CommandOutput::new(outcome.data, outcome.text))
+}
+
+/// `--script`: runs the rules against the verified claims and fails with every broken rule.
+fn run_script(path: &Path, token: &str, outcome: &mut VerifyOutcome) -> AppResult<String> {
+    let header = jwt_ops::decode_unverified(token)?.header_json;
+    let report = verify_script::run(path, &header, &outcome.data["claims"], now_epoch())?;
+    if !report.failures.is_empty() {
+        return Err(AppError::invalid_claims(format!(
+            "--script {}: {}",
+            path.display(),
+            report.failures.join("; ")
+        ))
+        .with_details(json!({
+            "reason": "script",
+            "script": path,
+            "rules": report.rules,
+            "violations": report.failures,
+        })));
+    }
+    outcome.data["script"] = json!({ "path": path, "rules": report.rules });
+    Ok(format!(
+        "script {}: {} rule(s) passed",
+        path.display(),
+        report.rules
+    ))
+}
 
+/// `--continue-on-claim-failure`: a token whose signature matched but whose claims failed is
+/// reported with its claims and violations. Every other error is returned unchanged.
+fn claim_failure_outcome(
+    err: AppError,
+    token: &str,
+    args: &VerifyCommonArgs,
+) -> AppResult<VerifyOutcome> {
+    let Some(details) = err
+        .details
+        .as_ref()
+        .filter(|details| details.get("violations").is_some())
+    else {
+        return Err(err);
+    };
+    let mut claims = jwt_ops::decode_unverified(token)?.payload_json;
+    let normalized = normalize_timestamps(&mut claims, args.timestamps);
+    let mut data = json!({
+        "valid": false,
+        "signature_valid": true,
+        "claims": claims,
+        "violations": details["violations"],
+    });
+    for key in ["matched_key", "checks", "attempts"] {
+        if let Some(value) = details.get(key) {
+            data[key] = value.clone();
+        }
+    }
+    if !normalized.is_empty() {
+        data["normalized_timestamps"] = Value::Object(normalized);
+    }
+    let mut lines = vec!["INVALID (signature OK; claims failed)".to_string()];
+    lines.extend(claim_checklist(&err, "violations"));
+    lines.push(serde_json::to_string_pretty(&data["claims"]).unwrap_or_default());
     Ok(VerifyOutcome {
         data,
-        text: "OK".to_string(),
+        text: lines.join("\n"),
     })
 }
 
-#[derive(Clone, Copy)]
-struct ResolvedAlg {
-    alg: jsonwebtoken::Algorithm,
-    inferred: bool,
+pub struct VerifyOutcome {
+    pub data: serde_json::Value,
+    pub text: String,
+}
+
+pub fn verify_token_with_args(
+    no_persist: bool,
+    data_dir: Option<PathBuf>,
+    args: &VerifyCommonArgs,
+    token: &str,
+) -> AppResult<VerifyOutcome> {
+    let access = VaultAccess::Open {
+        no_persist,
+        data_dir: &data_dir,
+    };
+    verify_token_in(access, args, token)
 }
 
-fn resolve_alg(alg: Option<JwtAlg>, token: &str) -> AppResult<ResolvedAlg> {
-    if let Some(val) = alg {
-        return Ok(ResolvedAlg {
-            alg: jsonwebtoken::Algorithm::from(val),
-            inferred: false,
-        });
+pub(crate) fn verify_token_in(
+    access: VaultAccess<'_>,
+    args: &VerifyCommonArgs,
+    token: &str,
+) -> AppResult<VerifyOutcome> {
+    let verified = access.verify(args, token)?;
+    debug!(
+        key_source = %verified.key_label,
+        alg = ?verified.alg,
+        alg_inferred = verified.alg_inferred,
+        "token verified"
+    );
+    let data = verified.to_json();
+    let mut lines = vec!["OK".to_string()];
+    if args.explain {
+        lines.extend(explain_lines(&data["explain"]));
     }
-    let header = jwt_ops::decode_header_only(token)?;
-    Ok(ResolvedAlg {
-        alg: header.alg,
-        inferred: true,
+    Ok(VerifyOutcome {
+        data,
+        text: lines.join("\n"),
     })
 }
 
-fn build_verify_explain(
-    args: &VerifyCommonArgs,
-    key_source: &str,
-    resolved: ResolvedAlg,
-) -> serde_json::Value {
+fn history_params(args: &VerifyArgs) -> serde_json::Value {
+    let common = &args.verify;
     json!({
-        "alg": format!("{:?}", resolved.alg),
-        "alg_inferred": resolved.inferred,
-        "key_source": key_source,
-        "iss": args.iss,
-        "sub": args.sub,
-        "aud": args.aud,
-        "leeway_secs": args.leeway_secs,
-        "ignore_exp": args.ignore_exp,
-        "require": args.require,
+        "token": history::describe_token_spec(&args.token),
+        "alg": common.alg.map(|alg| format!("{:?}", jsonwebtoken::Algorithm::from(alg))),
+        "secret": common.secret.as_deref().map(history::describe_key_spec),
+        "key": common.key.as_deref().map(history::describe_key_spec),
+        "jwks": common
+            .jwks
+            .iter()
+            .map(|spec| history::describe_key_spec(spec))
+            .collect::<Vec<_>>(),
+        "key_format": common.key_format.map(|format| format!("{format:?}").to_lowercase()),
+        "kid": common.kid,
+        "allow_single_jwk": common.allow_single_jwk,
+        "project": common.project,
+        "key_id": common.key_id,
+        "key_name": common.key_name,
+        "key_fingerprint": common.key_fingerprint,
+        "try_all_keys": common.try_all_keys,
+        "ignore_exp": common.ignore_exp,
+        "leeway_secs": common.leeway_secs,
+        "iss": common.iss,
+        "sub": common.sub,
+        "aud": common.aud,
+        "require": common.require,
+        "require_acr": common.require_acr,
+        "max_auth_age": common.max_auth_age.map(|age| humantime::format_duration(age).to_string()),
+        "check_revocation": common.check_revocation,
+        "tenant_config": args.tenant_config.as_deref().map(history::describe_key_spec),
+        "profile": args.profile.map(profile_name),
+        "client_id": args.client_id,
+        "print_claims": args.print_claims,
+        "sweep": args.sweep,
+        "continue_on_claim_failure": args.continue_on_claim_failure,
+        "script": args.script,
     })
 }
 
+/// Picks the requested claims; a name that is not a top-level claim is tried as a dotted path.
+/// Missing claims map to null so the projection always has the requested shape.
+fn select_claims(claims: &Value, names: &[String]) -> Map<String, Value> {
+    names
+        .iter()
+        .map(|name| name.trim())
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            let value = claims.get(name).or_else(|| {
+                name.split('.')
+                    .try_fold(claims, |value, segment| value.get(segment))
+            });
+            (name.to_string(), value.cloned().unwrap_or(Value::Null))
+        })
+        .collect()
+}
+
+fn format_claims_table(selected: &Map<String, Value>) -> String {
+    let width = selected.keys().map(String::len).max().unwrap_or(0);
+    selected
+        .iter()
+        .map(|(name, value)| {
+            let rendered = match value {
+                Value::Null => "(missing)".to_string(),
+                Value::String(s) => s.clone(),
+                Value::Number(n)
+                    if matches!(name.as_str(), "exp" | "nbf" | "iat" | "auth_time") =>
+                {
+                    match n.as_i64().map(|ts| format_timestamp(ts, DateMode::Utc)) {
+                        Some(Ok(date)) => format!("{n} ({date})"),
+                        _ => n.to_string(),
+                    }
+                }
+                other => other.to_string(),
+            };
+            format!("{name:<width$}  {rendered}")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn profile_name(profile: VerifyProfile) -> String {
+    profile
+        .to_possible_value()
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default()
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{build_verify_explain, resolve_alg};
-    use crate::cli::{JwtAlg, VerifyCommonArgs};
+    use super::{format_claims_table, select_claims};
+    use crate::cli::{TimestampUnit, VerifyCommonArgs};
     use crate::jwt_ops;
     use jsonwebtoken::{Algorithm, EncodingKey, Header};
     use serde_json::json;
 
-    fn base_args() -> VerifyCommonArgs {
-        VerifyCommonArgs {
-            secret: None,
-            key: None,
-            jwks: None,
-            key_format: None,
-            kid: None,
-            allow_single_jwk: false,
-            project: None,
-            key_id: None,
-            key_name: None,
-            try_all_keys: false,
-            ignore_exp: false,
-            leeway_secs: 30,
-            iss: None,
-            sub: None,
-            aud: Vec::new(),
-            require: Vec::new(),
-            explain: false,
-            alg: None,
-        }
-    }
-
     fn make_token() -> String {
         let header = Header::new(Algorithm::HS256);
         jwt_ops::encode_token(
@@ -190,32 +444,16 @@ mod tests {
     }
 
     #[test]
-    fn resolve_alg_infers_from_header() {
-        let token = make_token();
-        let resolved = resolve_alg(None, &token).expect("resolve");
-        assert_eq!(resolved.alg, Algorithm::HS256);
-        assert!(resolved.inferred);
-    }
-
-    #[test]
-    fn resolve_alg_uses_explicit_value() {
-        let token = make_token();
-        let resolved = resolve_alg(Some(JwtAlg::HS512), &token).expect("resolve");
-        assert_eq!(resolved.alg, Algorithm::HS512);
-        assert!(!resolved.inferred);
-    }
+    fn select_claims_follows_dotted_paths_and_keeps_missing() {
+        let claims = json!({ "sub": "u1", "exp": 0, "realm_access": { "roles": ["admin"] } });
+        let names = ["sub", "realm_access.roles", "scope", "exp"].map(String::from);
+        let selected = select_claims(&claims, &names);
+        assert_eq!(selected["realm_access.roles"], json!(["admin"]));
+        assert!(selected["scope"].is_null());
 
-    #[test]
-    fn build_verify_explain_contains_expected_fields() {
-        let mut args = base_args();
-        args.iss = Some("issuer".to_string());
-        args.aud = vec!["aud1".to_string()];
-        let resolved = resolve_alg(Some(JwtAlg::HS256), &make_token()).expect("resolve");
-        let explain = build_verify_explain(&args, "secret", resolved);
-        assert_eq!(explain["key_source"], "secret");
-        assert_eq!(explain["alg_inferred"], false);
-        assert_eq!(explain["iss"], "issuer");
-        assert_eq!(explain["aud"][0], "aud1");
+        let table = format_claims_table(&selected);
+        assert!(table.contains("scope               (missing)"));
+        assert!(table.contains("exp                 0 (1970-01-01"));
     }
 
     #[test]
@@ -225,30 +463,49 @@ mod tests {
             verify: VerifyCommonArgs {
                 secret: Some("secret".to_string()),
                 key: None,
-                jwks: None,
+                jwks: Vec::new(),
                 key_format: None,
                 kid: None,
                 allow_single_jwk: false,
                 project: None,
                 key_id: None,
                 key_name: None,
+                key_fingerprint: None,
                 try_all_keys: false,
+                auto_refresh: false,
                 ignore_exp: true,
                 leeway_secs: 30,
+                jwks_negative_ttl: std::time::Duration::from_secs(300),
+                timestamps: TimestampUnit::Auto,
                 iss: None,
                 sub: None,
                 aud: Vec::new(),
                 require: Vec::new(),
+                require_acr: Vec::new(),
+                max_auth_age: None,
+                check_revocation: false,
                 explain: true,
                 alg: None,
             },
+            tenant_config: None,
+            profile: None,
+            client_id: None,
+            print_claims: Vec::new(),
+            continue_on_claim_failure: false,
+            token_path: None,
+            script: None,
+            sweep: None,
             token,
         };
         let cfg = crate::output::OutputConfig {
             mode: crate::output::OutputMode::Json,
             quiet: true,
             no_color: true,
+            theme: crate::cli::Theme::Default,
             verbose: false,
+            template: None,
+            summary: None,
+            sinks: None,
         };
         let code = crate::commands::verify::run(true, None, args, cfg);
         assert_eq!(code, 0);
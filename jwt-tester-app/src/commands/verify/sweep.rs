@@ -0,0 +1,253 @@
+use crate::claims::parse_time;
+use crate::date_utils::{format_timestamp, DateMode};
+use crate::error::{AppError, AppResult};
+use crate::output::{paint, OutputConfig, Tone};
+use serde_json::{json, Value};
+
+/// Keeps a typo like `:1s` over a day-long range from producing a huge report.
+const MAX_POINTS: i64 = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct SweepRange {
+    start: i64,
+    end: i64,
+    step: i64,
+}
+
+/// `START..END:STEP`. Each end is `now`, `now±<duration>`, `±<duration>`, `<duration> ago` or an
+/// epoch; the step is a duration (`5m`).
+pub(super) fn parse_range(raw: &str, now: i64) -> AppResult<SweepRange> {
+    let invalid = || {
+        AppError::invalid_claims(format!(
+            "invalid --sweep '{raw}'; expected START..END:STEP, e.g. now-1h..now+2h:5m"
+        ))
+    };
+    let (range, step) = raw.trim().rsplit_once(':').ok_or_else(invalid)?;
+    let (start, end) = range.split_once("..").ok_or_else(invalid)?;
+    let start = parse_point(start, now)?;
+    let end = parse_point(end, now)?;
+    let step = humantime::parse_duration(step.trim())
+        .map_err(|e| AppError::invalid_claims(format!("invalid --sweep step '{step}': {e}")))?
+        .as_secs() as i64;
+    if step == 0 {
+        return Err(AppError::invalid_claims(
+            "--sweep step must be at least one second",
+        ));
+    }
+    if end < start {
+        return Err(AppError::invalid_claims("--sweep end is before its start"));
+    }
+    if (end - start) / step + 1 > MAX_POINTS {
+        return Err(AppError::invalid_claims(format!(
+            "--sweep would evaluate more than {MAX_POINTS} points; use a larger step"
+        )));
+    }
+    Ok(SweepRange { start, end, step })
+}
+
+fn parse_point(raw: &str, now: i64) -> AppResult<i64> {
+    let raw = raw.trim();
+    match raw.strip_prefix("now") {
+        Some("") => Ok(now),
+        Some(offset) => parse_time(offset, now),
+        None => parse_time(raw, now),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Validity {
+    NotYetValid,
+    Valid,
+    Expired,
+}
+
+impl Validity {
+    fn as_str(self) -> &'static str {
+        match self {
+            Validity::NotYetValid => "not_yet_valid",
+            Validity::Valid => "valid",
+            Validity::Expired => "expired",
+        }
+    }
+
+    fn tone(self) -> Tone {
+        match self {
+            Validity::Valid => Tone::Valid,
+            Validity::NotYetValid => Tone::Warning,
+            Validity::Expired => Tone::Expired,
+        }
+    }
+}
+
+/// A run of consecutive points with the same validity.
+struct Segment {
+    state: Validity,
+    from: i64,
+    to: i64,
+}
+
+pub(super) struct SweepReport {
+    range: SweepRange,
+    leeway: i64,
+    /// The exact bounds the samples approximate, leeway included.
+    valid_from: Option<i64>,
+    valid_until: Option<i64>,
+    segments: Vec<Segment>,
+}
+
+/// Applies the same `exp`/`nbf` rules as verification (leeway on both sides) at every point.
+pub(super) fn evaluate(
+    range: SweepRange,
+    claims: &Value,
+    leeway_secs: u64,
+    ignore_exp: bool,
+) -> SweepReport {
+    let leeway = leeway_secs as i64;
+    let valid_from = claims
+        .get("nbf")
+        .and_then(Value::as_i64)
+        .map(|nbf| nbf - leeway);
+    let valid_until = claims
+        .get("exp")
+        .and_then(Value::as_i64)
+        .filter(|_| !ignore_exp)
+        .map(|exp| exp + leeway);
+
+    let mut segments: Vec<Segment> = Vec::new();
+    let mut at = range.start;
+    while at <= range.end {
+        let state = if valid_from.is_some_and(|from| at < from) {
+            Validity::NotYetValid
+        } else if valid_until.is_some_and(|until| at > until) {
+            Validity::Expired
+        } else {
+            Validity::Valid
+        };
+        match segments.last_mut() {
+            Some(last) if last.state == state => last.to = at,
+            _ => segments.push(Segment {
+                state,
+                from: at,
+                to: at,
+            }),
+        }
+        at += range.step;
+    }
+
+    SweepReport {
+        range,
+        leeway,
+        valid_from,
+        valid_until,
+        segments,
+    }
+}
+
+impl SweepReport {
+    pub(super) fn to_json(&self) -> Value {
+        let segments: Vec<Value> = self
+            .segments
+            .iter()
+            .map(|segment| {
+                json!({
+                    "state": segment.state.as_str(),
+                    "from": segment.from,
+                    "to": segment.to,
+                })
+            })
+            .collect();
+        let transitions: Vec<Value> = self
+            .segments
+            .windows(2)
+            .map(|pair| {
+                json!({
+                    "at": pair[1].from,
+                    "from": pair[0].state.as_str(),
+                    "to": pair[1].state.as_str(),
+                })
+            })
+            .collect();
+        json!({
+            "start": self.range.start,
+            "end": self.range.end,
+            "step_secs": self.range.step,
+            "points": (self.range.end - self.range.start) / self.range.step + 1,
+            "leeway_secs": self.leeway,
+            "valid_from": self.valid_from,
+            "valid_until": self.valid_until,
+            "segments": segments,
+            "transitions": transitions,
+        })
+    }
+
+    pub(super) fn text_lines(&self, cfg: OutputConfig) -> Vec<String> {
+        let mut lines = vec![format!(
+            "sweep {} .. {} every {} (leeway {}s):",
+            utc(self.range.start),
+            utc(self.range.end),
+            humantime::format_duration(std::time::Duration::from_secs(self.range.step as u64)),
+            self.leeway
+        )];
+        for segment in &self.segments {
+            lines.push(format!(
+                "  {} .. {}  {}",
+                utc(segment.from),
+                utc(segment.to),
+                paint(cfg, segment.state.tone(), segment.state.as_str())
+            ));
+        }
+        if let Some(from) = self.valid_from {
+            lines.push(format!("valid from {} (nbf - leeway)", utc(from)));
+        }
+        if let Some(until) = self.valid_until {
+            lines.push(format!("valid until {} (exp + leeway)", utc(until)));
+        }
+        lines
+    }
+}
+
+fn utc(ts: i64) -> String {
+    format_timestamp(ts, DateMode::Utc).unwrap_or_else(|_| ts.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_relative_and_absolute_ranges() {
+        let range = parse_range("now-1h..now+2h:5m", 10_000).unwrap();
+        assert_eq!(
+            range,
+            SweepRange {
+                start: 6_400,
+                end: 17_200,
+                step: 300
+            }
+        );
+        assert_eq!(parse_range("100..200:10s", 0).unwrap().end, 200);
+        assert!(parse_range("now..now-1h:5m", 10_000).is_err());
+        assert!(parse_range("now..now+1h", 0).is_err());
+        assert!(parse_range("0..100000:1s", 0).is_err());
+    }
+
+    #[test]
+    fn reports_transitions_with_leeway() {
+        let range = parse_range("0..100:10s", 0).unwrap();
+        let claims = json!({ "nbf": 35, "exp": 65 });
+        let data = evaluate(range, &claims, 5, false).to_json();
+        assert_eq!(data["valid_from"], 30);
+        assert_eq!(data["valid_until"], 70);
+        assert_eq!(
+            data["transitions"],
+            json!([
+                { "at": 30, "from": "not_yet_valid", "to": "valid" },
+                { "at": 80, "from": "valid", "to": "expired" },
+            ])
+        );
+        assert_eq!(data["segments"][1]["to"], 70);
+
+        let data = evaluate(range, &claims, 5, true).to_json();
+        assert_eq!(data["segments"].as_array().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,266 @@
+use crate::claims::now_epoch;
+use crate::cli::{BodyHashAlg, WebhookArgs, WebhookCmd, WebhookVerifyArgs};
+use crate::commands::verify::verify_token_with_args;
+use crate::error::{AppError, AppResult};
+use crate::io_utils::{extract_token, read_input};
+use crate::output::{emit_err, emit_ok, CommandOutput, OutputConfig};
+use base64::engine::general_purpose::{STANDARD_NO_PAD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+pub fn run(
+    no_persist: bool,
+    data_dir: Option<PathBuf>,
+    args: WebhookArgs,
+    cfg: OutputConfig,
+) -> i32 {
+    let result = match args.cmd {
+        WebhookCmd::Verify(verify) => verify_webhook(no_persist, data_dir, &verify),
+    };
+
+    match result.and_then(|out| emit_ok(cfg, out)) {
+        Ok(()) => 0,
+        Err(err) => {
+            let code = err.exit_code();
+            emit_err(cfg, err);
+            code
+        }
+    }
+}
+
+fn verify_webhook(
+    no_persist: bool,
+    data_dir: Option<PathBuf>,
+    args: &WebhookVerifyArgs,
+) -> AppResult<CommandOutput> {
+    if args.body.as_os_str() == "-" && args.signature == "-" {
+        return Err(AppError::invalid_token(
+            "--body and --signature cannot both be read from stdin",
+        ));
+    }
+    let token = signature_token(&read_input(&args.signature)?)?;
+    let body = read_body(&args.body)?;
+    let outcome = verify_token_with_args(no_persist, data_dir, &args.verify, &token)?;
+    let claims = &outcome.data["claims"];
+
+    if let Some(max_age) = args.max_age {
+        check_freshness(claims, max_age.as_secs())?;
+    }
+
+    let claimed = claims
+        .get(&args.hash_claim)
+        .and_then(Value::as_str)
+        .ok_or_else(|| {
+            AppError::invalid_claims(format!(
+                "signature token has no string '{}' claim",
+                args.hash_claim
+            ))
+        })?;
+    let digest = hash_body(args.hash_alg, &body);
+    let Some(encoding) = match_digest(claimed, &digest) else {
+        return Err(body_mismatch(args, claimed, &body, &digest));
+    };
+
+    let data = json!({
+        "valid": true,
+        "claims": claims,
+        "body_hash": {
+            "claim": args.hash_claim,
+            "alg": hash_alg_name(args.hash_alg),
+            "encoding": encoding,
+            "value": claimed,
+        },
+        "body_len": body.len(),
+    });
+    let text = format!(
+        "OK ({} matches {} body bytes, {}/{})",
+        args.hash_claim,
+        body.len(),
+        hash_alg_name(args.hash_alg),
+        encoding
+    );
+    Ok(CommandOutput::new(data, text))
+}
+
+/// Accepts the bare JWT or a copied header line such as `Plaid-Verification: eyJ...`.
+fn signature_token(raw: &str) -> AppResult<String> {
+    let raw = raw.trim();
+    match raw.split_once(':') {
+        Some((name, value))
+            if !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') =>
+        {
+            extract_token(value)
+        }
+        _ => extract_token(raw),
+    }
+}
+
+fn read_body(path: &Path) -> AppResult<Vec<u8>> {
+    if path.as_os_str() == "-" {
+        let mut buf = Vec::new();
+        std::io::stdin()
+            .read_to_end(&mut buf)
+            .map_err(|e| AppError::invalid_token(format!("failed to read stdin: {e}")))?;
+        return Ok(buf);
+    }
+    std::fs::read(path)
+        .map_err(|e| AppError::invalid_token(format!("failed to read body {path:?}: {e}")))
+}
+
+fn check_freshness(claims: &Value, max_age: u64) -> AppResult<()> {
+    let iat = claims
+        .get("iat")
+        .and_then(Value::as_i64)
+        .ok_or_else(|| AppError::invalid_claims("--max-age requires an iat claim"))?;
+    let age = now_epoch() - iat;
+    if age > i64::try_from(max_age).unwrap_or(i64::MAX) {
+        return Err(AppError::invalid_claims(format!(
+            "signature was issued {age}s ago, older than --max-age {max_age}s"
+        )));
+    }
+    Ok(())
+}
+
+fn hash_body(alg: BodyHashAlg, body: &[u8]) -> Vec<u8> {
+    match alg {
+        BodyHashAlg::Sha256 => Sha256::digest(body).to_vec(),
+        BodyHashAlg::Sha384 => Sha384::digest(body).to_vec(),
+        BodyHashAlg::Sha512 => Sha512::digest(body).to_vec(),
+    }
+}
+
+fn hash_alg_name(alg: BodyHashAlg) -> &'static str {
+    match alg {
+        BodyHashAlg::Sha256 => "sha256",
+        BodyHashAlg::Sha384 => "sha384",
+        BodyHashAlg::Sha512 => "sha512",
+    }
+}
+
+fn encodings(digest: &[u8]) -> [(&'static str, String); 3] {
+    [
+        ("hex", hex::encode(digest)),
+        ("base64url", URL_SAFE_NO_PAD.encode(digest)),
+        ("base64", STANDARD_NO_PAD.encode(digest)),
+    ]
+}
+
+/// Returns the encoding the claim uses when it matches the digest. Hex is compared
+/// case-insensitively and base64 padding is optional.
+fn match_digest(claimed: &str, digest: &[u8]) -> Option<&'static str> {
+    let claimed = claimed.trim().trim_end_matches('=');
+    encodings(digest)
+        .into_iter()
+        .find(|(name, encoded)| match *name {
+            "hex" => claimed.eq_ignore_ascii_case(encoded),
+            _ => claimed == encoded,
+        })
+        .map(|(name, _)| name)
+}
+
+fn body_mismatch(args: &WebhookVerifyArgs, claimed: &str, body: &[u8], digest: &[u8]) -> AppError {
+    let computed: serde_json::Map<String, Value> = encodings(digest)
+        .into_iter()
+        .map(|(name, encoded)| (name.to_string(), json!(encoded)))
+        .collect();
+    let hint = mismatch_hint(args.hash_alg, claimed, body);
+    let mut message = format!(
+        "body hash mismatch: {} is {claimed}, but the {} body bytes hash to {} ({})",
+        args.hash_claim,
+        body.len(),
+        hex::encode(digest),
+        hash_alg_name(args.hash_alg)
+    );
+    if let Some(hint) = &hint {
+        message.push_str(&format!("; {hint}"));
+    }
+    AppError::invalid_claims(message).with_details(json!({
+        "claim": args.hash_claim,
+        "alg": hash_alg_name(args.hash_alg),
+        "expected": claimed,
+        "computed": computed,
+        "body_len": body.len(),
+        "hint": hint,
+    }))
+}
+
+/// Tries the usual ways a stored body drifts from what the sender hashed.
+fn mismatch_hint(alg: BodyHashAlg, claimed: &str, body: &[u8]) -> Option<String> {
+    for other in [
+        BodyHashAlg::Sha256,
+        BodyHashAlg::Sha384,
+        BodyHashAlg::Sha512,
+    ] {
+        if other != alg && match_digest(claimed, &hash_body(other, body)).is_some() {
+            return Some(format!(
+                "the claim matches with --hash-alg {}",
+                hash_alg_name(other)
+            ));
+        }
+    }
+
+    let mut variants: Vec<(&str, Vec<u8>)> = Vec::new();
+    let trimmed = body.trim_ascii_end();
+    if trimmed.len() != body.len() {
+        variants.push((
+            "after removing trailing whitespace/newlines",
+            trimmed.to_vec(),
+        ));
+    }
+    if body.windows(2).any(|pair| pair == b"\r\n") {
+        let normalized = String::from_utf8_lossy(body).replace("\r\n", "\n");
+        variants.push(("after converting CRLF to LF", normalized.into_bytes()));
+    }
+    if let Ok(value) = serde_json::from_slice::<Value>(body) {
+        let compact = serde_json::to_vec(&value).unwrap_or_default();
+        if compact != body {
+            variants.push(("when the JSON body is re-serialized compactly", compact));
+        }
+    }
+    variants
+        .into_iter()
+        .find(|(_, bytes)| match_digest(claimed, &hash_body(alg, bytes)).is_some())
+        .map(|(how, _)| format!("the body matches {how}; it was modified after delivery"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hash_body, match_digest, mismatch_hint, signature_token};
+    use crate::cli::BodyHashAlg;
+
+    #[test]
+    fn match_digest_accepts_hex_and_base64_forms() {
+        let digest = hash_body(BodyHashAlg::Sha256, b"{}");
+        let hex = hex::encode(&digest);
+        assert_eq!(match_digest(&hex.to_uppercase(), &digest), Some("hex"));
+        assert_eq!(
+            match_digest("RBNvo1WzZ4oRRq0W9-hknpT7T8If536DEMBg9hyq_4o", &digest),
+            Some("base64url")
+        );
+        assert_eq!(match_digest("nope", &digest), None);
+    }
+
+    #[test]
+    fn mismatch_hint_detects_trailing_newline() {
+        let claimed = hex::encode(hash_body(BodyHashAlg::Sha256, b"{\"a\":1}"));
+        let hint = mismatch_hint(BodyHashAlg::Sha256, &claimed, b"{\"a\":1}\n").expect("hint");
+        assert!(hint.contains("trailing"));
+        let hint = mismatch_hint(BodyHashAlg::Sha512, &claimed, b"{\"a\":1}").expect("hint");
+        assert!(hint.contains("--hash-alg sha256"));
+    }
+
+    #[test]
+    fn signature_token_strips_header_names() {
+        assert_eq!(
+            signature_token("Plaid-Verification: a.b.c").unwrap(),
+            "a.b.c"
+        );
+        assert_eq!(signature_token("a.b.c").unwrap(), "a.b.c");
+    }
+}
@@ -1,11 +1,39 @@
+use crate::claims::now_epoch;
+use crate::cli::TimestampUnit;
 use crate::error::{AppError, AppResult};
-use serde_json::{json, Value};
+use crate::output::{paint, OutputConfig, Tone};
+use serde_json::{json, Map, Value};
 use time::format_description::well_known::Rfc3339;
-use time::{OffsetDateTime, UtcOffset};
+use time::{Date, Month, OffsetDateTime, PrimitiveDateTime, Time, UtcOffset, Weekday};
 
 pub struct DateExtraction {
     pub json: Value,
     pub lines: Vec<String>,
+    tones: Vec<Option<Tone>>,
+}
+
+impl DateExtraction {
+    /// `lines` colored by status: an `exp` in the past is expired, a future `exp` valid, and a
+    /// future `nbf` a warning.
+    pub fn styled_lines(&self, cfg: OutputConfig) -> Vec<String> {
+        self.lines
+            .iter()
+            .zip(&self.tones)
+            .map(|(line, tone)| match tone {
+                Some(tone) => paint(cfg, *tone, line),
+                None => line.clone(),
+            })
+            .collect()
+    }
+}
+
+fn date_tone(key: &str, ts: i64, now: i64) -> Option<Tone> {
+    match key {
+        "exp" if ts <= now => Some(Tone::Expired),
+        "exp" => Some(Tone::Valid),
+        "nbf" if ts > now => Some(Tone::Warning),
+        _ => None,
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -20,11 +48,14 @@ pub fn extract_dates(payload: &Value, mode: Option<DateMode>) -> AppResult<DateE
         return Ok(DateExtraction {
             json: json!({}),
             lines: Vec::new(),
+            tones: Vec::new(),
         });
     };
 
     let mut json_map = serde_json::Map::new();
     let mut lines = Vec::new();
+    let mut tones = Vec::new();
+    let now = now_epoch();
 
     if let Some(obj) = payload.as_object() {
         for key in ["exp", "nbf", "iat"] {
@@ -33,6 +64,7 @@ pub fn extract_dates(payload: &Value, mode: Option<DateMode>) -> AppResult<DateE
                     let rendered = format_timestamp(num, mode)?;
                     json_map.insert(key.to_string(), json!({ "raw": num, "rfc3339": rendered }));
                     lines.push(format!("{key}: {num} -> {rendered}"));
+                    tones.push(date_tone(key, num, now));
                 }
             }
         }
@@ -41,9 +73,53 @@ pub fn extract_dates(payload: &Value, mode: Option<DateMode>) -> AppResult<DateE
     Ok(DateExtraction {
         json: Value::Object(json_map),
         lines,
+        tones,
     })
 }
 
+/// Seconds since the epoch stay below this until the year 5138, so a larger `exp`, `nbf` or `iat`
+/// is in milliseconds (read as seconds it would land in the year 56000 or so).
+pub const MILLIS_THRESHOLD: f64 = 1e11;
+
+/// Converts `exp`, `nbf` and `iat` to seconds according to `unit` and returns the original value
+/// of every claim that was changed.
+pub fn normalize_timestamps(claims: &mut Value, unit: TimestampUnit) -> Map<String, Value> {
+    let mut normalized = Map::new();
+    let Some(obj) = claims.as_object_mut() else {
+        return normalized;
+    };
+    for key in ["exp", "nbf", "iat"] {
+        let Some(value) = obj.get_mut(key) else {
+            continue;
+        };
+        let Some(raw) = value.as_f64() else {
+            continue;
+        };
+        let millis = match unit {
+            TimestampUnit::Seconds => false,
+            TimestampUnit::Millis => true,
+            TimestampUnit::Auto => raw.abs() >= MILLIS_THRESHOLD,
+        };
+        if millis {
+            normalized.insert(key.to_string(), value.clone());
+            *value = json!((raw / 1000.0).floor() as i64);
+        }
+    }
+    normalized
+}
+
+/// The warning shown when [`normalize_timestamps`] changed anything.
+pub fn timestamp_warning(normalized: &Map<String, Value>) -> Option<String> {
+    if normalized.is_empty() {
+        return None;
+    }
+    let names: Vec<&str> = normalized.keys().map(String::as_str).collect();
+    Some(format!(
+        "{} in milliseconds; normalized to seconds (JWT NumericDate is seconds, see --timestamps)",
+        names.join(", ")
+    ))
+}
+
 pub fn parse_date_mode(input: Option<String>) -> AppResult<Option<DateMode>> {
     let Some(raw) = input else {
         return Ok(None);
@@ -85,7 +161,169 @@ fn parse_offset(input: &str) -> AppResult<Option<UtcOffset>> {
     Ok(Some(offset))
 }
 
-fn format_timestamp(ts: i64, mode: DateMode) -> AppResult<String> {
+/// Parses an absolute moment for `--exp`/`--nbf`/`--iat`:
+///
+/// - RFC 3339: `2025-06-30T12:00:00Z`, `2025-06-30T14:00:00+02:00`
+/// - a date with an optional time: `2025-06-30`, `2025-06-30 12:00`, `2025-06-30T12:00:30`
+/// - a day phrase with an optional time: `today`, `tomorrow 09:00`, `yesterday at noon`,
+///   `next friday 17:30` (a weekday always means the next one after today)
+///
+/// A missing time is midnight. Values without a zone are UTC; append `utc`, `local` or `+HH:MM`
+/// to say otherwise. Returns `None` when `raw` does not start like a date, so callers can try
+/// durations next.
+pub fn parse_datetime(raw: &str, now: i64) -> AppResult<Option<i64>> {
+    let raw = raw.trim();
+    if let Ok(parsed) = OffsetDateTime::parse(raw, &Rfc3339) {
+        return Ok(Some(parsed.unix_timestamp()));
+    }
+    let lowered = raw.to_lowercase();
+    let mut words: Vec<&str> = lowered
+        .split_whitespace()
+        .filter(|word| *word != "at")
+        .collect();
+    // `2025-06-30t12:00+02:00` is one word; split it into date, time and zone.
+    if let Some((date, time)) = words
+        .first()
+        .and_then(|first| first.split_once('t'))
+        .filter(|(date, _)| is_iso_date(date))
+    {
+        let (time, zone) = match time.find(['+', '-', 'z']) {
+            Some(at) => (&time[..at], Some(&time[at..])),
+            None => (time, None),
+        };
+        words.splice(0..1, [date, time].into_iter().chain(zone));
+    }
+    if !words.first().is_some_and(|first| starts_date(first)) {
+        return Ok(None);
+    }
+
+    let invalid = || AppError::invalid_claims(format!("invalid date '{raw}'"));
+    let mut zone = UtcOffset::UTC;
+    let mut local = false;
+    if words.len() > 1 {
+        match words[words.len() - 1] {
+            "z" | "utc" => {
+                words.pop();
+            }
+            "local" => {
+                local = true;
+                words.pop();
+            }
+            last => {
+                if let Some(offset) = parse_offset(last)? {
+                    zone = offset;
+                    words.pop();
+                }
+            }
+        }
+    }
+    if local {
+        zone = UtcOffset::current_local_offset()
+            .map_err(|_| AppError::invalid_claims("unable to determine local offset"))?;
+    }
+
+    let now = OffsetDateTime::from_unix_timestamp(now)
+        .map_err(|_| AppError::invalid_claims("invalid timestamp"))?
+        .to_offset(zone);
+    let today = now.date();
+    let mut rest = words.as_slice();
+    let date = match rest {
+        [word, tail @ ..] if is_iso_date(word) => {
+            rest = tail;
+            parse_iso_date(word).ok_or_else(invalid)?
+        }
+        ["today", tail @ ..] => {
+            rest = tail;
+            today
+        }
+        ["tomorrow", tail @ ..] => {
+            rest = tail;
+            today.next_day().ok_or_else(invalid)?
+        }
+        ["yesterday", tail @ ..] => {
+            rest = tail;
+            today.previous_day().ok_or_else(invalid)?
+        }
+        ["next", day, tail @ ..] | [day, tail @ ..] if parse_weekday(day).is_some() => {
+            rest = tail;
+            let weekday = parse_weekday(day).ok_or_else(invalid)?;
+            let mut date = today.next_day().ok_or_else(invalid)?;
+            while date.weekday() != weekday {
+                date = date.next_day().ok_or_else(invalid)?;
+            }
+            date
+        }
+        [word, ..] if parse_time_of_day(word).is_some() => today,
+        _ => return Err(invalid()),
+    };
+    let time = match rest {
+        [] => Time::MIDNIGHT,
+        [word] => parse_time_of_day(word).ok_or_else(invalid)?,
+        _ => return Err(invalid()),
+    };
+    let moment = PrimitiveDateTime::new(date, time).assume_offset(zone);
+    Ok(Some(moment.unix_timestamp()))
+}
+
+fn starts_date(word: &str) -> bool {
+    is_iso_date(word)
+        || matches!(word, "today" | "tomorrow" | "yesterday" | "next")
+        || parse_weekday(word).is_some()
+        || parse_time_of_day(word).is_some()
+}
+
+fn is_iso_date(word: &str) -> bool {
+    let parts: Vec<&str> = word.split('-').collect();
+    parts.len() == 3
+        && parts[0].len() == 4
+        && parts
+            .iter()
+            .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()))
+}
+
+fn parse_iso_date(word: &str) -> Option<Date> {
+    let mut parts = word.split('-').map(str::parse::<u32>);
+    let year = parts.next()?.ok()? as i32;
+    let month = Month::try_from(parts.next()?.ok()? as u8).ok()?;
+    let day = parts.next()?.ok()? as u8;
+    Date::from_calendar_date(year, month, day).ok()
+}
+
+/// `HH:MM`, `HH:MM:SS` (fractions dropped), `noon` or `midnight`.
+fn parse_time_of_day(word: &str) -> Option<Time> {
+    match word {
+        "noon" => return Time::from_hms(12, 0, 0).ok(),
+        "midnight" => return Some(Time::MIDNIGHT),
+        _ => {}
+    }
+    let word = word.split('.').next()?;
+    let mut parts = word.split(':');
+    let hour = parts.next()?.parse::<u8>().ok()?;
+    let minute = parts.next()?.parse::<u8>().ok()?;
+    let second = match parts.next() {
+        Some(second) => second.parse::<u8>().ok()?,
+        None => 0,
+    };
+    if parts.next().is_some() {
+        return None;
+    }
+    Time::from_hms(hour, minute, second).ok()
+}
+
+fn parse_weekday(word: &str) -> Option<Weekday> {
+    Some(match word {
+        "monday" | "mon" => Weekday::Monday,
+        "tuesday" | "tue" => Weekday::Tuesday,
+        "wednesday" | "wed" => Weekday::Wednesday,
+        "thursday" | "thu" => Weekday::Thursday,
+        "friday" | "fri" => Weekday::Friday,
+        "saturday" | "sat" => Weekday::Saturday,
+        "sunday" | "sun" => Weekday::Sunday,
+        _ => return None,
+    })
+}
+
+pub fn format_timestamp(ts: i64, mode: DateMode) -> AppResult<String> {
     let odt = OffsetDateTime::from_unix_timestamp(ts)
         .map_err(|_| AppError::invalid_claims("invalid timestamp"))?;
     let adjusted = match mode {
@@ -107,6 +345,14 @@ mod tests {
     use super::*;
     use serde_json::json;
 
+    #[test]
+    fn date_tone_flags_expired_and_future_nbf() {
+        assert_eq!(date_tone("exp", 10, 20), Some(Tone::Expired));
+        assert_eq!(date_tone("exp", 30, 20), Some(Tone::Valid));
+        assert_eq!(date_tone("nbf", 30, 20), Some(Tone::Warning));
+        assert_eq!(date_tone("iat", 10, 20), None);
+    }
+
     #[test]
     fn date_mode_parses_utc_local_offset() {
         assert!(matches!(
@@ -123,6 +369,75 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn parse_datetime_handles_rfc3339_dates_and_phrases() {
+        // Wednesday 2025-06-25T10:00:00Z
+        let now = 1_750_845_600;
+        let noon_june_30 = 1_751_284_800;
+        for raw in [
+            "2025-06-30T12:00:00Z",
+            "2025-06-30T14:00:00+02:00",
+            "2025-06-30 12:00",
+            "2025-06-30t14:00+02:00",
+            "2025-06-30 14:00 +02:00",
+            "2025-06-30 at noon UTC",
+        ] {
+            assert_eq!(
+                parse_datetime(raw, now).unwrap(),
+                Some(noon_june_30),
+                "{raw}"
+            );
+        }
+        assert_eq!(
+            parse_datetime("2025-06-30", now).unwrap(),
+            Some(1_751_241_600)
+        );
+        assert_eq!(parse_datetime("today", now).unwrap(), Some(1_750_809_600));
+        assert_eq!(
+            parse_datetime("tomorrow 09:00", now).unwrap(),
+            Some(1_750_928_400)
+        );
+        assert_eq!(
+            parse_datetime("yesterday at noon", now).unwrap(),
+            Some(1_750_766_400)
+        );
+        assert_eq!(
+            parse_datetime("next Friday 17:30", now).unwrap(),
+            Some(1_751_045_400)
+        );
+        assert_eq!(
+            parse_datetime("wednesday", now).unwrap(),
+            Some(1_751_414_400)
+        );
+
+        assert_eq!(parse_datetime("2 days", now).unwrap(), None);
+        assert_eq!(parse_datetime("+1h", now).unwrap(), None);
+        assert!(parse_datetime("2025-02-30", now).is_err());
+        assert!(parse_datetime("tomorrow 25:00", now).is_err());
+        assert!(parse_datetime("next week", now).is_err());
+    }
+
+    #[test]
+    fn normalize_timestamps_detects_and_forces_milliseconds() {
+        let mut claims = json!({ "exp": 1_751_284_800_000_i64, "iat": 1_751_284_000, "sub": "a" });
+        let normalized = normalize_timestamps(&mut claims, TimestampUnit::Auto);
+        assert_eq!(claims["exp"], 1_751_284_800);
+        assert_eq!(claims["iat"], 1_751_284_000);
+        assert_eq!(
+            Value::Object(normalized.clone()),
+            json!({ "exp": 1_751_284_800_000_i64 })
+        );
+        assert!(timestamp_warning(&normalized)
+            .unwrap()
+            .starts_with("exp in milliseconds"));
+
+        let mut claims = json!({ "nbf": 1_751_284_000 });
+        assert!(normalize_timestamps(&mut claims.clone(), TimestampUnit::Seconds).is_empty());
+        normalize_timestamps(&mut claims, TimestampUnit::Millis);
+        assert_eq!(claims["nbf"], 1_751_284);
+        assert_eq!(timestamp_warning(&Map::new()), None);
+    }
+
     #[test]
     fn extract_dates_empty_when_missing() {
         let payload = json!({ "sub": "123" });
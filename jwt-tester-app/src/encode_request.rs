@@ -0,0 +1,621 @@
+use crate::claim_plugin;
+use crate::claims::{self, StandardClaims};
+use crate::cli::{EncodeArgs, JwtAlg, KeyFormat, PayloadZip, PresetArgs};
+use crate::error::{AppError, AppResult};
+use crate::io_utils::{read_input, read_json_value, read_token_input};
+use crate::jwt_ops;
+use crate::presets;
+use crate::signer::SigningKey;
+use jsonwebtoken::jwk::Jwk;
+use jsonwebtoken::{Algorithm, Header};
+use serde_json::{Map, Value};
+
+/// Header parameters `--mimic` does not copy: they name or embed the captured token's signing
+/// key, or change what its signature covers. `alg` comes from `--alg` instead.
+const MIMIC_SKIPPED_HEADER: [&str; 10] = [
+    "alg", "kid", "jku", "jwk", "x5u", "x5c", "x5t", "x5t#S256", "b64", "crit",
+];
+
+/// A token to sign, independent of clap. `encode` builds one from `EncodeArgs`, the UI from its
+/// JSON body; both then resolve a key for it and call [`EncodeRequest::encode`].
+#[derive(Debug, Clone)]
+pub struct EncodeRequest {
+    pub(crate) alg: JwtAlg,
+    pub(crate) secret: Option<String>,
+    pub(crate) key: Option<String>,
+    pub(crate) key_format: Option<KeyFormat>,
+    pub(crate) project: Option<String>,
+    pub(crate) key_id: Option<String>,
+    pub(crate) key_name: Option<String>,
+    pub(crate) key_fingerprint: Option<String>,
+    claims: Value,
+    claim_files: Vec<Value>,
+    standard: StandardClaims,
+    claim_kv: Vec<String>,
+    claim_namespace: Option<String>,
+    keep_payload_order: bool,
+    header: Option<Value>,
+    kid: Option<String>,
+    typ: Option<String>,
+    no_typ: bool,
+    header_params: Vec<String>,
+    /// Header parameters copied by [`EncodeRequest::mimic`]; `--header-param` overrides them.
+    mimic_params: Map<String, Value>,
+    zip: Option<PayloadZip>,
+    preset: PresetArgs,
+    wasm_module: Option<String>,
+}
+
+impl EncodeRequest {
+    pub fn new(alg: JwtAlg) -> Self {
+        EncodeRequest {
+            alg,
+            secret: None,
+            key: None,
+            key_format: None,
+            project: None,
+            key_id: None,
+            key_name: None,
+            key_fingerprint: None,
+            claims: Value::Object(Map::new()),
+            claim_files: Vec::new(),
+            standard: StandardClaims::default(),
+            claim_kv: Vec::new(),
+            claim_namespace: None,
+            keep_payload_order: false,
+            header: None,
+            kid: None,
+            typ: None,
+            no_typ: false,
+            header_params: Vec::new(),
+            mimic_params: Map::new(),
+            zip: None,
+            preset: PresetArgs::default(),
+            wasm_module: None,
+        }
+    }
+
+    /// Reads the claims, header and claim files named by the CLI flags.
+    pub fn from_args(args: &EncodeArgs) -> AppResult<Self> {
+        let mut request = EncodeRequest::new(args.alg)
+            .standard_claims(StandardClaims {
+                iss: args.iss.clone(),
+                sub: args.sub.clone(),
+                aud: claims::build_audiences(
+                    &args.aud,
+                    args.aud_from_file.as_deref().map(read_input).transpose()?,
+                    args.aud_pattern.as_deref(),
+                )?,
+                jti: args.jti.clone(),
+                iat: args.iat.clone(),
+                nbf: args.nbf.clone(),
+                exp: args.exp.clone(),
+                no_iat: args.no_iat,
+                acr: args.acr.clone(),
+                amr: args.amr.clone(),
+                auth_time: args.auth_time.clone(),
+            })
+            .keep_payload_order(args.keep_payload_order)
+            .no_typ(args.no_typ)
+            .zip(args.zip)
+            .preset(args.preset.clone());
+        if let Some(secret) = &args.secret {
+            request = request.secret(secret.as_str());
+        }
+        if let Some(key) = &args.key {
+            request = request.key(key.as_str());
+        }
+        if let Some(format) = args.key_format {
+            request = request.key_format(format);
+        }
+        if let Some(project) = &args.project {
+            request = request.project(project.as_str());
+        }
+        if let Some(key_id) = &args.key_id {
+            request = request.key_id(key_id.as_str());
+        }
+        if let Some(key_name) = &args.key_name {
+            request = request.key_name(key_name.as_str());
+        }
+        if let Some(fingerprint) = &args.key_fingerprint {
+            request = request.key_fingerprint(fingerprint.as_str());
+        }
+        if let Some(kid) = &args.kid {
+            request = request.kid(kid.as_str());
+        }
+        if let Some(typ) = &args.typ {
+            request = request.typ(typ.as_str());
+        }
+        if let Some(spec) = &args.mimic {
+            request = request.mimic(&read_token_input(spec)?)?;
+        }
+        if let Some(namespace) = &args.claim_namespace {
+            request = request.claim_namespace(namespace.as_str());
+        }
+        if let Some(spec) = args.claims.as_deref() {
+            request = request.claims(read_json_value(spec)?);
+        }
+        if let Some(spec) = args.header.as_deref() {
+            request = request.header(read_json_value(spec)?);
+        }
+        for spec in &args.claim_file {
+            request = request.claim_file(read_json_value(spec)?);
+        }
+        for kv in &args.claim {
+            request = request.claim(kv.as_str());
+        }
+        for kv in &args.header_param {
+            request = request.header_param(kv.as_str());
+        }
+        Ok(request)
+    }
+
+    /// HMAC secret spec (raw, @file, env:NAME, b64:...).
+    pub fn secret(mut self, spec: impl Into<String>) -> Self {
+        self.secret = Some(spec.into());
+        self
+    }
+
+    /// Private key spec (PEM/DER input, `ssh:`, `pkcs11:`, ...).
+    pub fn key(mut self, spec: impl Into<String>) -> Self {
+        self.key = Some(spec.into());
+        self
+    }
+
+    pub fn key_format(mut self, format: KeyFormat) -> Self {
+        self.key_format = Some(format);
+        self
+    }
+
+    /// Signs with a vault key of this project.
+    pub fn project(mut self, project: impl Into<String>) -> Self {
+        self.project = Some(project.into());
+        self
+    }
+
+    pub fn key_id(mut self, key_id: impl Into<String>) -> Self {
+        self.key_id = Some(key_id.into());
+        self
+    }
+
+    pub fn key_name(mut self, key_name: impl Into<String>) -> Self {
+        self.key_name = Some(key_name.into());
+        self
+    }
+
+    /// Selects the project key by fingerprint (`sha256:<hex>` or a unique prefix).
+    pub fn key_fingerprint(mut self, fingerprint: impl Into<String>) -> Self {
+        self.key_fingerprint = Some(fingerprint.into());
+        self
+    }
+
+    /// Base claims; must be a JSON object.
+    pub fn claims(mut self, claims: Value) -> Self {
+        self.claims = claims;
+        self
+    }
+
+    /// Merged over the base claims, in the order added.
+    pub fn claim_file(mut self, claims: Value) -> Self {
+        self.claim_files.push(claims);
+        self
+    }
+
+    pub fn standard_claims(mut self, standard: StandardClaims) -> Self {
+        self.standard = standard;
+        self
+    }
+
+    /// A `k=v` claim, applied last; the value is JSON if it parses, otherwise a string.
+    pub fn claim(mut self, kv: impl Into<String>) -> Self {
+        self.claim_kv.push(kv.into());
+        self
+    }
+
+    pub fn claim_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.claim_namespace = Some(namespace.into());
+        self
+    }
+
+    pub fn keep_payload_order(mut self, keep: bool) -> Self {
+        self.keep_payload_order = keep;
+        self
+    }
+
+    /// Header JSON whose registered fields override the defaults.
+    pub fn header(mut self, header: Value) -> Self {
+        self.header = Some(header);
+        self
+    }
+
+    pub fn kid(mut self, kid: impl Into<String>) -> Self {
+        self.kid = Some(kid.into());
+        self
+    }
+
+    pub fn typ(mut self, typ: impl Into<String>) -> Self {
+        self.typ = Some(typ.into());
+        self
+    }
+
+    pub fn no_typ(mut self, no_typ: bool) -> Self {
+        self.no_typ = no_typ;
+        self
+    }
+
+    /// A `k=v` protected header parameter `Header` cannot carry (e.g. `nonce`).
+    pub fn header_param(mut self, kv: impl Into<String>) -> Self {
+        self.header_params.push(kv.into());
+        self
+    }
+
+    /// Starts from a captured token: its payload (in its original claim order) becomes the base
+    /// claims, and its header is copied except for [`MIMIC_SKIPPED_HEADER`]. A `typ` already set,
+    /// or `no_typ`, wins over the captured one, and `zip: DEF` carries over as compression.
+    pub fn mimic(mut self, token: &str) -> AppResult<Self> {
+        let decoded = jwt_ops::decode_unverified(token)?;
+        let captured = decoded
+            .header_json
+            .as_object()
+            .ok_or_else(|| AppError::invalid_token("header must be a JSON object"))?;
+        if self.typ.is_none() && !self.no_typ {
+            match captured.get("typ").map(|typ| parse_opt_string(typ, "typ")) {
+                Some(typ) => {
+                    self.typ = typ?;
+                    self.no_typ = self.typ.is_none();
+                }
+                None => self.no_typ = true,
+            }
+        }
+        let mut header = Map::new();
+        for (name, value) in captured {
+            match name.as_str() {
+                "typ" | "zip" => {}
+                "cty" => {
+                    header.insert(name.clone(), value.clone());
+                }
+                skipped if MIMIC_SKIPPED_HEADER.contains(&skipped) => {}
+                _ => {
+                    self.mimic_params.insert(name.clone(), value.clone());
+                }
+            }
+        }
+        if decoded.compressed && self.zip.is_none() {
+            self.zip = Some(PayloadZip::Def);
+        }
+        self.header = Some(Value::Object(header));
+        self.claims = decoded.payload_json;
+        self.keep_payload_order = true;
+        Ok(self)
+    }
+
+    /// Compress the payload and mark it with the `zip` header.
+    pub fn zip(mut self, zip: Option<PayloadZip>) -> Self {
+        self.zip = zip;
+        self
+    }
+
+    pub fn preset(mut self, preset: PresetArgs) -> Self {
+        self.preset = preset;
+        self
+    }
+
+    /// Passes the built claims through the module's `jwt_transform` export; see [`claim_plugin`].
+    pub fn wasm_module(mut self, path: impl Into<String>) -> Self {
+        self.wasm_module = Some(path.into());
+        self
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        Algorithm::from(self.alg)
+    }
+
+    pub fn build_claims(&self) -> AppResult<Value> {
+        let mut base_claims = self.claims.clone();
+        presets::apply_preset(&self.preset, &mut base_claims)?;
+        let claims = claims::build_claims(
+            base_claims,
+            self.claim_files.clone(),
+            self.standard.clone(),
+            self.namespaced_claim_kv()?,
+            self.keep_payload_order,
+        )?;
+        let claims = match &self.wasm_module {
+            Some(path) => claim_plugin::transform_claims(path, claims)?,
+            None => claims,
+        };
+        presets::check_preset_claims(&self.preset, &claims)?;
+        Ok(claims)
+    }
+
+    pub fn build_header(&self) -> AppResult<Header> {
+        let alg = self.algorithm();
+        let mut header = Header::new(alg);
+        if let Some(value) = &self.header {
+            apply_header_overrides(&mut header, value, alg)?;
+        }
+        header.kid = self.kid.clone();
+        if self.no_typ {
+            header.typ = None;
+        } else if let Some(typ) = &self.typ {
+            header.typ = Some(typ.clone());
+        } else if let Some(typ) = presets::preset_typ(&self.preset) {
+            header.typ = typ.map(str::to_string);
+        } else {
+            header.typ = Some("JWT".to_string());
+        }
+        Ok(header)
+    }
+
+    /// Builds the claims and header and signs them with `key`.
+    pub fn encode(&self, key: &SigningKey) -> AppResult<String> {
+        let claims = self.build_claims()?;
+        let header = self.build_header()?;
+        let mut params = self.build_header_params()?;
+        presets::apply_preset_header(&self.preset, &header, &mut params)?;
+        match self.zip {
+            Some(PayloadZip::Def) => jwt_ops::encode_token_deflated(&header, &params, &claims, key),
+            None => jwt_ops::encode_token_with_params(&header, &params, &claims, key),
+        }
+    }
+
+    fn namespaced_claim_kv(&self) -> AppResult<Vec<String>> {
+        let Some(raw) = self.claim_namespace.as_deref() else {
+            return Ok(self.claim_kv.clone());
+        };
+        let namespace = claims::normalize_namespace(raw)?;
+        Ok(self
+            .claim_kv
+            .iter()
+            .map(|kv| claims::namespace_claim_kv(kv, &namespace))
+            .collect())
+    }
+
+    /// Parses `--header-param k=v`; values use the same JSON-or-string rule as `--claim`.
+    fn build_header_params(&self) -> AppResult<Map<String, Value>> {
+        let mut params = self.mimic_params.clone();
+        for raw in &self.header_params {
+            let (name, value) = claims::parse_claim_kv(raw)?;
+            if name == "alg" {
+                return Err(AppError::invalid_claims(
+                    "--header-param cannot set alg; use --alg",
+                ));
+            }
+            params.insert(name, value);
+        }
+        Ok(params)
+    }
+}
+
+fn apply_header_overrides(header: &mut Header, value: &Value, alg: Algorithm) -> AppResult<()> {
+    let obj = value
+        .as_object()
+        .ok_or_else(|| AppError::invalid_claims("header JSON must be an object"))?;
+
+    for (key, val) in obj {
+        match key.as_str() {
+            "typ" => header.typ = parse_opt_string(val, "typ")?,
+            "kid" => header.kid = parse_opt_string(val, "kid")?,
+            "cty" => header.cty = parse_opt_string(val, "cty")?,
+            "jku" => header.jku = parse_opt_string(val, "jku")?,
+            "jwk" => {
+                if val.is_null() {
+                    header.jwk = None;
+                } else {
+                    let jwk: Jwk = serde_json::from_value(val.clone())
+                        .map_err(|e| AppError::invalid_claims(format!("invalid jwk: {e}")))?;
+                    header.jwk = Some(jwk);
+                }
+            }
+            "x5u" => header.x5u = parse_opt_string(val, "x5u")?,
+            "x5c" => header.x5c = parse_opt_string_list(val, "x5c")?,
+            "x5t" => header.x5t = parse_opt_string(val, "x5t")?,
+            "x5t#S256" => header.x5t_s256 = parse_opt_string(val, "x5t#S256")?,
+            "alg" => {
+                let expected = format!("{:?}", alg);
+                let provided = val
+                    .as_str()
+                    .ok_or_else(|| AppError::invalid_claims("header alg must be a string"))?;
+                if !provided.eq_ignore_ascii_case(&expected) {
+                    return Err(AppError::invalid_claims(format!(
+                        "header alg '{provided}' does not match --alg {expected}"
+                    )));
+                }
+            }
+            other => {
+                return Err(AppError::invalid_claims(format!(
+                    "unsupported header field '{other}'"
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn parse_opt_string(value: &Value, label: &str) -> AppResult<Option<String>> {
+    if value.is_null() {
+        return Ok(None);
+    }
+    value
+        .as_str()
+        .map(|s| Some(s.to_string()))
+        .ok_or_else(|| AppError::invalid_claims(format!("{label} must be a string or null")))
+}
+
+fn parse_opt_string_list(value: &Value, label: &str) -> AppResult<Option<Vec<String>>> {
+    if value.is_null() {
+        return Ok(None);
+    }
+    let arr = value.as_array().ok_or_else(|| {
+        AppError::invalid_claims(format!("{label} must be an array of strings or null"))
+    })?;
+    let mut out = Vec::with_capacity(arr.len());
+    for item in arr {
+        let s = item.as_str().ok_or_else(|| {
+            AppError::invalid_claims(format!("{label} must contain only strings"))
+        })?;
+        out.push(s.to_string());
+    }
+    Ok(Some(out))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{DecodingKey, EncodingKey, Validation};
+    use serde_json::json;
+
+    #[test]
+    fn parse_opt_string_handles_null_and_string() {
+        assert_eq!(parse_opt_string(&json!(null), "typ").unwrap(), None);
+        assert_eq!(
+            parse_opt_string(&json!("JWT"), "typ").unwrap(),
+            Some("JWT".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_opt_string_rejects_non_string() {
+        let err = parse_opt_string(&json!(123), "typ").expect_err("expected error");
+        assert!(err.to_string().contains("typ must be a string"));
+    }
+
+    #[test]
+    fn parse_opt_string_list_handles_null_and_array() {
+        assert_eq!(parse_opt_string_list(&json!(null), "x5c").unwrap(), None);
+        assert_eq!(
+            parse_opt_string_list(&json!(["a", "b"]), "x5c").unwrap(),
+            Some(vec!["a".to_string(), "b".to_string()])
+        );
+    }
+
+    #[test]
+    fn parse_opt_string_list_rejects_invalid_inputs() {
+        let err = parse_opt_string_list(&json!("nope"), "x5c").expect_err("expected error");
+        assert!(err.to_string().contains("x5c must be an array"));
+        let err = parse_opt_string_list(&json!(["ok", 1]), "x5c").expect_err("expected error");
+        assert!(err.to_string().contains("x5c must contain only strings"));
+    }
+
+    #[test]
+    fn apply_header_overrides_rejects_unknown_and_alg_mismatch() {
+        let mut header = Header::new(Algorithm::HS256);
+        let err = apply_header_overrides(&mut header, &json!({ "nope": "x" }), Algorithm::HS256)
+            .expect_err("expected error");
+        assert!(err.to_string().contains("unsupported header field"));
+
+        let mut header = Header::new(Algorithm::RS256);
+        let err = apply_header_overrides(&mut header, &json!({ "alg": "HS256" }), Algorithm::RS256)
+            .expect_err("expected error");
+        assert!(err.to_string().contains("does not match --alg"));
+    }
+
+    #[test]
+    fn build_header_sets_typ_and_kid() {
+        let header = EncodeRequest::new(JwtAlg::HS256)
+            .kid("kid-1")
+            .build_header()
+            .expect("header");
+        assert_eq!(header.kid.as_deref(), Some("kid-1"));
+        assert_eq!(header.typ.as_deref(), Some("JWT"));
+    }
+
+    #[test]
+    fn build_header_respects_no_typ() {
+        let request = EncodeRequest::new(JwtAlg::HS256).no_typ(true);
+        assert_eq!(request.build_header().expect("header").typ, None);
+
+        let header = request
+            .no_typ(false)
+            .typ("JOSE")
+            .build_header()
+            .expect("header");
+        assert_eq!(header.typ.as_deref(), Some("JOSE"));
+    }
+
+    #[test]
+    fn mimic_copies_claims_in_order_and_keyless_header() {
+        let captured = jwt_ops::encode_token_with_params(
+            &Header {
+                kid: Some("prod-key".to_string()),
+                x5t: Some("thumb".to_string()),
+                typ: Some("at+jwt".to_string()),
+                ..Header::new(Algorithm::HS256)
+            },
+            &json!({ "tenant": "acme" }).as_object().unwrap().clone(),
+            &json!({ "sub": "user", "exp": 1, "aud": "api" }),
+            &SigningKey::Local(EncodingKey::from_secret(b"theirs")),
+        )
+        .expect("captured");
+        let request = EncodeRequest::new(JwtAlg::HS256)
+            .mimic(&captured)
+            .expect("mimic")
+            .standard_claims(StandardClaims {
+                exp: Some("100".to_string()),
+                ..StandardClaims::default()
+            });
+        let claims = request.build_claims().expect("claims");
+        let names: Vec<&String> = claims.as_object().unwrap().keys().collect();
+        assert_eq!(names, ["sub", "exp", "aud"]);
+        assert_eq!(claims["exp"], 100);
+
+        let header = request.build_header().expect("header");
+        assert_eq!(header.typ.as_deref(), Some("at+jwt"));
+        assert_eq!((header.kid, header.x5t), (None, None));
+        assert_eq!(
+            request.build_header_params().expect("params"),
+            *json!({ "tenant": "acme" }).as_object().unwrap()
+        );
+
+        let untyped = jwt_ops::encode_token(
+            &Header {
+                typ: None,
+                ..Header::new(Algorithm::HS256)
+            },
+            &json!({}),
+            &EncodingKey::from_secret(b"theirs"),
+        )
+        .expect("untyped");
+        let header = EncodeRequest::new(JwtAlg::HS256)
+            .mimic(&untyped)
+            .expect("mimic")
+            .build_header()
+            .expect("header");
+        assert_eq!(header.typ, None);
+    }
+
+    #[test]
+    fn encode_merges_claim_sources_and_signs() {
+        let request = EncodeRequest::new(JwtAlg::HS256)
+            .claims(json!({ "sub": "user" }))
+            .claim_file(json!({ "role": "admin" }))
+            .claim("role=owner")
+            .standard_claims(StandardClaims {
+                iss: Some("issuer".to_string()),
+                ..StandardClaims::default()
+            })
+            .header_param("nonce=abc");
+        let key = SigningKey::Local(EncodingKey::from_secret(b"secret"));
+        let token = request.encode(&key).expect("token");
+
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.required_spec_claims.clear();
+        validation.validate_exp = false;
+        let decoded = jsonwebtoken::decode::<Value>(
+            &token,
+            &DecodingKey::from_secret(b"secret"),
+            &validation,
+        )
+        .expect("decode");
+        assert_eq!(
+            decoded.claims,
+            json!({ "iss": "issuer", "role": "owner", "sub": "user" })
+        );
+
+        let err = EncodeRequest::new(JwtAlg::HS256)
+            .claims(json!([1]))
+            .build_claims()
+            .expect_err("array claims");
+        assert_eq!(err.exit_code(), 12);
+    }
+}
@@ -7,7 +7,15 @@ pub enum ErrorKind {
     InvalidSignature,
     InvalidClaims,
     InvalidKey,
+    /// A named vault project, key, token or JWKS does not exist.
+    NotFound,
     Internal,
+    /// A network call was needed while `--offline` was set.
+    Offline,
+    /// The token is on its project's revocation list (`verify --check-revocation`).
+    Revoked,
+    /// A global option's value is unusable (`--template` that does not parse or render).
+    InvalidInput,
 }
 
 #[derive(Debug, Clone)]
@@ -44,17 +52,42 @@ impl AppError {
         Self::new(ErrorKind::InvalidKey, message)
     }
 
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::NotFound, message)
+    }
+
     pub fn internal(message: impl Into<String>) -> Self {
         Self::new(ErrorKind::Internal, message)
     }
 
+    pub fn offline(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Offline, message)
+    }
+
+    pub fn revoked(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Revoked, message)
+    }
+
+    pub fn invalid_input(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::InvalidInput, message)
+    }
+
+    pub fn with_details(mut self, details: Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
     pub fn code(&self) -> &'static str {
         match self.kind {
             ErrorKind::InvalidToken => "INVALID_TOKEN",
             ErrorKind::InvalidSignature => "INVALID_SIGNATURE",
             ErrorKind::InvalidClaims => "INVALID_CLAIMS",
             ErrorKind::InvalidKey => "INVALID_KEY",
+            ErrorKind::NotFound => "NOT_FOUND",
             ErrorKind::Internal => "INTERNAL_ERROR",
+            ErrorKind::Offline => "OFFLINE",
+            ErrorKind::Revoked => "TOKEN_REVOKED",
+            ErrorKind::InvalidInput => "INVALID_INPUT",
         }
     }
 
@@ -63,8 +96,12 @@ impl AppError {
             ErrorKind::InvalidToken => 10,
             ErrorKind::InvalidSignature => 11,
             ErrorKind::InvalidClaims => 12,
-            ErrorKind::InvalidKey => 13,
+            // A missing project/key is a key-resolution failure; keep the exit code stable.
+            ErrorKind::InvalidKey | ErrorKind::NotFound => 13,
             ErrorKind::Internal => 14,
+            ErrorKind::Offline => 15,
+            ErrorKind::Revoked => 16,
+            ErrorKind::InvalidInput => 17,
         }
     }
 
@@ -93,9 +130,12 @@ impl From<jsonwebtoken::errors::Error> for AppError {
         use jsonwebtoken::errors::ErrorKind as JwtErr;
         match err.kind() {
             JwtErr::InvalidSignature => AppError::invalid_signature(err.to_string()),
-            JwtErr::ExpiredSignature
-            | JwtErr::ImmatureSignature
-            | JwtErr::InvalidIssuer
+            // `reason` lets callers tell an expired token from one that is not yet valid.
+            JwtErr::ExpiredSignature => AppError::invalid_claims(err.to_string())
+                .with_details(json!({ "reason": "expired" })),
+            JwtErr::ImmatureSignature => AppError::invalid_claims(err.to_string())
+                .with_details(json!({ "reason": "not_yet_valid" })),
+            JwtErr::InvalidIssuer
             | JwtErr::InvalidAudience
             | JwtErr::InvalidSubject
             | JwtErr::MissingRequiredClaim(_) => AppError::invalid_claims(err.to_string()),
@@ -137,9 +177,27 @@ mod tests {
         assert_eq!(err.code(), "INVALID_KEY");
         assert_eq!(err.exit_code(), 13);
 
+        let err = AppError::not_found("project");
+        assert_eq!(err.code(), "NOT_FOUND");
+        assert_eq!(err.exit_code(), 13);
+
         let err = AppError::internal("boom");
         assert_eq!(err.code(), "INTERNAL_ERROR");
         assert_eq!(err.exit_code(), 14);
+
+        let err = AppError::invalid_input("template");
+        assert_eq!(err.code(), "INVALID_INPUT");
+        assert_eq!(err.exit_code(), 17);
+    }
+
+    #[test]
+    fn time_claim_errors_carry_a_reason() {
+        use jsonwebtoken::errors::ErrorKind as JwtErr;
+        let expired = AppError::from(jsonwebtoken::errors::Error::from(JwtErr::ExpiredSignature));
+        assert_eq!(expired.kind, ErrorKind::InvalidClaims);
+        assert_eq!(expired.details, Some(json!({ "reason": "expired" })));
+        let immature = AppError::from(jsonwebtoken::errors::Error::from(JwtErr::ImmatureSignature));
+        assert_eq!(immature.details, Some(json!({ "reason": "not_yet_valid" })));
     }
 
     #[test]
@@ -0,0 +1,439 @@
+//! `inspect --register-file-association`: per-user OS handlers so double-clicking a `.jwt` or
+//! `.jwks` file (or following a `jwt-tester://open?path=...` link) runs `jwt-tester ui
+//! --open-file <target>`, which starts the local UI with that file loaded.
+//!
+//! Registration only touches the current user's settings (XDG data dir, `~/Applications`,
+//! `HKEY_CURRENT_USER`), so it needs no administrator rights.
+
+use crate::error::{AppError, AppResult};
+use serde::Serialize;
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+pub const URL_SCHEME: &str = "jwt-tester";
+/// Files opened through an association are tokens or key sets, never this large.
+#[cfg(feature = "ui")]
+const MAX_OPENED_FILE: u64 = 1024 * 1024;
+const DESKTOP_FILE: &str = "jwt-tester.desktop";
+const JWT_MIME: &str = "application/x-jwt";
+const JWKS_MIME: &str = "application/jwk-set+json";
+const WINDOWS_PROG_ID: &str = "jwt-tester.file";
+const MACOS_BUNDLE_ID: &str = "io.github.jmerta.jwt-tester.open";
+
+/// Everything one registration writes and runs, so `--dry-run` can print it unchanged.
+#[derive(Debug, Serialize)]
+pub struct Registration {
+    pub platform: &'static str,
+    pub files: Vec<PlannedFile>,
+    pub commands: Vec<Vec<String>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlannedFile {
+    pub path: PathBuf,
+    #[serde(skip)]
+    pub contents: String,
+}
+
+/// The handler registration for this platform, pointing at `exe`.
+pub fn plan(exe: &Path) -> AppResult<Registration> {
+    let home = || {
+        directories::BaseDirs::new()
+            .ok_or_else(|| AppError::internal("cannot determine the home directory"))
+    };
+    if cfg!(windows) {
+        Ok(windows_plan(exe))
+    } else if cfg!(target_os = "macos") {
+        Ok(macos_plan(exe, home()?.home_dir()))
+    } else if cfg!(unix) {
+        // BaseDirs honors XDG_DATA_HOME.
+        Ok(linux_plan(exe, home()?.data_dir()))
+    } else {
+        Err(AppError::internal(
+            "file associations are not supported on this platform",
+        ))
+    }
+}
+
+/// Writes the planned files and runs the planned commands. A missing or failing helper (e.g.
+/// `update-desktop-database` on a minimal system) is reported as a warning, not an error: the
+/// files are in place and the desktop picks them up on its next scan.
+pub fn apply(registration: &Registration) -> AppResult<Vec<String>> {
+    for file in &registration.files {
+        if let Some(parent) = file.path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                AppError::internal(format!("failed to create {}: {e}", parent.display()))
+            })?;
+        }
+        std::fs::write(&file.path, &file.contents).map_err(|e| {
+            AppError::internal(format!("failed to write {}: {e}", file.path.display()))
+        })?;
+    }
+    let mut warnings = Vec::new();
+    for command in &registration.commands {
+        let Some((program, args)) = command.split_first() else {
+            continue;
+        };
+        let status = Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+        match status {
+            Ok(status) if status.success() => {}
+            Ok(status) => warnings.push(format!("`{program}` exited with {status}")),
+            Err(err) => warnings.push(format!("could not run `{program}`: {err}")),
+        }
+    }
+    Ok(warnings)
+}
+
+fn linux_plan(exe: &Path, data_dir: &Path) -> Registration {
+    let desktop = format!(
+        "[Desktop Entry]\n\
+         Type=Application\n\
+         Name=JWT Tester\n\
+         Comment=Inspect JWTs and JWKS in the local jwt-tester UI\n\
+         Exec=\"{}\" ui --open-file %u\n\
+         Terminal=false\n\
+         NoDisplay=true\n\
+         MimeType={JWT_MIME};{JWKS_MIME};x-scheme-handler/{URL_SCHEME};\n",
+        exe.display()
+    );
+    let mime = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <mime-info xmlns=\"http://www.freedesktop.org/standards/shared-mime-info\">\n\
+         \x20 <mime-type type=\"{JWT_MIME}\">\n\
+         \x20   <comment>JSON Web Token</comment>\n\
+         \x20   <glob pattern=\"*.jwt\"/>\n\
+         \x20 </mime-type>\n\
+         \x20 <mime-type type=\"{JWKS_MIME}\">\n\
+         \x20   <comment>JSON Web Key Set</comment>\n\
+         \x20   <glob pattern=\"*.jwks\"/>\n\
+         \x20 </mime-type>\n\
+         </mime-info>\n"
+    );
+    let applications = data_dir.join("applications");
+    let mime_dir = data_dir.join("mime");
+    Registration {
+        platform: "linux",
+        files: vec![
+            PlannedFile {
+                path: applications.join(DESKTOP_FILE),
+                contents: desktop,
+            },
+            PlannedFile {
+                path: mime_dir.join("packages").join("jwt-tester.xml"),
+                contents: mime,
+            },
+        ],
+        commands: vec![
+            vec![
+                "update-mime-database".to_string(),
+                mime_dir.display().to_string(),
+            ],
+            vec![
+                "update-desktop-database".to_string(),
+                applications.display().to_string(),
+            ],
+            vec![
+                "xdg-mime".to_string(),
+                "default".to_string(),
+                DESKTOP_FILE.to_string(),
+                JWT_MIME.to_string(),
+                JWKS_MIME.to_string(),
+                format!("x-scheme-handler/{URL_SCHEME}"),
+            ],
+        ],
+    }
+}
+
+/// An AppleScript applet: macOS delivers opened files and URLs as Apple Events, which a plain
+/// shell script in a bundle never sees.
+fn macos_plan(exe: &Path, home: &Path) -> Registration {
+    let exe = exe.display().to_string().replace('"', "\\\"");
+    let script = format!(
+        "on open theFiles\n\
+         \x20 repeat with f in theFiles\n\
+         \x20   do shell script quoted form of \"{exe}\" & \" ui --open-file \" & quoted form of POSIX path of f & \" > /dev/null 2>&1 &\"\n\
+         \x20 end repeat\n\
+         end open\n\
+         \n\
+         on open location theURL\n\
+         \x20 do shell script quoted form of \"{exe}\" & \" ui --open-file \" & quoted form of theURL & \" > /dev/null 2>&1 &\"\n\
+         end open location\n"
+    );
+    let support = home
+        .join("Library")
+        .join("Application Support")
+        .join("jwt-tester");
+    let script_path = support.join("open-handler.applescript");
+    let app = home.join("Applications").join("JWT Tester.app");
+    let plist = app.join("Contents").join("Info.plist");
+    let document_types = json!([{
+        "CFBundleTypeName": "JSON Web Token",
+        "CFBundleTypeRole": "Viewer",
+        "LSHandlerRank": "Owner",
+        "CFBundleTypeExtensions": ["jwt", "jwks"],
+    }]);
+    let url_types = json!([{
+        "CFBundleURLName": "jwt-tester",
+        "CFBundleURLSchemes": [URL_SCHEME],
+    }]);
+    let plutil = |key: &str, kind: &str, value: String| {
+        vec![
+            "plutil".to_string(),
+            "-replace".to_string(),
+            key.to_string(),
+            kind.to_string(),
+            value,
+            plist.display().to_string(),
+        ]
+    };
+    Registration {
+        platform: "macos",
+        files: vec![PlannedFile {
+            path: script_path.clone(),
+            contents: script,
+        }],
+        commands: vec![
+            vec![
+                "osacompile".to_string(),
+                "-o".to_string(),
+                app.display().to_string(),
+                script_path.display().to_string(),
+            ],
+            plutil(
+                "CFBundleIdentifier",
+                "-string",
+                MACOS_BUNDLE_ID.to_string(),
+            ),
+            plutil("CFBundleDocumentTypes", "-json", document_types.to_string()),
+            plutil("CFBundleURLTypes", "-json", url_types.to_string()),
+            vec![
+                "/System/Library/Frameworks/CoreServices.framework/Frameworks/LaunchServices.framework/Support/lsregister"
+                    .to_string(),
+                "-f".to_string(),
+                app.display().to_string(),
+            ],
+        ],
+    }
+}
+
+fn windows_plan(exe: &Path) -> Registration {
+    let classes = r"HKCU\Software\Classes";
+    let open_command = format!("\"{}\" ui --open-file \"%1\"", exe.display());
+    let default_value = |key: String, value: &str| {
+        vec![
+            "reg".to_string(),
+            "add".to_string(),
+            key,
+            "/ve".to_string(),
+            "/d".to_string(),
+            value.to_string(),
+            "/f".to_string(),
+        ]
+    };
+    Registration {
+        platform: "windows",
+        files: Vec::new(),
+        commands: vec![
+            default_value(format!(r"{classes}\.jwt"), WINDOWS_PROG_ID),
+            default_value(format!(r"{classes}\.jwks"), WINDOWS_PROG_ID),
+            default_value(format!(r"{classes}\{WINDOWS_PROG_ID}"), "JSON Web Token"),
+            default_value(
+                format!(r"{classes}\{WINDOWS_PROG_ID}\shell\open\command"),
+                &open_command,
+            ),
+            default_value(format!(r"{classes}\{URL_SCHEME}"), "URL:jwt-tester"),
+            vec![
+                "reg".to_string(),
+                "add".to_string(),
+                format!(r"{classes}\{URL_SCHEME}"),
+                "/v".to_string(),
+                "URL Protocol".to_string(),
+                "/d".to_string(),
+                String::new(),
+                "/f".to_string(),
+            ],
+            default_value(
+                format!(r"{classes}\{URL_SCHEME}\shell\open\command"),
+                &open_command,
+            ),
+        ],
+    }
+}
+
+/// A file handed to `ui --open-file`, shown in the UI once the page loads.
+#[cfg(feature = "ui")]
+#[derive(Debug, Clone, Serialize)]
+pub struct OpenedFile {
+    pub name: String,
+    /// `jwt` or `jwks`.
+    pub kind: &'static str,
+    pub content: String,
+}
+
+/// Reads the target of `ui --open-file`: a path, a `file://` URL (what desktop launchers pass
+/// for `%u`), or `jwt-tester://open?path=<percent-encoded path>`.
+#[cfg(feature = "ui")]
+pub fn read_opened(target: &str) -> AppResult<OpenedFile> {
+    let path = open_target_path(target)?;
+    let meta = std::fs::metadata(&path)
+        .map_err(|e| AppError::invalid_token(format!("cannot open {}: {e}", path.display())))?;
+    if meta.len() > MAX_OPENED_FILE {
+        return Err(AppError::invalid_token(format!(
+            "{} is larger than {MAX_OPENED_FILE} bytes",
+            path.display()
+        )));
+    }
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| AppError::invalid_token(format!("cannot read {}: {e}", path.display())))?;
+    let content = content.trim().to_string();
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let jwks = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("jwks"))
+        || serde_json::from_str::<serde_json::Value>(&content)
+            .is_ok_and(|doc| doc.get("keys").is_some_and(|keys| keys.is_array()));
+    Ok(OpenedFile {
+        name,
+        kind: if jwks { "jwks" } else { "jwt" },
+        content,
+    })
+}
+
+#[cfg(feature = "ui")]
+fn open_target_path(target: &str) -> AppResult<PathBuf> {
+    if let Some(rest) = target.strip_prefix("file://") {
+        // `file:///tmp/a.jwt` and `file://localhost/tmp/a.jwt` both name /tmp/a.jwt.
+        let path = percent_decode(rest.strip_prefix("localhost").unwrap_or(rest))?;
+        // `file:///C:/x.jwt` on Windows.
+        let path = match path.as_bytes() {
+            [b'/', _, b':', ..] => path[1..].to_string(),
+            _ => path,
+        };
+        return Ok(PathBuf::from(path));
+    }
+    let Some(rest) = target.strip_prefix(&format!("{URL_SCHEME}:")) else {
+        return Ok(PathBuf::from(target));
+    };
+    let rest = rest.trim_start_matches('/');
+    let query = rest
+        .strip_prefix("open?")
+        .or_else(|| rest.strip_prefix("open/?"))
+        .ok_or_else(|| {
+            AppError::invalid_token(format!(
+                "unsupported {URL_SCHEME} URL: expected {URL_SCHEME}://open?path=<PATH>"
+            ))
+        })?;
+    query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("path="))
+        .ok_or_else(|| AppError::invalid_token(format!("{URL_SCHEME} URL has no path parameter")))
+        .and_then(|path| percent_decode(&path.replace('+', " ")))
+        .map(PathBuf::from)
+}
+
+#[cfg(feature = "ui")]
+fn percent_decode(value: &str) -> AppResult<String> {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let byte = value
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| {
+                    AppError::invalid_token(format!("invalid percent-encoding in '{value}'"))
+                })?;
+            out.push(byte);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out)
+        .map_err(|_| AppError::invalid_token(format!("'{value}' is not valid UTF-8")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn linux_plan_registers_both_extensions_and_the_scheme() {
+        let plan = linux_plan(
+            Path::new("/opt/jwt tester/jwt-tester"),
+            Path::new("/home/qa/.local/share"),
+        );
+        let desktop = &plan.files[0];
+        assert!(desktop.path.ends_with("applications/jwt-tester.desktop"));
+        assert!(desktop
+            .contents
+            .contains("Exec=\"/opt/jwt tester/jwt-tester\" ui --open-file %u"));
+        assert!(desktop.contents.contains("x-scheme-handler/jwt-tester;"));
+        assert!(plan.files[1].contents.contains("*.jwks"));
+        assert_eq!(plan.commands[2][0], "xdg-mime");
+    }
+
+    #[test]
+    fn windows_plan_quotes_the_executable_and_argument() {
+        let plan = windows_plan(Path::new(r"C:\Tools\jwt-tester.exe"));
+        let command = plan
+            .commands
+            .iter()
+            .find(|c| c[2].ends_with(r"jwt-tester.file\shell\open\command"))
+            .expect("open command");
+        assert_eq!(
+            command[5],
+            r#""C:\Tools\jwt-tester.exe" ui --open-file "%1""#
+        );
+        assert!(plan
+            .commands
+            .iter()
+            .any(|c| c.contains(&"URL Protocol".to_string())));
+    }
+
+    #[cfg(feature = "ui")]
+    #[test]
+    fn open_targets_accept_paths_file_urls_and_the_scheme() {
+        assert_eq!(
+            open_target_path("/tmp/a.jwt").unwrap(),
+            PathBuf::from("/tmp/a.jwt")
+        );
+        assert_eq!(
+            open_target_path("file:///tmp/my%20token.jwt").unwrap(),
+            PathBuf::from("/tmp/my token.jwt")
+        );
+        assert_eq!(
+            open_target_path("jwt-tester://open?path=%2Ftmp%2Fkeys.jwks").unwrap(),
+            PathBuf::from("/tmp/keys.jwks")
+        );
+        assert!(open_target_path("jwt-tester://delete?path=x").is_err());
+    }
+
+    #[cfg(feature = "ui")]
+    #[test]
+    fn read_opened_tells_tokens_from_key_sets() {
+        let dir = tempfile::tempdir().unwrap();
+        let token = dir.path().join("a.jwt");
+        std::fs::write(&token, "eyJhbGciOiJub25lIn0.e30.\n").unwrap();
+        let opened = read_opened(token.to_str().unwrap()).unwrap();
+        assert_eq!(
+            (opened.kind, opened.content.as_str()),
+            ("jwt", "eyJhbGciOiJub25lIn0.e30.")
+        );
+
+        let keys = dir.path().join("keys.json");
+        std::fs::write(&keys, r#"{"keys":[]}"#).unwrap();
+        assert_eq!(read_opened(keys.to_str().unwrap()).unwrap().kind, "jwks");
+    }
+}
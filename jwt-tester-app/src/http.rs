@@ -0,0 +1,361 @@
+use crate::error::{AppError, AppResult};
+#[cfg(feature = "http")]
+use base64::engine::general_purpose::STANDARD;
+#[cfg(feature = "http")]
+use base64::Engine;
+use serde_json::Value;
+#[cfg(feature = "http")]
+use std::io::{self, Read};
+#[cfg(feature = "http")]
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "http")]
+use std::time::Duration;
+
+#[cfg(feature = "http")]
+const TIMEOUT: Duration = Duration::from_secs(10);
+#[cfg(feature = "http")]
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(300);
+
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+/// Set once from `--offline`; every request below fails fast afterwards.
+pub fn set_offline(offline: bool) {
+    OFFLINE.store(offline, Ordering::Relaxed);
+}
+
+fn ensure_online(url: &str) -> AppResult<()> {
+    if OFFLINE.load(Ordering::Relaxed) {
+        return Err(AppError::offline(format!(
+            "network access is disabled by --offline (needed {url})"
+        )));
+    }
+    Ok(())
+}
+
+/// Status and body of a response; the body is the raw text when it is not JSON.
+pub struct JsonResponse {
+    pub status: u16,
+    pub body: Value,
+}
+
+impl JsonResponse {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+#[cfg(feature = "http")]
+fn agent() -> ureq::Agent {
+    agent_with_timeout(TIMEOUT)
+}
+
+#[cfg(feature = "http")]
+fn agent_with_timeout(timeout: Duration) -> ureq::Agent {
+    ureq::AgentBuilder::new()
+        .timeout(timeout)
+        .user_agent(concat!("jwt-tester/", env!("CARGO_PKG_VERSION")))
+        .build()
+}
+
+/// GETs a JSON document; non-2xx statuses are errors.
+#[cfg(feature = "http")]
+pub fn get_json(url: &str, accept: &str) -> AppResult<Value> {
+    ensure_online(url)?;
+    agent()
+        .get(url)
+        .set("Accept", accept)
+        .call()
+        .map_err(|e| AppError::internal(format!("GET {url} failed: {e}")))?
+        .into_json()
+        .map_err(|e| AppError::internal(format!("{url} did not return JSON: {e}")))
+}
+
+/// Downloads a response body of at most `limit` bytes, with a longer timeout than API calls.
+#[cfg(feature = "http")]
+pub fn download(url: &str, limit: u64) -> AppResult<Vec<u8>> {
+    ensure_online(url)?;
+    let response = agent_with_timeout(DOWNLOAD_TIMEOUT)
+        .get(url)
+        .call()
+        .map_err(|e| AppError::internal(format!("GET {url} failed: {e}")))?;
+    let mut bytes = Vec::new();
+    response
+        .into_reader()
+        .take(limit + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|e| AppError::internal(format!("failed to download {url}: {e}")))?;
+    if bytes.len() as u64 > limit {
+        return Err(AppError::internal(format!(
+            "{url} is larger than {limit} bytes"
+        )));
+    }
+    Ok(bytes)
+}
+
+/// GETs a URL that came from untrusted input, such as a token's `jku`/`x5u` header. Redirects
+/// are not followed, the body is capped at `limit` bytes, and unless `allow_internal` is set the
+/// host may only resolve to public addresses. The check runs in the resolver ureq connects
+/// with, so a DNS answer cannot change between the check and the connection.
+#[cfg(feature = "http")]
+pub fn get_untrusted(
+    url: &str,
+    accept: &str,
+    limit: u64,
+    allow_internal: bool,
+) -> AppResult<Vec<u8>> {
+    ensure_online(url)?;
+    let agent = ureq::AgentBuilder::new()
+        .timeout(TIMEOUT)
+        .redirects(0)
+        .user_agent(concat!("jwt-tester/", env!("CARGO_PKG_VERSION")))
+        .resolver(move |netloc: &str| -> io::Result<Vec<SocketAddr>> {
+            let addrs: Vec<SocketAddr> = netloc.to_socket_addrs()?.collect();
+            if allow_internal {
+                return Ok(addrs);
+            }
+            let public: Vec<SocketAddr> = addrs
+                .into_iter()
+                .filter(|addr| is_public(addr.ip()))
+                .collect();
+            if public.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    "host resolves only to loopback, private or link-local addresses",
+                ));
+            }
+            Ok(public)
+        })
+        .build();
+    let response = agent
+        .get(url)
+        .set("Accept", accept)
+        .call()
+        .map_err(|e| AppError::internal(format!("GET {url} failed: {e}")))?;
+    if !(200..300).contains(&response.status()) {
+        return Err(AppError::internal(format!(
+            "GET {url} returned status {} (redirects are not followed)",
+            response.status()
+        )));
+    }
+    let mut bytes = Vec::new();
+    response
+        .into_reader()
+        .take(limit + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|e| AppError::internal(format!("failed to read {url}: {e}")))?;
+    if bytes.len() as u64 > limit {
+        return Err(AppError::internal(format!(
+            "{url} is larger than {limit} bytes"
+        )));
+    }
+    Ok(bytes)
+}
+
+/// False for addresses this machine can reach but an outside caller should not: loopback,
+/// private, link-local (cloud metadata), CGNAT, unspecified and unique-local IPv6.
+#[cfg(feature = "http")]
+pub fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+            !(v4.is_loopback()
+                || v4.is_private()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                || v4.is_documentation()
+                || (a == 100 && (64..128).contains(&b)))
+        }
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return is_public(IpAddr::V4(v4));
+            }
+            let first = v6.segments()[0];
+            !(v6.is_loopback()
+                || v6.is_unspecified()
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+/// POSTs a JSON body and discards the response; non-2xx statuses are errors.
+#[cfg(feature = "http")]
+pub fn post_json(url: &str, body: &Value) -> AppResult<()> {
+    ensure_online(url)?;
+    agent()
+        .post(url)
+        .send_json(body)
+        .map_err(|e| AppError::internal(format!("POST {url} failed: {e}")))?;
+    Ok(())
+}
+
+/// POSTs a pre-serialized body with extra headers (e.g. a signature computed over exactly these
+/// bytes); non-2xx statuses are errors.
+#[cfg(feature = "http")]
+pub fn post_bytes(
+    url: &str,
+    content_type: &str,
+    body: &[u8],
+    headers: &[(&str, &str)],
+) -> AppResult<()> {
+    ensure_online(url)?;
+    let mut request = agent().post(url).set("Content-Type", content_type);
+    for (name, value) in headers {
+        request = request.set(name, value);
+    }
+    request
+        .send_bytes(body)
+        .map_err(|e| AppError::internal(format!("POST {url} failed: {e}")))?;
+    Ok(())
+}
+
+/// Gets a fresh anti-replay nonce from an ACME `newNonce` endpoint (RFC 8555 §7.2).
+#[cfg(feature = "http")]
+pub fn fetch_nonce(url: &str) -> AppResult<String> {
+    ensure_online(url)?;
+    let response = agent()
+        .head(url)
+        .call()
+        .map_err(|e| AppError::internal(format!("HEAD {url} failed: {e}")))?;
+    response
+        .header("Replay-Nonce")
+        .map(str::to_string)
+        .ok_or_else(|| AppError::internal(format!("{url} did not return a Replay-Nonce header")))
+}
+
+/// POSTs an `application/x-www-form-urlencoded` body. Error statuses are returned, not raised,
+/// so callers can read OAuth error responses; only transport failures are errors.
+#[cfg(feature = "http")]
+pub fn post_form(
+    url: &str,
+    form: &[(&str, &str)],
+    basic_auth: Option<(&str, &str)>,
+) -> AppResult<JsonResponse> {
+    ensure_online(url)?;
+    let mut request = agent().post(url).set("Accept", "application/json");
+    if let Some((user, password)) = basic_auth {
+        request = request.set("Authorization", &basic_auth_header(user, password));
+    }
+    let response = match request.send_form(form) {
+        Ok(response) | Err(ureq::Error::Status(_, response)) => response,
+        Err(err) => return Err(AppError::internal(format!("POST {url} failed: {err}"))),
+    };
+    let status = response.status();
+    let text = response
+        .into_string()
+        .map_err(|e| AppError::internal(format!("failed to read response from {url}: {e}")))?;
+    let body = serde_json::from_str(&text).unwrap_or(Value::String(text));
+    Ok(JsonResponse { status, body })
+}
+
+/// `client_secret_basic` credentials: both parts are form-encoded first (RFC 6749 §2.3.1).
+#[cfg(feature = "http")]
+pub fn basic_auth_header(user: &str, password: &str) -> String {
+    let credentials = format!("{}:{}", form_encode(user), form_encode(password));
+    format!("Basic {}", STANDARD.encode(credentials))
+}
+
+#[cfg(feature = "http")]
+fn form_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            b' ' => "+".to_string(),
+            other => format!("%{other:02X}"),
+        })
+        .collect()
+}
+
+#[cfg(not(feature = "http"))]
+fn unavailable(url: &str) -> AppError {
+    AppError::internal(format!(
+        "fetching {url} requires a build with the `http` feature"
+    ))
+}
+
+#[cfg(not(feature = "http"))]
+pub fn get_json(url: &str, _accept: &str) -> AppResult<Value> {
+    ensure_online(url)?;
+    Err(unavailable(url))
+}
+
+#[cfg(not(feature = "http"))]
+pub fn get_untrusted(
+    url: &str,
+    _accept: &str,
+    _limit: u64,
+    _allow_internal: bool,
+) -> AppResult<Vec<u8>> {
+    ensure_online(url)?;
+    Err(unavailable(url))
+}
+
+#[cfg(not(feature = "http"))]
+pub fn post_json(url: &str, _body: &Value) -> AppResult<()> {
+    ensure_online(url)?;
+    Err(unavailable(url))
+}
+
+#[cfg(not(feature = "http"))]
+pub fn post_bytes(
+    url: &str,
+    _content_type: &str,
+    _body: &[u8],
+    _headers: &[(&str, &str)],
+) -> AppResult<()> {
+    ensure_online(url)?;
+    Err(unavailable(url))
+}
+
+#[cfg(not(feature = "http"))]
+pub fn fetch_nonce(url: &str) -> AppResult<String> {
+    ensure_online(url)?;
+    Err(unavailable(url))
+}
+
+#[cfg(not(feature = "http"))]
+pub fn post_form(
+    url: &str,
+    _form: &[(&str, &str)],
+    _basic_auth: Option<(&str, &str)>,
+) -> AppResult<JsonResponse> {
+    ensure_online(url)?;
+    Err(unavailable(url))
+}
+
+#[cfg(all(test, feature = "http"))]
+mod tests {
+    use super::{basic_auth_header, is_public};
+
+    #[test]
+    fn basic_auth_header_form_encodes_credentials() {
+        // base64("client%3A1:s+cret%2F")
+        assert_eq!(
+            basic_auth_header("client:1", "s cret/"),
+            "Basic Y2xpZW50JTNBMTpzK2NyZXQlMkY="
+        );
+    }
+
+    #[test]
+    fn internal_addresses_are_not_public() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "169.254.169.254",
+            "100.64.0.1",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:192.168.0.1",
+        ] {
+            assert!(!is_public(ip.parse().unwrap()), "{ip}");
+        }
+        assert!(is_public("93.184.216.34".parse().unwrap()));
+        assert!(is_public("2606:4700::1111".parse().unwrap()));
+    }
+}
@@ -1,9 +1,15 @@
+use crate::armor;
 use crate::error::{AppError, AppResult};
+use crate::prompt;
 use base64::engine::general_purpose::STANDARD;
 use base64::Engine;
 use serde_json::Value;
-use std::io::IsTerminal;
-use std::io::{Read, Write};
+use std::io::Read;
+
+mod dotenv;
+mod json_path;
+
+pub use dotenv::{read_dotenv, wildcard_match};
 
 fn prompt_label(spec: &str) -> Option<&str> {
     if spec == "prompt" {
@@ -13,25 +19,35 @@ fn prompt_label(spec: &str) -> Option<&str> {
     }
 }
 
-fn read_prompt_value(prompt: &str) -> std::io::Result<String> {
-    eprint!("{prompt}");
-    std::io::stderr().flush()?;
-    rpassword::read_password()
+/// Reads an inherited file descriptor (`fd:3` with `3<secret.file`), so secrets never appear in
+/// argv or the environment. Goes through `/dev/fd/<n>`, leaving the descriptor itself open.
+fn read_fd(spec: &str) -> Option<Result<Vec<u8>, String>> {
+    let raw = spec.strip_prefix("fd:")?;
+    let Ok(fd) = raw.trim().parse::<u32>() else {
+        return Some(Err(format!("invalid file descriptor '{raw}'")));
+    };
+    if cfg!(not(unix)) {
+        return Some(Err("fd: input is only supported on Unix".to_string()));
+    }
+    Some(
+        std::fs::read(format!("/dev/fd/{fd}"))
+            .map_err(|e| format!("failed to read file descriptor {fd}: {e}")),
+    )
 }
 
 pub fn read_input(spec: &str) -> AppResult<String> {
     if let Some(label) = prompt_label(spec) {
-        if !std::io::stdin().is_terminal() {
+        if !prompt::has_terminal() {
             return Err(AppError::invalid_token(
-                "prompt input requires a TTY; use '-', '@file', or env:NAME".to_string(),
+                "prompt input requires a TTY; use '-', '@file', fd:N, or env:NAME".to_string(),
             ));
         }
-        let prompt = if label.trim().is_empty() {
+        let message = if label.trim().is_empty() {
             "Enter value: "
         } else {
             label
         };
-        let value = read_prompt_value(prompt)
+        let value = prompt::read_hidden(message)
             .map_err(|e| AppError::invalid_token(format!("failed to read prompt: {e}")))?;
         return Ok(value.trim().to_string());
     }
@@ -47,6 +63,11 @@ pub fn read_input(spec: &str) -> AppResult<String> {
             .map_err(|e| AppError::invalid_token(format!("failed to read file {rest}: {e}")))?;
         return Ok(data.trim().to_string());
     }
+    if let Some(data) = read_fd(spec) {
+        let data = String::from_utf8(data.map_err(AppError::invalid_token)?)
+            .map_err(|_| AppError::invalid_token("file descriptor input is not valid UTF-8"))?;
+        return Ok(data.trim().to_string());
+    }
     if let Some(env) = spec.strip_prefix("env:") {
         return std::env::var(env)
             .map_err(|_| AppError::invalid_key(format!("env var {env} not set")));
@@ -54,19 +75,135 @@ pub fn read_input(spec: &str) -> AppResult<String> {
     Ok(spec.to_string())
 }
 
+const TOKEN_URL_PARAMS: [&str; 3] = ["id_token", "access_token", "assertion"];
+
+/// Reads a token argument like [`read_input`], then unwraps the containers tokens are usually
+/// copied from: `Authorization:`/`Cookie:` header lines, `Bearer ` prefixes, redirect URLs
+/// carrying `id_token`/`access_token`/`assertion`, and percent-encoding.
+pub fn read_token_input(spec: &str) -> AppResult<String> {
+    extract_token(&read_input(spec)?)
+}
+
+/// [`read_token_input`] for a JSON document (a token endpoint response, a SAML bridge reply):
+/// the token is the string at `path` (`$.access_token`), then unwrapped the same way.
+pub fn read_token_at_path(spec: &str, path: &str) -> AppResult<String> {
+    extract_token(&json_path::select_string(&read_input(spec)?, path)?)
+}
+
+pub fn extract_token(raw: &str) -> AppResult<String> {
+    if armor::is_armored(raw) {
+        return armor::dearmor(raw);
+    }
+    let value = raw.trim();
+    if let Some((name, rest)) = value.split_once(':') {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "authorization" => return Ok(percent_decode_lossy(strip_auth_scheme(rest.trim()))),
+            "cookie" | "set-cookie" => return token_from_cookie(rest),
+            _ => {}
+        }
+    }
+    if value.contains("://") || value.starts_with('?') || value.starts_with('#') {
+        return token_from_url(value);
+    }
+    Ok(percent_decode_lossy(strip_auth_scheme(value)))
+}
+
+fn strip_auth_scheme(value: &str) -> &str {
+    for scheme in ["bearer ", "dpop "] {
+        if value.len() > scheme.len() && value[..scheme.len()].eq_ignore_ascii_case(scheme) {
+            return value[scheme.len()..].trim();
+        }
+    }
+    value
+}
+
+fn token_from_url(url: &str) -> AppResult<String> {
+    let (before_fragment, fragment) = url.split_once('#').unwrap_or((url, ""));
+    let query = before_fragment
+        .split_once('?')
+        .map(|(_, query)| query)
+        .unwrap_or("");
+    let params: Vec<(String, String)> = [query, fragment]
+        .iter()
+        .flat_map(|part| part.split('&'))
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(name, value)| (percent_decode_lossy(name), percent_decode_lossy(value)))
+        .collect();
+    TOKEN_URL_PARAMS
+        .iter()
+        .find_map(|wanted| {
+            params
+                .iter()
+                .find(|(name, value)| name == wanted && !value.is_empty())
+                .map(|(_, value)| value.clone())
+        })
+        .ok_or_else(|| {
+            AppError::invalid_token(
+                "no id_token, access_token, or assertion parameter found in URL",
+            )
+        })
+}
+
+fn token_from_cookie(header: &str) -> AppResult<String> {
+    header
+        .split(';')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(_, value)| percent_decode_lossy(value.trim().trim_matches('"')))
+        .find(|value| looks_like_jwt(value))
+        .ok_or_else(|| AppError::invalid_token("no JWT-shaped cookie value found"))
+}
+
+fn looks_like_jwt(value: &str) -> bool {
+    let segments: Vec<&str> = value.split('.').collect();
+    matches!(segments.len(), 3 | 5)
+        && !segments[0].is_empty()
+        && segments.iter().all(|segment| {
+            segment
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_' || b == b'=')
+        })
+}
+
+// Invalid escapes are kept verbatim; the JWT parser reports anything still malformed.
+fn percent_decode_lossy(value: &str) -> String {
+    if !value.contains('%') {
+        return value.to_string();
+    }
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| value.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                out.push(byte);
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
 pub fn read_input_bytes(spec: &str) -> AppResult<Vec<u8>> {
     if let Some(label) = prompt_label(spec) {
-        if !std::io::stdin().is_terminal() {
+        if !prompt::has_terminal() {
             return Err(AppError::invalid_key(
-                "prompt input requires a TTY; use '-', '@file', or env:NAME".to_string(),
+                "prompt input requires a TTY; use '-', '@file', fd:N, or env:NAME".to_string(),
             ));
         }
-        let prompt = if label.trim().is_empty() {
+        let message = if label.trim().is_empty() {
             "Enter value: "
         } else {
             label
         };
-        let value = read_prompt_value(prompt)
+        let value = prompt::read_hidden(message)
             .map_err(|e| AppError::invalid_key(format!("failed to read prompt: {e}")))?;
         return Ok(value.trim().as_bytes().to_vec());
     }
@@ -82,6 +219,9 @@ pub fn read_input_bytes(spec: &str) -> AppResult<Vec<u8>> {
             .map_err(|e| AppError::invalid_key(format!("failed to read file {rest}: {e}")))?;
         return Ok(data);
     }
+    if let Some(data) = read_fd(spec) {
+        return data.map_err(AppError::invalid_key);
+    }
     if let Some(rest) = spec.strip_prefix("b64:") {
         let decoded = STANDARD
             .decode(rest)
@@ -122,6 +262,29 @@ mod tests {
         std::env::remove_var(&var);
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn read_input_reads_inherited_file_descriptor() {
+        use std::os::fd::AsRawFd;
+        let dir = tempdir().expect("tempdir");
+        let path = dir.path().join("secret.txt");
+        std::fs::write(&path, "fd-secret\n").expect("write file");
+        let file = std::fs::File::open(&path).expect("open file");
+        let spec = format!("fd:{}", file.as_raw_fd());
+        assert_eq!(read_input(&spec).expect("read fd"), "fd-secret");
+        // Linux reopens the file behind `/dev/fd/<n>`, so a second read starts from the top again;
+        // macOS and the BSDs dup the descriptor and share its (now exhausted) offset.
+        #[cfg(target_os = "linux")]
+        assert_eq!(
+            read_input_bytes(&spec).expect("read fd bytes"),
+            b"fd-secret\n"
+        );
+
+        let err = read_input("fd:three").expect_err("expected fd error");
+        assert!(err.to_string().contains("invalid file descriptor"));
+        assert!(read_input_bytes("fd:987654").is_err());
+    }
+
     #[test]
     fn read_input_env_missing_errors() {
         let var = format!("JWT_TESTER_ENV_{}", Uuid::new_v4());
@@ -132,7 +295,7 @@ mod tests {
 
     #[test]
     fn read_input_prompt_requires_tty() {
-        if std::io::stdin().is_terminal() {
+        if prompt::has_terminal() {
             return;
         }
         let err = read_input("prompt").expect_err("expected prompt error");
@@ -153,13 +316,56 @@ mod tests {
 
     #[test]
     fn read_input_bytes_prompt_requires_tty() {
-        if std::io::stdin().is_terminal() {
+        if prompt::has_terminal() {
             return;
         }
         let err = read_input_bytes("prompt").expect_err("expected prompt error");
         assert!(err.to_string().contains("TTY"));
     }
 
+    #[test]
+    fn extract_token_unwraps_headers_and_bearer() {
+        assert_eq!(extract_token("  a.b.c ").unwrap(), "a.b.c");
+        assert_eq!(extract_token("Bearer a.b.c").unwrap(), "a.b.c");
+        assert_eq!(
+            extract_token("Authorization: bearer a.b.c").unwrap(),
+            "a.b.c"
+        );
+        assert_eq!(
+            extract_token("Cookie: theme=dark; session=a.b.c; lang=en").unwrap(),
+            "a.b.c"
+        );
+        assert!(extract_token("Cookie: theme=dark")
+            .unwrap_err()
+            .to_string()
+            .contains("cookie"));
+    }
+
+    #[test]
+    fn extract_token_reads_url_query_and_fragment() {
+        assert_eq!(
+            extract_token("https://app.test/cb?state=x#id_token=a.b.c&token_type=Bearer").unwrap(),
+            "a.b.c"
+        );
+        assert_eq!(
+            extract_token("https://app.test/cb?access_token=a.b.c&id_token=d.e.f").unwrap(),
+            "d.e.f"
+        );
+        assert_eq!(
+            extract_token("https://idp.test/token?assertion=a%2Eb.c").unwrap(),
+            "a.b.c"
+        );
+        assert!(extract_token("https://app.test/cb?code=abc")
+            .unwrap_err()
+            .to_string()
+            .contains("id_token"));
+    }
+
+    #[test]
+    fn percent_decode_lossy_keeps_invalid_escapes() {
+        assert_eq!(percent_decode_lossy("a%2Eb%zz"), "a.b%zz");
+    }
+
     #[test]
     fn read_json_value_invalid_errors() {
         let err = read_json_value("{not-json}").expect_err("expected json error");
@@ -0,0 +1,127 @@
+use crate::error::{AppError, AppResult};
+use std::path::Path;
+
+/// Reads `NAME=value` pairs from a dotenv file, in file order. Supports `export ` prefixes,
+/// `#` comments, single-quoted (literal) and double-quoted (`\n`, `\"`, `\\` escapes) values.
+pub fn read_dotenv(path: &Path) -> AppResult<Vec<(String, String)>> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| AppError::invalid_key(format!("failed to read {}: {e}", path.display())))?;
+    parse_dotenv(&text)
+        .map_err(|e| AppError::invalid_key(format!("{}: {}", path.display(), e.message)))
+}
+
+fn parse_dotenv(text: &str) -> AppResult<Vec<(String, String)>> {
+    let mut vars = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (name, raw) = line.split_once('=').ok_or_else(|| {
+            AppError::invalid_key(format!("line {}: expected NAME=value", index + 1))
+        })?;
+        let name = name.trim();
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(AppError::invalid_key(format!(
+                "line {}: invalid variable name '{name}'",
+                index + 1
+            )));
+        }
+        let value = parse_value(raw.trim()).ok_or_else(|| {
+            AppError::invalid_key(format!("line {}: unterminated quote", index + 1))
+        })?;
+        vars.push((name.to_string(), value));
+    }
+    Ok(vars)
+}
+
+fn parse_value(raw: &str) -> Option<String> {
+    if let Some(rest) = raw.strip_prefix('\'') {
+        return rest.find('\'').map(|end| rest[..end].to_string());
+    }
+    if let Some(rest) = raw.strip_prefix('"') {
+        let mut value = String::new();
+        let mut chars = rest.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => return Some(value),
+                '\\' => match chars.next()? {
+                    'n' => value.push('\n'),
+                    other => value.push(other),
+                },
+                c => value.push(c),
+            }
+        }
+        return None;
+    }
+    // Unquoted: a ` #` starts a comment.
+    let value = raw.split(" #").next().unwrap_or_default();
+    Some(value.trim().to_string())
+}
+
+/// Shell-style match where `*` is any run of characters and `?` one character.
+pub fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    n = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_dotenv_quoting_and_comments() {
+        let vars = parse_dotenv(
+            "# service config\n\
+             export API_JWT_SECRET=abc123 # rotated monthly\n\
+             QUOTED=\"line\\nbreak \\\"q\\\"\"\n\
+             LITERAL='a # b'\n\
+             EMPTY=\n",
+        )
+        .unwrap();
+        assert_eq!(
+            vars,
+            [
+                ("API_JWT_SECRET".to_string(), "abc123".to_string()),
+                ("QUOTED".to_string(), "line\nbreak \"q\"".to_string()),
+                ("LITERAL".to_string(), "a # b".to_string()),
+                ("EMPTY".to_string(), String::new()),
+            ]
+        );
+        assert!(parse_dotenv("NOEQUALS").is_err());
+        assert!(parse_dotenv("X=\"open").is_err());
+    }
+
+    #[test]
+    fn wildcard_patterns() {
+        assert!(wildcard_match("*_JWT_SECRET", "BILLING_JWT_SECRET"));
+        assert!(!wildcard_match("*_JWT_SECRET", "BILLING_JWT_SECRET_OLD"));
+        assert!(wildcard_match("A?C*", "ABCDEF"));
+        assert!(wildcard_match("*", ""));
+        assert!(!wildcard_match("A*B", "ACBD"));
+    }
+}
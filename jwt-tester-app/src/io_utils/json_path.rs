@@ -0,0 +1,114 @@
+use crate::error::{AppError, AppResult};
+use serde_json::Value;
+
+#[derive(Debug, PartialEq)]
+enum Step {
+    Key(String),
+    Index(usize),
+}
+
+/// Parses the JSONPath subset `--token-path` accepts: `$` followed by `.name`, `['name']` or
+/// `[0]` steps. The leading `$.` may be left out (`access_token`, `data.tokens[0]`).
+fn parse(path: &str) -> AppResult<Vec<Step>> {
+    let invalid =
+        |why: &str| AppError::invalid_token(format!("invalid token path '{path}': {why}"));
+    let trimmed = path.trim();
+    let mut rest = match trimmed.strip_prefix('$') {
+        Some(after) => after.strip_prefix('.').unwrap_or(after),
+        None => trimmed,
+    };
+    let mut steps = Vec::new();
+    while !rest.is_empty() {
+        if let Some(inner) = rest.strip_prefix('[') {
+            let end = inner.find(']').ok_or_else(|| invalid("unclosed '['"))?;
+            let (body, after) = (inner[..end].trim(), &inner[end + 1..]);
+            let quoted = ['\'', '"']
+                .into_iter()
+                .find_map(|q| body.strip_prefix(q).and_then(|b| b.strip_suffix(q)));
+            steps.push(match quoted {
+                Some(name) => Step::Key(name.to_string()),
+                None => Step::Index(
+                    body.parse()
+                        .map_err(|_| invalid("expected an index or a quoted name in '[...]'"))?,
+                ),
+            });
+            rest = after.strip_prefix('.').unwrap_or(after);
+            continue;
+        }
+        let end = rest.find(['.', '[']).unwrap_or(rest.len());
+        if end == 0 {
+            return Err(invalid("empty name"));
+        }
+        steps.push(Step::Key(rest[..end].to_string()));
+        rest = &rest[end..];
+        rest = rest.strip_prefix('.').unwrap_or(rest);
+    }
+    if steps.is_empty() {
+        return Err(invalid("it selects the whole document"));
+    }
+    Ok(steps)
+}
+
+/// The string at `path` in the JSON document `text`.
+pub fn select_string(text: &str, path: &str) -> AppResult<String> {
+    let steps = parse(path)?;
+    let document: Value = serde_json::from_str(text).map_err(|e| {
+        AppError::invalid_token(format!("--token-path needs a JSON document as input: {e}"))
+    })?;
+    let mut current = &document;
+    for (depth, step) in steps.iter().enumerate() {
+        let next = match step {
+            Step::Key(name) => current.get(name),
+            Step::Index(index) => current.get(index),
+        };
+        current = next.ok_or_else(|| {
+            let at = match step {
+                Step::Key(name) => format!("'{name}'"),
+                Step::Index(index) => format!("[{index}]"),
+            };
+            AppError::invalid_token(format!(
+                "token path '{path}' matches nothing: {at} not found (step {})",
+                depth + 1
+            ))
+        })?;
+    }
+    current.as_str().map(str::to_string).ok_or_else(|| {
+        AppError::invalid_token(format!("token path '{path}' does not point to a string"))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_dotted_bracketed_and_bare_paths() {
+        assert_eq!(
+            parse("$.data['id token'][1].jwt").unwrap(),
+            [
+                Step::Key("data".to_string()),
+                Step::Key("id token".to_string()),
+                Step::Index(1),
+                Step::Key("jwt".to_string()),
+            ]
+        );
+        assert_eq!(
+            parse("access_token").unwrap(),
+            [Step::Key("access_token".to_string())]
+        );
+        assert!(parse("$").is_err());
+        assert!(parse("$.a[").is_err());
+    }
+
+    #[test]
+    fn selects_strings_only() {
+        let body = r#"{"access_token":"a.b.c","tokens":[{"jwt":"x.y.z"}],"expires_in":3600}"#;
+        assert_eq!(select_string(body, "$.access_token").unwrap(), "a.b.c");
+        assert_eq!(select_string(body, "$.tokens[0].jwt").unwrap(), "x.y.z");
+        let err = select_string(body, "$.expires_in").unwrap_err();
+        assert!(err.message.contains("does not point to a string"));
+        let err = select_string(body, "$.id_token").unwrap_err();
+        assert!(err.message.contains("'id_token' not found"));
+        assert!(select_string("a.b.c", "$.access_token").is_err());
+    }
+}
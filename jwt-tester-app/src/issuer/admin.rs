@@ -0,0 +1,307 @@
+use super::{IssuerState, Signer};
+use crate::cli::OAuthErrorCode;
+use crate::error::{AppError, AppResult};
+use crate::keygen::{
+    generate_key_pair, public_jwk, EcCurve, EdCurve, KeyGenSpec, KeyUse, DEFAULT_HMAC_BYTES,
+    DEFAULT_RSA_BITS,
+};
+use crate::vault::KeyEntryInput;
+use axum::extract::{Request, State};
+use axum::http::header::AUTHORIZATION;
+use axum::http::{HeaderMap, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use clap::ValueEnum;
+use jsonwebtoken::Algorithm;
+use serde_json::{json, Map, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use subtle::ConstantTimeEq;
+use tracing::info;
+use uuid::Uuid;
+
+/// Test controls for how the issuer misbehaves. Set from flags at startup and changed at runtime
+/// through `POST /admin/behavior`.
+#[derive(Debug, Clone, Default)]
+pub(super) struct Behavior {
+    /// Seconds added to the real clock for `iat`, `exp` and the template `now`.
+    pub(super) clock_offset: i64,
+    error: Option<OAuthErrorCode>,
+    /// Token requests left that get `error`; `None` means every request.
+    error_times: Option<u64>,
+    pub(super) delay: Duration,
+}
+
+impl Behavior {
+    pub(super) fn new(
+        clock_offset: i64,
+        error: Option<OAuthErrorCode>,
+        delay: Option<Duration>,
+    ) -> Self {
+        Behavior {
+            clock_offset,
+            error,
+            error_times: None,
+            delay: delay.unwrap_or_default(),
+        }
+    }
+
+    /// The error the next token request must get, counting down a limited run of them.
+    pub(super) fn take_error(&mut self) -> Option<OAuthErrorCode> {
+        let error = self.error?;
+        match self.error_times {
+            Some(0) => {
+                self.error = None;
+                self.error_times = None;
+                return None;
+            }
+            Some(left) => self.error_times = Some(left - 1),
+            None => {}
+        }
+        Some(error)
+    }
+
+    /// Applies a `POST /admin/behavior` body. Present fields replace the setting; `null` clears it.
+    fn update(&mut self, changes: &Map<String, Value>) -> AppResult<()> {
+        let text = |name: &str, value: &Value| -> AppResult<Option<String>> {
+            match value {
+                Value::Null => Ok(None),
+                Value::String(raw) => Ok(Some(raw.clone())),
+                _ => Err(AppError::invalid_claims(format!(
+                    "{name} must be a string or null"
+                ))),
+            }
+        };
+        let mut next = self.clone();
+        for (name, value) in changes {
+            match name.as_str() {
+                "clock_offset" => {
+                    next.clock_offset = match text(name, value)? {
+                        Some(raw) => parse_clock_offset(&raw)?,
+                        None => 0,
+                    }
+                }
+                "error" => {
+                    next.error = match text(name, value)? {
+                        Some(raw) => Some(OAuthErrorCode::from_str(&raw, false).map_err(|_| {
+                            AppError::invalid_claims(format!("unknown OAuth error '{raw}'"))
+                        })?),
+                        None => None,
+                    };
+                    next.error_times = None;
+                }
+                "delay" => {
+                    next.delay = match text(name, value)? {
+                        Some(raw) => humantime::parse_duration(raw.trim()).map_err(|e| {
+                            AppError::invalid_claims(format!("invalid delay '{raw}': {e}"))
+                        })?,
+                        None => Duration::ZERO,
+                    }
+                }
+                "times" => {}
+                other => {
+                    return Err(AppError::invalid_claims(format!(
+                        "unknown behavior setting '{other}' (expected clock_offset, error, times or delay)"
+                    )))
+                }
+            }
+        }
+        if let Some(times) = changes.get("times") {
+            next.error_times = match times {
+                Value::Null => None,
+                _ => Some(times.as_u64().ok_or_else(|| {
+                    AppError::invalid_claims("times must be a non-negative integer or null")
+                })?),
+            };
+        }
+        *self = next;
+        Ok(())
+    }
+
+    pub(super) fn to_json(&self) -> Value {
+        json!({
+            "clock_offset_secs": self.clock_offset,
+            "error": self.error.map(OAuthErrorCode::as_str),
+            "error_times": self.error_times,
+            "delay_ms": self.delay.as_millis() as u64,
+        })
+    }
+}
+
+/// `-2h`, `+30m` or `90s`.
+pub(super) fn parse_clock_offset(raw: &str) -> AppResult<i64> {
+    let trimmed = raw.trim();
+    let (sign, duration) = match trimmed.strip_prefix('-') {
+        Some(rest) => (-1, rest),
+        None => (1, trimmed.strip_prefix('+').unwrap_or(trimmed)),
+    };
+    let duration = humantime::parse_duration(duration.trim())
+        .map_err(|e| AppError::invalid_claims(format!("invalid clock offset '{raw}': {e}")))?;
+    Ok(sign * duration.as_secs() as i64)
+}
+
+/// Holds back every public response by the configured delay.
+pub(super) async fn slow_down(
+    State(state): State<Arc<IssuerState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let delay = state.behavior().delay;
+    if !delay.is_zero() {
+        tokio::time::sleep(delay).await;
+    }
+    next.run(request).await
+}
+
+/// Admin endpoints need `Authorization: Bearer <admin token>`.
+pub(super) async fn require_admin(
+    State(state): State<Arc<IssuerState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !is_admin(request.headers(), &state.admin_token) {
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({ "error": "admin token missing or invalid" })),
+        )
+            .into_response();
+    }
+    next.run(request).await
+}
+
+fn is_admin(headers: &HeaderMap, expected: &str) -> bool {
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|token| bool::from(token.trim().as_bytes().ct_eq(expected.as_bytes())))
+}
+
+pub(super) async fn get_behavior(State(state): State<Arc<IssuerState>>) -> Json<Value> {
+    Json(state.behavior().to_json())
+}
+
+pub(super) async fn set_behavior(
+    State(state): State<Arc<IssuerState>>,
+    Json(changes): Json<Map<String, Value>>,
+) -> Result<Json<Value>, AppError> {
+    let mut behavior = state.behavior_mut();
+    behavior.update(&changes)?;
+    info!("issuer behavior changed: {}", behavior.to_json());
+    Ok(Json(behavior.to_json()))
+}
+
+/// Generates a key of the same kind (and size or curve) into the project and signs with it from
+/// now on. The previous key stays in the project, so it is still published in the JWKS.
+pub(super) async fn rotate(State(state): State<Arc<IssuerState>>) -> Result<Json<Value>, AppError> {
+    let previous = state.signer();
+    let material = state
+        .vault
+        .get_key_material(&previous.key.id)
+        .map_err(|e| AppError::internal(e.to_string()))?;
+    let generated = generate_key_pair(rotation_spec(&previous.key.kind, &material)?)?;
+    let kid = Uuid::new_v4().simple().to_string()[..12].to_string();
+    let key = state
+        .vault
+        .add_key(KeyEntryInput {
+            project_id: state.project.id.clone(),
+            name: format!("issuer-{kid}"),
+            kind: previous.key.kind.clone(),
+            secret: generated.material,
+            kid: Some(kid.clone()),
+            description: Some(format!(
+                "rotated in by issuer serve, replacing {}",
+                previous.kid()
+            )),
+            tags: Vec::new(),
+        })
+        .map_err(|e| AppError::internal(e.to_string()))?;
+    let signer = Signer {
+        key,
+        alg: previous.alg,
+    };
+    let data = json!({
+        "key_id": signer.key.id,
+        "kid": signer.kid(),
+        "alg": format!("{:?}", Algorithm::from(signer.alg)),
+        "previous_kid": previous.kid(),
+    });
+    info!("issuer rotated signing key to {kid}");
+    *state.signer_mut() = signer;
+    Ok(Json(data))
+}
+
+fn rotation_spec(kind: &str, material: &str) -> AppResult<KeyGenSpec> {
+    let jwk = public_jwk(material);
+    let spec = match kind.to_lowercase().as_str() {
+        "hmac" => KeyGenSpec::Hmac {
+            bytes: DEFAULT_HMAC_BYTES,
+        },
+        "rsa" => {
+            let bits = jwk
+                .as_ref()
+                .and_then(|jwk| jwk["n"].as_str())
+                .and_then(|n| URL_SAFE_NO_PAD.decode(n).ok())
+                .map(|n| n.len() * 8)
+                .filter(|bits| [2048, 3072, 4096].contains(bits))
+                .unwrap_or(DEFAULT_RSA_BITS);
+            KeyGenSpec::Rsa {
+                bits,
+                key_use: KeyUse::Sig,
+            }
+        }
+        "ec" => KeyGenSpec::Ec {
+            curve: match jwk {
+                Some(jwk) if jwk["crv"] == "P-384" => EcCurve::P384,
+                _ => EcCurve::P256,
+            },
+        },
+        "eddsa" => KeyGenSpec::EdDsa {
+            curve: EdCurve::Ed25519,
+        },
+        other => {
+            return Err(AppError::invalid_key(format!(
+                "cannot rotate a key of kind '{other}'"
+            )))
+        }
+    };
+    Ok(spec)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_signed_clock_offsets() {
+        assert_eq!(parse_clock_offset("-2h").unwrap(), -7200);
+        assert_eq!(parse_clock_offset("+30m").unwrap(), 1800);
+        assert_eq!(parse_clock_offset("90s").unwrap(), 90);
+        assert!(parse_clock_offset("soon").is_err());
+    }
+
+    #[test]
+    fn limited_errors_run_out_and_updates_are_atomic() {
+        let mut behavior = Behavior::default();
+        let changes = json!({ "error": "invalid_client", "times": 2, "delay": "1s" });
+        behavior.update(changes.as_object().unwrap()).unwrap();
+        assert_eq!(behavior.delay, Duration::from_secs(1));
+        assert_eq!(behavior.take_error(), Some(OAuthErrorCode::InvalidClient));
+        assert_eq!(behavior.take_error(), Some(OAuthErrorCode::InvalidClient));
+        assert_eq!(behavior.take_error(), None);
+        assert_eq!(behavior.to_json()["error"], Value::Null);
+
+        let bad = json!({ "clock_offset": "-1h", "error": "nope" });
+        assert!(behavior.update(bad.as_object().unwrap()).is_err());
+        assert_eq!(behavior.clock_offset, 0);
+        let clear = json!({ "delay": null, "clock_offset": "-1h" });
+        behavior.update(clear.as_object().unwrap()).unwrap();
+        assert_eq!(
+            (behavior.clock_offset, behavior.delay),
+            (-3600, Duration::ZERO)
+        );
+    }
+}
@@ -0,0 +1,226 @@
+use crate::error::{AppError, AppResult};
+use crate::io_utils::read_input;
+use minijinja::Environment;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Entry used for any client_id without its own registration.
+const WILDCARD: &str = "*";
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawClient {
+    #[serde(default)]
+    secret: Option<String>,
+    #[serde(default)]
+    claims: Map<String, Value>,
+    #[serde(default)]
+    audience: Option<String>,
+    #[serde(default)]
+    expires_in: Option<String>,
+}
+
+/// One registered client. String claim values are minijinja templates over the request.
+#[derive(Debug, Clone)]
+pub(super) struct Client {
+    pub(super) secret: Option<String>,
+    pub(super) claims: Map<String, Value>,
+    pub(super) audience: Option<String>,
+    pub(super) expires_in: Duration,
+}
+
+/// `None` registrations accept every client_id with no secret and no extra claims.
+#[derive(Debug, Clone)]
+pub(super) struct Clients {
+    registered: Option<BTreeMap<String, Client>>,
+    default_expires_in: Duration,
+}
+
+impl Clients {
+    /// Reads `--clients` and checks every template and lifetime up front.
+    pub(super) fn load(spec: Option<&str>, default_expires_in: Duration) -> AppResult<Self> {
+        let Some(spec) = spec else {
+            return Ok(Clients {
+                registered: None,
+                default_expires_in,
+            });
+        };
+        let raw: BTreeMap<String, RawClient> = serde_json::from_str(&read_input(spec)?)
+            .map_err(|e| AppError::invalid_claims(format!("invalid --clients: {e}")))?;
+        let mut registered = BTreeMap::new();
+        for (client_id, client) in raw {
+            let expires_in = match client.expires_in.as_deref() {
+                Some(raw) => parse_lifetime(raw).map_err(|e| {
+                    AppError::invalid_claims(format!("client {client_id}: {}", e.message))
+                })?,
+                None => default_expires_in,
+            };
+            for (name, template) in &client.claims {
+                check_templates(template).map_err(|e| {
+                    AppError::invalid_claims(format!(
+                        "client {client_id}: claim {name}: invalid template: {e}"
+                    ))
+                })?;
+            }
+            registered.insert(
+                client_id,
+                Client {
+                    secret: client.secret,
+                    claims: client.claims,
+                    audience: client.audience,
+                    expires_in,
+                },
+            );
+        }
+        Ok(Clients {
+            registered: Some(registered),
+            default_expires_in,
+        })
+    }
+
+    /// The registration for `client_id`, falling back to the `*` entry.
+    pub(super) fn find(&self, client_id: &str) -> Option<Client> {
+        let Some(registered) = &self.registered else {
+            return Some(Client {
+                secret: None,
+                claims: Map::new(),
+                audience: None,
+                expires_in: self.default_expires_in,
+            });
+        };
+        registered
+            .get(client_id)
+            .or_else(|| registered.get(WILDCARD))
+            .cloned()
+    }
+
+    pub(super) fn count(&self) -> Option<usize> {
+        self.registered.as_ref().map(BTreeMap::len)
+    }
+}
+
+pub(super) fn parse_lifetime(raw: &str) -> AppResult<Duration> {
+    let lifetime = humantime::parse_duration(raw.trim())
+        .map_err(|e| AppError::invalid_claims(format!("invalid expires_in '{raw}': {e}")))?;
+    if lifetime.as_secs() == 0 {
+        return Err(AppError::invalid_claims(
+            "expires_in must be at least one second",
+        ));
+    }
+    Ok(lifetime)
+}
+
+fn check_templates(value: &Value) -> Result<(), minijinja::Error> {
+    match value {
+        Value::String(source) => Environment::new().template_from_str(source).map(|_| ()),
+        Value::Array(items) => items.iter().try_for_each(check_templates),
+        Value::Object(map) => map.values().try_for_each(check_templates),
+        _ => Ok(()),
+    }
+}
+
+/// Renders the client's claim templates against `context`. Strings (also inside arrays and
+/// objects) are rendered; top-level claims that render to an empty string are left out, so
+/// `"scope": "{{ scope }}"` disappears when no scope was requested.
+pub(super) fn render_claims(client: &Client, context: &Value) -> AppResult<Map<String, Value>> {
+    let env = Environment::new();
+    let mut out = Map::new();
+    for (name, template) in &client.claims {
+        let value = render_value(&env, template, context)
+            .map_err(|e| AppError::invalid_claims(format!("claim {name}: {e}")))?;
+        if value.as_str() != Some("") {
+            out.insert(name.clone(), value);
+        }
+    }
+    Ok(out)
+}
+
+fn render_value(
+    env: &Environment,
+    value: &Value,
+    context: &Value,
+) -> Result<Value, minijinja::Error> {
+    Ok(match value {
+        Value::String(source) => Value::String(env.render_str(source, context)?),
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| render_value(env, item, context))
+                .collect::<Result<_, _>>()?,
+        ),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, item)| Ok((key.clone(), render_value(env, item, context)?)))
+                .collect::<Result<_, minijinja::Error>>()?,
+        ),
+        other => other.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn loads_clients_with_wildcard_and_lifetimes() {
+        let clients = Clients::load(
+            Some(r#"{"svc": {"secret": "s", "expires_in": "5m"}, "*": {}}"#),
+            Duration::from_secs(3600),
+        )
+        .unwrap();
+        assert_eq!(clients.count(), Some(2));
+        let svc = clients.find("svc").unwrap();
+        assert_eq!(svc.secret.as_deref(), Some("s"));
+        assert_eq!(svc.expires_in, Duration::from_secs(300));
+        assert_eq!(
+            clients.find("other").unwrap().expires_in,
+            Duration::from_secs(3600)
+        );
+
+        let strict = Clients::load(Some(r#"{"svc": {}}"#), Duration::from_secs(60)).unwrap();
+        assert!(strict.find("other").is_none());
+        assert!(Clients::load(None, Duration::from_secs(60))
+            .unwrap()
+            .find("anyone")
+            .is_some());
+        assert!(Clients::load(
+            Some(r#"{"svc": {"claims": {"x": "{{ oops"}}}"#),
+            Duration::ZERO
+        )
+        .is_err());
+        assert!(Clients::load(Some(r#"{"svc": {"scopes": []}}"#), Duration::ZERO).is_err());
+    }
+
+    #[test]
+    fn renders_nested_templates_and_drops_empty_claims() {
+        let clients = Clients::load(
+            Some(
+                r#"{"svc": {"claims": {
+                    "sub": "svc:{{ client_id }}",
+                    "scope": "{{ scope }}",
+                    "roles": ["reader", "{{ params.tenant }}-admin"],
+                    "ext": {"level": 3}
+                }}}"#,
+            ),
+            Duration::from_secs(60),
+        )
+        .unwrap();
+        let client = clients.find("svc").unwrap();
+        let claims = render_claims(
+            &client,
+            &json!({ "client_id": "svc", "scope": "", "params": { "tenant": "acme" } }),
+        )
+        .unwrap();
+        assert_eq!(
+            Value::Object(claims),
+            json!({
+                "sub": "svc:svc",
+                "roles": ["reader", "acme-admin"],
+                "ext": { "level": 3 },
+            })
+        );
+    }
+}
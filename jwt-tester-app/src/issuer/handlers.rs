@@ -0,0 +1,273 @@
+use super::clients::render_claims;
+use super::{default_alg, key_kid, IssuerState};
+use crate::cli::OAuthErrorCode;
+use crate::error::{AppError, AppResult};
+use crate::key_resolver::resolve_encoding_key_with_vault;
+use crate::keygen::public_jwk;
+use axum::extract::State;
+use axum::http::header::{AUTHORIZATION, CACHE_CONTROL, PRAGMA, WWW_AUTHENTICATE};
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::{Form, Json};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use jsonwebtoken::Algorithm;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use subtle::ConstantTimeEq;
+use tracing::info;
+use uuid::Uuid;
+
+/// An RFC 6749 §5.2 error response.
+struct OAuthError {
+    status: StatusCode,
+    error: &'static str,
+    description: String,
+    /// Set when the client authenticated with HTTP Basic, which requires a challenge on 401.
+    basic_challenge: bool,
+}
+
+impl OAuthError {
+    fn new(status: StatusCode, error: &'static str, description: impl Into<String>) -> Self {
+        OAuthError {
+            status,
+            error,
+            description: description.into(),
+            basic_challenge: false,
+        }
+    }
+
+    fn invalid_request(description: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, "invalid_request", description)
+    }
+
+    fn invalid_client(description: impl Into<String>, basic: bool) -> Self {
+        OAuthError {
+            basic_challenge: basic,
+            ..Self::new(StatusCode::UNAUTHORIZED, "invalid_client", description)
+        }
+    }
+
+    /// A failure forced through `--force-error` or `POST /admin/behavior`.
+    fn forced(code: OAuthErrorCode) -> Self {
+        let status = match code {
+            OAuthErrorCode::InvalidClient => StatusCode::UNAUTHORIZED,
+            OAuthErrorCode::ServerError => StatusCode::INTERNAL_SERVER_ERROR,
+            OAuthErrorCode::TemporarilyUnavailable => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::BAD_REQUEST,
+        };
+        Self::new(
+            status,
+            code.as_str(),
+            "forced by the issuer's test controls",
+        )
+    }
+
+    fn server_error(err: AppError) -> Self {
+        Self::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "server_error",
+            err.to_string(),
+        )
+    }
+}
+
+impl IntoResponse for OAuthError {
+    fn into_response(self) -> Response {
+        let mut response = (
+            self.status,
+            Json(json!({ "error": self.error, "error_description": self.description })),
+        )
+            .into_response();
+        if self.basic_challenge {
+            response.headers_mut().insert(
+                WWW_AUTHENTICATE,
+                HeaderValue::from_static("Basic realm=\"jwt-tester issuer\""),
+            );
+        }
+        response
+    }
+}
+
+pub(super) async fn discovery(State(state): State<Arc<IssuerState>>) -> Json<Value> {
+    Json(json!({
+        "issuer": state.issuer,
+        "jwks_uri": format!("{}/jwks.json", state.base_url),
+        "token_endpoint": format!("{}/token", state.base_url),
+        "grant_types_supported": ["client_credentials"],
+        "token_endpoint_auth_methods_supported": ["client_secret_basic", "client_secret_post"],
+        "response_types_supported": ["token"],
+        "subject_types_supported": ["public"],
+        "id_token_signing_alg_values_supported": [format!("{:?}", Algorithm::from(state.signer().alg))],
+    }))
+}
+
+/// Public halves of the project's asymmetric keys. HMAC secrets are never published.
+pub(super) async fn jwks(State(state): State<Arc<IssuerState>>) -> Result<Json<Value>, AppError> {
+    let keys = state
+        .vault
+        .list_keys(Some(&state.project.id))
+        .map_err(|e| AppError::internal(e.to_string()))?;
+    let signer = state.signer();
+    let mut published = Vec::new();
+    for key in keys
+        .iter()
+        .filter(|key| !key.kind.eq_ignore_ascii_case("hmac"))
+    {
+        let material = state
+            .vault
+            .get_key_material(&key.id)
+            .map_err(|e| AppError::internal(e.to_string()))?;
+        let Some(Value::Object(mut jwk)) = public_jwk(&material) else {
+            continue;
+        };
+        let alg = if key.id == signer.key.id {
+            signer.alg
+        } else {
+            match default_alg(&key.kind, &material) {
+                Ok(alg) => alg,
+                Err(_) => continue,
+            }
+        };
+        jwk.insert("kid".to_string(), json!(key_kid(key)));
+        jwk.insert(
+            "alg".to_string(),
+            json!(format!("{:?}", Algorithm::from(alg))),
+        );
+        published.push(Value::Object(jwk));
+    }
+    Ok(Json(json!({ "keys": published })))
+}
+
+pub(super) async fn token(
+    State(state): State<Arc<IssuerState>>,
+    headers: HeaderMap,
+    Form(form): Form<HashMap<String, String>>,
+) -> Response {
+    match issue(&state, &headers, &form) {
+        Ok(body) => {
+            let mut response = Json(body).into_response();
+            let headers = response.headers_mut();
+            headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-store"));
+            headers.insert(PRAGMA, HeaderValue::from_static("no-cache"));
+            response
+        }
+        Err(err) => err.into_response(),
+    }
+}
+
+fn issue(
+    state: &IssuerState,
+    headers: &HeaderMap,
+    form: &HashMap<String, String>,
+) -> Result<Value, OAuthError> {
+    let forced = state.behavior_mut().take_error();
+    if let Some(code) = forced {
+        return Err(OAuthError::forced(code));
+    }
+    let grant_type = form
+        .get("grant_type")
+        .ok_or_else(|| OAuthError::invalid_request("missing grant_type"))?;
+    if grant_type != "client_credentials" {
+        return Err(OAuthError::new(
+            StatusCode::BAD_REQUEST,
+            "unsupported_grant_type",
+            format!("grant_type '{grant_type}' is not supported; use client_credentials"),
+        ));
+    }
+
+    let (client_id, secret, basic) = client_credentials(headers, form)?;
+    let client = state.clients.find(&client_id).ok_or_else(|| {
+        OAuthError::invalid_client(format!("unknown client '{client_id}'"), basic)
+    })?;
+    if let Some(expected) = &client.secret {
+        let provided = secret.unwrap_or_default();
+        if !bool::from(expected.as_bytes().ct_eq(provided.as_bytes())) {
+            return Err(OAuthError::invalid_client(
+                "client authentication failed",
+                basic,
+            ));
+        }
+    }
+
+    let scope = form.get("scope").cloned().unwrap_or_default();
+    let audience = form.get("audience").cloned().or(client.audience.clone());
+    let now = state.now();
+    let expires_in = client.expires_in.as_secs();
+
+    let mut claims = Map::new();
+    claims.insert("iss".to_string(), json!(state.issuer));
+    claims.insert("sub".to_string(), json!(client_id));
+    if let Some(audience) = &audience {
+        claims.insert("aud".to_string(), json!(audience));
+    }
+    claims.insert("client_id".to_string(), json!(client_id));
+    if !scope.is_empty() {
+        claims.insert("scope".to_string(), json!(scope));
+    }
+    claims.insert("iat".to_string(), json!(now));
+    claims.insert("exp".to_string(), json!(now + expires_in as i64));
+    claims.insert("jti".to_string(), json!(Uuid::new_v4().to_string()));
+    let context = json!({
+        "client_id": client_id,
+        "scope": scope,
+        "audience": audience,
+        "grant_type": grant_type,
+        "issuer": state.issuer,
+        "now": now,
+        "params": form,
+    });
+    claims.extend(render_claims(&client, &context).map_err(OAuthError::server_error)?);
+
+    let access_token = sign(state, Value::Object(claims)).map_err(OAuthError::server_error)?;
+    info!("issued token for client {client_id}");
+    let mut body = json!({
+        "access_token": access_token,
+        "token_type": "Bearer",
+        "expires_in": expires_in,
+    });
+    if !scope.is_empty() {
+        body["scope"] = json!(scope);
+    }
+    Ok(body)
+}
+
+fn sign(state: &IssuerState, claims: Value) -> AppResult<String> {
+    let request = state.signer().request(&state.project).claims(claims);
+    let (key, _) = resolve_encoding_key_with_vault(&state.vault, &request)?;
+    request.encode(&key)
+}
+
+/// `client_secret_basic` (Authorization header) or `client_secret_post` (form fields); a request
+/// must not use both. Returns the client id, the secret if any, and whether Basic was used.
+fn client_credentials(
+    headers: &HeaderMap,
+    form: &HashMap<String, String>,
+) -> Result<(String, Option<String>, bool), OAuthError> {
+    let basic = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Basic "));
+    let Some(encoded) = basic else {
+        let client_id = form
+            .get("client_id")
+            .filter(|id| !id.is_empty())
+            .ok_or_else(|| OAuthError::invalid_client("missing client_id", false))?;
+        return Ok((client_id.clone(), form.get("client_secret").cloned(), false));
+    };
+    if form.contains_key("client_secret") {
+        return Err(OAuthError::invalid_request(
+            "use either HTTP Basic or client_secret, not both",
+        ));
+    }
+    let decoded = STANDARD
+        .decode(encoded.trim())
+        .ok()
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+        .ok_or_else(|| OAuthError::invalid_client("malformed Basic credentials", true))?;
+    let (client_id, secret) = decoded
+        .split_once(':')
+        .ok_or_else(|| OAuthError::invalid_client("malformed Basic credentials", true))?;
+    Ok((client_id.to_string(), Some(secret.to_string()), true))
+}
@@ -0,0 +1,257 @@
+//! `issuer serve`: a local fake identity provider for integration tests. It publishes discovery
+//! metadata and a JWKS for a vault project and mints client_credentials tokens signed with one of
+//! the project's keys.
+
+mod admin;
+mod clients;
+mod handlers;
+
+use crate::cli::{IssuerServeArgs, JwtAlg};
+use crate::encode_request::EncodeRequest;
+use crate::error::{AppError, AppResult};
+use crate::io_utils::read_input;
+use crate::key_resolver::{resolve_encoding_key_with_vault, resolve_project_key_single};
+use crate::keygen::public_jwk;
+use crate::output::{emit_ok, CommandOutput, OutputConfig};
+use crate::ui::validate_bind_target;
+use crate::vault::{KeyEntry, KeyUsage, ProjectEntry, Vault, VaultConfig};
+use admin::Behavior;
+use axum::middleware::from_fn_with_state;
+use axum::routing::{get, post};
+use axum::Router;
+use clients::Clients;
+use jsonwebtoken::Algorithm;
+use serde_json::json;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use tokio::net::TcpListener;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+pub(super) struct IssuerState {
+    vault: Vault,
+    project: ProjectEntry,
+    issuer: String,
+    base_url: String,
+    clients: Clients,
+    admin_token: String,
+    signer: RwLock<Signer>,
+    behavior: RwLock<Behavior>,
+}
+
+/// The key tokens are signed with; `POST /admin/rotate` swaps it.
+#[derive(Debug, Clone)]
+pub(super) struct Signer {
+    key: KeyEntry,
+    alg: JwtAlg,
+}
+
+impl Signer {
+    fn kid(&self) -> &str {
+        key_kid(&self.key)
+    }
+
+    fn request(&self, project: &ProjectEntry) -> EncodeRequest {
+        let request = EncodeRequest::new(self.alg)
+            .project(project.name.clone())
+            .key_id(self.key.id.clone())
+            .kid(self.kid());
+        match &project.wasm_module {
+            Some(module) => request.wasm_module(module.clone()),
+            None => request,
+        }
+    }
+}
+
+/// The `kid` published in the JWKS and stamped on tokens: the key's kid, else its vault id.
+fn key_kid(key: &KeyEntry) -> &str {
+    key.kid.as_deref().unwrap_or(&key.id)
+}
+
+// A handler that panics while holding a lock leaves plain data behind, so poisoning is ignored.
+impl IssuerState {
+    fn signer(&self) -> Signer {
+        self.signer
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    fn signer_mut(&self) -> RwLockWriteGuard<'_, Signer> {
+        self.signer
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn behavior(&self) -> RwLockReadGuard<'_, Behavior> {
+        self.behavior
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn behavior_mut(&self) -> RwLockWriteGuard<'_, Behavior> {
+        self.behavior
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// The issuer's clock: real time plus the configured offset.
+    fn now(&self) -> i64 {
+        crate::claims::now_epoch() + self.behavior().clock_offset
+    }
+}
+
+pub async fn serve(
+    no_persist: bool,
+    data_dir: Option<PathBuf>,
+    args: IssuerServeArgs,
+    output: OutputConfig,
+) -> AppResult<()> {
+    validate_bind_target(args.host, args.allow_remote, "issuer")?;
+    let default_expires_in = clients::parse_lifetime(&args.expires_in)?;
+    let clients = Clients::load(args.clients.as_deref(), default_expires_in)?;
+    let clock_offset = match args.clock_offset.as_deref() {
+        Some(raw) => admin::parse_clock_offset(raw)?,
+        None => 0,
+    };
+    let (admin_token, generated_admin_token) = match args.admin_token.as_deref() {
+        Some(spec) => (read_input(spec)?, false),
+        None => (Uuid::new_v4().simple().to_string(), true),
+    };
+
+    let vault = Vault::open(VaultConfig {
+        no_persist,
+        data_dir,
+    })
+    .map_err(|e| AppError::invalid_key(e.to_string()))?;
+    let (project, key) = resolve_project_key_single(
+        &vault,
+        &args.project,
+        &args.key_id,
+        &args.key_name,
+        &None,
+        KeyUsage::Sign,
+    )?;
+    let alg = match args.alg {
+        Some(alg) => alg,
+        None => {
+            let material = vault
+                .get_key_material(&key.id)
+                .map_err(|e| AppError::invalid_key(e.to_string()))?;
+            default_alg(&key.kind, &material)?
+        }
+    };
+
+    let listener = TcpListener::bind(SocketAddr::new(args.host, args.port))
+        .await
+        .map_err(|e| AppError::internal(format!("failed to bind issuer: {e}")))?;
+    let local_addr = listener
+        .local_addr()
+        .map_err(|e| AppError::internal(format!("failed to get issuer address: {e}")))?;
+    let base_url = format!("http://{}:{}", local_addr.ip(), local_addr.port());
+    let issuer = args.issuer.unwrap_or_else(|| base_url.clone());
+
+    let signer = Signer { key, alg };
+    // Fail at startup, not on the first request, when the key cannot sign with `alg`.
+    resolve_encoding_key_with_vault(&vault, &signer.request(&project))?;
+
+    let state = Arc::new(IssuerState {
+        vault,
+        project,
+        issuer,
+        base_url,
+        clients,
+        admin_token,
+        signer: RwLock::new(signer.clone()),
+        behavior: RwLock::new(Behavior::new(clock_offset, args.force_error, args.delay)),
+    });
+
+    let alg_name = format!("{:?}", Algorithm::from(alg));
+    let kid = signer.kid();
+    let token_endpoint = format!("{}/token", state.base_url);
+    let jwks_uri = format!("{}/jwks.json", state.base_url);
+    info!("issuer started at {}", state.base_url);
+    let text = if output.quiet {
+        String::new()
+    } else {
+        let mut text = format!(
+            "{}\ntoken endpoint: {token_endpoint}\njwks: {jwks_uri}\nsigning with {alg_name} (kid {kid})",
+            state.base_url
+        );
+        if generated_admin_token {
+            text.push_str(&format!("\nadmin token: {}", state.admin_token));
+        }
+        text
+    };
+    let data = json!({
+        "url": state.base_url,
+        "issuer": state.issuer,
+        "discovery": format!("{}/.well-known/openid-configuration", state.base_url),
+        "jwks_uri": jwks_uri,
+        "token_endpoint": token_endpoint,
+        "project": state.project.name,
+        "key_id": signer.key.id,
+        "kid": kid,
+        "alg": alg_name,
+        "clients": state.clients.count(),
+        "behavior": state.behavior().to_json(),
+        "admin": {
+            "behavior": format!("{}/admin/behavior", state.base_url),
+            "rotate": format!("{}/admin/rotate", state.base_url),
+            "token": generated_admin_token.then_some(&state.admin_token),
+        },
+    });
+    emit_ok(output, CommandOutput::new(data, text))?;
+
+    let admin = Router::new()
+        .route(
+            "/admin/behavior",
+            get(admin::get_behavior).post(admin::set_behavior),
+        )
+        .route("/admin/rotate", post(admin::rotate))
+        .layer(from_fn_with_state(state.clone(), admin::require_admin));
+    let app = Router::new()
+        .route(
+            "/.well-known/openid-configuration",
+            get(handlers::discovery),
+        )
+        .route(
+            "/.well-known/oauth-authorization-server",
+            get(handlers::discovery),
+        )
+        .route("/jwks.json", get(handlers::jwks))
+        .route("/token", post(handlers::token))
+        .layer(from_fn_with_state(state.clone(), admin::slow_down))
+        .merge(admin)
+        .with_state(state);
+
+    let shutdown = async {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            warn!("failed to install ctrl+c handler: {err}");
+        } else {
+            info!("issuer shutdown requested (ctrl+c)");
+        }
+    };
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown)
+        .await
+        .map_err(|e| AppError::internal(format!("issuer server failed: {e}")))?;
+    Ok(())
+}
+
+/// The algorithm a key kind signs with when `--alg` is not given. EC keys pick ES384 for P-384.
+fn default_alg(kind: &str, material: &str) -> AppResult<JwtAlg> {
+    match kind.to_lowercase().as_str() {
+        "hmac" => Ok(JwtAlg::HS256),
+        "rsa" => Ok(JwtAlg::RS256),
+        "ec" => match public_jwk(material) {
+            Some(jwk) if jwk["crv"] == "P-384" => Ok(JwtAlg::ES384),
+            _ => Ok(JwtAlg::ES256),
+        },
+        "eddsa" => Ok(JwtAlg::EdDSA),
+        other => Err(AppError::invalid_key(format!(
+            "key kind '{other}' has no default algorithm; pass --alg"
+        ))),
+    }
+}
@@ -1,29 +1,75 @@
-use crate::error::{AppError, AppResult};
-use jsonwebtoken::jwk::{Jwk, JwkSet};
-use jsonwebtoken::DecodingKey;
+use crate::error::{AppError, AppResult, ErrorKind};
+use crate::http;
+use crate::io_utils::read_input;
+use crate::jwt_ops::VerifyKey;
+use jsonwebtoken::jwk::{Jwk, JwkSet, KeyOperations, PublicKeyUse};
+use jsonwebtoken::Algorithm;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+use tracing::warn;
 
+const NEGATIVE_CACHE_FILE: &str = "jwks-negative-cache.json";
+
+/// Picks the key that verifies a token signed with `alg`. Keys marked `"use": "enc"`, keys whose
+/// `key_ops` leave out `verify`, and keys declaring a different `alg` are never chosen; among
+/// the rest, keys that declare the token's `alg` win over keys that declare none.
 pub fn select_jwk(
     jwks_json: &str,
+    alg: Algorithm,
     token_kid: Option<String>,
     explicit_kid: Option<String>,
     allow_single: bool,
 ) -> AppResult<Jwk> {
-    let set: JwkSet = serde_json::from_str(jwks_json)
-        .map_err(|e| AppError::invalid_key(format!("invalid JWKS JSON: {e}")))?;
+    let set = parse_jwks(jwks_json)?;
     if set.keys.is_empty() {
         return Err(AppError::invalid_key("JWKS contains no keys"));
     }
+    let usable: Vec<&Jwk> = set
+        .keys
+        .iter()
+        .filter(|jwk| unusable_reason(jwk, alg).is_none())
+        .collect();
 
     let kid = explicit_kid.or(token_kid);
     if let Some(kid) = kid {
-        return set
-            .find(&kid)
-            .cloned()
-            .ok_or_else(|| AppError::invalid_key(format!("no JWKS key found for kid {kid}")));
+        let matching: Vec<&Jwk> = usable
+            .iter()
+            .copied()
+            .filter(|jwk| jwk.common.key_id.as_deref() == Some(kid.as_str()))
+            .collect();
+        if let Some(jwk) = prefer_declared_alg(&matching, alg) {
+            return Ok(jwk.clone());
+        }
+        return Err(
+            match set.find(&kid).and_then(|jwk| unusable_reason(jwk, alg)) {
+                Some(reason) => AppError::invalid_key(format!(
+                    "JWKS key {kid} cannot verify {alg:?} signatures ({reason})"
+                ))
+                .with_details(json!({ "kid": kid, "reason": "jwk_restricted" })),
+                None => AppError::invalid_key(format!("no JWKS key found for kid {kid}")),
+            },
+        );
     }
 
-    if allow_single && set.keys.len() == 1 {
-        return Ok(set.keys[0].clone());
+    if allow_single {
+        if let [jwk] = usable.as_slice() {
+            return Ok((*jwk).clone());
+        }
+        let declared: Vec<&Jwk> = usable
+            .iter()
+            .copied()
+            .filter(|jwk| declares_alg(jwk, alg))
+            .collect();
+        if let [jwk] = declared.as_slice() {
+            return Ok((*jwk).clone());
+        }
+        if usable.is_empty() {
+            return Err(AppError::invalid_key(format!(
+                "JWKS has no key that may verify {alg:?} signatures"
+            )));
+        }
     }
 
     Err(AppError::invalid_key(
@@ -31,8 +77,161 @@ pub fn select_jwk(
     ))
 }
 
-pub fn decoding_key_from_jwk(jwk: &Jwk) -> AppResult<DecodingKey> {
-    DecodingKey::from_jwk(jwk).map_err(AppError::from)
+/// Why a JWK may not verify `alg` signatures, per its `use`, `key_ops` and `alg` parameters.
+fn unusable_reason(jwk: &Jwk, alg: Algorithm) -> Option<String> {
+    let common = &jwk.common;
+    if let Some(public_key_use) = &common.public_key_use {
+        if *public_key_use != PublicKeyUse::Signature {
+            return Some("its use is not \"sig\"".to_string());
+        }
+    }
+    if let Some(ops) = &common.key_operations {
+        if !ops.contains(&KeyOperations::Verify) {
+            return Some("its key_ops do not include \"verify\"".to_string());
+        }
+    }
+    match &common.key_algorithm {
+        Some(declared) if format!("{declared:?}") != format!("{alg:?}") => {
+            Some(format!("it is declared for {declared:?}"))
+        }
+        _ => None,
+    }
+}
+
+fn declares_alg(jwk: &Jwk, alg: Algorithm) -> bool {
+    jwk.common
+        .key_algorithm
+        .is_some_and(|declared| format!("{declared:?}") == format!("{alg:?}"))
+}
+
+fn prefer_declared_alg<'a>(keys: &[&'a Jwk], alg: Algorithm) -> Option<&'a Jwk> {
+    keys.iter()
+        .find(|jwk| declares_alg(jwk, alg))
+        .or_else(|| keys.first())
+        .copied()
+}
+
+pub fn parse_jwks(jwks_json: &str) -> AppResult<JwkSet> {
+    serde_json::from_str(jwks_json)
+        .map_err(|e| AppError::invalid_key(format!("invalid JWKS JSON: {e}")))
+}
+
+/// Reads a JWKS from an http(s) URL or any regular input spec (inline JSON, @file, env:NAME).
+pub fn read_jwks(spec: &str) -> AppResult<String> {
+    if spec.starts_with("https://") || spec.starts_with("http://") {
+        return http::get_json(spec, "application/json")
+            .map(|body| body.to_string())
+            .map_err(|e| match e.kind {
+                ErrorKind::Offline => e,
+                _ => AppError::invalid_key(e.to_string()),
+            });
+    }
+    read_input(spec)
+}
+
+/// Reads a JWKS for storing: it must parse and hold at least one key.
+pub fn read_jwks_document(spec: &str) -> AppResult<(String, JwkSet)> {
+    let document = read_jwks(spec)?;
+    let set = parse_jwks(&document)?;
+    if set.keys.is_empty() {
+        return Err(AppError::invalid_key("JWKS contains no keys"));
+    }
+    Ok((document, set))
+}
+
+pub fn is_remote(spec: &str) -> bool {
+    spec.starts_with("https://") || spec.starts_with("http://")
+}
+
+/// True when `jwks_json` is a valid set that has no key with this kid.
+pub fn lacks_kid(jwks_json: &str, kid: &str) -> bool {
+    serde_json::from_str::<JwkSet>(jwks_json).is_ok_and(|set| set.find(kid).is_none())
+}
+
+/// Remembers remote JWKS URLs that did not list a kid, so later runs skip the fetch until the
+/// TTL runs out instead of hitting the network for a key that is not there. Entries are stored
+/// as `{ "<url> <kid>": <expires_at> }` in the data directory; without one (or with a zero TTL)
+/// the cache is disabled.
+#[derive(Debug, Default)]
+pub struct NegativeCache {
+    path: Option<PathBuf>,
+    ttl_secs: i64,
+    entries: BTreeMap<String, i64>,
+    dirty: bool,
+}
+
+impl NegativeCache {
+    pub fn open(data_dir: Option<PathBuf>, ttl: Duration) -> Self {
+        let ttl_secs = i64::try_from(ttl.as_secs()).unwrap_or(i64::MAX);
+        let Some(dir) = data_dir.filter(|_| ttl_secs > 0) else {
+            return Self::default();
+        };
+        let path = dir.join(NEGATIVE_CACHE_FILE);
+        // A missing or corrupt cache only costs a fetch.
+        let entries = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default();
+        Self {
+            path: Some(path),
+            ttl_secs,
+            entries,
+            dirty: false,
+        }
+    }
+
+    /// Seconds until a cached miss for this URL and kid expires, if there is one.
+    pub fn cached_miss(&self, url: &str, kid: &str, now: i64) -> Option<i64> {
+        self.path.as_ref()?;
+        let expires_at = *self.entries.get(&cache_key(url, kid))?;
+        (expires_at > now).then_some(expires_at - now)
+    }
+
+    pub fn record_miss(&mut self, url: &str, kid: &str, now: i64) {
+        if self.path.is_some() {
+            self.entries
+                .insert(cache_key(url, kid), now.saturating_add(self.ttl_secs));
+            self.dirty = true;
+        }
+    }
+
+    pub fn save(&mut self, now: i64) {
+        let Some(path) = self.path.as_ref().filter(|_| self.dirty) else {
+            return;
+        };
+        self.entries.retain(|_, expires_at| *expires_at > now);
+        let written = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|()| {
+                let body = serde_json::to_string_pretty(&self.entries).unwrap_or_default();
+                std::fs::write(path, body)
+            });
+        if let Err(err) = written {
+            warn!("failed to write JWKS negative cache {path:?}: {err}");
+        }
+        self.dirty = false;
+    }
+}
+
+fn cache_key(url: &str, kid: &str) -> String {
+    format!("{url} {kid}")
+}
+
+/// Names a JWKS source for labels and errors; inline JSON is not echoed.
+pub fn describe_source(spec: &str) -> String {
+    let reference = ["@", "env:", "fd:", "http://", "https://"]
+        .iter()
+        .any(|prefix| spec.starts_with(prefix));
+    match spec {
+        "-" => "stdin".to_string(),
+        _ if reference => spec.to_string(),
+        _ => "<inline>".to_string(),
+    }
+}
+
+pub fn verify_key_from_jwk(jwk: &Jwk) -> AppResult<VerifyKey> {
+    VerifyKey::from_jwk(jwk)
 }
 
 #[cfg(test)]
@@ -42,21 +241,72 @@ mod tests {
     #[test]
     fn select_jwk_by_kid() {
         let jwks = r#"{"keys":[{"kty":"oct","kid":"a","k":"aGVsbG8"},{"kty":"oct","kid":"b","k":"d29ybGQ"}]}"#;
-        let jwk = select_jwk(jwks, None, Some("b".to_string()), false).unwrap();
+        let jwk = select_jwk(jwks, Algorithm::HS256, None, Some("b".to_string()), false).unwrap();
         assert_eq!(jwk.common.key_id.as_deref(), Some("b"));
     }
 
     #[test]
     fn select_jwk_requires_kid_when_multiple() {
         let jwks = r#"{"keys":[{"kty":"oct","kid":"a","k":"aGVsbG8"},{"kty":"oct","kid":"b","k":"d29ybGQ"}]}"#;
-        let err = select_jwk(jwks, None, None, false).unwrap_err();
+        let err = select_jwk(jwks, Algorithm::HS256, None, None, false).unwrap_err();
         assert_eq!(err.kind, crate::error::ErrorKind::InvalidKey);
     }
 
+    #[test]
+    fn negative_cache_expires_and_persists() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = "https://idp.example/jwks";
+        let mut cache =
+            NegativeCache::open(Some(dir.path().to_path_buf()), Duration::from_secs(60));
+        assert_eq!(cache.cached_miss(url, "k1", 1_000), None);
+        cache.record_miss(url, "k1", 1_000);
+        cache.save(1_000);
+
+        let cache = NegativeCache::open(Some(dir.path().to_path_buf()), Duration::from_secs(60));
+        assert_eq!(cache.cached_miss(url, "k1", 1_030), Some(30));
+        assert_eq!(cache.cached_miss(url, "k1", 1_060), None);
+        assert_eq!(cache.cached_miss(url, "k2", 1_030), None);
+
+        let disabled = NegativeCache::open(Some(dir.path().to_path_buf()), Duration::ZERO);
+        assert_eq!(disabled.cached_miss(url, "k1", 1_030), None);
+    }
+
     #[test]
     fn select_jwk_allows_single_without_kid() {
         let jwks = r#"{"keys":[{"kty":"oct","k":"aGVsbG8"}]}"#;
-        let jwk = select_jwk(jwks, None, None, true).unwrap();
+        let jwk = select_jwk(jwks, Algorithm::HS256, None, None, true).unwrap();
         assert!(jwk.common.key_id.is_none());
     }
+
+    #[test]
+    fn select_jwk_skips_encryption_keys_and_prefers_matching_alg() {
+        let jwks = r#"{"keys":[
+            {"kty":"oct","kid":"a","use":"enc","k":"aGVsbG8"},
+            {"kty":"oct","kid":"a","use":"sig","k":"d29ybGQ"},
+            {"kty":"oct","kid":"b","key_ops":["encrypt"],"k":"aGVsbG8"},
+            {"kty":"oct","kid":"c","alg":"HS512","k":"aGVsbG8"},
+            {"kty":"oct","kid":"c","alg":"HS256","k":"c2Vjb25k"}
+        ]}"#;
+        let jwk = select_jwk(jwks, Algorithm::HS256, Some("a".to_string()), None, false).unwrap();
+        assert_eq!(jwk.common.public_key_use, Some(PublicKeyUse::Signature));
+
+        let err =
+            select_jwk(jwks, Algorithm::HS256, Some("b".to_string()), None, false).unwrap_err();
+        assert!(err.message.contains("key_ops"), "{}", err.message);
+
+        let jwk = select_jwk(jwks, Algorithm::HS256, Some("c".to_string()), None, false).unwrap();
+        assert!(format!("{:?}", jwk.common.key_algorithm).contains("HS256"));
+        let err =
+            select_jwk(jwks, Algorithm::HS384, Some("c".to_string()), None, false).unwrap_err();
+        assert!(
+            err.message.contains("declared for HS512"),
+            "{}",
+            err.message
+        );
+
+        let mixed =
+            r#"{"keys":[{"kty":"oct","use":"enc","k":"aGVsbG8"},{"kty":"oct","k":"d29ybGQ"}]}"#;
+        let jwk = select_jwk(mixed, Algorithm::HS256, None, None, true).unwrap();
+        assert!(jwk.common.public_key_use.is_none());
+    }
 }
@@ -1,16 +1,31 @@
+use crate::claims::now_epoch;
+use crate::cli::TimestampUnit;
+use crate::date_utils::normalize_timestamps;
 use crate::error::{AppError, AppResult};
+use crate::signer::SigningKey;
 use base64::engine::general_purpose::URL_SAFE_NO_PAD;
 use base64::Engine;
 use jsonwebtoken::{
-    decode, decode_header, encode, Algorithm, DecodingKey, EncodingKey, Header, TokenData,
-    Validation,
+    decode, decode_header, encode, Algorithm, EncodingKey, Header, TokenData, Validation,
 };
-use serde_json::Value;
+use serde_json::{Map, Value};
+
+#[cfg(feature = "aws-lc")]
+mod aws_lc;
+mod claim_checks;
+mod verify_key;
+
+pub use verify_key::VerifyKey;
+
+/// The only `zip` header value JOSE defines: raw DEFLATE (RFC 1951, RFC 7516 §4.1.3).
+pub const ZIP_DEFLATE: &str = "DEF";
 
 #[derive(Debug)]
 pub struct DecodedToken {
     pub header_json: Value,
     pub payload_json: Value,
+    /// The payload was inflated because the header sets `zip: DEF`.
+    pub compressed: bool,
 }
 
 #[derive(Clone)]
@@ -18,10 +33,18 @@ pub struct VerifyOptions {
     pub alg: Algorithm,
     pub leeway_secs: u64,
     pub ignore_exp: bool,
+    /// Skip the `nbf` check too; `verify --sweep` evaluates both time claims itself.
+    pub ignore_nbf: bool,
     pub iss: Option<String>,
     pub sub: Option<String>,
     pub aud: Vec<String>,
     pub require: Vec<String>,
+    /// Accepted `acr` values; when set, a token without one of them fails.
+    pub acr: Vec<String>,
+    /// Maximum age of `auth_time` in seconds; when set, a token without `auth_time` fails.
+    pub max_auth_age: Option<u64>,
+    /// How `exp`/`nbf`/`iat` are read; millisecond values are validated as seconds.
+    pub timestamps: TimestampUnit,
 }
 
 pub fn decode_unverified(token: &str) -> AppResult<DecodedToken> {
@@ -31,77 +54,348 @@ pub fn decode_unverified(token: &str) -> AppResult<DecodedToken> {
             "token must have 3 dot-separated segments",
         ));
     }
-    let header_bytes = URL_SAFE_NO_PAD
-        .decode(parts[0])
-        .map_err(|e| AppError::invalid_token(format!("invalid base64url header segment: {e}")))?;
-    let payload_bytes = URL_SAFE_NO_PAD
-        .decode(parts[1])
-        .map_err(|e| AppError::invalid_token(format!("invalid base64url payload segment: {e}")))?;
-
-    let header_json: Value = serde_json::from_slice(&header_bytes)
-        .map_err(|e| AppError::invalid_token(format!("header is not valid JSON: {e}")))?;
-    let payload_json: Value = serde_json::from_slice(&payload_bytes)
-        .map_err(|e| AppError::invalid_token(format!("payload is not valid JSON: {e}")))?;
+    let header_json = decode_segment_json(parts[0], "header")?;
+    let payload_json = decode_payload_json(parts[1], &header_json)?;
 
     Ok(DecodedToken {
+        compressed: header_json.get("zip").is_some(),
         header_json,
         payload_json,
     })
 }
 
+/// Compact JOSE serializations `inspect` can tell apart without any key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoseKind {
+    /// Signed JWT/JWS: `header.payload.signature`.
+    Jws,
+    /// Encrypted JWE: `header.encrypted_key.iv.ciphertext.tag`.
+    Jwe,
+    /// `alg: none` JWT, with an empty or missing signature segment.
+    Unsecured,
+}
+
+impl JoseKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            JoseKind::Jws => "jws",
+            JoseKind::Jwe => "jwe",
+            JoseKind::Unsecured => "unsecured",
+        }
+    }
+}
+
+pub fn classify_compact(token: &str) -> AppResult<JoseKind> {
+    let parts: Vec<&str> = token.trim().split('.').collect();
+    match parts.as_slice() {
+        [_, _, _, _, _] => Ok(JoseKind::Jwe),
+        [_, _] => Ok(JoseKind::Unsecured),
+        [header, _, ""] => {
+            let header = decode_segment_json(header, "header")?;
+            if header.get("alg").and_then(Value::as_str) == Some("none") {
+                Ok(JoseKind::Unsecured)
+            } else {
+                Ok(JoseKind::Jws)
+            }
+        }
+        [_, _, _] => Ok(JoseKind::Jws),
+        _ => Err(AppError::invalid_token(format!(
+            "expected a compact JWS (3 segments), JWE (5) or unsecured JWT (2), got {} segments",
+            parts.len()
+        ))),
+    }
+}
+
+/// Decodes one base64url segment that must hold JSON (a header or a payload).
+pub fn decode_segment_json(segment: &str, what: &str) -> AppResult<Value> {
+    let bytes = decode_segment(segment, what)?;
+    serde_json::from_slice(&bytes)
+        .map_err(|e| AppError::invalid_token(format!("{what} is not valid JSON: {e}")))
+}
+
+/// Like `decode_segment_json` for the payload, inflating it first when the header sets `zip`.
+pub fn decode_payload_json(segment: &str, header_json: &Value) -> AppResult<Value> {
+    match header_json.get("zip") {
+        None => decode_segment_json(segment, "payload"),
+        Some(Value::String(zip)) if zip == ZIP_DEFLATE => {
+            let bytes = inflate(&decode_segment(segment, "payload")?)?;
+            serde_json::from_slice(&bytes).map_err(|e| {
+                AppError::invalid_token(format!("inflated payload is not valid JSON: {e}"))
+            })
+        }
+        Some(other) => Err(AppError::invalid_token(format!(
+            "unsupported zip {other}; only {ZIP_DEFLATE} is defined"
+        ))),
+    }
+}
+
+fn decode_segment(segment: &str, what: &str) -> AppResult<Vec<u8>> {
+    URL_SAFE_NO_PAD
+        .decode(segment)
+        .map_err(|e| AppError::invalid_token(format!("invalid base64url {what} segment: {e}")))
+}
+
+#[cfg(feature = "deflate")]
+fn inflate(bytes: &[u8]) -> AppResult<Vec<u8>> {
+    use flate2::read::DeflateDecoder;
+    use std::io::Read;
+
+    let mut out = Vec::new();
+    DeflateDecoder::new(bytes)
+        .read_to_end(&mut out)
+        .map_err(|e| AppError::invalid_token(format!("payload does not inflate (zip DEF): {e}")))?;
+    Ok(out)
+}
+
+#[cfg(feature = "deflate")]
+fn deflate(bytes: &[u8]) -> AppResult<Vec<u8>> {
+    use flate2::write::DeflateEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(bytes)
+        .and_then(|_| encoder.finish())
+        .map_err(|e| AppError::internal(format!("deflate payload: {e}")))
+}
+
+#[cfg(not(feature = "deflate"))]
+fn inflate(_bytes: &[u8]) -> AppResult<Vec<u8>> {
+    Err(AppError::invalid_token(format!(
+        "zip {ZIP_DEFLATE} payloads require a build with the `deflate` feature"
+    )))
+}
+
+#[cfg(not(feature = "deflate"))]
+fn deflate(_bytes: &[u8]) -> AppResult<Vec<u8>> {
+    Err(AppError::invalid_token(format!(
+        "zip {ZIP_DEFLATE} payloads require a build with the `deflate` feature"
+    )))
+}
+
 pub fn decode_header_only(token: &str) -> AppResult<Header> {
     decode_header(token).map_err(AppError::from)
 }
 
+/// Checks only the signature of a compact JWS with the header's `alg`; no claim is validated.
+pub fn signature_valid(token: &str, key: &VerifyKey) -> AppResult<bool> {
+    let token = token.trim();
+    let alg = decode_header_only(token)?.alg;
+    let (message, signature) = token
+        .rsplit_once('.')
+        .ok_or_else(|| AppError::invalid_token("token must have 3 dot-separated segments"))?;
+    key.verify(signature, message.as_bytes(), alg)
+}
+
+/// Checks the signature with jsonwebtoken, then runs every configured claim check; a token that
+/// fails several checks reports all of them in the error's `violations`.
 pub fn verify_token(
     token: &str,
-    key: &DecodingKey,
+    key: &VerifyKey,
     opts: VerifyOptions,
 ) -> AppResult<TokenData<Value>> {
     let mut validation = Validation::new(opts.alg);
     validation.required_spec_claims.clear();
-    validation.leeway = opts.leeway_secs;
-    validation.validate_nbf = true;
+    validation.validate_exp = false;
+    validation.validate_nbf = false;
+    validation.validate_aud = false;
 
-    if opts.ignore_exp {
-        validation.validate_exp = false;
+    let data = match rewritten_token(token.trim(), key, opts.alg, opts.timestamps)? {
+        // The signature covers the original bytes, so it is checked above and the claims are
+        // validated on a copy with the inflated or normalized payload.
+        Some(rewritten) => {
+            validation.insecure_disable_signature_validation();
+            decode::<Value>(&rewritten, key.decoding_key(), &validation)
+        }
+        None if key.precheck(token.trim(), opts.alg)? => {
+            validation.insecure_disable_signature_validation();
+            decode::<Value>(token.trim(), key.decoding_key(), &validation)
+        }
+        None => decode::<Value>(token.trim(), key.decoding_key(), &validation),
     }
+    .map_err(AppError::from)?;
 
-    if opts.aud.is_empty() {
-        validation.validate_aud = false;
-    } else {
-        validation.set_audience(&opts.aud);
+    let claims_obj = data
+        .claims
+        .as_object()
+        .ok_or_else(|| AppError::invalid_claims("claims must be a JSON object"))?;
+    let now = u64::try_from(now_epoch()).unwrap_or_default();
+    let checks = claim_checks::check_claims(claims_obj, &opts, now);
+    match claim_checks::violations_error(&checks) {
+        Some(err) => Err(err),
+        None => Ok(data),
     }
+}
 
-    if let Some(iss) = opts.iss {
-        validation.set_issuer(&[iss]);
+/// For a `zip: DEF` token or one with millisecond timestamps, checks the signature and returns
+/// the token with its payload inflated and its timestamps in seconds; `None` for every other token.
+fn rewritten_token(
+    token: &str,
+    key: &VerifyKey,
+    alg: Algorithm,
+    timestamps: TimestampUnit,
+) -> AppResult<Option<String>> {
+    let parts: Vec<&str> = token.split('.').collect();
+    let [header, payload, signature] = parts.as_slice() else {
+        return Ok(None);
+    };
+    let header_json = decode_segment_json(header, "header")?;
+    let mut payload_json = decode_payload_json(payload, &header_json)?;
+    let normalized = normalize_timestamps(&mut payload_json, timestamps);
+    if header_json.get("zip").is_none() && normalized.is_empty() {
+        return Ok(None);
     }
-
-    if let Some(sub) = opts.sub {
-        validation.sub = Some(sub);
+    let token_alg = decode_header_only(token)?.alg;
+    if token_alg != alg {
+        return Err(jsonwebtoken::errors::Error::from(
+            jsonwebtoken::errors::ErrorKind::InvalidAlgorithm,
+        )
+        .into());
     }
+    let message = format!("{header}.{payload}");
+    if !key.verify(signature, message.as_bytes(), alg)? {
+        return Err(jsonwebtoken::errors::Error::from(
+            jsonwebtoken::errors::ErrorKind::InvalidSignature,
+        )
+        .into());
+    }
+    let payload_bytes = serde_json::to_vec(&payload_json)
+        .map_err(|e| AppError::internal(format!("serialize claims: {e}")))?;
+    Ok(Some(format!(
+        "{header}.{}.{signature}",
+        URL_SAFE_NO_PAD.encode(payload_bytes)
+    )))
+}
 
-    let data = decode::<Value>(token.trim(), key, &validation).map_err(AppError::from)?;
+pub fn encode_token(header: &Header, claims: &Value, key: &EncodingKey) -> AppResult<String> {
+    encode::<Value>(header, claims, key).map_err(AppError::from)
+}
 
-    if !opts.require.is_empty() {
-        let claims_obj = data
-            .claims
-            .as_object()
-            .ok_or_else(|| AppError::invalid_claims("claims must be a JSON object"))?;
-        for name in opts.require {
-            if !claims_obj.contains_key(&name) {
-                return Err(AppError::invalid_claims(format!(
-                    "missing required claim: {name}"
-                )));
-            }
+pub fn encode_token_with(header: &Header, claims: &Value, key: &SigningKey) -> AppResult<String> {
+    match key {
+        SigningKey::Local(key) => encode_token(header, claims, key),
+        SigningKey::External(_) => {
+            let header_json = serde_json::to_value(header)
+                .map_err(|e| AppError::internal(format!("serialize header: {e}")))?;
+            sign_compact(header.alg, &header_json, &claims_bytes(claims)?, key)
         }
     }
+}
 
-    Ok(data)
+/// Like `encode_token_with`, but adds protected header parameters that `Header` cannot carry
+/// (e.g. ACME `nonce`/`url`). Parameters replace header fields of the same name.
+pub fn encode_token_with_params(
+    header: &Header,
+    params: &Map<String, Value>,
+    claims: &Value,
+    key: &SigningKey,
+) -> AppResult<String> {
+    if params.is_empty() {
+        return encode_token_with(header, claims, key);
+    }
+    let mut header_json = serde_json::to_value(header)
+        .map_err(|e| AppError::internal(format!("serialize header: {e}")))?;
+    if let Some(obj) = header_json.as_object_mut() {
+        obj.extend(params.clone());
+    }
+    sign_compact(header.alg, &header_json, &claims_bytes(claims)?, key)
 }
 
-pub fn encode_token(header: &Header, claims: &Value, key: &EncodingKey) -> AppResult<String> {
-    encode::<Value>(header, claims, key).map_err(AppError::from)
+/// Like `encode_token_with_params`, but deflates the payload and sets `zip: DEF`. JWS has no
+/// `zip` of its own (RFC 7515 reserves it for JWE); this exists to test verifiers that accept it.
+pub fn encode_token_deflated(
+    header: &Header,
+    params: &Map<String, Value>,
+    claims: &Value,
+    key: &SigningKey,
+) -> AppResult<String> {
+    let mut header_json = serde_json::to_value(header)
+        .map_err(|e| AppError::internal(format!("serialize header: {e}")))?;
+    if let Some(obj) = header_json.as_object_mut() {
+        obj.extend(params.clone());
+        obj.insert("zip".to_string(), Value::from(ZIP_DEFLATE));
+    }
+    let payload = deflate(&claims_bytes(claims)?)?;
+    sign_compact(header.alg, &header_json, &payload, key)
+}
+
+/// Signs `claims` under `header_json` as given, custom parameters and all. A `zip: DEF` header
+/// deflates the payload, as `encode_token_deflated` does.
+pub fn encode_token_with_header_json(
+    alg: Algorithm,
+    header_json: &Value,
+    claims: &Value,
+    key: &SigningKey,
+) -> AppResult<String> {
+    sign_compact(alg, header_json, &payload_bytes(header_json, claims)?, key)
+}
+
+/// Like `encode_token_with_header_json`, but with `signature` put in as-is instead of signing.
+pub fn compact_with_signature(
+    header_json: &Value,
+    claims: &Value,
+    signature: &[u8],
+) -> AppResult<String> {
+    let header_bytes = serde_json::to_vec(header_json)
+        .map_err(|e| AppError::internal(format!("serialize header: {e}")))?;
+    Ok(format!(
+        "{}.{}.{}",
+        URL_SAFE_NO_PAD.encode(header_bytes),
+        URL_SAFE_NO_PAD.encode(payload_bytes(header_json, claims)?),
+        URL_SAFE_NO_PAD.encode(signature)
+    ))
+}
+
+fn payload_bytes(header_json: &Value, claims: &Value) -> AppResult<Vec<u8>> {
+    let bytes = claims_bytes(claims)?;
+    if header_json.get("zip").and_then(Value::as_str) == Some(ZIP_DEFLATE) {
+        deflate(&bytes)
+    } else {
+        Ok(bytes)
+    }
+}
+
+fn claims_bytes(claims: &Value) -> AppResult<Vec<u8>> {
+    serde_json::to_vec(claims).map_err(|e| AppError::internal(format!("serialize claims: {e}")))
+}
+
+fn sign_compact(
+    alg: Algorithm,
+    header_json: &Value,
+    payload: &[u8],
+    key: &SigningKey,
+) -> AppResult<String> {
+    let header_bytes = serde_json::to_vec(header_json)
+        .map_err(|e| AppError::internal(format!("serialize header: {e}")))?;
+    let signing_input = format!(
+        "{}.{}",
+        URL_SAFE_NO_PAD.encode(header_bytes),
+        URL_SAFE_NO_PAD.encode(payload)
+    );
+    let signature = match key {
+        SigningKey::Local(key) => jsonwebtoken::crypto::sign(signing_input.as_bytes(), key, alg)
+            .map_err(AppError::from)?,
+        SigningKey::External(signer) => {
+            URL_SAFE_NO_PAD.encode(signer.sign(alg, signing_input.as_bytes())?)
+        }
+    };
+    Ok(format!("{signing_input}.{signature}"))
+}
+
+/// Splits a compact JWS into the flattened JSON serialization (RFC 7515 §7.2.2) used by ACME.
+pub fn flattened_jws(token: &str) -> AppResult<Value> {
+    let parts: Vec<&str> = token.trim().split('.').collect();
+    match parts.as_slice() {
+        [protected, payload, signature] => Ok(serde_json::json!({
+            "protected": protected,
+            "payload": payload,
+            "signature": signature,
+        })),
+        _ => Err(AppError::invalid_token(
+            "token must have 3 dot-separated segments",
+        )),
+    }
 }
 
 #[cfg(test)]
@@ -120,6 +414,28 @@ mod tests {
             .as_secs() as i64
     }
 
+    #[test]
+    fn classify_compact_tells_jose_objects_apart() {
+        let none = URL_SAFE_NO_PAD.encode(br#"{"alg":"none"}"#);
+        let hs = URL_SAFE_NO_PAD.encode(br#"{"alg":"HS256"}"#);
+        let body = URL_SAFE_NO_PAD.encode(br#"{"sub":"a"}"#);
+        assert_eq!(
+            classify_compact(&format!("{none}.{body}.")).unwrap(),
+            JoseKind::Unsecured
+        );
+        assert_eq!(
+            classify_compact(&format!("{none}.{body}")).unwrap(),
+            JoseKind::Unsecured
+        );
+        assert_eq!(
+            classify_compact(&format!("{hs}.{body}.")).unwrap(),
+            JoseKind::Jws
+        );
+        assert_eq!(classify_compact("a.b.c.d.e").unwrap(), JoseKind::Jwe);
+        let err = classify_compact("a.b.c.d").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidToken);
+    }
+
     #[test]
     fn decode_unverified_rejects_bad_segments() {
         let err = decode_unverified("a.b").unwrap_err();
@@ -168,12 +484,16 @@ mod tests {
             alg: Algorithm::HS256,
             leeway_secs: 0,
             ignore_exp: false,
+            ignore_nbf: false,
             iss: None,
             sub: None,
             aud: Vec::new(),
             require: vec!["role".to_string()],
+            acr: Vec::new(),
+            max_auth_age: None,
+            timestamps: TimestampUnit::Seconds,
         };
-        let err = verify_token(&token, &DecodingKey::from_secret(b"secret"), opts).unwrap_err();
+        let err = verify_token(&token, &VerifyKey::from_secret(b"secret"), opts).unwrap_err();
         assert_eq!(err.kind, ErrorKind::InvalidClaims);
 
         let claims = json!({ "sub": "user" });
@@ -183,25 +503,199 @@ mod tests {
             alg: Algorithm::HS256,
             leeway_secs: 0,
             ignore_exp: false,
+            ignore_nbf: false,
             iss: None,
             sub: None,
             aud: Vec::new(),
             require: Vec::new(),
+            acr: Vec::new(),
+            max_auth_age: None,
+            timestamps: TimestampUnit::Seconds,
         };
         let data =
-            verify_token(&token, &DecodingKey::from_secret(b"secret"), opts).expect("verify token");
+            verify_token(&token, &VerifyKey::from_secret(b"secret"), opts).expect("verify token");
         assert_eq!(data.claims["sub"], "user");
 
         let opts = VerifyOptions {
             alg: Algorithm::HS256,
             leeway_secs: 0,
             ignore_exp: false,
+            ignore_nbf: false,
             iss: None,
             sub: None,
             aud: Vec::new(),
             require: vec!["exp".to_string()],
+            acr: Vec::new(),
+            max_auth_age: None,
+            timestamps: TimestampUnit::Seconds,
         };
-        let err = verify_token(&token, &DecodingKey::from_secret(b"secret"), opts).unwrap_err();
+        let err = verify_token(&token, &VerifyKey::from_secret(b"secret"), opts).unwrap_err();
         assert_eq!(err.kind, ErrorKind::InvalidClaims);
     }
+
+    struct HmacTestSigner(&'static [u8]);
+
+    impl crate::signer::ExternalSigner for HmacTestSigner {
+        fn sign(&self, alg: Algorithm, message: &[u8]) -> AppResult<Vec<u8>> {
+            let sig = jsonwebtoken::crypto::sign(message, &EncodingKey::from_secret(self.0), alg)
+                .map_err(AppError::from)?;
+            URL_SAFE_NO_PAD
+                .decode(sig)
+                .map_err(|e| AppError::internal(e.to_string()))
+        }
+    }
+
+    #[test]
+    fn external_signer_matches_local_encoding() {
+        let header = Header::new(Algorithm::HS256);
+        let claims = json!({ "sub": "user" });
+        let local = encode_token(&header, &claims, &EncodingKey::from_secret(b"secret"))
+            .expect("encode token");
+        let external = encode_token_with(
+            &header,
+            &claims,
+            &SigningKey::External(Box::new(HmacTestSigner(b"secret"))),
+        )
+        .expect("encode with signer");
+        assert_eq!(external, local);
+    }
+
+    #[test]
+    fn header_params_are_signed_into_the_protected_header() {
+        let key = SigningKey::Local(EncodingKey::from_secret(b"secret"));
+        let header = Header::new(Algorithm::HS256);
+        let mut params = Map::new();
+        params.insert("nonce".to_string(), json!("n-1"));
+        let token = encode_token_with_params(&header, &params, &json!({}), &key).expect("encode");
+        let decoded = decode_unverified(&token).expect("decode");
+        assert_eq!(decoded.header_json["nonce"], "n-1");
+
+        let external = encode_token_with_params(
+            &header,
+            &params,
+            &json!({}),
+            &SigningKey::External(Box::new(HmacTestSigner(b"secret"))),
+        )
+        .expect("encode with signer");
+        assert_eq!(external, token);
+
+        let flattened = flattened_jws(&token).expect("flattened");
+        let signature = token.rsplit('.').next().unwrap();
+        assert_eq!(flattened["signature"], signature);
+    }
+
+    #[cfg(feature = "deflate")]
+    #[test]
+    fn deflated_payloads_inflate_on_decode_and_verify() {
+        let key = SigningKey::Local(EncodingKey::from_secret(b"secret"));
+        let header = Header::new(Algorithm::HS256);
+        let claims = json!({ "sub": "a".repeat(200), "exp": now_ts() + 60 });
+        let token =
+            encode_token_deflated(&header, &Map::new(), &claims, &key).expect("encode deflated");
+        let payload_segment = token.split('.').nth(1).unwrap();
+        assert!(payload_segment.len() < 100);
+
+        let decoded = decode_unverified(&token).expect("decode");
+        assert!(decoded.compressed);
+        assert_eq!(decoded.header_json["zip"], "DEF");
+        assert_eq!(decoded.payload_json, claims);
+
+        let opts = VerifyOptions {
+            alg: Algorithm::HS256,
+            leeway_secs: 0,
+            ignore_exp: false,
+            ignore_nbf: false,
+            iss: None,
+            sub: Some("a".repeat(200)),
+            aud: Vec::new(),
+            require: vec!["exp".to_string()],
+            acr: Vec::new(),
+            max_auth_age: None,
+            timestamps: TimestampUnit::Seconds,
+        };
+        let data = verify_token(&token, &VerifyKey::from_secret(b"secret"), opts.clone())
+            .expect("verify deflated");
+        assert_eq!(data.claims, claims);
+        let err = verify_token(&token, &VerifyKey::from_secret(b"other"), opts).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidSignature);
+    }
+
+    fn fixture(name: &str) -> Vec<u8> {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+        std::fs::read(path.join(name)).expect("fixture")
+    }
+
+    fn tamper(token: &str) -> String {
+        let mut chars: Vec<char> = token.chars().collect();
+        let at = token.rfind('.').expect("compact JWS") + 10;
+        chars[at] = if chars[at] == 'A' { 'B' } else { 'A' };
+        chars.into_iter().collect()
+    }
+
+    #[test]
+    fn asymmetric_signatures_check_against_public_keys() {
+        let claims = json!({ "sub": "a" });
+        let rsa = VerifyKey::from_rsa_pem(&fixture("rsa_public.pem")).expect("rsa");
+        let ec256 = VerifyKey::from_ec_pem(&fixture("ec256_public.pem")).expect("ec256");
+        let ec384 = VerifyKey::from_ec_pem(&fixture("ec384_public.pem")).expect("ec384");
+        let ed = VerifyKey::from_ed_pem(&fixture("ed25519_public.pem")).expect("ed25519");
+        let rsa_signer = EncodingKey::from_rsa_pem(&fixture("rsa_private.pem")).expect("rsa");
+        let cases = [
+            (Algorithm::RS256, rsa_signer.clone(), &rsa),
+            (Algorithm::PS256, rsa_signer, &rsa),
+            (
+                Algorithm::ES256,
+                EncodingKey::from_ec_pem(&fixture("ec256_private.pem")).expect("ec256"),
+                &ec256,
+            ),
+            (
+                Algorithm::ES384,
+                EncodingKey::from_ec_pem(&fixture("ec384_private.pem")).expect("ec384"),
+                &ec384,
+            ),
+            (
+                Algorithm::EdDSA,
+                EncodingKey::from_ed_pem(&fixture("ed25519_private.pem")).expect("ed25519"),
+                &ed,
+            ),
+        ];
+        for (alg, signer, key) in cases {
+            let token = jsonwebtoken::encode(&Header::new(alg), &claims, &signer).expect("sign");
+            assert!(signature_valid(&token, key).expect("check"), "{alg:?}");
+            assert!(
+                !signature_valid(&tamper(&token), key).expect("check"),
+                "{alg:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn verify_token_with_jwk_key_reports_bad_signatures_and_algorithms() {
+        let jwks: jsonwebtoken::jwk::JwkSet =
+            serde_json::from_slice(&fixture("jwks.json")).expect("jwks");
+        let key = VerifyKey::from_jwk(jwks.find("rsa1").expect("rsa1")).expect("jwk");
+        let signer = EncodingKey::from_rsa_pem(&fixture("rsa_private.pem")).expect("rsa");
+        let claims = json!({ "sub": "a", "exp": now_ts() + 60 });
+        let token =
+            jsonwebtoken::encode(&Header::new(Algorithm::RS256), &claims, &signer).expect("sign");
+        let opts = |alg| VerifyOptions {
+            alg,
+            leeway_secs: 0,
+            ignore_exp: false,
+            ignore_nbf: false,
+            iss: None,
+            sub: None,
+            aud: Vec::new(),
+            require: Vec::new(),
+            acr: Vec::new(),
+            max_auth_age: None,
+            timestamps: TimestampUnit::Seconds,
+        };
+
+        let data = verify_token(&token, &key, opts(Algorithm::RS256)).expect("verify");
+        assert_eq!(data.claims, claims);
+        let err = verify_token(&tamper(&token), &key, opts(Algorithm::RS256)).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidSignature);
+        assert!(verify_token(&token, &key, opts(Algorithm::PS256)).is_err());
+    }
 }
@@ -0,0 +1,174 @@
+//! The `aws-lc` verification backend. jsonwebtoken hands ring the key bytes for every token, so
+//! each RSA check starts by parsing the modulus again; here the key is parsed into aws-lc-rs once
+//! and reused for every token checked with it.
+
+use aws_lc_rs::signature::{self, ParsedPublicKey, VerificationAlgorithm};
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use jsonwebtoken::jwk::{AlgorithmParameters, EllipticCurve, Jwk};
+use jsonwebtoken::Algorithm;
+use std::sync::{Arc, OnceLock};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(super) enum Family {
+    Rsa,
+    Ec,
+    Ed,
+}
+
+/// Public key bytes in any form aws-lc-rs accepts: PKCS#1 or SPKI DER for RSA, an uncompressed
+/// point or SPKI DER for EC, the raw key or SPKI DER for Ed25519.
+#[derive(Clone)]
+pub(super) struct PublicKey {
+    family: Family,
+    bytes: Arc<[u8]>,
+    /// Parsed for the first algorithm the key is used with; a key is nearly always used with one.
+    parsed: Arc<OnceLock<(Algorithm, Option<ParsedPublicKey>)>>,
+}
+
+impl PublicKey {
+    fn new(family: Family, bytes: Vec<u8>) -> Self {
+        PublicKey {
+            family,
+            bytes: bytes.into(),
+            parsed: Arc::new(OnceLock::new()),
+        }
+    }
+
+    pub(super) fn from_der(family: Family, der: &[u8]) -> Option<Self> {
+        Some(Self::new(family, der.to_vec()))
+    }
+
+    /// Only public key PEMs; private keys stay with jsonwebtoken.
+    pub(super) fn from_pem(family: Family, pem: &[u8]) -> Option<Self> {
+        let text = std::str::from_utf8(pem).ok()?;
+        let mut lines = text
+            .lines()
+            .map(str::trim)
+            .skip_while(|line| line.is_empty());
+        let begin = lines.next()?;
+        if begin != "-----BEGIN PUBLIC KEY-----" && begin != "-----BEGIN RSA PUBLIC KEY-----" {
+            return None;
+        }
+        let body: String = lines
+            .take_while(|line| !line.starts_with("-----END"))
+            .collect();
+        let der = STANDARD.decode(body).ok()?;
+        Some(Self::new(family, der))
+    }
+
+    pub(super) fn from_jwk(jwk: &Jwk) -> Option<Self> {
+        let decode = |value: &str| URL_SAFE_NO_PAD.decode(value).ok();
+        match &jwk.algorithm {
+            AlgorithmParameters::RSA(params) => {
+                let der = rsa_public_key_der(&decode(&params.n)?, &decode(&params.e)?);
+                Some(Self::new(Family::Rsa, der))
+            }
+            AlgorithmParameters::EllipticCurve(params) => {
+                let mut point = vec![0x04];
+                point.extend(decode(&params.x)?);
+                point.extend(decode(&params.y)?);
+                Some(Self::new(Family::Ec, point))
+            }
+            AlgorithmParameters::OctetKeyPair(params) if params.curve == EllipticCurve::Ed25519 => {
+                Some(Self::new(Family::Ed, decode(&params.x)?))
+            }
+            _ => None,
+        }
+    }
+
+    /// `None` when `alg` does not fit this key, the signature is not base64url or aws-lc-rs
+    /// rejects the key; jsonwebtoken then makes the call and reports its usual error.
+    pub(super) fn verify(&self, alg: Algorithm, signature: &str, message: &[u8]) -> Option<bool> {
+        let algorithm = verification_algorithm(self.family, alg)?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+        let (parsed_alg, parsed) = self
+            .parsed
+            .get_or_init(|| (alg, ParsedPublicKey::new(algorithm, &*self.bytes).ok()));
+        let valid = |key: &ParsedPublicKey| key.verify_sig(message, &signature).is_ok();
+        if *parsed_alg == alg {
+            return parsed.as_ref().map(valid);
+        }
+        ParsedPublicKey::new(algorithm, &*self.bytes)
+            .ok()
+            .as_ref()
+            .map(valid)
+    }
+}
+
+/// The same parameters jsonwebtoken passes to ring, so both backends accept the same signatures.
+fn verification_algorithm(
+    family: Family,
+    alg: Algorithm,
+) -> Option<&'static dyn VerificationAlgorithm> {
+    Some(match (family, alg) {
+        (Family::Rsa, Algorithm::RS256) => &signature::RSA_PKCS1_2048_8192_SHA256,
+        (Family::Rsa, Algorithm::RS384) => &signature::RSA_PKCS1_2048_8192_SHA384,
+        (Family::Rsa, Algorithm::RS512) => &signature::RSA_PKCS1_2048_8192_SHA512,
+        (Family::Rsa, Algorithm::PS256) => &signature::RSA_PSS_2048_8192_SHA256,
+        (Family::Rsa, Algorithm::PS384) => &signature::RSA_PSS_2048_8192_SHA384,
+        (Family::Rsa, Algorithm::PS512) => &signature::RSA_PSS_2048_8192_SHA512,
+        (Family::Ec, Algorithm::ES256) => &signature::ECDSA_P256_SHA256_FIXED,
+        (Family::Ec, Algorithm::ES384) => &signature::ECDSA_P384_SHA384_FIXED,
+        (Family::Ed, Algorithm::EdDSA) => &signature::ED25519,
+        _ => return None,
+    })
+}
+
+/// DER `RSAPublicKey` (RFC 8017 A.1.1) from a JWK's big-endian modulus and exponent.
+fn rsa_public_key_der(n: &[u8], e: &[u8]) -> Vec<u8> {
+    let mut body = der_integer(n);
+    body.extend(der_integer(e));
+    der_element(0x30, body)
+}
+
+fn der_integer(bytes: &[u8]) -> Vec<u8> {
+    let start = bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len());
+    let digits = &bytes[start..];
+    let mut value = Vec::with_capacity(digits.len() + 1);
+    // A leading zero keeps the integer positive (and encodes zero itself).
+    if digits.first().is_none_or(|b| b & 0x80 != 0) {
+        value.push(0);
+    }
+    value.extend_from_slice(digits);
+    der_element(0x02, value)
+}
+
+fn der_element(tag: u8, value: Vec<u8>) -> Vec<u8> {
+    let mut out = vec![tag];
+    let len = value.len();
+    if len < 0x80 {
+        out.push(len as u8);
+    } else {
+        let len_bytes = len.to_be_bytes();
+        let skip = len_bytes.iter().take_while(|b| **b == 0).count();
+        out.push(0x80 | (len_bytes.len() - skip) as u8);
+        out.extend_from_slice(&len_bytes[skip..]);
+    }
+    out.extend(value);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{der_integer, rsa_public_key_der};
+
+    #[test]
+    fn der_integers_stay_positive_and_minimal() {
+        assert_eq!(
+            der_integer(&[0x01, 0x00, 0x01]),
+            [0x02, 0x03, 0x01, 0x00, 0x01]
+        );
+        assert_eq!(der_integer(&[0x00, 0x00, 0x80]), [0x02, 0x02, 0x00, 0x80]);
+        assert_eq!(der_integer(&[]), [0x02, 0x01, 0x00]);
+    }
+
+    #[test]
+    fn long_rsa_keys_use_long_form_lengths() {
+        let der = rsa_public_key_der(&[0xc3; 256], &[0x01, 0x00, 0x01]);
+        // 257-byte modulus INTEGER (4-byte header) plus a 5-byte exponent INTEGER.
+        assert_eq!(&der[..4], [0x30, 0x82, 0x01, 0x0a]);
+        assert_eq!(&der[4..8], [0x02, 0x82, 0x01, 0x01]);
+        assert_eq!(der.len(), 4 + 0x010a);
+    }
+}
@@ -0,0 +1,338 @@
+use super::VerifyOptions;
+use crate::error::AppError;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+/// One claim check. `verify_token` runs every configured check, so a failing token reports all
+/// of its problems at once instead of the first one jsonwebtoken happens to hit.
+#[derive(Debug, Clone, Serialize)]
+pub struct ClaimCheck {
+    pub claim: String,
+    pub ok: bool,
+    /// Machine-readable failure class (`expired`, `not_yet_valid`, `mismatch`, `missing`,
+    /// `auth_too_old`);
+    /// absent for passing checks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<&'static str>,
+    pub message: String,
+}
+
+impl ClaimCheck {
+    fn pass(claim: &str, message: impl Into<String>) -> Self {
+        ClaimCheck {
+            claim: claim.to_string(),
+            ok: true,
+            reason: None,
+            message: message.into(),
+        }
+    }
+
+    fn fail(claim: &str, reason: &'static str, message: impl Into<String>) -> Self {
+        ClaimCheck {
+            claim: claim.to_string(),
+            ok: false,
+            reason: Some(reason),
+            message: message.into(),
+        }
+    }
+}
+
+/// Evaluates exp, nbf, iss, sub, aud and the required claims independently, with the same
+/// rules jsonwebtoken applies: a claim that is absent, or not of a type the check understands,
+/// only fails through `require`. The OpenID Connect `acr` and `auth_time` checks have no such
+/// leniency: asking for them makes the claim mandatory.
+pub(super) fn check_claims(
+    claims: &Map<String, Value>,
+    opts: &VerifyOptions,
+    now: u64,
+) -> Vec<ClaimCheck> {
+    let mut checks = Vec::new();
+    let leeway = opts.leeway_secs;
+    if !opts.ignore_exp {
+        if let Some(exp) = numeric_date(claims.get("exp")) {
+            checks.push(if exp < now.saturating_sub(leeway) {
+                ClaimCheck::fail(
+                    "exp",
+                    "expired",
+                    format!("expired {}s ago (exp {exp}, leeway {leeway}s)", now - exp),
+                )
+            } else {
+                ClaimCheck::pass("exp", format!("not expired (exp {exp})"))
+            });
+        }
+    }
+    if !opts.ignore_nbf {
+        if let Some(nbf) = numeric_date(claims.get("nbf")) {
+            checks.push(if nbf > now + leeway {
+                ClaimCheck::fail(
+                    "nbf",
+                    "not_yet_valid",
+                    format!(
+                        "not valid for another {}s (nbf {nbf}, leeway {leeway}s)",
+                        nbf - now
+                    ),
+                )
+            } else {
+                ClaimCheck::pass("nbf", format!("already valid (nbf {nbf})"))
+            });
+        }
+    }
+    if let Some(expected) = &opts.iss {
+        checks.extend(check_one_of(
+            "iss",
+            claims.get("iss"),
+            std::slice::from_ref(expected),
+        ));
+    }
+    if let Some(expected) = &opts.sub {
+        if let Some(Value::String(sub)) = claims.get("sub") {
+            checks.push(if sub == expected {
+                ClaimCheck::pass("sub", format!("is {sub:?}"))
+            } else {
+                ClaimCheck::fail(
+                    "sub",
+                    "mismatch",
+                    format!("is {sub:?}, expected {expected:?}"),
+                )
+            });
+        }
+    }
+    if !opts.aud.is_empty() {
+        checks.extend(check_one_of("aud", claims.get("aud"), &opts.aud));
+    }
+    if !opts.acr.is_empty() {
+        checks.push(match claims.get("acr") {
+            Some(Value::String(acr)) if opts.acr.contains(acr) => {
+                ClaimCheck::pass("acr", format!("is {acr:?}"))
+            }
+            Some(acr) => ClaimCheck::fail(
+                "acr",
+                "mismatch",
+                format!("is {acr}, expected {}", expected_values(&opts.acr)),
+            ),
+            None => ClaimCheck::fail(
+                "acr",
+                "missing",
+                format!("missing, expected {}", expected_values(&opts.acr)),
+            ),
+        });
+    }
+    if let Some(max_age) = opts.max_auth_age {
+        checks.push(match numeric_date(claims.get("auth_time")) {
+            Some(auth_time) => {
+                let age = now.saturating_sub(auth_time);
+                if age > max_age + leeway {
+                    ClaimCheck::fail(
+                        "auth_time",
+                        "auth_too_old",
+                        format!("authenticated {age}s ago, max age {max_age}s (leeway {leeway}s)"),
+                    )
+                } else {
+                    ClaimCheck::pass("auth_time", format!("authenticated {age}s ago"))
+                }
+            }
+            None => ClaimCheck::fail(
+                "auth_time",
+                "missing",
+                "missing or not a NumericDate; required by the max auth age",
+            ),
+        });
+    }
+    for name in &opts.require {
+        checks.push(if claims.contains_key(name) {
+            ClaimCheck::pass(name, "present")
+        } else {
+            ClaimCheck::fail(name, "missing", format!("missing required claim: {name}"))
+        });
+    }
+    checks
+}
+
+/// The `INVALID_CLAIMS` error for the failed `checks`, or `None` when all passed. `reason` keeps
+/// naming the time failures the way the jsonwebtoken errors did; `violations` lists them all.
+pub(super) fn violations_error(checks: &[ClaimCheck]) -> Option<AppError> {
+    let violations: Vec<&ClaimCheck> = checks.iter().filter(|check| !check.ok).collect();
+    if violations.is_empty() {
+        return None;
+    }
+    let message = violations
+        .iter()
+        .map(|check| format!("{}: {}", check.claim, check.message))
+        .collect::<Vec<_>>()
+        .join("; ");
+    let mut details = json!({ "violations": violations, "checks": checks });
+    if let Some(reason) = violations.iter().find_map(|check| {
+        check
+            .reason
+            .filter(|r| matches!(*r, "expired" | "not_yet_valid"))
+    }) {
+        details["reason"] = json!(reason);
+    }
+    Some(AppError::invalid_claims(message).with_details(details))
+}
+
+/// A NumericDate as jsonwebtoken reads it: a non-negative number, rounded to whole seconds.
+fn numeric_date(value: Option<&Value>) -> Option<u64> {
+    let value = value?;
+    if let Some(n) = value.as_u64() {
+        return Some(n);
+    }
+    let n = value.as_f64()?;
+    (n.is_finite() && n >= 0.0 && n < u64::MAX as f64).then(|| n.round() as u64)
+}
+
+/// `iss` and `aud` pass when the claim (a string or an array of strings) shares at least one
+/// value with `expected`. Claims of any other shape are skipped, as jsonwebtoken skips them.
+fn check_one_of(claim: &str, value: Option<&Value>, expected: &[String]) -> Option<ClaimCheck> {
+    let actual: Vec<&str> = match value? {
+        Value::String(s) => vec![s.as_str()],
+        Value::Array(items) => items.iter().map(Value::as_str).collect::<Option<_>>()?,
+        _ => return None,
+    };
+    let shown = match actual.as_slice() {
+        [single] => format!("{single:?}"),
+        _ => format!("{actual:?}"),
+    };
+    Some(if actual.iter().any(|a| expected.iter().any(|e| e == a)) {
+        ClaimCheck::pass(claim, format!("is {shown}"))
+    } else {
+        ClaimCheck::fail(
+            claim,
+            "mismatch",
+            format!("is {shown}, expected {}", expected_values(expected)),
+        )
+    })
+}
+
+fn expected_values(expected: &[String]) -> String {
+    match expected {
+        [single] => format!("{single:?}"),
+        _ => format!("one of {expected:?}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::TimestampUnit;
+    use jsonwebtoken::Algorithm;
+
+    fn opts() -> VerifyOptions {
+        VerifyOptions {
+            alg: Algorithm::HS256,
+            leeway_secs: 30,
+            ignore_exp: false,
+            ignore_nbf: false,
+            iss: Some("issuer".to_string()),
+            sub: Some("user".to_string()),
+            aud: vec!["web".to_string(), "mobile".to_string()],
+            require: vec!["role".to_string()],
+            acr: Vec::new(),
+            max_auth_age: None,
+            timestamps: TimestampUnit::Seconds,
+        }
+    }
+
+    fn claims(value: Value) -> Map<String, Value> {
+        value.as_object().cloned().expect("object")
+    }
+
+    #[test]
+    fn every_failing_claim_is_reported() {
+        let now = 1_000_000;
+        let checks = check_claims(
+            &claims(json!({
+                "exp": now - 100,
+                "nbf": now + 100,
+                "iss": "other",
+                "sub": "admin",
+                "aud": ["api"],
+            })),
+            &opts(),
+            now,
+        );
+        let failed: Vec<(&str, Option<&str>)> = checks
+            .iter()
+            .filter(|c| !c.ok)
+            .map(|c| (c.claim.as_str(), c.reason))
+            .collect();
+        assert_eq!(
+            failed,
+            vec![
+                ("exp", Some("expired")),
+                ("nbf", Some("not_yet_valid")),
+                ("iss", Some("mismatch")),
+                ("sub", Some("mismatch")),
+                ("aud", Some("mismatch")),
+                ("role", Some("missing")),
+            ]
+        );
+        let err = violations_error(&checks).expect("violations");
+        let details = err.details.expect("details");
+        assert_eq!(details["reason"], "expired");
+        assert_eq!(details["violations"].as_array().map(Vec::len), Some(6));
+        assert!(err.message.starts_with("exp: expired 100s ago"));
+        assert!(err.message.contains("; aud: is \"api\", expected one of"));
+    }
+
+    #[test]
+    fn passing_and_absent_claims_follow_jsonwebtoken_rules() {
+        let now = 1_000_000;
+        let checks = check_claims(
+            &claims(json!({
+                "exp": now - 10,
+                "nbf": (now + 10) as f64 + 0.4,
+                "iss": ["issuer", "backup"],
+                "aud": "mobile",
+                "role": "admin",
+                "sub": 42,
+            })),
+            &opts(),
+            now,
+        );
+        assert!(checks.iter().all(|c| c.ok), "{checks:?}");
+        let names: Vec<&str> = checks.iter().map(|c| c.claim.as_str()).collect();
+        // `sub` is not a string, so it is skipped like jsonwebtoken skips it.
+        assert_eq!(names, ["exp", "nbf", "iss", "aud", "role"]);
+        assert!(violations_error(&checks).is_none());
+
+        let checks = check_claims(&claims(json!({})), &opts(), now);
+        assert_eq!(checks.len(), 1);
+        assert_eq!(checks[0].reason, Some("missing"));
+    }
+
+    #[test]
+    fn acr_and_auth_age_checks_require_the_claims() {
+        let now = 1_000_000;
+        let opts = VerifyOptions {
+            acr: vec!["phr".to_string(), "phrh".to_string()],
+            max_auth_age: Some(300),
+            ..opts()
+        };
+        let reasons = |value: Value| -> Vec<(String, Option<&'static str>)> {
+            check_claims(&claims(value), &opts, now)
+                .into_iter()
+                .filter(|c| matches!(c.claim.as_str(), "acr" | "auth_time"))
+                .map(|c| (c.claim, c.reason))
+                .collect()
+        };
+        assert_eq!(
+            reasons(json!({ "acr": "phrh", "auth_time": now - 320 })),
+            [("acr".to_string(), None), ("auth_time".to_string(), None)]
+        );
+        assert_eq!(
+            reasons(json!({ "acr": "0", "auth_time": now - 400 })),
+            [
+                ("acr".to_string(), Some("mismatch")),
+                ("auth_time".to_string(), Some("auth_too_old"))
+            ]
+        );
+        assert_eq!(
+            reasons(json!({})),
+            [
+                ("acr".to_string(), Some("missing")),
+                ("auth_time".to_string(), Some("missing"))
+            ]
+        );
+    }
+}
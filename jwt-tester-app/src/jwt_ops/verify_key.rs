@@ -0,0 +1,141 @@
+#[cfg(feature = "aws-lc")]
+use super::aws_lc::{Family, PublicKey};
+use crate::error::{AppError, AppResult};
+use jsonwebtoken::jwk::Jwk;
+use jsonwebtoken::{Algorithm, DecodingKey};
+
+/// A key for signature checks. jsonwebtoken's `DecodingKey` covers every algorithm; with the
+/// `aws-lc` feature the public key is kept as well, and RSA, ECDSA and EdDSA signatures are then
+/// checked by aws-lc-rs against a key parsed only once.
+#[derive(Clone)]
+pub struct VerifyKey {
+    key: DecodingKey,
+    #[cfg(feature = "aws-lc")]
+    public: Option<PublicKey>,
+}
+
+impl From<DecodingKey> for VerifyKey {
+    /// A key without public key bytes; always checked by jsonwebtoken.
+    fn from(key: DecodingKey) -> Self {
+        VerifyKey {
+            key,
+            #[cfg(feature = "aws-lc")]
+            public: None,
+        }
+    }
+}
+
+impl VerifyKey {
+    pub fn from_secret(secret: &[u8]) -> Self {
+        DecodingKey::from_secret(secret).into()
+    }
+
+    pub fn from_rsa_der(der: &[u8]) -> Self {
+        VerifyKey {
+            key: DecodingKey::from_rsa_der(der),
+            #[cfg(feature = "aws-lc")]
+            public: PublicKey::from_der(Family::Rsa, der),
+        }
+    }
+
+    pub fn from_ec_der(der: &[u8]) -> Self {
+        VerifyKey {
+            key: DecodingKey::from_ec_der(der),
+            #[cfg(feature = "aws-lc")]
+            public: PublicKey::from_der(Family::Ec, der),
+        }
+    }
+
+    pub fn from_ed_der(der: &[u8]) -> Self {
+        VerifyKey {
+            key: DecodingKey::from_ed_der(der),
+            #[cfg(feature = "aws-lc")]
+            public: PublicKey::from_der(Family::Ed, der),
+        }
+    }
+
+    pub fn from_rsa_pem(pem: &[u8]) -> AppResult<Self> {
+        Ok(VerifyKey {
+            key: DecodingKey::from_rsa_pem(pem)?,
+            #[cfg(feature = "aws-lc")]
+            public: PublicKey::from_pem(Family::Rsa, pem),
+        })
+    }
+
+    pub fn from_ec_pem(pem: &[u8]) -> AppResult<Self> {
+        Ok(VerifyKey {
+            key: DecodingKey::from_ec_pem(pem)?,
+            #[cfg(feature = "aws-lc")]
+            public: PublicKey::from_pem(Family::Ec, pem),
+        })
+    }
+
+    pub fn from_ed_pem(pem: &[u8]) -> AppResult<Self> {
+        Ok(VerifyKey {
+            key: DecodingKey::from_ed_pem(pem)?,
+            #[cfg(feature = "aws-lc")]
+            public: PublicKey::from_pem(Family::Ed, pem),
+        })
+    }
+
+    pub fn from_jwk(jwk: &Jwk) -> AppResult<Self> {
+        Ok(VerifyKey {
+            key: DecodingKey::from_jwk(jwk)?,
+            #[cfg(feature = "aws-lc")]
+            public: PublicKey::from_jwk(jwk),
+        })
+    }
+
+    pub fn decoding_key(&self) -> &DecodingKey {
+        &self.key
+    }
+
+    /// Checks a base64url `signature` over `message`, like `jsonwebtoken::crypto::verify`.
+    pub(super) fn verify(
+        &self,
+        signature: &str,
+        message: &[u8],
+        alg: Algorithm,
+    ) -> AppResult<bool> {
+        #[cfg(feature = "aws-lc")]
+        if let Some(valid) = self
+            .public
+            .as_ref()
+            .and_then(|public| public.verify(alg, signature, message))
+        {
+            return Ok(valid);
+        }
+        jsonwebtoken::crypto::verify(signature, message, &self.key, alg).map_err(AppError::from)
+    }
+
+    /// Checks the signature of a compact JWS ahead of `jsonwebtoken::decode`, which can then skip
+    /// its own check. `Ok(false)` leaves the whole check to jsonwebtoken; a token that fails here
+    /// gets the error jsonwebtoken would have raised.
+    #[cfg(feature = "aws-lc")]
+    pub(super) fn precheck(&self, token: &str, alg: Algorithm) -> AppResult<bool> {
+        use jsonwebtoken::errors::{Error, ErrorKind};
+
+        let Some(public) = &self.public else {
+            return Ok(false);
+        };
+        let Some((message, signature)) = token.rsplit_once('.') else {
+            return Ok(false);
+        };
+        let Ok(header) = jsonwebtoken::decode_header(token) else {
+            return Ok(false);
+        };
+        if header.alg != alg {
+            return Err(Error::from(ErrorKind::InvalidAlgorithm).into());
+        }
+        match public.verify(alg, signature, message.as_bytes()) {
+            Some(true) => Ok(true),
+            Some(false) => Err(Error::from(ErrorKind::InvalidSignature).into()),
+            None => Ok(false),
+        }
+    }
+
+    #[cfg(not(feature = "aws-lc"))]
+    pub(super) fn precheck(&self, _token: &str, _alg: Algorithm) -> AppResult<bool> {
+        Ok(false)
+    }
+}
@@ -1,8 +1,18 @@
 mod format;
 mod project;
 mod resolve;
+mod trace;
 
+#[cfg(feature = "keygen")]
+pub use format::key_pair_from_material;
+pub use format::{
+    check_new_key_material, is_public_key_material, platform_key_ref, validate_key_material,
+    PLATFORM_KIND,
+};
+#[cfg(feature = "ui")]
+pub use project::resolve_project_key_single;
 pub use resolve::{
     resolve_encoding_key, resolve_encoding_key_with_vault, resolve_verification_key,
     resolve_verification_key_with_vault, KeySource,
 };
+pub use trace::KeyTrace;
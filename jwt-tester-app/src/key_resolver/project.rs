@@ -1,6 +1,7 @@
 use crate::error::{AppError, AppResult};
-use crate::vault::{KeyEntry, ProjectEntry, Vault};
+use crate::vault::{KeyEntry, KeyUsage, ProjectEntry, Vault};
 use jsonwebtoken::Algorithm;
+use serde_json::json;
 
 pub(super) fn expected_kind(alg: Algorithm) -> String {
     match alg {
@@ -16,18 +17,24 @@ pub(super) fn expected_kind(alg: Algorithm) -> String {
     }
 }
 
+/// Keys are only selected for a `purpose` (`KeyUsage::Sign` or `KeyUsage::Verify`) their usage
+/// allows: naming a key with the wrong usage is an error, and the implicit choices (kid, default
+/// key, the only key) skip such keys.
+#[allow(clippy::too_many_arguments)]
 pub(super) fn resolve_project_keys(
     vault: &Vault,
     project_name: &str,
     key_id: &Option<String>,
     key_name: &Option<String>,
+    key_fingerprint: &Option<String>,
     token_kid: Option<String>,
     try_all: bool,
+    purpose: KeyUsage,
 ) -> AppResult<(ProjectEntry, Vec<KeyEntry>)> {
     let project = vault
         .find_project_by_name(project_name)
         .map_err(|e| AppError::invalid_key(e.to_string()))?
-        .ok_or_else(|| AppError::invalid_key(format!("project not found: {project_name}")))?;
+        .ok_or_else(|| AppError::not_found(format!("project not found: {project_name}")))?;
 
     let keys = vault
         .list_keys(Some(&project.id))
@@ -41,8 +48,8 @@ pub(super) fn resolve_project_keys(
             .iter()
             .find(|k| &k.id == id)
             .cloned()
-            .ok_or_else(|| AppError::invalid_key("key id not found in project"))?;
-        return Ok((project, vec![k]));
+            .ok_or_else(|| AppError::not_found("key id not found in project"))?;
+        return Ok((project, vec![check_usage(k, purpose)?]));
     }
 
     if let Some(name) = key_name {
@@ -50,10 +57,28 @@ pub(super) fn resolve_project_keys(
             .iter()
             .find(|k| &k.name == name)
             .cloned()
-            .ok_or_else(|| AppError::invalid_key("key name not found in project"))?;
-        return Ok((project, vec![k]));
+            .ok_or_else(|| AppError::not_found("key name not found in project"))?;
+        return Ok((project, vec![check_usage(k, purpose)?]));
     }
 
+    if let Some(fingerprint) = key_fingerprint {
+        let k = find_key_by_fingerprint(vault, &keys, fingerprint)?;
+        return Ok((project, vec![check_usage(k, purpose)?]));
+    }
+
+    let usable: Vec<KeyEntry> = keys
+        .iter()
+        .filter(|k| k.usage.allows(purpose))
+        .cloned()
+        .collect();
+    let with_others = |selected: KeyEntry| {
+        let mut candidates = vec![selected.clone()];
+        if try_all {
+            candidates.extend(usable.iter().filter(|k| k.id != selected.id).cloned());
+        }
+        candidates
+    };
+
     if let Some(kid) = token_kid.as_deref() {
         let matches: Vec<_> = keys
             .iter()
@@ -61,17 +86,8 @@ pub(super) fn resolve_project_keys(
             .cloned()
             .collect();
         if matches.len() == 1 {
-            let selected = matches[0].clone();
-            if try_all {
-                let mut candidates = vec![selected.clone()];
-                for k in &keys {
-                    if k.id != selected.id {
-                        candidates.push(k.clone());
-                    }
-                }
-                return Ok((project, candidates));
-            }
-            return Ok((project, vec![selected]));
+            let selected = check_usage(matches[0].clone(), purpose)?;
+            return Ok((project, with_others(selected)));
         }
         if matches.len() > 1 {
             return Err(AppError::invalid_key(format!(
@@ -89,35 +105,98 @@ pub(super) fn resolve_project_keys(
             .find(|k| k.id == default_id)
             .cloned()
             .ok_or_else(|| AppError::invalid_key("project default_key_id points to missing key"))?;
-        if try_all {
-            let mut candidates = vec![default.clone()];
-            for k in &keys {
-                if k.id != default.id {
-                    candidates.push(k.clone());
-                }
-            }
-            return Ok((project, candidates));
+        if default.usage.allows(purpose) {
+            return Ok((project, with_others(default)));
         }
-        return Ok((project, vec![default]));
     }
 
-    if keys.len() == 1 {
-        return Ok((project, vec![keys[0].clone()]));
+    if usable.len() == 1 {
+        return Ok((project, vec![usable[0].clone()]));
+    }
+    if usable.is_empty() {
+        return Err(AppError::invalid_key(format!(
+            "project has no keys that may {}",
+            purpose.as_str()
+        )));
     }
 
     Err(AppError::invalid_key(format!(
-        "project has {} keys and no default; specify --key-id/--key-name or set a default key",
-        keys.len()
+        "project has {} keys that may {} and no default; specify --key-id/--key-name/--key-fingerprint or set a default key",
+        usable.len(),
+        purpose.as_str()
     )))
 }
 
-pub(super) fn resolve_project_key_single(
+fn check_usage(key: KeyEntry, purpose: KeyUsage) -> AppResult<KeyEntry> {
+    if key.usage.allows(purpose) {
+        return Ok(key);
+    }
+    Err(AppError::invalid_key(format!(
+        "key '{}' is {}-only and cannot be used to {}",
+        key.name,
+        key.usage.as_str(),
+        purpose.as_str()
+    ))
+    .with_details(json!({ "reason": "key_usage", "key": key.name, "usage": key.usage })))
+}
+
+/// Matches `sha256:<hex>`, bare hex, or a unique prefix of either (at least 8 hex digits).
+fn find_key_by_fingerprint(
+    vault: &Vault,
+    keys: &[KeyEntry],
+    fingerprint: &str,
+) -> AppResult<KeyEntry> {
+    let wanted = normalize_fingerprint(fingerprint);
+    if wanted.len() < 8 || !wanted.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(AppError::invalid_key(format!(
+            "invalid key fingerprint '{fingerprint}' (expected sha256:<hex>, at least 8 hex digits)"
+        )));
+    }
+    let mut matches = Vec::new();
+    for key in keys {
+        let stored = vault
+            .key_fingerprint(key)
+            .map_err(|e| AppError::invalid_key(e.to_string()))?;
+        if stored.is_some_and(|stored| normalize_fingerprint(&stored).starts_with(&wanted)) {
+            matches.push(key.clone());
+        }
+    }
+    if matches.len() > 1 {
+        return Err(AppError::invalid_key(format!(
+            "multiple keys match fingerprint '{fingerprint}'; give more digits"
+        )));
+    }
+    matches
+        .pop()
+        .ok_or_else(|| AppError::not_found("key fingerprint not found in project"))
+}
+
+fn normalize_fingerprint(value: &str) -> String {
+    let value = value.trim().to_ascii_lowercase();
+    value
+        .strip_prefix("sha256:")
+        .unwrap_or(&value)
+        .replace(':', "")
+}
+
+pub fn resolve_project_key_single(
     vault: &Vault,
     project_name: &str,
     key_id: &Option<String>,
     key_name: &Option<String>,
+    key_fingerprint: &Option<String>,
+    purpose: KeyUsage,
 ) -> AppResult<(ProjectEntry, KeyEntry)> {
-    let (project, keys) = resolve_project_keys(vault, project_name, key_id, key_name, None, false)?;
+    let (project, keys) = resolve_project_keys(
+        vault,
+        project_name,
+        key_id,
+        key_name,
+        key_fingerprint,
+        None,
+        false,
+        purpose,
+    )?;
     Ok((project, keys.into_iter().next().unwrap()))
 }
 
@@ -169,7 +248,17 @@ mod tests {
     #[test]
     fn resolve_project_keys_errors_when_project_missing() {
         let vault = memory_vault();
-        let err = resolve_project_keys(&vault, "missing", &None, &None, None, false).unwrap_err();
+        let err = resolve_project_keys(
+            &vault,
+            "missing",
+            &None,
+            &None,
+            &None,
+            None,
+            false,
+            KeyUsage::Verify,
+        )
+        .unwrap_err();
         assert!(err.to_string().contains("project not found"));
     }
 
@@ -177,7 +266,17 @@ mod tests {
     fn resolve_project_keys_errors_when_no_keys() {
         let vault = memory_vault();
         add_project(&vault, "alpha");
-        let err = resolve_project_keys(&vault, "alpha", &None, &None, None, false).unwrap_err();
+        let err = resolve_project_keys(
+            &vault,
+            "alpha",
+            &None,
+            &None,
+            &None,
+            None,
+            false,
+            KeyUsage::Verify,
+        )
+        .unwrap_err();
         assert!(err.to_string().contains("project has no keys"));
     }
 
@@ -188,9 +287,17 @@ mod tests {
         let key1 = add_hmac_key(&vault, &project.id, "one", None);
         let key2 = add_hmac_key(&vault, &project.id, "two", None);
 
-        let (_p, keys) =
-            resolve_project_keys(&vault, "alpha", &Some(key1.id.clone()), &None, None, false)
-                .expect("resolve by id");
+        let (_p, keys) = resolve_project_keys(
+            &vault,
+            "alpha",
+            &Some(key1.id.clone()),
+            &None,
+            &None,
+            None,
+            false,
+            KeyUsage::Verify,
+        )
+        .expect("resolve by id");
         assert_eq!(keys.len(), 1);
         assert_eq!(keys[0].id, key1.id);
 
@@ -199,8 +306,10 @@ mod tests {
             "alpha",
             &None,
             &Some(key2.name.clone()),
+            &None,
             None,
             false,
+            KeyUsage::Verify,
         )
         .expect("resolve by name");
         assert_eq!(keys.len(), 1);
@@ -219,8 +328,10 @@ mod tests {
             "alpha",
             &None,
             &None,
+            &None,
             Some("kid1".to_string()),
             true,
+            KeyUsage::Verify,
         )
         .expect("resolve by kid");
         assert_eq!(keys.len(), 2);
@@ -231,8 +342,10 @@ mod tests {
             "alpha",
             &None,
             &None,
+            &None,
             Some("missing".to_string()),
             false,
+            KeyUsage::Verify,
         )
         .unwrap_err();
         assert!(err.to_string().contains("no key with kid"));
@@ -243,8 +356,10 @@ mod tests {
             "alpha",
             &None,
             &None,
+            &None,
             Some("kid1".to_string()),
             false,
+            KeyUsage::Verify,
         )
         .unwrap_err();
         assert!(err.to_string().contains("multiple keys match kid"));
@@ -262,20 +377,48 @@ mod tests {
             .set_default_key(&project.id, Some(&key2.id))
             .expect("set default key");
 
-        let (_p, keys) = resolve_project_keys(&vault, "alpha", &None, &None, None, false)
-            .expect("resolve default");
+        let (_p, keys) = resolve_project_keys(
+            &vault,
+            "alpha",
+            &None,
+            &None,
+            &None,
+            None,
+            false,
+            KeyUsage::Verify,
+        )
+        .expect("resolve default");
         assert_eq!(keys.len(), 1);
         assert_eq!(keys[0].id, key2.id);
 
-        let (_p, keys) = resolve_project_keys(&vault, "alpha", &None, &None, None, true)
-            .expect("resolve default try all");
+        let (_p, keys) = resolve_project_keys(
+            &vault,
+            "alpha",
+            &None,
+            &None,
+            &None,
+            None,
+            true,
+            KeyUsage::Verify,
+        )
+        .expect("resolve default try all");
         assert_eq!(keys.len(), 2);
         assert_eq!(keys[0].id, key2.id);
 
         vault
             .set_default_key(&project.id, None)
             .expect("clear default key");
-        let err = resolve_project_keys(&vault, "alpha", &None, &None, None, false).unwrap_err();
+        let err = resolve_project_keys(
+            &vault,
+            "alpha",
+            &None,
+            &None,
+            &None,
+            None,
+            false,
+            KeyUsage::Verify,
+        )
+        .unwrap_err();
         assert!(err.to_string().contains("project has"));
     }
 
@@ -286,9 +429,141 @@ mod tests {
         let project = vault.find_project_by_name("solo").unwrap().unwrap();
         let key = add_hmac_key(&vault, &project.id, "only", None);
 
-        let (_p, keys) = resolve_project_keys(&vault, "solo", &None, &None, None, false)
-            .expect("resolve single key");
+        let (_p, keys) = resolve_project_keys(
+            &vault,
+            "solo",
+            &None,
+            &None,
+            &None,
+            None,
+            false,
+            KeyUsage::Verify,
+        )
+        .expect("resolve single key");
         assert_eq!(keys.len(), 1);
         assert_eq!(keys[0].id, key.id);
     }
+
+    #[test]
+    fn resolve_project_keys_only_selects_keys_their_usage_allows() {
+        let vault = memory_vault();
+        let project = add_project(&vault, "alpha");
+        let public = add_hmac_key(&vault, &project.id, "prod-public", Some("prod"));
+        let signer = add_hmac_key(&vault, &project.id, "signer", None);
+        vault
+            .set_key_usage(&public.id, KeyUsage::Verify)
+            .expect("set usage");
+        vault
+            .set_default_key(&project.id, Some(&public.id))
+            .expect("set default key");
+
+        let (_p, key) =
+            resolve_project_key_single(&vault, "alpha", &None, &None, &None, KeyUsage::Sign)
+                .expect("the only signing key");
+        assert_eq!(key.id, signer.id);
+        let (_p, key) =
+            resolve_project_key_single(&vault, "alpha", &None, &None, &None, KeyUsage::Verify)
+                .expect("default key verifies");
+        assert_eq!(key.id, public.id);
+
+        let err = resolve_project_key_single(
+            &vault,
+            "alpha",
+            &None,
+            &Some("prod-public".to_string()),
+            &None,
+            KeyUsage::Sign,
+        )
+        .unwrap_err();
+        assert!(err.message.contains("verify-only"), "{}", err.message);
+        let err = resolve_project_keys(
+            &vault,
+            "alpha",
+            &None,
+            &None,
+            &None,
+            Some("prod".to_string()),
+            false,
+            KeyUsage::Sign,
+        )
+        .unwrap_err();
+        assert!(err.message.contains("verify-only"), "{}", err.message);
+
+        vault
+            .set_key_usage(&signer.id, KeyUsage::Verify)
+            .expect("set usage");
+        let err = resolve_project_key_single(&vault, "alpha", &None, &None, &None, KeyUsage::Sign)
+            .unwrap_err();
+        assert!(
+            err.message.contains("no keys that may sign"),
+            "{}",
+            err.message
+        );
+    }
+
+    #[cfg(feature = "keygen")]
+    #[test]
+    fn resolve_project_keys_by_fingerprint() {
+        let vault = memory_vault();
+        let project = add_project(&vault, "alpha");
+        add_hmac_key(&vault, &project.id, "hmac", None);
+        let pem = std::fs::read_to_string(
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/rsa_private.pem"),
+        )
+        .expect("read fixture");
+        let rsa = vault
+            .add_key(KeyEntryInput {
+                project_id: project.id.clone(),
+                name: "rsa".to_string(),
+                kind: "rsa".to_string(),
+                secret: pem,
+                kid: None,
+                description: None,
+                tags: Vec::new(),
+            })
+            .expect("add key");
+        let fingerprint = rsa.fingerprint.clone().expect("fingerprint");
+
+        let short = fingerprint["sha256:".len().."sha256:".len() + 12].to_uppercase();
+        for selector in [fingerprint.clone(), short] {
+            let (_p, keys) = resolve_project_keys(
+                &vault,
+                "alpha",
+                &None,
+                &None,
+                &Some(selector),
+                None,
+                false,
+                KeyUsage::Verify,
+            )
+            .expect("resolve by fingerprint");
+            assert_eq!(keys.len(), 1);
+            assert_eq!(keys[0].id, rsa.id);
+        }
+
+        let err = resolve_project_keys(
+            &vault,
+            "alpha",
+            &None,
+            &None,
+            &Some("sha256:0000".to_string()),
+            None,
+            false,
+            KeyUsage::Verify,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("invalid key fingerprint"));
+        let err = resolve_project_keys(
+            &vault,
+            "alpha",
+            &None,
+            &None,
+            &Some("00000000".to_string()),
+            None,
+            false,
+            KeyUsage::Verify,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("fingerprint not found"));
+    }
 }
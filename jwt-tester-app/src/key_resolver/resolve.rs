@@ -1,18 +1,27 @@
-use super::format::{decoding_key_from_bytes, detect_key_format, encoding_key_from_bytes};
+use super::format::{
+    decoding_key_from_bytes, detect_key_format, encoding_key_from_bytes, platform_key_ref,
+    PLATFORM_KIND,
+};
 use super::project::{expected_kind, resolve_project_key_single, resolve_project_keys};
-use crate::cli::{EncodeArgs, VerifyCommonArgs};
-use crate::error::{AppError, AppResult};
-use crate::io_utils::{read_input, read_input_bytes};
+use super::trace::{Decision, KeyTrace};
+use crate::claims::now_epoch;
+use crate::cli::{KeyFormat, VerifyCommonArgs};
+use crate::encode_request::EncodeRequest;
+use crate::error::{AppError, AppResult, ErrorKind};
+use crate::io_utils::read_input_bytes;
 use crate::jwks;
-use crate::jwt_ops;
-use crate::vault::{Vault, VaultConfig};
-use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey};
+use crate::jwt_ops::{self, VerifyKey};
+use crate::signer::{load_ssh_private_key, open_external_signer, ExternalKeyRef, SigningKey};
+use crate::vault::{JwksEntry, KeyEntry, KeyUsage, ProjectEntry, Vault, VaultConfig};
+use jsonwebtoken::jwk::Jwk;
+use jsonwebtoken::{Algorithm, EncodingKey};
 use std::path::PathBuf;
 
 #[derive(Clone)]
 pub enum KeySource {
-    Single(DecodingKey, String),
-    Multiple(Vec<DecodingKey>, String),
+    Single(VerifyKey, String),
+    /// Candidates for `--try-all-keys`, each with the vault key name reported in `--explain`.
+    Multiple(Vec<(VerifyKey, String)>, String),
 }
 
 pub fn resolve_verification_key(
@@ -21,13 +30,24 @@ pub fn resolve_verification_key(
     args: &VerifyCommonArgs,
     token: &str,
     alg: Algorithm,
+    trace: &mut KeyTrace,
 ) -> AppResult<KeySource> {
-    let vault = Vault::open(VaultConfig {
-        no_persist,
-        data_dir,
-    })
-    .map_err(|e| AppError::invalid_key(e.to_string()))?;
-    resolve_verification_key_with_vault(&vault, args, token, alg)
+    // Direct key inputs never need the vault; opening it can trigger keychain prompts.
+    let cache_dir = if no_persist {
+        None
+    } else {
+        VaultConfig {
+            no_persist,
+            data_dir: data_dir.clone(),
+        }
+        .resolved_data_dir()
+    };
+    if let Some(source) = resolve_direct_verification_key(args, token, alg, cache_dir, trace)? {
+        return Ok(source);
+    }
+    let project = require_project(&args.project)?;
+    let vault = open_vault(no_persist, data_dir)?;
+    resolve_project_verification_key(&vault, &project, args, token, alg, trace)
 }
 
 pub fn resolve_verification_key_with_vault(
@@ -35,86 +55,356 @@ pub fn resolve_verification_key_with_vault(
     args: &VerifyCommonArgs,
     token: &str,
     alg: Algorithm,
+    trace: &mut KeyTrace,
 ) -> AppResult<KeySource> {
-    let direct = args.secret.is_some() || args.key.is_some() || args.jwks.is_some();
-    if direct {
-        if args.try_all_keys {
+    if let Some(source) = resolve_direct_verification_key(args, token, alg, None, trace)? {
+        return Ok(source);
+    }
+    let project = require_project(&args.project)?;
+    resolve_project_verification_key(vault, &project, args, token, alg, trace)
+}
+
+fn open_vault(no_persist: bool, data_dir: Option<PathBuf>) -> AppResult<Vault> {
+    Vault::open(VaultConfig {
+        no_persist,
+        data_dir,
+    })
+    .map_err(|e| AppError::invalid_key(e.to_string()))
+}
+
+fn require_project(project: &Option<String>) -> AppResult<String> {
+    project
+        .clone()
+        .ok_or_else(|| AppError::invalid_key("provide --project or a direct key input"))
+}
+
+fn resolve_direct_verification_key(
+    args: &VerifyCommonArgs,
+    token: &str,
+    alg: Algorithm,
+    cache_dir: Option<PathBuf>,
+    trace: &mut KeyTrace,
+) -> AppResult<Option<KeySource>> {
+    let direct = args.secret.is_some() || args.key.is_some() || !args.jwks.is_empty();
+    if !direct {
+        return Ok(None);
+    }
+    if args.try_all_keys {
+        return Err(AppError::invalid_key(
+            "--try-all-keys is only valid with --project",
+        ));
+    }
+    if !args.jwks.is_empty() {
+        let mut cache = jwks::NegativeCache::open(cache_dir, args.jwks_negative_ttl);
+        let resolved = resolve_jwks_key(args, token, &mut cache, trace);
+        cache.save(now_epoch());
+        return resolved.map(Some);
+    }
+
+    if args.secret.is_some() && args.key.is_some() {
+        return Err(AppError::invalid_key(
+            "provide only one of --secret or --key",
+        ));
+    }
+
+    if let Some(secret) = &args.secret {
+        if !matches!(alg, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512) {
             return Err(AppError::invalid_key(
-                "--try-all-keys is only valid with --project",
+                "--secret is only valid with HS256/384/512",
             ));
         }
-        if let Some(jwks_spec) = &args.jwks {
-            let jwks_raw = read_input(jwks_spec)?;
-            let header = jwt_ops::decode_header_only(token)?;
-            let jwk = jwks::select_jwk(
-                &jwks_raw,
-                header.kid,
-                args.kid.clone(),
-                args.allow_single_jwk,
-            )?;
-            let key = jwks::decoding_key_from_jwk(&jwk)?;
-            return Ok(KeySource::Single(key, "jwks".to_string()));
-        }
+        let secret = read_input_bytes(secret)?;
+        let key = VerifyKey::from_secret(&secret);
+        trace.record("secret", None, Decision::Used, "--secret given");
+        return Ok(Some(KeySource::Single(key, "secret".to_string())));
+    }
 
-        if args.secret.is_some() && args.key.is_some() {
-            return Err(AppError::invalid_key(
-                "provide only one of --secret or --key",
-            ));
+    let Some(key_spec) = &args.key else {
+        return Ok(None);
+    };
+    if matches!(alg, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512) {
+        return Err(AppError::invalid_key(
+            "--key is only valid with RSA/PS/EC/EdDSA algorithms",
+        ));
+    }
+    let bytes = read_input_bytes(key_spec)?;
+    let format = args.key_format.unwrap_or_else(|| detect_key_format(&bytes));
+    let how = if args.key_format.is_some() {
+        "given with --key-format"
+    } else {
+        "detected"
+    };
+    let reason = format!("--key given; format {} ({how})", format_name(format));
+    match decoding_key_from_bytes(alg, &bytes, format) {
+        Ok(key) => {
+            trace.record("key", None, Decision::Used, reason);
+            Ok(Some(KeySource::Single(key, "key".to_string())))
+        }
+        Err(err) => {
+            trace.record("key", None, Decision::Failed, format!("{reason}: {err}"));
+            Err(err)
         }
+    }
+}
 
-        if let Some(secret) = &args.secret {
-            if !matches!(alg, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512) {
-                return Err(AppError::invalid_key(
-                    "--secret is only valid with HS256/384/512",
-                ));
+/// Tries each `--jwks` source in the order given and uses the first one with a matching key,
+/// so a token signed with a key that only the previous JWKS snapshot still has verifies during
+/// rotation. A source that cannot be read or has no match is skipped; if none matches, every
+/// source's reason is reported. Remote sources known not to list the kid are skipped without a
+/// fetch, and `--offline` fails immediately instead of falling through to other sources.
+fn resolve_jwks_key(
+    args: &VerifyCommonArgs,
+    token: &str,
+    cache: &mut jwks::NegativeCache,
+    trace: &mut KeyTrace,
+) -> AppResult<KeySource> {
+    let header = jwt_ops::decode_header_only(token)?;
+    let kid = args.kid.clone().or(header.kid.clone());
+    let now = now_epoch();
+    let mut failures = Vec::new();
+    for spec in &args.jwks {
+        let source = jwks::describe_source(spec);
+        let remote_kid = kid.as_deref().filter(|_| jwks::is_remote(spec));
+        let label = format!("jwks:{source}");
+        if let Some(remaining) = remote_kid.and_then(|kid| cache.cached_miss(spec, kid, now)) {
+            let reason = format!(
+                "kid {} not listed (cached, retried in {remaining}s)",
+                kid.as_deref().unwrap_or_default()
+            );
+            trace.record(&label, None, Decision::Skipped, &reason);
+            failures.push(format!("{source}: {reason}"));
+            continue;
+        }
+        let raw = match jwks::read_jwks(spec) {
+            Ok(raw) => raw,
+            Err(err) => {
+                trace.record(&label, None, Decision::Failed, err.to_string());
+                if err.kind == ErrorKind::Offline || args.jwks.len() == 1 {
+                    return Err(err);
+                }
+                failures.push(format!("{source}: {err}"));
+                continue;
+            }
+        };
+        let selected = jwks::select_jwk(
+            &raw,
+            header.alg,
+            header.kid.clone(),
+            args.kid.clone(),
+            args.allow_single_jwk,
+        );
+        match selected {
+            Ok(jwk) => {
+                let key = jwks::verify_key_from_jwk(&jwk)?;
+                trace.record(
+                    &label,
+                    jwk.common.key_id.as_deref(),
+                    Decision::Used,
+                    jwk_reason(&jwk),
+                );
+                return Ok(KeySource::Single(key, label));
+            }
+            Err(err) => {
+                trace.record(&label, None, Decision::Skipped, err.to_string());
+                if let Some(kid) = remote_kid.filter(|kid| jwks::lacks_kid(&raw, kid)) {
+                    cache.record_miss(spec, kid, now);
+                }
+                if args.jwks.len() == 1 {
+                    return Err(err);
+                }
+                failures.push(format!("{source}: {err}"));
             }
-            let secret = read_input_bytes(secret)?;
-            let key = DecodingKey::from_secret(&secret);
-            return Ok(KeySource::Single(key, "secret".to_string()));
         }
+    }
+    Err(AppError::invalid_key(format!(
+        "no JWKS source had a usable key ({})",
+        failures.join("; ")
+    )))
+}
 
-        if let Some(key_spec) = &args.key {
-            if matches!(alg, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512) {
-                return Err(AppError::invalid_key(
-                    "--key is only valid with RSA/PS/EC/EdDSA algorithms",
-                ));
-            }
-            let bytes = read_input_bytes(key_spec)?;
-            let format = args.key_format.unwrap_or_else(|| detect_key_format(&bytes));
-            let key = decoding_key_from_bytes(alg, &bytes, format)?;
-            return Ok(KeySource::Single(key, "key".to_string()));
+/// Individual vault keys come first; when they cannot satisfy the token (e.g. its kid is not a
+/// stored key) the project's stored JWKS documents are searched, unless a key was named explicitly.
+fn resolve_project_verification_key(
+    vault: &Vault,
+    project: &str,
+    args: &VerifyCommonArgs,
+    token: &str,
+    alg: Algorithm,
+    trace: &mut KeyTrace,
+) -> AppResult<KeySource> {
+    let keyed = resolve_project_vault_key(vault, project, args, token, alg, trace);
+    if keyed.is_ok()
+        || args.key_id.is_some()
+        || args.key_name.is_some()
+        || args.key_fingerprint.is_some()
+    {
+        return keyed;
+    }
+    match resolve_project_jwks(vault, project, args, token, trace)? {
+        Some(source) => Ok(source),
+        None => keyed,
+    }
+}
+
+fn resolve_project_jwks(
+    vault: &Vault,
+    project: &str,
+    args: &VerifyCommonArgs,
+    token: &str,
+    trace: &mut KeyTrace,
+) -> AppResult<Option<KeySource>> {
+    let Some(project_entry) = vault
+        .find_project_by_name(project)
+        .map_err(|e| AppError::invalid_key(e.to_string()))?
+    else {
+        return Ok(None);
+    };
+    let entries = vault
+        .list_jwks(Some(&project_entry.id))
+        .map_err(|e| AppError::invalid_key(e.to_string()))?;
+    let header = jwt_ops::decode_header_only(token)?;
+    let select = |document: &str| {
+        jwks::select_jwk(
+            document,
+            header.alg,
+            header.kid.clone(),
+            args.kid.clone(),
+            args.allow_single_jwk,
+        )
+    };
+    for entry in &entries {
+        let document = vault
+            .get_jwks_document(&entry.id)
+            .map_err(|e| AppError::invalid_key(e.to_string()))?;
+        match select(&document) {
+            Ok(jwk) => return stored_jwks_source(&jwk, entry, trace).map(Some),
+            Err(err) => trace.record(
+                &format!("vault-jwks:{}", entry.name),
+                None,
+                Decision::Skipped,
+                err.to_string(),
+            ),
         }
     }
+    if !args.auto_refresh {
+        return Ok(None);
+    }
 
-    let project = args
-        .project
-        .clone()
-        .ok_or_else(|| AppError::invalid_key("provide --project or a direct key input"))?;
+    // The kid may belong to a key the issuer rotated in after the document was stored.
+    for entry in &entries {
+        let Some(url) = entry.source_url.as_deref() else {
+            continue;
+        };
+        let (document, _) = jwks::read_jwks_document(url)?;
+        let refreshed = vault
+            .update_jwks_document(&entry.id, &document)
+            .map_err(|e| AppError::invalid_key(e.to_string()))?;
+        match select(&document) {
+            Ok(jwk) => return stored_jwks_source(&jwk, &refreshed, trace).map(Some),
+            Err(err) => trace.record(
+                &format!("vault-jwks:{}", entry.name),
+                None,
+                Decision::Skipped,
+                format!("after refresh: {err}"),
+            ),
+        }
+    }
+    Ok(None)
+}
+
+fn stored_jwks_source(jwk: &Jwk, entry: &JwksEntry, trace: &mut KeyTrace) -> AppResult<KeySource> {
+    let key = jwks::verify_key_from_jwk(jwk)?;
+    let label = format!("vault-jwks:{}", entry.name);
+    trace.record(
+        &label,
+        jwk.common.key_id.as_deref(),
+        Decision::Used,
+        jwk_reason(jwk),
+    );
+    Ok(KeySource::Single(key, label))
+}
+
+fn jwk_reason(jwk: &Jwk) -> String {
+    match &jwk.common.key_id {
+        Some(kid) => format!("JWKS key with kid '{kid}'"),
+        None => "the only usable JWKS key (--allow-single-jwk)".to_string(),
+    }
+}
+
+fn format_name(format: KeyFormat) -> &'static str {
+    match format {
+        KeyFormat::Pem => "pem",
+        KeyFormat::Der => "der",
+    }
+}
+
+fn resolve_project_vault_key(
+    vault: &Vault,
+    project: &str,
+    args: &VerifyCommonArgs,
+    token: &str,
+    alg: Algorithm,
+    trace: &mut KeyTrace,
+) -> AppResult<KeySource> {
     let header = jwt_ops::decode_header_only(token)?;
     let token_kid = header.kid.clone();
-    let (project_entry, candidates) = resolve_project_keys(
+    let resolved = resolve_project_keys(
         vault,
-        &project,
+        project,
         &args.key_id,
         &args.key_name,
-        token_kid,
+        &args.key_fingerprint,
+        token_kid.clone(),
         args.try_all_keys,
-    )?;
+        KeyUsage::Verify,
+    );
+    let (project_entry, candidates) = match resolved {
+        Ok(resolved) => resolved,
+        Err(err) => {
+            trace_project_keys(trace, vault, project, args, token_kid.as_deref(), &[]);
+            return Err(err);
+        }
+    };
 
     let expected_kind = expected_kind(alg);
     let mut matching_keys = Vec::new();
-    for key in candidates {
-        if key.kind.to_lowercase() != expected_kind {
+    let mut outcomes = Vec::new();
+    for (index, entry) in candidates.into_iter().enumerate() {
+        let why = selected_reason(index, &entry, &project_entry, args, token_kid.as_deref());
+        if entry.kind.to_lowercase() != expected_kind {
+            outcomes.push((
+                entry.id,
+                Decision::Skipped,
+                format!(
+                    "{why}, but kind mismatch: key is {}, {alg:?} needs {expected_kind}",
+                    entry.kind
+                ),
+            ));
             continue;
         }
         let material = vault
-            .get_key_material(&key.id)
+            .get_key_material(&entry.id)
             .map_err(|e| AppError::invalid_key(e.to_string()))?;
         let bytes = material.into_bytes();
         let format = detect_key_format(&bytes);
-        let key = decoding_key_from_bytes(alg, &bytes, format)?;
-        matching_keys.push(key);
+        let why = if expected_kind == "hmac" {
+            why
+        } else {
+            format!("{why}; format {} (detected)", format_name(format))
+        };
+        match decoding_key_from_bytes(alg, &bytes, format) {
+            Ok(key) => {
+                outcomes.push((entry.id, Decision::Used, why));
+                matching_keys.push((key, entry.name));
+            }
+            Err(err) => {
+                outcomes.push((entry.id, Decision::Failed, format!("{why}: {err}")));
+                trace_project_keys(trace, vault, project, args, token_kid.as_deref(), &outcomes);
+                return Err(err);
+            }
+        }
     }
+    trace_project_keys(trace, vault, project, args, token_kid.as_deref(), &outcomes);
 
     if matching_keys.is_empty() {
         return Err(AppError::invalid_key(format!(
@@ -125,7 +415,7 @@ pub fn resolve_verification_key_with_vault(
 
     if matching_keys.len() == 1 {
         Ok(KeySource::Single(
-            matching_keys.remove(0),
+            matching_keys.remove(0).0,
             "vault".to_string(),
         ))
     } else {
@@ -133,85 +423,202 @@ pub fn resolve_verification_key_with_vault(
     }
 }
 
+/// Why `resolve_project_keys` put this key at `index` of its candidates.
+fn selected_reason(
+    index: usize,
+    key: &KeyEntry,
+    project: &ProjectEntry,
+    args: &VerifyCommonArgs,
+    token_kid: Option<&str>,
+) -> String {
+    if index > 0 {
+        return "tried by --try-all-keys".to_string();
+    }
+    if args.key_id.is_some() {
+        "named by --key-id".to_string()
+    } else if args.key_name.is_some() {
+        "named by --key-name".to_string()
+    } else if args.key_fingerprint.is_some() {
+        "named by --key-fingerprint".to_string()
+    } else if let Some(kid) = token_kid {
+        format!("kid matches the token ('{kid}')")
+    } else if project.default_key_id.as_deref() == Some(key.id.as_str()) {
+        "project default key".to_string()
+    } else {
+        "the only key that may verify".to_string()
+    }
+}
+
+/// Records one decision per project key: `outcomes` (key id, decision, reason) for the keys
+/// resolution picked, and for every other key the reason it was passed over.
+fn trace_project_keys(
+    trace: &mut KeyTrace,
+    vault: &Vault,
+    project: &str,
+    args: &VerifyCommonArgs,
+    token_kid: Option<&str>,
+    outcomes: &[(String, Decision, String)],
+) {
+    let Ok(Some(project)) = vault.find_project_by_name(project) else {
+        return;
+    };
+    let Ok(keys) = vault.list_keys(Some(&project.id)) else {
+        return;
+    };
+    for key in &keys {
+        match outcomes.iter().find(|(id, ..)| *id == key.id) {
+            Some((_, decision, reason)) => {
+                trace.record("vault", Some(&key.name), *decision, reason.clone());
+            }
+            None => trace.record(
+                "vault",
+                Some(&key.name),
+                Decision::Skipped,
+                passed_over_reason(key, &project, args, token_kid),
+            ),
+        }
+    }
+}
+
+fn passed_over_reason(
+    key: &KeyEntry,
+    project: &ProjectEntry,
+    args: &VerifyCommonArgs,
+    token_kid: Option<&str>,
+) -> String {
+    if args.key_id.is_some() || args.key_name.is_some() || args.key_fingerprint.is_some() {
+        return "another key was named with --key-id/--key-name/--key-fingerprint".to_string();
+    }
+    if !key.usage.allows(KeyUsage::Verify) {
+        return format!("{}-only key", key.usage.as_str());
+    }
+    if let Some(kid) = token_kid {
+        return match key.kid.as_deref() {
+            Some(own) if own == kid => "kid matches the token, but so does another key".to_string(),
+            Some(own) => format!("kid mismatch: key has '{own}', token has '{kid}'"),
+            None => format!("kid mismatch: key has no kid, token has '{kid}'"),
+        };
+    }
+    if project.default_key_id.is_some() {
+        "not the project default key (--try-all-keys tries it too)".to_string()
+    } else {
+        "several keys may verify and the project has no default key".to_string()
+    }
+}
+
 pub fn resolve_encoding_key(
     no_persist: bool,
     data_dir: Option<PathBuf>,
-    args: &EncodeArgs,
-) -> AppResult<(EncodingKey, String)> {
-    let vault = Vault::open(VaultConfig {
-        no_persist,
-        data_dir,
-    })
-    .map_err(|e| AppError::invalid_key(e.to_string()))?;
-    resolve_encoding_key_with_vault(&vault, args)
+    request: &EncodeRequest,
+) -> AppResult<(SigningKey, String)> {
+    if let Some(resolved) = resolve_direct_encoding_key(request)? {
+        return Ok(resolved);
+    }
+    let project = require_project(&request.project)?;
+    let vault = open_vault(no_persist, data_dir)?;
+    resolve_project_encoding_key(&vault, &project, request)
 }
 
 pub fn resolve_encoding_key_with_vault(
     vault: &Vault,
-    args: &EncodeArgs,
-) -> AppResult<(EncodingKey, String)> {
-    let direct = args.secret.is_some() || args.key.is_some();
-    if direct {
-        if args.secret.is_some() && args.key.is_some() {
+    request: &EncodeRequest,
+) -> AppResult<(SigningKey, String)> {
+    if let Some(resolved) = resolve_direct_encoding_key(request)? {
+        return Ok(resolved);
+    }
+    let project = require_project(&request.project)?;
+    resolve_project_encoding_key(vault, &project, request)
+}
+
+fn resolve_direct_encoding_key(request: &EncodeRequest) -> AppResult<Option<(SigningKey, String)>> {
+    if request.secret.is_some() && request.key.is_some() {
+        return Err(AppError::invalid_key(
+            "provide only one of --secret or --key",
+        ));
+    }
+
+    if let Some(secret) = &request.secret {
+        let alg = Algorithm::from(request.alg);
+        if !matches!(alg, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512) {
             return Err(AppError::invalid_key(
-                "provide only one of --secret or --key",
+                "--secret is only valid with HS256/384/512",
             ));
         }
+        let secret = read_input_bytes(secret)?;
+        let key = EncodingKey::from_secret(&secret);
+        return Ok(Some((SigningKey::Local(key), "secret".to_string())));
+    }
 
-        if let Some(secret) = &args.secret {
-            let alg = Algorithm::from(args.alg);
-            if !matches!(alg, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512) {
-                return Err(AppError::invalid_key(
-                    "--secret is only valid with HS256/384/512",
-                ));
-            }
-            let secret = read_input_bytes(secret)?;
-            let key = EncodingKey::from_secret(&secret);
-            return Ok((key, "secret".to_string()));
-        }
-
-        if let Some(key_spec) = &args.key {
-            let alg = Algorithm::from(args.alg);
-            if matches!(alg, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512) {
-                return Err(AppError::invalid_key(
-                    "--key is only valid with RSA/PS/EC/EdDSA algorithms",
-                ));
-            }
-            let bytes = read_input_bytes(key_spec)?;
-            let format = args.key_format.unwrap_or_else(|| detect_key_format(&bytes));
-            let key = encoding_key_from_bytes(alg, &bytes, format)?;
-            return Ok((key, "key".to_string()));
-        }
+    let Some(key_spec) = &request.key else {
+        return Ok(None);
+    };
+    let alg = Algorithm::from(request.alg);
+    if matches!(alg, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512) {
+        return Err(AppError::invalid_key(
+            "--key is only valid with RSA/PS/EC/EdDSA algorithms",
+        ));
+    }
+    if let Some(path) = key_spec.trim().strip_prefix("ssh:") {
+        let key = load_ssh_private_key(path, alg)?;
+        return Ok(Some((SigningKey::Local(key), "ssh".to_string())));
+    }
+    if let Some(external) = ExternalKeyRef::parse(key_spec)? {
+        let signer = open_external_signer(&external, alg)?;
+        return Ok(Some((SigningKey::External(signer), external.label())));
     }
+    let bytes = read_input_bytes(key_spec)?;
+    let format = request
+        .key_format
+        .unwrap_or_else(|| detect_key_format(&bytes));
+    let key = encoding_key_from_bytes(alg, &bytes, format)?;
+    Ok(Some((SigningKey::Local(key), "key".to_string())))
+}
 
-    let project = args
-        .project
-        .clone()
-        .ok_or_else(|| AppError::invalid_key("provide --project or a direct key input"))?;
-    let (_project_entry, key) =
-        resolve_project_key_single(vault, &project, &args.key_id, &args.key_name)?;
-    let expected_kind = expected_kind(Algorithm::from(args.alg));
-    if key.kind.to_lowercase() != expected_kind {
+fn resolve_project_encoding_key(
+    vault: &Vault,
+    project: &str,
+    request: &EncodeRequest,
+) -> AppResult<(SigningKey, String)> {
+    let (_project_entry, key) = resolve_project_key_single(
+        vault,
+        project,
+        &request.key_id,
+        &request.key_name,
+        &request.key_fingerprint,
+        KeyUsage::Sign,
+    )?;
+    let alg = Algorithm::from(request.alg);
+    // Platform keys store a reference; the keystore checks the algorithm when it opens the key.
+    let platform = key.kind.eq_ignore_ascii_case(PLATFORM_KIND);
+    let expected_kind = expected_kind(alg);
+    if !platform && key.kind.to_lowercase() != expected_kind {
         return Err(AppError::invalid_key(format!(
-            "key kind '{}' does not match algorithm {:?}",
-            key.kind,
-            Algorithm::from(args.alg)
+            "key kind '{}' does not match algorithm {alg:?}",
+            key.kind
         )));
     }
 
     let material = vault
         .get_key_material(&key.id)
         .map_err(|e| AppError::invalid_key(e.to_string()))?;
+    if platform {
+        let external = platform_key_ref(&material)?;
+        let signer = open_external_signer(&external, alg)?;
+        return Ok((SigningKey::External(signer), external.label()));
+    }
     let bytes = material.into_bytes();
     let format = detect_key_format(&bytes);
-    let key = encoding_key_from_bytes(Algorithm::from(args.alg), &bytes, format)?;
-    Ok((key, "vault".to_string()))
+    let key = encoding_key_from_bytes(alg, &bytes, format)?;
+    Ok((SigningKey::Local(key), "vault".to_string()))
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{resolve_verification_key_with_vault, KeySource};
-    use crate::cli::{JwtAlg, VerifyCommonArgs};
+    use super::{
+        resolve_encoding_key_with_vault, resolve_verification_key_with_vault, KeySource, KeyTrace,
+    };
+    use crate::cli::{JwtAlg, TimestampUnit, VerifyCommonArgs};
+    use crate::encode_request::EncodeRequest;
     use crate::jwt_ops::{self, VerifyOptions};
     use crate::vault::{KeyEntryInput, ProjectInput, Vault, VaultConfig};
     use jsonwebtoken::{Algorithm, EncodingKey, Header};
@@ -262,20 +669,27 @@ mod tests {
         VerifyCommonArgs {
             secret: None,
             key: None,
-            jwks: None,
+            jwks: Vec::new(),
             key_format: None,
             kid: None,
             allow_single_jwk: false,
             project: Some(project.to_string()),
             key_id: None,
             key_name: None,
+            key_fingerprint: None,
             try_all_keys: try_all,
+            auto_refresh: false,
             ignore_exp: false,
             leeway_secs: 30,
+            jwks_negative_ttl: std::time::Duration::from_secs(300),
+            timestamps: TimestampUnit::Auto,
             iss: None,
             sub: None,
             aud: Vec::new(),
             require: Vec::new(),
+            require_acr: Vec::new(),
+            max_auth_age: None,
+            check_revocation: false,
             explain: false,
             alg: Some(JwtAlg::HS256),
         }
@@ -289,8 +703,14 @@ mod tests {
 
         let token = make_token("secret1", Some("kid1"));
         let args = base_args("proj", false);
-        let source = resolve_verification_key_with_vault(&vault, &args, &token, Algorithm::HS256)
-            .expect("resolve key");
+        let source = resolve_verification_key_with_vault(
+            &vault,
+            &args,
+            &token,
+            Algorithm::HS256,
+            &mut KeyTrace::default(),
+        )
+        .expect("resolve key");
 
         match source {
             KeySource::Single(key, _) => {
@@ -298,10 +718,14 @@ mod tests {
                     alg: Algorithm::HS256,
                     leeway_secs: 0,
                     ignore_exp: true,
+                    ignore_nbf: false,
                     iss: None,
                     sub: None,
                     aud: Vec::new(),
                     require: Vec::new(),
+                    acr: Vec::new(),
+                    max_auth_age: None,
+                    timestamps: TimestampUnit::Auto,
                 };
                 let data = jwt_ops::verify_token(&token, &key, opts).expect("verify token");
                 assert_eq!(data.claims["sub"], "test");
@@ -318,8 +742,14 @@ mod tests {
 
         let token = make_token("secret1", Some("kid1"));
         let args = base_args("proj", true);
-        let source = resolve_verification_key_with_vault(&vault, &args, &token, Algorithm::HS256)
-            .expect("resolve key");
+        let source = resolve_verification_key_with_vault(
+            &vault,
+            &args,
+            &token,
+            Algorithm::HS256,
+            &mut KeyTrace::default(),
+        )
+        .expect("resolve key");
 
         match source {
             KeySource::Multiple(keys, _) => {
@@ -328,12 +758,17 @@ mod tests {
                     alg: Algorithm::HS256,
                     leeway_secs: 0,
                     ignore_exp: true,
+                    ignore_nbf: false,
                     iss: None,
                     sub: None,
                     aud: Vec::new(),
                     require: Vec::new(),
+                    acr: Vec::new(),
+                    max_auth_age: None,
+                    timestamps: TimestampUnit::Auto,
                 };
-                let data = jwt_ops::verify_token(&token, &keys[0], opts).expect("verify token");
+                assert_eq!(keys[0].1, "k1");
+                let data = jwt_ops::verify_token(&token, &keys[0].0, opts).expect("verify token");
                 assert_eq!(data.claims["sub"], "test");
             }
             _ => panic!("expected multiple keys"),
@@ -347,11 +782,43 @@ mod tests {
 
         let token = make_token("secret1", Some("missing"));
         let args = base_args("proj", false);
-        let err = match resolve_verification_key_with_vault(&vault, &args, &token, Algorithm::HS256)
-        {
+        let err = match resolve_verification_key_with_vault(
+            &vault,
+            &args,
+            &token,
+            Algorithm::HS256,
+            &mut KeyTrace::default(),
+        ) {
             Ok(_) => panic!("expected error"),
             Err(err) => err,
         };
         assert!(err.to_string().contains("no key with kid"));
     }
+
+    #[test]
+    fn platform_keys_sign_through_the_keystore_reference() {
+        let (vault, project_id) = build_vault();
+        vault
+            .add_key(KeyEntryInput {
+                project_id,
+                name: "enclave".to_string(),
+                kind: "platform".to_string(),
+                secret: "secure-enclave:jwt-dev".to_string(),
+                kid: None,
+                description: None,
+                tags: Vec::new(),
+            })
+            .expect("add key");
+
+        // The reference is opened as an external signer: RS256 is refused before any keystore
+        // call, where a PEM key would have failed the kind check instead.
+        let request = EncodeRequest::new(JwtAlg::RS256).project("proj");
+        let err = match resolve_encoding_key_with_vault(&vault, &request) {
+            Ok(_) => panic!("expected error"),
+            Err(err) => err,
+        };
+        assert!(err
+            .to_string()
+            .contains("secure-enclave:jwt-dev keys only support ES256"));
+    }
 }
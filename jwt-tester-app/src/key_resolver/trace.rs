@@ -0,0 +1,50 @@
+use serde_json::{json, Value};
+
+/// The `--explain` decision log of key resolution: every key source and vault key that was
+/// looked at, and whether it was used, skipped (with the reason) or failed to load. Recording is
+/// cheap, so resolution always fills it and callers drop it when `--explain` is off.
+#[derive(Debug, Default, Clone)]
+pub struct KeyTrace {
+    entries: Vec<Value>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(super) enum Decision {
+    Used,
+    Skipped,
+    Failed,
+}
+
+impl Decision {
+    fn as_str(self) -> &'static str {
+        match self {
+            Decision::Used => "used",
+            Decision::Skipped => "skipped",
+            Decision::Failed => "failed",
+        }
+    }
+}
+
+impl KeyTrace {
+    pub(super) fn record(
+        &mut self,
+        source: &str,
+        candidate: Option<&str>,
+        decision: Decision,
+        reason: impl Into<String>,
+    ) {
+        let mut entry = json!({
+            "source": source,
+            "decision": decision.as_str(),
+            "reason": reason.into(),
+        });
+        if let Some(candidate) = candidate {
+            entry["candidate"] = json!(candidate);
+        }
+        self.entries.push(entry);
+    }
+
+    pub fn to_json(&self) -> Value {
+        Value::Array(self.entries.clone())
+    }
+}
@@ -0,0 +1,77 @@
+//! Ed448 key generation (RFC 8032). The public key is one fixed-base scalar multiplication, done
+//! in constant time by `ed448-goldilocks`; this module only hashes and clamps the private key and
+//! wraps both halves in DER. Signing with Ed448 keys is not supported.
+
+use ed448_goldilocks::curve::edwards::ExtendedPoint;
+use ed448_goldilocks::Scalar;
+use sha3::digest::{ExtendableOutput, Update, XofReader};
+use sha3::Shake256;
+
+pub const KEY_LEN: usize = 57;
+
+/// DER prefix of a PKCS#8 `PrivateKeyInfo` for id-Ed448 (1.3.101.113); the raw key follows.
+const PKCS8_PREFIX: [u8; 16] = [
+    0x30, 0x47, 0x02, 0x01, 0x00, 0x30, 0x05, 0x06, 0x03, 0x2B, 0x65, 0x71, 0x04, 0x3B, 0x04, 0x39,
+];
+/// DER prefix of a `SubjectPublicKeyInfo` for id-Ed448; the raw public key follows.
+const SPKI_PREFIX: [u8; 12] = [
+    0x30, 0x43, 0x30, 0x05, 0x06, 0x03, 0x2B, 0x65, 0x71, 0x03, 0x3A, 0x00,
+];
+
+pub fn private_key_der(secret: &[u8; KEY_LEN]) -> Vec<u8> {
+    [PKCS8_PREFIX.as_slice(), secret].concat()
+}
+
+pub fn public_key_der(public: &[u8; KEY_LEN]) -> Vec<u8> {
+    [SPKI_PREFIX.as_slice(), public].concat()
+}
+
+/// Derives the encoded public key `[s]B` from a 57-byte private key.
+pub fn public_from_secret(secret: &[u8; KEY_LEN]) -> [u8; KEY_LEN] {
+    let mut digest = [0u8; 2 * KEY_LEN];
+    let mut hasher = Shake256::default();
+    hasher.update(secret);
+    hasher.finalize_xof().read(&mut digest);
+
+    let mut scalar = [0u8; KEY_LEN];
+    scalar.copy_from_slice(&digest[..KEY_LEN]);
+    scalar[0] &= 0xFC;
+    scalar[55] |= 0x80;
+    scalar[56] = 0;
+
+    // The clamped scalar is below 2^448, so it fits the low half of a wide reduction; reducing it
+    // modulo the group order leaves `[s]B` unchanged.
+    let mut wide = [0u8; 2 * KEY_LEN];
+    wide[..KEY_LEN].copy_from_slice(&scalar);
+    let scalar = Scalar::from_bytes_mod_order_wide(&wide);
+    ExtendedPoint::generator().scalar_mul(&scalar).compress().0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn public_key_matches_rfc8032_vectors() {
+        for (secret, expected) in [
+            (
+                "6c82a562cb808d10d632be89c8513ebf6c929f34ddfa8c9f63c9960ef6e348a3528c8a3fcc2f044e39a3fc5b94492f8f032e7549a20098f95b",
+                "5fd7449b59b461fd2ce787ec616ad46a1da1342485a70e1f8a0ea75d80e96778edf124769b46c7061bd6783df1e50f6cd1fa1abeafe8256180",
+            ),
+            (
+                "c4eab05d357007c632f3dbb48489924d552b08fe0c353a0d4a1f00acda2c463afbea67c5e8d2877c5e3bc397a659949ef8021e954e0a12274e",
+                "43ba28f430cdff456ae531545f7ecd0ac834a55d9358c0372bfa0c6c6798c0866aea01eb00742802b8438ea4cb82169c235160627b4c3a9480",
+            ),
+        ] {
+            let secret: [u8; KEY_LEN] = hex_bytes(secret).try_into().unwrap();
+            assert_eq!(public_from_secret(&secret).to_vec(), hex_bytes(expected));
+        }
+    }
+
+    fn hex_bytes(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+}
@@ -1,38 +1,153 @@
+mod advisor;
+mod anonymize;
+mod armor;
+mod bundle_recipients;
+mod claim_lint;
+mod claim_plugin;
 mod claims;
 mod cli;
 mod commands;
 mod date_utils;
+mod encode_request;
 mod error;
+mod file_assoc;
+mod history;
+mod http;
 mod io_utils;
+#[cfg(feature = "ui")]
+mod issuer;
 mod jwks;
 mod jwt_ops;
 mod key_resolver;
 #[cfg(feature = "keygen")]
 mod keygen;
+mod notify;
 mod output;
+mod presets;
+mod prompt;
+mod qr;
+mod sandbox;
+mod signer;
+mod summary;
+mod template;
+mod tenants;
 #[cfg(feature = "ui")]
 mod ui;
 mod vault;
 mod vault_export;
+mod verify_script;
+mod verify_service;
+mod workspace;
+mod x509;
 
-#[cfg(all(feature = "ui", feature = "cli-only"))]
-compile_error!("Features \"ui\" and \"cli-only\" are mutually exclusive. Build with default features for jwt-tester or with --no-default-features --features cli-only for jwt-tester-cli.");
+#[cfg(all(feature = "ui", feature = "cli"))]
+compile_error!("Features \"ui\" and \"cli\" (cli-only, no-vault, no-keygen, hmac-only) are mutually exclusive. Build with default features for jwt-tester or with --no-default-features --features cli-only for jwt-tester-cli.");
 
-use clap::Parser;
 use cli::{App, Command};
+use error::AppResult;
 use output::{emit_err, OutputConfig, OutputMode};
+use std::time::Duration;
+
+/// The enclosing `.jwt-tester` workspace, if any, and the command line parsed with its defaults.
+fn parse_app() -> (App, AppResult<Option<workspace::Loaded>>) {
+    let loaded = match std::env::current_dir() {
+        Ok(dir) => workspace::find(&dir),
+        Err(_) => Ok(None),
+    };
+    let app = workspace::parse_app(loaded.as_ref().ok().and_then(Option::as_ref));
+    (app, loaded)
+}
+
+/// Applies the workspace to the command, and returns it for `--rpc` to apply to each request.
+fn apply_workspace(
+    app: &mut App,
+    loaded: AppResult<Option<workspace::Loaded>>,
+    cfg: OutputConfig,
+) -> Option<workspace::Loaded> {
+    // `init --force` must be able to replace a broken workspace file.
+    if matches!(app.command, Some(Command::Init(_))) {
+        return None;
+    }
+    match loaded {
+        Ok(Some(loaded)) => {
+            if let Some(command) = &mut app.command {
+                workspace::apply(command, &loaded);
+            }
+            Some(loaded)
+        }
+        Ok(None) => None,
+        Err(err) => {
+            let code = err.exit_code();
+            emit_err(cfg, err);
+            std::process::exit(code);
+        }
+    }
+}
+
+/// `--vault-seed` fills the in-memory vault before any command opens it.
+fn seed_vault(app: &mut App, cfg: OutputConfig) {
+    let Some(bundle) = app.vault_seed.as_deref() else {
+        return;
+    };
+    app.no_persist = true;
+    if let Err(err) = commands::vault::seed(
+        bundle,
+        app.vault_seed_passphrase.as_deref(),
+        app.vault_seed_identity.as_deref(),
+    ) {
+        let code = err.exit_code();
+        emit_err(cfg, err);
+        std::process::exit(code);
+    }
+}
+
+/// How long queued vault webhooks may hold up the exit.
+const WEBHOOK_FLUSH_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Exits once queued vault webhooks are delivered, or after [`WEBHOOK_FLUSH_TIMEOUT`].
+fn exit(code: i32) -> ! {
+    let pending = vault::flush_webhooks(WEBHOOK_FLUSH_TIMEOUT);
+    if pending > 0 {
+        tracing::warn!("exiting with {pending} vault webhook delivery(ies) still pending");
+    }
+    std::process::exit(code)
+}
 
 fn build_output_config(app: &App) -> OutputConfig {
-    OutputConfig {
+    let mut cfg = OutputConfig {
         mode: if app.json {
             OutputMode::Json
+        } else if app.json_lines {
+            OutputMode::JsonLines
         } else {
             OutputMode::Text
         },
         quiet: app.quiet,
         no_color: app.no_color,
+        theme: app.theme,
         verbose: app.verbose,
+        template: None,
+        summary: match (&app.summary_file, &app.command) {
+            (Some(path), Some(command)) => Some(summary::SummaryFile::start(path.clone(), command)),
+            _ => None,
+        },
+        sinks: output::OutputSinks::start(
+            app.output_file.clone(),
+            app.output_webhook.clone(),
+            app.output_syslog,
+        ),
+    };
+    if let Some(spec) = app.template.as_deref() {
+        match template::load(spec) {
+            Ok(source) => cfg.template = Some(source),
+            Err(err) => {
+                let code = err.exit_code();
+                emit_err(cfg, err);
+                std::process::exit(code);
+            }
+        }
     }
+    cfg
 }
 
 #[cfg(feature = "ui")]
@@ -42,12 +157,23 @@ async fn main() {
         .with_env_filter(
             tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()),
         )
+        // Keep stdout for command output so warnings never corrupt --json.
+        .with_writer(std::io::stderr)
         .init();
 
-    let app = App::parse();
+    let (mut app, workspace) = parse_app();
+    http::set_offline(app.offline);
+    notify::set_notify_done(app.notify_done);
+    output::set_progress_hidden(app.quiet);
     let output_cfg = build_output_config(&app);
+    let workspace = apply_workspace(&mut app, workspace, output_cfg);
+    seed_vault(&mut app, output_cfg);
+    let Some(command) = app.command else {
+        let code = commands::rpc::run(app.no_persist, app.data_dir, workspace.as_ref(), output_cfg);
+        exit(code);
+    };
 
-    let exit_code = match app.command {
+    let exit_code = match command {
         Command::Ui(args) => {
             let run = ui::run_ui(
                 ui::UiConfig {
@@ -58,7 +184,13 @@ async fn main() {
                     data_dir: app.data_dir,
                     force_build: args.build,
                     dev_mode: args.dev,
+                    dev_port: args.dev_port,
+                    open_browser: args.open || args.open_file.is_some(),
+                    open_file: args.open_file,
+                    qr: args.qr,
                     npm_path: args.npm,
+                    record: args.record,
+                    allow_cors_inspect: args.allow_cors_inspect,
                 },
                 output_cfg,
             )
@@ -71,6 +203,29 @@ async fn main() {
                 }
             }
         }
+        Command::Issuer(args) => {
+            let cli::IssuerCmd::Serve(serve) = args.cmd;
+            let run = issuer::serve(app.no_persist, app.data_dir, serve, output_cfg).await;
+            match run {
+                Ok(()) => 0,
+                Err(err) => {
+                    emit_err(output_cfg, err.clone());
+                    err.exit_code()
+                }
+            }
+        }
+        Command::Serve(args) => {
+            let run =
+                commands::serve::run(app.no_persist, app.data_dir, workspace, args, output_cfg)
+                    .await;
+            match run {
+                Ok(()) => 0,
+                Err(err) => {
+                    emit_err(output_cfg, err.clone());
+                    err.exit_code()
+                }
+            }
+        }
         Command::Vault(args) => {
             commands::vault::run(app.no_persist, app.data_dir, args, output_cfg)
         }
@@ -81,14 +236,40 @@ async fn main() {
             commands::verify::run(app.no_persist, app.data_dir, args, output_cfg)
         }
         Command::Encode(args) => {
-            commands::encode::run(app.no_persist, app.data_dir, args, output_cfg)
+            commands::encode::run(app.no_persist, app.data_dir, *args, output_cfg)
         }
+        Command::History(args) => {
+            commands::history::run(app.no_persist, app.data_dir, args, output_cfg)
+        }
+        Command::Webhook(args) => {
+            commands::webhook::run(app.no_persist, app.data_dir, args, output_cfg)
+        }
+        Command::FetchToken(args) => {
+            commands::fetch_token::run(app.no_persist, app.data_dir, args, output_cfg)
+        }
+        Command::Monitor(args) => {
+            commands::monitor::run(app.no_persist, app.data_dir, args, output_cfg)
+        }
+        Command::Replay(args) => {
+            commands::replay::run(app.no_persist, app.data_dir, args, output_cfg)
+        }
+        Command::Init(args) => commands::init::run(app.no_persist, app.data_dir, args, output_cfg),
+        Command::Examples(args) => commands::examples::run(args, output_cfg),
         Command::Inspect(args) => commands::inspect::run(args, output_cfg),
+        Command::Analyze(args) => commands::analyze::run(args, output_cfg),
+        Command::Anonymize(args) => commands::anonymize::run(args, output_cfg),
+        Command::Lint(args) => commands::lint::run(args, output_cfg),
+        Command::Advise(args) => {
+            commands::advise::run(app.no_persist, app.data_dir, args, output_cfg)
+        }
+        Command::LintClaims(args) => commands::lint_claims::run(args, output_cfg),
         Command::Split(args) => commands::split::run(args, output_cfg),
         Command::Completion(args) => commands::completion::run(args),
+        #[cfg(feature = "self-update")]
+        Command::SelfUpdate(args) => commands::self_update::run(args, output_cfg),
     };
 
-    std::process::exit(exit_code);
+    exit(exit_code);
 }
 
 #[cfg(not(feature = "ui"))]
@@ -97,12 +278,23 @@ fn main() {
         .with_env_filter(
             tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()),
         )
+        // Keep stdout for command output so warnings never corrupt --json.
+        .with_writer(std::io::stderr)
         .init();
 
-    let app = App::parse();
+    let (mut app, workspace) = parse_app();
+    http::set_offline(app.offline);
+    notify::set_notify_done(app.notify_done);
+    output::set_progress_hidden(app.quiet);
     let output_cfg = build_output_config(&app);
+    let workspace = apply_workspace(&mut app, workspace, output_cfg);
+    seed_vault(&mut app, output_cfg);
+    let Some(command) = app.command else {
+        let code = commands::rpc::run(app.no_persist, app.data_dir, workspace.as_ref(), output_cfg);
+        exit(code);
+    };
 
-    let exit_code = match app.command {
+    let exit_code = match command {
         Command::Vault(args) => {
             commands::vault::run(app.no_persist, app.data_dir, args, output_cfg)
         }
@@ -113,12 +305,38 @@ fn main() {
             commands::verify::run(app.no_persist, app.data_dir, args, output_cfg)
         }
         Command::Encode(args) => {
-            commands::encode::run(app.no_persist, app.data_dir, args, output_cfg)
+            commands::encode::run(app.no_persist, app.data_dir, *args, output_cfg)
         }
+        Command::History(args) => {
+            commands::history::run(app.no_persist, app.data_dir, args, output_cfg)
+        }
+        Command::Webhook(args) => {
+            commands::webhook::run(app.no_persist, app.data_dir, args, output_cfg)
+        }
+        Command::FetchToken(args) => {
+            commands::fetch_token::run(app.no_persist, app.data_dir, args, output_cfg)
+        }
+        Command::Monitor(args) => {
+            commands::monitor::run(app.no_persist, app.data_dir, args, output_cfg)
+        }
+        Command::Replay(args) => {
+            commands::replay::run(app.no_persist, app.data_dir, args, output_cfg)
+        }
+        Command::Init(args) => commands::init::run(app.no_persist, app.data_dir, args, output_cfg),
+        Command::Examples(args) => commands::examples::run(args, output_cfg),
         Command::Inspect(args) => commands::inspect::run(args, output_cfg),
+        Command::Analyze(args) => commands::analyze::run(args, output_cfg),
+        Command::Anonymize(args) => commands::anonymize::run(args, output_cfg),
+        Command::Lint(args) => commands::lint::run(args, output_cfg),
+        Command::Advise(args) => {
+            commands::advise::run(app.no_persist, app.data_dir, args, output_cfg)
+        }
+        Command::LintClaims(args) => commands::lint_claims::run(args, output_cfg),
         Command::Split(args) => commands::split::run(args, output_cfg),
         Command::Completion(args) => commands::completion::run(args),
+        #[cfg(feature = "self-update")]
+        Command::SelfUpdate(args) => commands::self_update::run(args, output_cfg),
     };
 
-    std::process::exit(exit_code);
+    exit(exit_code);
 }
@@ -0,0 +1,100 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+static NOTIFY_DONE: AtomicBool = AtomicBool::new(false);
+
+const TITLE: &str = "jwt-tester";
+
+/// Set once from `--notify-done`; long operations report their completion afterwards.
+pub fn set_notify_done(enabled: bool) {
+    NOTIFY_DONE.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether `--notify-done` is on.
+pub fn notify_done() -> bool {
+    NOTIFY_DONE.load(Ordering::Relaxed)
+}
+
+/// Tells the user a long operation has finished, successfully or not, so they can switch back.
+/// Does nothing without `--notify-done`; a notifier that fails only logs a warning.
+pub fn operation_finished(operation: &str, started: Instant, ok: bool) {
+    if !NOTIFY_DONE.load(Ordering::Relaxed) {
+        return;
+    }
+    if let Err(err) = desktop(TITLE, &completion_message(operation, started.elapsed(), ok)) {
+        warn!("failed to show desktop notification: {err}");
+    }
+}
+
+fn completion_message(operation: &str, elapsed: Duration, ok: bool) -> String {
+    let outcome = if ok { "finished" } else { "failed" };
+    format!("{operation} {outcome} after {:.1}s", elapsed.as_secs_f64())
+}
+
+/// Shows one desktop notification. Built with the `notify` feature this talks to the platform
+/// notification service directly (notify-rust); otherwise it shells out to `notify-send` or
+/// `osascript`.
+#[cfg(feature = "notify")]
+pub fn desktop(title: &str, body: &str) -> std::io::Result<()> {
+    notify_rust::Notification::new()
+        .appname(TITLE)
+        .summary(title)
+        .body(body)
+        .show()
+        .map(|_| ())
+        .map_err(std::io::Error::other)
+}
+
+#[cfg(not(feature = "notify"))]
+pub fn desktop(title: &str, body: &str) -> std::io::Result<()> {
+    use std::process::{Command, Stdio};
+
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = Command::new("osascript");
+        command.args([
+            "-e",
+            &format!("display notification {body:?} with title {title:?}"),
+        ]);
+        command
+    } else if cfg!(unix) {
+        let mut command = Command::new("notify-send");
+        command.args([title, body]);
+        command
+    } else {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "desktop notifications are not supported on this platform",
+        ));
+    };
+    let status = command.stdout(Stdio::null()).status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(std::io::Error::other(format!(
+            "notifier exited with {status}"
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::completion_message;
+    use std::time::Duration;
+
+    #[test]
+    fn completion_message_reports_outcome_and_duration() {
+        assert_eq!(
+            completion_message(
+                "RSA-4096 key generation",
+                Duration::from_millis(12_340),
+                true
+            ),
+            "RSA-4096 key generation finished after 12.3s"
+        );
+        assert_eq!(
+            completion_message("vault import", Duration::from_millis(1_500), false),
+            "vault import failed after 1.5s"
+        );
+    }
+}
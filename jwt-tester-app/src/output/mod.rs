@@ -0,0 +1,468 @@
+use crate::cli::{ListFormat, Theme};
+use crate::error::{AppError, AppResult};
+use crate::summary::SummaryFile;
+use crate::template;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::io::{IsTerminal, Write};
+
+mod progress;
+mod redact;
+mod sinks;
+
+pub use progress::{set_progress_hidden, Progress, CANCELLED_EXIT_CODE};
+pub use redact::redact_secrets;
+#[cfg(feature = "ui")]
+pub use redact::REDACTED;
+pub use sinks::OutputSinks;
+
+#[derive(Debug, Clone, Copy)]
+pub enum OutputMode {
+    Json,
+    /// `--json-lines`: compact objects, one per line, flushed as they are produced.
+    JsonLines,
+    Text,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct OutputConfig {
+    pub mode: OutputMode,
+    pub quiet: bool,
+    pub no_color: bool,
+    pub theme: Theme,
+    pub verbose: bool,
+    /// `--template` source, already syntax-checked.
+    pub template: Option<&'static str>,
+    /// `--summary-file` target, written by `emit_ok`/`emit_err`.
+    pub summary: Option<&'static SummaryFile>,
+    /// `--output-file`/`--output-webhook`/`--output-syslog`, mirrored by `emit_ok`/`emit_err`.
+    pub sinks: Option<&'static OutputSinks>,
+}
+
+/// Semantic color of a piece of text output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tone {
+    Valid,
+    Expired,
+    Warning,
+    Error,
+}
+
+impl OutputConfig {
+    /// `--json-lines` without `--template` (a template replaces the whole output).
+    pub fn json_lines(&self) -> bool {
+        matches!(self.mode, OutputMode::JsonLines) && self.template.is_none()
+    }
+
+    /// Colors need a theme, no `--no-color`/`NO_COLOR`, and a terminal on the target stream
+    /// (or `CLICOLOR_FORCE`).
+    fn colors_enabled(&self, is_terminal: bool) -> bool {
+        let env_set = |name: &str| std::env::var_os(name).is_some_and(|v| !v.is_empty());
+        let forced = std::env::var_os("CLICOLOR_FORCE").is_some_and(|v| !v.is_empty() && v != "0");
+        !self.no_color
+            && self.theme != Theme::None
+            && !env_set("NO_COLOR")
+            && (is_terminal || forced)
+    }
+
+    fn tone_code(&self, tone: Tone) -> &'static str {
+        match (self.theme, tone) {
+            (Theme::Bright, Tone::Valid) => "92",
+            (Theme::Bright, Tone::Expired | Tone::Error) => "91",
+            (Theme::Bright, Tone::Warning) => "93",
+            (_, Tone::Valid) => "32",
+            (_, Tone::Expired | Tone::Error) => "31",
+            (_, Tone::Warning) => "33",
+        }
+    }
+
+    fn paint_for(&self, is_terminal: bool, tone: Tone, text: &str) -> String {
+        if self.colors_enabled(is_terminal) {
+            format!("\u{1b}[{}m{text}\u{1b}[0m", self.tone_code(tone))
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+/// Colors text that goes to stdout (text mode only; JSON output is never colored).
+pub fn paint(cfg: OutputConfig, tone: Tone, text: &str) -> String {
+    if !matches!(cfg.mode, OutputMode::Text) {
+        return text.to_string();
+    }
+    cfg.paint_for(std::io::stdout().is_terminal(), tone, text)
+}
+
+#[derive(Debug)]
+pub struct CommandOutput {
+    pub data: Value,
+    pub text: String,
+    /// Key of the result array in `data` that `--json-lines` prints one element per line.
+    pub records: Option<&'static str>,
+}
+
+impl CommandOutput {
+    pub fn new(data: Value, text: impl Into<String>) -> Self {
+        Self {
+            data,
+            text: text.into(),
+            records: None,
+        }
+    }
+
+    pub fn with_records(mut self, key: &'static str) -> Self {
+        self.records = Some(key);
+        self
+    }
+}
+
+/// Writes one line and flushes it, so a consumer reading a pipe sees each result as it lands.
+fn write_line(line: &str) {
+    let mut stdout = std::io::stdout().lock();
+    // A closed pipe (`| head`) is not worth a panic; the exit code still reports the result.
+    let _ = writeln!(stdout, "{line}").and_then(|_| stdout.flush());
+}
+
+/// Prints one result of a batch as soon as it is known (`--json-lines` only). Commands that
+/// stream their results this way leave them out of the final `data`.
+pub fn emit_record(cfg: OutputConfig, record: &Value) {
+    if cfg.json_lines() {
+        write_line(&record.to_string());
+    }
+}
+
+/// Prints a successful result. The `--template` is rendered first: when it fails nothing has
+/// been written yet, and the error goes back to the caller to report through [`emit_err`].
+pub fn emit_ok(cfg: OutputConfig, mut output: CommandOutput) -> AppResult<()> {
+    let rendered = cfg
+        .template
+        .map(|source| template::render(source, &output.data))
+        .transpose()?;
+    if let Some(summary) = cfg.summary {
+        summary.write_ok();
+    }
+    if let Some(sinks) = cfg.sinks {
+        sinks.publish(&json!({ "ok": true, "data": output.data }));
+    }
+    if let Some(rendered) = rendered {
+        println!("{rendered}");
+        return Ok(());
+    }
+    match cfg.mode {
+        OutputMode::Json => {
+            let body = json!({
+                "ok": true,
+                "data": output.data,
+            });
+            println!("{}", body);
+        }
+        OutputMode::JsonLines => {
+            let records = output
+                .records
+                .filter(|key| output.data[*key].is_array())
+                .and_then(|key| output.data.as_object_mut()?.remove(key));
+            if let Some(Value::Array(records)) = records {
+                for record in records {
+                    write_line(&record.to_string());
+                }
+            }
+            write_line(&json!({ "ok": true, "data": output.data }).to_string());
+        }
+        OutputMode::Text => {
+            if !output.text.is_empty() {
+                println!("{}", output.text);
+            } else if !cfg.quiet {
+                println!("OK");
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn emit_err(cfg: OutputConfig, err: AppError) {
+    if let Some(summary) = cfg.summary {
+        summary.write_err(&err);
+    }
+    if let Some(sinks) = cfg.sinks {
+        sinks.publish(&err.as_json());
+    }
+    match cfg.mode {
+        OutputMode::Json => {
+            println!("{}", err.as_json());
+        }
+        OutputMode::JsonLines => write_line(&err.as_json().to_string()),
+        OutputMode::Text => {
+            let prefix = if cfg.verbose {
+                format!("[{}] ", err.code())
+            } else {
+                String::new()
+            };
+            let message = format!("{prefix}{err}");
+            eprintln!(
+                "{}",
+                cfg.paint_for(std::io::stderr().is_terminal(), Tone::Error, &message)
+            );
+        }
+    }
+}
+
+/// Listing rows rendered for `--format table|csv|json-lines`.
+#[derive(Debug)]
+pub struct Table {
+    columns: Vec<String>,
+    rows: Vec<Map<String, Value>>,
+}
+
+impl Table {
+    /// Builds a table from serializable records. `selected` picks columns (in that order);
+    /// when empty, `default_columns` are used.
+    pub fn new<T: Serialize>(
+        records: &[T],
+        default_columns: &[&str],
+        selected: &[String],
+    ) -> AppResult<Self> {
+        let rows = records
+            .iter()
+            .map(|record| match serde_json::to_value(record) {
+                Ok(Value::Object(row)) => Ok(row),
+                Ok(_) => Err(AppError::internal("table rows must be JSON objects")),
+                Err(e) => Err(AppError::internal(format!("serialize row: {e}"))),
+            })
+            .collect::<AppResult<Vec<_>>>()?;
+        let columns: Vec<String> = if selected.is_empty() {
+            default_columns.iter().map(|c| c.to_string()).collect()
+        } else {
+            selected.iter().map(|c| c.trim().to_string()).collect()
+        };
+        for column in &columns {
+            if !default_columns.contains(&column.as_str())
+                && !rows.iter().any(|row| row.contains_key(column))
+            {
+                return Err(AppError::invalid_key(format!(
+                    "unknown column '{column}' (available: {})",
+                    default_columns.join(", ")
+                )));
+            }
+        }
+        Ok(Self { columns, rows })
+    }
+
+    pub fn render(&self, format: ListFormat) -> String {
+        match format {
+            ListFormat::Table => self.to_text(),
+            ListFormat::Csv => self.to_csv(),
+            ListFormat::JsonLines => self.to_json_lines(),
+        }
+    }
+
+    fn cells(&self, empty: &str) -> Vec<Vec<String>> {
+        self.rows
+            .iter()
+            .map(|row| {
+                self.columns
+                    .iter()
+                    .map(|column| match row.get(column) {
+                        None | Some(Value::Null) => empty.to_string(),
+                        Some(Value::String(s)) => s.clone(),
+                        Some(Value::Array(items)) => items
+                            .iter()
+                            .map(|item| {
+                                item.as_str()
+                                    .map(str::to_string)
+                                    .unwrap_or(item.to_string())
+                            })
+                            .collect::<Vec<_>>()
+                            .join(","),
+                        Some(other) => other.to_string(),
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn to_text(&self) -> String {
+        let header: Vec<String> = self.columns.iter().map(|c| c.to_uppercase()).collect();
+        let cells = self.cells("-");
+        let widths: Vec<usize> = (0..header.len())
+            .map(|idx| {
+                cells
+                    .iter()
+                    .map(|row| row[idx].chars().count())
+                    .chain([header[idx].len()])
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+        std::iter::once(header)
+            .chain(cells)
+            .map(|row| {
+                let line = row
+                    .iter()
+                    .zip(&widths)
+                    .map(|(cell, width)| format!("{cell:<width$}"))
+                    .collect::<Vec<_>>()
+                    .join("  ");
+                line.trim_end().to_string()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn to_csv(&self) -> String {
+        std::iter::once(self.columns.clone())
+            .chain(self.cells(""))
+            .map(|row| {
+                row.iter()
+                    .map(|cell| csv_field(cell))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn to_json_lines(&self) -> String {
+        self.rows
+            .iter()
+            .map(|row| {
+                let selected: Map<String, Value> = self
+                    .columns
+                    .iter()
+                    .map(|column| {
+                        let value = row.get(column).cloned().unwrap_or(Value::Null);
+                        (column.clone(), value)
+                    })
+                    .collect();
+                Value::Object(selected).to_string()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Quotes a CSV field when needed (RFC 4180).
+fn csv_field(cell: &str) -> String {
+    if cell.contains([',', '"', '\n', '\r']) || cell.trim() != cell {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::AppError;
+
+    #[test]
+    fn emit_ok_json_and_text_do_not_panic() {
+        let cfg = OutputConfig {
+            mode: OutputMode::Json,
+            quiet: false,
+            no_color: true,
+            theme: Theme::Default,
+            verbose: false,
+            template: None,
+            summary: None,
+            sinks: None,
+        };
+        emit_ok(cfg, CommandOutput::new(json!({ "ok": true }), "OK")).expect("emit");
+
+        let cfg = OutputConfig {
+            mode: OutputMode::Text,
+            quiet: true,
+            no_color: true,
+            theme: Theme::Default,
+            verbose: false,
+            template: None,
+            summary: None,
+            sinks: None,
+        };
+        emit_ok(cfg, CommandOutput::new(json!({}), "")).expect("emit");
+    }
+
+    #[test]
+    fn paint_follows_theme_and_no_color() {
+        let mut cfg = OutputConfig {
+            mode: OutputMode::Text,
+            quiet: false,
+            no_color: false,
+            theme: Theme::Default,
+            verbose: false,
+            template: None,
+            summary: None,
+            sinks: None,
+        };
+        if std::env::var_os("NO_COLOR").is_none() {
+            assert_eq!(
+                cfg.paint_for(true, Tone::Valid, "OK"),
+                "\u{1b}[32mOK\u{1b}[0m"
+            );
+            cfg.theme = Theme::Bright;
+            assert_eq!(
+                cfg.paint_for(true, Tone::Warning, "hm"),
+                "\u{1b}[93mhm\u{1b}[0m"
+            );
+        }
+        cfg.theme = Theme::None;
+        assert_eq!(cfg.paint_for(true, Tone::Error, "bad"), "bad");
+        cfg.theme = Theme::Default;
+        cfg.no_color = true;
+        assert_eq!(cfg.paint_for(true, Tone::Error, "bad"), "bad");
+    }
+
+    #[test]
+    fn table_renders_text_csv_and_json_lines() {
+        let records = vec![
+            json!({ "id": "1", "name": "alpha", "tags": ["a", "b"], "kid": null }),
+            json!({ "id": "22", "name": "b,c", "tags": [], "kid": "k1" }),
+        ];
+        let table = Table::new(&records, &["id", "name", "tags", "kid"], &[]).expect("table");
+        assert_eq!(
+            table.render(ListFormat::Table),
+            "ID  NAME   TAGS  KID\n1   alpha  a,b   -\n22  b,c          k1"
+        );
+        assert_eq!(
+            table.render(ListFormat::Csv),
+            "id,name,tags,kid\n1,alpha,\"a,b\",\n22,\"b,c\",,k1"
+        );
+
+        let selected = ["name".to_string(), "id".to_string()];
+        let table = Table::new(&records, &["id", "name"], &selected).expect("table");
+        assert_eq!(
+            table.render(ListFormat::JsonLines).lines().next(),
+            Some(r#"{"name":"alpha","id":"1"}"#)
+        );
+
+        let err = Table::new(&records, &["id"], &["secret".to_string()]).expect_err("column");
+        assert!(err.to_string().contains("unknown column 'secret'"));
+    }
+
+    #[test]
+    fn emit_err_json_and_text_do_not_panic() {
+        let err = AppError::invalid_token("bad token");
+        let cfg = OutputConfig {
+            mode: OutputMode::Json,
+            quiet: false,
+            no_color: true,
+            theme: Theme::Default,
+            verbose: false,
+            template: None,
+            summary: None,
+            sinks: None,
+        };
+        emit_err(cfg, err.clone());
+
+        let cfg = OutputConfig {
+            mode: OutputMode::Text,
+            quiet: false,
+            no_color: true,
+            theme: Theme::Default,
+            verbose: true,
+            template: None,
+            summary: None,
+            sinks: None,
+        };
+        emit_err(cfg, err);
+    }
+}
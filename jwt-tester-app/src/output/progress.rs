@@ -0,0 +1,90 @@
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Once;
+use std::time::Duration;
+use tracing::warn;
+
+/// Exit code for a run stopped with Ctrl+C after reporting its partial results (128 + SIGINT).
+pub const CANCELLED_EXIT_CODE: i32 = 130;
+
+static HIDDEN: AtomicBool = AtomicBool::new(false);
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+static HANDLER: Once = Once::new();
+
+/// Set once from `--quiet`. Progress is drawn on stderr, and only when stderr is a terminal,
+/// so it never mixes with `--json` output or ends up in logs.
+pub fn set_progress_hidden(hidden: bool) {
+    HIDDEN.store(hidden, Ordering::Relaxed);
+}
+
+/// Progress of one batch operation. Dropping it clears the bar.
+pub struct Progress {
+    bar: ProgressBar,
+}
+
+impl Progress {
+    /// A bar over `len` items that the caller can cancel: the first Ctrl+C only sets
+    /// [`Progress::cancelled`] so the loop stops before its next item and reports what it has;
+    /// a second Ctrl+C exits immediately.
+    pub fn items(len: usize, message: &str) -> Self {
+        install_interrupt_handler();
+        let bar = ProgressBar::with_draw_target(Some(len as u64), draw_target());
+        bar.set_style(
+            ProgressStyle::with_template("{msg} [{bar:30}] {pos}/{len} ({elapsed})")
+                .unwrap_or_else(|_| ProgressStyle::default_bar())
+                .progress_chars("=> "),
+        );
+        bar.set_message(message.to_string());
+        Progress { bar }
+    }
+
+    /// A spinner for one long step that cannot stop halfway (e.g. encrypting a bundle). Ctrl+C
+    /// keeps its default behavior here.
+    pub fn spinner(message: &str) -> Self {
+        let bar = ProgressBar::with_draw_target(None, draw_target());
+        bar.set_style(
+            ProgressStyle::with_template("{spinner} {msg} ({elapsed})")
+                .unwrap_or_else(|_| ProgressStyle::default_spinner()),
+        );
+        bar.set_message(message.to_string());
+        bar.enable_steady_tick(Duration::from_millis(120));
+        Progress { bar }
+    }
+
+    pub fn inc(&self) {
+        self.bar.inc(1);
+    }
+
+    /// Whether Ctrl+C was pressed since the batch started.
+    pub fn cancelled(&self) -> bool {
+        INTERRUPTED.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        self.bar.finish_and_clear();
+    }
+}
+
+fn draw_target() -> ProgressDrawTarget {
+    if HIDDEN.load(Ordering::Relaxed) || !std::io::stderr().is_terminal() {
+        ProgressDrawTarget::hidden()
+    } else {
+        ProgressDrawTarget::stderr()
+    }
+}
+
+fn install_interrupt_handler() {
+    HANDLER.call_once(|| {
+        let installed = ctrlc::set_handler(|| {
+            if INTERRUPTED.swap(true, Ordering::Relaxed) {
+                std::process::exit(CANCELLED_EXIT_CODE);
+            }
+        });
+        if let Err(err) = installed {
+            warn!("failed to install Ctrl+C handler; batch cannot be cancelled: {err}");
+        }
+    });
+}
@@ -0,0 +1,33 @@
+use serde_json::{json, Value};
+
+/// Field names whose values never leave the process through a side channel (output sinks, the
+/// `ui --record` session file), at any depth.
+const SECRET_FIELDS: [&str; 8] = [
+    "secret",
+    "client_secret",
+    "password",
+    "passphrase",
+    "pin",
+    "private_key",
+    "key_material",
+    "material",
+];
+pub const REDACTED: &str = "<redacted>";
+
+/// Replaces the value of every secret-named field (case-insensitive) with [`REDACTED`].
+/// `null` stays `null`, so "not set" is still visible.
+pub fn redact_secrets(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (name, item) in map.iter_mut() {
+                if SECRET_FIELDS.contains(&name.to_ascii_lowercase().as_str()) && !item.is_null() {
+                    *item = json!(REDACTED);
+                } else {
+                    redact_secrets(item);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact_secrets),
+        _ => {}
+    }
+}
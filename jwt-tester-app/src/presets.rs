@@ -0,0 +1,261 @@
+use crate::claims::{now_epoch, parse_time};
+use crate::cli::{EncodePreset, PresetArgs, VerifyProfile};
+use crate::error::{AppError, AppResult};
+use crate::http;
+use jsonwebtoken::Header;
+use serde_json::{json, Map, Value};
+use uuid::Uuid;
+
+/// Explicit `typ` for JWT-secured authorization requests (RFC 9101 §10.8).
+pub const REQUEST_OBJECT_TYP: &str = "oauth-authz-req+jwt";
+const REQUEST_OBJECT_LIFETIME: &str = "+5m";
+
+/// Header `typ` the preset uses when neither `--typ` nor `--no-typ` is given. `Some(None)` means
+/// the preset leaves `typ` out.
+pub fn preset_typ(args: &PresetArgs) -> Option<Option<&'static str>> {
+    match args.preset? {
+        EncodePreset::RequestObject => Some(Some(REQUEST_OBJECT_TYP)),
+        EncodePreset::Acme => Some(None),
+    }
+}
+
+/// Fills preset claims into the base claims object. Preset flags (`--client-id`, `--scope`, ...)
+/// replace values from the claims JSON; generated defaults only fill claims that are missing.
+/// Standard claim flags, claim files and `--claim` are applied afterwards and still win.
+pub fn apply_preset(args: &PresetArgs, base: &mut Value) -> AppResult<()> {
+    let Some(preset) = args.preset else {
+        return Ok(());
+    };
+    let obj = base
+        .as_object_mut()
+        .ok_or_else(|| AppError::invalid_claims("claims JSON must be a JSON object"))?;
+    match preset {
+        EncodePreset::RequestObject => fill_request_object(args, obj),
+        // The ACME payload is the request body as given.
+        EncodePreset::Acme => Ok(()),
+    }
+}
+
+/// Completes the extra protected header parameters for the preset. For ACME a missing `nonce`
+/// is fetched from `--nonce-url`; `url` and exactly one of `jwk`/`kid` are required.
+pub fn apply_preset_header(
+    args: &PresetArgs,
+    header: &Header,
+    params: &mut Map<String, Value>,
+) -> AppResult<()> {
+    if args.preset != Some(EncodePreset::Acme) {
+        return Ok(());
+    }
+    if !params.contains_key("nonce") {
+        let url = args.nonce_url.as_deref().ok_or_else(|| {
+            AppError::invalid_claims(
+                "--preset acme requires --header-param nonce=... or --nonce-url <newNonce URL>",
+            )
+        })?;
+        params.insert("nonce".to_string(), json!(http::fetch_nonce(url)?));
+    }
+    if !params.contains_key("url") {
+        return Err(AppError::invalid_claims(
+            "--preset acme requires --header-param url=<request URL>",
+        ));
+    }
+    let has_jwk = header.jwk.is_some() || params.contains_key("jwk");
+    let has_kid = header.kid.is_some() || params.contains_key("kid");
+    if has_jwk == has_kid {
+        return Err(AppError::invalid_claims(
+            "--preset acme requires exactly one of --kid <account URL> or a jwk header",
+        ));
+    }
+    Ok(())
+}
+
+/// Checks the final claims for what the preset cannot default.
+pub fn check_preset_claims(args: &PresetArgs, claims: &Value) -> AppResult<()> {
+    match args.preset {
+        Some(EncodePreset::RequestObject) if claims.get("aud").is_none() => {
+            Err(AppError::invalid_claims(
+                "--preset request-object requires --aud (the authorization server's issuer)",
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn fill_request_object(args: &PresetArgs, obj: &mut Map<String, Value>) -> AppResult<()> {
+    let client_id = args
+        .client_id
+        .as_deref()
+        .or_else(|| obj.get("client_id").and_then(Value::as_str))
+        .map(str::to_string)
+        .ok_or_else(|| AppError::invalid_claims("--preset request-object requires --client-id"))?;
+
+    let explicit = [
+        ("client_id", Some(&client_id)),
+        ("response_type", args.response_type.as_ref()),
+        ("redirect_uri", args.redirect_uri.as_ref()),
+        ("scope", args.scope.as_ref()),
+        ("state", args.state.as_ref()),
+        ("nonce", args.nonce.as_ref()),
+    ];
+    for (name, value) in explicit {
+        if let Some(value) = value {
+            obj.insert(name.to_string(), json!(value));
+        }
+    }
+
+    let now = now_epoch();
+    let defaults = [
+        ("iss", json!(client_id)),
+        ("response_type", json!("code")),
+        ("iat", json!(now)),
+        ("nbf", json!(now)),
+        ("exp", json!(parse_time(REQUEST_OBJECT_LIFETIME, now)?)),
+        ("jti", json!(Uuid::new_v4().to_string())),
+    ];
+    for (name, value) in defaults {
+        obj.entry(name.to_string()).or_insert(value);
+    }
+    Ok(())
+}
+
+/// Applies a verify profile to an already verified token.
+pub fn check_profile(
+    profile: VerifyProfile,
+    header: &Header,
+    claims: &Value,
+    client_id: Option<&str>,
+) -> AppResult<()> {
+    match profile {
+        VerifyProfile::RequestObject => check_request_object(header, claims, client_id),
+    }
+}
+
+/// JAR rules (RFC 9101): explicit typing, `client_id` present and matching `iss`, an audience,
+/// and no nested `request`/`request_uri`.
+fn check_request_object(header: &Header, claims: &Value, client_id: Option<&str>) -> AppResult<()> {
+    let typ = header.typ.as_deref().unwrap_or_default();
+    let typ = typ.strip_prefix("application/").unwrap_or(typ);
+    if !typ.eq_ignore_ascii_case(REQUEST_OBJECT_TYP) {
+        return Err(AppError::invalid_token(format!(
+            "request object typ must be '{REQUEST_OBJECT_TYP}' (got {})",
+            header.typ.as_deref().unwrap_or("none")
+        )));
+    }
+
+    let claimed = claims
+        .get("client_id")
+        .and_then(Value::as_str)
+        .ok_or_else(|| AppError::invalid_claims("request object is missing client_id"))?;
+    if let Some(expected) = client_id {
+        if claimed != expected {
+            return Err(AppError::invalid_claims(format!(
+                "request object client_id '{claimed}' does not match expected '{expected}'"
+            )));
+        }
+    }
+    if let Some(iss) = claims.get("iss") {
+        if iss.as_str() != Some(claimed) {
+            return Err(AppError::invalid_claims(format!(
+                "request object iss {iss} must equal client_id '{claimed}'"
+            )));
+        }
+    }
+    if claims.get("aud").is_none() {
+        return Err(AppError::invalid_claims("request object is missing aud"));
+    }
+    for nested in ["request", "request_uri"] {
+        if claims.get(nested).is_some() {
+            return Err(AppError::invalid_claims(format!(
+                "request object must not contain a '{nested}' claim"
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_preset, apply_preset_header, check_profile, REQUEST_OBJECT_TYP};
+    use crate::cli::{EncodePreset, PresetArgs, VerifyProfile};
+    use crate::error::ErrorKind;
+    use jsonwebtoken::{Algorithm, Header};
+    use serde_json::{json, Map};
+
+    fn request_object_args() -> PresetArgs {
+        PresetArgs {
+            preset: Some(EncodePreset::RequestObject),
+            client_id: Some("client-1".to_string()),
+            scope: Some("openid profile".to_string()),
+            ..PresetArgs::default()
+        }
+    }
+
+    #[test]
+    fn request_object_preset_fills_defaults_without_overriding_claims() {
+        let mut base = json!({ "exp": 42, "scope": "from-json" });
+        apply_preset(&request_object_args(), &mut base).expect("preset");
+        assert_eq!(base["client_id"], "client-1");
+        assert_eq!(base["iss"], "client-1");
+        assert_eq!(base["response_type"], "code");
+        assert_eq!(base["scope"], "openid profile");
+        assert_eq!(base["exp"], 42);
+        assert!(base["jti"].is_string());
+        assert!(base["iat"].is_i64());
+
+        let err = apply_preset(
+            &PresetArgs {
+                client_id: None,
+                ..request_object_args()
+            },
+            &mut json!({}),
+        )
+        .expect_err("missing client_id");
+        assert!(err.to_string().contains("--client-id"));
+    }
+
+    #[test]
+    fn acme_preset_header_requires_url_and_one_key_reference() {
+        let args = PresetArgs {
+            preset: Some(EncodePreset::Acme),
+            ..PresetArgs::default()
+        };
+        let mut header = Header::new(Algorithm::ES256);
+        header.kid = Some("https://acme/acct/1".to_string());
+        let mut params = Map::new();
+        params.insert("nonce".to_string(), json!("n-1"));
+        let err = apply_preset_header(&args, &header, &mut params).expect_err("url");
+        assert!(err.to_string().contains("url="));
+
+        params.insert("url".to_string(), json!("https://acme/new-order"));
+        apply_preset_header(&args, &header, &mut params).expect("kid only");
+        params.insert("jwk".to_string(), json!({ "kty": "EC" }));
+        assert!(apply_preset_header(&args, &header, &mut params).is_err());
+
+        let err = apply_preset_header(&args, &header, &mut Map::new()).expect_err("nonce");
+        assert!(err.to_string().contains("--nonce-url"));
+    }
+
+    #[test]
+    fn request_object_profile_applies_jar_rules() {
+        let mut header = Header::new(Algorithm::RS256);
+        header.typ = Some(REQUEST_OBJECT_TYP.to_string());
+        let claims = json!({ "iss": "client-1", "client_id": "client-1", "aud": "https://as" });
+        let profile = VerifyProfile::RequestObject;
+
+        check_profile(profile, &header, &claims, Some("client-1")).expect("valid");
+        let err = check_profile(profile, &header, &claims, Some("other")).expect_err("client");
+        assert!(matches!(err.kind, ErrorKind::InvalidClaims));
+
+        let nested =
+            json!({ "client_id": "client-1", "aud": "https://as", "request_uri": "urn:x" });
+        let err = check_profile(profile, &header, &nested, None).expect_err("nested");
+        assert!(err.to_string().contains("request_uri"));
+
+        let wrong_iss = json!({ "iss": "someone", "client_id": "client-1", "aud": "https://as" });
+        assert!(check_profile(profile, &header, &wrong_iss, None).is_err());
+
+        header.typ = Some("JWT".to_string());
+        let err = check_profile(profile, &header, &claims, None).expect_err("typ");
+        assert!(matches!(err.kind, ErrorKind::InvalidToken));
+    }
+}
@@ -0,0 +1,67 @@
+//! Interactive prompts. Questions go to stderr and answers are read from the controlling
+//! terminal rather than stdin, so prompting still works while a token or bundle is piped in.
+
+use crate::error::{AppError, AppResult};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, IsTerminal, Write};
+
+#[cfg(unix)]
+const TERMINAL: &str = "/dev/tty";
+#[cfg(windows)]
+const TERMINAL: &str = "CONIN$";
+
+fn open_terminal() -> std::io::Result<File> {
+    OpenOptions::new().read(true).write(true).open(TERMINAL)
+}
+
+/// A user can answer: stderr (where questions go) is a terminal and the process has one to read
+/// from. Scripts that redirect stderr never block on a prompt.
+pub fn has_terminal() -> bool {
+    std::io::stderr().is_terminal() && open_terminal().is_ok()
+}
+
+/// Reads a line without echoing it (secrets, passphrases).
+pub fn read_hidden(prompt: &str) -> std::io::Result<String> {
+    if !has_terminal() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "no terminal to prompt on",
+        ));
+    }
+    rpassword::prompt_password(prompt)
+}
+
+/// Asks a yes/no question unless `assume_yes` (`--yes`) is set. Without a terminal the answer
+/// cannot be given, so this fails instead of guessing. Anything but `y`/`yes` is a no.
+pub fn confirm(question: &str, assume_yes: bool) -> AppResult<bool> {
+    if assume_yes {
+        return Ok(true);
+    }
+    if !has_terminal() {
+        return Err(AppError::invalid_key(format!(
+            "{question} needs confirmation; pass --yes when not running in a terminal"
+        )));
+    }
+    let read_answer = || -> std::io::Result<String> {
+        eprint!("{question} [y/N] ");
+        std::io::stderr().flush()?;
+        let mut answer = String::new();
+        BufReader::new(open_terminal()?).read_line(&mut answer)?;
+        Ok(answer)
+    };
+    let answer = read_answer()
+        .map_err(|e| AppError::invalid_token(format!("failed to read answer: {e}")))?;
+    Ok(matches!(
+        answer.trim().to_ascii_lowercase().as_str(),
+        "y" | "yes"
+    ))
+}
+
+/// [`confirm`], treating a "no" as an error so destructive commands stop before changing anything.
+pub fn require_confirmation(question: &str, assume_yes: bool) -> AppResult<()> {
+    if confirm(question, assume_yes)? {
+        Ok(())
+    } else {
+        Err(AppError::invalid_key("cancelled"))
+    }
+}
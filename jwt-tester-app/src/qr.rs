@@ -0,0 +1,99 @@
+use crate::error::{AppError, AppResult};
+use std::process::Command;
+
+const ZBARIMG_ENV: &str = "JWT_TESTER_ZBARIMG";
+
+/// Light modules around the code. Phone scanners cope with two, which keeps the code compact.
+#[cfg(feature = "qr")]
+const QUIET_ZONE: i32 = 2;
+
+/// Renders `text` as a QR code with half-block characters, two modules per terminal row.
+/// Dark modules are drawn as spaces on a light background so phones read it on dark terminals.
+#[cfg(feature = "qr")]
+pub(crate) fn render(text: &str) -> AppResult<String> {
+    use qrcodegen::{QrCode, QrCodeEcc};
+
+    let qr = QrCode::encode_text(text, QrCodeEcc::Low)
+        .map_err(|e| AppError::internal(format!("cannot encode QR code: {e}")))?;
+    let size = qr.size();
+    // Outside the symbol counts as light, which draws the quiet zone.
+    let dark = |x: i32, y: i32| qr.get_module(x, y);
+    let mut out = String::new();
+    let mut y = -QUIET_ZONE;
+    while y < size + QUIET_ZONE {
+        for x in -QUIET_ZONE..size + QUIET_ZONE {
+            out.push(match (dark(x, y), dark(x, y + 1)) {
+                (false, false) => '█',
+                (false, true) => '▀',
+                (true, false) => '▄',
+                (true, true) => ' ',
+            });
+        }
+        out.push('\n');
+        y += 2;
+    }
+    Ok(out)
+}
+
+#[cfg(not(feature = "qr"))]
+pub(crate) fn render(_text: &str) -> AppResult<String> {
+    Err(AppError::internal(
+        "printing QR codes requires a build with the `qr` feature",
+    ))
+}
+
+/// Reads the QR code in an image file (`@path` or a plain path) via the local `zbarimg` binary
+/// from zbar (override with `JWT_TESTER_ZBARIMG`).
+pub fn scan_image(spec: &str) -> AppResult<String> {
+    let path = spec.strip_prefix('@').unwrap_or(spec);
+    if !std::path::Path::new(path).is_file() {
+        return Err(AppError::invalid_token(format!("image not found: {path}")));
+    }
+    let program = std::env::var_os(ZBARIMG_ENV).unwrap_or_else(|| "zbarimg".into());
+    let output = Command::new(&program)
+        .args(["--raw", "--quiet", "-Sdisable", "-Sqrcode.enable"])
+        .arg(path)
+        .output()
+        .map_err(|e| {
+            AppError::internal(format!(
+                "failed to run {program:?}: {e} (install zbar or set {ZBARIMG_ENV})"
+            ))
+        })?;
+    // zbarimg exits with 4 when the image holds no barcode.
+    if output.status.code() == Some(4) {
+        return Err(AppError::invalid_token(format!(
+            "no QR code found in {path}"
+        )));
+    }
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(AppError::internal(format!(
+            "zbarimg failed ({}): {}",
+            output.status,
+            stderr.trim()
+        )));
+    }
+    let text = String::from_utf8_lossy(&output.stdout);
+    text.lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(str::to_string)
+        .ok_or_else(|| AppError::invalid_token(format!("no QR code found in {path}")))
+}
+
+#[cfg(all(test, feature = "qr"))]
+mod tests {
+    use super::render;
+
+    #[test]
+    fn render_draws_a_square_with_quiet_zone() {
+        let qr = render("http://192.168.1.20:3000/").expect("render");
+        let lines: Vec<&str> = qr.lines().collect();
+        let width = lines[0].chars().count();
+        // Version 2 (25 modules) plus the quiet zone on both sides, two modules per row.
+        assert_eq!(width, 25 + 4);
+        assert_eq!(lines.len(), width.div_ceil(2));
+        assert!(lines[0].chars().all(|c| c == '█'));
+        assert!(lines.iter().all(|line| line.chars().count() == width));
+    }
+}
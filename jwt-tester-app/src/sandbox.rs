@@ -0,0 +1,187 @@
+//! `--sandbox` for `decode` and `inspect`: once the token has been read, a seccomp filter takes
+//! away filesystem, network and process access for the rest of the run, so a parsing bug hit by a
+//! hostile token cannot reach past the terminal. Output already goes to open descriptors
+//! (stdout/stderr), which stay usable.
+
+use crate::error::{AppError, AppResult};
+use crate::output::OutputConfig;
+
+/// The only syscalls left once `--sandbox` is on; every other call fails with `EPERM`. Reading and
+/// writing descriptors that are already open, allocating memory, signals, clocks and exiting stay
+/// allowed. Opening files, sockets, new threads or processes, namespaces, io_uring and
+/// everything else are not.
+#[cfg(all(feature = "sandbox", target_os = "linux"))]
+const ALLOWED: &[libc::c_long] = &[
+    // Already open descriptors.
+    libc::SYS_read,
+    libc::SYS_write,
+    libc::SYS_readv,
+    libc::SYS_writev,
+    libc::SYS_pread64,
+    libc::SYS_pwrite64,
+    libc::SYS_lseek,
+    libc::SYS_fstat,
+    libc::SYS_fcntl,
+    libc::SYS_close,
+    // Memory.
+    libc::SYS_brk,
+    libc::SYS_mmap,
+    libc::SYS_munmap,
+    libc::SYS_mremap,
+    libc::SYS_mprotect,
+    libc::SYS_madvise,
+    // Locks, signals and panics.
+    libc::SYS_futex,
+    libc::SYS_sched_yield,
+    libc::SYS_rt_sigaction,
+    libc::SYS_rt_sigprocmask,
+    libc::SYS_rt_sigreturn,
+    libc::SYS_sigaltstack,
+    libc::SYS_restart_syscall,
+    libc::SYS_getpid,
+    libc::SYS_gettid,
+    libc::SYS_tgkill,
+    // Clocks and randomness.
+    libc::SYS_clock_gettime,
+    libc::SYS_clock_getres,
+    libc::SYS_clock_nanosleep,
+    libc::SYS_gettimeofday,
+    libc::SYS_nanosleep,
+    libc::SYS_getrandom,
+    // Exiting.
+    libc::SYS_exit,
+    libc::SYS_exit_group,
+];
+
+/// `ioctl` requests left allowed: terminal detection and size, for colored and wrapped output.
+/// Anything else (`TIOCSTI` typing into the terminal, for one) is refused.
+#[cfg(all(feature = "sandbox", target_os = "linux"))]
+const ALLOWED_IOCTLS: &[u64] = &[libc::TCGETS as _, libc::TIOCGWINSZ as _];
+
+/// Checks the rest of the run needs nothing the sandbox takes away. `--summary-file` and the
+/// `--output-*` sinks write files or send requests after the command has run, and
+/// `--notify-done` starts a notifier process or talks to the desktop bus.
+pub fn check_output(cfg: OutputConfig) -> AppResult<()> {
+    if cfg.summary.is_some() || cfg.sinks.is_some() {
+        return Err(AppError::internal(
+            "--sandbox cannot be combined with --summary-file or --output-file/--output-webhook/--output-syslog",
+        ));
+    }
+    if crate::notify::notify_done() {
+        return Err(AppError::internal(
+            "--sandbox cannot be combined with --notify-done",
+        ));
+    }
+    Ok(())
+}
+
+/// Turns the sandbox on for every thread of the process. There is no way back.
+#[cfg(all(feature = "sandbox", target_os = "linux"))]
+pub fn enter() -> AppResult<()> {
+    seccompiler::apply_filter_all_threads(&filter()?)
+        .map_err(|e| AppError::internal(format!("failed to enable --sandbox: {e}")))
+}
+
+#[cfg(all(not(feature = "sandbox"), target_os = "linux"))]
+pub fn enter() -> AppResult<()> {
+    Err(AppError::internal(
+        "--sandbox requires a build with the `sandbox` feature",
+    ))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn enter() -> AppResult<()> {
+    Err(AppError::internal(
+        "--sandbox uses seccomp and is only available on Linux",
+    ))
+}
+
+#[cfg(all(feature = "sandbox", target_os = "linux"))]
+fn filter() -> AppResult<seccompiler::BpfProgram> {
+    use seccompiler::{
+        SeccompAction, SeccompCmpArgLen, SeccompCmpOp, SeccompCondition, SeccompFilter,
+        SeccompRule, TargetArch,
+    };
+
+    let arch = TargetArch::try_from(std::env::consts::ARCH)
+        .map_err(|e| AppError::internal(format!("--sandbox is not supported here: {e}")))?;
+    fn build_error(e: impl std::fmt::Display) -> AppError {
+        AppError::internal(format!("failed to build the --sandbox filter: {e}"))
+    }
+    let ioctls = ALLOWED_IOCTLS
+        .iter()
+        .map(|request| {
+            SeccompCondition::new(1, SeccompCmpArgLen::Dword, SeccompCmpOp::Eq, *request)
+                .and_then(|condition| SeccompRule::new(vec![condition]))
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(build_error)?;
+    let mut rules: std::collections::BTreeMap<_, _> = ALLOWED
+        .iter()
+        .map(|syscall| (*syscall, Vec::new()))
+        .collect();
+    rules.insert(libc::SYS_ioctl, ioctls);
+    SeccompFilter::new(
+        rules,
+        SeccompAction::Errno(libc::EPERM as u32),
+        SeccompAction::Allow,
+        arch,
+    )
+    .map_err(build_error)
+    .and_then(|filter| filter.try_into().map_err(build_error))
+}
+
+#[cfg(all(test, feature = "sandbox", target_os = "linux"))]
+mod tests {
+    use super::filter;
+    use crate::jwt_ops;
+    use std::io::ErrorKind;
+
+    #[test]
+    fn sandboxed_thread_decodes_but_cannot_reach_out() {
+        let program = filter().expect("filter");
+        let manifest = concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml");
+        // Only the spawned thread is filtered, so the rest of the test run is unaffected.
+        std::thread::spawn(move || {
+            seccompiler::apply_filter(&program).expect("apply");
+            let denied = |err: std::io::Error| err.kind() == ErrorKind::PermissionDenied;
+
+            assert!(std::fs::read(manifest).is_err_and(denied));
+            assert!(std::fs::write(std::env::temp_dir().join("sandboxed"), b"x").is_err_and(denied));
+            assert!(std::net::TcpListener::bind("127.0.0.1:0").is_err_and(denied));
+            assert!(std::net::UdpSocket::bind("127.0.0.1:0").is_err_and(denied));
+            assert!(std::process::Command::new("true").status().is_err());
+            assert!(std::thread::Builder::new().spawn(|| ()).is_err());
+            // Syscalls that reach files, sockets or other namespaces without `open`/`socket`.
+            for syscall in [
+                libc::SYS_io_uring_setup,
+                libc::SYS_unshare,
+                libc::SYS_setns,
+                libc::SYS_bpf,
+                libc::SYS_memfd_create,
+                libc::SYS_clone3,
+            ] {
+                // SAFETY: every call is refused by the filter before its arguments are read.
+                let ret = unsafe { libc::syscall(syscall, 0, 0, 0) };
+                assert_eq!(ret, -1, "syscall {syscall} was allowed");
+                assert_eq!(
+                    std::io::Error::last_os_error().raw_os_error(),
+                    Some(libc::EPERM)
+                );
+            }
+            // SAFETY: TIOCSTI with a null argument is refused before the pointer is read.
+            let ret = unsafe { libc::ioctl(0, libc::TIOCSTI, std::ptr::null::<u8>()) };
+            assert_eq!(ret, -1);
+            assert_eq!(
+                std::io::Error::last_os_error().raw_os_error(),
+                Some(libc::EPERM)
+            );
+
+            let token = "eyJhbGciOiJIUzI1NiJ9.eyJzdWIiOiJhIn0.c2ln";
+            let decoded = jwt_ops::decode_unverified(token).expect("decode");
+            assert_eq!(decoded.payload_json["sub"], "a");
+        })
+        .join()
+        .expect("sandboxed thread");
+    }
+}
@@ -0,0 +1,361 @@
+use super::ExternalSigner;
+#[cfg(not(windows))]
+use crate::error::AppError;
+use crate::error::AppResult;
+use jsonwebtoken::Algorithm;
+
+/// Key storage provider holding `cng:` keys; the TPM-backed "Microsoft Platform Crypto Provider"
+/// is the usual alternative.
+#[cfg(windows)]
+pub(super) const PROVIDER_ENV: &str = "JWT_TESTER_CNG_PROVIDER";
+
+#[cfg(windows)]
+pub(super) fn open_signer(name: &str, alg: Algorithm) -> AppResult<Box<dyn ExternalSigner>> {
+    let signer = ffi::CngSigner::open(&provider_name(), name, alg)?;
+    Ok(Box::new(signer))
+}
+
+/// Creates a persisted P-256 (ES256) or RSA-2048 (RS256/PS256) key. No export policy is set,
+/// so the provider refuses to export the private key.
+#[cfg(windows)]
+pub(super) fn create_key(name: &str, alg: Algorithm) -> AppResult<()> {
+    ffi::create_key(&provider_name(), name, alg)
+}
+
+#[cfg(windows)]
+fn provider_name() -> String {
+    std::env::var(PROVIDER_ENV)
+        .ok()
+        .filter(|provider| !provider.trim().is_empty())
+        .unwrap_or_else(|| ffi::SOFTWARE_PROVIDER.to_string())
+}
+
+#[cfg(not(windows))]
+pub(super) fn open_signer(name: &str, _alg: Algorithm) -> AppResult<Box<dyn ExternalSigner>> {
+    Err(unavailable(name))
+}
+
+#[cfg(not(windows))]
+pub(super) fn create_key(name: &str, _alg: Algorithm) -> AppResult<()> {
+    Err(unavailable(name))
+}
+
+#[cfg(not(windows))]
+fn unavailable(name: &str) -> AppError {
+    AppError::invalid_key(format!(
+        "cng:{name}: CNG keys are only available on Windows"
+    ))
+}
+
+#[cfg(windows)]
+mod ffi {
+    //! The handful of ncrypt.dll calls needed to create or open a persisted key and sign a
+    //! digest with it. The key never leaves the provider; only the SHA-256 digest crosses the API.
+
+    use super::ExternalSigner;
+    use crate::error::{AppError, AppResult};
+    use jsonwebtoken::Algorithm;
+    use sha2::{Digest, Sha256};
+    use std::ffi::c_void;
+    use std::ptr;
+
+    type SecurityStatus = i32;
+    type NcryptHandle = usize;
+
+    pub(super) const SOFTWARE_PROVIDER: &str = "Microsoft Software Key Storage Provider";
+    const ERROR_SUCCESS: SecurityStatus = 0;
+    const NCRYPT_PAD_PKCS1_FLAG: u32 = 0x2;
+    const NCRYPT_PAD_PSS_FLAG: u32 = 0x8;
+    const ALGORITHM_GROUP_PROPERTY: &str = "AlgorithmGroup";
+    const LENGTH_PROPERTY: &str = "Length";
+    const ECDSA_P256_ALGORITHM: &str = "ECDSA_P256";
+    const RSA_ALGORITHM: &str = "RSA";
+    const RSA_KEY_BITS: u32 = 2048;
+    const SHA256_ALGORITHM: &str = "SHA256";
+
+    #[repr(C)]
+    struct Pkcs1PaddingInfo {
+        alg_id: *const u16,
+    }
+
+    #[repr(C)]
+    struct PssPaddingInfo {
+        alg_id: *const u16,
+        salt_len: u32,
+    }
+
+    #[link(name = "ncrypt")]
+    extern "system" {
+        fn NCryptOpenStorageProvider(
+            provider: *mut NcryptHandle,
+            name: *const u16,
+            flags: u32,
+        ) -> SecurityStatus;
+        fn NCryptOpenKey(
+            provider: NcryptHandle,
+            key: *mut NcryptHandle,
+            name: *const u16,
+            legacy_key_spec: u32,
+            flags: u32,
+        ) -> SecurityStatus;
+        fn NCryptCreatePersistedKey(
+            provider: NcryptHandle,
+            key: *mut NcryptHandle,
+            alg_id: *const u16,
+            name: *const u16,
+            legacy_key_spec: u32,
+            flags: u32,
+        ) -> SecurityStatus;
+        fn NCryptSetProperty(
+            object: NcryptHandle,
+            property: *const u16,
+            input: *const u8,
+            input_len: u32,
+            flags: u32,
+        ) -> SecurityStatus;
+        fn NCryptFinalizeKey(key: NcryptHandle, flags: u32) -> SecurityStatus;
+        fn NCryptGetProperty(
+            object: NcryptHandle,
+            property: *const u16,
+            output: *mut u8,
+            output_len: u32,
+            result_len: *mut u32,
+            flags: u32,
+        ) -> SecurityStatus;
+        fn NCryptSignHash(
+            key: NcryptHandle,
+            padding_info: *const c_void,
+            hash: *const u8,
+            hash_len: u32,
+            signature: *mut u8,
+            signature_len: u32,
+            result_len: *mut u32,
+            flags: u32,
+        ) -> SecurityStatus;
+        fn NCryptFreeObject(object: NcryptHandle) -> SecurityStatus;
+    }
+
+    fn wide(value: &str) -> Vec<u16> {
+        value.encode_utf16().chain(std::iter::once(0)).collect()
+    }
+
+    fn check(status: SecurityStatus, label: &str, call: &str) -> AppResult<()> {
+        if status == ERROR_SUCCESS {
+            Ok(())
+        } else {
+            Err(AppError::invalid_key(format!(
+                "{label}: {call} failed (0x{:08X})",
+                status as u32
+            )))
+        }
+    }
+
+    /// Frees an ncrypt handle when dropped.
+    struct Handle(NcryptHandle);
+
+    impl Drop for Handle {
+        fn drop(&mut self) {
+            if self.0 != 0 {
+                unsafe {
+                    NCryptFreeObject(self.0);
+                }
+            }
+        }
+    }
+
+    fn open_provider(provider_name: &str, label: &str) -> AppResult<Handle> {
+        let mut provider = Handle(0);
+        check(
+            unsafe { NCryptOpenStorageProvider(&mut provider.0, wide(provider_name).as_ptr(), 0) },
+            label,
+            "NCryptOpenStorageProvider",
+        )?;
+        Ok(provider)
+    }
+
+    pub(super) fn create_key(provider_name: &str, name: &str, alg: Algorithm) -> AppResult<()> {
+        let label = format!("cng:{name}");
+        let provider = open_provider(provider_name, &label)?;
+        let alg_id = if alg == Algorithm::ES256 {
+            ECDSA_P256_ALGORITHM
+        } else {
+            RSA_ALGORITHM
+        };
+        let mut key = Handle(0);
+        // Without NCRYPT_OVERWRITE_KEY_FLAG an existing key of the same name is an error.
+        check(
+            unsafe {
+                NCryptCreatePersistedKey(
+                    provider.0,
+                    &mut key.0,
+                    wide(alg_id).as_ptr(),
+                    wide(name).as_ptr(),
+                    0,
+                    0,
+                )
+            },
+            &label,
+            "NCryptCreatePersistedKey",
+        )?;
+        if alg_id == RSA_ALGORITHM {
+            let bits = RSA_KEY_BITS.to_le_bytes();
+            check(
+                unsafe {
+                    NCryptSetProperty(
+                        key.0,
+                        wide(LENGTH_PROPERTY).as_ptr(),
+                        bits.as_ptr(),
+                        bits.len() as u32,
+                        0,
+                    )
+                },
+                &label,
+                "NCryptSetProperty",
+            )?;
+        }
+        check(
+            unsafe { NCryptFinalizeKey(key.0, 0) },
+            &label,
+            "NCryptFinalizeKey",
+        )
+    }
+
+    pub(super) struct CngSigner {
+        label: String,
+        // Dropped in declaration order: the key before the provider it came from.
+        key: Handle,
+        _provider: Handle,
+    }
+
+    impl CngSigner {
+        pub(super) fn open(provider_name: &str, name: &str, alg: Algorithm) -> AppResult<Self> {
+            let label = format!("cng:{name}");
+            let provider = open_provider(provider_name, &label)?;
+            let mut key = Handle(0);
+            check(
+                unsafe { NCryptOpenKey(provider.0, &mut key.0, wide(name).as_ptr(), 0, 0) },
+                &label,
+                "NCryptOpenKey",
+            )?;
+            let signer = CngSigner {
+                label,
+                key,
+                _provider: provider,
+            };
+            let group = signer.algorithm_group()?;
+            let expected = if alg == Algorithm::ES256 {
+                "ECDSA"
+            } else {
+                "RSA"
+            };
+            if group != expected {
+                return Err(AppError::invalid_key(format!(
+                    "{} is a {group} key and cannot sign {alg:?}",
+                    signer.label
+                )));
+            }
+            Ok(signer)
+        }
+
+        /// `RSA` or `ECDSA` (a P-256 key is `ECDSA`; the curve is checked by NCryptSignHash).
+        fn algorithm_group(&self) -> AppResult<String> {
+            let property = wide(ALGORITHM_GROUP_PROPERTY);
+            let mut buf = [0u8; 64];
+            let mut len = 0u32;
+            check(
+                unsafe {
+                    NCryptGetProperty(
+                        self.key.0,
+                        property.as_ptr(),
+                        buf.as_mut_ptr(),
+                        buf.len() as u32,
+                        &mut len,
+                        0,
+                    )
+                },
+                &self.label,
+                "NCryptGetProperty",
+            )?;
+            let units: Vec<u16> = buf[..len as usize]
+                .chunks_exact(2)
+                .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+                .take_while(|unit| *unit != 0)
+                .collect();
+            Ok(String::from_utf16_lossy(&units))
+        }
+    }
+
+    impl ExternalSigner for CngSigner {
+        fn sign(&self, alg: Algorithm, message: &[u8]) -> AppResult<Vec<u8>> {
+            let digest = Sha256::digest(message);
+            let sha256 = wide(SHA256_ALGORITHM);
+            let pkcs1 = Pkcs1PaddingInfo {
+                alg_id: sha256.as_ptr(),
+            };
+            let pss = PssPaddingInfo {
+                alg_id: sha256.as_ptr(),
+                salt_len: digest.len() as u32,
+            };
+            // ECDSA takes no padding and returns the raw `r || s` JWS wants.
+            let (padding, flags) = match alg {
+                Algorithm::ES256 => (ptr::null(), 0),
+                Algorithm::RS256 => (
+                    &pkcs1 as *const Pkcs1PaddingInfo as *const c_void,
+                    NCRYPT_PAD_PKCS1_FLAG,
+                ),
+                Algorithm::PS256 => (
+                    &pss as *const PssPaddingInfo as *const c_void,
+                    NCRYPT_PAD_PSS_FLAG,
+                ),
+                other => {
+                    return Err(AppError::invalid_key(format!(
+                        "{} cannot sign {other:?}",
+                        self.label
+                    )))
+                }
+            };
+            let mut len = 0u32;
+            check(
+                unsafe {
+                    NCryptSignHash(
+                        self.key.0,
+                        padding,
+                        digest.as_ptr(),
+                        digest.len() as u32,
+                        ptr::null_mut(),
+                        0,
+                        &mut len,
+                        flags,
+                    )
+                },
+                &self.label,
+                "NCryptSignHash",
+            )?;
+            let mut signature = vec![0u8; len as usize];
+            check(
+                unsafe {
+                    NCryptSignHash(
+                        self.key.0,
+                        padding,
+                        digest.as_ptr(),
+                        digest.len() as u32,
+                        signature.as_mut_ptr(),
+                        len,
+                        &mut len,
+                        flags,
+                    )
+                },
+                &self.label,
+                "NCryptSignHash",
+            )?;
+            signature.truncate(len as usize);
+            if alg == Algorithm::ES256 && signature.len() != 64 {
+                return Err(AppError::invalid_key(format!(
+                    "{} is not a P-256 key and cannot sign ES256",
+                    self.label
+                )));
+            }
+            Ok(signature)
+        }
+    }
+}
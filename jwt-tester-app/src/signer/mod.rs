@@ -0,0 +1,169 @@
+mod cng;
+mod pkcs11;
+mod secure_enclave;
+mod ssh;
+
+use crate::error::{AppError, AppResult};
+use jsonwebtoken::{Algorithm, EncodingKey};
+pub use pkcs11::Pkcs11Uri;
+pub(crate) use ssh::load_private_key as load_ssh_private_key;
+
+/// Signs JWS signing input outside of `jsonwebtoken`, e.g. with a key that never
+/// leaves a platform keystore. Signatures use the JWS encoding (raw `r || s` for ECDSA).
+pub trait ExternalSigner {
+    fn sign(&self, alg: Algorithm, message: &[u8]) -> AppResult<Vec<u8>>;
+}
+
+pub enum SigningKey {
+    Local(EncodingKey),
+    External(Box<dyn ExternalSigner>),
+}
+
+/// Key references handled by an external signer rather than loaded as bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExternalKeyRef {
+    /// Windows CNG key by name (`cng:<name>`), created as non-exportable.
+    Cng(String),
+    /// macOS Secure Enclave / Keychain key by label (`secure-enclave:<label>`).
+    SecureEnclave(String),
+    /// PKCS#11 / HSM key (`pkcs11:token=dev;id=01`), requires the `pkcs11` feature.
+    Pkcs11(Pkcs11Uri),
+    /// Key held by ssh-agent (`ssh-agent` or `ssh-agent:<comment|SHA256:fingerprint>`).
+    SshAgent(Option<String>),
+}
+
+impl ExternalKeyRef {
+    /// Returns `None` for specs that are ordinary key inputs (`@file`, `env:`, PEM, ...).
+    pub fn parse(spec: &str) -> AppResult<Option<Self>> {
+        let spec = spec.trim();
+        if spec == "ssh-agent" {
+            return Ok(Some(ExternalKeyRef::SshAgent(None)));
+        }
+        let (scheme, rest) = match spec.split_once(':') {
+            Some(parts) => parts,
+            None => return Ok(None),
+        };
+        if scheme == "ssh-agent" {
+            let selector = rest.trim();
+            return Ok(Some(ExternalKeyRef::SshAgent(
+                (!selector.is_empty()).then(|| selector.to_string()),
+            )));
+        }
+        if scheme == "pkcs11" {
+            return Pkcs11Uri::parse(rest).map(|uri| Some(ExternalKeyRef::Pkcs11(uri)));
+        }
+        let build: fn(String) -> Self = match scheme {
+            "cng" => ExternalKeyRef::Cng,
+            "secure-enclave" => ExternalKeyRef::SecureEnclave,
+            _ => return Ok(None),
+        };
+        let name = rest.trim();
+        if name.is_empty() {
+            return Err(AppError::invalid_key(format!(
+                "{scheme}: key reference requires a key name"
+            )));
+        }
+        Ok(Some(build(name.to_string())))
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            ExternalKeyRef::Cng(name) => format!("cng:{name}"),
+            ExternalKeyRef::SecureEnclave(name) => format!("secure-enclave:{name}"),
+            ExternalKeyRef::Pkcs11(uri) => uri.label(),
+            ExternalKeyRef::SshAgent(None) => "ssh-agent".to_string(),
+            ExternalKeyRef::SshAgent(Some(selector)) => format!("ssh-agent:{selector}"),
+        }
+    }
+}
+
+pub fn open_external_signer(
+    key: &ExternalKeyRef,
+    alg: Algorithm,
+) -> AppResult<Box<dyn ExternalSigner>> {
+    match key {
+        ExternalKeyRef::Pkcs11(uri) => pkcs11::open_signer(uri, alg),
+        ExternalKeyRef::SshAgent(selector) => {
+            let signer = ssh::SshAgentSigner::connect(selector.as_deref(), alg)?;
+            Ok(Box::new(signer))
+        }
+        ExternalKeyRef::Cng(name) => {
+            check_platform_alg(key, alg)?;
+            cng::open_signer(name, alg)
+        }
+        ExternalKeyRef::SecureEnclave(label) => {
+            check_platform_alg(key, alg)?;
+            secure_enclave::open_signer(label, alg)
+        }
+    }
+}
+
+/// Creates a non-exportable key for a `cng:` or `secure-enclave:` reference; signing with it
+/// later goes through [`open_external_signer`]. Fails if a key with that name already exists.
+pub fn create_platform_key(key: &ExternalKeyRef, alg: Algorithm) -> AppResult<()> {
+    check_platform_alg(key, alg)?;
+    match key {
+        ExternalKeyRef::Cng(name) => cng::create_key(name, alg),
+        ExternalKeyRef::SecureEnclave(label) => secure_enclave::create_key(label),
+        other => Err(AppError::invalid_key(format!(
+            "{} keys cannot be created here; only cng: and secure-enclave: keys can",
+            other.label()
+        ))),
+    }
+}
+
+// Secure Enclave keys are P-256 only; CNG software/TPM providers add RSA.
+fn check_platform_alg(key: &ExternalKeyRef, alg: Algorithm) -> AppResult<()> {
+    let (supported, allowed) = match key {
+        ExternalKeyRef::SecureEnclave(_) => (alg == Algorithm::ES256, "ES256"),
+        _ => (
+            matches!(alg, Algorithm::ES256 | Algorithm::RS256 | Algorithm::PS256),
+            "ES256 and RS256/PS256",
+        ),
+    };
+    if supported {
+        return Ok(());
+    }
+    Err(AppError::invalid_key(format!(
+        "{} keys only support {allowed}, not {alg:?}",
+        key.label()
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{create_platform_key, open_external_signer, ExternalKeyRef};
+    use jsonwebtoken::Algorithm;
+
+    #[test]
+    fn parse_recognizes_platform_schemes_only() {
+        assert_eq!(
+            ExternalKeyRef::parse("cng:jwt-dev").unwrap(),
+            Some(ExternalKeyRef::Cng("jwt-dev".to_string()))
+        );
+        assert_eq!(
+            ExternalKeyRef::parse("secure-enclave: signing ").unwrap(),
+            Some(ExternalKeyRef::SecureEnclave("signing".to_string()))
+        );
+        assert_eq!(ExternalKeyRef::parse("@key.pem").unwrap(), None);
+        assert_eq!(ExternalKeyRef::parse("env:KEY").unwrap(), None);
+        assert!(ExternalKeyRef::parse("cng:").is_err());
+    }
+
+    #[test]
+    fn platform_keys_report_unsupported_algorithms() {
+        let key = ExternalKeyRef::Cng("dev".to_string());
+        let err = open_external_signer(&key, Algorithm::EdDSA)
+            .err()
+            .expect("error");
+        assert!(err.to_string().contains("only support"));
+
+        // The Secure Enclave only holds P-256 keys, so RSA is refused before any platform call.
+        let key = ExternalKeyRef::SecureEnclave("dev".to_string());
+        for alg in [Algorithm::RS256, Algorithm::PS256] {
+            let err = open_external_signer(&key, alg).err().expect("error");
+            assert!(err.to_string().contains("only support ES256,"), "{err}");
+            assert!(create_platform_key(&key, alg).is_err());
+        }
+    }
+}
@@ -0,0 +1,873 @@
+use super::ExternalSigner;
+use crate::error::{AppError, AppResult};
+use jsonwebtoken::Algorithm;
+
+pub(super) const MODULE_ENV: &str = "JWT_TESTER_PKCS11_MODULE";
+pub(super) const PIN_ENV: &str = "JWT_TESTER_PKCS11_PIN";
+
+/// Parsed `pkcs11:` key reference (RFC 7512 subset).
+///
+/// Path attributes: `token`, `slot-id`, `object` (label), `id`.
+/// Query attributes: `module-path`, `pin-source`. `pin-value` is refused: a PIN in the URI
+/// ends up in shell history, process listings and CI logs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pkcs11Uri {
+    pub token: Option<String>,
+    pub slot_id: Option<u64>,
+    pub object: Option<String>,
+    pub id: Option<Vec<u8>>,
+    pub module_path: Option<String>,
+    /// Where the PIN is read from, as an input spec (`@path`, `fd:N`, `prompt[:label]`, `env:NAME`).
+    pub pin_source: Option<String>,
+}
+
+impl Pkcs11Uri {
+    /// Parses everything after the `pkcs11:` scheme.
+    pub fn parse(rest: &str) -> AppResult<Self> {
+        let (path, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let mut uri = Pkcs11Uri {
+            token: None,
+            slot_id: None,
+            object: None,
+            id: None,
+            module_path: None,
+            pin_source: None,
+        };
+
+        for (name, value) in attributes(path, ';')? {
+            match name {
+                "token" => uri.token = Some(percent_decode_string(value)?),
+                "object" => uri.object = Some(percent_decode_string(value)?),
+                "id" => uri.id = Some(decode_id(value)?),
+                "slot-id" => {
+                    let slot = value.parse().map_err(|_| {
+                        AppError::invalid_key(format!("pkcs11: invalid slot-id '{value}'"))
+                    })?;
+                    uri.slot_id = Some(slot);
+                }
+                other => {
+                    return Err(AppError::invalid_key(format!(
+                        "pkcs11: unsupported attribute '{other}' (expected token, slot-id, object, or id)"
+                    )))
+                }
+            }
+        }
+        for (name, value) in attributes(query, '&')? {
+            match name {
+                "module-path" => uri.module_path = Some(percent_decode_string(value)?),
+                "pin-source" => uri.pin_source = Some(pin_source(&percent_decode_string(value)?)?),
+                "pin-value" => {
+                    return Err(AppError::invalid_key(format!(
+                        "pkcs11: pin-value is not accepted; use pin-source=file:<path>, fd:<n>, prompt or env:<NAME>, or set {PIN_ENV}"
+                    )))
+                }
+                other => {
+                    return Err(AppError::invalid_key(format!(
+                        "pkcs11: unsupported query attribute '{other}' (expected module-path or pin-source)"
+                    )))
+                }
+            }
+        }
+
+        if uri.id.is_none() && uri.object.is_none() {
+            return Err(AppError::invalid_key(
+                "pkcs11: key reference requires id=<hex> or object=<label>",
+            ));
+        }
+        Ok(uri)
+    }
+
+    pub fn label(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(token) = &self.token {
+            parts.push(format!("token={token}"));
+        }
+        if let Some(slot) = self.slot_id {
+            parts.push(format!("slot-id={slot}"));
+        }
+        if let Some(object) = &self.object {
+            parts.push(format!("object={object}"));
+        }
+        if let Some(id) = &self.id {
+            parts.push(format!("id={}", hex::encode(id)));
+        }
+        format!("pkcs11:{}", parts.join(";"))
+    }
+
+    #[cfg_attr(not(all(feature = "pkcs11", unix)), allow(dead_code))]
+    fn module_path(&self) -> AppResult<String> {
+        self.module_path
+            .clone()
+            .or_else(|| std::env::var(MODULE_ENV).ok())
+            .filter(|path| !path.trim().is_empty())
+            .ok_or_else(|| {
+                AppError::invalid_key(format!(
+                    "pkcs11: set module-path=<lib> in the key reference or {MODULE_ENV}"
+                ))
+            })
+    }
+
+    #[cfg_attr(not(all(feature = "pkcs11", unix)), allow(dead_code))]
+    fn pin(&self) -> AppResult<Option<String>> {
+        let Some(source) = &self.pin_source else {
+            return Ok(std::env::var(PIN_ENV).ok());
+        };
+        crate::io_utils::read_input(source).map(Some).map_err(|e| {
+            AppError::invalid_key(format!("pkcs11: failed to read the PIN: {}", e.message))
+        })
+    }
+}
+
+// RFC 7512 `pin-source` is a URI; `file:` (or a bare absolute path) is the form it defines, and
+// `fd:`, `prompt` and `env:` are the input specs accepted everywhere else.
+fn pin_source(value: &str) -> AppResult<String> {
+    if let Some(path) = value.strip_prefix("file:") {
+        let path = path.strip_prefix("//").unwrap_or(path);
+        if !path.is_empty() {
+            return Ok(format!("@{path}"));
+        }
+    } else if value.starts_with('/') {
+        return Ok(format!("@{value}"));
+    } else if value.starts_with("fd:")
+        || value == "prompt"
+        || value.starts_with("prompt:")
+        || value.starts_with("env:")
+    {
+        return Ok(value.to_string());
+    }
+    Err(AppError::invalid_key(format!(
+        "pkcs11: unsupported pin-source '{value}' (expected file:<path>, fd:<n>, prompt or env:<NAME>)"
+    )))
+}
+
+#[cfg(all(feature = "pkcs11", unix))]
+pub(super) fn open_signer(uri: &Pkcs11Uri, alg: Algorithm) -> AppResult<Box<dyn ExternalSigner>> {
+    let module = uri.module_path()?;
+    let signer = ffi::Pkcs11Signer::open(&module, uri, uri.pin()?, alg)?;
+    Ok(Box::new(signer))
+}
+
+#[cfg(not(feature = "pkcs11"))]
+pub(super) fn open_signer(uri: &Pkcs11Uri, _alg: Algorithm) -> AppResult<Box<dyn ExternalSigner>> {
+    Err(AppError::invalid_key(format!(
+        "{} requires a build with the `pkcs11` feature",
+        uri.label()
+    )))
+}
+
+// Windows PKCS#11 modules pack their structs to 1-byte alignment; the bindings below only
+// describe the unpacked Unix layout, so they are not built there.
+#[cfg(all(feature = "pkcs11", not(unix)))]
+pub(super) fn open_signer(uri: &Pkcs11Uri, _alg: Algorithm) -> AppResult<Box<dyn ExternalSigner>> {
+    Err(AppError::invalid_key(format!(
+        "{}: PKCS#11 signing is only supported on Unix",
+        uri.label()
+    )))
+}
+
+fn attributes(raw: &str, separator: char) -> AppResult<Vec<(&str, &str)>> {
+    raw.split(separator)
+        .filter(|part| !part.trim().is_empty())
+        .map(|part| {
+            part.split_once('=')
+                .map(|(name, value)| (name.trim(), value.trim()))
+                .ok_or_else(|| {
+                    AppError::invalid_key(format!("pkcs11: expected name=value, got '{part}'"))
+                })
+        })
+        .collect()
+}
+
+// `id=01ab` is read as hex; percent-encoded ids (`id=%01%ab`, RFC 7512) are also accepted.
+fn decode_id(value: &str) -> AppResult<Vec<u8>> {
+    if value.contains('%') {
+        return percent_decode(value);
+    }
+    hex::decode(value).map_err(|_| {
+        AppError::invalid_key(format!(
+            "pkcs11: id must be hex or percent-encoded, got '{value}'"
+        ))
+    })
+}
+
+fn percent_decode_string(value: &str) -> AppResult<String> {
+    String::from_utf8(percent_decode(value)?)
+        .map_err(|_| AppError::invalid_key(format!("pkcs11: '{value}' is not valid UTF-8")))
+}
+
+fn percent_decode(value: &str) -> AppResult<Vec<u8>> {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = value
+                .get(i + 1..i + 3)
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+                .ok_or_else(|| {
+                    AppError::invalid_key(format!("pkcs11: invalid percent-encoding in '{value}'"))
+                })?;
+            out.push(hex);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    Ok(out)
+}
+
+mod mechanism {
+    //! Maps JWS algorithms to PKCS#11 mechanisms and checks the signatures they return. Kept
+    //! outside `ffi` so the mapping is tested in every build.
+    #![cfg_attr(not(all(feature = "pkcs11", unix)), allow(dead_code))]
+
+    use crate::error::{AppError, AppResult};
+    use jsonwebtoken::Algorithm;
+    use sha2::{Digest, Sha256, Sha384};
+
+    pub(super) type CkUlong = std::os::raw::c_ulong;
+
+    pub(super) const CKM_SHA256_RSA_PKCS: CkUlong = 0x40;
+    pub(super) const CKM_SHA384_RSA_PKCS: CkUlong = 0x41;
+    pub(super) const CKM_SHA512_RSA_PKCS: CkUlong = 0x42;
+    pub(super) const CKM_SHA256_RSA_PKCS_PSS: CkUlong = 0x43;
+    pub(super) const CKM_SHA384_RSA_PKCS_PSS: CkUlong = 0x44;
+    pub(super) const CKM_SHA512_RSA_PKCS_PSS: CkUlong = 0x45;
+    pub(super) const CKM_ECDSA: CkUlong = 0x1041;
+    pub(super) const CKM_EDDSA: CkUlong = 0x1057;
+    pub(super) const CKM_SHA256: CkUlong = 0x250;
+    pub(super) const CKM_SHA384: CkUlong = 0x260;
+    pub(super) const CKM_SHA512: CkUlong = 0x270;
+    pub(super) const CKG_MGF1_SHA256: CkUlong = 0x2;
+    pub(super) const CKG_MGF1_SHA384: CkUlong = 0x3;
+    pub(super) const CKG_MGF1_SHA512: CkUlong = 0x4;
+
+    /// `CK_RSA_PKCS_PSS_PARAMS`: hash, MGF and salt length.
+    pub(super) type PssParams = (CkUlong, CkUlong, CkUlong);
+
+    pub(super) enum Digestor {
+        Sha256,
+        Sha384,
+    }
+
+    pub(super) struct SigningInput {
+        pub mechanism: CkUlong,
+        pub pss: Option<PssParams>,
+        pub data: Vec<u8>,
+    }
+
+    pub(super) fn mechanism(
+        alg: Algorithm,
+    ) -> AppResult<(CkUlong, Option<PssParams>, Option<Digestor>)> {
+        Ok(match alg {
+            Algorithm::RS256 => (CKM_SHA256_RSA_PKCS, None, None),
+            Algorithm::RS384 => (CKM_SHA384_RSA_PKCS, None, None),
+            Algorithm::RS512 => (CKM_SHA512_RSA_PKCS, None, None),
+            Algorithm::PS256 => (
+                CKM_SHA256_RSA_PKCS_PSS,
+                Some((CKM_SHA256, CKG_MGF1_SHA256, 32)),
+                None,
+            ),
+            Algorithm::PS384 => (
+                CKM_SHA384_RSA_PKCS_PSS,
+                Some((CKM_SHA384, CKG_MGF1_SHA384, 48)),
+                None,
+            ),
+            Algorithm::PS512 => (
+                CKM_SHA512_RSA_PKCS_PSS,
+                Some((CKM_SHA512, CKG_MGF1_SHA512, 64)),
+                None,
+            ),
+            Algorithm::ES256 => (CKM_ECDSA, None, Some(Digestor::Sha256)),
+            Algorithm::ES384 => (CKM_ECDSA, None, Some(Digestor::Sha384)),
+            Algorithm::EdDSA => (CKM_EDDSA, None, None),
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => {
+                return Err(AppError::invalid_key(
+                    "pkcs11: HMAC algorithms are not supported; use --secret",
+                ))
+            }
+        })
+    }
+
+    /// What `C_Sign` is given: CKM_ECDSA signs a pre-computed digest; every other mechanism
+    /// hashes the signing input itself.
+    pub(super) fn signing_input(alg: Algorithm, message: &[u8]) -> AppResult<SigningInput> {
+        let (mechanism, pss, digest) = mechanism(alg)?;
+        let data = match digest {
+            Some(Digestor::Sha256) => Sha256::digest(message).to_vec(),
+            Some(Digestor::Sha384) => Sha384::digest(message).to_vec(),
+            None => message.to_vec(),
+        };
+        Ok(SigningInput {
+            mechanism,
+            pss,
+            data,
+        })
+    }
+
+    /// CKM_ECDSA and CKM_EDDSA return the raw `r || s` / `R || S` encoding JWS uses; a module
+    /// that hands back DER (or a truncated buffer) would otherwise produce an unverifiable token.
+    pub(super) fn check_signature(alg: Algorithm, signature: &[u8]) -> AppResult<()> {
+        let expected: &[usize] = match alg {
+            Algorithm::ES256 => &[64],
+            Algorithm::ES384 => &[96],
+            // Ed25519 or Ed448
+            Algorithm::EdDSA => &[64, 114],
+            _ => return Ok(()),
+        };
+        if expected.contains(&signature.len()) {
+            return Ok(());
+        }
+        Err(AppError::invalid_key(format!(
+            "pkcs11: module returned a {}-byte {alg:?} signature, expected {} bytes",
+            signature.len(),
+            expected
+                .iter()
+                .map(usize::to_string)
+                .collect::<Vec<_>>()
+                .join(" or ")
+        )))
+    }
+}
+
+#[cfg(all(feature = "pkcs11", unix))]
+mod ffi {
+    //! Minimal PKCS#11 v2.40/v3.0 bindings: just enough to log in, find a private
+    //! key, and sign. Struct layouts follow the Unix ABI (no packing).
+
+    use super::mechanism::{self, CkUlong};
+    use super::{ExternalSigner, Pkcs11Uri};
+    use crate::error::{AppError, AppResult};
+    use jsonwebtoken::Algorithm;
+    use std::os::raw::c_void;
+    use std::ptr;
+
+    type CkRv = CkUlong;
+    type Unused = Option<unsafe extern "C" fn()>;
+
+    const CKR_OK: CkRv = 0;
+    const CKR_USER_ALREADY_LOGGED_IN: CkRv = 0x100;
+    const CKR_CRYPTOKI_ALREADY_INITIALIZED: CkRv = 0x191;
+    const CKF_SERIAL_SESSION: CkUlong = 0x4;
+    const CKU_USER: CkUlong = 1;
+    const CKA_CLASS: CkUlong = 0x0;
+    const CKA_LABEL: CkUlong = 0x3;
+    const CKA_ID: CkUlong = 0x102;
+    const CKO_PRIVATE_KEY: CkUlong = 0x3;
+
+    #[repr(C)]
+    struct CkVersion {
+        major: u8,
+        minor: u8,
+    }
+
+    #[repr(C)]
+    struct CkAttribute {
+        kind: CkUlong,
+        value: *mut c_void,
+        len: CkUlong,
+    }
+
+    #[repr(C)]
+    struct CkMechanism {
+        mechanism: CkUlong,
+        parameter: *mut c_void,
+        len: CkUlong,
+    }
+
+    #[repr(C)]
+    struct CkRsaPssParams {
+        hash_alg: CkUlong,
+        mgf: CkUlong,
+        salt_len: CkUlong,
+    }
+
+    // Only the label is read; the remainder is sized generously for the rest of CK_TOKEN_INFO.
+    #[repr(C, align(8))]
+    struct CkTokenInfo {
+        label: [u8; 32],
+        rest: [u8; 256],
+    }
+
+    #[repr(C)]
+    struct CkFunctionList {
+        version: CkVersion,
+        initialize: Option<unsafe extern "C" fn(*mut c_void) -> CkRv>,
+        finalize: Option<unsafe extern "C" fn(*mut c_void) -> CkRv>,
+        get_info: Unused,
+        get_function_list: Unused,
+        get_slot_list: Option<unsafe extern "C" fn(u8, *mut CkUlong, *mut CkUlong) -> CkRv>,
+        get_slot_info: Unused,
+        get_token_info: Option<unsafe extern "C" fn(CkUlong, *mut CkTokenInfo) -> CkRv>,
+        get_mechanism_list: Unused,
+        get_mechanism_info: Unused,
+        init_token: Unused,
+        init_pin: Unused,
+        set_pin: Unused,
+        open_session: Option<
+            unsafe extern "C" fn(CkUlong, CkUlong, *mut c_void, *mut c_void, *mut CkUlong) -> CkRv,
+        >,
+        close_session: Option<unsafe extern "C" fn(CkUlong) -> CkRv>,
+        close_all_sessions: Unused,
+        get_session_info: Unused,
+        get_operation_state: Unused,
+        set_operation_state: Unused,
+        login: Option<unsafe extern "C" fn(CkUlong, CkUlong, *const u8, CkUlong) -> CkRv>,
+        logout: Unused,
+        create_object: Unused,
+        copy_object: Unused,
+        destroy_object: Unused,
+        get_object_size: Unused,
+        get_attribute_value: Unused,
+        set_attribute_value: Unused,
+        find_objects_init: Option<unsafe extern "C" fn(CkUlong, *mut CkAttribute, CkUlong) -> CkRv>,
+        find_objects:
+            Option<unsafe extern "C" fn(CkUlong, *mut CkUlong, CkUlong, *mut CkUlong) -> CkRv>,
+        find_objects_final: Option<unsafe extern "C" fn(CkUlong) -> CkRv>,
+        encrypt_init: Unused,
+        encrypt: Unused,
+        encrypt_update: Unused,
+        encrypt_final: Unused,
+        decrypt_init: Unused,
+        decrypt: Unused,
+        decrypt_update: Unused,
+        decrypt_final: Unused,
+        digest_init: Unused,
+        digest: Unused,
+        digest_update: Unused,
+        digest_key: Unused,
+        digest_final: Unused,
+        sign_init: Option<unsafe extern "C" fn(CkUlong, *mut CkMechanism, CkUlong) -> CkRv>,
+        sign: Option<
+            unsafe extern "C" fn(CkUlong, *const u8, CkUlong, *mut u8, *mut CkUlong) -> CkRv,
+        >,
+    }
+
+    type GetFunctionList = unsafe extern "C" fn(*mut *const CkFunctionList) -> CkRv;
+
+    pub(super) struct Pkcs11Signer {
+        // Keeps the module loaded for as long as `functions` is used.
+        _library: libloading::Library,
+        functions: &'static CkFunctionList,
+        session: CkUlong,
+        key: CkUlong,
+        finalize: bool,
+    }
+
+    impl Pkcs11Signer {
+        pub(super) fn open(
+            module: &str,
+            uri: &Pkcs11Uri,
+            pin: Option<String>,
+            alg: Algorithm,
+        ) -> AppResult<Self> {
+            mechanism::mechanism(alg)?;
+            // SAFETY: loading a PKCS#11 module runs its initializers; the user chose the module.
+            let library = unsafe { libloading::Library::new(module) }.map_err(|e| {
+                AppError::invalid_key(format!("pkcs11: failed to load module {module}: {e}"))
+            })?;
+            let functions: &'static CkFunctionList = unsafe {
+                let get: libloading::Symbol<GetFunctionList> =
+                    library.get(b"C_GetFunctionList\0").map_err(|e| {
+                        AppError::invalid_key(format!(
+                            "pkcs11: {module} is not a PKCS#11 module: {e}"
+                        ))
+                    })?;
+                let mut list: *const CkFunctionList = ptr::null();
+                check(get(&mut list), "C_GetFunctionList")?;
+                // SAFETY: the list stays valid while the library, owned by `Self`, is loaded.
+                list.as_ref().ok_or_else(|| {
+                    AppError::invalid_key("pkcs11: module returned no function list")
+                })?
+            };
+
+            let rv = unsafe { func(functions.initialize, "C_Initialize")?(ptr::null_mut()) };
+            let finalize = rv != CKR_CRYPTOKI_ALREADY_INITIALIZED;
+            if finalize {
+                check(rv, "C_Initialize")?;
+            }
+
+            let mut signer = Pkcs11Signer {
+                _library: library,
+                functions,
+                session: 0,
+                key: 0,
+                finalize,
+            };
+            let slot = signer.find_slot(uri)?;
+            signer.open_session(slot)?;
+            if let Some(pin) = pin {
+                signer.login(&pin)?;
+            }
+            signer.key = signer.find_key(uri)?;
+            Ok(signer)
+        }
+
+        fn find_slot(&self, uri: &Pkcs11Uri) -> AppResult<CkUlong> {
+            let get_slot_list = func(self.functions.get_slot_list, "C_GetSlotList")?;
+            let mut count: CkUlong = 0;
+            unsafe {
+                check(
+                    get_slot_list(1, ptr::null_mut(), &mut count),
+                    "C_GetSlotList",
+                )?
+            };
+            let mut slots = vec![0 as CkUlong; count as usize];
+            unsafe {
+                check(
+                    get_slot_list(1, slots.as_mut_ptr(), &mut count),
+                    "C_GetSlotList",
+                )?
+            };
+            slots.truncate(count as usize);
+
+            if let Some(wanted) = uri.slot_id {
+                return slots
+                    .into_iter()
+                    // CK_ULONG is 32-bit on Windows, so the cast is not a no-op everywhere.
+                    .find(|slot| {
+                        #[allow(clippy::unnecessary_cast)]
+                        let slot = *slot as u64;
+                        slot == wanted
+                    })
+                    .ok_or_else(|| {
+                        AppError::invalid_key(format!("pkcs11: no token present in slot {wanted}"))
+                    });
+            }
+
+            let Some(token) = &uri.token else {
+                return match slots.as_slice() {
+                    [slot] => Ok(*slot),
+                    [] => Err(AppError::invalid_key("pkcs11: no tokens present")),
+                    _ => Err(AppError::invalid_key(
+                        "pkcs11: several tokens present; add token=<label> or slot-id=<n>",
+                    )),
+                };
+            };
+            let get_token_info = func(self.functions.get_token_info, "C_GetTokenInfo")?;
+            for slot in slots {
+                let mut info = CkTokenInfo {
+                    label: [b' '; 32],
+                    rest: [0; 256],
+                };
+                unsafe { check(get_token_info(slot, &mut info), "C_GetTokenInfo")? };
+                let label = String::from_utf8_lossy(&info.label);
+                if label.trim_end() == token {
+                    return Ok(slot);
+                }
+            }
+            Err(AppError::invalid_key(format!(
+                "pkcs11: no token labelled '{token}'"
+            )))
+        }
+
+        fn open_session(&mut self, slot: CkUlong) -> AppResult<()> {
+            let open_session = func(self.functions.open_session, "C_OpenSession")?;
+            unsafe {
+                check(
+                    open_session(
+                        slot,
+                        CKF_SERIAL_SESSION,
+                        ptr::null_mut(),
+                        ptr::null_mut(),
+                        &mut self.session,
+                    ),
+                    "C_OpenSession",
+                )
+            }
+        }
+
+        fn login(&self, pin: &str) -> AppResult<()> {
+            let login = func(self.functions.login, "C_Login")?;
+            let rv = unsafe { login(self.session, CKU_USER, pin.as_ptr(), pin.len() as CkUlong) };
+            if rv == CKR_USER_ALREADY_LOGGED_IN {
+                return Ok(());
+            }
+            check(rv, "C_Login")
+        }
+
+        fn find_key(&self, uri: &Pkcs11Uri) -> AppResult<CkUlong> {
+            let mut class = CKO_PRIVATE_KEY;
+            let mut template = vec![CkAttribute {
+                kind: CKA_CLASS,
+                value: &mut class as *mut CkUlong as *mut c_void,
+                len: std::mem::size_of::<CkUlong>() as CkUlong,
+            }];
+            let mut id = uri.id.clone().unwrap_or_default();
+            if uri.id.is_some() {
+                template.push(CkAttribute {
+                    kind: CKA_ID,
+                    value: id.as_mut_ptr() as *mut c_void,
+                    len: id.len() as CkUlong,
+                });
+            }
+            let mut label = uri.object.clone().unwrap_or_default().into_bytes();
+            if uri.object.is_some() {
+                template.push(CkAttribute {
+                    kind: CKA_LABEL,
+                    value: label.as_mut_ptr() as *mut c_void,
+                    len: label.len() as CkUlong,
+                });
+            }
+
+            let init = func(self.functions.find_objects_init, "C_FindObjectsInit")?;
+            let find = func(self.functions.find_objects, "C_FindObjects")?;
+            let finish = func(self.functions.find_objects_final, "C_FindObjectsFinal")?;
+            let mut found = [0 as CkUlong; 2];
+            let mut count: CkUlong = 0;
+            unsafe {
+                check(
+                    init(
+                        self.session,
+                        template.as_mut_ptr(),
+                        template.len() as CkUlong,
+                    ),
+                    "C_FindObjectsInit",
+                )?;
+                let rv = find(self.session, found.as_mut_ptr(), 2, &mut count);
+                finish(self.session);
+                check(rv, "C_FindObjects")?;
+            }
+            match count {
+                0 => Err(AppError::invalid_key(format!(
+                    "pkcs11: private key not found (log in with pin-source or {})",
+                    super::PIN_ENV
+                ))),
+                1 => Ok(found[0]),
+                _ => Err(AppError::invalid_key(
+                    "pkcs11: several private keys match; add id=<hex> or object=<label>",
+                )),
+            }
+        }
+    }
+
+    impl ExternalSigner for Pkcs11Signer {
+        fn sign(&self, alg: Algorithm, message: &[u8]) -> AppResult<Vec<u8>> {
+            let request = mechanism::signing_input(alg, message)?;
+            let mut pss_params = request.pss.map(|(hash_alg, mgf, salt_len)| CkRsaPssParams {
+                hash_alg,
+                mgf,
+                salt_len,
+            });
+            let (parameter, parameter_len) = match pss_params.as_mut() {
+                Some(params) => (
+                    params as *mut CkRsaPssParams as *mut c_void,
+                    std::mem::size_of::<CkRsaPssParams>() as CkUlong,
+                ),
+                None => (ptr::null_mut(), 0),
+            };
+            let mut mechanism = CkMechanism {
+                mechanism: request.mechanism,
+                parameter,
+                len: parameter_len,
+            };
+            let data = request.data;
+
+            let sign_init = func(self.functions.sign_init, "C_SignInit")?;
+            let sign = func(self.functions.sign, "C_Sign")?;
+            unsafe {
+                check(
+                    sign_init(self.session, &mut mechanism, self.key),
+                    "C_SignInit",
+                )?;
+                let mut len: CkUlong = 0;
+                check(
+                    sign(
+                        self.session,
+                        data.as_ptr(),
+                        data.len() as CkUlong,
+                        ptr::null_mut(),
+                        &mut len,
+                    ),
+                    "C_Sign",
+                )?;
+                let mut signature = vec![0u8; len as usize];
+                check(
+                    sign(
+                        self.session,
+                        data.as_ptr(),
+                        data.len() as CkUlong,
+                        signature.as_mut_ptr(),
+                        &mut len,
+                    ),
+                    "C_Sign",
+                )?;
+                signature.truncate(len as usize);
+                mechanism::check_signature(alg, &signature)?;
+                Ok(signature)
+            }
+        }
+    }
+
+    impl Drop for Pkcs11Signer {
+        fn drop(&mut self) {
+            unsafe {
+                if let Some(close) = self.functions.close_session {
+                    close(self.session);
+                }
+                if self.finalize {
+                    if let Some(finalize) = self.functions.finalize {
+                        finalize(ptr::null_mut());
+                    }
+                }
+            }
+        }
+    }
+
+    fn func<F>(f: Option<F>, name: &str) -> AppResult<F> {
+        f.ok_or_else(|| AppError::invalid_key(format!("pkcs11: module does not implement {name}")))
+    }
+
+    fn check(rv: CkRv, what: &str) -> AppResult<()> {
+        if rv == CKR_OK {
+            Ok(())
+        } else {
+            Err(AppError::invalid_key(format!(
+                "pkcs11: {what} failed (CKR 0x{rv:x})"
+            )))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::mechanism::*;
+    use super::Pkcs11Uri;
+    use jsonwebtoken::Algorithm;
+    use sha2::{Digest, Sha256, Sha384};
+
+    #[test]
+    fn parses_token_id_and_query() {
+        let uri = Pkcs11Uri::parse(
+            "token=dev;id=01;object=signing%20key?module-path=/usr/lib/softhsm/libsofthsm2.so&pin-source=file:/run/secrets/pin",
+        )
+        .expect("parse");
+        assert_eq!(uri.token.as_deref(), Some("dev"));
+        assert_eq!(uri.id, Some(vec![0x01]));
+        assert_eq!(uri.object.as_deref(), Some("signing key"));
+        assert_eq!(
+            uri.module_path.as_deref(),
+            Some("/usr/lib/softhsm/libsofthsm2.so")
+        );
+        assert_eq!(uri.pin_source.as_deref(), Some("@/run/secrets/pin"));
+        assert_eq!(uri.label(), "pkcs11:token=dev;object=signing key;id=01");
+    }
+
+    #[test]
+    fn pin_comes_from_pin_source_never_the_uri() {
+        let err = Pkcs11Uri::parse("id=01?pin-value=1234").expect_err("pin-value");
+        assert!(err.message.contains("pin-source"), "{}", err.message);
+        assert!(!err.message.contains("1234"));
+
+        for (source, spec) in [
+            ("file:///run/pin", "@/run/pin"),
+            ("/run/pin", "@/run/pin"),
+            ("fd:3", "fd:3"),
+            ("prompt", "prompt"),
+            ("prompt:Token%20PIN:%20", "prompt:Token PIN: "),
+            ("env:HSM_PIN", "env:HSM_PIN"),
+        ] {
+            let uri = Pkcs11Uri::parse(&format!("id=01?pin-source={source}")).expect(source);
+            assert_eq!(uri.pin_source.as_deref(), Some(spec), "{source}");
+        }
+        assert!(Pkcs11Uri::parse("id=01?pin-source=1234").is_err());
+        assert!(Pkcs11Uri::parse("id=01?pin-source=|cat%20pin").is_err());
+
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("pin");
+        std::fs::write(&path, "4321\n").expect("write pin");
+        let uri =
+            Pkcs11Uri::parse(&format!("id=01?pin-source=file:{}", path.display())).expect("parse");
+        assert_eq!(uri.pin().expect("pin").as_deref(), Some("4321"));
+        let missing = Pkcs11Uri::parse("id=01?pin-source=file:/nonexistent/pin").expect("parse");
+        assert!(missing.pin().is_err());
+    }
+
+    #[test]
+    fn rsa_mechanisms_hash_inside_the_token() {
+        let message = b"header.payload";
+        for (alg, expected) in [
+            (Algorithm::RS256, CKM_SHA256_RSA_PKCS),
+            (Algorithm::RS384, CKM_SHA384_RSA_PKCS),
+            (Algorithm::RS512, CKM_SHA512_RSA_PKCS),
+        ] {
+            let input = signing_input(alg, message).expect("rsa");
+            assert_eq!(input.mechanism, expected);
+            assert!(input.pss.is_none());
+            assert_eq!(input.data, message);
+        }
+        for (alg, expected, hash, mgf, salt) in [
+            (
+                Algorithm::PS256,
+                CKM_SHA256_RSA_PKCS_PSS,
+                CKM_SHA256,
+                CKG_MGF1_SHA256,
+                32,
+            ),
+            (
+                Algorithm::PS384,
+                CKM_SHA384_RSA_PKCS_PSS,
+                CKM_SHA384,
+                CKG_MGF1_SHA384,
+                48,
+            ),
+            (
+                Algorithm::PS512,
+                CKM_SHA512_RSA_PKCS_PSS,
+                CKM_SHA512,
+                CKG_MGF1_SHA512,
+                64,
+            ),
+        ] {
+            let input = signing_input(alg, message).expect("pss");
+            assert_eq!(input.mechanism, expected);
+            assert_eq!(input.pss, Some((hash, mgf, salt)));
+            assert_eq!(input.data, message);
+        }
+    }
+
+    #[test]
+    fn ecdsa_signs_a_local_digest_and_eddsa_the_message() {
+        let message = b"header.payload";
+        let es256 = signing_input(Algorithm::ES256, message).expect("es256");
+        assert_eq!(es256.mechanism, CKM_ECDSA);
+        assert_eq!(es256.data, Sha256::digest(message).to_vec());
+        let es384 = signing_input(Algorithm::ES384, message).expect("es384");
+        assert_eq!(es384.mechanism, CKM_ECDSA);
+        assert_eq!(es384.data, Sha384::digest(message).to_vec());
+        let eddsa = signing_input(Algorithm::EdDSA, message).expect("eddsa");
+        assert_eq!(eddsa.mechanism, CKM_EDDSA);
+        assert!(eddsa.pss.is_none());
+        assert_eq!(eddsa.data, message);
+        assert!(signing_input(Algorithm::HS256, message).is_err());
+    }
+
+    #[test]
+    fn signatures_must_use_the_raw_jws_encoding() {
+        assert!(check_signature(Algorithm::ES256, &[0; 64]).is_ok());
+        assert!(check_signature(Algorithm::ES384, &[0; 96]).is_ok());
+        assert!(check_signature(Algorithm::EdDSA, &[0; 64]).is_ok());
+        assert!(check_signature(Algorithm::EdDSA, &[0; 114]).is_ok());
+        assert!(check_signature(Algorithm::RS256, &[0; 256]).is_ok());
+        // A DER-encoded ECDSA signature (SEQUENCE of two INTEGERs) is 70-72 bytes for P-256.
+        let err = check_signature(Algorithm::ES256, &[0x30; 71]).expect_err("der");
+        assert!(err.message.contains("expected 64 bytes"), "{}", err.message);
+        assert!(check_signature(Algorithm::ES384, &[0; 64]).is_err());
+    }
+
+    #[test]
+    fn percent_encoded_id_and_slot() {
+        let uri = Pkcs11Uri::parse("slot-id=3;id=%01%AB").expect("parse");
+        assert_eq!(uri.slot_id, Some(3));
+        assert_eq!(uri.id, Some(vec![0x01, 0xab]));
+    }
+
+    #[test]
+    fn rejects_missing_key_selector_and_unknown_attributes() {
+        assert!(Pkcs11Uri::parse("token=dev").is_err());
+        assert!(Pkcs11Uri::parse("token=dev;id=zz").is_err());
+        assert!(Pkcs11Uri::parse("token=dev;id=01;colour=blue").is_err());
+        assert!(Pkcs11Uri::parse("id=01?slot=1").is_err());
+    }
+}
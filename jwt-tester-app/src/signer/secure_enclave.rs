@@ -0,0 +1,417 @@
+use super::ExternalSigner;
+use crate::error::{AppError, AppResult};
+use jsonwebtoken::Algorithm;
+
+#[cfg(target_os = "macos")]
+pub(super) fn open_signer(label: &str, alg: Algorithm) -> AppResult<Box<dyn ExternalSigner>> {
+    let signer = ffi::KeychainSigner::find(label, alg)?;
+    Ok(Box::new(signer))
+}
+
+/// Generates a P-256 key inside the Secure Enclave and stores it in the keychain under `label`.
+/// Only a reference to it ever reaches this process.
+#[cfg(target_os = "macos")]
+pub(super) fn create_key(label: &str) -> AppResult<()> {
+    ffi::create_key(label)
+}
+
+#[cfg(not(target_os = "macos"))]
+pub(super) fn open_signer(label: &str, _alg: Algorithm) -> AppResult<Box<dyn ExternalSigner>> {
+    Err(unavailable(label))
+}
+
+#[cfg(not(target_os = "macos"))]
+pub(super) fn create_key(label: &str) -> AppResult<()> {
+    Err(unavailable(label))
+}
+
+#[cfg(not(target_os = "macos"))]
+fn unavailable(label: &str) -> AppError {
+    AppError::invalid_key(format!(
+        "secure-enclave:{label}: Secure Enclave keys are only available on macOS"
+    ))
+}
+
+/// Converts a DER `ECDSA-Sig-Value` (`SEQUENCE { INTEGER r, INTEGER s }`), which is what the
+/// Security framework returns, to the fixed-width `r || s` form JWS uses.
+#[cfg(any(target_os = "macos", test))]
+fn ecdsa_der_to_jws(der: &[u8], width: usize) -> AppResult<Vec<u8>> {
+    let invalid = || AppError::invalid_key("secure-enclave: malformed ECDSA signature");
+    let (tag, body, rest) = der_element(der).ok_or_else(invalid)?;
+    if tag != 0x30 || !rest.is_empty() {
+        return Err(invalid());
+    }
+    let mut out = Vec::with_capacity(width * 2);
+    let mut remaining = body;
+    for _ in 0..2 {
+        let (tag, integer, rest) = der_element(remaining).ok_or_else(invalid)?;
+        if tag != 0x02 {
+            return Err(invalid());
+        }
+        let trimmed = match integer.iter().position(|b| *b != 0) {
+            Some(start) => &integer[start..],
+            None => &[][..],
+        };
+        if trimmed.len() > width {
+            return Err(invalid());
+        }
+        out.extend(std::iter::repeat_n(0u8, width - trimmed.len()));
+        out.extend_from_slice(trimmed);
+        remaining = rest;
+    }
+    if !remaining.is_empty() {
+        return Err(invalid());
+    }
+    Ok(out)
+}
+
+/// Splits one DER element off `input`: its tag, contents and whatever follows it. Signatures
+/// never need more than a one-byte long-form length.
+#[cfg(any(target_os = "macos", test))]
+fn der_element(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = input.split_first()?;
+    let (&first, rest) = rest.split_first()?;
+    let (len, rest) = match first {
+        0x00..=0x7f => (first as usize, rest),
+        0x81 => {
+            let (&len, rest) = rest.split_first()?;
+            (len as usize, rest)
+        }
+        _ => return None,
+    };
+    (rest.len() >= len).then(|| (tag, &rest[..len], &rest[len..]))
+}
+
+#[cfg(target_os = "macos")]
+mod ffi {
+    //! Security framework calls to create a Secure Enclave key, look it up by label and sign with
+    //! it. Keys in the Secure Enclave cannot be exported, so signing has to go through
+    //! `SecKeyCreateSignature`.
+
+    use super::{ecdsa_der_to_jws, ExternalSigner};
+    use crate::error::{AppError, AppResult};
+    use jsonwebtoken::Algorithm;
+    use std::ffi::c_void;
+    use std::ptr;
+
+    type CFTypeRef = *const c_void;
+    type CFStringRef = *const c_void;
+    type CFDataRef = *const c_void;
+    type CFErrorRef = *const c_void;
+    type CFDictionaryRef = *const c_void;
+    type SecKeyRef = *const c_void;
+    type CFNumberRef = *const c_void;
+    type SecAccessControlRef = *const c_void;
+    type CFIndex = isize;
+    type OSStatus = i32;
+
+    const ERR_SEC_SUCCESS: OSStatus = 0;
+    const ERR_SEC_ITEM_NOT_FOUND: OSStatus = -25300;
+    const CF_STRING_ENCODING_UTF8: u32 = 0x0800_0100;
+    /// `kCFNumberSInt32Type`.
+    const CF_NUMBER_SINT32: CFIndex = 3;
+    /// `kSecAccessControlPrivateKeyUsage`: the key may sign, nothing else.
+    const ACCESS_CONTROL_PRIVATE_KEY_USAGE: usize = 1 << 30;
+    const P256_BITS: i32 = 256;
+
+    /// Opaque `CFDictionaryKeyCallBacks` / `CFDictionaryValueCallBacks`; only their address is used.
+    #[repr(C)]
+    struct CallBacks {
+        _private: [u8; 0],
+    }
+
+    #[link(name = "CoreFoundation", kind = "framework")]
+    extern "C" {
+        static kCFBooleanTrue: CFTypeRef;
+        static kCFTypeDictionaryKeyCallBacks: CallBacks;
+        static kCFTypeDictionaryValueCallBacks: CallBacks;
+
+        fn CFStringCreateWithBytes(
+            allocator: CFTypeRef,
+            bytes: *const u8,
+            len: CFIndex,
+            encoding: u32,
+            external: u8,
+        ) -> CFStringRef;
+        fn CFDataCreate(allocator: CFTypeRef, bytes: *const u8, len: CFIndex) -> CFDataRef;
+        fn CFNumberCreate(allocator: CFTypeRef, kind: CFIndex, value: *const c_void)
+            -> CFNumberRef;
+        fn CFDataGetLength(data: CFDataRef) -> CFIndex;
+        fn CFDataGetBytePtr(data: CFDataRef) -> *const u8;
+        fn CFDictionaryCreate(
+            allocator: CFTypeRef,
+            keys: *const CFTypeRef,
+            values: *const CFTypeRef,
+            count: CFIndex,
+            key_callbacks: *const CallBacks,
+            value_callbacks: *const CallBacks,
+        ) -> CFDictionaryRef;
+        fn CFErrorGetCode(error: CFErrorRef) -> CFIndex;
+        fn CFRelease(object: CFTypeRef);
+    }
+
+    #[link(name = "Security", kind = "framework")]
+    extern "C" {
+        static kSecClass: CFStringRef;
+        static kSecClassKey: CFStringRef;
+        static kSecAttrKeyClass: CFStringRef;
+        static kSecAttrKeyClassPrivate: CFStringRef;
+        static kSecAttrLabel: CFStringRef;
+        static kSecReturnRef: CFStringRef;
+        static kSecMatchLimit: CFStringRef;
+        static kSecMatchLimitOne: CFStringRef;
+        static kSecAttrKeyType: CFStringRef;
+        static kSecAttrKeyTypeECSECPrimeRandom: CFStringRef;
+        static kSecAttrKeySizeInBits: CFStringRef;
+        static kSecAttrTokenID: CFStringRef;
+        static kSecAttrTokenIDSecureEnclave: CFStringRef;
+        static kSecAttrIsPermanent: CFStringRef;
+        static kSecAttrAccessControl: CFStringRef;
+        static kSecAttrAccessibleWhenUnlockedThisDeviceOnly: CFStringRef;
+        static kSecPrivateKeyAttrs: CFStringRef;
+        static kSecKeyAlgorithmECDSASignatureMessageX962SHA256: CFStringRef;
+
+        fn SecItemCopyMatching(query: CFDictionaryRef, result: *mut CFTypeRef) -> OSStatus;
+        fn SecAccessControlCreateWithFlags(
+            allocator: CFTypeRef,
+            protection: CFTypeRef,
+            flags: usize,
+            error: *mut CFErrorRef,
+        ) -> SecAccessControlRef;
+        fn SecKeyCreateRandomKey(parameters: CFDictionaryRef, error: *mut CFErrorRef) -> SecKeyRef;
+        fn SecKeyIsAlgorithmSupported(key: SecKeyRef, operation: CFIndex, alg: CFStringRef) -> u8;
+        fn SecKeyCreateSignature(
+            key: SecKeyRef,
+            alg: CFStringRef,
+            data: CFDataRef,
+            error: *mut CFErrorRef,
+        ) -> CFDataRef;
+    }
+
+    /// `kSecKeyOperationTypeSign`.
+    const KEY_OPERATION_SIGN: CFIndex = 0;
+
+    /// Releases a Core Foundation object when dropped.
+    struct Owned(CFTypeRef);
+
+    impl Drop for Owned {
+        fn drop(&mut self) {
+            if !self.0.is_null() {
+                unsafe { CFRelease(self.0) }
+            }
+        }
+    }
+
+    fn cf_string(value: &str) -> Owned {
+        Owned(unsafe {
+            CFStringCreateWithBytes(
+                ptr::null(),
+                value.as_ptr(),
+                value.len() as CFIndex,
+                CF_STRING_ENCODING_UTF8,
+                0,
+            )
+        })
+    }
+
+    /// Builds a `CFDictionary` retaining its keys and values.
+    unsafe fn dictionary(keys: &[CFTypeRef], values: &[CFTypeRef]) -> Owned {
+        Owned(CFDictionaryCreate(
+            ptr::null(),
+            keys.as_ptr(),
+            values.as_ptr(),
+            keys.len() as CFIndex,
+            &kCFTypeDictionaryKeyCallBacks,
+            &kCFTypeDictionaryValueCallBacks,
+        ))
+    }
+
+    fn error_code(error: &Owned) -> CFIndex {
+        if error.0.is_null() {
+            0
+        } else {
+            unsafe { CFErrorGetCode(error.0) }
+        }
+    }
+
+    pub(super) fn create_key(label: &str) -> AppResult<()> {
+        let name = format!("secure-enclave:{label}");
+        if find_key(label).is_ok() {
+            return Err(AppError::invalid_key(format!(
+                "{name}: a key with this label already exists"
+            )));
+        }
+        let label_ref = cf_string(label);
+        let mut error: CFErrorRef = ptr::null();
+        let access = Owned(unsafe {
+            SecAccessControlCreateWithFlags(
+                ptr::null(),
+                kSecAttrAccessibleWhenUnlockedThisDeviceOnly,
+                ACCESS_CONTROL_PRIVATE_KEY_USAGE,
+                &mut error,
+            )
+        });
+        let error = Owned(error);
+        if access.0.is_null() {
+            return Err(AppError::invalid_key(format!(
+                "{name}: SecAccessControlCreateWithFlags failed (error {})",
+                error_code(&error)
+            )));
+        }
+        let bits = Owned(unsafe {
+            CFNumberCreate(
+                ptr::null(),
+                CF_NUMBER_SINT32,
+                &P256_BITS as *const i32 as *const c_void,
+            )
+        });
+        let mut error: CFErrorRef = ptr::null();
+        let key = unsafe {
+            let private = dictionary(
+                &[kSecAttrIsPermanent, kSecAttrLabel, kSecAttrAccessControl],
+                &[kCFBooleanTrue, label_ref.0, access.0],
+            );
+            let parameters = dictionary(
+                &[
+                    kSecAttrKeyType,
+                    kSecAttrKeySizeInBits,
+                    kSecAttrTokenID,
+                    kSecPrivateKeyAttrs,
+                ],
+                &[
+                    kSecAttrKeyTypeECSECPrimeRandom,
+                    bits.0,
+                    kSecAttrTokenIDSecureEnclave,
+                    private.0,
+                ],
+            );
+            Owned(SecKeyCreateRandomKey(parameters.0, &mut error))
+        };
+        let error = Owned(error);
+        if key.0.is_null() {
+            return Err(AppError::invalid_key(format!(
+                "{name}: SecKeyCreateRandomKey failed (error {}); Secure Enclave keys need a \
+                 Mac with a Secure Enclave and a signed binary with keychain access",
+                error_code(&error)
+            )));
+        }
+        Ok(())
+    }
+
+    /// Looks up the private key labelled `label`, mapping a miss to a readable error.
+    fn find_key(label: &str) -> AppResult<Owned> {
+        let name = format!("secure-enclave:{label}");
+        let label_ref = cf_string(label);
+        let mut key: CFTypeRef = ptr::null();
+        let status = unsafe {
+            let query = dictionary(
+                &[
+                    kSecClass,
+                    kSecAttrKeyClass,
+                    kSecAttrLabel,
+                    kSecReturnRef,
+                    kSecMatchLimit,
+                ],
+                &[
+                    kSecClassKey,
+                    kSecAttrKeyClassPrivate,
+                    label_ref.0,
+                    kCFBooleanTrue,
+                    kSecMatchLimitOne,
+                ],
+            );
+            SecItemCopyMatching(query.0, &mut key)
+        };
+        let key = Owned(key);
+        match status {
+            ERR_SEC_SUCCESS => Ok(key),
+            ERR_SEC_ITEM_NOT_FOUND => Err(AppError::invalid_key(format!(
+                "{name}: no private key with this label in the keychain"
+            ))),
+            other => Err(AppError::invalid_key(format!(
+                "{name}: keychain lookup failed (OSStatus {other})"
+            ))),
+        }
+    }
+
+    pub(super) struct KeychainSigner {
+        label: String,
+        key: Owned,
+    }
+
+    impl KeychainSigner {
+        pub(super) fn find(label: &str, alg: Algorithm) -> AppResult<Self> {
+            let name = format!("secure-enclave:{label}");
+            let key = find_key(label)?;
+            if unsafe { SecKeyIsAlgorithmSupported(key.0, KEY_OPERATION_SIGN, algorithm(alg)?) }
+                == 0
+            {
+                return Err(AppError::invalid_key(format!(
+                    "{name}: key cannot sign {alg:?}"
+                )));
+            }
+            Ok(KeychainSigner { label: name, key })
+        }
+    }
+
+    /// The message variant hashes inside the Security framework. The Secure Enclave only holds
+    /// P-256 keys, so ES256 is the one algorithm.
+    fn algorithm(alg: Algorithm) -> AppResult<CFStringRef> {
+        match alg {
+            Algorithm::ES256 => Ok(unsafe { kSecKeyAlgorithmECDSASignatureMessageX962SHA256 }),
+            other => Err(AppError::invalid_key(format!(
+                "secure-enclave keys cannot sign {other:?}"
+            ))),
+        }
+    }
+
+    impl ExternalSigner for KeychainSigner {
+        fn sign(&self, alg: Algorithm, message: &[u8]) -> AppResult<Vec<u8>> {
+            let algorithm = algorithm(alg)?;
+            let data = Owned(unsafe {
+                CFDataCreate(ptr::null(), message.as_ptr(), message.len() as CFIndex)
+            });
+            let mut error: CFErrorRef = ptr::null();
+            let signature =
+                Owned(unsafe { SecKeyCreateSignature(self.key.0, algorithm, data.0, &mut error) });
+            let error = Owned(error);
+            if signature.0.is_null() {
+                return Err(AppError::invalid_key(format!(
+                    "{}: SecKeyCreateSignature failed (error {})",
+                    self.label,
+                    error_code(&error)
+                )));
+            }
+            let bytes = unsafe {
+                std::slice::from_raw_parts(
+                    CFDataGetBytePtr(signature.0),
+                    CFDataGetLength(signature.0) as usize,
+                )
+            }
+            .to_vec();
+            ecdsa_der_to_jws(&bytes, 32)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ecdsa_der_to_jws;
+
+    #[test]
+    fn der_signatures_become_fixed_width() {
+        // r has a leading zero to keep it positive, s is one byte short of the width.
+        let mut der = vec![0x30, 0x44, 0x02, 0x21, 0x00];
+        der.extend([0x80; 32]);
+        der.extend([0x02, 0x1f]);
+        der.extend([0x11; 31]);
+        let raw = ecdsa_der_to_jws(&der, 32).expect("convert");
+        assert_eq!(raw.len(), 64);
+        assert_eq!(&raw[..32], &[0x80; 32]);
+        assert_eq!(raw[32], 0);
+        assert_eq!(&raw[33..], &[0x11; 31]);
+
+        assert!(ecdsa_der_to_jws(&der[..der.len() - 1], 32).is_err());
+        assert!(ecdsa_der_to_jws(&[0x31, 0x00], 32).is_err());
+    }
+}
@@ -0,0 +1,164 @@
+use crate::cli::{Command, VerifyCommonArgs, WebhookCmd};
+use crate::error::AppError;
+use crate::history::describe_key_spec;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::time::Instant;
+use tracing::warn;
+
+/// Where `--summary-file` goes and what is known about the run before it finishes.
+#[derive(Debug)]
+pub struct SummaryFile {
+    path: PathBuf,
+    command: &'static str,
+    key_source: Option<String>,
+    started: Instant,
+}
+
+impl SummaryFile {
+    /// Leaked so `OutputConfig` stays `Copy`; there is one summary per process.
+    pub fn start(path: PathBuf, command: &Command) -> &'static SummaryFile {
+        Box::leak(Box::new(SummaryFile {
+            path,
+            command: command_name(command),
+            key_source: key_source(command),
+            started: Instant::now(),
+        }))
+    }
+
+    pub fn write_ok(&self) {
+        self.write(json!({ "status": "ok", "exit_code": 0, "error": null }));
+    }
+
+    pub fn write_err(&self, err: &AppError) {
+        self.write(json!({
+            "status": "error",
+            "exit_code": err.exit_code(),
+            "error": { "code": err.code(), "message": err.to_string() },
+        }));
+    }
+
+    /// Commands may emit more than once (e.g. `monitor`); the last outcome wins. Failing to
+    /// write the summary never changes the command's own result.
+    fn write(&self, outcome: Value) {
+        let mut summary = outcome;
+        summary["command"] = json!(self.command);
+        summary["key_source"] = json!(self.key_source);
+        summary["duration_ms"] = json!(self.started.elapsed().as_millis() as u64);
+        let body = serde_json::to_string_pretty(&summary).unwrap_or_default();
+        if let Err(err) = std::fs::write(&self.path, format!("{body}\n")) {
+            warn!("failed to write summary file {:?}: {err}", self.path);
+        }
+    }
+}
+
+fn command_name(command: &Command) -> &'static str {
+    match command {
+        #[cfg(feature = "ui")]
+        Command::Ui(_) => "ui",
+        Command::Vault(_) => "vault",
+        Command::Decode(_) => "decode",
+        Command::Verify(_) => "verify",
+        Command::Encode(_) => "encode",
+        Command::Inspect(_) => "inspect",
+        Command::Analyze(_) => "analyze",
+        Command::Anonymize(_) => "anonymize",
+        Command::Lint(_) => "lint",
+        Command::LintClaims(_) => "lint-claims",
+        Command::Advise(_) => "advise",
+        Command::Split(_) => "split",
+        Command::History(_) => "history",
+        Command::Webhook(_) => "webhook",
+        Command::FetchToken(_) => "fetch-token",
+        Command::Monitor(_) => "monitor",
+        Command::Replay(_) => "replay",
+        Command::Examples(_) => "examples",
+        Command::Init(_) => "init",
+        #[cfg(feature = "ui")]
+        Command::Issuer(_) => "issuer",
+        #[cfg(feature = "ui")]
+        Command::Serve(_) => "serve",
+        Command::Completion(_) => "completion",
+        #[cfg(feature = "self-update")]
+        Command::SelfUpdate(_) => "self-update",
+    }
+}
+
+/// Describes the signing/verification key the same way history does (no key material).
+fn key_source(command: &Command) -> Option<String> {
+    match command {
+        Command::Encode(args) => describe_source(
+            args.secret.as_deref(),
+            args.key.as_deref(),
+            &[],
+            args.project.as_deref(),
+            args.key_id
+                .as_deref()
+                .or(args.key_name.as_deref())
+                .or(args.key_fingerprint.as_deref()),
+        ),
+        Command::Verify(args) => describe_verify_source(&args.verify),
+        Command::Webhook(args) => match &args.cmd {
+            WebhookCmd::Verify(verify) => describe_verify_source(&verify.verify),
+        },
+        _ => None,
+    }
+}
+
+fn describe_verify_source(args: &VerifyCommonArgs) -> Option<String> {
+    describe_source(
+        args.secret.as_deref(),
+        args.key.as_deref(),
+        &args.jwks,
+        args.project.as_deref(),
+        args.key_id
+            .as_deref()
+            .or(args.key_name.as_deref())
+            .or(args.key_fingerprint.as_deref()),
+    )
+}
+
+fn describe_source(
+    secret: Option<&str>,
+    key: Option<&str>,
+    jwks: &[String],
+    project: Option<&str>,
+    vault_key: Option<&str>,
+) -> Option<String> {
+    if let Some(secret) = secret {
+        return Some(format!("secret:{}", describe_key_spec(secret)));
+    }
+    if let Some(key) = key {
+        return Some(format!("key:{}", describe_key_spec(key)));
+    }
+    if !jwks.is_empty() {
+        let sources: Vec<String> = jwks.iter().map(|spec| describe_key_spec(spec)).collect();
+        return Some(format!("jwks:{}", sources.join(",")));
+    }
+    project.map(|project| match vault_key {
+        Some(key) => format!("vault:{project}/{key}"),
+        None => format!("vault:{project}"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::describe_source;
+
+    #[test]
+    fn describe_source_never_includes_inline_material() {
+        assert_eq!(
+            describe_source(Some("hunter2"), None, &[], None, None).as_deref(),
+            Some("secret:<inline>")
+        );
+        assert_eq!(
+            describe_source(None, None, &["https://idp/jwks".to_string()], None, None).as_deref(),
+            Some("jwks:https://idp/jwks")
+        );
+        assert_eq!(
+            describe_source(None, None, &[], Some("api"), Some("signing")).as_deref(),
+            Some("vault:api/signing")
+        );
+        assert_eq!(describe_source(None, None, &[], None, None), None);
+    }
+}
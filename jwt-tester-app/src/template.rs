@@ -0,0 +1,146 @@
+#[cfg(feature = "template")]
+use crate::claims::now_epoch;
+#[cfg(feature = "template")]
+use crate::date_utils::{format_timestamp, DateMode};
+use crate::error::{AppError, AppResult};
+#[cfg(feature = "template")]
+use crate::io_utils::read_input;
+#[cfg(feature = "template")]
+use crate::jwt_ops;
+#[cfg(feature = "template")]
+use minijinja::Environment;
+use serde_json::Value;
+#[cfg(feature = "template")]
+use serde_json::{json, Map};
+#[cfg(feature = "template")]
+use std::sync::OnceLock;
+#[cfg(feature = "template")]
+use std::time::Duration;
+
+/// The process-wide `--template` source; there is one per invocation.
+#[cfg(feature = "template")]
+static SOURCE: OnceLock<String> = OnceLock::new();
+
+/// Reads a `--template` value (inline, `@file`, or `env:NAME`) and checks its syntax up front,
+/// so a typo fails before the command runs. The template lives for the rest of the process.
+#[cfg(feature = "template")]
+pub fn load(spec: &str) -> AppResult<&'static str> {
+    let source = read_input(spec)?;
+    Environment::new()
+        .template_from_str(&source)
+        .map_err(|e| AppError::invalid_input(format!("invalid --template: {e}")))?;
+    Ok(SOURCE.get_or_init(|| source))
+}
+
+/// Renders a template against a command's JSON `data`, enriched with `claims`, `header`, and
+/// `dates.<exp|nbf|iat>.{raw,rfc3339,relative}` where a token or its claims are available.
+#[cfg(feature = "template")]
+pub fn render(source: &str, data: &Value) -> AppResult<String> {
+    let context = build_context(data, now_epoch());
+    Environment::new()
+        .render_str(source, context)
+        .map_err(|e| AppError::invalid_input(format!("failed to render --template: {e}")))
+}
+
+#[cfg(not(feature = "template"))]
+pub fn load(_spec: &str) -> AppResult<&'static str> {
+    Err(unavailable())
+}
+
+#[cfg(not(feature = "template"))]
+pub fn render(_source: &str, _data: &Value) -> AppResult<String> {
+    Err(unavailable())
+}
+
+#[cfg(not(feature = "template"))]
+fn unavailable() -> AppError {
+    AppError::invalid_input("--template requires a build with the `template` feature")
+}
+
+#[cfg(feature = "template")]
+fn build_context(data: &Value, now: i64) -> Value {
+    let mut context = match data {
+        Value::Object(map) => map.clone(),
+        other => {
+            let mut map = Map::new();
+            map.insert("data".to_string(), other.clone());
+            map
+        }
+    };
+
+    // decode reports `payload`, verify `claims`; encode only has the token it produced.
+    if !context.contains_key("claims") {
+        if let Some(payload) = context.get("payload").cloned() {
+            context.insert("claims".to_string(), payload);
+        } else if let Some(decoded) = context
+            .get("token")
+            .and_then(Value::as_str)
+            .and_then(|token| jwt_ops::decode_unverified(token).ok())
+        {
+            context.insert("claims".to_string(), decoded.payload_json);
+            context
+                .entry("header".to_string())
+                .or_insert(decoded.header_json);
+        }
+    }
+
+    let claims = context.get("claims").cloned().unwrap_or(Value::Null);
+    let mut dates = match context.remove("dates") {
+        Some(Value::Object(dates)) => dates,
+        _ => Map::new(),
+    };
+    for key in ["exp", "nbf", "iat"] {
+        let Some(ts) = claims.get(key).and_then(Value::as_i64) else {
+            continue;
+        };
+        let rfc3339 = dates
+            .get(key)
+            .and_then(|entry| entry.get("rfc3339"))
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .or_else(|| format_timestamp(ts, DateMode::Utc).ok());
+        dates.insert(
+            key.to_string(),
+            json!({ "raw": ts, "rfc3339": rfc3339, "relative": relative(ts, now) }),
+        );
+    }
+    context.insert("dates".to_string(), Value::Object(dates));
+    Value::Object(context)
+}
+
+#[cfg(feature = "template")]
+fn relative(ts: i64, now: i64) -> String {
+    let span = humantime::format_duration(Duration::from_secs(ts.abs_diff(now)));
+    match ts.cmp(&now) {
+        std::cmp::Ordering::Greater => format!("in {span}"),
+        std::cmp::Ordering::Less => format!("{span} ago"),
+        std::cmp::Ordering::Equal => "now".to_string(),
+    }
+}
+
+#[cfg(all(test, feature = "template"))]
+mod tests {
+    use super::{build_context, render};
+    use serde_json::json;
+
+    #[test]
+    fn context_adds_claims_and_relative_dates() {
+        let data = json!({ "valid": true, "claims": { "sub": "u1", "exp": 1_090, "iat": 1_000 } });
+        let context = build_context(&data, 1_030);
+        assert_eq!(context["dates"]["exp"]["relative"], "in 1m");
+        assert_eq!(context["dates"]["iat"]["relative"], "30s ago");
+        assert_eq!(context["dates"]["exp"]["rfc3339"], "1970-01-01T00:18:10Z");
+
+        let decoded = json!({ "payload": { "sub": "u2" }, "dates": {} });
+        assert_eq!(build_context(&decoded, 0)["claims"]["sub"], "u2");
+    }
+
+    #[test]
+    fn render_supports_loops_and_reports_errors() {
+        let data = json!({ "projects": [{ "name": "a" }, { "name": "b" }] });
+        let out = render("{% for p in projects %}{{ p.name }};{% endfor %}", &data).unwrap();
+        assert_eq!(out, "a;b;");
+        assert_eq!(render("{{ missing }}", &data).unwrap(), "");
+        assert!(render("{{ projects | nope }}", &data).is_err());
+    }
+}
@@ -0,0 +1,246 @@
+//! `verify --tenant-config`: per-issuer verification rules, so a gateway that accepts tokens
+//! from many issuers can verify each one with that issuer's keys, audiences and algorithms.
+
+use crate::cli::{JwtAlg, VerifyCommonArgs};
+use crate::error::{AppError, AppResult};
+use crate::io_utils::read_input;
+use crate::jwt_ops;
+use clap::ValueEnum;
+use jsonwebtoken::Algorithm;
+use serde::{Deserialize, Deserializer};
+use serde_json::{json, Value};
+use std::path::Path;
+
+/// Contents of a tenant config (YAML, or JSON since JSON is valid YAML).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TenantConfig {
+    pub tenants: Vec<Tenant>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Tenant {
+    /// Label for output; defaults to the issuer.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Exact `iss` value routed to this tenant.
+    pub issuer: String,
+    /// Accepted `aud` values; empty skips the audience check.
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub audiences: Vec<String>,
+    /// JWKS sources as accepted by `--jwks`; a relative `@file` is resolved from the config's
+    /// directory.
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub jwks: Vec<String>,
+    /// Vault project holding the tenant's keys, instead of `jwks`.
+    #[serde(default)]
+    pub project: Option<String>,
+    /// Accepted header `alg` values; empty accepts any algorithm the keys support.
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub algorithms: Vec<String>,
+}
+
+impl Tenant {
+    pub fn label(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.issuer)
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({ "name": self.label(), "issuer": self.issuer })
+    }
+}
+
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    })
+}
+
+/// Reads and validates a tenant config (raw, @file, -, env:NAME).
+pub fn load(spec: &str) -> AppResult<TenantConfig> {
+    let raw = read_input(spec)?;
+    let mut config = parse(&raw)?;
+    let base = spec
+        .strip_prefix('@')
+        .and_then(|path| Path::new(path).parent());
+    validate(&mut config, base)?;
+    Ok(config)
+}
+
+#[cfg(feature = "yaml")]
+fn parse(raw: &str) -> AppResult<TenantConfig> {
+    serde_yaml::from_str(raw)
+        .map_err(|e| AppError::invalid_claims(format!("invalid tenant config: {e}")))
+}
+
+/// Without the `yaml` feature only JSON configs can be read.
+#[cfg(not(feature = "yaml"))]
+fn parse(raw: &str) -> AppResult<TenantConfig> {
+    serde_json::from_str(raw).map_err(|e| {
+        AppError::invalid_claims(format!(
+            "invalid tenant config: {e} (YAML configs require a build with the `yaml` feature)"
+        ))
+    })
+}
+
+fn validate(config: &mut TenantConfig, base: Option<&Path>) -> AppResult<()> {
+    if config.tenants.is_empty() {
+        return Err(AppError::invalid_claims(
+            "tenant config must list at least one tenant",
+        ));
+    }
+    for (index, tenant) in config.tenants.iter().enumerate() {
+        if tenant.issuer.trim().is_empty() {
+            return Err(AppError::invalid_claims(format!(
+                "tenant #{} has an empty issuer",
+                index + 1
+            )));
+        }
+        if config.tenants[..index]
+            .iter()
+            .any(|other| other.issuer == tenant.issuer)
+        {
+            return Err(AppError::invalid_claims(format!(
+                "issuer {} is listed by more than one tenant",
+                tenant.issuer
+            )));
+        }
+        if tenant.jwks.is_empty() == tenant.project.is_none() {
+            return Err(AppError::invalid_claims(format!(
+                "tenant {} needs exactly one of jwks or project",
+                tenant.label()
+            )));
+        }
+        for alg in &tenant.algorithms {
+            parse_alg(alg).map_err(|_| {
+                AppError::invalid_claims(format!(
+                    "tenant {} lists unknown algorithm {alg}",
+                    tenant.label()
+                ))
+            })?;
+        }
+    }
+    if let Some(base) = base.filter(|base| !base.as_os_str().is_empty()) {
+        for tenant in &mut config.tenants {
+            for source in &mut tenant.jwks {
+                if let Some(path) = source.strip_prefix('@') {
+                    if Path::new(path).is_relative() {
+                        *source = format!("@{}", base.join(path).display());
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn parse_alg(raw: &str) -> Result<JwtAlg, String> {
+    JwtAlg::from_str(raw.trim(), true)
+}
+
+impl TenantConfig {
+    /// The tenant whose issuer equals the token's `iss`. Routing reads the claims unverified;
+    /// verification against the tenant's rules (including `iss`) happens afterwards.
+    pub fn route(&self, token: &str) -> AppResult<&Tenant> {
+        let claims = jwt_ops::decode_unverified(token)?.payload_json;
+        let Some(iss) = claims.get("iss").and_then(Value::as_str) else {
+            return Err(
+                AppError::invalid_claims("token has no iss claim to pick a tenant by")
+                    .with_details(json!({ "reason": "missing_iss" })),
+            );
+        };
+        self.tenants
+            .iter()
+            .find(|tenant| tenant.issuer == iss)
+            .ok_or_else(|| {
+                AppError::invalid_claims(format!("no tenant is configured for issuer {iss}"))
+                    .with_details(json!({ "reason": "unknown_tenant", "iss": iss }))
+            })
+    }
+}
+
+/// `args` with the tenant's key source, issuer, audiences and (checked) algorithm filled in.
+pub fn apply(tenant: &Tenant, args: &VerifyCommonArgs, token: &str) -> AppResult<VerifyCommonArgs> {
+    let header = jwt_ops::decode_header_only(token)?;
+    let mut args = args.clone();
+    if !tenant.algorithms.is_empty() {
+        let alg = tenant
+            .algorithms
+            .iter()
+            .filter_map(|raw| parse_alg(raw).ok())
+            .find(|alg| Algorithm::from(*alg) == header.alg)
+            .ok_or_else(|| {
+                AppError::invalid_token(format!(
+                    "alg {:?} is not allowed for tenant {} (allowed: {})",
+                    header.alg,
+                    tenant.label(),
+                    tenant.algorithms.join(", ")
+                ))
+            })?;
+        args.alg = Some(alg);
+    }
+    args.jwks = tenant.jwks.clone();
+    args.project = tenant.project.clone();
+    args.iss = Some(tenant.issuer.clone());
+    if !tenant.audiences.is_empty() {
+        args.aud = tenant.audiences.clone();
+    }
+    Ok(args)
+}
+
+#[cfg(all(test, feature = "yaml"))]
+mod tests {
+    use super::{load, validate, TenantConfig};
+    use std::path::Path;
+
+    const CONFIG: &str = "
+tenants:
+  - name: acme
+    issuer: https://acme.example.com/
+    audiences: api://gateway
+    jwks: '@keys/acme.json'
+    algorithms: [RS256, ES256]
+  - issuer: https://globex.example.com
+    project: globex
+";
+
+    #[test]
+    fn load_accepts_single_values_and_resolves_jwks_files() {
+        let mut config: TenantConfig = serde_yaml::from_str(CONFIG).expect("parse");
+        validate(&mut config, Some(Path::new("conf"))).expect("validate");
+        let acme = &config.tenants[0];
+        assert_eq!(acme.audiences, ["api://gateway"]);
+        assert_eq!(
+            acme.jwks,
+            [format!(
+                "@{}",
+                Path::new("conf").join("keys/acme.json").display()
+            )]
+        );
+        assert_eq!(config.tenants[1].label(), "https://globex.example.com");
+    }
+
+    #[test]
+    fn load_rejects_duplicate_issuers_and_missing_key_sources() {
+        let err = load("tenants:\n  - issuer: a\n    project: p\n  - issuer: a\n    project: q")
+            .unwrap_err();
+        assert!(
+            err.message.contains("more than one tenant"),
+            "{}",
+            err.message
+        );
+        let err = load("tenants:\n  - issuer: a").unwrap_err();
+        assert!(err.message.contains("exactly one of jwks or project"));
+        let err =
+            load("tenants:\n  - issuer: a\n    project: p\n    algorithms: [XX1]").unwrap_err();
+        assert!(err.message.contains("unknown algorithm XX1"));
+    }
+}
@@ -0,0 +1,49 @@
+use std::process::{Command, Stdio};
+
+/// Opens `url` in the default browser through the platform's launcher (`open`, `xdg-open`,
+/// `start`). `BROWSER` overrides it, as it does for other command-line tools.
+pub(crate) fn open(url: &str) -> std::io::Result<()> {
+    let mut command = match std::env::var_os("BROWSER").filter(|value| !value.is_empty()) {
+        Some(browser) => {
+            let mut command = Command::new(browser);
+            command.arg(url);
+            command
+        }
+        None => launcher(url)?,
+    };
+    let status = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(std::io::Error::other(format!(
+            "browser launcher exited with {status}"
+        )))
+    }
+}
+
+fn launcher(url: &str) -> std::io::Result<Command> {
+    if cfg!(target_os = "macos") {
+        let mut command = Command::new("open");
+        command.arg(url);
+        Ok(command)
+    } else if cfg!(windows) {
+        // `start` is a cmd builtin; the empty string is the window title it would otherwise
+        // take from a quoted URL.
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", "", url]);
+        Ok(command)
+    } else if cfg!(unix) {
+        let mut command = Command::new("xdg-open");
+        command.arg(url);
+        Ok(command)
+    } else {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "opening a browser is not supported on this platform",
+        ))
+    }
+}
@@ -0,0 +1,240 @@
+use axum::http::header::COOKIE;
+use axum::http::HeaderMap;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use rand::RngCore;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use subtle::ConstantTimeEq;
+
+pub(crate) const SESSION_COOKIE: &str = "jwt_tester_session";
+pub(crate) const CSRF_HEADER: &str = "x-csrf-token";
+/// Sessions unused for this long are dropped. Sessions are never evicted to make room, so a
+/// flood of cookieless `GET /api/csrf` requests cannot push out a user's live session.
+const SESSION_IDLE_TTL: Duration = Duration::from_secs(12 * 60 * 60);
+
+struct CsrfSession {
+    token: String,
+    last_used: Instant,
+}
+
+/// Issued CSRF tokens keyed by the session id stored in the session cookie.
+pub(crate) struct CsrfStore {
+    sessions: Mutex<HashMap<String, CsrfSession>>,
+    idle_ttl: Duration,
+}
+
+impl Default for CsrfStore {
+    fn default() -> Self {
+        CsrfStore {
+            sessions: Mutex::default(),
+            idle_ttl: SESSION_IDLE_TTL,
+        }
+    }
+}
+
+/// Result of issuing a CSRF token: `new_session` is set when a cookie must be sent.
+pub(crate) struct CsrfIssue {
+    pub(crate) session_id: String,
+    pub(crate) token: String,
+    pub(crate) new_session: bool,
+}
+
+impl CsrfStore {
+    /// Returns the token for the caller's session, creating a session if needed.
+    pub(crate) fn issue(&self, headers: &HeaderMap) -> CsrfIssue {
+        let mut sessions = self.lock();
+        let now = Instant::now();
+        sessions.retain(|_, session| now.duration_since(session.last_used) < self.idle_ttl);
+        if let Some(session_id) = session_from_headers(headers) {
+            if let Some(session) = sessions.get_mut(&session_id) {
+                session.last_used = now;
+                return CsrfIssue {
+                    token: session.token.clone(),
+                    session_id,
+                    new_session: false,
+                };
+            }
+        }
+
+        let session_id = random_token();
+        let token = random_token();
+        sessions.insert(
+            session_id.clone(),
+            CsrfSession {
+                token: token.clone(),
+                last_used: now,
+            },
+        );
+        CsrfIssue {
+            session_id,
+            token,
+            new_session: true,
+        }
+    }
+
+    /// Checks the `x-csrf-token` header against the token bound to the session cookie.
+    pub(crate) fn verify(&self, headers: &HeaderMap) -> bool {
+        let Some(session_id) = session_from_headers(headers) else {
+            return false;
+        };
+        let Some(provided) = headers.get(CSRF_HEADER).and_then(|v| v.to_str().ok()) else {
+            return false;
+        };
+        let mut sessions = self.lock();
+        let Some(session) = sessions.get_mut(&session_id) else {
+            return false;
+        };
+        let now = Instant::now();
+        if now.duration_since(session.last_used) >= self.idle_ttl {
+            sessions.remove(&session_id);
+            return false;
+        }
+        if !constant_time_eq(session.token.as_bytes(), provided.as_bytes()) {
+            return false;
+        }
+        session.last_used = now;
+        true
+    }
+
+    /// Replaces the session's token and returns the new value.
+    pub(crate) fn rotate(&self, headers: &HeaderMap) -> Option<String> {
+        let session_id = session_from_headers(headers)?;
+        let mut sessions = self.lock();
+        let session = sessions.get_mut(&session_id)?;
+        session.token = random_token();
+        session.last_used = Instant::now();
+        Some(session.token.clone())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, CsrfSession>> {
+        // A poisoned lock only means another request panicked; the map itself stays valid.
+        self.sessions
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+pub(crate) fn session_cookie(session_id: &str) -> String {
+    format!("{SESSION_COOKIE}={session_id}; Path=/; HttpOnly; SameSite=Strict")
+}
+
+fn session_from_headers(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, _)| *name == SESSION_COOKIE)
+        .map(|(_, value)| value.to_string())
+        .filter(|value| !value.is_empty())
+}
+
+fn constant_time_eq(expected: &[u8], provided: &[u8]) -> bool {
+    expected.ct_eq(provided).into()
+}
+
+fn random_token() -> String {
+    let mut raw = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut raw);
+    URL_SAFE_NO_PAD.encode(raw)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{constant_time_eq, session_cookie, CsrfStore, CSRF_HEADER, SESSION_COOKIE};
+    use axum::http::header::COOKIE;
+    use axum::http::{HeaderMap, HeaderValue};
+    use std::time::Duration;
+
+    fn request_headers(session: &str, token: Option<&str>) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            COOKIE,
+            HeaderValue::from_str(&format!("theme=dark; {SESSION_COOKIE}={session}")).unwrap(),
+        );
+        if let Some(token) = token {
+            headers.insert(CSRF_HEADER, HeaderValue::from_str(token).unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn issue_reuses_existing_session() {
+        let store = CsrfStore::default();
+        let first = store.issue(&HeaderMap::new());
+        assert!(first.new_session);
+
+        let again = store.issue(&request_headers(&first.session_id, None));
+        assert!(!again.new_session);
+        assert_eq!(again.token, first.token);
+    }
+
+    #[test]
+    fn verify_requires_matching_session_and_token() {
+        let store = CsrfStore::default();
+        let issued = store.issue(&HeaderMap::new());
+        let other = store.issue(&HeaderMap::new());
+
+        assert!(store.verify(&request_headers(&issued.session_id, Some(&issued.token))));
+        assert!(!store.verify(&request_headers(&issued.session_id, Some(&other.token))));
+        assert!(!store.verify(&request_headers(&issued.session_id, None)));
+        assert!(!store.verify(&request_headers("unknown", Some(&issued.token))));
+
+        let mut no_cookie = HeaderMap::new();
+        no_cookie.insert(CSRF_HEADER, HeaderValue::from_str(&issued.token).unwrap());
+        assert!(!store.verify(&no_cookie));
+    }
+
+    #[test]
+    fn rotate_invalidates_previous_token() {
+        let store = CsrfStore::default();
+        let issued = store.issue(&HeaderMap::new());
+        let headers = request_headers(&issued.session_id, Some(&issued.token));
+
+        let rotated = store.rotate(&headers).expect("rotate");
+        assert_ne!(rotated, issued.token);
+        assert!(!store.verify(&headers));
+        assert!(store.verify(&request_headers(&issued.session_id, Some(&rotated))));
+    }
+
+    #[test]
+    fn new_sessions_never_push_out_a_live_one() {
+        let store = CsrfStore::default();
+        let issued = store.issue(&HeaderMap::new());
+        for _ in 0..1000 {
+            store.issue(&HeaderMap::new());
+        }
+        assert!(store.verify(&request_headers(&issued.session_id, Some(&issued.token))));
+    }
+
+    #[test]
+    fn idle_sessions_expire() {
+        let store = CsrfStore {
+            idle_ttl: Duration::ZERO,
+            ..CsrfStore::default()
+        };
+        let issued = store.issue(&HeaderMap::new());
+        assert!(!store.verify(&request_headers(&issued.session_id, Some(&issued.token))));
+        let again = store.issue(&request_headers(&issued.session_id, None));
+        assert!(again.new_session);
+        assert_ne!(again.session_id, issued.session_id);
+    }
+
+    #[test]
+    fn constant_time_eq_handles_length_mismatch() {
+        assert!(constant_time_eq(b"abc", b"abc"));
+        assert!(!constant_time_eq(b"abc", b"abcd"));
+        assert!(!constant_time_eq(b"abc", b"abd"));
+    }
+
+    #[test]
+    fn session_cookie_is_http_only_and_strict() {
+        let cookie = session_cookie("id");
+        assert!(cookie.starts_with("jwt_tester_session=id;"));
+        assert!(cookie.contains("HttpOnly"));
+        assert!(cookie.contains("SameSite=Strict"));
+    }
+}
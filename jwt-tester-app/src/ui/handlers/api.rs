@@ -1,8 +1,11 @@
+use super::super::csrf::{session_cookie, CsrfStore, CSRF_HEADER};
 use super::super::AppState;
-use crate::error::AppError;
-use axum::extract::State;
-use axum::http::{HeaderMap, StatusCode};
-use axum::response::IntoResponse;
+use crate::error::{AppError, ErrorKind};
+use axum::extract::{Request, State};
+use axum::http::header::SET_COOKIE;
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
 use axum::Json;
 use serde::Serialize;
 
@@ -35,11 +38,62 @@ pub(super) struct ApiCsrf {
     pub(super) csrf: String,
 }
 
-pub(crate) async fn csrf(State(state): State<AppState>) -> impl IntoResponse {
-    Json(ApiCsrf {
+pub(crate) async fn csrf(State(state): State<AppState>, headers: HeaderMap) -> impl IntoResponse {
+    let issued = state.csrf.issue(&headers);
+    let mut response = Json(ApiCsrf {
         ok: true,
-        csrf: state.csrf.as_str().to_string(),
+        csrf: issued.token,
     })
+    .into_response();
+    if issued.new_session {
+        set_session_cookie(&mut response, &issued.session_id);
+    }
+    response
+}
+
+/// The `ui --open-file` file, handed out once so reloading the page starts empty.
+pub(crate) async fn opened_file(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    if require_csrf(&headers, &state.csrf).is_err() {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(api_err("CSRF token missing/invalid")),
+        )
+            .into_response();
+    }
+    let opened = state.opened_file.lock().unwrap().take();
+    Json(ApiList {
+        ok: true,
+        data: opened,
+    })
+    .into_response()
+}
+
+pub(super) fn set_session_cookie(response: &mut Response, session_id: &str) {
+    if let Ok(value) = HeaderValue::from_str(&session_cookie(session_id)) {
+        response.headers_mut().insert(SET_COOKIE, value);
+    }
+}
+
+/// Rotates the session CSRF token after sensitive operations and returns it in `x-csrf-token`.
+pub(crate) async fn rotate_csrf(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let headers = request.headers().clone();
+    let mut response = next.run(request).await;
+    if response.status() == StatusCode::FORBIDDEN {
+        return response;
+    }
+    if let Some(token) = state.csrf.rotate(&headers) {
+        if let Ok(value) = HeaderValue::from_str(&token) {
+            response.headers_mut().insert(CSRF_HEADER, value);
+        }
+    }
+    response
 }
 
 pub(super) fn api_err(error: impl Into<String>) -> ApiErr {
@@ -58,9 +112,30 @@ pub(super) fn api_err_with_code(err: &AppError) -> ApiErr {
     }
 }
 
-pub(super) fn require_csrf(headers: &HeaderMap, expected: &str) -> Result<(), StatusCode> {
-    match headers.get("x-csrf-token").and_then(|v| v.to_str().ok()) {
-        Some(v) if v == expected => Ok(()),
-        _ => Err(StatusCode::FORBIDDEN),
+/// Every handler error goes through here so statuses follow the error kind. Signature and claim
+/// failures are 401 rather than 403: 403 is reserved for CSRF rejections, which the history and
+/// CSRF-rotation middleware skip.
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let status = match self.kind {
+            ErrorKind::NotFound => StatusCode::NOT_FOUND,
+            ErrorKind::InvalidSignature | ErrorKind::InvalidClaims | ErrorKind::Revoked => {
+                StatusCode::UNAUTHORIZED
+            }
+            ErrorKind::InvalidToken | ErrorKind::InvalidKey | ErrorKind::InvalidInput => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            ErrorKind::Offline => StatusCode::SERVICE_UNAVAILABLE,
+            ErrorKind::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, Json(api_err_with_code(&self))).into_response()
+    }
+}
+
+pub(super) fn require_csrf(headers: &HeaderMap, store: &CsrfStore) -> Result<(), StatusCode> {
+    if store.verify(headers) {
+        Ok(())
+    } else {
+        Err(StatusCode::FORBIDDEN)
     }
 }
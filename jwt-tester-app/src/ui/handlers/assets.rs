@@ -1,7 +1,8 @@
 use super::super::AppState;
+use super::api::set_session_cookie;
 use axum::body::Body;
 use axum::extract::{Path, State};
-use axum::http::StatusCode;
+use axum::http::{HeaderMap, StatusCode};
 use axum::response::{Html, IntoResponse, Response};
 use std::path::Path as FsPath;
 
@@ -58,12 +59,17 @@ npm run build</code></pre>
     )
 }
 
-pub(crate) async fn index(State(state): State<AppState>) -> impl IntoResponse {
+pub(crate) async fn index(State(state): State<AppState>, headers: HeaderMap) -> impl IntoResponse {
     let index_path = super::super::assets_root().join("index.html");
     match tokio::fs::read_to_string(&index_path).await {
         Ok(html) => {
-            let html = html.replace("{csrf}", state.csrf.as_str());
-            Html(html).into_response()
+            let issued = state.csrf.issue(&headers);
+            let html = html.replace("{csrf}", &issued.token);
+            let mut response = Html(html).into_response();
+            if issued.new_session {
+                set_session_cookie(&mut response, &issued.session_id);
+            }
+            response
         }
         Err(err) => (
             StatusCode::SERVICE_UNAVAILABLE,
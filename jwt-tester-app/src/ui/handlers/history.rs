@@ -0,0 +1,125 @@
+use super::super::AppState;
+use super::api::{api_err, require_csrf, ApiList};
+use crate::error::AppError;
+use crate::history::{self, history_limit};
+use axum::body::{to_bytes, Body};
+use axum::extract::{Query, Request, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Deserialize;
+use serde_json::json;
+
+// Encode/verify bodies are small JSON documents; anything larger is passed through unrecorded.
+const MAX_RECORDED_BODY: usize = 1024 * 1024;
+
+#[derive(Deserialize)]
+pub(crate) struct HistoryQuery {
+    pub limit: Option<usize>,
+}
+
+pub(crate) async fn list_history(
+    State(state): State<AppState>,
+    Query(query): Query<HistoryQuery>,
+) -> impl IntoResponse {
+    match state.vault.list_history(query.limit) {
+        Ok(entries) => Json(ApiList {
+            ok: true,
+            data: json!({ "enabled": history_limit().is_some(), "entries": entries }),
+        })
+        .into_response(),
+        Err(err) => AppError::internal(err.to_string()).into_response(),
+    }
+}
+
+pub(crate) async fn clear_history(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    if require_csrf(&headers, &state.csrf).is_err() {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(api_err("CSRF token missing/invalid")),
+        )
+            .into_response();
+    }
+
+    match state.vault.clear_history() {
+        Ok(removed) => Json(ApiList {
+            ok: true,
+            data: json!({ "removed": removed }),
+        })
+        .into_response(),
+        Err(err) => AppError::internal(err.to_string()).into_response(),
+    }
+}
+
+/// Records encode/verify calls when `JWT_TESTER_HISTORY` is set. UI requests only reference
+/// vault keys, so the request body is safe to keep once the token itself is truncated.
+pub(crate) async fn record_history(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if history_limit().is_none() {
+        return next.run(request).await;
+    }
+    let operation = match request.uri().path() {
+        "/api/jwt/encode" => "encode",
+        "/api/jwt/verify" => "verify",
+        _ => return next.run(request).await,
+    };
+
+    let (parts, body) = request.into_parts();
+    let Ok(request_bytes) = to_bytes(body, MAX_RECORDED_BODY).await else {
+        return (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            Json(api_err("request too large")),
+        )
+            .into_response();
+    };
+    let response = next
+        .run(Request::from_parts(
+            parts,
+            Body::from(request_bytes.clone()),
+        ))
+        .await;
+    if response.status() == StatusCode::FORBIDDEN {
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let Ok(response_bytes) = to_bytes(body, usize::MAX).await else {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(api_err("failed to read response")),
+        )
+            .into_response();
+    };
+
+    let mut params: serde_json::Value =
+        serde_json::from_slice(&request_bytes).unwrap_or_else(|_| json!({}));
+    if let Some(token) = params.get_mut("token") {
+        if let Some(raw) = token.as_str() {
+            *token = json!(history::truncate_result(raw));
+        }
+    }
+    let body: serde_json::Value =
+        serde_json::from_slice(&response_bytes).unwrap_or_else(|_| json!({}));
+    let ok = parts.status.is_success();
+    let result = if ok {
+        body["data"]["token"]
+            .as_str()
+            .unwrap_or("valid")
+            .to_string()
+    } else {
+        body["error"]
+            .as_str()
+            .unwrap_or("request failed")
+            .to_string()
+    };
+    history::record(&state.vault, operation, params, ok, &result);
+
+    Response::from_parts(parts, Body::from(response_bytes))
+}
@@ -1,19 +1,18 @@
 use super::super::AppState;
-use super::api::{api_err, api_err_with_code, require_csrf, ApiList};
+use super::api::{api_err, require_csrf, ApiList};
 use super::types::{EncodeReq, InspectReq, VerifyReq};
 use crate::claims;
-use crate::cli::{EncodeArgs, JwtAlg, VerifyCommonArgs};
+use crate::cli::{JwtAlg, TimestampUnit, VerifyCommonArgs};
 use crate::date_utils::{extract_dates, parse_date_mode};
-use crate::error::{AppError, AppResult, ErrorKind};
-use crate::jwt_ops::{self, VerifyOptions};
-use crate::key_resolver::{
-    resolve_encoding_key_with_vault, resolve_verification_key_with_vault, KeySource,
-};
+use crate::encode_request::EncodeRequest;
+use crate::error::{AppError, AppResult};
+use crate::jwt_ops;
+use crate::key_resolver::resolve_encoding_key_with_vault;
+use crate::verify_service;
 use axum::extract::State;
 use axum::http::{HeaderMap, StatusCode};
 use axum::response::IntoResponse;
 use axum::Json;
-use jsonwebtoken::Algorithm;
 use serde_json::json;
 
 pub(crate) async fn encode_token(
@@ -21,7 +20,7 @@ pub(crate) async fn encode_token(
     headers: HeaderMap,
     Json(req): Json<EncodeReq>,
 ) -> impl IntoResponse {
-    if require_csrf(&headers, state.csrf.as_str()).is_err() {
+    if require_csrf(&headers, &state.csrf).is_err() {
         return (
             StatusCode::FORBIDDEN,
             Json(api_err("CSRF token missing/invalid")),
@@ -33,6 +32,7 @@ pub(crate) async fn encode_token(
         project,
         key_id,
         key_name,
+        key_fingerprint,
         alg,
         claims,
         kid,
@@ -50,100 +50,70 @@ pub(crate) async fn encode_token(
 
     let alg = match parse_jwt_alg(&alg) {
         Ok(val) => val,
-        Err(err) => {
-            return (StatusCode::BAD_REQUEST, Json(api_err_with_code(&err))).into_response();
-        }
+        Err(err) => return err.into_response(),
     };
 
-    let no_typ_flag = no_typ.unwrap_or(false);
-    let no_iat_flag = no_iat.unwrap_or(false);
-    let aud_list = aud.unwrap_or_default();
-
-    let args = EncodeArgs {
-        secret: None,
-        key: None,
-        key_format: None,
-        project: Some(project),
-        key_id,
-        key_name,
-        alg,
-        claims: None,
-        header: None,
-        kid: kid.clone(),
-        typ: typ.clone(),
-        no_typ: no_typ_flag,
-        iss: iss.clone(),
-        sub: sub.clone(),
-        aud: aud_list.clone(),
-        jti: jti.clone(),
-        iat: iat.clone(),
-        no_iat: no_iat_flag,
-        nbf: nbf.clone(),
-        exp: exp.clone(),
-        claim: Vec::new(),
-        claim_file: Vec::new(),
-        keep_payload_order: false,
-        out: None,
-    };
+    let mut request = EncodeRequest::new(alg)
+        .project(project.as_str())
+        .standard_claims(claims::StandardClaims {
+            iss,
+            sub,
+            aud: aud.unwrap_or_default(),
+            jti,
+            iat,
+            nbf,
+            exp,
+            no_iat: no_iat.unwrap_or(false),
+            ..claims::StandardClaims::default()
+        })
+        .no_typ(no_typ.unwrap_or(false));
+    if let Some(key_id) = key_id {
+        request = request.key_id(key_id);
+    }
+    if let Some(key_name) = key_name {
+        request = request.key_name(key_name);
+    }
+    if let Some(fingerprint) = key_fingerprint {
+        request = request.key_fingerprint(fingerprint);
+    }
+    if let Some(kid) = kid {
+        request = request.kid(kid);
+    }
+    if let Some(typ) = typ {
+        request = request.typ(typ);
+    }
 
-    let (key, key_source) = match resolve_encoding_key_with_vault(&state.vault, &args) {
+    let (key, key_source) = match resolve_encoding_key_with_vault(&state.vault, &request) {
         Ok(result) => result,
-        Err(err) => {
-            return (StatusCode::BAD_REQUEST, Json(api_err_with_code(&err))).into_response();
-        }
+        Err(err) => return err.into_response(),
     };
+    match state.vault.find_project_by_name(&project) {
+        Ok(found) => {
+            if let Some(module) = found.and_then(|p| p.wasm_module) {
+                request = request.wasm_module(module);
+            }
+        }
+        Err(err) => return AppError::invalid_key(err.to_string()).into_response(),
+    }
 
     let claims_raw = claims.unwrap_or_default();
-    let base_claims = if claims_raw.trim().is_empty() {
-        json!({})
-    } else {
+    if !claims_raw.trim().is_empty() {
         match serde_json::from_str(&claims_raw) {
-            Ok(val) => val,
+            Ok(val) => request = request.claims(val),
             Err(err) => {
-                return (
-                    StatusCode::BAD_REQUEST,
-                    Json(api_err(format!("invalid claims JSON: {err}"))),
-                )
+                return AppError::invalid_token(format!("invalid claims JSON: {err}"))
                     .into_response();
             }
         }
-    };
-
-    let standard = claims::StandardClaims {
-        iss,
-        sub,
-        aud: aud_list,
-        jti,
-        iat,
-        nbf,
-        exp,
-        no_iat: no_iat_flag,
-    };
-
-    let claims = match claims::build_claims(base_claims, Vec::new(), standard, Vec::new(), false) {
-        Ok(val) => val,
-        Err(err) => {
-            return (StatusCode::BAD_REQUEST, Json(api_err_with_code(&err))).into_response();
-        }
-    };
-
-    let mut header = jsonwebtoken::Header::new(Algorithm::from(alg));
-    header.kid = kid;
-    if no_typ_flag {
-        header.typ = None;
-    } else if let Some(typ) = typ {
-        header.typ = Some(typ);
-    } else {
-        header.typ = Some("JWT".to_string());
     }
 
-    match jwt_ops::encode_token(&header, &claims, &key) {
+    match request.encode(&key) {
         Ok(token) => Json(ApiList {
             ok: true,
             data: json!({ "token": token, "key_source": key_source }),
         })
         .into_response(),
-        Err(err) => (StatusCode::BAD_REQUEST, Json(api_err_with_code(&err))).into_response(),
+        Err(err) => err.into_response(),
     }
 }
 
@@ -152,7 +122,7 @@ pub(crate) async fn verify_token(
     headers: HeaderMap,
     Json(req): Json<VerifyReq>,
 ) -> impl IntoResponse {
-    if require_csrf(&headers, state.csrf.as_str()).is_err() {
+    if require_csrf(&headers, &state.csrf).is_err() {
         return (
             StatusCode::FORBIDDEN,
             Json(api_err("CSRF token missing/invalid")),
@@ -164,6 +134,7 @@ pub(crate) async fn verify_token(
         project,
         key_id,
         key_name,
+        key_fingerprint,
         alg,
         token,
         try_all_keys,
@@ -178,107 +149,44 @@ pub(crate) async fn verify_token(
 
     let alg = match parse_jwt_alg_opt(alg.as_deref()) {
         Ok(val) => val,
-        Err(err) => {
-            return (StatusCode::BAD_REQUEST, Json(api_err_with_code(&err))).into_response();
-        }
-    };
-    let resolved_alg = match resolve_verify_alg(alg, &token) {
-        Ok(val) => val,
-        Err(err) => {
-            return (StatusCode::BAD_REQUEST, Json(api_err_with_code(&err))).into_response();
-        }
+        Err(err) => return err.into_response(),
     };
 
-    let aud_list = aud.unwrap_or_default();
-    let require_list = require.unwrap_or_default();
-
     let args = VerifyCommonArgs {
         secret: None,
         key: None,
-        jwks: None,
+        jwks: Vec::new(),
         key_format: None,
         kid: None,
         allow_single_jwk: false,
         project: Some(project),
         key_id,
         key_name,
+        key_fingerprint,
         try_all_keys: try_all_keys.unwrap_or(false),
+        auto_refresh: false,
         ignore_exp: ignore_exp.unwrap_or(false),
         leeway_secs: leeway_secs.unwrap_or(30),
-        iss: iss.clone(),
-        sub: sub.clone(),
-        aud: aud_list.clone(),
-        require: require_list.clone(),
-        explain: explain.unwrap_or(false),
-        alg,
-    };
-
-    let key_source =
-        match resolve_verification_key_with_vault(&state.vault, &args, &token, resolved_alg.alg) {
-            Ok(source) => source,
-            Err(err) => {
-                return (StatusCode::BAD_REQUEST, Json(api_err_with_code(&err))).into_response();
-            }
-        };
-
-    let verify_opts = VerifyOptions {
-        alg: resolved_alg.alg,
-        leeway_secs: args.leeway_secs,
-        ignore_exp: args.ignore_exp,
+        jwks_negative_ttl: std::time::Duration::from_secs(300),
+        timestamps: TimestampUnit::Auto,
         iss,
         sub,
-        aud: aud_list,
-        require: require_list,
+        aud: aud.unwrap_or_default(),
+        require: require.unwrap_or_default(),
+        require_acr: Vec::new(),
+        max_auth_age: None,
+        check_revocation: false,
+        explain: explain.unwrap_or(false),
+        alg,
     };
 
-    let source_label = key_source_label(&key_source);
-    let build_success = |claims| {
-        let mut info = json!({ "valid": true, "claims": claims });
-        if args.explain {
-            info["explain"] = json!({
-                "alg": format!("{:?}", resolved_alg.alg),
-                "alg_inferred": resolved_alg.inferred,
-                "key_source": source_label.clone(),
-                "iss": args.iss,
-                "sub": args.sub,
-                "aud": args.aud,
-                "leeway_secs": args.leeway_secs,
-                "ignore_exp": args.ignore_exp,
-                "require": args.require,
-            });
-        }
-        Json(ApiList {
+    match verify_service::verify_with_vault(&state.vault, &args, &token) {
+        Ok(verified) => Json(ApiList {
             ok: true,
-            data: info,
+            data: verified.to_json(),
         })
-        .into_response()
-    };
-
-    match key_source {
-        KeySource::Single(key, _label) => match jwt_ops::verify_token(&token, &key, verify_opts) {
-            Ok(token_data) => build_success(token_data.claims),
-            Err(err) => (StatusCode::BAD_REQUEST, Json(api_err_with_code(&err))).into_response(),
-        },
-        KeySource::Multiple(keys, _label) => {
-            let mut last_sig_err: Option<AppError> = None;
-            for key in keys {
-                match jwt_ops::verify_token(&token, &key, verify_opts.clone()) {
-                    Ok(token_data) => return build_success(token_data.claims),
-                    Err(err) => {
-                        if matches!(err.kind, ErrorKind::InvalidSignature) {
-                            last_sig_err = Some(err);
-                            continue;
-                        }
-                        return (StatusCode::BAD_REQUEST, Json(api_err_with_code(&err)))
-                            .into_response();
-                    }
-                }
-            }
-            let err = last_sig_err.unwrap_or_else(|| {
-                AppError::invalid_signature("signature invalid for all candidate keys")
-            });
-            (StatusCode::BAD_REQUEST, Json(api_err_with_code(&err))).into_response()
-        }
+        .into_response(),
+        Err(err) => err.into_response(),
     }
 }
 
@@ -287,7 +195,8 @@ pub(crate) async fn inspect_token(
     headers: HeaderMap,
     Json(req): Json<InspectReq>,
 ) -> impl IntoResponse {
-    if require_csrf(&headers, state.csrf.as_str()).is_err() {
+    // Inspect reads nothing from the vault, so `--allow-cors-inspect` can drop the CSRF check.
+    if !state.cors_inspect && require_csrf(&headers, &state.csrf).is_err() {
         return (
             StatusCode::FORBIDDEN,
             Json(api_err("CSRF token missing/invalid")),
@@ -297,30 +206,22 @@ pub(crate) async fn inspect_token(
 
     let date_mode = match parse_date_mode(req.date) {
         Ok(mode) => mode,
-        Err(err) => {
-            return (StatusCode::BAD_REQUEST, Json(api_err_with_code(&err))).into_response();
-        }
+        Err(err) => return err.into_response(),
     };
 
     let decoded = match jwt_ops::decode_unverified(&req.token) {
         Ok(val) => val,
-        Err(err) => {
-            return (StatusCode::BAD_REQUEST, Json(api_err_with_code(&err))).into_response();
-        }
+        Err(err) => return err.into_response(),
     };
 
     let header = match jwt_ops::decode_header_only(&req.token) {
         Ok(val) => val,
-        Err(err) => {
-            return (StatusCode::BAD_REQUEST, Json(api_err_with_code(&err))).into_response();
-        }
+        Err(err) => return err.into_response(),
     };
 
     let dates = match extract_dates(&decoded.payload_json, date_mode) {
         Ok(val) => val,
-        Err(err) => {
-            return (StatusCode::BAD_REQUEST, Json(api_err_with_code(&err))).into_response();
-        }
+        Err(err) => return err.into_response(),
     };
 
     let segments: Vec<&str> = req.token.trim().split('.').collect();
@@ -376,29 +277,188 @@ fn parse_jwt_alg_opt(raw: Option<&str>) -> AppResult<Option<JwtAlg>> {
     parse_jwt_alg(val).map(Some)
 }
 
-#[derive(Clone, Copy)]
-struct ResolvedAlg {
-    alg: Algorithm,
-    inferred: bool,
-}
+#[cfg(test)]
+mod tests {
+    use super::super::super::csrf::{CsrfStore, CSRF_HEADER, SESSION_COOKIE};
+    use super::super::super::AppState;
+    use super::verify_token;
+    use crate::cli::{TimestampUnit, VerifyCommonArgs};
+    use crate::commands::verify::verify_token_with_args;
+    use crate::jwt_ops;
+    use crate::vault::{KeyEntryInput, ProjectInput, Vault, VaultConfig};
+    use axum::extract::State;
+    use axum::http::header::COOKIE;
+    use axum::http::{HeaderMap, HeaderValue, StatusCode};
+    use axum::response::IntoResponse;
+    use axum::Json;
+    use jsonwebtoken::{Algorithm, EncodingKey, Header};
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+
+    fn state_with_key(secret: &str) -> AppState {
+        let vault = Vault::open(VaultConfig {
+            no_persist: true,
+            data_dir: None,
+        })
+        .expect("memory vault");
+        let project = vault
+            .add_project(ProjectInput {
+                name: "api".to_string(),
+                description: None,
+                tags: Vec::new(),
+            })
+            .expect("project");
+        vault
+            .add_key(KeyEntryInput {
+                project_id: project.id,
+                name: "signing".to_string(),
+                kind: "hmac".to_string(),
+                secret: secret.to_string(),
+                kid: None,
+                description: None,
+                tags: Vec::new(),
+            })
+            .expect("key");
+        AppState {
+            csrf: Arc::new(CsrfStore::default()),
+            vault,
+            recorder: None,
+            cors_inspect: false,
+            opened_file: Default::default(),
+        }
+    }
 
-fn resolve_verify_alg(alg: Option<JwtAlg>, token: &str) -> AppResult<ResolvedAlg> {
-    if let Some(val) = alg {
-        return Ok(ResolvedAlg {
-            alg: Algorithm::from(val),
-            inferred: false,
-        });
+    fn csrf_headers(store: &CsrfStore) -> HeaderMap {
+        let issued = store.issue(&HeaderMap::new());
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            COOKIE,
+            HeaderValue::from_str(&format!("{SESSION_COOKIE}={}", issued.session_id)).unwrap(),
+        );
+        headers.insert(CSRF_HEADER, HeaderValue::from_str(&issued.token).unwrap());
+        headers
+    }
+
+    fn cli_args(secret: &str) -> VerifyCommonArgs {
+        VerifyCommonArgs {
+            secret: Some(secret.to_string()),
+            key: None,
+            jwks: Vec::new(),
+            key_format: None,
+            kid: None,
+            allow_single_jwk: false,
+            project: None,
+            key_id: None,
+            key_name: None,
+            key_fingerprint: None,
+            try_all_keys: false,
+            auto_refresh: false,
+            ignore_exp: false,
+            leeway_secs: 30,
+            jwks_negative_ttl: std::time::Duration::from_secs(300),
+            timestamps: TimestampUnit::Auto,
+            iss: Some("issuer".to_string()),
+            sub: None,
+            aud: vec!["web".to_string()],
+            require: Vec::new(),
+            require_acr: Vec::new(),
+            max_auth_age: None,
+            check_revocation: false,
+            explain: true,
+            alg: None,
+        }
+    }
+
+    async fn ui_verify(state: AppState, token: &str) -> (StatusCode, Value) {
+        ui_verify_project(state, "api", token).await
     }
-    let header = jwt_ops::decode_header_only(token)?;
-    Ok(ResolvedAlg {
-        alg: header.alg,
-        inferred: true,
-    })
-}
 
-fn key_source_label(source: &KeySource) -> String {
-    match source {
-        KeySource::Single(_, label) => label.clone(),
-        KeySource::Multiple(_, label) => label.clone(),
+    async fn ui_verify_project(state: AppState, project: &str, token: &str) -> (StatusCode, Value) {
+        let headers = csrf_headers(&state.csrf);
+        let req = serde_json::from_value(json!({
+            "project": project,
+            "key_name": "signing",
+            "token": token,
+            "iss": "issuer",
+            "aud": ["web"],
+            "explain": true,
+        }))
+        .expect("request");
+        let response = verify_token(State(state), headers, Json(req))
+            .await
+            .into_response();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("body");
+        (status, serde_json::from_slice(&body).expect("json body"))
+    }
+
+    fn make_token() -> String {
+        jwt_ops::encode_token(
+            &Header::new(Algorithm::HS256),
+            &json!({ "sub": "tester", "iss": "issuer", "aud": "web" }),
+            &EncodingKey::from_secret(b"secret"),
+        )
+        .expect("encode token")
+    }
+
+    /// The explain object minus what depends on where the key came from and on timing.
+    fn without_key_source(mut explain: Value) -> Value {
+        let explain_map = explain.as_object_mut().expect("explain object");
+        for field in ["key_source", "decisions", "steps"] {
+            explain_map.remove(field);
+        }
+        explain
+    }
+
+    #[tokio::test]
+    async fn verify_matches_cli_outcome() {
+        let token = make_token();
+        let cli =
+            verify_token_with_args(true, None, &cli_args("secret"), &token).expect("cli verifies");
+        let (status, ui) = ui_verify(state_with_key("secret"), &token).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(ui["data"]["valid"], cli.data["valid"]);
+        assert_eq!(ui["data"]["claims"], cli.data["claims"]);
+        assert_eq!(
+            without_key_source(ui["data"]["explain"].clone()),
+            without_key_source(cli.data["explain"].clone())
+        );
+    }
+
+    #[tokio::test]
+    async fn verify_failure_matches_cli_error_code() {
+        let token = make_token();
+        let cli_err = verify_token_with_args(true, None, &cli_args("wrong"), &token)
+            .err()
+            .expect("cli rejects");
+        let (status, ui) = ui_verify(state_with_key("wrong"), &token).await;
+
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(ui["code"], cli_err.code());
+    }
+
+    #[tokio::test]
+    async fn error_statuses_follow_error_kind() {
+        let token = make_token();
+        let (status, body) = ui_verify_project(state_with_key("secret"), "missing", &token).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["code"], "NOT_FOUND");
+
+        let (status, body) = ui_verify(state_with_key("secret"), "not-a-jwt").await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["code"], "INVALID_TOKEN");
+
+        let expired = jwt_ops::encode_token(
+            &Header::new(Algorithm::HS256),
+            &json!({ "iss": "issuer", "aud": "web", "exp": 1 }),
+            &EncodingKey::from_secret(b"secret"),
+        )
+        .expect("encode token");
+        let (status, body) = ui_verify(state_with_key("secret"), &expired).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body["code"], "INVALID_CLAIMS");
     }
 }
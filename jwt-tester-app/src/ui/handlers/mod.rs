@@ -1,16 +1,20 @@
 mod api;
 mod assets;
+mod history;
 mod jwt;
+mod record;
 mod security;
 mod types;
 mod vault;
 
-pub(super) use api::{csrf, health};
+pub(super) use api::{csrf, health, opened_file, rotate_csrf};
 pub(super) use assets::{asset, index};
+pub(super) use history::{clear_history, list_history, record_history};
 pub(super) use jwt::{encode_token, inspect_token, verify_token};
-pub(super) use security::security_headers;
+pub(super) use record::{record_session, SessionRecorder};
+pub(super) use security::{cors_inspect, security_headers};
 pub(super) use vault::{
-    add_key, add_project, add_token, delete_key, delete_project, delete_token, export_vault,
-    generate_key, import_vault, list_keys, list_projects, list_tokens, reveal_token,
-    set_default_key,
+    add_key, add_project, add_token, delete_key, delete_project, delete_token, export_project,
+    export_vault, generate_key, import_project, import_vault, key_material, list_keys,
+    list_projects, list_tokens, reveal_token, set_default_key,
 };
@@ -0,0 +1,159 @@
+use super::super::AppState;
+use super::api::api_err;
+use crate::claims::now_epoch;
+use crate::error::{AppError, AppResult};
+use crate::output::{redact_secrets, REDACTED};
+use axum::body::{to_bytes, Body};
+use axum::extract::{Request, State};
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::{json, Value};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::warn;
+
+// Same bound as history: JWT API bodies are small JSON documents.
+const MAX_RECORDED_BODY: usize = 1024 * 1024;
+
+/// The `ui --record` session file. Entries are appended as JSON lines.
+pub(crate) struct SessionRecorder {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl SessionRecorder {
+    /// Opens (or creates) the session file up front so an unwritable path fails at startup.
+    pub(crate) fn open(path: &Path) -> AppResult<Self> {
+        let mut options = OpenOptions::new();
+        options.create(true).append(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let file = options.open(path).map_err(|e| {
+            AppError::internal(format!(
+                "failed to open session file {}: {e}",
+                path.display()
+            ))
+        })?;
+        Ok(SessionRecorder {
+            path: path.to_path_buf(),
+            file: Mutex::new(file),
+        })
+    }
+
+    fn append(&self, entry: &Value) {
+        let line = format!("{entry}\n");
+        let mut file = self
+            .file
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Err(err) = file.write_all(line.as_bytes()) {
+            warn!("failed to record UI session to {:?}: {err}", self.path);
+        }
+    }
+}
+
+/// Records `/api/jwt/*` calls for `jwt-tester replay` when the UI runs with `--record`. Requests
+/// keep their tokens (replay needs them); minted tokens lose their signature and secret-named
+/// fields are redacted everywhere.
+pub(crate) async fn record_session(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(recorder) = state.recorder.clone() else {
+        return next.run(request).await;
+    };
+    let Some(endpoint) = request.uri().path().strip_prefix("/api/jwt/") else {
+        return next.run(request).await;
+    };
+    let endpoint = endpoint.to_string();
+
+    let (parts, body) = request.into_parts();
+    let Ok(request_bytes) = to_bytes(body, MAX_RECORDED_BODY).await else {
+        return (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            Json(api_err("request too large")),
+        )
+            .into_response();
+    };
+    let response = next
+        .run(Request::from_parts(
+            parts,
+            Body::from(request_bytes.clone()),
+        ))
+        .await;
+    // CSRF rejections never reached the JWT code; there is nothing to reproduce.
+    if response.status() == StatusCode::FORBIDDEN {
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let Ok(response_bytes) = to_bytes(body, usize::MAX).await else {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(api_err("failed to read response")),
+        )
+            .into_response();
+    };
+
+    let mut request_json: Value = serde_json::from_slice(&request_bytes).unwrap_or(Value::Null);
+    redact_secrets(&mut request_json);
+    let mut response_json: Value = serde_json::from_slice(&response_bytes).unwrap_or(Value::Null);
+    redact_secrets(&mut response_json);
+    if let Some(token) = response_json.pointer_mut("/data/token") {
+        if let Some(raw) = token.as_str() {
+            *token = json!(strip_signature(raw));
+        }
+    }
+    recorder.append(&json!({
+        "ts": now_epoch(),
+        "endpoint": endpoint,
+        "request": request_json,
+        "status": parts.status.as_u16(),
+        "response": response_json,
+    }));
+
+    Response::from_parts(parts, Body::from(response_bytes))
+}
+
+/// Keeps the header and payload of a minted token so they can be diffed, but not a usable token.
+fn strip_signature(token: &str) -> String {
+    match token.rsplit_once('.') {
+        Some((signed, _)) => format!("{signed}.{REDACTED}"),
+        None => REDACTED.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacts_secret_fields_at_any_depth() {
+        let mut value = json!({
+            "project": "demo",
+            "token": "a.b.c",
+            "nested": [{ "Secret": "hunter2", "kid": "k1" }],
+            "passphrase": null,
+        });
+        redact_secrets(&mut value);
+        assert_eq!(
+            value,
+            json!({
+                "project": "demo",
+                "token": "a.b.c",
+                "nested": [{ "Secret": REDACTED, "kid": "k1" }],
+                "passphrase": null,
+            })
+        );
+        assert_eq!(strip_signature("h.p.sig"), "h.p.<redacted>");
+        assert_eq!(strip_signature("garbage"), REDACTED);
+    }
+}
@@ -1,9 +1,14 @@
+use super::super::AppState;
 use super::api::api_err;
-use axum::http::{Request, StatusCode};
+use axum::extract::State;
+use axum::http::{Method, Request, StatusCode};
 use axum::response::{IntoResponse, Response};
 use axum::Json;
 
+const INSPECT_PATH: &str = "/api/jwt/inspect";
+
 pub(crate) async fn security_headers(
+    State(state): State<AppState>,
     req: Request<axum::body::Body>,
     next: axum::middleware::Next,
 ) -> Response {
@@ -17,9 +22,10 @@ pub(crate) async fn security_headers(
         .and_then(|v| v.to_str().ok())
         .map(|s| s.to_string());
 
-    // We can't reliably access State here without custom middleware wiring; keep this check in handlers via CSRF.
-    // (CSRF token is required for POST/DELETE and is only embedded in our served HTML.)
-    if matches!(method.as_str(), "POST" | "PUT" | "PATCH" | "DELETE") {
+    // CSRF tokens are still checked in handlers; they are only embedded in our served HTML.
+    // `--allow-cors-inspect` exempts the read-only inspect endpoint and nothing else.
+    let cors_exempt = state.cors_inspect && req.uri().path() == INSPECT_PATH;
+    if !cors_exempt && matches!(method.as_str(), "POST" | "PUT" | "PATCH" | "DELETE") {
         if let Some(o) = origin {
             if !o.starts_with("http://127.0.0.1") && !o.starts_with("http://localhost") {
                 // conservative: block non-local origins
@@ -44,3 +50,35 @@ pub(crate) async fn security_headers(
 
     res
 }
+
+/// Layered on `/api/jwt/inspect` only. With `--allow-cors-inspect` it answers preflights and
+/// marks responses readable from any origin; credentials are never allowed, so a page can
+/// inspect tokens it already holds but cannot ride the UI session.
+pub(crate) async fn cors_inspect(
+    State(state): State<AppState>,
+    req: Request<axum::body::Body>,
+    next: axum::middleware::Next,
+) -> Response {
+    if !state.cors_inspect {
+        return next.run(req).await;
+    }
+    if req.method() == Method::OPTIONS {
+        let mut res = StatusCode::NO_CONTENT.into_response();
+        let headers = res.headers_mut();
+        headers.insert("Access-Control-Allow-Origin", "*".parse().unwrap());
+        headers.insert(
+            "Access-Control-Allow-Methods",
+            "POST, OPTIONS".parse().unwrap(),
+        );
+        headers.insert(
+            "Access-Control-Allow-Headers",
+            "content-type".parse().unwrap(),
+        );
+        headers.insert("Access-Control-Max-Age", "600".parse().unwrap());
+        return res;
+    }
+    let mut res = next.run(req).await;
+    res.headers_mut()
+        .insert("Access-Control-Allow-Origin", "*".parse().unwrap());
+    res
+}
@@ -9,6 +9,7 @@ pub(crate) struct AddKeyReq {
     pub kid: Option<String>,
     pub description: Option<String>,
     pub tags: Option<Vec<String>>,
+    pub allow_public: Option<bool>,
 }
 
 #[derive(Deserialize)]
@@ -22,6 +23,8 @@ pub(crate) struct GenerateKeyReq {
     pub hmac_bytes: Option<usize>,
     pub rsa_bits: Option<usize>,
     pub ec_curve: Option<String>,
+    pub ed_curve: Option<String>,
+    pub key_use: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -55,11 +58,21 @@ pub(crate) struct ImportReq {
     pub replace: Option<bool>,
 }
 
+#[derive(Deserialize)]
+pub(crate) struct ProjectImportReq {
+    pub bundle: String,
+    pub passphrase: String,
+    /// `skip` (default), `overwrite`, or `rename`
+    pub on_conflict: Option<String>,
+    pub dry_run: Option<bool>,
+}
+
 #[derive(Deserialize)]
 pub(crate) struct EncodeReq {
     pub project: String,
     pub key_id: Option<String>,
     pub key_name: Option<String>,
+    pub key_fingerprint: Option<String>,
     pub alg: String,
     pub claims: Option<String>,
     pub kid: Option<String>,
@@ -80,6 +93,7 @@ pub(crate) struct VerifyReq {
     pub project: String,
     pub key_id: Option<String>,
     pub key_name: Option<String>,
+    pub key_fingerprint: Option<String>,
     pub alg: Option<String>,
     pub token: String,
     pub try_all_keys: Option<bool>,
@@ -103,3 +117,8 @@ pub(crate) struct InspectReq {
 pub(crate) struct ProjectFilter {
     pub project_id: Option<String>,
 }
+
+#[derive(Deserialize)]
+pub(crate) struct MaterialQuery {
+    pub mode: Option<String>,
+}
@@ -1,19 +1,60 @@
 use super::super::AppState;
 use super::api::{api_err, require_csrf, ApiList, ApiOk};
 use super::types::{
-    AddKeyReq, AddProjectReq, AddTokenReq, ExportReq, GenerateKeyReq, ImportReq, ProjectFilter,
-    SetDefaultKeyReq,
+    AddKeyReq, AddProjectReq, AddTokenReq, ExportReq, GenerateKeyReq, ImportReq, MaterialQuery,
+    ProjectFilter, ProjectImportReq, SetDefaultKeyReq,
 };
+use crate::error::AppError;
+use crate::key_resolver::{check_new_key_material, is_public_key_material, PLATFORM_KIND};
 use crate::keygen::{
-    generate_key_material, parse_ec_curve, KeyGenSpec, DEFAULT_HMAC_BYTES, DEFAULT_RSA_BITS,
+    generate_key_pair, key_type_details, parse_ec_curve, parse_ed_curve, parse_key_use, KeyGenSpec,
+    DEFAULT_HMAC_BYTES, DEFAULT_RSA_BITS,
 };
-use crate::vault::{KeyEntryInput, ProjectInput, TokenEntryInput};
-use crate::vault_export::ExportBundle;
+use crate::vault::{
+    ConflictStrategy, KeyEntry, KeyEntryInput, KeyUsage, ProjectEntry, ProjectInput, TokenEntry,
+    TokenEntryInput,
+};
+use crate::vault_export::{BundleProtection, BundleUnlock, ExportBundle};
 use axum::extract::{Path, Query, State};
 use axum::http::{HeaderMap, StatusCode};
 use axum::response::IntoResponse;
 use axum::Json;
-use serde_json::json;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+/// Characters shown at each end of masked PEM material. Short bodies show fewer: never more than
+/// an eighth of the material at either end. HMAC secrets get no preview at all.
+const MASK_PREVIEW_CHARS: usize = 4;
+
+// Ids are looked up before acting on them, so an unknown id is a 404 in both vault backends
+// instead of a silent no-op (memory) or a storage error (SQLite).
+fn find_project(state: &AppState, id: &str) -> Result<ProjectEntry, AppError> {
+    state
+        .vault
+        .find_project_by_id(id)
+        .map_err(|err| AppError::internal(err.to_string()))?
+        .ok_or_else(|| AppError::not_found("project not found"))
+}
+
+fn find_key(state: &AppState, id: &str) -> Result<KeyEntry, AppError> {
+    state
+        .vault
+        .list_keys(None)
+        .map_err(|err| AppError::internal(err.to_string()))?
+        .into_iter()
+        .find(|key| key.id == id)
+        .ok_or_else(|| AppError::not_found("key not found"))
+}
+
+fn find_token(state: &AppState, id: &str) -> Result<TokenEntry, AppError> {
+    state
+        .vault
+        .list_tokens(None)
+        .map_err(|err| AppError::internal(err.to_string()))?
+        .into_iter()
+        .find(|token| token.id == id)
+        .ok_or_else(|| AppError::not_found("token not found"))
+}
 
 pub(crate) async fn list_projects(State(state): State<AppState>) -> impl IntoResponse {
     match state.vault.list_projects() {
@@ -22,11 +63,7 @@ pub(crate) async fn list_projects(State(state): State<AppState>) -> impl IntoRes
             data: projects,
         })
         .into_response(),
-        Err(err) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(api_err(err.to_string())),
-        )
-            .into_response(),
+        Err(err) => AppError::internal(err.to_string()).into_response(),
     }
 }
 
@@ -35,7 +72,7 @@ pub(crate) async fn add_project(
     headers: HeaderMap,
     Json(req): Json<AddProjectReq>,
 ) -> impl IntoResponse {
-    if require_csrf(&headers, state.csrf.as_str()).is_err() {
+    if require_csrf(&headers, &state.csrf).is_err() {
         return (
             StatusCode::FORBIDDEN,
             Json(api_err("CSRF token missing/invalid")),
@@ -53,7 +90,7 @@ pub(crate) async fn add_project(
             data: saved,
         })
         .into_response(),
-        Err(err) => (StatusCode::BAD_REQUEST, Json(api_err(err.to_string()))).into_response(),
+        Err(err) => AppError::invalid_key(err.to_string()).into_response(),
     }
 }
 
@@ -63,7 +100,7 @@ pub(crate) async fn set_default_key(
     Path(id): Path<String>,
     Json(req): Json<SetDefaultKeyReq>,
 ) -> impl IntoResponse {
-    if require_csrf(&headers, state.csrf.as_str()).is_err() {
+    if require_csrf(&headers, &state.csrf).is_err() {
         return (
             StatusCode::FORBIDDEN,
             Json(api_err("CSRF token missing/invalid")),
@@ -71,36 +108,17 @@ pub(crate) async fn set_default_key(
             .into_response();
     }
 
-    let project = match state.vault.find_project_by_id(&id) {
-        Ok(Some(p)) => p,
-        Ok(None) => {
-            return (StatusCode::BAD_REQUEST, Json(api_err("project not found"))).into_response();
-        }
-        Err(err) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(api_err(err.to_string())),
-            )
-                .into_response();
-        }
+    let project = match find_project(&state, &id) {
+        Ok(project) => project,
+        Err(err) => return err.into_response(),
     };
 
     if let Some(key_id) = req.key_id.as_deref() {
         match state.vault.list_keys(Some(&project.id)) {
             Ok(keys) if keys.iter().any(|k| k.id == key_id) => {}
-            Ok(_) => {
-                return (
-                    StatusCode::BAD_REQUEST,
-                    Json(api_err("key not found in project")),
-                )
-                    .into_response();
-            }
+            Ok(_) => return AppError::not_found("key not found in project").into_response(),
             Err(err) => {
-                return (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(api_err(err.to_string())),
-                )
-                    .into_response();
+                return AppError::internal(err.to_string()).into_response();
             }
         }
     }
@@ -114,7 +132,7 @@ pub(crate) async fn set_default_key(
             "data": { "project_id": project.id, "default_key_id": req.key_id }
         }))
         .into_response(),
-        Err(err) => (StatusCode::BAD_REQUEST, Json(api_err(err.to_string()))).into_response(),
+        Err(err) => AppError::invalid_key(err.to_string()).into_response(),
     }
 }
 
@@ -123,7 +141,7 @@ pub(crate) async fn delete_project(
     headers: HeaderMap,
     Path(id): Path<String>,
 ) -> impl IntoResponse {
-    if require_csrf(&headers, state.csrf.as_str()).is_err() {
+    if require_csrf(&headers, &state.csrf).is_err() {
         return (
             StatusCode::FORBIDDEN,
             Json(api_err("CSRF token missing/invalid")),
@@ -131,9 +149,16 @@ pub(crate) async fn delete_project(
             .into_response();
     }
 
+    if let Err(err) = find_project(&state, &id) {
+        return err.into_response();
+    }
     match state.vault.delete_project(&id) {
-        Ok(_) => Json(ApiOk { ok: true }).into_response(),
-        Err(err) => (StatusCode::BAD_REQUEST, Json(api_err(err.to_string()))).into_response(),
+        Ok(report) => Json(ApiList {
+            ok: true,
+            data: report,
+        })
+        .into_response(),
+        Err(err) => AppError::invalid_key(err.to_string()).into_response(),
     }
 }
 
@@ -147,11 +172,7 @@ pub(crate) async fn list_keys(
             data: keys,
         })
         .into_response(),
-        Err(err) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(api_err(err.to_string())),
-        )
-            .into_response(),
+        Err(err) => AppError::internal(err.to_string()).into_response(),
     }
 }
 
@@ -160,7 +181,7 @@ pub(crate) async fn add_key(
     headers: HeaderMap,
     Json(req): Json<AddKeyReq>,
 ) -> impl IntoResponse {
-    if require_csrf(&headers, state.csrf.as_str()).is_err() {
+    if require_csrf(&headers, &state.csrf).is_err() {
         return (
             StatusCode::FORBIDDEN,
             Json(api_err("CSRF token missing/invalid")),
@@ -168,6 +189,24 @@ pub(crate) async fn add_key(
             .into_response();
     }
 
+    if let Err(err) = check_new_key_material(
+        &req.kind,
+        &req.secret,
+        req.allow_public.unwrap_or(false),
+        None,
+    ) {
+        return err.into_response();
+    }
+
+    // Public keys can only verify and platform key references can only sign, so they are stored
+    // with that usage as on the command line.
+    let usage = if is_public_key_material(&req.secret) {
+        KeyUsage::Verify
+    } else if req.kind.eq_ignore_ascii_case(PLATFORM_KIND) {
+        KeyUsage::Sign
+    } else {
+        KeyUsage::Both
+    };
     let input = KeyEntryInput {
         project_id: req.project_id,
         name: req.name,
@@ -178,13 +217,20 @@ pub(crate) async fn add_key(
         tags: req.tags.unwrap_or_default(),
     };
 
-    match state.vault.add_key(input) {
+    let saved = state.vault.add_key(input).and_then(|saved| {
+        if usage != KeyUsage::Both {
+            state.vault.set_key_usage(&saved.id, usage)
+        } else {
+            Ok(saved)
+        }
+    });
+    match saved {
         Ok(saved) => Json(ApiList {
             ok: true,
             data: saved,
         })
         .into_response(),
-        Err(err) => (StatusCode::BAD_REQUEST, Json(api_err(err.to_string()))).into_response(),
+        Err(err) => AppError::invalid_key(err.to_string()).into_response(),
     }
 }
 
@@ -193,7 +239,7 @@ pub(crate) async fn generate_key(
     headers: HeaderMap,
     Json(req): Json<GenerateKeyReq>,
 ) -> impl IntoResponse {
-    if require_csrf(&headers, state.csrf.as_str()).is_err() {
+    if require_csrf(&headers, &state.csrf).is_err() {
         return (
             StatusCode::FORBIDDEN,
             Json(api_err("CSRF token missing/invalid")),
@@ -203,35 +249,33 @@ pub(crate) async fn generate_key(
 
     let kind = req.kind.trim().to_ascii_lowercase();
     let spec = match kind.as_str() {
-        "hmac" => KeyGenSpec::Hmac {
+        "hmac" => Ok(KeyGenSpec::Hmac {
             bytes: req.hmac_bytes.unwrap_or(DEFAULT_HMAC_BYTES),
-        },
-        "rsa" => KeyGenSpec::Rsa {
+        }),
+        "rsa" => parse_key_use(req.key_use.as_deref()).map(|key_use| KeyGenSpec::Rsa {
             bits: req.rsa_bits.unwrap_or(DEFAULT_RSA_BITS),
-        },
-        "ec" => match parse_ec_curve(req.ec_curve.as_deref()) {
-            Ok(curve) => KeyGenSpec::Ec { curve },
-            Err(err) => {
-                return (StatusCode::BAD_REQUEST, Json(api_err(err.to_string()))).into_response();
-            }
-        },
-        "eddsa" => KeyGenSpec::EdDsa,
+            key_use,
+        }),
+        "ec" => parse_ec_curve(req.ec_curve.as_deref()).map(|curve| KeyGenSpec::Ec { curve }),
+        "eddsa" => parse_ed_curve(req.ed_curve.as_deref()).map(|curve| KeyGenSpec::EdDsa { curve }),
         other => {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(api_err(format!(
-                    "unsupported key kind '{other}' for generation"
-                ))),
-            )
+            return AppError::invalid_key(format!("unsupported key kind '{other}' for generation"))
                 .into_response();
         }
     };
+    if req.key_use.is_some() && kind != "rsa" {
+        return AppError::invalid_key("key_use is only valid for RSA keys").into_response();
+    }
 
-    let (secret, format) = match generate_key_material(spec) {
-        Ok(secret) => (secret, if kind == "hmac" { "base64url" } else { "pem" }),
-        Err(err) => {
-            return (StatusCode::BAD_REQUEST, Json(api_err(err.to_string()))).into_response()
-        }
+    let (generated, format) = match spec.and_then(generate_key_pair) {
+        Ok(generated) => (generated, if kind == "hmac" { "base64url" } else { "pem" }),
+        Err(err) => return err.into_response(),
+    };
+    let secret = generated.material;
+    // Material of a non-exportable project is stored but never sent back.
+    let exportable = match state.vault.find_project_by_id(&req.project_id) {
+        Ok(project) => project.is_none_or(|p| p.exportable),
+        Err(err) => return AppError::invalid_key(err.to_string()).into_response(),
     };
 
     let input = KeyEntryInput {
@@ -245,16 +289,23 @@ pub(crate) async fn generate_key(
     };
 
     match state.vault.add_key(input) {
-        Ok(saved) => Json(ApiList {
-            ok: true,
-            data: json!({
-                "key": saved,
-                "material": secret,
-                "format": format
-            }),
-        })
-        .into_response(),
-        Err(err) => (StatusCode::BAD_REQUEST, Json(api_err(err.to_string()))).into_response(),
+        Ok(saved) => {
+            let public = generated
+                .public
+                .map(|public| public.with_kid(saved.kid.as_deref()));
+            Json(ApiList {
+                ok: true,
+                data: json!({
+                    "key": saved,
+                    "material": exportable.then_some(secret),
+                    "format": format,
+                    "public_pem": public.as_ref().map(|p| &p.pem),
+                    "public_jwk": public.map(|p| p.jwk),
+                }),
+            })
+            .into_response()
+        }
+        Err(err) => AppError::invalid_key(err.to_string()).into_response(),
     }
 }
 
@@ -263,7 +314,7 @@ pub(crate) async fn delete_key(
     headers: HeaderMap,
     Path(id): Path<String>,
 ) -> impl IntoResponse {
-    if require_csrf(&headers, state.csrf.as_str()).is_err() {
+    if require_csrf(&headers, &state.csrf).is_err() {
         return (
             StatusCode::FORBIDDEN,
             Json(api_err("CSRF token missing/invalid")),
@@ -271,10 +322,104 @@ pub(crate) async fn delete_key(
             .into_response();
     }
 
+    if let Err(err) = find_key(&state, &id) {
+        return err.into_response();
+    }
     match state.vault.delete_key(&id) {
         Ok(_) => Json(ApiOk { ok: true }).into_response(),
-        Err(err) => (StatusCode::BAD_REQUEST, Json(api_err(err.to_string()))).into_response(),
+        Err(err) => AppError::invalid_key(err.to_string()).into_response(),
+    }
+}
+
+/// A view of a key that identifies it without revealing it: fingerprint, type details and a
+/// few characters from each end of the material. `mode=masked` is the only mode; full material
+/// is never served.
+pub(crate) async fn key_material(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Query(query): Query<MaterialQuery>,
+) -> impl IntoResponse {
+    if require_csrf(&headers, &state.csrf).is_err() {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(api_err("CSRF token missing/invalid")),
+        )
+            .into_response();
+    }
+    if query.mode.as_deref() != Some("masked") {
+        return AppError::invalid_key("mode must be \"masked\"").into_response();
+    }
+
+    let entry = match find_key(&state, &id) {
+        Ok(entry) => entry,
+        Err(err) => return err.into_response(),
+    };
+    let masked = state.vault.get_key_material(&id).and_then(|material| {
+        let fingerprint = if entry.kind.eq_ignore_ascii_case("hmac") {
+            Some(format!(
+                "sha256:{}",
+                hex::encode(Sha256::digest(material.trim().as_bytes()))
+            ))
+        } else {
+            state.vault.key_fingerprint(&entry)?
+        };
+        Ok((mask_material(&entry.kind, &material), fingerprint))
+    });
+    match masked {
+        Ok((mut data, fingerprint)) => {
+            data["id"] = json!(entry.id);
+            data["name"] = json!(entry.name);
+            data["kind"] = json!(entry.kind);
+            data["mode"] = json!("masked");
+            data["fingerprint"] = json!(fingerprint);
+            Json(ApiList { ok: true, data }).into_response()
+        }
+        Err(err) => AppError::invalid_key(err.to_string()).into_response(),
+    }
+}
+
+/// The PEM label, type details and masked preview of `material`. The preview is taken from the
+/// base64 body for PEM keys, so the armor lines do not use up the visible characters. HMAC
+/// secrets are short enough that any preview is a real share of the key, so they only report
+/// their length.
+fn mask_material(kind: &str, material: &str) -> Value {
+    let material = material.trim();
+    if kind.eq_ignore_ascii_case("hmac") {
+        return json!({
+            "pem_label": null,
+            "details": { "kty": "oct", "bytes": material.len() },
+            "preview": null,
+            "length": material.chars().count(),
+        });
     }
+    let label = material
+        .lines()
+        .next()
+        .and_then(|line| line.strip_prefix("-----BEGIN "))
+        .and_then(|line| line.strip_suffix("-----"));
+    let body: Vec<char> = match label {
+        Some(_) => material
+            .lines()
+            .filter(|line| !line.starts_with("-----"))
+            .flat_map(str::chars)
+            .collect(),
+        None => material.chars().collect(),
+    };
+    let shown = MASK_PREVIEW_CHARS.min(body.len() / 8);
+    let preview = if shown == 0 {
+        "…".to_string()
+    } else {
+        let head: String = body[..shown].iter().collect();
+        let tail: String = body[body.len() - shown..].iter().collect();
+        format!("{head}…{tail}")
+    };
+    json!({
+        "pem_label": label,
+        "details": key_type_details(material),
+        "preview": preview,
+        "length": body.len(),
+    })
 }
 
 pub(crate) async fn list_tokens(
@@ -287,11 +432,7 @@ pub(crate) async fn list_tokens(
             data: tokens,
         })
         .into_response(),
-        Err(err) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(api_err(err.to_string())),
-        )
-            .into_response(),
+        Err(err) => AppError::internal(err.to_string()).into_response(),
     }
 }
 
@@ -300,7 +441,7 @@ pub(crate) async fn reveal_token(
     headers: HeaderMap,
     Path(id): Path<String>,
 ) -> impl IntoResponse {
-    if require_csrf(&headers, state.csrf.as_str()).is_err() {
+    if require_csrf(&headers, &state.csrf).is_err() {
         return (
             StatusCode::FORBIDDEN,
             Json(api_err("CSRF token missing/invalid")),
@@ -308,13 +449,16 @@ pub(crate) async fn reveal_token(
             .into_response();
     }
 
+    if let Err(err) = find_token(&state, &id) {
+        return err.into_response();
+    }
     match state.vault.get_token_material(&id) {
         Ok(token) => Json(ApiList {
             ok: true,
             data: json!({ "token": token }),
         })
         .into_response(),
-        Err(err) => (StatusCode::BAD_REQUEST, Json(api_err(err.to_string()))).into_response(),
+        Err(err) => AppError::invalid_key(err.to_string()).into_response(),
     }
 }
 
@@ -323,7 +467,7 @@ pub(crate) async fn add_token(
     headers: HeaderMap,
     Json(req): Json<AddTokenReq>,
 ) -> impl IntoResponse {
-    if require_csrf(&headers, state.csrf.as_str()).is_err() {
+    if require_csrf(&headers, &state.csrf).is_err() {
         return (
             StatusCode::FORBIDDEN,
             Json(api_err("CSRF token missing/invalid")),
@@ -343,7 +487,7 @@ pub(crate) async fn add_token(
             data: saved,
         })
         .into_response(),
-        Err(err) => (StatusCode::BAD_REQUEST, Json(api_err(err.to_string()))).into_response(),
+        Err(err) => AppError::invalid_key(err.to_string()).into_response(),
     }
 }
 
@@ -352,7 +496,7 @@ pub(crate) async fn delete_token(
     headers: HeaderMap,
     Path(id): Path<String>,
 ) -> impl IntoResponse {
-    if require_csrf(&headers, state.csrf.as_str()).is_err() {
+    if require_csrf(&headers, &state.csrf).is_err() {
         return (
             StatusCode::FORBIDDEN,
             Json(api_err("CSRF token missing/invalid")),
@@ -360,9 +504,12 @@ pub(crate) async fn delete_token(
             .into_response();
     }
 
+    if let Err(err) = find_token(&state, &id) {
+        return err.into_response();
+    }
     match state.vault.delete_token(&id) {
         Ok(_) => Json(ApiOk { ok: true }).into_response(),
-        Err(err) => (StatusCode::BAD_REQUEST, Json(api_err(err.to_string()))).into_response(),
+        Err(err) => AppError::invalid_key(err.to_string()).into_response(),
     }
 }
 
@@ -371,7 +518,7 @@ pub(crate) async fn export_vault(
     headers: HeaderMap,
     Json(req): Json<ExportReq>,
 ) -> impl IntoResponse {
-    if require_csrf(&headers, state.csrf.as_str()).is_err() {
+    if require_csrf(&headers, &state.csrf).is_err() {
         return (
             StatusCode::FORBIDDEN,
             Json(api_err("CSRF token missing/invalid")),
@@ -379,25 +526,26 @@ pub(crate) async fn export_vault(
             .into_response();
     }
 
-    match state.vault.export_bundle(&req.passphrase) {
-        Ok(bundle) => {
-            let bundle_json = match serde_json::to_string_pretty(&bundle) {
+    // The UI has no override: keys of non-exportable projects are always left out.
+    let protection = BundleProtection::Passphrase(req.passphrase);
+    match state.vault.export_bundle_with(&protection, false) {
+        Ok(report) => {
+            let bundle_json = match serde_json::to_string_pretty(&report.bundle) {
                 Ok(text) => text,
                 Err(err) => {
-                    return (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(api_err(format!("serialize bundle: {err}"))),
-                    )
-                        .into_response()
+                    return AppError::internal(format!("serialize bundle: {err}")).into_response()
                 }
             };
             Json(ApiList {
                 ok: true,
-                data: json!({ "bundle": bundle_json }),
+                data: json!({
+                    "bundle": bundle_json,
+                    "skipped_keys": report.non_exportable_keys,
+                }),
             })
             .into_response()
         }
-        Err(err) => (StatusCode::BAD_REQUEST, Json(api_err(err.to_string()))).into_response(),
+        Err(err) => AppError::invalid_key(err.to_string()).into_response(),
     }
 }
 
@@ -406,7 +554,7 @@ pub(crate) async fn import_vault(
     headers: HeaderMap,
     Json(req): Json<ImportReq>,
 ) -> impl IntoResponse {
-    if require_csrf(&headers, state.csrf.as_str()).is_err() {
+    if require_csrf(&headers, &state.csrf).is_err() {
         return (
             StatusCode::FORBIDDEN,
             Json(api_err("CSRF token missing/invalid")),
@@ -417,11 +565,7 @@ pub(crate) async fn import_vault(
     let bundle: ExportBundle = match serde_json::from_str(&req.bundle) {
         Ok(bundle) => bundle,
         Err(err) => {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(api_err(format!("invalid bundle JSON: {err}"))),
-            )
-                .into_response()
+            return AppError::invalid_key(format!("invalid bundle JSON: {err}")).into_response()
         }
     };
 
@@ -430,6 +574,269 @@ pub(crate) async fn import_vault(
         .import_bundle(&bundle, &req.passphrase, req.replace.unwrap_or(false))
     {
         Ok(()) => Json(ApiOk { ok: true }).into_response(),
-        Err(err) => (StatusCode::BAD_REQUEST, Json(api_err(err.to_string()))).into_response(),
+        Err(err) => AppError::invalid_key(err.to_string()).into_response(),
+    }
+}
+
+pub(crate) async fn export_project(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Json(req): Json<ExportReq>,
+) -> impl IntoResponse {
+    if require_csrf(&headers, &state.csrf).is_err() {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(api_err("CSRF token missing/invalid")),
+        )
+            .into_response();
+    }
+
+    if let Err(err) = find_project(&state, &id) {
+        return err.into_response();
+    }
+    let protection = BundleProtection::Passphrase(req.passphrase);
+    match state
+        .vault
+        .export_project_bundle_with(&id, &protection, false)
+    {
+        Ok(report) => {
+            let bundle_json = match serde_json::to_string_pretty(&report.bundle) {
+                Ok(text) => text,
+                Err(err) => {
+                    return AppError::internal(format!("serialize bundle: {err}")).into_response()
+                }
+            };
+            Json(ApiList {
+                ok: true,
+                data: json!({
+                    "bundle": bundle_json,
+                    "skipped_keys": report.non_exportable_keys,
+                }),
+            })
+            .into_response()
+        }
+        Err(err) => AppError::invalid_key(err.to_string()).into_response(),
+    }
+}
+
+pub(crate) async fn import_project(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Json(req): Json<ProjectImportReq>,
+) -> impl IntoResponse {
+    if require_csrf(&headers, &state.csrf).is_err() {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(api_err("CSRF token missing/invalid")),
+        )
+            .into_response();
+    }
+
+    if let Err(err) = find_project(&state, &id) {
+        return err.into_response();
+    }
+    let strategy = match req.on_conflict.as_deref().map(str::trim) {
+        None | Some("") | Some("skip") => ConflictStrategy::Skip,
+        Some("overwrite") => ConflictStrategy::Overwrite,
+        Some("rename") => ConflictStrategy::Rename,
+        Some(other) => {
+            return AppError::invalid_key(format!(
+                "unknown on_conflict '{other}' (expected skip, overwrite, or rename)"
+            ))
+            .into_response()
+        }
+    };
+    let bundle: ExportBundle = match serde_json::from_str(&req.bundle) {
+        Ok(bundle) => bundle,
+        Err(err) => {
+            return AppError::invalid_key(format!("invalid bundle JSON: {err}")).into_response()
+        }
+    };
+
+    let unlock = BundleUnlock::Passphrase(req.passphrase);
+    match state.vault.merge_bundle_into_project(
+        &bundle,
+        &unlock,
+        &id,
+        strategy,
+        req.dry_run.unwrap_or(false),
+    ) {
+        Ok(report) => Json(ApiList {
+            ok: true,
+            data: report,
+        })
+        .into_response(),
+        Err(err) => AppError::invalid_key(err.to_string()).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::csrf::{CsrfStore, CSRF_HEADER, SESSION_COOKIE};
+    use super::super::super::AppState;
+    use super::super::types::MaterialQuery;
+    use super::{delete_key, delete_project, delete_token, key_material, reveal_token};
+    use crate::keygen::{generate_key_material, EcCurve, KeyGenSpec};
+    use crate::vault::{KeyEntryInput, ProjectInput, Vault, VaultConfig};
+    use axum::extract::{Path, Query, State};
+    use axum::http::header::COOKIE;
+    use axum::http::{HeaderMap, HeaderValue, StatusCode};
+    use axum::response::{IntoResponse, Response};
+    use serde_json::Value;
+    use std::sync::Arc;
+
+    fn state_with_keys(keys: &[(&str, &str)]) -> (AppState, Vec<String>) {
+        let vault = Vault::open(VaultConfig {
+            no_persist: true,
+            data_dir: None,
+        })
+        .expect("memory vault");
+        let project = vault
+            .add_project(ProjectInput {
+                name: "api".to_string(),
+                description: None,
+                tags: Vec::new(),
+            })
+            .expect("project");
+        let ids = keys
+            .iter()
+            .map(|(kind, secret)| {
+                vault
+                    .add_key(KeyEntryInput {
+                        project_id: project.id.clone(),
+                        name: format!("{kind}-key"),
+                        kind: kind.to_string(),
+                        secret: secret.to_string(),
+                        kid: None,
+                        description: None,
+                        tags: Vec::new(),
+                    })
+                    .expect("key")
+                    .id
+            })
+            .collect();
+        let state = AppState {
+            csrf: Arc::new(CsrfStore::default()),
+            vault,
+            recorder: None,
+            cors_inspect: false,
+            opened_file: Default::default(),
+        };
+        (state, ids)
+    }
+
+    fn csrf_headers(state: &AppState) -> HeaderMap {
+        let issued = state.csrf.issue(&HeaderMap::new());
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            COOKIE,
+            HeaderValue::from_str(&format!("{SESSION_COOKIE}={}", issued.session_id)).unwrap(),
+        );
+        headers.insert(CSRF_HEADER, HeaderValue::from_str(&issued.token).unwrap());
+        headers
+    }
+
+    async fn json_body(response: Response) -> (StatusCode, Value) {
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("body");
+        (status, serde_json::from_slice(&body).expect("json body"))
+    }
+
+    async fn masked(state: &AppState, id: &str, mode: Option<&str>) -> (StatusCode, Value) {
+        let headers = csrf_headers(state);
+        let query = MaterialQuery {
+            mode: mode.map(str::to_string),
+        };
+        let response = key_material(
+            State(state.clone()),
+            headers,
+            Path(id.to_string()),
+            Query(query),
+        )
+        .await
+        .into_response();
+        json_body(response).await
+    }
+
+    #[tokio::test]
+    async fn unknown_ids_are_not_found() {
+        let (state, _) = state_with_keys(&[]);
+        let missing = || Path("missing".to_string());
+        let responses = [
+            delete_project(State(state.clone()), csrf_headers(&state), missing())
+                .await
+                .into_response(),
+            delete_key(State(state.clone()), csrf_headers(&state), missing())
+                .await
+                .into_response(),
+            reveal_token(State(state.clone()), csrf_headers(&state), missing())
+                .await
+                .into_response(),
+            delete_token(State(state.clone()), csrf_headers(&state), missing())
+                .await
+                .into_response(),
+        ];
+        for response in responses {
+            let (status, body) = json_body(response).await;
+            assert_eq!(status, StatusCode::NOT_FOUND);
+            assert_eq!(body["code"], "NOT_FOUND");
+        }
+    }
+
+    #[tokio::test]
+    async fn key_material_masked_identifies_keys_without_revealing_them() {
+        let pem = generate_key_material(KeyGenSpec::Ec {
+            curve: EcCurve::P256,
+        })
+        .expect("ec key");
+        let secret = "0123456789abcdef0123456789abcdef";
+        let (state, ids) = state_with_keys(&[("ec", &pem), ("hmac", secret)]);
+
+        let (status, body) = masked(&state, &ids[0], Some("masked")).await;
+        assert_eq!(status, StatusCode::OK);
+        let data = &body["data"];
+        assert_eq!(data["mode"], "masked");
+        assert_eq!(data["pem_label"], "PRIVATE KEY");
+        assert_eq!(data["details"]["kty"], "EC");
+        assert_eq!(data["details"]["crv"], "P-256");
+        assert!(data["fingerprint"]
+            .as_str()
+            .is_some_and(|f| f.starts_with("sha256:")));
+        let preview = data["preview"].as_str().expect("preview");
+        let (head, tail) = preview.split_once('…').expect("ellipsis");
+        assert_eq!((head.len(), tail.len()), (4, 4));
+        assert!(!body.to_string().contains(&pem[40..80]));
+
+        let (status, body) = masked(&state, &ids[1], Some("masked")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["pem_label"], Value::Null);
+        assert_eq!(body["data"]["details"]["bytes"], 32);
+        assert_eq!(body["data"]["length"], 32);
+        assert_eq!(body["data"]["preview"], Value::Null);
+        assert!(body["data"]["fingerprint"]
+            .as_str()
+            .is_some_and(|f| f.starts_with("sha256:")));
+        let text = body.to_string();
+        for start in 0..=secret.len() - 4 {
+            let piece = &secret[start..start + 4];
+            assert!(!text.contains(piece), "response leaks {piece:?}");
+        }
+
+        assert_eq!(
+            masked(&state, &ids[1], None).await.0,
+            StatusCode::UNPROCESSABLE_ENTITY
+        );
+        assert_eq!(
+            masked(&state, &ids[1], Some("full")).await.0,
+            StatusCode::UNPROCESSABLE_ENTITY
+        );
+        assert_eq!(
+            masked(&state, "missing", Some("masked")).await.0,
+            StatusCode::NOT_FOUND
+        );
     }
 }
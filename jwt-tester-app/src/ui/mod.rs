@@ -1,20 +1,23 @@
+mod browser;
+mod csrf;
 mod handlers;
 
 use crate::error::{AppError, AppResult};
+use crate::file_assoc::{self, OpenedFile};
 use crate::output::{emit_ok, CommandOutput, OutputConfig};
+use crate::qr;
 use crate::vault::Vault;
+use axum::middleware::from_fn_with_state;
 use axum::routing::{delete, get, post};
 use axum::Router;
-use base64::engine::general_purpose::URL_SAFE_NO_PAD;
-use base64::Engine;
-use rand::RngCore;
 use std::ffi::OsString;
 use std::future::Future;
-use std::net::{IpAddr, SocketAddr};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 use std::path::{Path, PathBuf};
 use std::pin::Pin;
 use std::process::Stdio;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tokio::net::TcpListener;
 use tokio::process::{Child, Command};
 use tracing::{info, warn};
@@ -28,37 +31,60 @@ pub struct UiConfig {
     pub data_dir: Option<PathBuf>,
     pub force_build: bool,
     pub dev_mode: bool,
+    /// Vite port; `None` picks one (see `pick_dev_port`).
+    pub dev_port: Option<u16>,
+    /// `--open`: launch the browser once the page answers.
+    pub open_browser: bool,
+    /// `--qr`: print a QR code of the page URL to stderr.
+    pub qr: bool,
+    /// `--open-file`: a file the page shows once it loads (see `file_assoc`).
+    pub open_file: Option<String>,
     pub npm_path: Option<PathBuf>,
+    pub record: Option<PathBuf>,
+    pub allow_cors_inspect: bool,
 }
 
 #[derive(Clone)]
 pub(super) struct AppState {
-    csrf: Arc<String>,
+    csrf: Arc<csrf::CsrfStore>,
     vault: Vault,
+    recorder: Option<Arc<handlers::SessionRecorder>>,
+    /// `--allow-cors-inspect`: `/api/jwt/inspect` accepts any origin without a CSRF token.
+    cors_inspect: bool,
+    /// `--open-file`: handed to the page once by `/api/opened-file`.
+    opened_file: Arc<Mutex<Option<OpenedFile>>>,
 }
 
 const UI_ASSETS_ENV: &str = "JWT_TESTER_UI_ASSETS_DIR";
 const UI_NPM_ENV: &str = "JWT_TESTER_NPM";
 const UI_DEV_HOST: &str = "127.0.0.1";
+/// Vite's own default, used when it is free so bookmarks keep working.
 const UI_DEV_PORT: u16 = 5173;
+const UI_DEV_PORT_ENV: &str = "JWT_TESTER_UI_DEV_PORT";
 
 pub async fn run_ui(config: UiConfig, output: OutputConfig) -> AppResult<()> {
-    validate_bind_target(config.host, config.allow_remote)?;
+    validate_bind_target(config.host, config.allow_remote, "UI")?;
+    // Read before anything starts, so a bad path fails instead of opening an empty page.
+    let opened_file = config
+        .open_file
+        .as_deref()
+        .map(file_assoc::read_opened)
+        .transpose()?;
     if config.force_build {
         ensure_ui_assets(true, config.npm_path.as_deref()).await?;
     } else if !config.dev_mode {
         ensure_ui_assets(false, config.npm_path.as_deref()).await?;
     }
 
-    let mut csrf_raw = [0u8; 32];
-    rand::thread_rng().fill_bytes(&mut csrf_raw);
-    let csrf = URL_SAFE_NO_PAD.encode(csrf_raw);
-
     let vault = Vault::open(crate::vault::VaultConfig {
         no_persist: config.no_persist,
         data_dir: config.data_dir,
     })
     .map_err(|e| AppError::internal(format!("failed to open vault: {e}")))?;
+    let recorder = match &config.record {
+        Some(path) => Some(Arc::new(handlers::SessionRecorder::open(path)?)),
+        None => None,
+    };
 
     let listener = TcpListener::bind(SocketAddr::new(config.host, config.port))
         .await
@@ -69,15 +95,20 @@ pub async fn run_ui(config: UiConfig, output: OutputConfig) -> AppResult<()> {
     let base_url = format!("http://{}:{}/", local_addr.ip(), local_addr.port());
     let api_base = format!("http://{}:{}", local_addr.ip(), local_addr.port());
 
-    let mut dev_server = if config.dev_mode {
-        Some(spawn_ui_dev_server(&api_base, config.npm_path.as_deref()).await?)
+    let dev_port = if config.dev_mode {
+        Some(match config.dev_port {
+            Some(port) => port,
+            None => pick_dev_port(UI_DEV_PORT)?,
+        })
     } else {
         None
     };
+    let mut dev_server = match dev_port {
+        Some(port) => Some(spawn_ui_dev_server(&api_base, port, config.npm_path.as_deref()).await?),
+        None => None,
+    };
 
-    let dev_url = config
-        .dev_mode
-        .then(|| format!("http://{}:{}/", UI_DEV_HOST, UI_DEV_PORT));
+    let dev_url = dev_port.map(|port| format!("http://{UI_DEV_HOST}:{port}/"));
 
     info!("UI started at {base_url}");
     if let Some(url) = &dev_url {
@@ -90,16 +121,40 @@ pub async fn run_ui(config: UiConfig, output: OutputConfig) -> AppResult<()> {
     } else {
         base_url.clone()
     };
-    let payload = if let Some(url) = &dev_url {
-        serde_json::json!({ "url": base_url, "dev_url": url })
+    let mut payload = if let Some(url) = &dev_url {
+        // `url` stays the API for existing consumers; `dev_url` is the page to open.
+        serde_json::json!({ "url": base_url, "api_url": api_base, "dev_url": url, "dev_port": dev_port })
     } else {
         serde_json::json!({ "url": base_url })
     };
-    emit_ok(output, CommandOutput::new(payload, text));
+    if let Some(path) = &config.record {
+        info!("recording /api/jwt/* calls to {}", path.display());
+        payload["record"] = serde_json::json!(path);
+    }
+    if config.allow_cors_inspect {
+        warn!("--allow-cors-inspect: any web page can call /api/jwt/inspect on this server");
+        payload["cors_inspect"] = serde_json::json!(true);
+    }
+    emit_ok(output, CommandOutput::new(payload, text))?;
+
+    // The page to hand out: Vite in dev mode, otherwise the server itself.
+    let page_addr = match dev_port {
+        Some(port) => SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port),
+        None => local_addr,
+    };
+    if config.qr {
+        print_qr(page_addr)?;
+    }
+    if config.open_browser {
+        tokio::spawn(open_when_ready(page_addr));
+    }
 
     let state = AppState {
-        csrf: Arc::new(csrf),
+        csrf: Arc::new(csrf::CsrfStore::default()),
         vault,
+        recorder,
+        cors_inspect: config.allow_cors_inspect,
+        opened_file: Arc::new(Mutex::new(opened_file)),
     };
 
     let app = Router::new()
@@ -107,9 +162,29 @@ pub async fn run_ui(config: UiConfig, output: OutputConfig) -> AppResult<()> {
         .route("/assets/*path", get(handlers::asset))
         .route("/api/health", get(handlers::health))
         .route("/api/csrf", get(handlers::csrf))
-        .route("/api/jwt/encode", post(handlers::encode_token))
-        .route("/api/jwt/verify", post(handlers::verify_token))
-        .route("/api/jwt/inspect", post(handlers::inspect_token))
+        .route("/api/opened-file", get(handlers::opened_file))
+        .route(
+            "/api/jwt/encode",
+            post(handlers::encode_token)
+                .layer(from_fn_with_state(state.clone(), handlers::record_history))
+                .layer(from_fn_with_state(state.clone(), handlers::record_session)),
+        )
+        .route(
+            "/api/jwt/verify",
+            post(handlers::verify_token)
+                .layer(from_fn_with_state(state.clone(), handlers::record_history))
+                .layer(from_fn_with_state(state.clone(), handlers::record_session)),
+        )
+        .route(
+            "/api/jwt/inspect",
+            post(handlers::inspect_token)
+                .layer(from_fn_with_state(state.clone(), handlers::record_session))
+                .layer(from_fn_with_state(state.clone(), handlers::cors_inspect)),
+        )
+        .route(
+            "/api/history",
+            get(handlers::list_history).delete(handlers::clear_history),
+        )
         .route(
             "/api/vault/projects",
             get(handlers::list_projects).post(handlers::add_project),
@@ -119,25 +194,45 @@ pub async fn run_ui(config: UiConfig, output: OutputConfig) -> AppResult<()> {
             post(handlers::set_default_key),
         )
         .route("/api/vault/projects/:id", delete(handlers::delete_project))
-        .route("/api/vault/export", post(handlers::export_vault))
-        .route("/api/vault/import", post(handlers::import_vault))
+        .route(
+            "/api/vault/projects/:id/export",
+            post(handlers::export_project)
+                .layer(from_fn_with_state(state.clone(), handlers::rotate_csrf)),
+        )
+        .route(
+            "/api/vault/projects/:id/import",
+            post(handlers::import_project)
+                .layer(from_fn_with_state(state.clone(), handlers::rotate_csrf)),
+        )
+        .route(
+            "/api/vault/export",
+            post(handlers::export_vault)
+                .layer(from_fn_with_state(state.clone(), handlers::rotate_csrf)),
+        )
+        .route(
+            "/api/vault/import",
+            post(handlers::import_vault)
+                .layer(from_fn_with_state(state.clone(), handlers::rotate_csrf)),
+        )
         .route(
             "/api/vault/keys",
             get(handlers::list_keys).post(handlers::add_key),
         )
         .route("/api/vault/keys/generate", post(handlers::generate_key))
         .route("/api/vault/keys/:id", delete(handlers::delete_key))
+        .route("/api/vault/keys/:id/material", get(handlers::key_material))
         .route(
             "/api/vault/tokens",
             get(handlers::list_tokens).post(handlers::add_token),
         )
         .route(
             "/api/vault/tokens/:id/material",
-            post(handlers::reveal_token),
+            post(handlers::reveal_token)
+                .layer(from_fn_with_state(state.clone(), handlers::rotate_csrf)),
         )
         .route("/api/vault/tokens/:id", delete(handlers::delete_token))
-        .with_state(state)
-        .layer(axum::middleware::from_fn(handlers::security_headers));
+        .with_state(state.clone())
+        .layer(from_fn_with_state(state, handlers::security_headers));
 
     let shutdown = async move {
         if let Err(err) = tokio::signal::ctrl_c().await {
@@ -159,6 +254,84 @@ pub async fn run_ui(config: UiConfig, output: OutputConfig) -> AppResult<()> {
     Ok(())
 }
 
+/// How long `--open` waits for the page (Vite can take a while to start) before giving up.
+const OPEN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Prints the QR code and the URL it encodes. A wildcard bind address is replaced by this
+/// machine's LAN address, the one another device would use.
+fn print_qr(addr: SocketAddr) -> AppResult<()> {
+    let host = if addr.ip().is_unspecified() {
+        lan_address(addr.is_ipv6()).unwrap_or(addr.ip())
+    } else {
+        addr.ip()
+    };
+    let url = page_url(SocketAddr::new(host, addr.port()));
+    eprint!("{}", qr::render(&url)?);
+    eprintln!("{url}");
+    if host.is_loopback() || host.is_unspecified() {
+        warn!("the QR code points at {host}, which other devices cannot reach; use --host 0.0.0.0 --allow-remote");
+    }
+    Ok(())
+}
+
+/// The address of the interface that routes outside; connecting a UDP socket sends nothing.
+fn lan_address(ipv6: bool) -> Option<IpAddr> {
+    let (bind, probe): (IpAddr, IpAddr) = if ipv6 {
+        // 2001:db8::/32 and 192.0.2.0/24 are documentation ranges: routable-looking, never used.
+        (
+            IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+            IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)),
+        )
+    } else {
+        (
+            IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)),
+        )
+    };
+    let socket = std::net::UdpSocket::bind((bind, 0)).ok()?;
+    socket.connect((probe, 9)).ok()?;
+    let ip = socket.local_addr().ok()?.ip();
+    (!ip.is_unspecified()).then_some(ip)
+}
+
+/// Waits until the page accepts connections, then opens it. Failures are only logged; the
+/// server keeps running either way.
+async fn open_when_ready(addr: SocketAddr) {
+    let addr = if addr.ip().is_unspecified() {
+        let loopback = if addr.is_ipv6() {
+            IpAddr::V6(Ipv6Addr::LOCALHOST)
+        } else {
+            IpAddr::V4(Ipv4Addr::LOCALHOST)
+        };
+        SocketAddr::new(loopback, addr.port())
+    } else {
+        addr
+    };
+    let url = page_url(addr);
+    let deadline = tokio::time::Instant::now() + OPEN_TIMEOUT;
+    while tokio::net::TcpStream::connect(addr).await.is_err() {
+        if tokio::time::Instant::now() >= deadline {
+            warn!("not opening a browser: {url} did not answer within {OPEN_TIMEOUT:?}");
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(250)).await;
+    }
+    let opened = tokio::task::spawn_blocking({
+        let url = url.clone();
+        move || browser::open(&url)
+    })
+    .await;
+    match opened {
+        Ok(Ok(())) => info!("opened {url} in the browser"),
+        Ok(Err(err)) => warn!("failed to open a browser for {url}: {err}"),
+        Err(err) => warn!("failed to open a browser for {url}: {err}"),
+    }
+}
+
+fn page_url(addr: SocketAddr) -> String {
+    format!("http://{addr}/")
+}
+
 fn assets_root() -> PathBuf {
     resolve_assets_root().0
 }
@@ -332,7 +505,31 @@ fn build_npm_command(invocation: &NpmInvocation) -> Command {
     command
 }
 
-async fn spawn_ui_dev_server(api_base: &str, npm_override: Option<&Path>) -> AppResult<Child> {
+/// `preferred` if it can be bound on the dev host right now, otherwise a port the OS hands out.
+/// The probe is released before Vite starts; `--strictPort` makes Vite fail loudly if another
+/// process grabs the port in between, rather than drifting to a URL nobody printed.
+fn pick_dev_port(preferred: u16) -> AppResult<u16> {
+    let host: IpAddr = UI_DEV_HOST
+        .parse()
+        .map_err(|e| AppError::internal(format!("invalid UI dev host: {e}")))?;
+    if std::net::TcpListener::bind((host, preferred)).is_ok() {
+        return Ok(preferred);
+    }
+    let listener = std::net::TcpListener::bind((host, 0))
+        .map_err(|e| AppError::internal(format!("failed to find a free UI dev port: {e}")))?;
+    let port = listener
+        .local_addr()
+        .map_err(|e| AppError::internal(format!("failed to find a free UI dev port: {e}")))?
+        .port();
+    info!("port {preferred} is busy; UI dev server uses {port}");
+    Ok(port)
+}
+
+async fn spawn_ui_dev_server(
+    api_base: &str,
+    port: u16,
+    npm_override: Option<&Path>,
+) -> AppResult<Child> {
     let ui_dir = ui_source_dir();
     let invocation = resolve_npm_invocation(npm_override)?;
     let mut command = build_npm_command(&invocation);
@@ -343,9 +540,11 @@ async fn spawn_ui_dev_server(api_base: &str, npm_override: Option<&Path>) -> App
         .arg("--host")
         .arg(UI_DEV_HOST)
         .arg("--port")
-        .arg(UI_DEV_PORT.to_string())
+        .arg(port.to_string())
+        .arg("--strictPort")
         .current_dir(ui_dir)
         .env("JWT_TESTER_API_URL", api_base)
+        .env(UI_DEV_PORT_ENV, port.to_string())
         .env("BROWSER", "none")
         .stdout(Stdio::inherit())
         .stderr(Stdio::inherit());
@@ -484,14 +683,15 @@ fn cmd_program() -> OsString {
     std::env::var_os("ComSpec").unwrap_or_else(|| OsString::from("cmd"))
 }
 
-fn validate_bind_target(host: IpAddr, allow_remote: bool) -> AppResult<()> {
+/// Local-only unless `--allow-remote`; `what` names the server in the refusal.
+pub(crate) fn validate_bind_target(host: IpAddr, allow_remote: bool, what: &str) -> AppResult<()> {
     let is_local = match host {
         IpAddr::V4(v4) => v4.is_loopback(),
         IpAddr::V6(v6) => v6.is_loopback(),
     };
     if !is_local && !allow_remote {
         return Err(AppError::invalid_key(format!(
-            "Refusing to bind UI to non-localhost address {host}. Use --allow-remote to override (dangerous)."
+            "Refusing to bind {what} to non-localhost address {host}. Use --allow-remote to override (dangerous)."
         )));
     }
     Ok(())
@@ -499,7 +699,12 @@ fn validate_bind_target(host: IpAddr, allow_remote: bool) -> AppResult<()> {
 
 #[cfg(test)]
 mod tests {
-    use super::{ensure_ui_assets_with, resolve_npm_invocation, validate_bind_target, UI_NPM_ENV};
+    #[cfg(windows)]
+    use super::UI_NPM_ENV;
+    use super::{
+        ensure_ui_assets_with, pick_dev_port, resolve_npm_invocation, validate_bind_target,
+        UI_DEV_HOST,
+    };
     use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
     use std::sync::atomic::{AtomicUsize, Ordering};
     use std::sync::Arc;
@@ -512,20 +717,29 @@ mod tests {
 
     #[test]
     fn validate_bind_target_allows_loopback() {
-        assert!(validate_bind_target(IpAddr::V4(Ipv4Addr::LOCALHOST), false).is_ok());
-        assert!(validate_bind_target(IpAddr::V6(Ipv6Addr::LOCALHOST), false).is_ok());
+        assert!(validate_bind_target(IpAddr::V4(Ipv4Addr::LOCALHOST), false, "UI").is_ok());
+        assert!(validate_bind_target(IpAddr::V6(Ipv6Addr::LOCALHOST), false, "UI").is_ok());
     }
 
     #[test]
     fn validate_bind_target_rejects_remote_without_override() {
-        let err = validate_bind_target(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), false)
+        let err = validate_bind_target(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), false, "UI")
             .expect_err("expected error");
         assert!(err.to_string().contains("Refusing to bind UI"));
     }
 
     #[test]
     fn validate_bind_target_allows_remote_with_override() {
-        assert!(validate_bind_target(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), true).is_ok());
+        assert!(validate_bind_target(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), true, "UI").is_ok());
+    }
+
+    #[test]
+    fn pick_dev_port_avoids_a_busy_port() {
+        let busy = std::net::TcpListener::bind((UI_DEV_HOST, 0)).expect("bind");
+        let busy_port = busy.local_addr().expect("addr").port();
+        let port = pick_dev_port(busy_port).expect("free port");
+        assert_ne!(port, busy_port);
+        assert_ne!(port, 0);
     }
 
     #[tokio::test]
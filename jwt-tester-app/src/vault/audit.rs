@@ -0,0 +1,80 @@
+use super::helpers::now_unix;
+#[cfg(feature = "vault")]
+use super::sqlite::open_db;
+use super::store::{Vault, VaultInner};
+#[cfg(feature = "vault")]
+use rusqlite::params;
+use serde::Serialize;
+use serde_json::Value;
+use uuid::Uuid;
+
+/// A recorded use of an override such as `--include-non-exportable`. Unlike history, the audit
+/// log is always on and never trimmed. `detail` names keys and projects, never their material.
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditEntry {
+    pub id: String,
+    pub created_at: i64,
+    pub action: String,
+    pub project_id: Option<String>,
+    pub detail: Value,
+}
+
+impl Vault {
+    pub fn record_audit(
+        &self,
+        action: &str,
+        project_id: Option<&str>,
+        detail: Value,
+    ) -> anyhow::Result<AuditEntry> {
+        let entry = AuditEntry {
+            id: Uuid::new_v4().to_string(),
+            created_at: now_unix(),
+            action: action.to_string(),
+            project_id: project_id.map(str::to_string),
+            detail,
+        };
+        match &self.inner {
+            VaultInner::Memory { state } => state.lock().unwrap().audit.insert(0, entry.clone()),
+            #[cfg(feature = "vault")]
+            VaultInner::Sqlite { db_path, .. } => {
+                let conn = open_db(db_path)?;
+                conn.execute(
+                    "INSERT INTO audit (id, created_at, action, project_id, detail) VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![
+                        entry.id,
+                        entry.created_at,
+                        entry.action,
+                        entry.project_id,
+                        entry.detail.to_string()
+                    ],
+                )?;
+            }
+        }
+        Ok(entry)
+    }
+
+    /// Lists audit entries, newest first.
+    pub fn list_audit(&self) -> anyhow::Result<Vec<AuditEntry>> {
+        match &self.inner {
+            VaultInner::Memory { state } => Ok(state.lock().unwrap().audit.clone()),
+            #[cfg(feature = "vault")]
+            VaultInner::Sqlite { db_path, .. } => {
+                let conn = open_db(db_path)?;
+                let mut stmt = conn.prepare(
+                    "SELECT id, created_at, action, project_id, detail FROM audit ORDER BY created_at DESC, rowid DESC",
+                )?;
+                let rows = stmt.query_map([], |row| {
+                    let detail: String = row.get(4)?;
+                    Ok(AuditEntry {
+                        id: row.get(0)?,
+                        created_at: row.get(1)?,
+                        action: row.get(2)?,
+                        project_id: row.get(3)?,
+                        detail: serde_json::from_str(&detail).unwrap_or(Value::Null),
+                    })
+                })?;
+                Ok(rows.collect::<Result<Vec<_>, _>>()?)
+            }
+        }
+    }
+}
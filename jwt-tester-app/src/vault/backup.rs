@@ -0,0 +1,268 @@
+use super::helpers::now_unix;
+use super::keychain::KeychainStore;
+use super::store::{Vault, VaultInner};
+use crate::vault_export::{self, BundleProtection, BundleUnlock, ExportBundle, ManifestCounts};
+use anyhow::Context;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+pub(super) const BACKUP_DIR: &str = "backups";
+const BACKUP_KEY_ACCOUNT: &str = "backup:key";
+const BACKUP_RETENTION_ENV: &str = "JWT_TESTER_BACKUP_RETENTION";
+const DEFAULT_BACKUP_RETENTION: usize = 10;
+
+/// On-disk backup file: the encrypted bundle plus enough metadata to list it.
+#[derive(Serialize, Deserialize)]
+struct BackupFile {
+    id: String,
+    created_at: i64,
+    reason: String,
+    bundle: ExportBundle,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupEntry {
+    pub id: String,
+    pub created_at: i64,
+    pub reason: String,
+    pub path: PathBuf,
+    /// Read from the sealed manifest; `None` when the backup key is unavailable.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub counts: Option<ManifestCounts>,
+}
+
+impl Vault {
+    /// Snapshots the vault into `<data_dir>/backups`, encrypted with a random key held
+    /// in the keychain. Returns `None` for in-memory or empty vaults.
+    pub fn create_backup(&self, reason: &str) -> anyhow::Result<Option<BackupEntry>> {
+        let Some(dir) = self.backup_dir() else {
+            return Ok(None);
+        };
+        if self.is_empty()? {
+            return Ok(None);
+        }
+
+        let key = self.backup_key(&dir)?;
+        let bundle = self.backup_bundle_with(&BundleProtection::RawKey(key))?;
+        let created_at = now_unix();
+        let reason = sanitize_reason(reason);
+        let mut suffix = [0u8; 4];
+        OsRng.fill_bytes(&mut suffix);
+        let id = format!("{created_at}-{reason}-{}", hex::encode(suffix));
+
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("create backup dir {}", dir.display()))?;
+        let path = dir.join(format!("{id}.json"));
+        let file = BackupFile {
+            id: id.clone(),
+            created_at,
+            reason: reason.clone(),
+            bundle,
+        };
+        let json = serde_json::to_vec_pretty(&file).context("serialize backup")?;
+        std::fs::write(&path, json).with_context(|| format!("write backup {}", path.display()))?;
+
+        let counts = vault_export::list_bundle(&file.bundle, &BundleUnlock::RawKey(key))
+            .ok()
+            .map(|listing| listing.manifest.counts);
+        Ok(Some(BackupEntry {
+            id,
+            created_at,
+            reason,
+            path,
+            counts,
+        }))
+    }
+
+    /// Backs up before a destructive operation and prunes to the configured retention.
+    /// `JWT_TESTER_BACKUP_RETENTION=0` disables automatic backups.
+    pub(super) fn auto_backup(&self, reason: &str) -> anyhow::Result<()> {
+        let retention = backup_retention()?;
+        if retention == 0 {
+            return Ok(());
+        }
+        self.create_backup(reason)
+            .context("automatic backup failed; set JWT_TESTER_BACKUP_RETENTION=0 to skip")?;
+        self.prune_backups(retention)?;
+        Ok(())
+    }
+
+    /// Lists backups, newest first.
+    pub fn list_backups(&self) -> anyhow::Result<Vec<BackupEntry>> {
+        let Some(dir) = self.backup_dir() else {
+            return Ok(Vec::new());
+        };
+        let files = read_backup_files(&dir)?;
+        let key = if files.is_empty() {
+            None
+        } else {
+            self.existing_backup_key().ok()
+        };
+
+        Ok(files
+            .into_iter()
+            .map(|(path, file)| {
+                let counts = key.and_then(|key| {
+                    vault_export::list_bundle(&file.bundle, &BundleUnlock::RawKey(key))
+                        .ok()
+                        .map(|listing| listing.manifest.counts)
+                });
+                BackupEntry {
+                    id: file.id,
+                    created_at: file.created_at,
+                    reason: file.reason,
+                    path,
+                    counts,
+                }
+            })
+            .collect())
+    }
+
+    /// Replaces the vault contents with the given backup. The current state is backed
+    /// up first, so a restore can itself be undone.
+    pub fn restore_backup(&self, id: &str) -> anyhow::Result<BackupEntry> {
+        let dir = self
+            .backup_dir()
+            .ok_or_else(|| anyhow::anyhow!("backups are not available for in-memory vaults"))?;
+        let (path, file) = read_backup_files(&dir)?
+            .into_iter()
+            .find(|(_, file)| file.id == id.trim())
+            .ok_or_else(|| anyhow::anyhow!("backup not found: {id}"))?;
+        let key = self.existing_backup_key()?;
+        let unlock = BundleUnlock::RawKey(key);
+        let counts = vault_export::list_bundle(&file.bundle, &unlock)
+            .ok()
+            .map(|listing| listing.manifest.counts);
+
+        self.import_bundle_with_reason(&file.bundle, &unlock, true, "restore")?;
+        Ok(BackupEntry {
+            id: file.id,
+            created_at: file.created_at,
+            reason: file.reason,
+            path,
+            counts,
+        })
+    }
+
+    /// Deletes all but the newest `keep` backups; returns how many were removed.
+    pub fn prune_backups(&self, keep: usize) -> anyhow::Result<usize> {
+        let Some(dir) = self.backup_dir() else {
+            return Ok(0);
+        };
+        let mut removed = 0;
+        for (path, _) in read_backup_files(&dir)?.into_iter().skip(keep) {
+            std::fs::remove_file(&path)
+                .with_context(|| format!("remove backup {}", path.display()))?;
+            removed += 1;
+        }
+        Ok(removed)
+    }
+
+    fn backup_dir(&self) -> Option<PathBuf> {
+        match &self.inner {
+            VaultInner::Memory { .. } => None,
+            #[cfg(feature = "vault")]
+            VaultInner::Sqlite { db_path, .. } => {
+                db_path.parent().map(|parent| parent.join(BACKUP_DIR))
+            }
+        }
+    }
+
+    fn existing_backup_key(&self) -> anyhow::Result<[u8; 32]> {
+        let (keychain_service, keychain) = self.backup_keychain()?;
+        let encoded = keychain
+            .get_password(keychain_service, BACKUP_KEY_ACCOUNT)
+            .context("backup key is missing from the keychain")?;
+        let bytes = hex::decode(encoded.trim()).context("decode backup key")?;
+        bytes
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("backup key has an invalid length"))
+    }
+
+    // A fresh key is only generated while no backups exist; otherwise a transient
+    // keychain error would orphan every earlier backup.
+    fn backup_key(&self, dir: &Path) -> anyhow::Result<[u8; 32]> {
+        match self.existing_backup_key() {
+            Ok(key) => Ok(key),
+            Err(err) if !read_backup_files(dir)?.is_empty() => Err(err),
+            Err(_) => {
+                let (keychain_service, keychain) = self.backup_keychain()?;
+                let mut key = [0u8; 32];
+                OsRng.fill_bytes(&mut key);
+                keychain.set_password(keychain_service, BACKUP_KEY_ACCOUNT, &hex::encode(key))?;
+                Ok(key)
+            }
+        }
+    }
+
+    fn backup_keychain(&self) -> anyhow::Result<(&str, &dyn KeychainStore)> {
+        match &self.inner {
+            #[cfg(feature = "vault")]
+            VaultInner::Sqlite {
+                keychain_service,
+                keychain,
+                ..
+            } => Ok((keychain_service, keychain.as_ref())),
+            _ => anyhow::bail!("backups are not available for in-memory vaults"),
+        }
+    }
+}
+
+fn backup_retention() -> anyhow::Result<usize> {
+    match std::env::var(BACKUP_RETENTION_ENV) {
+        Ok(raw) => raw
+            .trim()
+            .parse()
+            .with_context(|| format!("{BACKUP_RETENTION_ENV} must be a non-negative integer")),
+        Err(_) => Ok(DEFAULT_BACKUP_RETENTION),
+    }
+}
+
+fn read_backup_files(dir: &Path) -> anyhow::Result<Vec<(PathBuf, BackupFile)>> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err).with_context(|| format!("read {}", dir.display())),
+    };
+
+    let mut files = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+            continue;
+        }
+        let raw = std::fs::read(&path).with_context(|| format!("read {}", path.display()))?;
+        match serde_json::from_slice::<BackupFile>(&raw) {
+            Ok(file) => files.push((path, file)),
+            Err(err) => tracing::warn!("skipping unreadable backup {}: {err}", path.display()),
+        }
+    }
+    files.sort_by(|(_, a), (_, b)| {
+        b.created_at
+            .cmp(&a.created_at)
+            .then_with(|| b.id.cmp(&a.id))
+    });
+    Ok(files)
+}
+
+fn sanitize_reason(reason: &str) -> String {
+    let cleaned: String = reason
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    let cleaned = cleaned.trim_matches('-');
+    if cleaned.is_empty() {
+        "manual".to_string()
+    } else {
+        cleaned.to_string()
+    }
+}
@@ -0,0 +1,189 @@
+#[cfg(feature = "vault")]
+use super::keychain::KeychainStore;
+use super::store::Vault;
+#[cfg(feature = "vault")]
+use super::store::VaultInner;
+#[cfg(feature = "vault")]
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+#[cfg(feature = "vault")]
+use rand::{rngs::OsRng, RngCore};
+use serde::Serialize;
+use std::time::Duration;
+#[cfg(feature = "vault")]
+use std::time::Instant;
+
+/// Prefix of the throwaway accounts the benchmark writes, so a leftover entry is recognizable.
+#[cfg(feature = "vault")]
+const BENCH_ACCOUNT_PREFIX: &str = "bench:";
+
+/// Latency of one timed operation over all iterations, in milliseconds. Also used by `advise`
+/// for signing and verification.
+#[derive(Debug, Clone, Serialize)]
+pub struct LatencyStats {
+    pub min_ms: f64,
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+    pub mean_ms: f64,
+}
+
+impl LatencyStats {
+    /// `samples` must not be empty.
+    pub(crate) fn from_samples(samples: &mut [Duration]) -> Self {
+        samples.sort();
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        let total: Duration = samples.iter().sum();
+        LatencyStats {
+            min_ms: ms(samples[0]),
+            p50_ms: ms(percentile(samples, 50)),
+            p90_ms: ms(percentile(samples, 90)),
+            p99_ms: ms(percentile(samples, 99)),
+            max_ms: ms(samples[samples.len() - 1]),
+            mean_ms: ms(total) / samples.len() as f64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KeychainBench {
+    pub backend: &'static str,
+    pub service: String,
+    pub iterations: usize,
+    pub set: LatencyStats,
+    pub get: LatencyStats,
+    pub delete: LatencyStats,
+}
+
+/// Nearest-rank percentile of sorted, non-empty samples.
+fn percentile(sorted: &[Duration], pct: usize) -> Duration {
+    let rank = (pct * sorted.len()).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+#[cfg(feature = "vault")]
+impl Vault {
+    /// Times set/get/delete of throwaway entries against the keychain this vault uses. Entries
+    /// live under the vault's keychain service with a `bench:` account and are always deleted,
+    /// so stored keys and tokens are never touched.
+    pub fn bench_keychain(&self, iterations: usize) -> anyhow::Result<KeychainBench> {
+        let VaultInner::Sqlite {
+            keychain_service,
+            keychain,
+            ..
+        } = &self.inner
+        else {
+            anyhow::bail!(
+                "--no-persist vaults keep secrets in memory; there is no keychain to benchmark"
+            );
+        };
+        if iterations == 0 {
+            anyhow::bail!("iterations must be at least 1");
+        }
+        let run_id = uuid::Uuid::new_v4();
+        let mut set = Vec::with_capacity(iterations);
+        let mut get = Vec::with_capacity(iterations);
+        let mut delete = Vec::with_capacity(iterations);
+        for i in 0..iterations {
+            let account = format!("{BENCH_ACCOUNT_PREFIX}{run_id}:{i}");
+            let timed = time_round_trip(keychain.as_ref(), keychain_service, &account);
+            if timed.is_err() {
+                // Best effort: the failing operation may have left the entry behind.
+                let _ = keychain.delete_password(keychain_service, &account);
+            }
+            let (s, g, d) = timed?;
+            set.push(s);
+            get.push(g);
+            delete.push(d);
+        }
+        Ok(KeychainBench {
+            backend: keychain.backend(),
+            service: keychain_service.clone(),
+            iterations,
+            set: LatencyStats::from_samples(&mut set),
+            get: LatencyStats::from_samples(&mut get),
+            delete: LatencyStats::from_samples(&mut delete),
+        })
+    }
+}
+
+#[cfg(not(feature = "vault"))]
+impl Vault {
+    /// Without the `vault` feature every vault is in memory, so there is no keychain to time.
+    pub fn bench_keychain(&self, _iterations: usize) -> anyhow::Result<KeychainBench> {
+        anyhow::bail!("keychain benchmarks require a build with the `vault` feature")
+    }
+}
+
+#[cfg(feature = "vault")]
+fn time_round_trip(
+    keychain: &dyn KeychainStore,
+    service: &str,
+    account: &str,
+) -> anyhow::Result<(Duration, Duration, Duration)> {
+    // Same size as a generated HMAC key, so backends that encrypt per entry do realistic work.
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    let secret = URL_SAFE_NO_PAD.encode(bytes);
+
+    let started = Instant::now();
+    keychain.set_password(service, account, &secret)?;
+    let set = started.elapsed();
+
+    let started = Instant::now();
+    let read = keychain.get_password(service, account)?;
+    let get = started.elapsed();
+    if read != secret {
+        anyhow::bail!("keychain returned a different secret than was stored");
+    }
+
+    let started = Instant::now();
+    keychain.delete_password(service, account)?;
+    Ok((set, get, started.elapsed()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::percentile;
+    #[cfg(feature = "vault")]
+    use crate::vault::{MemoryKeychain, Vault, VaultConfig};
+    #[cfg(feature = "vault")]
+    use std::sync::Arc;
+    use std::time::Duration;
+    #[cfg(feature = "vault")]
+    use tempfile::TempDir;
+
+    #[test]
+    fn percentile_uses_nearest_rank() {
+        let samples: Vec<Duration> = (1..=10).map(Duration::from_millis).collect();
+        assert_eq!(percentile(&samples, 50), Duration::from_millis(5));
+        assert_eq!(percentile(&samples, 90), Duration::from_millis(9));
+        assert_eq!(percentile(&samples, 99), Duration::from_millis(10));
+        assert_eq!(percentile(&samples[..1], 50), Duration::from_millis(1));
+    }
+
+    #[cfg(feature = "vault")]
+    #[test]
+    fn bench_keychain_cleans_up_its_entries() {
+        let dir = TempDir::new().unwrap();
+        let keychain = Arc::new(MemoryKeychain::new());
+        let vault = Vault::open_with(
+            VaultConfig {
+                no_persist: false,
+                data_dir: Some(dir.path().to_path_buf()),
+            },
+            keychain.clone(),
+            "bench-test".to_string(),
+        )
+        .unwrap();
+
+        let bench = vault.bench_keychain(5).unwrap();
+        assert_eq!((bench.backend, bench.iterations), ("memory", 5));
+        assert!(bench.set.min_ms <= bench.set.p50_ms && bench.set.p50_ms <= bench.set.max_ms);
+        assert_eq!(keychain.len(), 0);
+
+        keychain.fail_deletes(1);
+        assert!(vault.bench_keychain(1).is_err());
+        assert_eq!(keychain.len(), 0);
+    }
+}
@@ -1,47 +1,189 @@
-use super::helpers::serialize_tags;
+use super::helpers::{key_fingerprint, serialize_policy, serialize_tags};
+use super::jwks::document_key_info;
 use super::snapshot::validate_snapshot;
+#[cfg(feature = "vault")]
+use super::sqlite::open_db;
 use super::store::{Vault, VaultInner};
+use super::types::{ExportReport, JwksEntry, KeyEntry, ProjectEntry, TokenEntry};
 use crate::vault_export;
-use rusqlite::{params, Connection};
+#[cfg(feature = "vault")]
+use rusqlite::params;
+use serde_json::json;
 
 impl Vault {
+    #[cfg(test)]
     pub fn export_bundle(&self, passphrase: &str) -> anyhow::Result<vault_export::ExportBundle> {
+        let report = self.export_bundle_with(
+            &vault_export::BundleProtection::Passphrase(passphrase.to_string()),
+            false,
+        )?;
+        Ok(report.bundle)
+    }
+
+    /// Exports the whole vault. Keys of non-exportable projects are left out unless
+    /// `include_non_exportable` is set, in which case the export is recorded in the audit log.
+    pub fn export_bundle_with(
+        &self,
+        protection: &vault_export::BundleProtection,
+        include_non_exportable: bool,
+    ) -> anyhow::Result<ExportReport> {
+        let projects = self.list_projects()?;
+        self.export_projects(projects, None, protection, include_non_exportable)
+    }
+
+    /// Exports one project with its keys, tokens and JWKS; the bundle imports like any other.
+    pub fn export_project_bundle_with(
+        &self,
+        project_id: &str,
+        protection: &vault_export::BundleProtection,
+        include_non_exportable: bool,
+    ) -> anyhow::Result<ExportReport> {
+        let project = self
+            .find_project_by_id(project_id)?
+            .ok_or_else(|| anyhow::anyhow!("project not found: {project_id}"))?;
+        self.export_projects(
+            vec![project],
+            Some(project_id),
+            protection,
+            include_non_exportable,
+        )
+    }
+
+    /// Everything, non-exportable keys included: backups never leave the data directory.
+    pub(super) fn backup_bundle_with(
+        &self,
+        protection: &vault_export::BundleProtection,
+    ) -> anyhow::Result<vault_export::ExportBundle> {
         let projects = self.list_projects()?;
         let keys = self.list_keys(None)?;
         let tokens = self.list_tokens(None)?;
+        let jwks = self.list_jwks(None)?;
+        self.protect_entries(projects, keys, tokens, jwks, protection)
+    }
 
-        let mut key_exports = Vec::with_capacity(keys.len());
-        for key in keys {
-            let material = self.get_key_material(&key.id)?;
-            key_exports.push(vault_export::KeyExport {
-                entry: key,
-                material,
+    fn export_projects(
+        &self,
+        mut projects: Vec<ProjectEntry>,
+        project_id: Option<&str>,
+        protection: &vault_export::BundleProtection,
+        include_non_exportable: bool,
+    ) -> anyhow::Result<ExportReport> {
+        let (non_exportable_keys, mut keys): (Vec<KeyEntry>, Vec<KeyEntry>) =
+            self.list_keys(project_id)?.into_iter().partition(|key| {
+                projects
+                    .iter()
+                    .any(|p| p.id == key.project_id && !p.exportable)
             });
+        if include_non_exportable {
+            if !non_exportable_keys.is_empty() {
+                self.record_audit(
+                    "export.non_exportable",
+                    project_id,
+                    json!({
+                        "keys": non_exportable_keys
+                            .iter()
+                            .map(|k| json!({ "id": k.id, "name": k.name, "project_id": k.project_id }))
+                            .collect::<Vec<_>>(),
+                    }),
+                )?;
+            }
+            keys.extend(non_exportable_keys.iter().cloned());
+        } else {
+            // The bundle must not point at a default key it does not carry.
+            for project in &mut projects {
+                if non_exportable_keys
+                    .iter()
+                    .any(|k| project.default_key_id.as_deref() == Some(k.id.as_str()))
+                {
+                    project.default_key_id = None;
+                }
+            }
         }
+        let tokens = self.list_tokens(project_id)?;
+        let jwks = self.list_jwks(project_id)?;
+        let bundle = self.protect_entries(projects, keys, tokens, jwks, protection)?;
+        Ok(ExportReport {
+            bundle,
+            non_exportable_keys,
+        })
+    }
 
-        let mut token_exports = Vec::with_capacity(tokens.len());
-        for token in tokens {
-            let material = self.get_token_material(&token.id)?;
-            token_exports.push(vault_export::TokenExport {
-                entry: token,
-                token: material,
-            });
+    fn protect_entries(
+        &self,
+        projects: Vec<ProjectEntry>,
+        keys: Vec<KeyEntry>,
+        tokens: Vec<TokenEntry>,
+        jwks: Vec<JwksEntry>,
+        protection: &vault_export::BundleProtection,
+    ) -> anyhow::Result<vault_export::ExportBundle> {
+        let key_ids: Vec<&str> = keys.iter().map(|k| k.id.as_str()).collect();
+        let key_materials = self.get_key_materials(&key_ids)?;
+        let key_exports = keys
+            .into_iter()
+            .zip(key_materials)
+            .map(|(entry, material)| vault_export::KeyExport { entry, material })
+            .collect();
+
+        let token_ids: Vec<&str> = tokens.iter().map(|t| t.id.as_str()).collect();
+        let token_materials = self.get_token_materials(&token_ids)?;
+        let token_exports = tokens
+            .into_iter()
+            .zip(token_materials)
+            .map(|(entry, token)| vault_export::TokenExport { entry, token })
+            .collect();
+
+        let mut jwks_exports = Vec::with_capacity(jwks.len());
+        for entry in jwks {
+            let document = self.get_jwks_document(&entry.id)?;
+            jwks_exports.push(vault_export::JwksExport { entry, document });
         }
 
-        let snapshot = vault_export::build_snapshot(projects, key_exports, token_exports);
-        vault_export::encrypt_snapshot(&snapshot, passphrase)
+        let snapshot =
+            vault_export::build_snapshot(projects, key_exports, token_exports, jwks_exports);
+        vault_export::protect_snapshot(&snapshot, protection)
     }
 
+    #[cfg(any(feature = "ui", test))]
     pub fn import_bundle(
         &self,
         bundle: &vault_export::ExportBundle,
         passphrase: &str,
         replace: bool,
     ) -> anyhow::Result<()> {
-        let snapshot = vault_export::decrypt_snapshot(bundle, passphrase)?;
+        self.import_bundle_with(
+            bundle,
+            &vault_export::BundleUnlock::Passphrase(passphrase.to_string()),
+            replace,
+        )
+    }
+
+    pub fn import_bundle_with(
+        &self,
+        bundle: &vault_export::ExportBundle,
+        unlock: &vault_export::BundleUnlock,
+        replace: bool,
+    ) -> anyhow::Result<()> {
+        self.import_bundle_with_reason(bundle, unlock, replace, "import-replace")
+    }
+
+    pub(super) fn import_bundle_with_reason(
+        &self,
+        bundle: &vault_export::ExportBundle,
+        unlock: &vault_export::BundleUnlock,
+        replace: bool,
+        backup_reason: &str,
+    ) -> anyhow::Result<()> {
+        let mut snapshot = vault_export::unlock_snapshot(bundle, unlock)?;
         validate_snapshot(&snapshot)?;
+        // Bundles written before fingerprints were recorded carry none.
+        for key in &mut snapshot.keys {
+            if key.entry.fingerprint.is_none() {
+                key.entry.fingerprint = key_fingerprint(&key.entry.kind, &key.material);
+            }
+        }
 
         if replace {
+            self.auto_backup(backup_reason)?;
             self.clear_all()?;
         } else if !self.is_empty()? {
             anyhow::bail!("vault is not empty; use --replace to overwrite");
@@ -63,24 +205,41 @@ impl Vault {
                     .iter()
                     .map(|t| (t.entry.id.clone(), t.token.clone()))
                     .collect();
+                locked.jwks = snapshot
+                    .jwks
+                    .iter()
+                    .map(|j| JwksEntry {
+                        keys: document_key_info(&j.document),
+                        ..j.entry.clone()
+                    })
+                    .collect();
+                locked.jwks_documents = snapshot
+                    .jwks
+                    .iter()
+                    .map(|j| (j.entry.id.clone(), j.document.clone()))
+                    .collect();
             }
+            #[cfg(feature = "vault")]
             VaultInner::Sqlite {
                 db_path,
                 keychain_service,
                 keychain,
             } => {
-                let conn = Connection::open(db_path)?;
+                let conn = open_db(db_path)?;
                 for project in &snapshot.projects {
                     let tags_json = serialize_tags(&project.tags);
                     conn.execute(
-                        "INSERT INTO projects (id, name, created_at, default_key_id, description, tags) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                        "INSERT INTO projects (id, name, created_at, default_key_id, description, tags, policy, exportable, wasm_module) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
                         params![
                             project.id,
                             project.name,
                             project.created_at,
                             project.default_key_id,
                             project.description,
-                            tags_json
+                            tags_json,
+                            serialize_policy(&project.policy),
+                            project.exportable,
+                            project.wasm_module
                         ],
                     )?;
                 }
@@ -91,7 +250,7 @@ impl Vault {
 
                     let tags_json = serialize_tags(&key.entry.tags);
                     let insert = conn.execute(
-                        "INSERT INTO keys (id, project_id, name, kind, created_at, kid, description, tags, keychain_service, keychain_account) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                        "INSERT INTO keys (id, project_id, name, kind, created_at, kid, description, tags, keychain_service, keychain_account, fingerprint, usage) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
                         params![
                             key.entry.id,
                             key.entry.project_id,
@@ -102,7 +261,9 @@ impl Vault {
                             key.entry.description,
                             tags_json,
                             keychain_service,
-                            account
+                            account,
+                            key.entry.fingerprint,
+                            key.entry.usage.as_str()
                         ],
                     );
                     if let Err(err) = insert {
@@ -131,16 +292,32 @@ impl Vault {
                         return Err(err.into());
                     }
                 }
+
+                for jwks in &snapshot.jwks {
+                    conn.execute(
+                        "INSERT INTO jwks (id, project_id, name, created_at, kids, document, source_url) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                        params![
+                            jwks.entry.id,
+                            jwks.entry.project_id,
+                            jwks.entry.name,
+                            jwks.entry.created_at,
+                            serde_json::to_string(&jwks.entry.kids)?,
+                            jwks.document,
+                            jwks.entry.source_url
+                        ],
+                    )?;
+                }
             }
         }
 
         Ok(())
     }
 
-    fn is_empty(&self) -> anyhow::Result<bool> {
+    pub(super) fn is_empty(&self) -> anyhow::Result<bool> {
         Ok(self.list_projects()?.is_empty()
             && self.list_keys(None)?.is_empty()
-            && self.list_tokens(None)?.is_empty())
+            && self.list_tokens(None)?.is_empty()
+            && self.list_jwks(None)?.is_empty())
     }
 
     fn clear_all(&self) -> anyhow::Result<()> {
@@ -152,11 +329,14 @@ impl Vault {
                 locked.tokens.clear();
                 locked.key_material.clear();
                 locked.token_material.clear();
+                locked.jwks.clear();
+                locked.jwks_documents.clear();
             }
+            #[cfg(feature = "vault")]
             VaultInner::Sqlite { .. } => {
                 let projects = self.list_projects()?;
                 for p in projects {
-                    self.delete_project(&p.id)?;
+                    self.delete_project_entries(&p.id)?;
                 }
             }
         }
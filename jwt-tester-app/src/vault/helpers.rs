@@ -1,4 +1,8 @@
+use super::types::FamilyPolicy;
 use directories::ProjectDirs;
+#[cfg(feature = "vault")]
+use rusqlite::{params, Connection};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -40,6 +44,34 @@ pub(super) fn parse_tags(raw: Option<String>) -> Vec<String> {
         .unwrap_or_default()
 }
 
+pub(super) fn serialize_policy(policy: &BTreeMap<String, FamilyPolicy>) -> Option<String> {
+    if policy.is_empty() {
+        None
+    } else {
+        serde_json::to_string(policy).ok()
+    }
+}
+
+pub(super) fn parse_policy(raw: Option<String>) -> BTreeMap<String, FamilyPolicy> {
+    raw.and_then(|val| serde_json::from_str(&val).ok())
+        .unwrap_or_default()
+}
+
+/// Fingerprint stored on asymmetric keys. Material the keygen module cannot parse (or HMAC
+/// secrets) gets none.
+#[cfg(feature = "keygen")]
+pub(super) fn key_fingerprint(kind: &str, material: &str) -> Option<String> {
+    if kind.eq_ignore_ascii_case("hmac") {
+        return None;
+    }
+    crate::keygen::public_key_fingerprint(material)
+}
+
+#[cfg(not(feature = "keygen"))]
+pub(super) fn key_fingerprint(_kind: &str, _material: &str) -> Option<String> {
+    None
+}
+
 pub(super) fn default_data_dir() -> Option<PathBuf> {
     ProjectDirs::from("dev", "jwt-tester", "jwt-tester").map(|d| d.data_dir().to_path_buf())
 }
@@ -51,6 +83,25 @@ pub(super) fn now_unix() -> i64 {
         .as_secs() as i64
 }
 
+#[cfg(feature = "vault")]
+/// Keychain `(service, account)` pairs for `ids` in `table` (`keys` or `tokens`), read over one
+/// connection. An unknown id is an error, like the single-entry lookups.
+pub(super) fn keychain_accounts(
+    conn: &Connection,
+    table: &'static str,
+    ids: &[&str],
+) -> anyhow::Result<Vec<(String, String)>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT keychain_service, keychain_account FROM {table} WHERE id = ?1"
+    ))?;
+    ids.iter()
+        .map(|id| {
+            stmt.query_row(params![id], |row| Ok((row.get(0)?, row.get(1)?)))
+                .map_err(|err| anyhow::anyhow!("{table} entry {id}: {err}"))
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::{normalize_opt_string, normalize_tags, parse_tags, serialize_tags};
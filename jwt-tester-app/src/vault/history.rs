@@ -0,0 +1,118 @@
+use super::helpers::now_unix;
+#[cfg(feature = "vault")]
+use super::sqlite::open_db;
+use super::store::{Vault, VaultInner};
+#[cfg(feature = "vault")]
+use rusqlite::params;
+use serde::Serialize;
+use uuid::Uuid;
+
+/// One recorded encode/verify operation. `params` never contains key material.
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryEntry {
+    pub id: String,
+    pub created_at: i64,
+    pub operation: String,
+    pub ok: bool,
+    pub params: serde_json::Value,
+    pub result: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct HistoryInput {
+    pub operation: String,
+    pub ok: bool,
+    pub params: serde_json::Value,
+    pub result: String,
+}
+
+impl Vault {
+    /// Appends an entry and drops everything beyond the newest `keep` entries.
+    pub fn record_history(&self, input: HistoryInput, keep: usize) -> anyhow::Result<HistoryEntry> {
+        let entry = HistoryEntry {
+            id: Uuid::new_v4().to_string(),
+            created_at: now_unix(),
+            operation: input.operation,
+            ok: input.ok,
+            params: input.params,
+            result: input.result,
+        };
+
+        match &self.inner {
+            VaultInner::Memory { state } => {
+                let mut locked = state.lock().unwrap();
+                locked.history.insert(0, entry.clone());
+                locked.history.truncate(keep);
+            }
+            #[cfg(feature = "vault")]
+            VaultInner::Sqlite { db_path, .. } => {
+                let conn = open_db(db_path)?;
+                conn.execute(
+                    "INSERT INTO history (id, created_at, operation, ok, params, result) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![
+                        entry.id,
+                        entry.created_at,
+                        entry.operation,
+                        entry.ok,
+                        entry.params.to_string(),
+                        entry.result
+                    ],
+                )?;
+                conn.execute(
+                    "DELETE FROM history WHERE rowid NOT IN (SELECT rowid FROM history ORDER BY created_at DESC, rowid DESC LIMIT ?1)",
+                    params![keep as i64],
+                )?;
+            }
+        }
+        Ok(entry)
+    }
+
+    /// Lists history entries, newest first.
+    pub fn list_history(&self, limit: Option<usize>) -> anyhow::Result<Vec<HistoryEntry>> {
+        let limit = limit.unwrap_or(usize::MAX);
+        match &self.inner {
+            VaultInner::Memory { state } => {
+                let locked = state.lock().unwrap();
+                Ok(locked.history.iter().take(limit).cloned().collect())
+            }
+            #[cfg(feature = "vault")]
+            VaultInner::Sqlite { db_path, .. } => {
+                let conn = open_db(db_path)?;
+                let mut stmt = conn.prepare(
+                    "SELECT id, created_at, operation, ok, params, result FROM history ORDER BY created_at DESC, rowid DESC LIMIT ?1",
+                )?;
+                let rows =
+                    stmt.query_map(params![i64::try_from(limit).unwrap_or(i64::MAX)], |row| {
+                        let params: String = row.get(4)?;
+                        Ok(HistoryEntry {
+                            id: row.get(0)?,
+                            created_at: row.get(1)?,
+                            operation: row.get(2)?,
+                            ok: row.get(3)?,
+                            params: serde_json::from_str(&params)
+                                .unwrap_or(serde_json::Value::Null),
+                            result: row.get(5)?,
+                        })
+                    })?;
+                Ok(rows.collect::<Result<Vec<_>, _>>()?)
+            }
+        }
+    }
+
+    /// Removes all history entries; returns how many were deleted.
+    pub fn clear_history(&self) -> anyhow::Result<usize> {
+        match &self.inner {
+            VaultInner::Memory { state } => {
+                let mut locked = state.lock().unwrap();
+                let removed = locked.history.len();
+                locked.history.clear();
+                Ok(removed)
+            }
+            #[cfg(feature = "vault")]
+            VaultInner::Sqlite { db_path, .. } => {
+                let conn = open_db(db_path)?;
+                Ok(conn.execute("DELETE FROM history", [])?)
+            }
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use super::helpers::{normalize_opt_string, now_unix};
+#[cfg(feature = "vault")]
+use super::sqlite::open_db;
+use super::store::{Vault, VaultInner};
+use super::types::{JwkInfo, JwksEntry, JwksEntryInput};
+#[cfg(feature = "vault")]
+use rusqlite::{params, Row};
+use serde_json::Value;
+use uuid::Uuid;
+
+// JWKS documents only hold public keys, so they live in SQLite instead of the keychain.
+impl Vault {
+    pub fn list_jwks(&self, project_id: Option<&str>) -> anyhow::Result<Vec<JwksEntry>> {
+        match &self.inner {
+            VaultInner::Memory { state } => {
+                let locked = state.lock().unwrap();
+                let entries = locked.jwks.clone();
+                Ok(match project_id {
+                    Some(pid) => entries
+                        .into_iter()
+                        .filter(|j| j.project_id == pid)
+                        .collect(),
+                    None => entries,
+                })
+            }
+            #[cfg(feature = "vault")]
+            VaultInner::Sqlite { db_path, .. } => {
+                let conn = open_db(db_path)?;
+                let entries = if let Some(pid) = project_id {
+                    let mut stmt = conn.prepare(
+                        "SELECT id, project_id, name, created_at, kids, source_url, document FROM jwks WHERE project_id = ?1 ORDER BY created_at DESC",
+                    )?;
+                    let rows = stmt.query_map(params![pid], jwks_row)?;
+                    rows.collect::<Result<Vec<_>, _>>()?
+                } else {
+                    let mut stmt = conn.prepare(
+                        "SELECT id, project_id, name, created_at, kids, source_url, document FROM jwks ORDER BY created_at DESC",
+                    )?;
+                    let rows = stmt.query_map([], jwks_row)?;
+                    rows.collect::<Result<Vec<_>, _>>()?
+                };
+                Ok(entries)
+            }
+        }
+    }
+
+    pub fn add_jwks(&self, input: JwksEntryInput) -> anyhow::Result<JwksEntry> {
+        if input.project_id.trim().is_empty() {
+            anyhow::bail!("project_id is required");
+        }
+        let kids = document_kids(&input.document)?;
+        let keys = document_key_info(&input.document);
+
+        let id = Uuid::new_v4().to_string();
+        let name = {
+            let trimmed = input.name.trim();
+            if trimmed.is_empty() {
+                format!("jwks-{}", id.chars().take(8).collect::<String>())
+            } else {
+                trimmed.to_string()
+            }
+        };
+        let row = JwksEntry {
+            id,
+            project_id: input.project_id,
+            name,
+            created_at: now_unix(),
+            kids,
+            source_url: normalize_opt_string(input.source_url),
+            keys,
+        };
+
+        match &self.inner {
+            VaultInner::Memory { state } => {
+                let mut locked = state.lock().unwrap();
+                locked.jwks_documents.insert(row.id.clone(), input.document);
+                locked.jwks.push(row.clone());
+            }
+            #[cfg(feature = "vault")]
+            VaultInner::Sqlite { db_path, .. } => {
+                let conn = open_db(db_path)?;
+                conn.execute(
+                    "INSERT INTO jwks (id, project_id, name, created_at, kids, document, source_url) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                    params![
+                        row.id,
+                        row.project_id,
+                        row.name,
+                        row.created_at,
+                        serde_json::to_string(&row.kids)?,
+                        input.document,
+                        row.source_url
+                    ],
+                )?;
+            }
+        }
+
+        Ok(row)
+    }
+
+    pub fn get_jwks_document(&self, jwks_id: &str) -> anyhow::Result<String> {
+        match &self.inner {
+            VaultInner::Memory { state } => state
+                .lock()
+                .unwrap()
+                .jwks_documents
+                .get(jwks_id)
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("JWKS document not found")),
+            #[cfg(feature = "vault")]
+            VaultInner::Sqlite { db_path, .. } => {
+                let conn = open_db(db_path)?;
+                let mut stmt = conn.prepare("SELECT document FROM jwks WHERE id = ?1")?;
+                Ok(stmt.query_row(params![jwks_id], |row| row.get(0))?)
+            }
+        }
+    }
+
+    /// Replaces a stored document (e.g. after re-fetching its source URL) and its kid list.
+    pub fn update_jwks_document(&self, jwks_id: &str, document: &str) -> anyhow::Result<JwksEntry> {
+        let kids = document_kids(document)?;
+        let keys = document_key_info(document);
+        match &self.inner {
+            VaultInner::Memory { state } => {
+                let mut locked = state.lock().unwrap();
+                let entry = locked
+                    .jwks
+                    .iter_mut()
+                    .find(|j| j.id == jwks_id)
+                    .ok_or_else(|| anyhow::anyhow!("JWKS not found: {jwks_id}"))?;
+                entry.kids = kids;
+                entry.keys = keys;
+                let updated = entry.clone();
+                locked
+                    .jwks_documents
+                    .insert(jwks_id.to_string(), document.to_string());
+                Ok(updated)
+            }
+            #[cfg(feature = "vault")]
+            VaultInner::Sqlite { db_path, .. } => {
+                let conn = open_db(db_path)?;
+                let changed = conn.execute(
+                    "UPDATE jwks SET document = ?1, kids = ?2 WHERE id = ?3",
+                    params![document, serde_json::to_string(&kids)?, jwks_id],
+                )?;
+                if changed == 0 {
+                    anyhow::bail!("JWKS not found: {jwks_id}");
+                }
+                let mut stmt = conn.prepare(
+                    "SELECT id, project_id, name, created_at, kids, source_url, document FROM jwks WHERE id = ?1",
+                )?;
+                Ok(stmt.query_row(params![jwks_id], jwks_row)?)
+            }
+        }
+    }
+
+    pub fn delete_jwks(&self, jwks_id: &str) -> anyhow::Result<()> {
+        match &self.inner {
+            VaultInner::Memory { state } => {
+                let mut locked = state.lock().unwrap();
+                locked.jwks.retain(|j| j.id != jwks_id);
+                locked.jwks_documents.remove(jwks_id);
+                Ok(())
+            }
+            #[cfg(feature = "vault")]
+            VaultInner::Sqlite { db_path, .. } => {
+                let conn = open_db(db_path)?;
+                conn.execute("DELETE FROM jwks WHERE id = ?1", params![jwks_id])?;
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(feature = "vault")]
+fn jwks_row(row: &Row<'_>) -> rusqlite::Result<JwksEntry> {
+    let kids: Option<String> = row.get(4)?;
+    let document: String = row.get(6)?;
+    Ok(JwksEntry {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        name: row.get(2)?,
+        created_at: row.get(3)?,
+        kids: kids
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default(),
+        source_url: row.get(5)?,
+        keys: document_key_info(&document),
+    })
+}
+
+/// The `kid`s listed in a JWKS document, in document order.
+pub(super) fn document_kids(document: &str) -> anyhow::Result<Vec<String>> {
+    let parsed: Value = serde_json::from_str(document)
+        .map_err(|e| anyhow::anyhow!("JWKS is not valid JSON: {e}"))?;
+    let keys = parsed
+        .get("keys")
+        .and_then(Value::as_array)
+        .ok_or_else(|| anyhow::anyhow!("JWKS must be an object with a \"keys\" array"))?;
+    Ok(keys
+        .iter()
+        .filter_map(|key| key.get("kid").and_then(Value::as_str))
+        .map(str::to_string)
+        .collect())
+}
+
+/// The `use`, `key_ops` and `alg` of each key, read leniently so a key with odd parameters still
+/// shows up with whatever it does declare.
+pub(super) fn document_key_info(document: &str) -> Vec<JwkInfo> {
+    let parsed: Value = serde_json::from_str(document).unwrap_or_default();
+    let text = |key: &Value, name: &str| key.get(name).and_then(Value::as_str).map(str::to_string);
+    parsed
+        .get("keys")
+        .and_then(Value::as_array)
+        .map(|keys| {
+            keys.iter()
+                .map(|key| JwkInfo {
+                    kid: text(key, "kid"),
+                    kty: text(key, "kty"),
+                    key_use: text(key, "use"),
+                    key_ops: key.get("key_ops").and_then(Value::as_array).map(|ops| {
+                        ops.iter()
+                            .filter_map(Value::as_str)
+                            .map(str::to_string)
+                            .collect()
+                    }),
+                    alg: text(key, "alg"),
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
@@ -1,7 +1,15 @@
-use super::helpers::{normalize_opt_string, normalize_tags, now_unix, parse_tags, serialize_tags};
+#[cfg(feature = "vault")]
+use super::helpers::keychain_accounts;
+use super::helpers::{
+    key_fingerprint, normalize_opt_string, normalize_tags, now_unix, parse_tags, serialize_tags,
+};
+#[cfg(feature = "vault")]
+use super::sqlite::open_db;
 use super::store::{Vault, VaultInner};
-use super::types::{KeyEntry, KeyEntryInput};
-use rusqlite::{params, Connection};
+use super::types::{KeyEntry, KeyEntryInput, KeyUsage};
+#[cfg(feature = "vault")]
+use rusqlite::{params, OptionalExtension, Row};
+use serde_json::json;
 use uuid::Uuid;
 
 impl Vault {
@@ -15,43 +23,20 @@ impl Vault {
                     None => keys,
                 })
             }
+            #[cfg(feature = "vault")]
             VaultInner::Sqlite { db_path, .. } => {
-                let conn = Connection::open(db_path)?;
+                let conn = open_db(db_path)?;
                 let keys = if let Some(pid) = project_id {
                     let mut stmt = conn.prepare(
-                        "SELECT id, project_id, name, kind, created_at, kid, description, tags FROM keys WHERE project_id = ?1 ORDER BY created_at DESC",
+                        "SELECT id, project_id, name, kind, created_at, kid, description, tags, fingerprint, usage FROM keys WHERE project_id = ?1 ORDER BY created_at DESC",
                     )?;
-                    let rows = stmt.query_map(params![pid], |row| {
-                        let tags = parse_tags(row.get(7)?);
-                        Ok(KeyEntry {
-                            id: row.get(0)?,
-                            project_id: row.get(1)?,
-                            name: row.get(2)?,
-                            kind: row.get(3)?,
-                            created_at: row.get(4)?,
-                            kid: row.get(5)?,
-                            description: row.get(6)?,
-                            tags,
-                        })
-                    })?;
+                    let rows = stmt.query_map(params![pid], key_row)?;
                     rows.collect::<Result<Vec<_>, _>>()?
                 } else {
                     let mut stmt = conn.prepare(
-                        "SELECT id, project_id, name, kind, created_at, kid, description, tags FROM keys ORDER BY created_at DESC",
+                        "SELECT id, project_id, name, kind, created_at, kid, description, tags, fingerprint, usage FROM keys ORDER BY created_at DESC",
                     )?;
-                    let rows = stmt.query_map([], |row| {
-                        let tags = parse_tags(row.get(7)?);
-                        Ok(KeyEntry {
-                            id: row.get(0)?,
-                            project_id: row.get(1)?,
-                            name: row.get(2)?,
-                            kind: row.get(3)?,
-                            created_at: row.get(4)?,
-                            kid: row.get(5)?,
-                            description: row.get(6)?,
-                            tags,
-                        })
-                    })?;
+                    let rows = stmt.query_map([], key_row)?;
                     rows.collect::<Result<Vec<_>, _>>()?
                 };
                 Ok(keys)
@@ -88,11 +73,13 @@ impl Vault {
             id: id.clone(),
             project_id: input.project_id,
             name,
-            kind: input.kind,
+            kind: input.kind.clone(),
             created_at,
             kid,
             description,
             tags,
+            fingerprint: key_fingerprint(&input.kind, &input.secret),
+            usage: KeyUsage::Both,
         };
 
         match &self.inner {
@@ -101,6 +88,7 @@ impl Vault {
                 locked.key_material.insert(row.id.clone(), input.secret);
                 locked.keys.push(row.clone());
             }
+            #[cfg(feature = "vault")]
             VaultInner::Sqlite {
                 db_path,
                 keychain_service,
@@ -109,9 +97,9 @@ impl Vault {
                 let account = format!("key:{id}");
                 keychain.set_password(keychain_service, &account, &input.secret)?;
 
-                let conn = Connection::open(db_path)?;
+                let conn = open_db(db_path)?;
                 conn.execute(
-                    "INSERT INTO keys (id, project_id, name, kind, created_at, kid, description, tags, keychain_service, keychain_account) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                    "INSERT INTO keys (id, project_id, name, kind, created_at, kid, description, tags, keychain_service, keychain_account, fingerprint) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
                     params![
                         row.id,
                         row.project_id,
@@ -122,15 +110,66 @@ impl Vault {
                         row.description,
                         tags_json,
                         keychain_service,
-                        account
+                        account,
+                        row.fingerprint
                     ],
                 )?;
             }
         }
 
+        self.emit_webhook_event(&row.project_id, "key.added", json!({ "key": row }));
         Ok(row)
     }
 
+    /// Restricts what `key_resolver` may select the key for.
+    pub fn set_key_usage(&self, key_id: &str, usage: KeyUsage) -> anyhow::Result<KeyEntry> {
+        match &self.inner {
+            VaultInner::Memory { state } => {
+                let mut locked = state.lock().unwrap();
+                let key = locked
+                    .keys
+                    .iter_mut()
+                    .find(|k| k.id == key_id)
+                    .ok_or_else(|| anyhow::anyhow!("key not found: {key_id}"))?;
+                key.usage = usage;
+                Ok(key.clone())
+            }
+            #[cfg(feature = "vault")]
+            VaultInner::Sqlite { db_path, .. } => {
+                let conn = open_db(db_path)?;
+                let updated = conn.execute(
+                    "UPDATE keys SET usage = ?1 WHERE id = ?2",
+                    params![usage.as_str(), key_id],
+                )?;
+                if updated == 0 {
+                    anyhow::bail!("key not found: {key_id}");
+                }
+                self.get_key(key_id)?
+                    .ok_or_else(|| anyhow::anyhow!("key not found: {key_id}"))
+            }
+        }
+    }
+
+    pub(super) fn get_key(&self, key_id: &str) -> anyhow::Result<Option<KeyEntry>> {
+        match &self.inner {
+            VaultInner::Memory { state } => Ok(state
+                .lock()
+                .unwrap()
+                .keys
+                .iter()
+                .find(|k| k.id == key_id)
+                .cloned()),
+            #[cfg(feature = "vault")]
+            VaultInner::Sqlite { db_path, .. } => {
+                let conn = open_db(db_path)?;
+                let mut stmt = conn.prepare(
+                    "SELECT id, project_id, name, kind, created_at, kid, description, tags, fingerprint, usage FROM keys WHERE id = ?1",
+                )?;
+                Ok(stmt.query_row(params![key_id], key_row).optional()?)
+            }
+        }
+    }
+
     pub fn find_key_in_project(
         &self,
         project_id: &str,
@@ -144,6 +183,16 @@ impl Vault {
         Ok(keys.into_iter().find(|k| k.name == key_name))
     }
 
+    /// The stored fingerprint, or one derived from the key material for keys stored before
+    /// fingerprints were recorded. `None` for HMAC keys.
+    pub fn key_fingerprint(&self, entry: &KeyEntry) -> anyhow::Result<Option<String>> {
+        if entry.fingerprint.is_some() || entry.kind.eq_ignore_ascii_case("hmac") {
+            return Ok(entry.fingerprint.clone());
+        }
+        let material = self.get_key_material(&entry.id)?;
+        Ok(key_fingerprint(&entry.kind, &material))
+    }
+
     pub fn get_key_material(&self, key_id: &str) -> anyhow::Result<String> {
         match &self.inner {
             VaultInner::Memory { state } => state
@@ -153,10 +202,11 @@ impl Vault {
                 .get(key_id)
                 .cloned()
                 .ok_or_else(|| anyhow::anyhow!("key material not found")),
+            #[cfg(feature = "vault")]
             VaultInner::Sqlite {
                 db_path, keychain, ..
             } => {
-                let conn = Connection::open(db_path)?;
+                let conn = open_db(db_path)?;
                 let mut stmt = conn
                     .prepare("SELECT keychain_service, keychain_account FROM keys WHERE id = ?1")?;
                 let (service, account): (String, String) =
@@ -166,7 +216,48 @@ impl Vault {
         }
     }
 
+    /// Material for several keys, in the order given, over one sqlite connection and one
+    /// batched keychain read. Used by export (and so by automatic backups).
+    pub fn get_key_materials(&self, key_ids: &[&str]) -> anyhow::Result<Vec<String>> {
+        match &self.inner {
+            VaultInner::Memory { state } => {
+                let locked = state.lock().unwrap();
+                key_ids
+                    .iter()
+                    .map(|id| {
+                        locked
+                            .key_material
+                            .get(*id)
+                            .cloned()
+                            .ok_or_else(|| anyhow::anyhow!("key material not found"))
+                    })
+                    .collect()
+            }
+            #[cfg(feature = "vault")]
+            VaultInner::Sqlite {
+                db_path, keychain, ..
+            } => {
+                let conn = open_db(db_path)?;
+                let accounts = keychain_accounts(&conn, "keys", key_ids)?;
+                keychain.get_passwords(&accounts)
+            }
+        }
+    }
+
+    /// Deletes several keys after one automatic backup.
+    pub fn delete_keys(&self, key_ids: &[&str]) -> anyhow::Result<()> {
+        if key_ids.is_empty() {
+            return Ok(());
+        }
+        self.auto_backup("key-bulk-delete")?;
+        for key_id in key_ids {
+            self.delete_key(key_id)?;
+        }
+        Ok(())
+    }
+
     pub fn delete_key(&self, key_id: &str) -> anyhow::Result<()> {
+        let deleted = self.get_key(key_id)?;
         match &self.inner {
             VaultInner::Memory { state } => {
                 let mut locked = state.lock().unwrap();
@@ -177,14 +268,14 @@ impl Vault {
                         p.default_key_id = None;
                     }
                 }
-                Ok(())
             }
+            #[cfg(feature = "vault")]
             VaultInner::Sqlite {
                 db_path,
                 keychain_service,
                 keychain,
             } => {
-                let conn = Connection::open(db_path)?;
+                let conn = open_db(db_path)?;
                 let mut stmt = conn.prepare("SELECT keychain_account FROM keys WHERE id = ?1")?;
                 let account: String = stmt.query_row(params![key_id], |row| row.get(0))?;
                 let _ = keychain.delete_password(keychain_service, &account);
@@ -194,8 +285,27 @@ impl Vault {
                     "UPDATE projects SET default_key_id = NULL WHERE default_key_id = ?1",
                     params![key_id],
                 )?;
-                Ok(())
             }
         }
+        if let Some(key) = deleted {
+            self.emit_webhook_event(&key.project_id, "key.deleted", json!({ "key": key }));
+        }
+        Ok(())
     }
 }
+
+#[cfg(feature = "vault")]
+fn key_row(row: &Row<'_>) -> rusqlite::Result<KeyEntry> {
+    Ok(KeyEntry {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        name: row.get(2)?,
+        kind: row.get(3)?,
+        created_at: row.get(4)?,
+        kid: row.get(5)?,
+        description: row.get(6)?,
+        tags: parse_tags(row.get(7)?),
+        fingerprint: row.get(8)?,
+        usage: KeyUsage::parse(&row.get::<_, String>(9)?).unwrap_or_default(),
+    })
+}
@@ -1,20 +1,46 @@
+#[cfg(feature = "vault")]
 use anyhow::Context;
+use std::path::Path;
 
 pub trait KeychainStore: Send + Sync {
+    /// Short backend name for reports (`os`, `file`).
+    fn backend(&self) -> &'static str;
+
+    /// Directory holding the entries, for backends that store them as files.
+    fn storage_dir(&self) -> Option<&Path> {
+        None
+    }
+
     fn set_password(&self, service: &str, account: &str, secret: &str) -> anyhow::Result<()>;
     fn get_password(&self, service: &str, account: &str) -> anyhow::Result<String>;
     fn delete_password(&self, service: &str, account: &str) -> anyhow::Result<()>;
+
+    /// Reads several `(service, account)` entries, in order. Backends that can share work
+    /// across entries override this; the default reads them one by one.
+    fn get_passwords(&self, entries: &[(String, String)]) -> anyhow::Result<Vec<String>> {
+        entries
+            .iter()
+            .map(|(service, account)| self.get_password(service, account))
+            .collect()
+    }
 }
 
+#[cfg(feature = "vault")]
 pub struct OsKeychain;
 
+#[cfg(feature = "vault")]
 impl OsKeychain {
     pub fn new() -> Self {
         Self
     }
 }
 
+#[cfg(feature = "vault")]
 impl KeychainStore for OsKeychain {
+    fn backend(&self) -> &'static str {
+        "os"
+    }
+
     fn set_password(&self, service: &str, account: &str, secret: &str) -> anyhow::Result<()> {
         let entry = keyring::Entry::new(service, account)
             .with_context(|| format!("open keychain entry for {service}:{account}"))?;
@@ -35,18 +61,23 @@ impl KeychainStore for OsKeychain {
     fn delete_password(&self, service: &str, account: &str) -> anyhow::Result<()> {
         let entry = keyring::Entry::new(service, account)
             .with_context(|| format!("open keychain entry for {service}:{account}"))?;
-        let _ = entry.delete_credential();
-        Ok(())
+        match entry.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(err) => {
+                Err(err).with_context(|| format!("delete keychain entry for {service}:{account}"))
+            }
+        }
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "vault"))]
 #[derive(Default)]
 pub(crate) struct MemoryKeychain {
     store: std::sync::Mutex<std::collections::HashMap<String, String>>,
+    failing_deletes: std::sync::atomic::AtomicUsize,
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "vault"))]
 impl MemoryKeychain {
     pub(crate) fn new() -> Self {
         Self::default()
@@ -56,13 +87,23 @@ impl MemoryKeychain {
         self.store.lock().unwrap().len()
     }
 
+    /// Makes the next `count` deletes fail, to simulate a locked or unavailable keychain.
+    pub(crate) fn fail_deletes(&self, count: usize) {
+        self.failing_deletes
+            .store(count, std::sync::atomic::Ordering::SeqCst);
+    }
+
     fn key(service: &str, account: &str) -> String {
         format!("{service}:{account}")
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "vault"))]
 impl KeychainStore for MemoryKeychain {
+    fn backend(&self) -> &'static str {
+        "memory"
+    }
+
     fn set_password(&self, service: &str, account: &str, secret: &str) -> anyhow::Result<()> {
         let mut locked = self.store.lock().unwrap();
         locked.insert(Self::key(service, account), secret.to_string());
@@ -78,6 +119,13 @@ impl KeychainStore for MemoryKeychain {
     }
 
     fn delete_password(&self, service: &str, account: &str) -> anyhow::Result<()> {
+        use std::sync::atomic::Ordering;
+        let failing = self
+            .failing_deletes
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1));
+        if failing.is_ok() {
+            anyhow::bail!("keychain is locked");
+        }
         let mut locked = self.store.lock().unwrap();
         locked.remove(&Self::key(service, account));
         Ok(())
@@ -17,6 +17,7 @@ const CIPHER_NAME: &str = "xchacha20poly1305";
 const KDF_MEM_KIB: u32 = 65_536;
 const KDF_ITERATIONS: u32 = 3;
 const KDF_PARALLELISM: u32 = 1;
+const MAX_PARALLEL_DECRYPTS: usize = 4;
 
 #[derive(Debug, Serialize, Deserialize)]
 struct KdfParams {
@@ -79,6 +80,14 @@ impl FileKeychain {
 }
 
 impl KeychainStore for FileKeychain {
+    fn backend(&self) -> &'static str {
+        "file"
+    }
+
+    fn storage_dir(&self) -> Option<&Path> {
+        Some(&self.root)
+    }
+
     fn set_password(&self, service: &str, account: &str, secret: &str) -> anyhow::Result<()> {
         let path = self.entry_path(service, account);
         let entry = encrypt_secret(&self.passphrase, secret)?;
@@ -103,6 +112,43 @@ impl KeychainStore for FileKeychain {
             Err(err) => Err(err).with_context(|| format!("delete keychain entry {:?}", path)),
         }
     }
+
+    /// Every entry has its own salt, so the Argon2 derivation cannot be shared; decrypting on a
+    /// few threads is what makes large exports fast. Capped because each derivation holds
+    /// `KDF_MEM_KIB` of memory.
+    fn get_passwords(&self, entries: &[(String, String)]) -> anyhow::Result<Vec<String>> {
+        if entries.len() < 2 {
+            return entries
+                .iter()
+                .map(|(service, account)| self.get_password(service, account))
+                .collect();
+        }
+        let threads = std::thread::available_parallelism()
+            .map_or(1, |n| n.get())
+            .clamp(1, MAX_PARALLEL_DECRYPTS);
+        let chunk_len = entries.len().div_ceil(threads);
+        std::thread::scope(|scope| {
+            let workers: Vec<_> = entries
+                .chunks(chunk_len)
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        chunk
+                            .iter()
+                            .map(|(service, account)| self.get_password(service, account))
+                            .collect::<anyhow::Result<Vec<_>>>()
+                    })
+                })
+                .collect();
+            let mut secrets = Vec::with_capacity(entries.len());
+            for worker in workers {
+                let chunk = worker
+                    .join()
+                    .map_err(|_| anyhow::anyhow!("keychain worker panicked"))??;
+                secrets.extend(chunk);
+            }
+            Ok(secrets)
+        })
+    }
 }
 
 fn encrypt_secret(passphrase: &str, secret: &str) -> anyhow::Result<EncryptedEntry> {
@@ -209,6 +255,30 @@ mod tests {
         assert!(keychain.get_password("svc", "acct").is_err());
     }
 
+    #[test]
+    fn file_keychain_bulk_read_keeps_order() {
+        let dir = TempDir::new().expect("temp dir");
+        let keychain =
+            FileKeychain::new(dir.path().join("kc"), "passphrase".to_string()).expect("keychain");
+        let entries: Vec<(String, String)> = (0..5)
+            .map(|i| ("svc".to_string(), format!("acct-{i}")))
+            .collect();
+        for (i, (service, account)) in entries.iter().enumerate() {
+            keychain
+                .set_password(service, account, &format!("secret-{i}"))
+                .expect("set");
+        }
+        let secrets = keychain.get_passwords(&entries).expect("bulk get");
+        assert_eq!(
+            secrets,
+            (0..5).map(|i| format!("secret-{i}")).collect::<Vec<_>>()
+        );
+
+        let mut with_missing = entries.clone();
+        with_missing.push(("svc".to_string(), "missing".to_string()));
+        assert!(keychain.get_passwords(&with_missing).is_err());
+    }
+
     #[test]
     fn file_keychain_rejects_wrong_passphrase() {
         let dir = TempDir::new().expect("temp dir");
@@ -0,0 +1,698 @@
+use super::helpers::{key_fingerprint, now_unix};
+#[cfg(feature = "vault")]
+use super::helpers::{serialize_policy, serialize_tags};
+use super::jwks::{document_key_info, document_kids};
+use super::snapshot::validate_snapshot;
+#[cfg(feature = "vault")]
+use super::sqlite::open_db;
+use super::store::{Vault, VaultInner};
+use super::types::{JwksEntry, KeyEntry, ProjectEntry, TokenEntry};
+use crate::vault_export;
+#[cfg(feature = "vault")]
+use rusqlite::params;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+/// What to do when an imported key/token collides with an existing one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictStrategy {
+    Skip,
+    Overwrite,
+    Rename,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeActionKind {
+    Add,
+    Merge,
+    Skip,
+    Overwrite,
+    Rename,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MergeAction {
+    pub entity: &'static str,
+    pub project: String,
+    pub name: String,
+    pub action: MergeActionKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub renamed_to: Option<String>,
+    /// Set when the key's kid was already taken in the target project and got a suffix.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub renamed_kid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conflict: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MergeSummary {
+    pub added: usize,
+    pub skipped: usize,
+    pub overwritten: usize,
+    pub renamed: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MergeReport {
+    pub dry_run: bool,
+    pub summary: MergeSummary,
+    pub actions: Vec<MergeAction>,
+}
+
+impl MergeReport {
+    fn push(&mut self, action: MergeAction) {
+        match action.action {
+            MergeActionKind::Add => self.summary.added += 1,
+            MergeActionKind::Skip => self.summary.skipped += 1,
+            MergeActionKind::Overwrite => self.summary.overwritten += 1,
+            MergeActionKind::Rename => self.summary.renamed += 1,
+            MergeActionKind::Merge => {}
+        }
+        self.actions.push(action);
+    }
+}
+
+impl Vault {
+    /// Folds a bundle into the existing vault: projects match by name, keys by kid
+    /// (falling back to name) and tokens and JWKS documents by name within the matched project.
+    /// All changes are saved together, so a merge that fails part-way changes nothing.
+    pub fn merge_bundle(
+        &self,
+        bundle: &vault_export::ExportBundle,
+        unlock: &vault_export::BundleUnlock,
+        strategy: ConflictStrategy,
+        dry_run: bool,
+    ) -> anyhow::Result<MergeReport> {
+        let snapshot = vault_export::unlock_snapshot(bundle, unlock)?;
+        validate_snapshot(&snapshot)?;
+        if strategy == ConflictStrategy::Overwrite && !dry_run {
+            self.auto_backup("import-merge")?;
+        }
+        self.merge_snapshot(&snapshot, strategy, dry_run)
+    }
+
+    fn merge_snapshot(
+        &self,
+        snapshot: &vault_export::VaultSnapshot,
+        strategy: ConflictStrategy,
+        dry_run: bool,
+    ) -> anyhow::Result<MergeReport> {
+        let mut report = MergeReport {
+            dry_run,
+            summary: MergeSummary::default(),
+            actions: Vec::new(),
+        };
+
+        let mut plan = MergePlan::default();
+        for project in &snapshot.projects {
+            let existing = self.find_project_by_name(&project.name)?;
+            self.plan_project(
+                snapshot,
+                project,
+                existing,
+                strategy,
+                &mut report,
+                &mut plan,
+            )?;
+        }
+        if !dry_run {
+            self.apply_merge(plan)?;
+        }
+        Ok(report)
+    }
+
+    /// Merges a single-project bundle into an existing project, regardless of the
+    /// project name recorded in the bundle.
+    #[cfg(any(feature = "ui", test))]
+    pub fn merge_bundle_into_project(
+        &self,
+        bundle: &vault_export::ExportBundle,
+        unlock: &vault_export::BundleUnlock,
+        project_id: &str,
+        strategy: ConflictStrategy,
+        dry_run: bool,
+    ) -> anyhow::Result<MergeReport> {
+        let target = self
+            .find_project_by_id(project_id)?
+            .ok_or_else(|| anyhow::anyhow!("project not found: {project_id}"))?;
+        let snapshot = vault_export::unlock_snapshot(bundle, unlock)?;
+        validate_snapshot(&snapshot)?;
+        let [source] = snapshot.projects.as_slice() else {
+            anyhow::bail!(
+                "bundle contains {} projects; project import expects exactly one",
+                snapshot.projects.len()
+            );
+        };
+        if strategy == ConflictStrategy::Overwrite && !dry_run {
+            self.auto_backup("import-merge")?;
+        }
+
+        let mut report = MergeReport {
+            dry_run,
+            summary: MergeSummary::default(),
+            actions: Vec::new(),
+        };
+        let mut plan = MergePlan::default();
+        self.plan_project(
+            &snapshot,
+            source,
+            Some(target),
+            strategy,
+            &mut report,
+            &mut plan,
+        )?;
+        if !dry_run {
+            self.apply_merge(plan)?;
+        }
+        Ok(report)
+    }
+
+    /// Decides what happens to one bundle project and its entries, recording the actions in
+    /// `report` and the writes in `plan`. Nothing is written yet.
+    fn plan_project(
+        &self,
+        snapshot: &vault_export::VaultSnapshot,
+        project: &ProjectEntry,
+        existing: Option<ProjectEntry>,
+        strategy: ConflictStrategy,
+        report: &mut MergeReport,
+        plan: &mut MergePlan,
+    ) -> anyhow::Result<()> {
+        let project_name = existing
+            .as_ref()
+            .map(|found| found.name.clone())
+            .unwrap_or_else(|| project.name.clone());
+        let (target_id, existing_keys, existing_tokens, existing_jwks) = match &existing {
+            Some(found) => {
+                report.push(MergeAction {
+                    entity: "project",
+                    project: project_name.clone(),
+                    name: project_name.clone(),
+                    action: MergeActionKind::Merge,
+                    renamed_to: None,
+                    renamed_kid: None,
+                    conflict: None,
+                });
+                (
+                    found.id.clone(),
+                    self.list_keys(Some(&found.id))?,
+                    self.list_tokens(Some(&found.id))?,
+                    self.list_jwks(Some(&found.id))?,
+                )
+            }
+            None => {
+                report.push(MergeAction {
+                    entity: "project",
+                    project: project_name.clone(),
+                    name: project.name.clone(),
+                    action: MergeActionKind::Add,
+                    renamed_to: None,
+                    renamed_kid: None,
+                    conflict: None,
+                });
+                let created = ProjectEntry {
+                    id: Uuid::new_v4().to_string(),
+                    name: project.name.clone(),
+                    created_at: now_unix(),
+                    default_key_id: None,
+                    description: project.description.clone(),
+                    tags: project.tags.clone(),
+                    policy: project.policy.clone(),
+                    exportable: project.exportable,
+                    wasm_module: project.wasm_module.clone(),
+                };
+                let id = created.id.clone();
+                plan.projects.push(created);
+                (id, Vec::new(), Vec::new(), Vec::new())
+            }
+        };
+
+        let mut default_key = existing.as_ref().and_then(|p| p.default_key_id.clone());
+        let mut key_names: HashSet<String> = existing_keys.iter().map(|k| k.name.clone()).collect();
+        let mut kids: HashSet<String> =
+            existing_keys.iter().filter_map(|k| k.kid.clone()).collect();
+        let mut imported_ids: HashMap<&str, String> = HashMap::new();
+
+        for key in snapshot
+            .keys
+            .iter()
+            .filter(|k| k.entry.project_id == project.id)
+        {
+            let conflict = find_key_conflict(&existing_keys, &key.entry);
+            let (action, name) = match (conflict, strategy) {
+                (None, _) => (MergeActionKind::Add, key.entry.name.clone()),
+                (Some(_), ConflictStrategy::Skip) => {
+                    (MergeActionKind::Skip, key.entry.name.clone())
+                }
+                (Some(_), ConflictStrategy::Overwrite) => {
+                    (MergeActionKind::Overwrite, key.entry.name.clone())
+                }
+                (Some(_), ConflictStrategy::Rename) => (
+                    MergeActionKind::Rename,
+                    unique_name(&key.entry.name, &key_names),
+                ),
+            };
+            key_names.insert(name.clone());
+            if let (MergeActionKind::Overwrite, Some((existing, _))) = (action, conflict) {
+                if let Some(kid) = &existing.kid {
+                    kids.remove(kid);
+                }
+            }
+            // Two keys sharing a kid would make kid-based selection ambiguous, so a key kept
+            // next to one with the same kid gets a suffixed kid too.
+            let kid = match (action, &key.entry.kid) {
+                (MergeActionKind::Skip, kid) => kid.clone(),
+                (_, Some(kid)) if kids.contains(kid) => Some(unique_name(kid, &kids)),
+                (_, kid) => kid.clone(),
+            };
+            if action != MergeActionKind::Skip {
+                kids.extend(kid.clone());
+            }
+            report.push(MergeAction {
+                entity: "key",
+                project: project_name.clone(),
+                name: key.entry.name.clone(),
+                action,
+                renamed_to: (action == MergeActionKind::Rename).then(|| name.clone()),
+                renamed_kid: kid
+                    .clone()
+                    .filter(|kid| key.entry.kid.as_ref() != Some(kid)),
+                conflict: conflict.map(|(existing, reason)| {
+                    format!("{reason} matches existing key {}", existing.id)
+                }),
+            });
+
+            if action == MergeActionKind::Skip {
+                continue;
+            }
+            let mut replaces_default = false;
+            if let (MergeActionKind::Overwrite, Some((existing, _))) = (action, conflict) {
+                plan.deleted_keys.push(existing.clone());
+                replaces_default = default_key.as_deref() == Some(existing.id.as_str());
+            }
+            let saved = KeyEntry {
+                id: Uuid::new_v4().to_string(),
+                project_id: target_id.clone(),
+                name,
+                kind: key.entry.kind.clone(),
+                created_at: now_unix(),
+                kid,
+                description: key.entry.description.clone(),
+                tags: key.entry.tags.clone(),
+                fingerprint: key_fingerprint(&key.entry.kind, &key.material),
+                usage: key.entry.usage,
+            };
+            if replaces_default {
+                plan.defaults
+                    .push((target_id.clone(), default_key.clone(), saved.id.clone()));
+                default_key = Some(saved.id.clone());
+            }
+            imported_ids.insert(key.entry.id.as_str(), saved.id.clone());
+            plan.keys.push((saved, key.material.clone()));
+        }
+
+        let mut token_names: HashSet<String> =
+            existing_tokens.iter().map(|t| t.name.clone()).collect();
+        for token in snapshot
+            .tokens
+            .iter()
+            .filter(|t| t.entry.project_id == project.id)
+        {
+            let conflict = find_token_conflict(&existing_tokens, &token.entry);
+            let (action, name) = match (conflict, strategy) {
+                (None, _) => (MergeActionKind::Add, token.entry.name.clone()),
+                (Some(_), ConflictStrategy::Skip) => {
+                    (MergeActionKind::Skip, token.entry.name.clone())
+                }
+                (Some(_), ConflictStrategy::Overwrite) => {
+                    (MergeActionKind::Overwrite, token.entry.name.clone())
+                }
+                (Some(_), ConflictStrategy::Rename) => (
+                    MergeActionKind::Rename,
+                    unique_name(&token.entry.name, &token_names),
+                ),
+            };
+            token_names.insert(name.clone());
+            report.push(MergeAction {
+                entity: "token",
+                project: project_name.clone(),
+                name: token.entry.name.clone(),
+                action,
+                renamed_to: (action == MergeActionKind::Rename).then(|| name.clone()),
+                renamed_kid: None,
+                conflict: conflict
+                    .map(|existing| format!("name matches existing token {}", existing.id)),
+            });
+
+            if action == MergeActionKind::Skip {
+                continue;
+            }
+            if let (MergeActionKind::Overwrite, Some(existing)) = (action, conflict) {
+                plan.deleted_tokens.push(existing.clone());
+            }
+            let saved = TokenEntry {
+                id: Uuid::new_v4().to_string(),
+                project_id: target_id.clone(),
+                name,
+                created_at: now_unix(),
+            };
+            plan.tokens.push((saved, token.token.clone()));
+        }
+
+        let mut jwks_names: HashSet<String> =
+            existing_jwks.iter().map(|j| j.name.clone()).collect();
+        for jwks in snapshot
+            .jwks
+            .iter()
+            .filter(|j| j.entry.project_id == project.id)
+        {
+            let conflict = find_jwks_conflict(&existing_jwks, &jwks.entry);
+            let (action, name) = match (conflict, strategy) {
+                (None, _) => (MergeActionKind::Add, jwks.entry.name.clone()),
+                (Some(_), ConflictStrategy::Skip) => {
+                    (MergeActionKind::Skip, jwks.entry.name.clone())
+                }
+                (Some(_), ConflictStrategy::Overwrite) => {
+                    (MergeActionKind::Overwrite, jwks.entry.name.clone())
+                }
+                (Some(_), ConflictStrategy::Rename) => (
+                    MergeActionKind::Rename,
+                    unique_name(&jwks.entry.name, &jwks_names),
+                ),
+            };
+            jwks_names.insert(name.clone());
+            report.push(MergeAction {
+                entity: "jwks",
+                project: project_name.clone(),
+                name: jwks.entry.name.clone(),
+                action,
+                renamed_to: (action == MergeActionKind::Rename).then(|| name.clone()),
+                renamed_kid: None,
+                conflict: conflict
+                    .map(|existing| format!("name matches existing jwks {}", existing.id)),
+            });
+
+            if action == MergeActionKind::Skip {
+                continue;
+            }
+            if let (MergeActionKind::Overwrite, Some(existing)) = (action, conflict) {
+                plan.deleted_jwks.push(existing.clone());
+            }
+            let saved = JwksEntry {
+                id: Uuid::new_v4().to_string(),
+                project_id: target_id.clone(),
+                name,
+                created_at: now_unix(),
+                kids: document_kids(&jwks.document)?,
+                source_url: jwks.entry.source_url.clone(),
+                keys: document_key_info(&jwks.document),
+            };
+            plan.jwks.push((saved, jwks.document.clone()));
+        }
+
+        // Projects without a default adopt the bundle's default key when it was imported.
+        if default_key.is_none() {
+            let imported_default = project
+                .default_key_id
+                .as_deref()
+                .and_then(|id| imported_ids.get(id));
+            if let Some(key_id) = imported_default {
+                plan.defaults
+                    .push((target_id.clone(), None, key_id.clone()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Applies every write of a merge at once: in a single SQLite transaction, or
+    /// under one lock for the in-memory vault. New secrets go to the keychain first and are
+    /// removed again if the transaction fails; secrets of replaced entries are removed only
+    /// after it commits. Webhooks hear about the changes once they are saved.
+    fn apply_merge(&self, plan: MergePlan) -> anyhow::Result<()> {
+        match &self.inner {
+            VaultInner::Memory { state } => {
+                let mut locked = state.lock().unwrap();
+                locked.projects.extend(plan.projects.iter().cloned());
+                for key in &plan.deleted_keys {
+                    locked.keys.retain(|k| k.id != key.id);
+                    locked.key_material.remove(&key.id);
+                    for project in &mut locked.projects {
+                        if project.default_key_id.as_deref() == Some(key.id.as_str()) {
+                            project.default_key_id = None;
+                        }
+                    }
+                }
+                for (key, material) in &plan.keys {
+                    locked.key_material.insert(key.id.clone(), material.clone());
+                    locked.keys.push(key.clone());
+                }
+                for token in &plan.deleted_tokens {
+                    locked.tokens.retain(|t| t.id != token.id);
+                    locked.token_material.remove(&token.id);
+                }
+                for (token, material) in &plan.tokens {
+                    locked
+                        .token_material
+                        .insert(token.id.clone(), material.clone());
+                    locked.tokens.push(token.clone());
+                }
+                for jwks in &plan.deleted_jwks {
+                    locked.jwks.retain(|j| j.id != jwks.id);
+                    locked.jwks_documents.remove(&jwks.id);
+                }
+                for (jwks, document) in &plan.jwks {
+                    locked
+                        .jwks_documents
+                        .insert(jwks.id.clone(), document.clone());
+                    locked.jwks.push(jwks.clone());
+                }
+                for (project_id, _, key_id) in &plan.defaults {
+                    if let Some(project) = locked.projects.iter_mut().find(|p| &p.id == project_id)
+                    {
+                        project.default_key_id = Some(key_id.clone());
+                    }
+                }
+            }
+            #[cfg(feature = "vault")]
+            VaultInner::Sqlite {
+                db_path,
+                keychain_service,
+                keychain,
+            } => {
+                let accounts: Vec<(String, &str)> = plan
+                    .keys
+                    .iter()
+                    .map(|(key, material)| (format!("key:{}", key.id), material.as_str()))
+                    .chain(plan.tokens.iter().map(|(token, material)| {
+                        (format!("token:{}", token.id), material.as_str())
+                    }))
+                    .collect();
+                let mut stored = Vec::new();
+                let written = accounts.iter().try_for_each(|(account, secret)| {
+                    keychain.set_password(keychain_service, account, secret)?;
+                    stored.push(account.as_str());
+                    anyhow::Ok(())
+                });
+                let replaced = written.and_then(|_| {
+                    let mut conn = open_db(db_path)?;
+                    let tx = conn.transaction()?;
+                    let replaced = write_plan(&tx, &plan, keychain_service)?;
+                    tx.commit()?;
+                    Ok(replaced)
+                });
+                let replaced = match replaced {
+                    Ok(replaced) => replaced,
+                    Err(err) => {
+                        for account in stored {
+                            let _ = keychain.delete_password(keychain_service, account);
+                        }
+                        return Err(err);
+                    }
+                };
+                for (service, account) in replaced {
+                    let _ = keychain.delete_password(&service, &account);
+                }
+            }
+        }
+
+        for key in &plan.deleted_keys {
+            self.emit_webhook_event(&key.project_id, "key.deleted", json!({ "key": key }));
+        }
+        for (key, _) in &plan.keys {
+            self.emit_webhook_event(&key.project_id, "key.added", json!({ "key": key }));
+        }
+        for (token, _) in &plan.tokens {
+            self.emit_webhook_event(&token.project_id, "token.added", json!({ "token": token }));
+        }
+        for (project_id, previous, key_id) in &plan.defaults {
+            if let Some((key, _)) = plan.keys.iter().find(|(key, _)| &key.id == key_id) {
+                self.emit_webhook_event(
+                    project_id,
+                    "key.rotated",
+                    json!({ "key": key, "previous_key_id": previous }),
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The writes a merge makes, collected before any of them runs.
+#[derive(Default)]
+struct MergePlan {
+    projects: Vec<ProjectEntry>,
+    deleted_keys: Vec<KeyEntry>,
+    keys: Vec<(KeyEntry, String)>,
+    deleted_tokens: Vec<TokenEntry>,
+    tokens: Vec<(TokenEntry, String)>,
+    deleted_jwks: Vec<JwksEntry>,
+    jwks: Vec<(JwksEntry, String)>,
+    /// `(project_id, previous default, new default)`.
+    defaults: Vec<(String, Option<String>, String)>,
+}
+
+/// Writes the plan's rows inside `tx`. Returns the keychain `(service, account)` pairs of the
+/// rows it deleted.
+#[cfg(feature = "vault")]
+fn write_plan(
+    tx: &rusqlite::Transaction<'_>,
+    plan: &MergePlan,
+    keychain_service: &str,
+) -> anyhow::Result<Vec<(String, String)>> {
+    let mut replaced = Vec::new();
+    for project in &plan.projects {
+        tx.execute(
+            "INSERT INTO projects (id, name, created_at, default_key_id, description, tags, policy, exportable, wasm_module) VALUES (?1, ?2, ?3, NULL, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                project.id,
+                project.name,
+                project.created_at,
+                project.description,
+                serialize_tags(&project.tags),
+                serialize_policy(&project.policy),
+                project.exportable,
+                project.wasm_module
+            ],
+        )?;
+    }
+    for (table, id) in plan
+        .deleted_keys
+        .iter()
+        .map(|k| ("keys", &k.id))
+        .chain(plan.deleted_tokens.iter().map(|t| ("tokens", &t.id)))
+    {
+        replaced.push(tx.query_row(
+            &format!("SELECT keychain_service, keychain_account FROM {table} WHERE id = ?1"),
+            params![id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?);
+        tx.execute(&format!("DELETE FROM {table} WHERE id = ?1"), params![id])?;
+    }
+    for key in &plan.deleted_keys {
+        tx.execute(
+            "UPDATE projects SET default_key_id = NULL WHERE default_key_id = ?1",
+            params![key.id],
+        )?;
+    }
+    for (key, _) in &plan.keys {
+        tx.execute(
+            "INSERT INTO keys (id, project_id, name, kind, created_at, kid, description, tags, keychain_service, keychain_account, fingerprint, usage) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            params![
+                key.id,
+                key.project_id,
+                key.name,
+                key.kind,
+                key.created_at,
+                key.kid,
+                key.description,
+                serialize_tags(&key.tags),
+                keychain_service,
+                format!("key:{}", key.id),
+                key.fingerprint,
+                key.usage.as_str()
+            ],
+        )?;
+    }
+    for (token, _) in &plan.tokens {
+        tx.execute(
+            "INSERT INTO tokens (id, project_id, name, created_at, keychain_service, keychain_account) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                token.id,
+                token.project_id,
+                token.name,
+                token.created_at,
+                keychain_service,
+                format!("token:{}", token.id)
+            ],
+        )?;
+    }
+    for jwks in &plan.deleted_jwks {
+        tx.execute("DELETE FROM jwks WHERE id = ?1", params![jwks.id])?;
+    }
+    for (jwks, document) in &plan.jwks {
+        tx.execute(
+            "INSERT INTO jwks (id, project_id, name, created_at, kids, document, source_url) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                jwks.id,
+                jwks.project_id,
+                jwks.name,
+                jwks.created_at,
+                serde_json::to_string(&jwks.kids)?,
+                document,
+                jwks.source_url
+            ],
+        )?;
+    }
+    for (project_id, _, key_id) in &plan.defaults {
+        tx.execute(
+            "UPDATE projects SET default_key_id = ?1 WHERE id = ?2",
+            params![key_id, project_id],
+        )?;
+    }
+    Ok(replaced)
+}
+
+fn find_key_conflict<'a>(
+    existing: &'a [KeyEntry],
+    incoming: &KeyEntry,
+) -> Option<(&'a KeyEntry, &'static str)> {
+    if let Some(kid) = &incoming.kid {
+        if let Some(found) = existing.iter().find(|k| k.kid.as_ref() == Some(kid)) {
+            return Some((found, "kid"));
+        }
+    }
+    existing
+        .iter()
+        .find(|k| k.name == incoming.name)
+        .map(|found| (found, "name"))
+}
+
+fn find_token_conflict<'a>(
+    existing: &'a [TokenEntry],
+    incoming: &TokenEntry,
+) -> Option<&'a TokenEntry> {
+    existing.iter().find(|t| t.name == incoming.name)
+}
+
+fn find_jwks_conflict<'a>(
+    existing: &'a [JwksEntry],
+    incoming: &JwksEntry,
+) -> Option<&'a JwksEntry> {
+    existing.iter().find(|j| j.name == incoming.name)
+}
+
+fn unique_name(base: &str, taken: &HashSet<String>) -> String {
+    let candidate = format!("{base}-imported");
+    if !taken.contains(&candidate) {
+        return candidate;
+    }
+    (2..)
+        .map(|n| format!("{base}-imported-{n}"))
+        .find(|name| !taken.contains(name))
+        .unwrap_or(candidate)
+}
@@ -1,20 +1,50 @@
+// Without the `vault` feature only the in-memory backend is compiled, which leaves the helpers
+// and imports that serve SQLite rows unused.
+#![cfg_attr(
+    not(feature = "vault"),
+    allow(dead_code, unused_imports, unused_variables)
+)]
+
+mod audit;
+mod backup;
+mod bench;
 mod export;
 mod helpers;
+mod history;
+mod jwks;
 mod key;
 mod keychain;
+#[cfg(feature = "vault")]
 mod keychain_file;
+mod merge;
 mod project;
+mod relocate;
+mod revocation;
 mod snapshot;
+#[cfg(feature = "vault")]
 mod sqlite;
+mod stats;
 mod store;
 mod token;
 mod types;
+mod webhook;
+mod webhook_queue;
 
+pub use backup::BackupEntry;
+#[cfg(feature = "keygen")]
+pub use bench::LatencyStats;
+pub use history::{HistoryEntry, HistoryInput};
+pub use merge::{ConflictStrategy, MergeActionKind, MergeReport};
+pub use revocation::token_hash;
 pub use store::{Vault, VaultConfig};
-pub use types::{KeyEntry, KeyEntryInput, ProjectEntry, ProjectInput, TokenEntry, TokenEntryInput};
+pub use types::{
+    FamilyPolicy, JwksEntry, JwksEntryInput, KeyEntry, KeyEntryInput, KeyUsage, ProjectEntry,
+    ProjectInput, RevocationInput, TokenEntry, TokenEntryInput, WebhookEntryInput,
+};
+pub use webhook_queue::flush_webhooks;
 
-#[cfg(test)]
+#[cfg(all(test, feature = "vault"))]
 pub(crate) use keychain::MemoryKeychain;
 
-#[cfg(test)]
+#[cfg(all(test, feature = "vault"))]
 mod tests;
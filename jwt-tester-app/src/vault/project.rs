@@ -1,29 +1,33 @@
-use super::helpers::{normalize_opt_string, normalize_tags, now_unix, parse_tags, serialize_tags};
+use super::helpers::{
+    normalize_opt_string, normalize_tags, now_unix, parse_policy, parse_tags, serialize_policy,
+    serialize_tags,
+};
+use super::keychain::KeychainStore;
+#[cfg(feature = "vault")]
+use super::sqlite::open_db;
 use super::store::{Vault, VaultInner};
-use super::types::{ProjectEntry, ProjectInput};
-use rusqlite::{params, Connection};
+use super::types::{
+    FamilyPolicy, JwksEntryInput, KeyEntryInput, KeyUsage, KeychainFailure, ProjectCloneReport,
+    ProjectDeleteReport, ProjectEntry, ProjectInput,
+};
+#[cfg(feature = "vault")]
+use rusqlite::{params, OptionalExtension, Row};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::time::Duration;
 use uuid::Uuid;
 
 impl Vault {
     pub fn list_projects(&self) -> anyhow::Result<Vec<ProjectEntry>> {
         match &self.inner {
             VaultInner::Memory { state } => Ok(state.lock().unwrap().projects.clone()),
+            #[cfg(feature = "vault")]
             VaultInner::Sqlite { db_path, .. } => {
-                let conn = Connection::open(db_path)?;
+                let conn = open_db(db_path)?;
                 let mut stmt = conn.prepare(
-                    "SELECT id, name, created_at, default_key_id, description, tags FROM projects ORDER BY created_at DESC",
+                    "SELECT id, name, created_at, default_key_id, description, tags, policy, exportable, wasm_module FROM projects ORDER BY created_at DESC",
                 )?;
-                let rows = stmt.query_map([], |row| {
-                    let tags = parse_tags(row.get(5)?);
-                    Ok(ProjectEntry {
-                        id: row.get(0)?,
-                        name: row.get(1)?,
-                        created_at: row.get(2)?,
-                        default_key_id: row.get(3)?,
-                        description: row.get(4)?,
-                        tags,
-                    })
-                })?;
+                let rows = stmt.query_map([], project_row)?;
                 Ok(rows.collect::<Result<Vec<_>, _>>()?)
             }
         }
@@ -46,6 +50,9 @@ impl Vault {
             default_key_id: None,
             description,
             tags,
+            policy: BTreeMap::new(),
+            exportable: true,
+            wasm_module: None,
         };
 
         match &self.inner {
@@ -56,8 +63,9 @@ impl Vault {
                 }
                 locked.projects.push(row.clone());
             }
+            #[cfg(feature = "vault")]
             VaultInner::Sqlite { db_path, .. } => {
-                let conn = Connection::open(db_path)?;
+                let conn = open_db(db_path)?;
                 conn.execute(
                     "INSERT INTO projects (id, name, created_at, default_key_id, description, tags) VALUES (?1, ?2, ?3, NULL, ?4, ?5)",
                     params![row.id, row.name, row.created_at, row.description, tags_json],
@@ -82,22 +90,13 @@ impl Vault {
                 .iter()
                 .find(|p| p.name == name)
                 .cloned()),
+            #[cfg(feature = "vault")]
             VaultInner::Sqlite { db_path, .. } => {
-                let conn = Connection::open(db_path)?;
+                let conn = open_db(db_path)?;
                 let mut stmt = conn.prepare(
-                    "SELECT id, name, created_at, default_key_id, description, tags FROM projects WHERE name = ?1",
+                    "SELECT id, name, created_at, default_key_id, description, tags, policy, exportable, wasm_module FROM projects WHERE name = ?1",
                 )?;
-                let result = stmt.query_row(params![name], |row| {
-                    let tags = parse_tags(row.get(5)?);
-                    Ok(ProjectEntry {
-                        id: row.get(0)?,
-                        name: row.get(1)?,
-                        created_at: row.get(2)?,
-                        default_key_id: row.get(3)?,
-                        description: row.get(4)?,
-                        tags,
-                    })
-                });
+                let result = stmt.query_row(params![name], project_row);
                 match result {
                     Ok(p) => Ok(Some(p)),
                     Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
@@ -107,8 +106,10 @@ impl Vault {
         }
     }
 
+    /// Changing a project's default to a different key is reported to its webhooks as
+    /// `key.rotated`.
     pub fn set_default_key(&self, project_id: &str, key_id: Option<&str>) -> anyhow::Result<()> {
-        match &self.inner {
+        let previous = match &self.inner {
             VaultInner::Memory { state } => {
                 let mut locked = state.lock().unwrap();
                 let project = locked
@@ -116,42 +117,317 @@ impl Vault {
                     .iter_mut()
                     .find(|p| p.id == project_id)
                     .ok_or_else(|| anyhow::anyhow!("project not found"))?;
-                project.default_key_id = key_id.map(|s| s.to_string());
-                Ok(())
+                std::mem::replace(&mut project.default_key_id, key_id.map(|s| s.to_string()))
             }
+            #[cfg(feature = "vault")]
             VaultInner::Sqlite { db_path, .. } => {
-                let conn = Connection::open(db_path)?;
+                let conn = open_db(db_path)?;
+                let previous: Option<String> = conn
+                    .query_row(
+                        "SELECT default_key_id FROM projects WHERE id = ?1",
+                        params![project_id],
+                        |row| row.get(0),
+                    )
+                    .optional()?
+                    .flatten();
                 conn.execute(
                     "UPDATE projects SET default_key_id = ?1 WHERE id = ?2",
                     params![key_id, project_id],
                 )?;
-                Ok(())
+                previous
+            }
+        };
+        if let Some(key_id) = key_id.filter(|id| previous.as_deref() != Some(*id)) {
+            if let Some(key) = self.get_key(key_id)? {
+                self.emit_webhook_event(
+                    project_id,
+                    "key.rotated",
+                    json!({ "key": key, "previous_key_id": previous }),
+                );
             }
         }
+        Ok(())
     }
 
-    pub fn delete_project(&self, project_id: &str) -> anyhow::Result<()> {
-        let keys = self.list_keys(Some(project_id))?;
-        for k in keys {
-            let _ = self.delete_key(&k.id);
+    /// Replaces a project's per-algorithm-family policy; an empty map removes it.
+    pub fn set_project_policy(
+        &self,
+        project_id: &str,
+        policy: &BTreeMap<String, FamilyPolicy>,
+    ) -> anyhow::Result<()> {
+        match &self.inner {
+            VaultInner::Memory { state } => {
+                let mut locked = state.lock().unwrap();
+                let project = locked
+                    .projects
+                    .iter_mut()
+                    .find(|p| p.id == project_id)
+                    .ok_or_else(|| anyhow::anyhow!("project not found"))?;
+                project.policy = policy.clone();
+                Ok(())
+            }
+            #[cfg(feature = "vault")]
+            VaultInner::Sqlite { db_path, .. } => {
+                let conn = open_db(db_path)?;
+                let updated = conn.execute(
+                    "UPDATE projects SET policy = ?1 WHERE id = ?2",
+                    params![serialize_policy(policy), project_id],
+                )?;
+                if updated == 0 {
+                    anyhow::bail!("project not found");
+                }
+                Ok(())
+            }
         }
-        let tokens = self.list_tokens(Some(project_id))?;
-        for t in tokens {
-            let _ = self.delete_token(&t.id);
+    }
+
+    /// Marks whether the project's key material may leave the vault (see
+    /// [`Vault::export_bundle_with`]).
+    pub fn set_project_exportable(&self, project_id: &str, exportable: bool) -> anyhow::Result<()> {
+        match &self.inner {
+            VaultInner::Memory { state } => {
+                let mut locked = state.lock().unwrap();
+                let project = locked
+                    .projects
+                    .iter_mut()
+                    .find(|p| p.id == project_id)
+                    .ok_or_else(|| anyhow::anyhow!("project not found"))?;
+                project.exportable = exportable;
+                Ok(())
+            }
+            #[cfg(feature = "vault")]
+            VaultInner::Sqlite { db_path, .. } => {
+                let conn = open_db(db_path)?;
+                let updated = conn.execute(
+                    "UPDATE projects SET exportable = ?1 WHERE id = ?2",
+                    params![exportable, project_id],
+                )?;
+                if updated == 0 {
+                    anyhow::bail!("project not found");
+                }
+                Ok(())
+            }
         }
+    }
 
+    /// Sets or clears the WASM module `encode`/`verify --project` run for the project.
+    pub fn set_project_wasm_module(
+        &self,
+        project_id: &str,
+        module: Option<&str>,
+    ) -> anyhow::Result<()> {
         match &self.inner {
             VaultInner::Memory { state } => {
                 let mut locked = state.lock().unwrap();
-                locked.projects.retain(|p| p.id != project_id);
+                let project = locked
+                    .projects
+                    .iter_mut()
+                    .find(|p| p.id == project_id)
+                    .ok_or_else(|| anyhow::anyhow!("project not found"))?;
+                project.wasm_module = module.map(str::to_string);
+                Ok(())
             }
+            #[cfg(feature = "vault")]
             VaultInner::Sqlite { db_path, .. } => {
-                let conn = Connection::open(db_path)?;
-                conn.execute("DELETE FROM projects WHERE id = ?1", params![project_id])?;
+                let conn = open_db(db_path)?;
+                let updated = conn.execute(
+                    "UPDATE projects SET wasm_module = ?1 WHERE id = ?2",
+                    params![module, project_id],
+                )?;
+                if updated == 0 {
+                    anyhow::bail!("project not found");
+                }
+                Ok(())
             }
         }
+    }
 
-        Ok(())
+    /// Copies a project's description, tags, policy, export setting, WASM module, keys (with their
+    /// usage), JWKS documents and default key into a new project. Key material is stored again
+    /// under new ids; stored tokens are not copied.
+    pub fn clone_project(
+        &self,
+        source_id: &str,
+        new_name: &str,
+    ) -> anyhow::Result<ProjectCloneReport> {
+        let source = self
+            .find_project_by_id(source_id)?
+            .ok_or_else(|| anyhow::anyhow!("project not found"))?;
+        if self.find_project(new_name)?.is_some() {
+            anyhow::bail!("project already exists");
+        }
+        // Oldest first, so the copies list in the same order as the originals.
+        let mut keys = self.list_keys(Some(&source.id))?;
+        keys.reverse();
+        let key_ids: Vec<&str> = keys.iter().map(|k| k.id.as_str()).collect();
+        let materials = self.get_key_materials(&key_ids)?;
+        let mut jwks = self.list_jwks(Some(&source.id))?;
+        jwks.reverse();
+
+        let project = self.add_project(ProjectInput {
+            name: new_name.to_string(),
+            description: source.description.clone(),
+            tags: source.tags.clone(),
+        })?;
+        if !source.policy.is_empty() {
+            self.set_project_policy(&project.id, &source.policy)?;
+        }
+        if !source.exportable {
+            self.set_project_exportable(&project.id, false)?;
+        }
+        if let Some(module) = &source.wasm_module {
+            self.set_project_wasm_module(&project.id, Some(module))?;
+        }
+        let mut default_key_id = None;
+        for (key, secret) in keys.iter().zip(materials) {
+            let copy = self.add_key(KeyEntryInput {
+                project_id: project.id.clone(),
+                name: key.name.clone(),
+                kind: key.kind.clone(),
+                secret,
+                kid: key.kid.clone(),
+                description: key.description.clone(),
+                tags: key.tags.clone(),
+            })?;
+            if key.usage != KeyUsage::Both {
+                self.set_key_usage(&copy.id, key.usage)?;
+            }
+            if source.default_key_id.as_deref() == Some(key.id.as_str()) {
+                default_key_id = Some(copy.id);
+            }
+        }
+        for entry in &jwks {
+            self.add_jwks(JwksEntryInput {
+                project_id: project.id.clone(),
+                name: entry.name.clone(),
+                document: self.get_jwks_document(&entry.id)?,
+                source_url: entry.source_url.clone(),
+            })?;
+        }
+        if let Some(key_id) = &default_key_id {
+            self.set_default_key(&project.id, Some(key_id))?;
+        }
+
+        Ok(ProjectCloneReport {
+            project: ProjectEntry {
+                default_key_id,
+                policy: source.policy,
+                exportable: source.exportable,
+                wasm_module: source.wasm_module,
+                ..project
+            },
+            keys: keys.len(),
+            jwks: jwks.len(),
+        })
+    }
+
+    /// The only material read here is the automatic backup's export, which already uses the bulk
+    /// `get_key_materials` / `get_token_materials`. The delete itself never reads secrets: it
+    /// collects every keychain account in one query and removes the entries by account.
+    pub fn delete_project(&self, project_id: &str) -> anyhow::Result<ProjectDeleteReport> {
+        self.auto_backup("project-delete")?;
+        self.delete_project_entries(project_id)
+    }
+
+    pub(super) fn delete_project_entries(
+        &self,
+        project_id: &str,
+    ) -> anyhow::Result<ProjectDeleteReport> {
+        match &self.inner {
+            VaultInner::Memory { state } => {
+                let mut locked = state.lock().unwrap();
+                let state = &mut *locked;
+                let mut report = ProjectDeleteReport::default();
+                state.keys.retain(|k| {
+                    let keep = k.project_id != project_id;
+                    if !keep {
+                        state.key_material.remove(&k.id);
+                        report.keys += 1;
+                    }
+                    keep
+                });
+                state.tokens.retain(|t| {
+                    let keep = t.project_id != project_id;
+                    if !keep {
+                        state.token_material.remove(&t.id);
+                        report.tokens += 1;
+                    }
+                    keep
+                });
+                state.jwks.retain(|j| {
+                    let keep = j.project_id != project_id;
+                    if !keep {
+                        state.jwks_documents.remove(&j.id);
+                        report.jwks += 1;
+                    }
+                    keep
+                });
+                state.webhooks.retain(|w| {
+                    let keep = w.project_id != project_id;
+                    if !keep {
+                        state.webhook_secrets.remove(&w.id);
+                        report.webhooks += 1;
+                    }
+                    keep
+                });
+                state.revocations.retain(|r| r.project_id != project_id);
+                state.projects.retain(|p| p.id != project_id);
+                Ok(report)
+            }
+            #[cfg(feature = "vault")]
+            VaultInner::Sqlite {
+                db_path, keychain, ..
+            } => {
+                let mut conn = open_db(db_path)?;
+                let tx = conn.transaction()?;
+                let accounts = {
+                    let mut stmt = tx.prepare(
+                        "SELECT keychain_service, keychain_account FROM keys WHERE project_id = ?1 \
+                         UNION ALL \
+                         SELECT keychain_service, keychain_account FROM tokens WHERE project_id = ?1 \
+                         UNION ALL \
+                         SELECT keychain_service, keychain_account FROM webhooks WHERE project_id = ?1",
+                    )?;
+                    let rows = stmt.query_map(params![project_id], |row| {
+                        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+                    })?;
+                    rows.collect::<Result<Vec<_>, _>>()?
+                };
+                let mut report = ProjectDeleteReport {
+                    keys: tx.execute(
+                        "DELETE FROM keys WHERE project_id = ?1",
+                        params![project_id],
+                    )?,
+                    tokens: tx.execute(
+                        "DELETE FROM tokens WHERE project_id = ?1",
+                        params![project_id],
+                    )?,
+                    jwks: tx.execute(
+                        "DELETE FROM jwks WHERE project_id = ?1",
+                        params![project_id],
+                    )?,
+                    webhooks: tx.execute(
+                        "DELETE FROM webhooks WHERE project_id = ?1",
+                        params![project_id],
+                    )?,
+                    keychain_failures: Vec::new(),
+                };
+                tx.execute("DELETE FROM projects WHERE id = ?1", params![project_id])?;
+                tx.commit()?;
+
+                // The rows are gone either way; a keychain entry that cannot be removed is only
+                // an orphaned secret, so it is reported rather than failing the delete.
+                for (service, account) in accounts {
+                    if let Err(err) = delete_with_retry(keychain.as_ref(), &service, &account) {
+                        report.keychain_failures.push(KeychainFailure {
+                            account,
+                            error: format!("{err:#}"),
+                        });
+                    }
+                }
+                Ok(report)
+            }
+        }
     }
 
     pub fn find_project_by_name(&self, name: &str) -> anyhow::Result<Option<ProjectEntry>> {
@@ -172,22 +448,13 @@ impl Vault {
                 .iter()
                 .find(|p| p.id == id)
                 .cloned()),
+            #[cfg(feature = "vault")]
             VaultInner::Sqlite { db_path, .. } => {
-                let conn = Connection::open(db_path)?;
+                let conn = open_db(db_path)?;
                 let mut stmt = conn.prepare(
-                    "SELECT id, name, created_at, default_key_id, description, tags FROM projects WHERE id = ?1",
+                    "SELECT id, name, created_at, default_key_id, description, tags, policy, exportable, wasm_module FROM projects WHERE id = ?1",
                 )?;
-                let result = stmt.query_row(params![id], |row| {
-                    let tags = parse_tags(row.get(5)?);
-                    Ok(ProjectEntry {
-                        id: row.get(0)?,
-                        name: row.get(1)?,
-                        created_at: row.get(2)?,
-                        default_key_id: row.get(3)?,
-                        description: row.get(4)?,
-                        tags,
-                    })
-                });
+                let result = stmt.query_row(params![id], project_row);
                 match result {
                     Ok(p) => Ok(Some(p)),
                     Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
@@ -197,3 +464,38 @@ impl Vault {
         }
     }
 }
+
+#[cfg(feature = "vault")]
+fn project_row(row: &Row<'_>) -> rusqlite::Result<ProjectEntry> {
+    Ok(ProjectEntry {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        created_at: row.get(2)?,
+        default_key_id: row.get(3)?,
+        description: row.get(4)?,
+        tags: parse_tags(row.get(5)?),
+        policy: parse_policy(row.get(6)?),
+        exportable: row.get(7)?,
+        wasm_module: row.get(8)?,
+    })
+}
+
+const KEYCHAIN_DELETE_ATTEMPTS: u32 = 3;
+
+fn delete_with_retry(
+    keychain: &dyn KeychainStore,
+    service: &str,
+    account: &str,
+) -> anyhow::Result<()> {
+    let mut attempt = 1;
+    loop {
+        match keychain.delete_password(service, account) {
+            Ok(()) => return Ok(()),
+            Err(err) if attempt >= KEYCHAIN_DELETE_ATTEMPTS => return Err(err),
+            Err(_) => {
+                std::thread::sleep(Duration::from_millis(50 * u64::from(attempt)));
+                attempt += 1;
+            }
+        }
+    }
+}
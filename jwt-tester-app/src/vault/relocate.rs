@@ -0,0 +1,190 @@
+#[cfg(feature = "vault")]
+use super::sqlite::open_db;
+use super::store::Vault;
+#[cfg(feature = "vault")]
+use super::store::VaultInner;
+use anyhow::Context;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+const DB_FILE: &str = "vault.sqlite3";
+/// The database and its sidecars; the database is copied through SQLite, never file by file.
+const DB_FILES: [&str; 4] = [
+    DB_FILE,
+    "vault.sqlite3-wal",
+    "vault.sqlite3-shm",
+    "vault.sqlite3-journal",
+];
+
+/// What `relocate` copied. Paths in `copied` are relative to both data directories.
+#[derive(Debug, Clone, Serialize)]
+pub struct RelocateReport {
+    pub from: PathBuf,
+    pub to: PathBuf,
+    pub copied: Vec<PathBuf>,
+    pub bytes: u64,
+    pub keychain_backend: &'static str,
+    /// New home of a file keychain that lived inside the data directory. `None` when the
+    /// keychain stays where it is (OS keychain, or `JWT_TESTER_KEYCHAIN_DIR` elsewhere).
+    pub keychain_dir: Option<PathBuf>,
+    pub source_removed: bool,
+}
+
+#[cfg(feature = "vault")]
+impl Vault {
+    /// Copies the data directory to `to` (an empty or missing directory) and opens the copy.
+    /// Entry counts are compared before returning; the source is left untouched, so callers
+    /// check the copy further and then call `RelocateReport::remove_source`. On any error the
+    /// partial copy is removed again.
+    pub fn relocate(&self, to: &Path) -> anyhow::Result<(Vault, RelocateReport)> {
+        let VaultInner::Sqlite {
+            db_path, keychain, ..
+        } = &self.inner
+        else {
+            anyhow::bail!("in-memory vaults (--no-persist) have nothing to relocate");
+        };
+        let from = db_path
+            .parent()
+            .context("vault database has no parent directory")?
+            .canonicalize()
+            .context("resolve the current data directory")?;
+        std::fs::create_dir_all(to).with_context(|| format!("create {}", to.display()))?;
+        let to = to
+            .canonicalize()
+            .with_context(|| format!("resolve {}", to.display()))?;
+        if to.starts_with(&from) || from.starts_with(&to) {
+            anyhow::bail!(
+                "{} and the data directory {} must not contain each other",
+                to.display(),
+                from.display()
+            );
+        }
+        if std::fs::read_dir(&to)?.next().is_some() {
+            anyhow::bail!("{} is not empty", to.display());
+        }
+
+        let keychain_dir = keychain
+            .storage_dir()
+            .and_then(|dir| dir.canonicalize().ok())
+            .and_then(|dir| dir.strip_prefix(&from).ok().map(|rel| to.join(rel)));
+        let mut report = RelocateReport {
+            from,
+            to,
+            copied: Vec::new(),
+            bytes: 0,
+            keychain_backend: keychain.backend(),
+            keychain_dir,
+            source_removed: false,
+        };
+        match self.copy_to(&mut report) {
+            Ok(target) => Ok((target, report)),
+            Err(err) => {
+                report.discard_copy();
+                Err(err)
+            }
+        }
+    }
+
+    fn copy_to(&self, report: &mut RelocateReport) -> anyhow::Result<Vault> {
+        let db_path = report.from.join(DB_FILE);
+        let target_db = report.to.join(DB_FILE);
+        // VACUUM INTO writes a consistent snapshot, WAL contents included.
+        open_db(&db_path)?
+            .execute("VACUUM INTO ?1", [target_db.to_string_lossy()])
+            .context("copy the vault database")?;
+        report.copied.push(PathBuf::from(DB_FILE));
+        report.bytes += std::fs::metadata(&target_db).map_or(0, |meta| meta.len());
+        copy_tree(Path::new(""), report)?;
+
+        let target = self.open_relocated(report.to.clone(), report.keychain_dir.clone())?;
+        let (before, after) = (self.stats()?.totals, target.stats()?.totals);
+        if before != after {
+            anyhow::bail!(
+                "the copy does not match the source ({} keys, {} tokens, {} JWKS in the source; \
+                 {} keys, {} tokens, {} JWKS in the copy)",
+                before.keys,
+                before.tokens,
+                before.jwks,
+                after.keys,
+                after.tokens,
+                after.jwks
+            );
+        }
+        Ok(target)
+    }
+}
+
+#[cfg(not(feature = "vault"))]
+impl Vault {
+    /// Without the `vault` feature there is no data directory to move.
+    pub fn relocate(&self, _to: &Path) -> anyhow::Result<(Vault, RelocateReport)> {
+        anyhow::bail!("relocating the vault requires a build with the `vault` feature")
+    }
+}
+
+impl RelocateReport {
+    /// Deletes what was copied from the old data directory, then any directories left empty.
+    /// Files that were never copied (created meanwhile, or not ours) stay.
+    pub fn remove_source(&mut self) -> anyhow::Result<()> {
+        for name in DB_FILES {
+            let path = self.from.join(name);
+            if path.exists() {
+                std::fs::remove_file(&path)
+                    .with_context(|| format!("remove {}", path.display()))?;
+            }
+        }
+        for rel in &self.copied {
+            let path = self.from.join(rel);
+            if path.is_file() {
+                std::fs::remove_file(&path)
+                    .with_context(|| format!("remove {}", path.display()))?;
+            }
+        }
+        remove_empty_dirs(&self.from);
+        self.source_removed = true;
+        Ok(())
+    }
+
+    /// Undoes a partial copy; best-effort, since it only runs on the way out of an error.
+    pub fn discard_copy(&self) {
+        for rel in self.copied.iter().rev() {
+            let _ = std::fs::remove_file(self.to.join(rel));
+        }
+        remove_empty_dirs(&self.to);
+    }
+}
+
+/// Copies everything below `rel` except the database files, recording each file.
+fn copy_tree(rel: &Path, report: &mut RelocateReport) -> anyhow::Result<()> {
+    let dir = report.from.join(rel);
+    for entry in std::fs::read_dir(&dir).with_context(|| format!("read {}", dir.display()))? {
+        let entry = entry?;
+        let rel = rel.join(entry.file_name());
+        if DB_FILES.iter().any(|name| rel == Path::new(name)) {
+            continue;
+        }
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            std::fs::create_dir_all(report.to.join(&rel))?;
+            copy_tree(&rel, report)?;
+        } else if file_type.is_file() {
+            let bytes = std::fs::copy(entry.path(), report.to.join(&rel))
+                .with_context(|| format!("copy {}", entry.path().display()))?;
+            report.bytes += bytes;
+            report.copied.push(rel);
+        }
+    }
+    Ok(())
+}
+
+fn remove_empty_dirs(dir: &Path) {
+    if let Ok(entries) = std::fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                remove_empty_dirs(&entry.path());
+            }
+        }
+    }
+    // Fails, as intended, while anything is left inside.
+    let _ = std::fs::remove_dir(dir);
+}
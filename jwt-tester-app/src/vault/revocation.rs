@@ -0,0 +1,114 @@
+use super::helpers::{normalize_opt_string, now_unix};
+#[cfg(feature = "vault")]
+use super::sqlite::open_db;
+use super::store::{Vault, VaultInner};
+use super::types::{RevocationEntry, RevocationInput};
+#[cfg(feature = "vault")]
+use rusqlite::{params, Row};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+/// Lowercase hex SHA-256 of a compact token, as stored in `token_hash`.
+pub fn token_hash(token: &str) -> String {
+    hex::encode(Sha256::digest(token.trim().as_bytes()))
+}
+
+// Revocations only hold a jti and a hash, so they live in SQLite like JWKS documents.
+impl Vault {
+    pub fn list_revocations(&self, project_id: &str) -> anyhow::Result<Vec<RevocationEntry>> {
+        match &self.inner {
+            VaultInner::Memory { state } => Ok(state
+                .lock()
+                .unwrap()
+                .revocations
+                .iter()
+                .filter(|r| r.project_id == project_id)
+                .cloned()
+                .collect()),
+            #[cfg(feature = "vault")]
+            VaultInner::Sqlite { db_path, .. } => {
+                let conn = open_db(db_path)?;
+                let mut stmt = conn.prepare(
+                    "SELECT id, project_id, jti, token_hash, token_id, created_at FROM revocations WHERE project_id = ?1 ORDER BY created_at",
+                )?;
+                let rows = stmt.query_map(params![project_id], revocation_row)?;
+                Ok(rows.collect::<Result<Vec<_>, _>>()?)
+            }
+        }
+    }
+
+    /// Records a revocation. Revoking a jti or token that is already revoked returns the existing
+    /// entry.
+    pub fn revoke(&self, input: RevocationInput) -> anyhow::Result<RevocationEntry> {
+        if input.project_id.trim().is_empty() {
+            anyhow::bail!("project_id is required");
+        }
+        let jti = normalize_opt_string(input.jti);
+        let token_hash = input
+            .token
+            .as_deref()
+            .filter(|token| !token.trim().is_empty())
+            .map(token_hash);
+        if jti.is_none() && token_hash.is_none() {
+            anyhow::bail!("a jti or a token is required");
+        }
+        if let Some(existing) =
+            self.find_revocation(&input.project_id, jti.as_deref(), token_hash.as_deref())?
+        {
+            return Ok(existing);
+        }
+
+        let row = RevocationEntry {
+            id: Uuid::new_v4().to_string(),
+            project_id: input.project_id,
+            jti,
+            token_hash,
+            token_id: input.token_id,
+            created_at: now_unix(),
+        };
+        match &self.inner {
+            VaultInner::Memory { state } => state.lock().unwrap().revocations.push(row.clone()),
+            #[cfg(feature = "vault")]
+            VaultInner::Sqlite { db_path, .. } => {
+                let conn = open_db(db_path)?;
+                conn.execute(
+                    "INSERT INTO revocations (id, project_id, jti, token_hash, token_id, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![
+                        row.id,
+                        row.project_id,
+                        row.jti,
+                        row.token_hash,
+                        row.token_id,
+                        row.created_at
+                    ],
+                )?;
+            }
+        }
+        Ok(row)
+    }
+
+    /// The revocation matching `jti` or `token_hash` in the project, if any.
+    pub fn find_revocation(
+        &self,
+        project_id: &str,
+        jti: Option<&str>,
+        token_hash: Option<&str>,
+    ) -> anyhow::Result<Option<RevocationEntry>> {
+        Ok(self.list_revocations(project_id)?.into_iter().find(|r| {
+            (jti.is_some() && r.jti.as_deref() == jti)
+                || (token_hash.is_some() && r.token_hash.as_deref() == token_hash)
+        }))
+    }
+}
+
+#[cfg(feature = "vault")]
+fn revocation_row(row: &Row<'_>) -> rusqlite::Result<RevocationEntry> {
+    Ok(RevocationEntry {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        jti: row.get(2)?,
+        token_hash: row.get(3)?,
+        token_id: row.get(4)?,
+        created_at: row.get(5)?,
+    })
+}
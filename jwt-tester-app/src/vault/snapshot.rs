@@ -2,7 +2,7 @@ use crate::vault_export;
 use std::collections::{HashMap, HashSet};
 
 pub(super) fn validate_snapshot(snapshot: &vault_export::VaultSnapshot) -> anyhow::Result<()> {
-    if snapshot.version != vault_export::EXPORT_VERSION {
+    if snapshot.version != vault_export::SNAPSHOT_VERSION {
         anyhow::bail!("unsupported snapshot version {}", snapshot.version);
     }
 
@@ -47,6 +47,20 @@ pub(super) fn validate_snapshot(snapshot: &vault_export::VaultSnapshot) -> anyho
         }
     }
 
+    let mut jwks_ids = HashSet::new();
+    for jwks in &snapshot.jwks {
+        if !jwks_ids.insert(jwks.entry.id.as_str()) {
+            anyhow::bail!("duplicate jwks id {}", jwks.entry.id);
+        }
+        if !project_ids.contains(jwks.entry.project_id.as_str()) {
+            anyhow::bail!(
+                "jwks {} references unknown project {}",
+                jwks.entry.id,
+                jwks.entry.project_id
+            );
+        }
+    }
+
     for project in &snapshot.projects {
         if let Some(default_id) = project.default_key_id.as_deref() {
             let Some(project_id) = key_project.get(default_id) else {
@@ -73,11 +87,11 @@ pub(super) fn validate_snapshot(snapshot: &vault_export::VaultSnapshot) -> anyho
 mod tests {
     use super::validate_snapshot;
     use crate::vault::{KeyEntry, ProjectEntry, TokenEntry};
-    use crate::vault_export::{KeyExport, TokenExport, VaultSnapshot, EXPORT_VERSION};
+    use crate::vault_export::{KeyExport, TokenExport, VaultSnapshot, SNAPSHOT_VERSION};
 
     fn base_snapshot() -> VaultSnapshot {
         VaultSnapshot {
-            version: EXPORT_VERSION,
+            version: SNAPSHOT_VERSION,
             exported_at: 1,
             projects: vec![ProjectEntry {
                 id: "p1".to_string(),
@@ -86,6 +100,9 @@ mod tests {
                 default_key_id: None,
                 description: None,
                 tags: vec![],
+                policy: Default::default(),
+                exportable: true,
+                wasm_module: None,
             }],
             keys: vec![KeyExport {
                 entry: KeyEntry {
@@ -97,6 +114,8 @@ mod tests {
                     kid: None,
                     description: None,
                     tags: vec![],
+                    fingerprint: None,
+                    usage: Default::default(),
                 },
                 material: "secret".to_string(),
             }],
@@ -109,6 +128,7 @@ mod tests {
                 },
                 token: "token".to_string(),
             }],
+            jwks: vec![],
         }
     }
 
@@ -146,6 +166,9 @@ mod tests {
             default_key_id: None,
             description: None,
             tags: vec![],
+            policy: Default::default(),
+            exportable: true,
+            wasm_module: None,
         });
         snapshot.projects[0].default_key_id = Some("k1".to_string());
         snapshot.keys[0].entry.project_id = "p2".to_string();
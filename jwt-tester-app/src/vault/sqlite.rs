@@ -1,7 +1,75 @@
 use rusqlite::Connection;
 use std::path::Path;
 
-pub(super) fn init_sqlite(path: &Path) -> anyhow::Result<()> {
+const KEYS_TABLE: &str = "CREATE TABLE IF NOT EXISTS keys (
+    id TEXT PRIMARY KEY,
+    project_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    kid TEXT NULL,
+    description TEXT NULL,
+    tags TEXT NULL,
+    keychain_service TEXT NOT NULL,
+    keychain_account TEXT NOT NULL,
+    fingerprint TEXT NULL,
+    usage TEXT NOT NULL DEFAULT 'both',
+    FOREIGN KEY(project_id) REFERENCES projects(id) ON DELETE CASCADE
+)";
+
+const TOKENS_TABLE: &str = "CREATE TABLE IF NOT EXISTS tokens (
+    id TEXT PRIMARY KEY,
+    project_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    keychain_service TEXT NOT NULL,
+    keychain_account TEXT NOT NULL,
+    FOREIGN KEY(project_id) REFERENCES projects(id) ON DELETE CASCADE
+)";
+
+const JWKS_TABLE: &str = "CREATE TABLE IF NOT EXISTS jwks (
+    id TEXT PRIMARY KEY,
+    project_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    kids TEXT NULL,
+    document TEXT NOT NULL,
+    source_url TEXT NULL,
+    FOREIGN KEY(project_id) REFERENCES projects(id) ON DELETE CASCADE
+)";
+
+const WEBHOOKS_TABLE: &str = "CREATE TABLE IF NOT EXISTS webhooks (
+    id TEXT PRIMARY KEY,
+    project_id TEXT NOT NULL,
+    url TEXT NOT NULL,
+    events TEXT NULL,
+    created_at INTEGER NOT NULL,
+    keychain_service TEXT NOT NULL,
+    keychain_account TEXT NOT NULL,
+    FOREIGN KEY(project_id) REFERENCES projects(id) ON DELETE CASCADE
+)";
+
+const REVOCATIONS_TABLE: &str = "CREATE TABLE IF NOT EXISTS revocations (
+    id TEXT PRIMARY KEY,
+    project_id TEXT NOT NULL,
+    jti TEXT NULL,
+    token_hash TEXT NULL,
+    token_id TEXT NULL,
+    created_at INTEGER NOT NULL,
+    FOREIGN KEY(project_id) REFERENCES projects(id) ON DELETE CASCADE
+)";
+
+/// Opens the vault database with foreign keys enforced, so deleting a project cascades to its
+/// keys, tokens, JWKS and webhooks. SQLite turns the pragma off for every new connection by default.
+pub(super) fn open_db(path: &Path) -> anyhow::Result<Connection> {
+    let conn = Connection::open(path)?;
+    conn.pragma_update(None, "foreign_keys", true)?;
+    Ok(conn)
+}
+
+/// Creates or upgrades the schema. Returns the keychain `(service, account)` pairs of orphaned
+/// key/token/webhook rows it removed, so the caller can delete their secrets too.
+pub(super) fn init_sqlite(path: &Path) -> anyhow::Result<Vec<(String, String)>> {
     let conn = Connection::open(path)?;
 
     // If an older schema exists (projects had a NOT NULL `domain` column), fail fast with an actionable message.
@@ -27,6 +95,9 @@ pub(super) fn init_sqlite(path: &Path) -> anyhow::Result<()> {
             default_key_id TEXT NULL,
             description TEXT NULL,
             tags TEXT NULL,
+            policy TEXT NULL,
+            exportable INTEGER NOT NULL DEFAULT 1,
+            wasm_module TEXT NULL,
             UNIQUE(name)
         )",
         [],
@@ -51,24 +122,27 @@ pub(super) fn init_sqlite(path: &Path) -> anyhow::Result<()> {
         "tags",
         "ALTER TABLE projects ADD COLUMN tags TEXT NULL",
     )?;
-
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS keys (
-            id TEXT PRIMARY KEY,
-            project_id TEXT NOT NULL,
-            name TEXT NOT NULL,
-            kind TEXT NOT NULL,
-            created_at INTEGER NOT NULL,
-            kid TEXT NULL,
-            description TEXT NULL,
-            tags TEXT NULL,
-            keychain_service TEXT NOT NULL,
-            keychain_account TEXT NOT NULL,
-            FOREIGN KEY(project_id) REFERENCES projects(id) ON DELETE CASCADE
-        )",
-        [],
+    ensure_column(
+        &conn,
+        "projects",
+        "policy",
+        "ALTER TABLE projects ADD COLUMN policy TEXT NULL",
+    )?;
+    ensure_column(
+        &conn,
+        "projects",
+        "exportable",
+        "ALTER TABLE projects ADD COLUMN exportable INTEGER NOT NULL DEFAULT 1",
+    )?;
+    ensure_column(
+        &conn,
+        "projects",
+        "wasm_module",
+        "ALTER TABLE projects ADD COLUMN wasm_module TEXT NULL",
     )?;
 
+    conn.execute(KEYS_TABLE, [])?;
+
     ensure_column(
         &conn,
         "keys",
@@ -87,20 +161,118 @@ pub(super) fn init_sqlite(path: &Path) -> anyhow::Result<()> {
         "tags",
         "ALTER TABLE keys ADD COLUMN tags TEXT NULL",
     )?;
+    ensure_column(
+        &conn,
+        "keys",
+        "fingerprint",
+        "ALTER TABLE keys ADD COLUMN fingerprint TEXT NULL",
+    )?;
+    ensure_column(
+        &conn,
+        "keys",
+        "usage",
+        "ALTER TABLE keys ADD COLUMN usage TEXT NOT NULL DEFAULT 'both'",
+    )?;
+
+    conn.execute(TOKENS_TABLE, [])?;
+
+    conn.execute(JWKS_TABLE, [])?;
+    ensure_column(
+        &conn,
+        "jwks",
+        "source_url",
+        "ALTER TABLE jwks ADD COLUMN source_url TEXT NULL",
+    )?;
+
+    conn.execute(WEBHOOKS_TABLE, [])?;
+    conn.execute(REVOCATIONS_TABLE, [])?;
 
     conn.execute(
-        "CREATE TABLE IF NOT EXISTS tokens (
+        "CREATE TABLE IF NOT EXISTS history (
+            id TEXT PRIMARY KEY,
+            created_at INTEGER NOT NULL,
+            operation TEXT NOT NULL,
+            ok INTEGER NOT NULL,
+            params TEXT NOT NULL,
+            result TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    // Audit entries outlive the projects they mention, so there is no foreign key.
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS audit (
             id TEXT PRIMARY KEY,
-            project_id TEXT NOT NULL,
-            name TEXT NOT NULL,
             created_at INTEGER NOT NULL,
-            keychain_service TEXT NOT NULL,
-            keychain_account TEXT NOT NULL,
-            FOREIGN KEY(project_id) REFERENCES projects(id) ON DELETE CASCADE
+            action TEXT NOT NULL,
+            project_id TEXT NULL,
+            detail TEXT NOT NULL
         )",
         [],
     )?;
 
+    // Vaults created before the foreign keys were declared need their tables rebuilt; orphans
+    // have to go first or the copy into the constrained table would fail.
+    let orphans = remove_orphans(&conn)?;
+    ensure_foreign_key(&conn, "keys", KEYS_TABLE)?;
+    ensure_foreign_key(&conn, "tokens", TOKENS_TABLE)?;
+    ensure_foreign_key(&conn, "jwks", JWKS_TABLE)?;
+
+    Ok(orphans)
+}
+
+/// Deletes key, token, JWKS, webhook and revocation rows whose project no longer exists and
+/// clears default keys that point at missing keys.
+fn remove_orphans(conn: &Connection) -> anyhow::Result<Vec<(String, String)>> {
+    let mut stmt = conn.prepare(
+        "SELECT keychain_service, keychain_account FROM keys WHERE project_id NOT IN (SELECT id FROM projects) \
+         UNION ALL \
+         SELECT keychain_service, keychain_account FROM tokens WHERE project_id NOT IN (SELECT id FROM projects) \
+         UNION ALL \
+         SELECT keychain_service, keychain_account FROM webhooks WHERE project_id NOT IN (SELECT id FROM projects)",
+    )?;
+    let orphans = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    for table in ["keys", "tokens", "jwks", "webhooks", "revocations"] {
+        conn.execute(
+            &format!("DELETE FROM {table} WHERE project_id NOT IN (SELECT id FROM projects)"),
+            [],
+        )?;
+    }
+    conn.execute(
+        "UPDATE projects SET default_key_id = NULL WHERE default_key_id IS NOT NULL AND default_key_id NOT IN (SELECT id FROM keys)",
+        [],
+    )?;
+    Ok(orphans)
+}
+
+/// Rebuilds `table` with `create_sql` when it has no foreign key (SQLite cannot add one in
+/// place), copying every column over.
+fn ensure_foreign_key(conn: &Connection, table: &str, create_sql: &str) -> anyhow::Result<()> {
+    let fk_count: i64 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM pragma_foreign_key_list('{table}')"),
+        [],
+        |row| row.get(0),
+    )?;
+    if fk_count > 0 {
+        return Ok(());
+    }
+    let columns = {
+        let mut stmt = conn.prepare(&format!("SELECT name FROM pragma_table_info('{table}')"))?;
+        let names = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        names.join(", ")
+    };
+    conn.execute_batch(&format!(
+        "BEGIN;
+         ALTER TABLE {table} RENAME TO {table}_without_fk;
+         {create_sql};
+         INSERT INTO {table} ({columns}) SELECT {columns} FROM {table}_without_fk;
+         DROP TABLE {table}_without_fk;
+         COMMIT;"
+    ))?;
     Ok(())
 }
 
@@ -162,6 +334,16 @@ mod tests {
             .collect::<Result<Vec<_>, _>>()
             .unwrap();
         assert!(token_cols.contains(&"keychain_account".to_string()));
+
+        let jwks_cols: Vec<String> = conn
+            .prepare("SELECT name FROM pragma_table_info('jwks')")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert!(jwks_cols.contains(&"document".to_string()));
+        assert!(jwks_cols.contains(&"source_url".to_string()));
     }
 
     #[test]
@@ -179,4 +361,90 @@ mod tests {
         let err = init_sqlite(&path).expect_err("expected legacy schema error");
         assert!(err.to_string().contains("older vault schema"));
     }
+
+    #[test]
+    fn init_sqlite_adds_foreign_keys_and_removes_orphans() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("vault.sqlite3");
+        let conn = Connection::open(&path).expect("open sqlite");
+        conn.execute_batch(
+            "CREATE TABLE projects (id TEXT PRIMARY KEY, name TEXT NOT NULL, created_at INTEGER NOT NULL, default_key_id TEXT NULL);
+             CREATE TABLE keys (id TEXT PRIMARY KEY, project_id TEXT NOT NULL, name TEXT NOT NULL, kind TEXT NOT NULL, created_at INTEGER NOT NULL, keychain_service TEXT NOT NULL, keychain_account TEXT NOT NULL);
+             CREATE TABLE tokens (id TEXT PRIMARY KEY, project_id TEXT NOT NULL, name TEXT NOT NULL, created_at INTEGER NOT NULL, keychain_service TEXT NOT NULL, keychain_account TEXT NOT NULL);
+             INSERT INTO projects VALUES ('p1', 'api', 0, 'gone');
+             INSERT INTO keys VALUES ('k1', 'p1', 'kept', 'hmac', 0, 'svc', 'key:k1');
+             INSERT INTO keys VALUES ('k2', 'missing', 'orphan', 'hmac', 0, 'svc', 'key:k2');
+             INSERT INTO tokens VALUES ('t1', 'missing', 'orphan', 0, 'svc', 'token:t1');",
+        )
+        .expect("create legacy tables");
+        drop(conn);
+
+        let orphans = init_sqlite(&path).expect("init sqlite");
+        assert_eq!(
+            orphans,
+            vec![
+                ("svc".to_string(), "key:k2".to_string()),
+                ("svc".to_string(), "token:t1".to_string()),
+            ]
+        );
+
+        let conn = open_db(&path).expect("open sqlite");
+        for table in ["keys", "tokens", "jwks"] {
+            let fks: i64 = conn
+                .query_row(
+                    &format!("SELECT COUNT(*) FROM pragma_foreign_key_list('{table}')"),
+                    [],
+                    |row| row.get(0),
+                )
+                .unwrap();
+            assert_eq!(fks, 1, "{table} should reference projects");
+        }
+        let key_ids: Vec<String> = conn
+            .prepare("SELECT id FROM keys")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(key_ids, vec!["k1".to_string()]);
+        let default_key: Option<String> = conn
+            .query_row("SELECT default_key_id FROM projects", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(default_key, None);
+
+        assert!(init_sqlite(&path).expect("second init").is_empty());
+    }
+
+    #[test]
+    fn open_db_enforces_foreign_keys() {
+        let dir = TempDir::new().expect("temp dir");
+        let path = dir.path().join("vault.sqlite3");
+        init_sqlite(&path).expect("init sqlite");
+        let conn = open_db(&path).expect("open sqlite");
+
+        let err = conn.execute(
+            "INSERT INTO keys (id, project_id, name, kind, created_at, keychain_service, keychain_account) \
+             VALUES ('k1', 'missing', 'k', 'hmac', 0, 'svc', 'acc')",
+            [],
+        );
+        assert!(err.is_err(), "key without a project must be rejected");
+
+        conn.execute_batch(
+            "INSERT INTO projects (id, name, created_at) VALUES ('p1', 'api', 0);
+             INSERT INTO keys (id, project_id, name, kind, created_at, keychain_service, keychain_account)
+               VALUES ('k1', 'p1', 'k', 'hmac', 0, 'svc', 'acc');
+             INSERT INTO tokens (id, project_id, name, created_at, keychain_service, keychain_account)
+               VALUES ('t1', 'p1', 't', 0, 'svc', 'tok');
+             DELETE FROM projects WHERE id = 'p1';",
+        )
+        .expect("cascade delete");
+        let remaining: i64 = conn
+            .query_row(
+                "SELECT (SELECT COUNT(*) FROM keys) + (SELECT COUNT(*) FROM tokens)",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(remaining, 0);
+    }
 }
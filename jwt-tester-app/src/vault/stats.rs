@@ -0,0 +1,156 @@
+#[cfg(feature = "vault")]
+use super::backup::BACKUP_DIR;
+use super::store::Vault;
+#[cfg(feature = "vault")]
+use super::store::VaultInner;
+use serde::Serialize;
+use std::collections::BTreeMap;
+#[cfg(feature = "vault")]
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Entry counts for one project. `oldest`/`newest` span its keys, tokens and JWKS documents.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectStats {
+    pub id: String,
+    pub name: String,
+    pub keys: usize,
+    pub keys_by_kind: BTreeMap<String, usize>,
+    pub tokens: usize,
+    pub jwks: usize,
+    pub oldest: Option<i64>,
+    pub newest: Option<i64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct StatsTotals {
+    pub projects: usize,
+    pub keys: usize,
+    pub keys_by_kind: BTreeMap<String, usize>,
+    pub tokens: usize,
+    pub jwks: usize,
+}
+
+/// Bytes on disk. `keychain_bytes` is only known for the file backend; OS keychains keep their
+/// entries out of reach.
+#[derive(Debug, Clone, Serialize)]
+pub struct StorageStats {
+    pub db_path: PathBuf,
+    /// The SQLite database plus its `-wal`/`-shm` files.
+    pub db_bytes: u64,
+    pub keychain_backend: &'static str,
+    pub keychain_dir: Option<PathBuf>,
+    pub keychain_bytes: Option<u64>,
+    pub backups_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VaultStats {
+    pub projects: Vec<ProjectStats>,
+    pub totals: StatsTotals,
+    /// `None` for `--no-persist` vaults.
+    pub storage: Option<StorageStats>,
+}
+
+impl Vault {
+    /// Per-project totals and storage sizes. Reads metadata only; no secret is loaded.
+    pub fn stats(&self) -> anyhow::Result<VaultStats> {
+        let keys = self.list_keys(None)?;
+        let tokens = self.list_tokens(None)?;
+        let jwks = self.list_jwks(None)?;
+        let mut totals = StatsTotals::default();
+        let mut projects = Vec::new();
+        for project in self.list_projects()? {
+            let project_keys: Vec<_> = keys.iter().filter(|k| k.project_id == project.id).collect();
+            let project_tokens: Vec<_> = tokens
+                .iter()
+                .filter(|t| t.project_id == project.id)
+                .collect();
+            let project_jwks: Vec<_> = jwks.iter().filter(|j| j.project_id == project.id).collect();
+            let created = project_keys
+                .iter()
+                .map(|k| k.created_at)
+                .chain(project_tokens.iter().map(|t| t.created_at))
+                .chain(project_jwks.iter().map(|j| j.created_at));
+            let mut keys_by_kind = BTreeMap::new();
+            for key in &project_keys {
+                *keys_by_kind.entry(key.kind.clone()).or_insert(0) += 1;
+                *totals.keys_by_kind.entry(key.kind.clone()).or_insert(0) += 1;
+            }
+            totals.projects += 1;
+            totals.keys += project_keys.len();
+            totals.tokens += project_tokens.len();
+            totals.jwks += project_jwks.len();
+            projects.push(ProjectStats {
+                id: project.id,
+                name: project.name,
+                keys: project_keys.len(),
+                keys_by_kind,
+                tokens: project_tokens.len(),
+                jwks: project_jwks.len(),
+                oldest: created.clone().min(),
+                newest: created.max(),
+            });
+        }
+        Ok(VaultStats {
+            projects,
+            totals,
+            storage: self.storage_stats(),
+        })
+    }
+
+    #[cfg(not(feature = "vault"))]
+    fn storage_stats(&self) -> Option<StorageStats> {
+        None
+    }
+
+    #[cfg(feature = "vault")]
+    fn storage_stats(&self) -> Option<StorageStats> {
+        let VaultInner::Sqlite {
+            db_path, keychain, ..
+        } = &self.inner
+        else {
+            return None;
+        };
+        let db_bytes = ["", "-wal", "-shm"]
+            .iter()
+            .map(|suffix| {
+                let mut path = db_path.clone().into_os_string();
+                path.push(suffix);
+                file_size(Path::new(&path))
+            })
+            .sum();
+        let keychain_dir = keychain.storage_dir().map(Path::to_path_buf);
+        let backups_bytes = db_path
+            .parent()
+            .map_or(0, |dir| dir_size(&dir.join(BACKUP_DIR)));
+        Some(StorageStats {
+            db_path: db_path.clone(),
+            db_bytes,
+            keychain_backend: keychain.backend(),
+            keychain_bytes: keychain_dir.as_deref().map(dir_size),
+            keychain_dir,
+            backups_bytes,
+        })
+    }
+}
+
+#[cfg(feature = "vault")]
+fn file_size(path: &Path) -> u64 {
+    std::fs::metadata(path).map_or(0, |meta| meta.len())
+}
+
+#[cfg(feature = "vault")]
+/// Total size of the files directly in `dir` (both the keychain and backups are flat); 0 when
+/// the directory does not exist yet.
+fn dir_size(dir: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|meta| meta.is_file())
+        .map(|meta| meta.len())
+        .sum()
+}
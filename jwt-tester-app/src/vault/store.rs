@@ -1,27 +1,52 @@
+use super::audit::AuditEntry;
 use super::helpers::default_data_dir;
+use super::history::HistoryEntry;
+#[cfg(feature = "vault")]
 use super::keychain::KeychainStore;
+#[cfg(feature = "vault")]
 use super::keychain::OsKeychain;
+#[cfg(feature = "vault")]
 use super::keychain_file::FileKeychain;
+#[cfg(feature = "vault")]
 use super::sqlite::init_sqlite;
-use super::types::{KeyEntry, ProjectEntry, TokenEntry};
+use super::types::{JwksEntry, KeyEntry, ProjectEntry, RevocationEntry, TokenEntry, WebhookEntry};
+use crate::vault_export::{BundleUnlock, ExportBundle};
 use std::collections::HashMap;
+#[cfg(feature = "vault")]
 use std::path::Path;
 use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, OnceLock};
 
+#[cfg(feature = "vault")]
 const DEFAULT_KEYCHAIN_SERVICE: &str = "jwt-tester";
+#[cfg(feature = "vault")]
 const KEYCHAIN_BACKEND_ENV: &str = "JWT_TESTER_KEYCHAIN_BACKEND";
+#[cfg(feature = "vault")]
 const KEYCHAIN_PASSPHRASE_ENV: &str = "JWT_TESTER_KEYCHAIN_PASSPHRASE";
+#[cfg(feature = "vault")]
 const KEYCHAIN_DIR_ENV: &str = "JWT_TESTER_KEYCHAIN_DIR";
+#[cfg(feature = "vault")]
 const KEYCHAIN_DOCKER_ENV: &str = "JWT_TESTER_DOCKER";
+#[cfg(feature = "vault")]
 const KEYCHAIN_DOCKER_TEST_ENV: &str = "JWT_TESTER_DOCKER_TEST";
 
+/// Set once from `--vault-seed`; every `--no-persist` vault opened afterwards shares this state
+/// instead of starting empty.
+static SEEDED_MEMORY: OnceLock<Arc<Mutex<MemoryState>>> = OnceLock::new();
+
 #[derive(Debug, Clone)]
 pub struct VaultConfig {
     pub no_persist: bool,
     pub data_dir: Option<PathBuf>,
 }
 
+impl VaultConfig {
+    /// The data directory the vault would use, for other per-user state (e.g. caches).
+    pub fn resolved_data_dir(&self) -> Option<PathBuf> {
+        resolve_data_dir(self).ok()
+    }
+}
+
 #[derive(Clone)]
 pub struct Vault {
     pub(super) inner: VaultInner,
@@ -32,6 +57,7 @@ pub(super) enum VaultInner {
     Memory {
         state: Arc<Mutex<MemoryState>>,
     },
+    #[cfg(feature = "vault")]
     Sqlite {
         db_path: PathBuf,
         keychain_service: String,
@@ -44,28 +70,86 @@ pub(super) struct MemoryState {
     pub(super) projects: Vec<ProjectEntry>,
     pub(super) keys: Vec<KeyEntry>,
     pub(super) tokens: Vec<TokenEntry>,
+    pub(super) jwks: Vec<JwksEntry>,
+    pub(super) webhooks: Vec<WebhookEntry>,
+    pub(super) revocations: Vec<RevocationEntry>,
     pub(super) key_material: HashMap<String, String>,
     pub(super) token_material: HashMap<String, String>,
+    pub(super) jwks_documents: HashMap<String, String>,
+    pub(super) webhook_secrets: HashMap<String, String>,
+    pub(super) history: Vec<HistoryEntry>,
+    pub(super) audit: Vec<AuditEntry>,
 }
 
 impl Vault {
     pub fn open(cfg: VaultConfig) -> anyhow::Result<Self> {
         if cfg.no_persist {
+            let state = SEEDED_MEMORY.get().cloned().unwrap_or_default();
             return Ok(Vault {
-                inner: VaultInner::Memory {
-                    state: Arc::new(Mutex::new(MemoryState::default())),
-                },
+                inner: VaultInner::Memory { state },
             });
         }
 
+        Self::open_persistent(cfg)
+    }
+
+    #[cfg(feature = "vault")]
+    fn open_persistent(cfg: VaultConfig) -> anyhow::Result<Self> {
         let data_dir = resolve_data_dir(&cfg)?;
         let keychain_service = std::env::var("JWT_TESTER_KEYCHAIN_SERVICE")
             .unwrap_or_else(|_| DEFAULT_KEYCHAIN_SERVICE.to_string());
-        let keychain = resolve_keychain(&data_dir)?;
+        let keychain = resolve_keychain(&data_dir, None)?;
         Self::open_with_data_dir(keychain, keychain_service, data_dir)
     }
 
-    #[cfg(test)]
+    #[cfg(not(feature = "vault"))]
+    fn open_persistent(_cfg: VaultConfig) -> anyhow::Result<Self> {
+        anyhow::bail!(
+            "the persistent vault requires a build with the `vault` feature; use --no-persist for \
+             an in-memory vault, or direct key inputs"
+        )
+    }
+
+    /// Opens the copy made by `relocate`: same keychain service, and the file keychain read from
+    /// `keychain_dir` when it moved along with the data.
+    #[cfg(feature = "vault")]
+    pub(super) fn open_relocated(
+        &self,
+        data_dir: PathBuf,
+        keychain_dir: Option<PathBuf>,
+    ) -> anyhow::Result<Self> {
+        let VaultInner::Sqlite {
+            keychain_service,
+            keychain,
+            ..
+        } = &self.inner
+        else {
+            anyhow::bail!("in-memory vaults cannot be relocated");
+        };
+        let keychain = match keychain_dir {
+            Some(root) => resolve_keychain(&data_dir, Some(root))?,
+            None => keychain.clone(),
+        };
+        Self::open_with_data_dir(keychain, keychain_service.clone(), data_dir)
+    }
+
+    /// Loads `bundle` into the in-memory vault that later `--no-persist` opens share. Nothing is
+    /// written to disk or to a keychain. A process can be seeded once.
+    pub fn seed_memory(bundle: &ExportBundle, unlock: &BundleUnlock) -> anyhow::Result<Self> {
+        let state = Arc::new(Mutex::new(MemoryState::default()));
+        let vault = Vault {
+            inner: VaultInner::Memory {
+                state: state.clone(),
+            },
+        };
+        vault.import_bundle_with(bundle, unlock, false)?;
+        SEEDED_MEMORY
+            .set(state)
+            .map_err(|_| anyhow::anyhow!("the in-memory vault is already seeded"))?;
+        Ok(vault)
+    }
+
+    #[cfg(all(test, feature = "vault"))]
     pub(crate) fn open_with(
         cfg: VaultConfig,
         keychain: Arc<dyn KeychainStore>,
@@ -83,6 +167,7 @@ impl Vault {
         Self::open_with_data_dir(keychain, keychain_service, data_dir)
     }
 
+    #[cfg(feature = "vault")]
     fn open_with_data_dir(
         keychain: Arc<dyn KeychainStore>,
         keychain_service: String,
@@ -90,7 +175,10 @@ impl Vault {
     ) -> anyhow::Result<Self> {
         std::fs::create_dir_all(&data_dir)?;
         let db_path = data_dir.join("vault.sqlite3");
-        init_sqlite(&db_path)?;
+        // Secrets of orphaned rows are best-effort: the rows are already gone either way.
+        for (service, account) in init_sqlite(&db_path)? {
+            let _ = keychain.delete_password(&service, &account);
+        }
 
         Ok(Vault {
             inner: VaultInner::Sqlite {
@@ -109,14 +197,20 @@ fn resolve_data_dir(cfg: &VaultConfig) -> anyhow::Result<PathBuf> {
         .ok_or_else(|| anyhow::anyhow!("Unable to determine default data dir"))
 }
 
-fn resolve_keychain(data_dir: &Path) -> anyhow::Result<Arc<dyn KeychainStore>> {
+/// `root` overrides `JWT_TESTER_KEYCHAIN_DIR` for the file backend.
+#[cfg(feature = "vault")]
+fn resolve_keychain(
+    data_dir: &Path,
+    root: Option<PathBuf>,
+) -> anyhow::Result<Arc<dyn KeychainStore>> {
     let backend = std::env::var(KEYCHAIN_BACKEND_ENV).unwrap_or_else(|_| "os".to_string());
     let passphrase = std::env::var(KEYCHAIN_PASSPHRASE_ENV).ok();
-    let root = std::env::var(KEYCHAIN_DIR_ENV).ok().map(PathBuf::from);
+    let root = root.or_else(|| std::env::var(KEYCHAIN_DIR_ENV).ok().map(PathBuf::from));
     let allow_file_backend = is_docker_environment();
     resolve_keychain_from(&backend, passphrase, root, data_dir, allow_file_backend)
 }
 
+#[cfg(feature = "vault")]
 fn resolve_keychain_from(
     backend: &str,
     passphrase: Option<String>,
@@ -145,10 +239,12 @@ fn resolve_keychain_from(
     }
 }
 
+#[cfg(feature = "vault")]
 fn is_docker_environment() -> bool {
     is_docker_environment_with(Path::new("/.dockerenv"))
 }
 
+#[cfg(feature = "vault")]
 fn is_docker_environment_with(marker: &Path) -> bool {
     let docker_env = env_flag_set(KEYCHAIN_DOCKER_ENV);
     if !docker_env {
@@ -166,13 +262,14 @@ fn is_docker_environment_with(marker: &Path) -> bool {
     false
 }
 
+#[cfg(feature = "vault")]
 fn env_flag_set(name: &str) -> bool {
     std::env::var(name)
         .map(|value| value == "1" || value.eq_ignore_ascii_case("true"))
         .unwrap_or(false)
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "vault"))]
 mod tests {
     use super::{is_docker_environment_with, resolve_keychain_from};
     use std::fs;
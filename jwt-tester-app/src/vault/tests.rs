@@ -1,4 +1,8 @@
-use super::{KeyEntryInput, MemoryKeychain, ProjectInput, TokenEntryInput, Vault, VaultConfig};
+use super::{
+    ConflictStrategy, FamilyPolicy, HistoryInput, JwksEntryInput, KeyEntryInput, KeyUsage,
+    MemoryKeychain, ProjectInput, TokenEntryInput, Vault, VaultConfig, WebhookEntryInput,
+};
+use crate::vault_export::{BundleProtection, BundleUnlock};
 use std::sync::Arc;
 use tempfile::TempDir;
 
@@ -306,6 +310,120 @@ fn sqlite_roundtrip_persists_metadata() {
     assert_eq!(token.project_id, project.id);
 }
 
+#[test]
+fn sqlite_project_policy_persists_and_clones() {
+    let (_dir, vault, _keychain) = sqlite_vault();
+    let project = add_project(&vault, "alpha");
+    let mut policy = std::collections::BTreeMap::new();
+    policy.insert(
+        "HS".to_string(),
+        FamilyPolicy {
+            required_headers: vec!["kid".to_string()],
+            required_claims: Vec::new(),
+            allowed_issuers: vec!["https://idp.internal".to_string()],
+        },
+    );
+    vault
+        .set_project_policy(&project.id, &policy)
+        .expect("set policy");
+    let stored = vault
+        .find_project_by_id(&project.id)
+        .expect("find project")
+        .expect("project");
+    assert_eq!(stored.policy, policy);
+
+    let cloned = vault.clone_project(&project.id, "beta").expect("clone");
+    let copy = vault.find_project("beta").expect("find").expect("clone");
+    assert_eq!(cloned.project.policy, policy);
+    assert_eq!(copy.policy, policy);
+
+    vault
+        .set_project_policy(&project.id, &Default::default())
+        .expect("clear policy");
+    let cleared = vault.find_project("alpha").expect("find").expect("project");
+    assert!(cleared.policy.is_empty());
+}
+
+#[test]
+fn jwks_documents_persist_export_and_cascade() {
+    let (_dir, vault, _keychain) = sqlite_vault();
+    let project = add_project(&vault, "alpha");
+    let document = r#"{"keys":[{"kty":"oct","kid":"a","k":"c2VjcmV0"},{"kty":"oct","k":"eA"}]}"#;
+    let entry = vault
+        .add_jwks(JwksEntryInput {
+            project_id: project.id.clone(),
+            name: " ".to_string(),
+            document: document.to_string(),
+            source_url: Some("https://idp.example/jwks".to_string()),
+        })
+        .expect("add jwks");
+    assert!(entry.name.starts_with("jwks-"));
+    assert_eq!(entry.kids, vec!["a".to_string()]);
+    assert!(vault
+        .add_jwks(JwksEntryInput {
+            project_id: project.id.clone(),
+            name: "bad".to_string(),
+            document: r#"{"keys":"nope"}"#.to_string(),
+            source_url: None,
+        })
+        .is_err());
+
+    let listed = vault.list_jwks(Some(&project.id)).expect("list jwks");
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].kids, entry.kids);
+    assert_eq!(vault.get_jwks_document(&entry.id).unwrap(), document);
+
+    let bundle = vault.export_bundle("passphrase").expect("export bundle");
+    let other = memory_vault();
+    other
+        .import_bundle(&bundle, "passphrase", false)
+        .expect("import bundle");
+    let imported = other.list_jwks(None).expect("list jwks");
+    assert_eq!(imported.len(), 1);
+    assert_eq!(other.get_jwks_document(&imported[0].id).unwrap(), document);
+
+    let updated = vault
+        .update_jwks_document(&entry.id, r#"{"keys":[{"kty":"oct","kid":"b","k":"eA"}]}"#)
+        .expect("update jwks");
+    assert_eq!(updated.kids, vec!["b".to_string()]);
+    assert_eq!(
+        updated.source_url.as_deref(),
+        Some("https://idp.example/jwks")
+    );
+
+    vault.delete_project(&project.id).expect("delete project");
+    assert!(vault.list_jwks(None).expect("list jwks").is_empty());
+}
+
+#[test]
+fn jwks_entries_record_key_use_ops_and_alg() {
+    let (_dir, vault, _keychain) = sqlite_vault();
+    let project = add_project(&vault, "alpha");
+    let document = r#"{"keys":[
+        {"kty":"RSA","kid":"sig","use":"sig","alg":"RS256","n":"AQAB","e":"AQAB"},
+        {"kty":"RSA","kid":"enc","use":"enc","n":"AQAB","e":"AQAB"},
+        {"kty":"oct","kid":"wrap","key_ops":["wrapKey"],"k":"eA"}
+    ]}"#;
+    let entry = vault
+        .add_jwks(JwksEntryInput {
+            project_id: project.id.clone(),
+            name: "mixed".to_string(),
+            document: document.to_string(),
+            source_url: None,
+        })
+        .expect("add jwks");
+    let listed = vault.list_jwks(Some(&project.id)).expect("list jwks");
+    assert_eq!(listed[0].keys, entry.keys);
+    let keys = &listed[0].keys;
+    assert_eq!(keys.len(), 3);
+    assert_eq!(keys[0].alg.as_deref(), Some("RS256"));
+    assert!(keys[0].may_verify());
+    assert_eq!(keys[1].key_use.as_deref(), Some("enc"));
+    assert!(!keys[1].may_verify());
+    assert_eq!(keys[2].key_ops, Some(vec!["wrapKey".to_string()]));
+    assert!(!keys[2].may_verify());
+}
+
 #[test]
 fn sqlite_delete_project_cleans_keychain() {
     let (_dir, vault, keychain) = sqlite_vault();
@@ -331,7 +449,563 @@ fn sqlite_delete_project_cleans_keychain() {
 
     assert!(keychain.len() >= 2);
     vault.delete_project(&project.id).expect("delete project");
-    assert_eq!(keychain.len(), 0);
+    // Only the backup key taken before the delete remains.
+    assert_eq!(keychain.len(), 1);
     let _ = key;
     let _ = token;
 }
+
+#[test]
+fn sqlite_webhooks_keep_secrets_in_keychain_and_go_with_their_project() {
+    let (_dir, vault, keychain) = sqlite_vault();
+    let project = add_project(&vault, "alpha");
+    let input = |url: &str, events: &[&str]| WebhookEntryInput {
+        project_id: project.id.clone(),
+        url: url.to_string(),
+        events: events.iter().map(|e| e.to_string()).collect(),
+        secret: "hook-secret".to_string(),
+    };
+
+    let err = vault
+        .add_webhook(input("ftp://inventory.test/hook", &[]))
+        .expect_err("non-http url");
+    assert!(err.to_string().contains("http"));
+    let err = vault
+        .add_webhook(input("https://inventory.test/hook", &["key.renamed"]))
+        .expect_err("unknown event");
+    assert!(err.to_string().contains("key.renamed"));
+
+    let hook = vault
+        .add_webhook(input(
+            " https://inventory.test/hook ",
+            &["key.added", "token.added", "key.added"],
+        ))
+        .expect("add webhook");
+    assert_eq!(hook.url, "https://inventory.test/hook");
+    assert_eq!(hook.events, vec!["key.added", "token.added"]);
+    assert_eq!(keychain.len(), 1);
+    let listed = vault.list_webhooks(Some(&project.id)).expect("list");
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].events, hook.events);
+
+    let report = vault.delete_project(&project.id).expect("delete project");
+    assert_eq!(report.webhooks, 1);
+    assert!(vault.list_webhooks(None).expect("list").is_empty());
+    // Only the backup key taken before the delete remains.
+    assert_eq!(keychain.len(), 1);
+}
+
+#[test]
+fn sqlite_exports_skip_non_exportable_keys_unless_included_and_audited() {
+    let (_dir, vault, _keychain) = sqlite_vault();
+    let open = add_project(&vault, "open");
+    let local = add_project(&vault, "local");
+    vault
+        .set_project_exportable(&local.id, false)
+        .expect("set exportable");
+    let add_key = |project_id: &str, name: &str| {
+        vault
+            .add_key(KeyEntryInput {
+                project_id: project_id.to_string(),
+                name: name.to_string(),
+                kind: "hmac".to_string(),
+                secret: format!("{name}-secret"),
+                kid: None,
+                description: None,
+                tags: Vec::new(),
+            })
+            .expect("add key")
+    };
+    add_key(&open.id, "shared");
+    let kept = add_key(&local.id, "kept");
+    vault
+        .set_default_key(&local.id, Some(&kept.id))
+        .expect("set default");
+    let protection = BundleProtection::Passphrase("passphrase".to_string());
+    let unlock = BundleUnlock::Passphrase("passphrase".to_string());
+
+    let report = vault
+        .export_bundle_with(&protection, false)
+        .expect("export");
+    assert_eq!(report.non_exportable_keys.len(), 1);
+    assert!(vault.list_audit().expect("audit").is_empty());
+    let other = memory_vault();
+    other
+        .import_bundle_with(&report.bundle, &unlock, false)
+        .expect("import");
+    let keys = other.list_keys(None).expect("keys");
+    assert_eq!(keys.len(), 1);
+    assert_eq!(keys[0].name, "shared");
+    let imported = other.find_project_by_name("local").expect("find").unwrap();
+    assert!(!imported.exportable);
+    assert_eq!(imported.default_key_id, None);
+
+    let report = vault
+        .export_project_bundle_with(&local.id, &protection, true)
+        .expect("export project");
+    let other = memory_vault();
+    other
+        .import_bundle_with(&report.bundle, &unlock, false)
+        .expect("import");
+    assert_eq!(other.list_keys(None).expect("keys").len(), 1);
+    let audit = vault.list_audit().expect("audit");
+    assert_eq!(audit.len(), 1);
+    assert_eq!(audit[0].action, "export.non_exportable");
+    assert_eq!(audit[0].project_id.as_deref(), Some(local.id.as_str()));
+    assert_eq!(audit[0].detail["keys"][0]["name"], "kept");
+}
+
+#[test]
+fn sqlite_key_usage_persists_through_clone_and_export() {
+    let (_dir, vault, _keychain) = sqlite_vault();
+    let project = add_project(&vault, "alpha");
+    let key = vault
+        .add_key(KeyEntryInput {
+            project_id: project.id.clone(),
+            name: "prod-public".to_string(),
+            kind: "hmac".to_string(),
+            secret: "secret".to_string(),
+            kid: None,
+            description: None,
+            tags: Vec::new(),
+        })
+        .expect("add key");
+    assert_eq!(key.usage, KeyUsage::Both);
+    let updated = vault
+        .set_key_usage(&key.id, KeyUsage::Verify)
+        .expect("set usage");
+    assert_eq!(updated.usage, KeyUsage::Verify);
+    assert_eq!(
+        vault.list_keys(None).expect("keys")[0].usage,
+        KeyUsage::Verify
+    );
+
+    vault.clone_project(&project.id, "beta").expect("clone");
+    let beta = vault.find_project_by_name("beta").expect("find").unwrap();
+    assert_eq!(
+        vault.list_keys(Some(&beta.id)).expect("keys")[0].usage,
+        KeyUsage::Verify
+    );
+
+    let protection = BundleProtection::Passphrase("passphrase".to_string());
+    let bundle = vault
+        .export_bundle_with(&protection, false)
+        .expect("export")
+        .bundle;
+    let (_other_dir, other, _other_keychain) = sqlite_vault();
+    other
+        .import_bundle_with(
+            &bundle,
+            &BundleUnlock::Passphrase("passphrase".to_string()),
+            false,
+        )
+        .expect("import");
+    let keys = other.list_keys(None).expect("keys");
+    assert_eq!(keys.len(), 2);
+    assert!(keys.iter().all(|k| k.usage == KeyUsage::Verify));
+}
+
+#[test]
+fn sqlite_delete_project_reports_keychain_failures_after_commit() {
+    let (_dir, vault, keychain) = sqlite_vault();
+    let seed = |name: &str| {
+        let project = add_project(&vault, name);
+        vault
+            .add_key(KeyEntryInput {
+                project_id: project.id.clone(),
+                name: "k1".to_string(),
+                kind: "hmac".to_string(),
+                secret: "secret".to_string(),
+                kid: None,
+                description: None,
+                tags: Vec::new(),
+            })
+            .expect("add key");
+        vault
+            .add_token(TokenEntryInput {
+                project_id: project.id.clone(),
+                name: "t1".to_string(),
+                token: "token".to_string(),
+            })
+            .expect("add token");
+        project
+    };
+
+    // A transient failure is retried away.
+    let alpha = seed("alpha");
+    keychain.fail_deletes(1);
+    let report = vault.delete_project(&alpha.id).expect("delete project");
+    assert_eq!((report.keys, report.tokens), (1, 1));
+    assert!(report.keychain_failures.is_empty());
+
+    // A keychain that keeps failing leaves orphaned secrets, but the rows are still gone.
+    let beta = seed("beta");
+    let before = keychain.len();
+    keychain.fail_deletes(usize::MAX);
+    let report = vault.delete_project(&beta.id).expect("delete project");
+    keychain.fail_deletes(0);
+    assert_eq!(report.keychain_failures.len(), 2);
+    assert!(report.keychain_failures[0].error.contains("locked"));
+    assert_eq!(keychain.len(), before);
+    assert!(vault.find_project_by_id(&beta.id).unwrap().is_none());
+    assert!(vault.list_keys(Some(&beta.id)).unwrap().is_empty());
+    assert!(vault.list_tokens(Some(&beta.id)).unwrap().is_empty());
+}
+
+fn add_hmac_key(vault: &Vault, project_id: &str, name: &str, kid: Option<&str>, secret: &str) {
+    vault
+        .add_key(KeyEntryInput {
+            project_id: project_id.to_string(),
+            name: name.to_string(),
+            kind: "hmac".to_string(),
+            secret: secret.to_string(),
+            kid: kid.map(|k| k.to_string()),
+            description: None,
+            tags: Vec::new(),
+        })
+        .expect("add key");
+}
+
+fn age_bundle(vault: &Vault) -> (crate::vault_export::ExportBundle, BundleUnlock) {
+    use age::secrecy::ExposeSecret;
+
+    let identity = age::x25519::Identity::generate();
+    let bundle = vault
+        .export_bundle_with(
+            &BundleProtection::Age(vec![identity.to_public().to_string()]),
+            false,
+        )
+        .expect("export")
+        .bundle;
+    let unlock = BundleUnlock::AgeIdentity(identity.to_string().expose_secret().to_string());
+    (bundle, unlock)
+}
+
+fn merge_fixture() -> (Vault, Vault) {
+    let source = memory_vault();
+    let shared = add_project(&source, "shared");
+    add_hmac_key(&source, &shared.id, "primary", Some("kid-1"), "incoming-1");
+    add_hmac_key(&source, &shared.id, "extra", None, "incoming-2");
+    let fresh = add_project(&source, "fresh");
+    add_hmac_key(&source, &fresh.id, "only", None, "incoming-3");
+
+    let target = memory_vault();
+    let existing = add_project(&target, "shared");
+    add_hmac_key(&target, &existing.id, "current", Some("kid-1"), "local-1");
+    (source, target)
+}
+
+#[test]
+fn merge_skip_keeps_existing_and_adds_new_entries() {
+    let (source, target) = merge_fixture();
+    let (bundle, unlock) = age_bundle(&source);
+
+    let report = target
+        .merge_bundle(&bundle, &unlock, ConflictStrategy::Skip, false)
+        .expect("merge");
+    assert_eq!(report.summary.skipped, 1);
+    assert_eq!(report.summary.added, 3);
+
+    let shared = target.find_project_by_name("shared").unwrap().unwrap();
+    let keys = target.list_keys(Some(&shared.id)).unwrap();
+    assert_eq!(keys.len(), 2);
+    let current = keys.iter().find(|k| k.name == "current").unwrap();
+    assert_eq!(target.get_key_material(&current.id).unwrap(), "local-1");
+    assert!(target.find_project_by_name("fresh").unwrap().is_some());
+}
+
+#[test]
+fn merge_overwrite_replaces_conflicting_key() {
+    let (source, target) = merge_fixture();
+    let (bundle, unlock) = age_bundle(&source);
+
+    target
+        .merge_bundle(&bundle, &unlock, ConflictStrategy::Overwrite, false)
+        .expect("merge");
+    let shared = target.find_project_by_name("shared").unwrap().unwrap();
+    let keys = target.list_keys(Some(&shared.id)).unwrap();
+    assert_eq!(keys.len(), 2);
+    let primary = keys
+        .iter()
+        .find(|k| k.kid.as_deref() == Some("kid-1"))
+        .unwrap();
+    assert_eq!(primary.name, "primary");
+    assert_eq!(target.get_key_material(&primary.id).unwrap(), "incoming-1");
+}
+
+#[test]
+fn merge_rename_keeps_both_keys() {
+    let (source, target) = merge_fixture();
+    let (bundle, unlock) = age_bundle(&source);
+
+    let report = target
+        .merge_bundle(&bundle, &unlock, ConflictStrategy::Rename, false)
+        .expect("merge");
+    assert_eq!(report.summary.renamed, 1);
+    let shared = target.find_project_by_name("shared").unwrap().unwrap();
+    let names: Vec<String> = target
+        .list_keys(Some(&shared.id))
+        .unwrap()
+        .into_iter()
+        .map(|k| k.name)
+        .collect();
+    assert!(names.contains(&"current".to_string()));
+    assert!(names.contains(&"primary-imported".to_string()));
+}
+
+#[test]
+fn merge_rename_suffixes_a_colliding_kid() {
+    let (source, target) = merge_fixture();
+    let (bundle, unlock) = age_bundle(&source);
+
+    let report = target
+        .merge_bundle(&bundle, &unlock, ConflictStrategy::Rename, false)
+        .expect("merge");
+    let action = report
+        .actions
+        .iter()
+        .find(|a| a.name == "primary")
+        .expect("primary action");
+    assert_eq!(action.renamed_kid.as_deref(), Some("kid-1-imported"));
+    let shared = target.find_project_by_name("shared").unwrap().unwrap();
+    let keys = target.list_keys(Some(&shared.id)).unwrap();
+    let kid_of = |name: &str| {
+        keys.iter()
+            .find(|k| k.name == name)
+            .and_then(|k| k.kid.clone())
+    };
+    assert_eq!(kid_of("current").as_deref(), Some("kid-1"));
+    assert_eq!(
+        kid_of("primary-imported").as_deref(),
+        Some("kid-1-imported")
+    );
+}
+
+#[test]
+fn failed_merge_leaves_the_vault_and_keychain_unchanged() {
+    let (source, _) = merge_fixture();
+    let shared = source.find_project_by_name("shared").unwrap().unwrap();
+    source
+        .add_jwks(JwksEntryInput {
+            project_id: shared.id.clone(),
+            name: "idp".to_string(),
+            document: r#"{"keys":[{"kty":"oct","kid":"a","k":"AA"}]}"#.to_string(),
+            source_url: None,
+        })
+        .expect("add jwks");
+    let (bundle, unlock) = age_bundle(&source);
+
+    let (dir, target, keychain) = sqlite_vault();
+    let existing = add_project(&target, "shared");
+    add_hmac_key(&target, &existing.id, "current", Some("kid-1"), "local-1");
+    let keychain_entries = keychain.len();
+    // The JWKS rows are written after the project and key rows, so this fails the merge late.
+    rusqlite::Connection::open(dir.path().join("vault.sqlite3"))
+        .expect("open db")
+        .execute_batch(
+            "CREATE TRIGGER no_jwks BEFORE INSERT ON jwks BEGIN SELECT RAISE(ABORT, 'no jwks'); END;",
+        )
+        .expect("trigger");
+
+    let err = target
+        .merge_bundle(&bundle, &unlock, ConflictStrategy::Rename, false)
+        .unwrap_err();
+    assert!(err.to_string().contains("no jwks"), "{err:#}");
+    assert!(target.find_project_by_name("fresh").unwrap().is_none());
+    let keys = target.list_keys(None).unwrap();
+    assert_eq!(keys.len(), 1);
+    assert_eq!(target.get_key_material(&keys[0].id).unwrap(), "local-1");
+    assert_eq!(keychain.len(), keychain_entries);
+}
+
+#[test]
+fn merge_dry_run_writes_nothing() {
+    let (source, target) = merge_fixture();
+    let (bundle, unlock) = age_bundle(&source);
+
+    let report = target
+        .merge_bundle(&bundle, &unlock, ConflictStrategy::Overwrite, true)
+        .expect("merge");
+    assert!(report.dry_run);
+    assert_eq!(report.summary.overwritten, 1);
+    assert!(target.find_project_by_name("fresh").unwrap().is_none());
+    assert_eq!(target.list_keys(None).unwrap().len(), 1);
+}
+
+#[test]
+fn project_bundle_merges_into_a_differently_named_project() {
+    let (source, target) = merge_fixture();
+    let shared = source.find_project_by_name("shared").unwrap().unwrap();
+    let bundle = source
+        .export_project_bundle_with(
+            &shared.id,
+            &BundleProtection::Passphrase("pw".into()),
+            false,
+        )
+        .expect("export project")
+        .bundle;
+    let unlock = BundleUnlock::Passphrase("pw".into());
+
+    let renamed = add_project(&target, "renamed");
+    let report = target
+        .merge_bundle_into_project(&bundle, &unlock, &renamed.id, ConflictStrategy::Skip, false)
+        .expect("merge into project");
+    assert_eq!(report.summary.added, 2);
+    assert!(report.actions.iter().all(|a| a.project == "renamed"));
+    assert_eq!(target.list_keys(Some(&renamed.id)).unwrap().len(), 2);
+    assert!(target.find_project_by_name("fresh").unwrap().is_none());
+
+    let (full, full_unlock) = age_bundle(&source);
+    let err = target
+        .merge_bundle_into_project(
+            &full,
+            &full_unlock,
+            &renamed.id,
+            ConflictStrategy::Skip,
+            true,
+        )
+        .unwrap_err();
+    assert!(err.to_string().contains("exactly one"));
+}
+
+#[test]
+fn delete_project_takes_backup_that_restores() {
+    let (_dir, vault, _keychain) = sqlite_vault();
+    let project = add_project(&vault, "alpha");
+    add_hmac_key(&vault, &project.id, "k1", Some("kid-1"), "secret-1");
+
+    vault.delete_project(&project.id).expect("delete project");
+    assert!(vault.list_projects().expect("list").is_empty());
+
+    let backups = vault.list_backups().expect("list backups");
+    assert_eq!(backups.len(), 1);
+    assert_eq!(backups[0].reason, "project-delete");
+    let counts = backups[0].counts.as_ref().expect("counts");
+    assert_eq!((counts.projects, counts.keys, counts.tokens), (1, 1, 0));
+
+    vault.restore_backup(&backups[0].id).expect("restore");
+    let restored = vault.find_project_by_name("alpha").expect("find").unwrap();
+    let keys = vault.list_keys(Some(&restored.id)).expect("keys");
+    assert_eq!(keys.len(), 1);
+    assert_eq!(
+        vault.get_key_material(&keys[0].id).expect("material"),
+        "secret-1"
+    );
+}
+
+#[test]
+fn import_replace_backs_up_previous_contents() {
+    let (_dir, vault, _keychain) = sqlite_vault();
+    let project = add_project(&vault, "alpha");
+    add_hmac_key(&vault, &project.id, "k1", None, "secret-1");
+
+    let source = memory_vault();
+    add_project(&source, "beta");
+    let (bundle, unlock) = age_bundle(&source);
+    vault
+        .import_bundle_with(&bundle, &unlock, true)
+        .expect("import replace");
+
+    let backups = vault.list_backups().expect("list backups");
+    assert_eq!(backups.len(), 1);
+    assert_eq!(backups[0].reason, "import-replace");
+
+    vault.restore_backup(&backups[0].id).expect("restore");
+    assert!(vault.find_project_by_name("alpha").expect("find").is_some());
+    assert!(vault.find_project_by_name("beta").expect("find").is_none());
+    assert_eq!(vault.list_backups().expect("list backups").len(), 2);
+}
+
+#[test]
+fn backups_prune_and_skip_memory_vaults() {
+    let (_dir, vault, _keychain) = sqlite_vault();
+    assert!(vault.create_backup("empty").expect("backup").is_none());
+
+    add_project(&vault, "alpha");
+    for _ in 0..3 {
+        vault
+            .create_backup("manual")
+            .expect("backup")
+            .expect("entry");
+    }
+    assert_eq!(vault.prune_backups(1).expect("prune"), 2);
+    assert_eq!(vault.list_backups().expect("list").len(), 1);
+    assert!(vault.restore_backup("missing").is_err());
+
+    let memory = memory_vault();
+    add_project(&memory, "alpha");
+    assert!(memory.create_backup("manual").expect("backup").is_none());
+    assert!(memory.list_backups().expect("list").is_empty());
+}
+
+#[test]
+fn sqlite_history_keeps_newest_entries() {
+    let (_dir, vault, _keychain) = sqlite_vault();
+    for n in 0..4 {
+        vault
+            .record_history(
+                HistoryInput {
+                    operation: "verify".to_string(),
+                    ok: n % 2 == 0,
+                    params: serde_json::json!({ "project": "alpha", "n": n }),
+                    result: format!("result-{n}"),
+                },
+                3,
+            )
+            .expect("record history");
+    }
+
+    let entries = vault.list_history(None).expect("list history");
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[0].result, "result-3");
+    assert_eq!(entries[2].params["n"], 1);
+    assert!(!entries[0].ok);
+    assert_eq!(vault.list_history(Some(1)).unwrap().len(), 1);
+
+    assert_eq!(vault.clear_history().unwrap(), 3);
+    assert!(vault.list_history(None).unwrap().is_empty());
+}
+
+#[test]
+fn bulk_material_reads_keep_order() {
+    let (_dir, sqlite, _keychain) = sqlite_vault();
+    for vault in [memory_vault(), sqlite] {
+        let project = add_project(&vault, "alpha");
+        let mut ids = Vec::new();
+        for i in 0..3 {
+            let key = vault
+                .add_key(KeyEntryInput {
+                    project_id: project.id.clone(),
+                    name: format!("k{i}"),
+                    kind: "hmac".to_string(),
+                    secret: format!("secret-{i}"),
+                    kid: None,
+                    description: None,
+                    tags: Vec::new(),
+                })
+                .expect("add key");
+            ids.push(key.id);
+        }
+        let token = vault
+            .add_token(TokenEntryInput {
+                project_id: project.id.clone(),
+                name: "t".to_string(),
+                token: "token-value".to_string(),
+            })
+            .expect("add token");
+
+        let reversed: Vec<&str> = ids.iter().rev().map(String::as_str).collect();
+        assert_eq!(
+            vault.get_key_materials(&reversed).expect("bulk keys"),
+            vec!["secret-2", "secret-1", "secret-0"]
+        );
+        assert_eq!(
+            vault
+                .get_token_materials(&[&token.id])
+                .expect("bulk tokens"),
+            vec!["token-value"]
+        );
+        assert!(vault.get_key_materials(&[&ids[0], "missing"]).is_err());
+        assert!(vault.get_key_materials(&[]).expect("empty").is_empty());
+    }
+}
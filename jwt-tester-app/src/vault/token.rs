@@ -1,6 +1,12 @@
+#[cfg(feature = "vault")]
+use super::helpers::keychain_accounts;
+#[cfg(feature = "vault")]
+use super::sqlite::open_db;
 use super::store::{Vault, VaultInner};
 use super::types::{TokenEntry, TokenEntryInput};
-use rusqlite::{params, Connection};
+#[cfg(feature = "vault")]
+use rusqlite::params;
+use serde_json::json;
 use uuid::Uuid;
 
 impl Vault {
@@ -14,8 +20,9 @@ impl Vault {
                     None => tokens,
                 })
             }
+            #[cfg(feature = "vault")]
             VaultInner::Sqlite { db_path, .. } => {
-                let conn = Connection::open(db_path)?;
+                let conn = open_db(db_path)?;
                 let tokens = if let Some(pid) = project_id {
                     let mut stmt = conn.prepare(
                         "SELECT id, project_id, name, created_at FROM tokens WHERE project_id = ?1 ORDER BY created_at DESC",
@@ -75,6 +82,7 @@ impl Vault {
                 locked.token_material.insert(row.id.clone(), input.token);
                 locked.tokens.push(row.clone());
             }
+            #[cfg(feature = "vault")]
             VaultInner::Sqlite {
                 db_path,
                 keychain_service,
@@ -83,7 +91,7 @@ impl Vault {
                 let account = format!("token:{id}");
                 keychain.set_password(keychain_service, &account, &input.token)?;
 
-                let conn = Connection::open(db_path)?;
+                let conn = open_db(db_path)?;
                 conn.execute(
                     "INSERT INTO tokens (id, project_id, name, created_at, keychain_service, keychain_account) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
                     params![row.id, row.project_id, row.name, row.created_at, keychain_service, account],
@@ -91,6 +99,7 @@ impl Vault {
             }
         }
 
+        self.emit_webhook_event(&row.project_id, "token.added", json!({ "token": row }));
         Ok(row)
     }
 
@@ -103,10 +112,11 @@ impl Vault {
                 .get(token_id)
                 .cloned()
                 .ok_or_else(|| anyhow::anyhow!("token material not found")),
+            #[cfg(feature = "vault")]
             VaultInner::Sqlite {
                 db_path, keychain, ..
             } => {
-                let conn = Connection::open(db_path)?;
+                let conn = open_db(db_path)?;
                 let mut stmt = conn.prepare(
                     "SELECT keychain_service, keychain_account FROM tokens WHERE id = ?1",
                 )?;
@@ -117,6 +127,33 @@ impl Vault {
         }
     }
 
+    /// Material for several tokens, in the order given; see [`Vault::get_key_materials`].
+    pub fn get_token_materials(&self, token_ids: &[&str]) -> anyhow::Result<Vec<String>> {
+        match &self.inner {
+            VaultInner::Memory { state } => {
+                let locked = state.lock().unwrap();
+                token_ids
+                    .iter()
+                    .map(|id| {
+                        locked
+                            .token_material
+                            .get(*id)
+                            .cloned()
+                            .ok_or_else(|| anyhow::anyhow!("token material not found"))
+                    })
+                    .collect()
+            }
+            #[cfg(feature = "vault")]
+            VaultInner::Sqlite {
+                db_path, keychain, ..
+            } => {
+                let conn = open_db(db_path)?;
+                let accounts = keychain_accounts(&conn, "tokens", token_ids)?;
+                keychain.get_passwords(&accounts)
+            }
+        }
+    }
+
     pub fn delete_token(&self, token_id: &str) -> anyhow::Result<()> {
         match &self.inner {
             VaultInner::Memory { state } => {
@@ -125,12 +162,13 @@ impl Vault {
                 locked.token_material.remove(token_id);
                 Ok(())
             }
+            #[cfg(feature = "vault")]
             VaultInner::Sqlite {
                 db_path,
                 keychain_service,
                 keychain,
             } => {
-                let conn = Connection::open(db_path)?;
+                let conn = open_db(db_path)?;
                 let mut stmt = conn.prepare("SELECT keychain_account FROM tokens WHERE id = ?1")?;
                 let account: String = stmt.query_row(params![token_id], |row| row.get(0))?;
                 let _ = keychain.delete_password(keychain_service, &account);
@@ -1,4 +1,6 @@
+use crate::vault_export::ExportBundle;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ProjectEntry {
@@ -8,6 +10,40 @@ pub struct ProjectEntry {
     pub default_key_id: Option<String>,
     pub description: Option<String>,
     pub tags: Vec<String>,
+    /// Rules `verify --project` enforces, keyed by algorithm family (`HS`, `RS`, `PS`, `ES`,
+    /// `EdDSA`).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub policy: BTreeMap<String, FamilyPolicy>,
+    /// False for projects whose keys must stay in this vault: exports leave them out and
+    /// generated material is not shown unless `--include-non-exportable` is passed.
+    #[serde(default = "exportable_default", skip_serializing_if = "is_exportable")]
+    pub exportable: bool,
+    /// Path of a WASM module whose `jwt_transform` export rewrites claims on `encode --project`
+    /// and whose `jwt_validate` export adds checks to `verify --project` (see `claim_plugin`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wasm_module: Option<String>,
+}
+
+fn exportable_default() -> bool {
+    true
+}
+
+// Only `false` is written, so bundle manifests hash projects exactly as before the flag existed.
+fn is_exportable(exportable: &bool) -> bool {
+    *exportable
+}
+
+/// What tokens of one algorithm family must carry to pass `verify --project`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct FamilyPolicy {
+    /// Header parameters that must be present (`kid`, `x5t`, ...).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub required_headers: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub required_claims: Vec<String>,
+    /// When set, `iss` must be one of these.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_issuers: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -20,6 +56,51 @@ pub struct KeyEntry {
     pub kid: Option<String>,
     pub description: Option<String>,
     pub tags: Vec<String>,
+    /// `sha256:<hex>` of the public key's SPKI DER; absent for HMAC keys.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<String>,
+    /// What `key_resolver` may select the key for. Like `exportable`, the default is not
+    /// written, so bundle manifests hash unrestricted keys as before.
+    #[serde(default, skip_serializing_if = "KeyUsage::is_both")]
+    pub usage: KeyUsage,
+}
+
+/// Whether a key may sign (`encode`, the issuer), verify (`verify`, `decode`), or both.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyUsage {
+    #[default]
+    Both,
+    Sign,
+    Verify,
+}
+
+impl KeyUsage {
+    pub fn parse(raw: &str) -> anyhow::Result<Self> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "both" => Ok(Self::Both),
+            "sign" => Ok(Self::Sign),
+            "verify" => Ok(Self::Verify),
+            other => anyhow::bail!("unknown key usage '{other}' (expected sign, verify or both)"),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Both => "both",
+            Self::Sign => "sign",
+            Self::Verify => "verify",
+        }
+    }
+
+    /// True when a key with this usage may be used for `purpose` (`Sign` or `Verify`).
+    pub fn allows(self, purpose: KeyUsage) -> bool {
+        self == Self::Both || self == purpose
+    }
+
+    fn is_both(&self) -> bool {
+        *self == Self::Both
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -30,6 +111,109 @@ pub struct TokenEntry {
     pub created_at: i64,
 }
 
+/// What `delete_project` removed. Rows are deleted in one SQLite transaction; keychain entries
+/// are removed after it commits, and the ones that still fail after retrying are listed.
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct ProjectDeleteReport {
+    pub keys: usize,
+    pub tokens: usize,
+    pub jwks: usize,
+    pub webhooks: usize,
+    pub keychain_failures: Vec<KeychainFailure>,
+}
+
+/// A bundle written by `export_bundle_with` and the keys of non-exportable projects it
+/// covered: left out of the bundle, or included and recorded in the audit log.
+#[derive(Debug)]
+pub struct ExportReport {
+    pub bundle: ExportBundle,
+    pub non_exportable_keys: Vec<KeyEntry>,
+}
+
+/// What `clone_project` copied into the new project.
+#[derive(Debug, Serialize, Clone)]
+pub struct ProjectCloneReport {
+    pub project: ProjectEntry,
+    pub keys: usize,
+    pub jwks: usize,
+}
+
+/// A keychain entry left behind after its row was deleted.
+#[derive(Debug, Serialize, Clone)]
+pub struct KeychainFailure {
+    pub account: String,
+    pub error: String,
+}
+
+/// A stored JWKS document; the document itself is fetched with `get_jwks_document`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct JwksEntry {
+    pub id: String,
+    pub project_id: String,
+    pub name: String,
+    pub created_at: i64,
+    pub kids: Vec<String>,
+    /// Where the document was fetched from; `vault jwks refresh` re-fetches it.
+    #[serde(default)]
+    pub source_url: Option<String>,
+    /// The `use`/`key_ops`/`alg` each key in the document declares, in document order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keys: Vec<JwkInfo>,
+}
+
+/// What one JWKS key says it may be used for. Resolution skips keys that may not verify the
+/// token's algorithm.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct JwkInfo {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kty: Option<String>,
+    #[serde(rename = "use", default, skip_serializing_if = "Option::is_none")]
+    pub key_use: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_ops: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alg: Option<String>,
+}
+
+impl JwkInfo {
+    /// False for keys marked for encryption or whose `key_ops` leave out `verify`.
+    pub fn may_verify(&self) -> bool {
+        self.key_use.as_deref().is_none_or(|u| u == "sig")
+            && self
+                .key_ops
+                .as_ref()
+                .is_none_or(|ops| ops.iter().any(|op| op == "verify"))
+    }
+}
+
+/// A URL told about key and token changes in one project. Its signing secret lives in the
+/// keychain next to key material.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WebhookEntry {
+    pub id: String,
+    pub project_id: String,
+    pub url: String,
+    /// Events it receives; empty means every event.
+    pub events: Vec<String>,
+    pub created_at: i64,
+}
+
+/// A token `verify --check-revocation` rejects for its project, matched by `jti` or by the
+/// SHA-256 of the whole compact token.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RevocationEntry {
+    pub id: String,
+    pub project_id: String,
+    pub jti: Option<String>,
+    /// Lowercase hex SHA-256 of the compact token.
+    pub token_hash: Option<String>,
+    /// The stored token it was revoked from, if any.
+    pub token_id: Option<String>,
+    pub created_at: i64,
+}
+
 pub struct ProjectInput {
     pub name: String,
     pub description: Option<String>,
@@ -51,3 +235,25 @@ pub struct TokenEntryInput {
     pub name: String,
     pub token: String,
 }
+
+pub struct JwksEntryInput {
+    pub project_id: String,
+    pub name: String,
+    pub document: String,
+    pub source_url: Option<String>,
+}
+
+pub struct WebhookEntryInput {
+    pub project_id: String,
+    pub url: String,
+    pub events: Vec<String>,
+    pub secret: String,
+}
+
+pub struct RevocationInput {
+    pub project_id: String,
+    pub jti: Option<String>,
+    /// The compact token; only its hash is stored.
+    pub token: Option<String>,
+    pub token_id: Option<String>,
+}
@@ -0,0 +1,233 @@
+use super::helpers::now_unix;
+#[cfg(feature = "vault")]
+use super::sqlite::open_db;
+use super::store::{Vault, VaultInner};
+use super::types::{WebhookEntry, WebhookEntryInput};
+use super::webhook_queue::{self, Delivery};
+#[cfg(feature = "vault")]
+use rusqlite::{params, Row};
+use serde_json::{json, Value};
+use tracing::warn;
+use uuid::Uuid;
+
+/// Everything a webhook can subscribe to.
+const WEBHOOK_EVENTS: &[&str] = &["key.added", "key.rotated", "key.deleted", "token.added"];
+
+impl Vault {
+    pub fn list_webhooks(&self, project_id: Option<&str>) -> anyhow::Result<Vec<WebhookEntry>> {
+        match &self.inner {
+            VaultInner::Memory { state } => {
+                let locked = state.lock().unwrap();
+                let hooks = locked.webhooks.clone();
+                Ok(match project_id {
+                    Some(pid) => hooks.into_iter().filter(|w| w.project_id == pid).collect(),
+                    None => hooks,
+                })
+            }
+            #[cfg(feature = "vault")]
+            VaultInner::Sqlite { db_path, .. } => {
+                let conn = open_db(db_path)?;
+                let hooks = if let Some(pid) = project_id {
+                    let mut stmt = conn.prepare(
+                        "SELECT id, project_id, url, events, created_at FROM webhooks WHERE project_id = ?1 ORDER BY created_at",
+                    )?;
+                    let rows = stmt.query_map(params![pid], webhook_row)?;
+                    rows.collect::<Result<Vec<_>, _>>()?
+                } else {
+                    let mut stmt = conn.prepare(
+                        "SELECT id, project_id, url, events, created_at FROM webhooks ORDER BY created_at",
+                    )?;
+                    let rows = stmt.query_map([], webhook_row)?;
+                    rows.collect::<Result<Vec<_>, _>>()?
+                };
+                Ok(hooks)
+            }
+        }
+    }
+
+    pub fn add_webhook(&self, input: WebhookEntryInput) -> anyhow::Result<WebhookEntry> {
+        if input.project_id.trim().is_empty() {
+            anyhow::bail!("project_id is required");
+        }
+        let url = input.url.trim();
+        if !(url.starts_with("http://") || url.starts_with("https://")) {
+            anyhow::bail!("webhook URL must be http:// or https://");
+        }
+        if input.secret.is_empty() {
+            anyhow::bail!("secret is required");
+        }
+        let mut events: Vec<String> = Vec::new();
+        for event in input.events {
+            let event = event.trim();
+            if !WEBHOOK_EVENTS.contains(&event) {
+                anyhow::bail!(
+                    "unknown webhook event '{event}' (expected one of: {})",
+                    WEBHOOK_EVENTS.join(", ")
+                );
+            }
+            if !events.iter().any(|e| e == event) {
+                events.push(event.to_string());
+            }
+        }
+
+        let id = Uuid::new_v4().to_string();
+        let row = WebhookEntry {
+            id: id.clone(),
+            project_id: input.project_id,
+            url: url.to_string(),
+            events,
+            created_at: now_unix(),
+        };
+
+        match &self.inner {
+            VaultInner::Memory { state } => {
+                let mut locked = state.lock().unwrap();
+                locked.webhook_secrets.insert(row.id.clone(), input.secret);
+                locked.webhooks.push(row.clone());
+            }
+            #[cfg(feature = "vault")]
+            VaultInner::Sqlite {
+                db_path,
+                keychain_service,
+                keychain,
+            } => {
+                let account = format!("webhook:{id}");
+                keychain.set_password(keychain_service, &account, &input.secret)?;
+
+                let conn = open_db(db_path)?;
+                conn.execute(
+                    "INSERT INTO webhooks (id, project_id, url, events, created_at, keychain_service, keychain_account) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                    params![
+                        row.id,
+                        row.project_id,
+                        row.url,
+                        serde_json::to_string(&row.events)?,
+                        row.created_at,
+                        keychain_service,
+                        account
+                    ],
+                )?;
+            }
+        }
+
+        Ok(row)
+    }
+
+    pub fn delete_webhook(&self, webhook_id: &str) -> anyhow::Result<()> {
+        match &self.inner {
+            VaultInner::Memory { state } => {
+                let mut locked = state.lock().unwrap();
+                let before = locked.webhooks.len();
+                locked.webhooks.retain(|w| w.id != webhook_id);
+                if locked.webhooks.len() == before {
+                    anyhow::bail!("webhook not found: {webhook_id}");
+                }
+                locked.webhook_secrets.remove(webhook_id);
+                Ok(())
+            }
+            #[cfg(feature = "vault")]
+            VaultInner::Sqlite {
+                db_path,
+                keychain_service,
+                keychain,
+            } => {
+                let conn = open_db(db_path)?;
+                let mut stmt =
+                    conn.prepare("SELECT keychain_account FROM webhooks WHERE id = ?1")?;
+                let account: String = stmt
+                    .query_row(params![webhook_id], |row| row.get(0))
+                    .map_err(|_| anyhow::anyhow!("webhook not found: {webhook_id}"))?;
+                let _ = keychain.delete_password(keychain_service, &account);
+
+                conn.execute("DELETE FROM webhooks WHERE id = ?1", params![webhook_id])?;
+                Ok(())
+            }
+        }
+    }
+
+    fn webhook_secret(&self, webhook_id: &str) -> anyhow::Result<String> {
+        match &self.inner {
+            VaultInner::Memory { state } => state
+                .lock()
+                .unwrap()
+                .webhook_secrets
+                .get(webhook_id)
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("webhook secret not found")),
+            #[cfg(feature = "vault")]
+            VaultInner::Sqlite {
+                db_path, keychain, ..
+            } => {
+                let conn = open_db(db_path)?;
+                let mut stmt = conn.prepare(
+                    "SELECT keychain_service, keychain_account FROM webhooks WHERE id = ?1",
+                )?;
+                let (service, account): (String, String) =
+                    stmt.query_row(params![webhook_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+                keychain.get_password(&service, &account)
+            }
+        }
+    }
+
+    /// Queues `event` for the project's webhooks. `subject` is merged into the payload and must
+    /// not carry key or token material. The change it reports has already been made, so
+    /// failures are only logged.
+    pub(super) fn emit_webhook_event(&self, project_id: &str, event: &str, subject: Value) {
+        if let Err(err) = self.queue_webhook_event(project_id, event, subject) {
+            warn!("failed to queue {event} webhooks: {err:#}");
+        }
+    }
+
+    fn queue_webhook_event(
+        &self,
+        project_id: &str,
+        event: &str,
+        subject: Value,
+    ) -> anyhow::Result<()> {
+        let hooks: Vec<WebhookEntry> = self
+            .list_webhooks(Some(project_id))?
+            .into_iter()
+            .filter(|w| w.events.is_empty() || w.events.iter().any(|e| e == event))
+            .collect();
+        if hooks.is_empty() {
+            return Ok(());
+        }
+        let project = self.find_project_by_id(project_id)?;
+        let mut payload = json!({
+            "id": Uuid::new_v4().to_string(),
+            "event": event,
+            "created_at": now_unix(),
+            "project": {
+                "id": project_id,
+                "name": project.map(|p| p.name),
+            },
+        });
+        if let (Some(payload), Value::Object(subject)) = (payload.as_object_mut(), subject) {
+            payload.extend(subject);
+        }
+        let body = serde_json::to_vec(&payload)?;
+        for hook in hooks {
+            webhook_queue::enqueue(Delivery {
+                secret: self.webhook_secret(&hook.id)?,
+                url: hook.url,
+                event: event.to_string(),
+                body: body.clone(),
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "vault")]
+fn webhook_row(row: &Row<'_>) -> rusqlite::Result<WebhookEntry> {
+    let events: Option<String> = row.get(3)?;
+    Ok(WebhookEntry {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        url: row.get(2)?,
+        events: events
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default(),
+        created_at: row.get(4)?,
+    })
+}
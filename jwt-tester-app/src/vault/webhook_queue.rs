@@ -0,0 +1,161 @@
+//! Background delivery of vault webhooks. Deliveries go through one worker thread so vault
+//! calls never wait on the network; failed POSTs are retried with a growing delay.
+
+use crate::error::ErrorKind;
+use crate::http;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use jsonwebtoken::{Algorithm, EncodingKey};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::time::Duration;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+const MAX_ATTEMPTS: u32 = 4;
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// `None` when the worker thread could not be started; deliveries then run inline.
+static QUEUE: OnceLock<Option<Queue>> = OnceLock::new();
+
+pub(super) struct Delivery {
+    pub(super) url: String,
+    pub(super) secret: String,
+    pub(super) event: String,
+    pub(super) body: Vec<u8>,
+}
+
+struct Queue {
+    sender: Sender<Delivery>,
+    pending: Arc<Pending>,
+}
+
+#[derive(Default)]
+struct Pending {
+    count: Mutex<usize>,
+    idle: Condvar,
+}
+
+impl Pending {
+    fn finish(&self) {
+        let mut count = self.count.lock().unwrap();
+        *count = count.saturating_sub(1);
+        if *count == 0 {
+            self.idle.notify_all();
+        }
+    }
+}
+
+pub(super) fn enqueue(delivery: Delivery) {
+    let Some(queue) = QUEUE.get_or_init(start) else {
+        deliver(&delivery);
+        return;
+    };
+    *queue.pending.count.lock().unwrap() += 1;
+    if let Err(mpsc::SendError(delivery)) = queue.sender.send(delivery) {
+        queue.pending.finish();
+        deliver(&delivery);
+    }
+}
+
+/// Waits until queued deliveries are done (including retries) or `timeout` passes, and returns
+/// how many were still pending. Called before the process exits.
+pub fn flush_webhooks(timeout: Duration) -> usize {
+    let Some(Some(queue)) = QUEUE.get() else {
+        return 0;
+    };
+    let count = queue.pending.count.lock().unwrap();
+    let (count, _) = queue
+        .pending
+        .idle
+        .wait_timeout_while(count, timeout, |count| *count > 0)
+        .unwrap();
+    *count
+}
+
+fn start() -> Option<Queue> {
+    let (sender, receiver) = mpsc::channel::<Delivery>();
+    let pending = Arc::new(Pending::default());
+    let worker = pending.clone();
+    let spawned = std::thread::Builder::new()
+        .name("vault-webhooks".to_string())
+        .spawn(move || {
+            for delivery in receiver {
+                deliver(&delivery);
+                worker.finish();
+            }
+        });
+    match spawned {
+        Ok(_) => Some(Queue { sender, pending }),
+        Err(err) => {
+            warn!("failed to start the webhook worker, delivering inline: {err}");
+            None
+        }
+    }
+}
+
+fn deliver(delivery: &Delivery) {
+    let event = delivery.event.as_str();
+    let url = delivery.url.as_str();
+    let signature = match sign(&delivery.secret, &delivery.body) {
+        Ok(signature) => signature,
+        Err(err) => {
+            warn!("failed to sign {event} webhook for {url}: {err:#}");
+            return;
+        }
+    };
+    let id = Uuid::new_v4().to_string();
+    let headers = [
+        ("X-Jwt-Tester-Event", event),
+        ("X-Jwt-Tester-Delivery", id.as_str()),
+        ("X-Jwt-Tester-Signature", signature.as_str()),
+    ];
+    let mut delay = RETRY_DELAY;
+    for attempt in 1..=MAX_ATTEMPTS {
+        match http::post_bytes(url, "application/json", &delivery.body, &headers) {
+            Ok(()) => {
+                debug!("delivered {event} webhook {id} to {url}");
+                return;
+            }
+            Err(err) if err.kind == ErrorKind::Offline || attempt == MAX_ATTEMPTS => {
+                warn!(
+                    "{event} webhook to {url} failed after {attempt} attempt(s): {}",
+                    err.message
+                );
+                return;
+            }
+            Err(_) => {
+                std::thread::sleep(delay);
+                delay *= 2;
+            }
+        }
+    }
+}
+
+/// `sha256=<hex>` HMAC-SHA256 of the body, the format most webhook receivers already verify.
+fn sign(secret: &str, body: &[u8]) -> anyhow::Result<String> {
+    let mac = jsonwebtoken::crypto::sign(
+        body,
+        &EncodingKey::from_secret(secret.as_bytes()),
+        Algorithm::HS256,
+    )?;
+    Ok(format!(
+        "sha256={}",
+        hex::encode(URL_SAFE_NO_PAD.decode(mac)?)
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sign;
+
+    #[test]
+    fn signature_is_hex_hmac_sha256_of_the_body() {
+        // RFC 4231 test case 2.
+        let signature = sign("Jefe", b"what do ya want for nothing?").expect("sign");
+        assert_eq!(
+            signature,
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+}
@@ -1,18 +1,27 @@
-use crate::vault::{KeyEntry, ProjectEntry, TokenEntry};
+use crate::bundle_recipients;
+use crate::vault::{JwksEntry, KeyEntry, ProjectEntry, TokenEntry};
 use anyhow::Context;
+#[cfg(feature = "vault")]
 use argon2::{Algorithm, Argon2, Params, Version};
 use base64::engine::general_purpose::URL_SAFE_NO_PAD;
 use base64::Engine;
+#[cfg(feature = "vault")]
 use chacha20poly1305::aead::{Aead, KeyInit};
+#[cfg(feature = "vault")]
 use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
 use rand::rngs::OsRng;
 use rand::RngCore;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::time::{SystemTime, UNIX_EPOCH};
 
-pub(crate) const EXPORT_VERSION: u8 = 1;
+pub(crate) const EXPORT_VERSION: u8 = 2;
+const LEGACY_EXPORT_VERSION: u8 = 1;
+pub(crate) const SNAPSHOT_VERSION: u8 = 1;
 const KDF_NAME: &str = "argon2id";
 const CIPHER_NAME: &str = "xchacha20poly1305";
+const AGE_CIPHER_NAME: &str = "age";
+const GPG_CIPHER_NAME: &str = "gpg";
 const KDF_MEM_KIB: u32 = 65_536;
 const KDF_ITERATIONS: u32 = 3;
 const KDF_PARALLELISM: u32 = 1;
@@ -20,9 +29,18 @@ const KDF_PARALLELISM: u32 = 1;
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ExportBundle {
     pub version: u8,
-    pub kdf: KdfParams,
+    /// Present for passphrase bundles only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kdf: Option<KdfParams>,
     pub cipher: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub nonce: String,
+    /// Public recipients the bundle was encrypted to (age/gpg bundles).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub recipients: Vec<String>,
+    /// Encrypted manifest (v2+); readable without decrypting the payload.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manifest: Option<SealedManifest>,
     pub ciphertext: String,
 }
 
@@ -35,6 +53,73 @@ pub struct KdfParams {
     pub salt: String,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SealedManifest {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+/// Summary of a bundle's contents, sealed with the same key as the payload.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleManifest {
+    pub tool_version: String,
+    pub exported_at: i64,
+    pub counts: ManifestCounts,
+    /// SHA-256 of the encrypted payload bytes; detects truncation before decrypting.
+    pub payload_sha256: String,
+    pub entries: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestCounts {
+    pub projects: usize,
+    pub keys: usize,
+    pub tokens: usize,
+    #[serde(default)]
+    pub jwks: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub kind: String,
+    pub id: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<String>,
+    pub sha256: String,
+}
+
+/// Result of `import --list`: the manifest plus whether it came from a legacy bundle.
+#[derive(Debug, Serialize)]
+pub struct BundleListing {
+    pub version: u8,
+    pub cipher: String,
+    /// True for v1 bundles, whose listing had to be derived by decrypting the payload.
+    pub legacy: bool,
+    pub manifest: BundleManifest,
+}
+
+/// How an exported snapshot is encrypted.
+#[derive(Debug, Clone)]
+pub enum BundleProtection {
+    Passphrase(String),
+    Age(Vec<String>),
+    Gpg(Vec<String>),
+    /// Pre-generated 32-byte key; no KDF is recorded (used for local backups).
+    RawKey([u8; 32]),
+}
+
+/// Credentials used to open an exported bundle.
+#[derive(Debug, Clone)]
+pub enum BundleUnlock {
+    Passphrase(String),
+    /// Contents of an age identity file.
+    AgeIdentity(String),
+    Gpg,
+    RawKey([u8; 32]),
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct VaultSnapshot {
     pub version: u8,
@@ -42,6 +127,9 @@ pub struct VaultSnapshot {
     pub projects: Vec<ProjectEntry>,
     pub keys: Vec<KeyExport>,
     pub tokens: Vec<TokenExport>,
+    /// Absent in bundles written before JWKS documents were stored in the vault.
+    #[serde(default)]
+    pub jwks: Vec<JwksExport>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -56,112 +144,394 @@ pub struct TokenExport {
     pub token: String,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JwksExport {
+    pub entry: JwksEntry,
+    pub document: String,
+}
+
+// Key material resolved once per export/import so Argon2 only runs once per bundle.
+enum SealKey<'a> {
+    Symmetric([u8; 32]),
+    Age(&'a [String]),
+    Gpg(&'a [String]),
+}
+
+enum OpenKey<'a> {
+    Symmetric([u8; 32]),
+    Age(&'a str),
+    Gpg,
+}
+
 pub fn build_snapshot(
     projects: Vec<ProjectEntry>,
     keys: Vec<KeyExport>,
     tokens: Vec<TokenExport>,
+    jwks: Vec<JwksExport>,
 ) -> VaultSnapshot {
     VaultSnapshot {
-        version: EXPORT_VERSION,
+        version: SNAPSHOT_VERSION,
         exported_at: now_unix(),
         projects,
         keys,
         tokens,
+        jwks,
     }
 }
 
-pub fn encrypt_snapshot(
+pub fn protect_snapshot(
     snapshot: &VaultSnapshot,
-    passphrase: &str,
+    protection: &BundleProtection,
 ) -> anyhow::Result<ExportBundle> {
-    if passphrase.trim().is_empty() {
-        anyhow::bail!("passphrase is required");
-    }
+    let (kdf, key, cipher, recipients) = match protection {
+        BundleProtection::Passphrase(passphrase) => {
+            if passphrase.trim().is_empty() {
+                anyhow::bail!("passphrase is required");
+            }
+            let mut salt = [0u8; 16];
+            OsRng.fill_bytes(&mut salt);
+            let kdf = KdfParams {
+                name: KDF_NAME.to_string(),
+                mem_kib: KDF_MEM_KIB,
+                iterations: KDF_ITERATIONS,
+                parallelism: KDF_PARALLELISM,
+                salt: URL_SAFE_NO_PAD.encode(salt),
+            };
+            let key = derive_key(&kdf, passphrase)?;
+            (Some(kdf), SealKey::Symmetric(key), CIPHER_NAME, Vec::new())
+        }
+        BundleProtection::RawKey(key) => (None, SealKey::Symmetric(*key), CIPHER_NAME, Vec::new()),
+        BundleProtection::Age(recipients) => (
+            None,
+            SealKey::Age(recipients),
+            AGE_CIPHER_NAME,
+            recipients.clone(),
+        ),
+        BundleProtection::Gpg(recipients) => (
+            None,
+            SealKey::Gpg(recipients),
+            GPG_CIPHER_NAME,
+            recipients.clone(),
+        ),
+    };
 
     let plaintext = serde_json::to_vec(snapshot).context("serialize vault snapshot")?;
+    let (nonce, ciphertext) = seal(&key, &plaintext).context("encrypt vault snapshot")?;
+    let manifest = build_manifest(snapshot, &ciphertext)?;
+    let manifest_json = serde_json::to_vec(&manifest).context("serialize bundle manifest")?;
+    let (manifest_nonce, manifest_ciphertext) =
+        seal(&key, &manifest_json).context("encrypt bundle manifest")?;
 
-    let mut salt = [0u8; 16];
-    OsRng.fill_bytes(&mut salt);
-    let params = Params::new(KDF_MEM_KIB, KDF_ITERATIONS, KDF_PARALLELISM, None)
-        .map_err(|e| anyhow::anyhow!("invalid kdf params: {e:?}"))?;
-    let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params);
+    Ok(ExportBundle {
+        version: EXPORT_VERSION,
+        kdf,
+        cipher: cipher.to_string(),
+        nonce,
+        recipients: recipients.iter().map(|r| r.trim().to_string()).collect(),
+        manifest: Some(SealedManifest {
+            nonce: manifest_nonce,
+            ciphertext: URL_SAFE_NO_PAD.encode(manifest_ciphertext),
+        }),
+        ciphertext: URL_SAFE_NO_PAD.encode(ciphertext),
+    })
+}
 
-    let mut key_bytes = [0u8; 32];
-    argon2
-        .hash_password_into(passphrase.as_bytes(), &salt, &mut key_bytes)
-        .map_err(|e| anyhow::anyhow!("derive key from passphrase: {e:?}"))?;
+/// Decrypts a bundle, checking the v2 manifest before and after decrypting the payload.
+pub fn unlock_snapshot(
+    bundle: &ExportBundle,
+    unlock: &BundleUnlock,
+) -> anyhow::Result<VaultSnapshot> {
+    check_version(bundle)?;
+    let key = open_key(bundle, unlock)?;
+    let ciphertext = decode_ciphertext(bundle)?;
+    let manifest = match bundle.version {
+        LEGACY_EXPORT_VERSION => None,
+        _ => {
+            let manifest = open_manifest(bundle, &key)?;
+            check_payload_hash(&manifest, &ciphertext)?;
+            Some(manifest)
+        }
+    };
 
-    let mut nonce_bytes = [0u8; 24];
-    OsRng.fill_bytes(&mut nonce_bytes);
+    let plaintext = open(&key, &bundle.nonce, &ciphertext).context("decrypt vault snapshot")?;
+    let snapshot = parse_snapshot(&plaintext)?;
+    if let Some(manifest) = manifest {
+        verify_manifest(&manifest, &snapshot)?;
+    }
+    Ok(snapshot)
+}
 
-    let cipher = XChaCha20Poly1305::new(Key::from_slice(&key_bytes));
-    let nonce = XNonce::from_slice(&nonce_bytes);
-    let ciphertext = cipher
-        .encrypt(nonce, plaintext.as_ref())
-        .map_err(|e| anyhow::anyhow!("encrypt vault snapshot: {e:?}"))?;
+/// Lists bundle contents; v2 bundles only decrypt the manifest.
+pub fn list_bundle(bundle: &ExportBundle, unlock: &BundleUnlock) -> anyhow::Result<BundleListing> {
+    check_version(bundle)?;
+    let key = open_key(bundle, unlock)?;
+    let ciphertext = decode_ciphertext(bundle)?;
+    let (legacy, manifest) = match bundle.version {
+        LEGACY_EXPORT_VERSION => {
+            let plaintext =
+                open(&key, &bundle.nonce, &ciphertext).context("decrypt vault snapshot")?;
+            let snapshot = parse_snapshot(&plaintext)?;
+            let mut manifest = build_manifest(&snapshot, &ciphertext)?;
+            manifest.tool_version = "unknown".to_string();
+            (true, manifest)
+        }
+        _ => {
+            let manifest = open_manifest(bundle, &key)?;
+            check_payload_hash(&manifest, &ciphertext)?;
+            (false, manifest)
+        }
+    };
+    Ok(BundleListing {
+        version: bundle.version,
+        cipher: bundle.cipher.clone(),
+        legacy,
+        manifest,
+    })
+}
 
-    Ok(ExportBundle {
-        version: EXPORT_VERSION,
-        kdf: KdfParams {
-            name: KDF_NAME.to_string(),
-            mem_kib: KDF_MEM_KIB,
-            iterations: KDF_ITERATIONS,
-            parallelism: KDF_PARALLELISM,
-            salt: URL_SAFE_NO_PAD.encode(salt),
+/// Picks the unlock method matching the bundle's cipher.
+pub fn unlock_for_bundle(
+    bundle: &ExportBundle,
+    passphrase: Option<String>,
+    age_identity: Option<String>,
+) -> anyhow::Result<BundleUnlock> {
+    match bundle.cipher.as_str() {
+        AGE_CIPHER_NAME => age_identity.map(BundleUnlock::AgeIdentity).ok_or_else(|| {
+            anyhow::anyhow!("bundle is encrypted to age recipients; provide --identity")
+        }),
+        GPG_CIPHER_NAME => Ok(BundleUnlock::Gpg),
+        _ => passphrase
+            .map(BundleUnlock::Passphrase)
+            .ok_or_else(|| anyhow::anyhow!("bundle is passphrase-encrypted; provide --passphrase")),
+    }
+}
+
+fn build_manifest(snapshot: &VaultSnapshot, ciphertext: &[u8]) -> anyhow::Result<BundleManifest> {
+    let mut entries = Vec::with_capacity(
+        snapshot.projects.len() + snapshot.keys.len() + snapshot.tokens.len() + snapshot.jwks.len(),
+    );
+    for project in &snapshot.projects {
+        entries.push(ManifestEntry {
+            kind: "project".to_string(),
+            id: project.id.clone(),
+            name: project.name.clone(),
+            project_id: None,
+            sha256: entry_hash(project)?,
+        });
+    }
+    for key in &snapshot.keys {
+        entries.push(ManifestEntry {
+            kind: "key".to_string(),
+            id: key.entry.id.clone(),
+            name: key.entry.name.clone(),
+            project_id: Some(key.entry.project_id.clone()),
+            sha256: entry_hash(key)?,
+        });
+    }
+    for token in &snapshot.tokens {
+        entries.push(ManifestEntry {
+            kind: "token".to_string(),
+            id: token.entry.id.clone(),
+            name: token.entry.name.clone(),
+            project_id: Some(token.entry.project_id.clone()),
+            sha256: entry_hash(token)?,
+        });
+    }
+    for jwks in &snapshot.jwks {
+        entries.push(ManifestEntry {
+            kind: "jwks".to_string(),
+            id: jwks.entry.id.clone(),
+            name: jwks.entry.name.clone(),
+            project_id: Some(jwks.entry.project_id.clone()),
+            sha256: entry_hash(jwks)?,
+        });
+    }
+
+    Ok(BundleManifest {
+        tool_version: env!("CARGO_PKG_VERSION").to_string(),
+        exported_at: snapshot.exported_at,
+        counts: ManifestCounts {
+            projects: snapshot.projects.len(),
+            keys: snapshot.keys.len(),
+            tokens: snapshot.tokens.len(),
+            jwks: snapshot.jwks.len(),
         },
-        cipher: CIPHER_NAME.to_string(),
-        nonce: URL_SAFE_NO_PAD.encode(nonce_bytes),
-        ciphertext: URL_SAFE_NO_PAD.encode(ciphertext),
+        payload_sha256: hex::encode(Sha256::digest(ciphertext)),
+        entries,
     })
 }
 
-pub fn decrypt_snapshot(bundle: &ExportBundle, passphrase: &str) -> anyhow::Result<VaultSnapshot> {
-    if bundle.version != EXPORT_VERSION {
-        anyhow::bail!("unsupported export version {}", bundle.version);
+fn verify_manifest(manifest: &BundleManifest, snapshot: &VaultSnapshot) -> anyhow::Result<()> {
+    let actual = build_manifest(snapshot, &[])?;
+    if actual.counts != manifest.counts {
+        anyhow::bail!(
+            "bundle manifest mismatch: expected {} projects/{} keys/{} tokens/{} jwks, found {}/{}/{}/{}",
+            manifest.counts.projects,
+            manifest.counts.keys,
+            manifest.counts.tokens,
+            manifest.counts.jwks,
+            actual.counts.projects,
+            actual.counts.keys,
+            actual.counts.tokens,
+            actual.counts.jwks
+        );
     }
-    if bundle.kdf.name != KDF_NAME {
-        anyhow::bail!("unsupported kdf {}", bundle.kdf.name);
+    for (expected, found) in manifest.entries.iter().zip(actual.entries.iter()) {
+        if expected != found {
+            anyhow::bail!(
+                "bundle manifest mismatch for {} {} ({})",
+                expected.kind,
+                expected.name,
+                expected.id
+            );
+        }
     }
-    if bundle.cipher != CIPHER_NAME {
-        anyhow::bail!("unsupported cipher {}", bundle.cipher);
-    }
-    if passphrase.trim().is_empty() {
-        anyhow::bail!("passphrase is required");
+    Ok(())
+}
+
+fn check_payload_hash(manifest: &BundleManifest, ciphertext: &[u8]) -> anyhow::Result<()> {
+    let actual = hex::encode(Sha256::digest(ciphertext));
+    if actual != manifest.payload_sha256 {
+        anyhow::bail!("bundle payload does not match its manifest (truncated or modified)");
     }
+    Ok(())
+}
+
+fn entry_hash<T: Serialize>(entry: &T) -> anyhow::Result<String> {
+    let bytes = serde_json::to_vec(entry).context("serialize manifest entry")?;
+    Ok(hex::encode(Sha256::digest(bytes)))
+}
 
-    let salt = URL_SAFE_NO_PAD
-        .decode(&bundle.kdf.salt)
-        .context("decode salt")?;
-    let nonce = URL_SAFE_NO_PAD
-        .decode(&bundle.nonce)
-        .context("decode nonce")?;
+fn open_manifest(bundle: &ExportBundle, key: &OpenKey) -> anyhow::Result<BundleManifest> {
+    let sealed = bundle
+        .manifest
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("bundle is missing its manifest"))?;
     let ciphertext = URL_SAFE_NO_PAD
-        .decode(&bundle.ciphertext)
-        .context("decode ciphertext")?;
-
-    let params = Params::new(
-        bundle.kdf.mem_kib,
-        bundle.kdf.iterations,
-        bundle.kdf.parallelism,
-        None,
-    )
-    .map_err(|e| anyhow::anyhow!("invalid kdf params: {e:?}"))?;
+        .decode(&sealed.ciphertext)
+        .context("decode manifest")?;
+    let plaintext = open(key, &sealed.nonce, &ciphertext).context("decrypt bundle manifest")?;
+    serde_json::from_slice(&plaintext).context("parse bundle manifest")
+}
+
+fn open_key<'a>(bundle: &ExportBundle, unlock: &'a BundleUnlock) -> anyhow::Result<OpenKey<'a>> {
+    match (bundle.cipher.as_str(), unlock) {
+        (CIPHER_NAME, BundleUnlock::RawKey(key)) => Ok(OpenKey::Symmetric(*key)),
+        (CIPHER_NAME, BundleUnlock::Passphrase(passphrase)) => {
+            let kdf = bundle
+                .kdf
+                .as_ref()
+                .ok_or_else(|| anyhow::anyhow!("bundle is missing kdf parameters"))?;
+            if kdf.name != KDF_NAME {
+                anyhow::bail!("unsupported kdf {}", kdf.name);
+            }
+            if passphrase.trim().is_empty() {
+                anyhow::bail!("passphrase is required");
+            }
+            Ok(OpenKey::Symmetric(derive_key(kdf, passphrase)?))
+        }
+        (AGE_CIPHER_NAME, BundleUnlock::AgeIdentity(identity)) => Ok(OpenKey::Age(identity)),
+        (GPG_CIPHER_NAME, BundleUnlock::Gpg) => Ok(OpenKey::Gpg),
+        (CIPHER_NAME, _) => anyhow::bail!("bundle is passphrase-encrypted; provide --passphrase"),
+        (AGE_CIPHER_NAME, _) => {
+            anyhow::bail!("bundle is encrypted to age recipients; provide --identity")
+        }
+        (GPG_CIPHER_NAME, _) => anyhow::bail!("bundle is encrypted to gpg recipients"),
+        (other, _) => anyhow::bail!("unsupported cipher {other}"),
+    }
+}
+
+#[cfg(feature = "vault")]
+fn derive_key(kdf: &KdfParams, passphrase: &str) -> anyhow::Result<[u8; 32]> {
+    let salt = URL_SAFE_NO_PAD.decode(&kdf.salt).context("decode salt")?;
+    let params = Params::new(kdf.mem_kib, kdf.iterations, kdf.parallelism, None)
+        .map_err(|e| anyhow::anyhow!("invalid kdf params: {e:?}"))?;
     let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params);
 
     let mut key_bytes = [0u8; 32];
     argon2
         .hash_password_into(passphrase.as_bytes(), &salt, &mut key_bytes)
         .map_err(|e| anyhow::anyhow!("derive key from passphrase: {e:?}"))?;
+    Ok(key_bytes)
+}
+
+#[cfg(not(feature = "vault"))]
+fn derive_key(_: &KdfParams, _: &str) -> anyhow::Result<[u8; 32]> {
+    anyhow::bail!("passphrase-protected bundles require a build with the `vault` feature")
+}
+
+fn seal(key: &SealKey, plaintext: &[u8]) -> anyhow::Result<(String, Vec<u8>)> {
+    match key {
+        SealKey::Symmetric(key_bytes) => seal_symmetric(key_bytes, plaintext),
+        SealKey::Age(recipients) => Ok((
+            String::new(),
+            bundle_recipients::age_encrypt(recipients, plaintext)?,
+        )),
+        SealKey::Gpg(recipients) => Ok((
+            String::new(),
+            bundle_recipients::gpg_encrypt(recipients, plaintext)?,
+        )),
+    }
+}
+
+fn open(key: &OpenKey, nonce: &str, ciphertext: &[u8]) -> anyhow::Result<Vec<u8>> {
+    match key {
+        OpenKey::Symmetric(key_bytes) => open_symmetric(key_bytes, nonce, ciphertext),
+        OpenKey::Age(identity) => bundle_recipients::age_decrypt(identity, ciphertext),
+        OpenKey::Gpg => bundle_recipients::gpg_decrypt(ciphertext),
+    }
+}
+
+#[cfg(feature = "vault")]
+fn seal_symmetric(key_bytes: &[u8; 32], plaintext: &[u8]) -> anyhow::Result<(String, Vec<u8>)> {
+    let mut nonce_bytes = [0u8; 24];
+    OsRng.fill_bytes(&mut nonce_bytes);
+    let cipher = XChaCha20Poly1305::new(Key::from_slice(key_bytes));
+    let ciphertext = cipher
+        .encrypt(XNonce::from_slice(&nonce_bytes), plaintext)
+        .map_err(|e| anyhow::anyhow!("{e:?}"))?;
+    Ok((URL_SAFE_NO_PAD.encode(nonce_bytes), ciphertext))
+}
+
+#[cfg(feature = "vault")]
+fn open_symmetric(key_bytes: &[u8; 32], nonce: &str, ciphertext: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let nonce = URL_SAFE_NO_PAD.decode(nonce).context("decode nonce")?;
+    if nonce.len() != 24 {
+        anyhow::bail!("invalid nonce length");
+    }
+    let cipher = XChaCha20Poly1305::new(Key::from_slice(key_bytes));
+    cipher
+        .decrypt(XNonce::from_slice(&nonce), ciphertext)
+        .map_err(|e| anyhow::anyhow!("{e:?}"))
+}
+
+#[cfg(not(feature = "vault"))]
+fn seal_symmetric(_: &[u8; 32], _: &[u8]) -> anyhow::Result<(String, Vec<u8>)> {
+    anyhow::bail!("encrypted bundles require a build with the `vault` feature")
+}
+
+#[cfg(not(feature = "vault"))]
+fn open_symmetric(_: &[u8; 32], _: &str, _: &[u8]) -> anyhow::Result<Vec<u8>> {
+    anyhow::bail!("encrypted bundles require a build with the `vault` feature")
+}
+
+fn check_version(bundle: &ExportBundle) -> anyhow::Result<()> {
+    if bundle.version != EXPORT_VERSION && bundle.version != LEGACY_EXPORT_VERSION {
+        anyhow::bail!("unsupported export version {}", bundle.version);
+    }
+    Ok(())
+}
 
-    let cipher = XChaCha20Poly1305::new(Key::from_slice(&key_bytes));
-    let nonce = XNonce::from_slice(&nonce);
-    let plaintext = cipher
-        .decrypt(nonce, ciphertext.as_ref())
-        .map_err(|e| anyhow::anyhow!("decrypt vault snapshot: {e:?}"))?;
+fn decode_ciphertext(bundle: &ExportBundle) -> anyhow::Result<Vec<u8>> {
+    URL_SAFE_NO_PAD
+        .decode(&bundle.ciphertext)
+        .context("decode ciphertext")
+}
 
+fn parse_snapshot(plaintext: &[u8]) -> anyhow::Result<VaultSnapshot> {
     let snapshot: VaultSnapshot =
-        serde_json::from_slice(&plaintext).context("parse vault snapshot")?;
-    if snapshot.version != EXPORT_VERSION {
+        serde_json::from_slice(plaintext).context("parse vault snapshot")?;
+    if snapshot.version != SNAPSHOT_VERSION {
         anyhow::bail!("unsupported snapshot version {}", snapshot.version);
     }
     Ok(snapshot)
@@ -174,15 +544,28 @@ fn now_unix() -> i64 {
         .as_secs() as i64
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "vault"))]
 mod tests {
     use super::*;
     use crate::vault::{KeyEntry, ProjectEntry, TokenEntry};
 
-    #[test]
-    fn export_encrypt_decrypt_roundtrip() {
-        let snapshot = VaultSnapshot {
-            version: EXPORT_VERSION,
+    fn encrypt_snapshot(
+        snapshot: &VaultSnapshot,
+        passphrase: &str,
+    ) -> anyhow::Result<ExportBundle> {
+        protect_snapshot(
+            snapshot,
+            &BundleProtection::Passphrase(passphrase.to_string()),
+        )
+    }
+
+    fn decrypt_snapshot(bundle: &ExportBundle, passphrase: &str) -> anyhow::Result<VaultSnapshot> {
+        unlock_snapshot(bundle, &BundleUnlock::Passphrase(passphrase.to_string()))
+    }
+
+    fn sample_snapshot() -> VaultSnapshot {
+        VaultSnapshot {
+            version: SNAPSHOT_VERSION,
             exported_at: 123,
             projects: vec![ProjectEntry {
                 id: "p1".to_string(),
@@ -191,6 +574,9 @@ mod tests {
                 default_key_id: None,
                 description: Some("desc".to_string()),
                 tags: vec!["tag".to_string()],
+                policy: Default::default(),
+                exportable: true,
+                wasm_module: None,
             }],
             keys: vec![KeyExport {
                 entry: KeyEntry {
@@ -202,6 +588,8 @@ mod tests {
                     kid: Some("kid".to_string()),
                     description: None,
                     tags: vec![],
+                    fingerprint: None,
+                    usage: Default::default(),
                 },
                 material: "secret".to_string(),
             }],
@@ -214,7 +602,13 @@ mod tests {
                 },
                 token: "token".to_string(),
             }],
-        };
+            jwks: vec![],
+        }
+    }
+
+    #[test]
+    fn export_encrypt_decrypt_roundtrip() {
+        let snapshot = sample_snapshot();
 
         let bundle = encrypt_snapshot(&snapshot, "passphrase").expect("encrypt");
         let decoded = decrypt_snapshot(&bundle, "passphrase").expect("decrypt");
@@ -228,14 +622,116 @@ mod tests {
     #[test]
     fn decrypt_rejects_wrong_passphrase() {
         let snapshot = VaultSnapshot {
-            version: EXPORT_VERSION,
+            version: SNAPSHOT_VERSION,
             exported_at: 1,
             projects: vec![],
             keys: vec![],
             tokens: vec![],
+            jwks: vec![],
         };
         let bundle = encrypt_snapshot(&snapshot, "good").expect("encrypt");
         let err = decrypt_snapshot(&bundle, "bad");
         assert!(err.is_err());
     }
+
+    #[test]
+    fn age_protected_bundle_roundtrip() {
+        use age::secrecy::ExposeSecret;
+
+        let snapshot = VaultSnapshot {
+            version: SNAPSHOT_VERSION,
+            exported_at: 1,
+            projects: vec![],
+            keys: vec![],
+            tokens: vec![],
+            jwks: vec![],
+        };
+        let identity = age::x25519::Identity::generate();
+        let recipient = identity.to_public().to_string();
+        let bundle = protect_snapshot(&snapshot, &BundleProtection::Age(vec![recipient.clone()]))
+            .expect("protect");
+        assert_eq!(bundle.cipher, "age");
+        assert!(bundle.kdf.is_none());
+        assert_eq!(bundle.recipients, vec![recipient]);
+
+        let err = unlock_snapshot(&bundle, &BundleUnlock::Passphrase("x".to_string()))
+            .expect_err("passphrase must not open age bundle");
+        assert!(err.to_string().contains("--identity"));
+
+        let unlock = unlock_for_bundle(
+            &bundle,
+            None,
+            Some(identity.to_string().expose_secret().to_string()),
+        )
+        .expect("unlock");
+        let decoded = unlock_snapshot(&bundle, &unlock).expect("unlock snapshot");
+        assert_eq!(decoded.exported_at, 1);
+    }
+
+    #[test]
+    fn v2_bundle_lists_manifest_without_payload() {
+        let snapshot = sample_snapshot();
+        let mut bundle = encrypt_snapshot(&snapshot, "passphrase").expect("encrypt");
+        assert_eq!(bundle.version, EXPORT_VERSION);
+
+        let unlock = BundleUnlock::Passphrase("passphrase".to_string());
+        let listing = list_bundle(&bundle, &unlock).expect("list");
+        assert!(!listing.legacy);
+        assert_eq!(
+            listing.manifest.counts,
+            ManifestCounts {
+                projects: 1,
+                keys: 1,
+                tokens: 1,
+                jwks: 0
+            }
+        );
+        assert_eq!(listing.manifest.entries[1].kind, "key");
+        assert_eq!(
+            listing.manifest.entries[1].project_id.as_deref(),
+            Some("p1")
+        );
+
+        let mut payload = URL_SAFE_NO_PAD.decode(&bundle.ciphertext).expect("decode");
+        payload.truncate(payload.len() - 3);
+        bundle.ciphertext = URL_SAFE_NO_PAD.encode(payload);
+        let err = list_bundle(&bundle, &unlock).expect_err("truncated payload");
+        assert!(err.to_string().contains("truncated"));
+        let err = unlock_snapshot(&bundle, &unlock).expect_err("truncated payload");
+        assert!(err.to_string().contains("truncated"));
+    }
+
+    #[test]
+    fn v2_bundle_rejects_missing_manifest() {
+        let snapshot = sample_snapshot();
+        let mut bundle = encrypt_snapshot(&snapshot, "passphrase").expect("encrypt");
+        bundle.manifest = None;
+        let err = decrypt_snapshot(&bundle, "passphrase").expect_err("manifest required");
+        assert!(err.to_string().contains("missing its manifest"));
+    }
+
+    #[test]
+    fn v1_bundle_still_imports_and_lists() {
+        let snapshot = sample_snapshot();
+        let mut bundle = encrypt_snapshot(&snapshot, "passphrase").expect("encrypt");
+        bundle.version = LEGACY_EXPORT_VERSION;
+        bundle.manifest = None;
+
+        let decoded = decrypt_snapshot(&bundle, "passphrase").expect("decrypt v1");
+        assert_eq!(decoded.keys[0].material, "secret");
+
+        let listing = list_bundle(&bundle, &BundleUnlock::Passphrase("passphrase".to_string()))
+            .expect("list v1");
+        assert!(listing.legacy);
+        assert_eq!(listing.manifest.counts.keys, 1);
+    }
+
+    #[test]
+    fn verify_manifest_detects_modified_entry() {
+        let snapshot = sample_snapshot();
+        let mut manifest = build_manifest(&snapshot, &[]).expect("manifest");
+        manifest.entries[1].sha256 = "0".repeat(64);
+        let err = verify_manifest(&manifest, &snapshot).expect_err("mismatch");
+        assert!(err.to_string().contains("manifest mismatch for key"));
+    }
 }
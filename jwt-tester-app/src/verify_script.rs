@@ -0,0 +1,148 @@
+use crate::error::{AppError, AppResult};
+#[cfg(feature = "script")]
+use rhai::module_resolvers::DummyModuleResolver;
+#[cfg(feature = "script")]
+use rhai::{Dynamic, Engine, Scope};
+use serde_json::Value;
+#[cfg(feature = "script")]
+use std::cell::RefCell;
+use std::path::Path;
+#[cfg(feature = "script")]
+use std::rc::Rc;
+#[cfg(feature = "script")]
+use tracing::debug;
+
+/// Steps a script may run before it is stopped, so a runaway loop fails instead of hanging.
+#[cfg(feature = "script")]
+const MAX_OPERATIONS: u64 = 1_000_000;
+#[cfg(feature = "script")]
+const MAX_COLLECTION_LEN: usize = 10_000;
+#[cfg(feature = "script")]
+const MAX_STRING_BYTES: usize = 64 * 1024;
+
+/// What a `verify --script` run found: how many `rule(...)` calls it made and the messages of
+/// those that failed.
+#[derive(Debug)]
+pub struct ScriptReport {
+    pub rules: usize,
+    pub failures: Vec<String>,
+}
+
+/// Runs a rhai script against a verified token. The script sees `claims` and `header` (maps) and
+/// `now` (Unix seconds), and calls `rule(condition, message)` for each check; every failed rule
+/// is collected. A script that evaluates to `false` also fails. Scripts cannot import modules,
+/// and `print`/`debug` go to the debug log.
+#[cfg(feature = "script")]
+pub fn run(path: &Path, header: &Value, claims: &Value, now: i64) -> AppResult<ScriptReport> {
+    let label = path.display().to_string();
+    let source = std::fs::read_to_string(path)
+        .map_err(|e| AppError::invalid_claims(format!("failed to read --script {label}: {e}")))?;
+
+    let checked = Rc::new(RefCell::new(ScriptReport {
+        rules: 0,
+        failures: Vec::new(),
+    }));
+    let engine = sandboxed_engine(Rc::clone(&checked));
+    let mut scope = Scope::new();
+    scope.push_constant("claims", to_dynamic(claims, "claims", &label)?);
+    scope.push_constant("header", to_dynamic(header, "header", &label)?);
+    scope.push_constant("now", now);
+
+    let result: Dynamic = engine
+        .eval_with_scope(&mut scope, &source)
+        .map_err(|e| AppError::invalid_claims(format!("--script {label}: {e}")))?;
+    drop(engine);
+    let mut report = Rc::try_unwrap(checked)
+        .map(RefCell::into_inner)
+        .map_err(|_| AppError::internal("script state is still in use"))?;
+    if result.as_bool() == Ok(false) {
+        report.failures.push(format!("{label} returned false"));
+    }
+    Ok(report)
+}
+
+#[cfg(not(feature = "script"))]
+pub fn run(path: &Path, _header: &Value, _claims: &Value, _now: i64) -> AppResult<ScriptReport> {
+    Err(AppError::invalid_claims(format!(
+        "--script {} requires a build with the `script` feature",
+        path.display()
+    )))
+}
+
+#[cfg(feature = "script")]
+fn sandboxed_engine(report: Rc<RefCell<ScriptReport>>) -> Engine {
+    let mut engine = Engine::new();
+    engine
+        .set_module_resolver(DummyModuleResolver::new())
+        .set_max_operations(MAX_OPERATIONS)
+        .set_max_call_levels(32)
+        .set_max_expr_depths(64, 32)
+        .set_max_string_size(MAX_STRING_BYTES)
+        .set_max_array_size(MAX_COLLECTION_LEN)
+        .set_max_map_size(MAX_COLLECTION_LEN)
+        .on_print(|text| debug!("verify script: {text}"))
+        .on_debug(|text, _, _| debug!("verify script: {text}"));
+    engine.register_fn("rule", move |ok: bool, message: &str| {
+        let mut report = report.borrow_mut();
+        report.rules += 1;
+        if !ok {
+            report.failures.push(message.to_string());
+        }
+    });
+    engine
+}
+
+#[cfg(feature = "script")]
+fn to_dynamic(value: &Value, name: &str, label: &str) -> AppResult<Dynamic> {
+    rhai::serde::to_dynamic(value).map_err(|e| {
+        AppError::invalid_claims(format!("--script {label}: cannot expose {name}: {e}"))
+    })
+}
+
+#[cfg(all(test, feature = "script"))]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn script(source: &str) -> (tempfile::TempDir, std::path::PathBuf) {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("rules.rhai");
+        std::fs::write(&path, source).expect("write script");
+        (dir, path)
+    }
+
+    #[test]
+    fn collects_every_failed_rule() {
+        let (_dir, path) = script(
+            r#"
+            rule(header.alg == "HS256", "alg must be HS256");
+            rule(claims.scope.split(" ").contains("admin"), "admin scope required");
+            rule(claims.exp - now <= 3600, "lifetime over an hour");
+            "#,
+        );
+        let header = json!({ "alg": "HS256" });
+        let claims = json!({ "scope": "read write", "exp": 10_000 });
+        let report = run(&path, &header, &claims, 1_000).unwrap();
+        assert_eq!(report.rules, 3);
+        assert_eq!(
+            report.failures,
+            ["admin scope required", "lifetime over an hour"]
+        );
+    }
+
+    #[test]
+    fn false_result_fails_and_runaway_scripts_stop() {
+        let (_dir, path) = script("claims.sub == \"svc\"");
+        let report = run(&path, &json!({}), &json!({ "sub": "user" }), 0).unwrap();
+        assert_eq!(report.rules, 0);
+        assert_eq!(report.failures.len(), 1);
+        assert!(report.failures[0].ends_with("returned false"));
+
+        let (_dir, path) = script("loop {}");
+        let err = run(&path, &json!({}), &json!({}), 0).unwrap_err();
+        assert!(err.message.contains("--script"), "{}", err.message);
+
+        let (_dir, path) = script("import \"other\" as other; true");
+        assert!(run(&path, &json!({}), &json!({}), 0).is_err());
+    }
+}
@@ -0,0 +1,611 @@
+use crate::cli::{JwtAlg, VerifyCommonArgs};
+use crate::date_utils::{normalize_timestamps, timestamp_warning};
+use crate::error::{AppError, AppResult, ErrorKind};
+use crate::jwt_ops::{self, VerifyOptions};
+use crate::key_resolver::{
+    resolve_verification_key, resolve_verification_key_with_vault, KeySource, KeyTrace,
+};
+use crate::vault::{token_hash, ProjectEntry, Vault, VaultConfig};
+use jsonwebtoken::Algorithm;
+use serde_json::{json, Map, Value};
+use std::path::PathBuf;
+use std::time::Instant;
+use tracing::warn;
+
+mod policy;
+
+/// A successfully verified token. `verify`, `decode`, `webhook verify` and the UI all go through
+/// this module so key retries and `--explain` output cannot drift apart.
+#[derive(Debug, Clone)]
+pub struct Verified {
+    pub claims: Value,
+    pub alg: Algorithm,
+    pub alg_inferred: bool,
+    pub key_label: String,
+    pub explain: Option<Value>,
+    /// Original values of the time claims that were read as milliseconds; `claims` has seconds.
+    pub normalized_timestamps: Map<String, Value>,
+}
+
+impl Verified {
+    /// The `{ valid, claims, explain? }` object every caller reports.
+    pub fn to_json(&self) -> Value {
+        let mut info = json!({ "valid": true, "claims": self.claims });
+        if let Some(explain) = &self.explain {
+            info["explain"] = explain.clone();
+        }
+        if !self.normalized_timestamps.is_empty() {
+            info["normalized_timestamps"] = Value::Object(self.normalized_timestamps.clone());
+        }
+        info
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ResolvedAlg {
+    pub alg: Algorithm,
+    pub inferred: bool,
+}
+
+/// The explicit `--alg`, or the token header's `alg` when none was given.
+pub fn resolve_alg(alg: Option<JwtAlg>, token: &str) -> AppResult<ResolvedAlg> {
+    if let Some(val) = alg {
+        return Ok(ResolvedAlg {
+            alg: Algorithm::from(val),
+            inferred: false,
+        });
+    }
+    let header = jwt_ops::decode_header_only(token)?;
+    Ok(ResolvedAlg {
+        alg: header.alg,
+        inferred: true,
+    })
+}
+
+/// `--explain` decision log: each step with its outcome and duration, plus the key resolver's
+/// decisions. It is attached to the result, or to the error's details, so a failure in a project
+/// with many keys shows which keys were considered and why each was used or skipped.
+struct ExplainLog {
+    enabled: bool,
+    steps: Vec<Value>,
+    trace: KeyTrace,
+}
+
+impl ExplainLog {
+    fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            steps: Vec::new(),
+            trace: KeyTrace::default(),
+        }
+    }
+
+    fn step<T>(
+        &mut self,
+        name: &str,
+        run: impl FnOnce(&mut KeyTrace) -> AppResult<T>,
+    ) -> AppResult<T> {
+        let started = Instant::now();
+        let result = run(&mut self.trace);
+        if self.enabled {
+            let elapsed = u64::try_from(started.elapsed().as_micros()).unwrap_or(u64::MAX);
+            let mut step = json!({ "step": name, "ok": result.is_ok(), "elapsed_us": elapsed });
+            if let Err(err) = &result {
+                step["error"] = json!(err.message);
+            }
+            self.steps.push(step);
+        }
+        result
+    }
+
+    fn finish(self, result: AppResult<Verified>) -> AppResult<Verified> {
+        if !self.enabled {
+            return result;
+        }
+        let decisions = self.trace.to_json();
+        let steps = Value::Array(self.steps);
+        match result {
+            Ok(mut verified) => {
+                if let Some(explain) = verified.explain.as_mut() {
+                    explain["decisions"] = decisions;
+                    explain["steps"] = steps;
+                }
+                Ok(verified)
+            }
+            Err(err) => {
+                let mut details = err.details.clone().unwrap_or_else(|| json!({}));
+                if let Some(map) = details.as_object_mut() {
+                    map.insert(
+                        "explain".to_string(),
+                        json!({ "decisions": decisions, "steps": steps }),
+                    );
+                }
+                Err(err.with_details(details))
+            }
+        }
+    }
+}
+
+/// Resolves the key the CLI way (direct inputs, JWKS, or a freshly opened vault) and verifies.
+pub fn verify(
+    no_persist: bool,
+    data_dir: Option<PathBuf>,
+    args: &VerifyCommonArgs,
+    token: &str,
+) -> AppResult<Verified> {
+    let mut log = ExplainLog::new(args.explain);
+    let result = (|| {
+        let resolved = log.step("resolve_alg", |_| resolve_alg(args.alg, token))?;
+        let source = log.step("resolve_key", |trace| {
+            resolve_verification_key(
+                no_persist,
+                data_dir.clone(),
+                args,
+                token,
+                resolved.alg,
+                trace,
+            )
+        })?;
+        let verified = log.step("verify", |_| {
+            verify_with_source(args, token, resolved, source)
+        })?;
+        log.step("project_policy", |_| {
+            enforce_project_policy_in(no_persist, data_dir, args, token, verified)
+        })
+    })();
+    log.finish(result)
+}
+
+/// Like [`verify`], but skips `exp` and `nbf`: the signature and every other claim check still
+/// apply. `verify --sweep` uses it to evaluate validity at times other than now.
+pub fn verify_untimed(
+    no_persist: bool,
+    data_dir: Option<PathBuf>,
+    args: &VerifyCommonArgs,
+    token: &str,
+) -> AppResult<Verified> {
+    let mut log = ExplainLog::new(args.explain);
+    let result = (|| {
+        let resolved = log.step("resolve_alg", |_| resolve_alg(args.alg, token))?;
+        let source = log.step("resolve_key", |trace| {
+            resolve_verification_key(
+                no_persist,
+                data_dir.clone(),
+                args,
+                token,
+                resolved.alg,
+                trace,
+            )
+        })?;
+        let verified = log.step("verify", |_| {
+            verify_candidates(args, token, resolved, source, false)
+        })?;
+        log.step("project_policy", |_| {
+            enforce_project_policy_in(no_persist, data_dir, args, token, verified)
+        })
+    })();
+    log.finish(result)
+}
+
+/// Like [`verify`], against an already open vault (the UI server's).
+pub fn verify_with_vault(
+    vault: &Vault,
+    args: &VerifyCommonArgs,
+    token: &str,
+) -> AppResult<Verified> {
+    let mut log = ExplainLog::new(args.explain);
+    let result = (|| {
+        let resolved = log.step("resolve_alg", |_| resolve_alg(args.alg, token))?;
+        let source = log.step("resolve_key", |trace| {
+            resolve_verification_key_with_vault(vault, args, token, resolved.alg, trace)
+        })?;
+        let verified = log.step("verify", |_| {
+            verify_with_source(args, token, resolved, source)
+        })?;
+        log.step("project_policy", |_| {
+            enforce_project_policy(vault, args, token, &verified)
+        })?;
+        Ok(verified)
+    })();
+    log.finish(result)
+}
+
+/// Like [`verify_untimed`], against an already open vault.
+pub fn verify_untimed_with_vault(
+    vault: &Vault,
+    args: &VerifyCommonArgs,
+    token: &str,
+) -> AppResult<Verified> {
+    let mut log = ExplainLog::new(args.explain);
+    let result = (|| {
+        let resolved = log.step("resolve_alg", |_| resolve_alg(args.alg, token))?;
+        let source = log.step("resolve_key", |trace| {
+            resolve_verification_key_with_vault(vault, args, token, resolved.alg, trace)
+        })?;
+        let verified = log.step("verify", |_| {
+            verify_candidates(args, token, resolved, source, false)
+        })?;
+        log.step("project_policy", |_| {
+            enforce_project_policy(vault, args, token, &verified)
+        })?;
+        Ok(verified)
+    })();
+    log.finish(result)
+}
+
+fn enforce_project_policy_in(
+    no_persist: bool,
+    data_dir: Option<PathBuf>,
+    args: &VerifyCommonArgs,
+    token: &str,
+    verified: Verified,
+) -> AppResult<Verified> {
+    if args.project.is_some() {
+        let vault = Vault::open(VaultConfig {
+            no_persist,
+            data_dir,
+        })
+        .map_err(|e| AppError::invalid_key(e.to_string()))?;
+        enforce_project_policy(&vault, args, token, &verified)?;
+    }
+    Ok(verified)
+}
+
+/// `--project` rules apply however the key was found. A project that does not exist only matters
+/// when its keys were needed, and key resolution has already reported that, or when its
+/// revocation list was asked for.
+fn enforce_project_policy(
+    vault: &Vault,
+    args: &VerifyCommonArgs,
+    token: &str,
+    verified: &Verified,
+) -> AppResult<()> {
+    let Some(name) = args.project.as_deref() else {
+        return Ok(());
+    };
+    let project = vault
+        .find_project_by_name(name)
+        .map_err(|e| AppError::invalid_key(e.to_string()))?;
+    match project {
+        Some(project) => {
+            policy::enforce(&project, verified.alg, token, &verified.claims)?;
+            policy::enforce_wasm_module(&project, token, &verified.claims)?;
+            if args.check_revocation {
+                check_revocation(vault, &project, token, &verified.claims)?;
+            }
+            Ok(())
+        }
+        None if args.check_revocation => {
+            Err(AppError::not_found(format!("project not found: {name}")))
+        }
+        None => Ok(()),
+    }
+}
+
+/// `--check-revocation`: fails when the token's `jti` or hash is on the project's list.
+fn check_revocation(
+    vault: &Vault,
+    project: &ProjectEntry,
+    token: &str,
+    claims: &Value,
+) -> AppResult<()> {
+    let jti = claims.get("jti").and_then(Value::as_str);
+    let hash = token_hash(token);
+    let revocation = vault
+        .find_revocation(&project.id, jti, Some(&hash))
+        .map_err(|e| AppError::invalid_key(e.to_string()))?;
+    let Some(revocation) = revocation else {
+        return Ok(());
+    };
+    let matched = if jti.is_some() && revocation.jti.as_deref() == jti {
+        "jti"
+    } else {
+        "token_hash"
+    };
+    Err(AppError::revoked(format!(
+        "token is revoked in project {} (by {matched})",
+        project.name
+    ))
+    .with_details(json!({ "matched": matched, "revocation": revocation })))
+}
+
+/// Tries each candidate key in order. Only signature failures move on to the next key: any other
+/// failure (expired, wrong audience, ...) means the signature matched, so it is reported against
+/// that key instead of being folded into a generic "no key matched" error.
+pub fn verify_with_source(
+    args: &VerifyCommonArgs,
+    token: &str,
+    resolved: ResolvedAlg,
+    source: KeySource,
+) -> AppResult<Verified> {
+    verify_candidates(args, token, resolved, source, true)
+}
+
+fn verify_candidates(
+    args: &VerifyCommonArgs,
+    token: &str,
+    resolved: ResolvedAlg,
+    source: KeySource,
+    check_times: bool,
+) -> AppResult<Verified> {
+    let verify_opts = VerifyOptions {
+        alg: resolved.alg,
+        leeway_secs: args.leeway_secs,
+        ignore_exp: args.ignore_exp || !check_times,
+        ignore_nbf: !check_times,
+        iss: args.iss.clone(),
+        sub: args.sub.clone(),
+        aud: args.aud.clone(),
+        require: args.require.clone(),
+        acr: args.require_acr.clone(),
+        max_auth_age: args.max_auth_age.map(|age| age.as_secs()),
+        timestamps: args.timestamps,
+    };
+    let normalized_timestamps = match jwt_ops::decode_unverified(token) {
+        Ok(decoded) => normalize_timestamps(&mut decoded.payload_json.clone(), args.timestamps),
+        Err(_) => Map::new(),
+    };
+    if let Some(warning) = timestamp_warning(&normalized_timestamps) {
+        warn!("{warning}");
+    }
+    let (candidates, label, multiple) = match source {
+        KeySource::Single(key, label) => (vec![(key, label.clone())], label, false),
+        KeySource::Multiple(keys, label) => (keys, label, true),
+    };
+
+    let total = candidates.len();
+    let mut attempts = Vec::new();
+    for (key, name) in candidates {
+        let started = Instant::now();
+        let result = jwt_ops::verify_token(token, &key, verify_opts.clone())
+            .map_err(|err| keep_checks_for_explain(err, args.explain));
+        let elapsed_us = u64::try_from(started.elapsed().as_micros()).unwrap_or(u64::MAX);
+        match result {
+            Ok(token_data) => {
+                attempts
+                    .push(json!({ "key": name, "result": "verified", "elapsed_us": elapsed_us }));
+                let explain = args.explain.then(|| {
+                    let mut explain = build_verify_explain(args, &label, resolved);
+                    if multiple {
+                        explain["attempts"] = json!(attempts);
+                    }
+                    explain
+                });
+                return Ok(Verified {
+                    claims: token_data.claims,
+                    alg: resolved.alg,
+                    alg_inferred: resolved.inferred,
+                    key_label: label,
+                    explain,
+                    normalized_timestamps,
+                });
+            }
+            Err(err) if matches!(err.kind, ErrorKind::InvalidSignature) => {
+                attempts.push(json!({
+                    "key": name,
+                    "result": "signature_mismatch",
+                    "elapsed_us": elapsed_us,
+                }));
+                if !multiple {
+                    return Err(err);
+                }
+            }
+            Err(err) if !multiple => return Err(err),
+            Err(err) => {
+                attempts.push(json!({
+                    "key": name,
+                    "result": attempt_result(&err),
+                    "error": err.message,
+                    "elapsed_us": elapsed_us,
+                }));
+                let mut details = err.details.clone().unwrap_or_else(|| json!({}));
+                details["matched_key"] = json!(name);
+                if !normalized_timestamps.is_empty() {
+                    details["normalized_timestamps"] = Value::Object(normalized_timestamps);
+                }
+                if args.explain {
+                    details["attempts"] = json!(attempts);
+                }
+                let message = format!("signature matched key '{name}', but {}", err.message);
+                return Err(AppError::new(err.kind, message).with_details(details));
+            }
+        }
+    }
+
+    let mut err = AppError::invalid_signature(format!(
+        "signature did not match any of {total} candidate keys"
+    ));
+    if args.explain {
+        err = err.with_details(json!({ "attempts": attempts }));
+    }
+    Err(err)
+}
+
+/// Claim failures carry every check that ran (`checks`) next to the failed ones (`violations`);
+/// the passing checks are only part of `--explain` output.
+fn keep_checks_for_explain(mut err: AppError, explain: bool) -> AppError {
+    if !explain {
+        if let Some(details) = err.details.as_mut().and_then(Value::as_object_mut) {
+            details.remove("checks");
+        }
+    }
+    err
+}
+
+/// Classifies a non-signature failure for the `--explain` attempt list.
+fn attempt_result(err: &AppError) -> &'static str {
+    match err.details.as_ref().and_then(|d| d["reason"].as_str()) {
+        Some("expired") => "expired",
+        Some("not_yet_valid") => "not_yet_valid",
+        _ if err.kind == ErrorKind::InvalidClaims => "claims_invalid",
+        _ => "error",
+    }
+}
+
+fn build_verify_explain(args: &VerifyCommonArgs, key_source: &str, resolved: ResolvedAlg) -> Value {
+    json!({
+        "alg": format!("{:?}", resolved.alg),
+        "alg_inferred": resolved.inferred,
+        "key_source": key_source,
+        "iss": args.iss,
+        "sub": args.sub,
+        "aud": args.aud,
+        "leeway_secs": args.leeway_secs,
+        "ignore_exp": args.ignore_exp,
+        "require": args.require,
+        "require_acr": args.require_acr,
+        "max_auth_age_secs": args.max_auth_age.map(|age| age.as_secs()),
+        "check_revocation": args.check_revocation,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::TimestampUnit;
+    use crate::jwt_ops::VerifyKey;
+    use jsonwebtoken::{EncodingKey, Header};
+
+    fn base_args() -> VerifyCommonArgs {
+        VerifyCommonArgs {
+            secret: None,
+            key: None,
+            jwks: Vec::new(),
+            key_format: None,
+            kid: None,
+            allow_single_jwk: false,
+            project: None,
+            key_id: None,
+            key_name: None,
+            key_fingerprint: None,
+            try_all_keys: false,
+            auto_refresh: false,
+            ignore_exp: false,
+            leeway_secs: 30,
+            jwks_negative_ttl: std::time::Duration::from_secs(300),
+            timestamps: TimestampUnit::Auto,
+            iss: None,
+            sub: None,
+            aud: Vec::new(),
+            require: Vec::new(),
+            require_acr: Vec::new(),
+            max_auth_age: None,
+            check_revocation: false,
+            explain: false,
+            alg: None,
+        }
+    }
+
+    fn make_token() -> String {
+        let header = Header::new(Algorithm::HS256);
+        jwt_ops::encode_token(
+            &header,
+            &json!({ "sub": "tester", "iss": "issuer" }),
+            &EncodingKey::from_secret(b"secret"),
+        )
+        .expect("encode token")
+    }
+
+    #[test]
+    fn resolve_alg_infers_from_header() {
+        let token = make_token();
+        let resolved = resolve_alg(None, &token).expect("resolve");
+        assert_eq!(resolved.alg, Algorithm::HS256);
+        assert!(resolved.inferred);
+    }
+
+    #[test]
+    fn resolve_alg_uses_explicit_value() {
+        let token = make_token();
+        let resolved = resolve_alg(Some(JwtAlg::HS512), &token).expect("resolve");
+        assert_eq!(resolved.alg, Algorithm::HS512);
+        assert!(!resolved.inferred);
+    }
+
+    #[test]
+    fn build_verify_explain_contains_expected_fields() {
+        let mut args = base_args();
+        args.iss = Some("issuer".to_string());
+        args.aud = vec!["aud1".to_string()];
+        let resolved = resolve_alg(Some(JwtAlg::HS256), &make_token()).expect("resolve");
+        let explain = build_verify_explain(&args, "secret", resolved);
+        assert_eq!(explain["key_source"], "secret");
+        assert_eq!(explain["alg_inferred"], false);
+        assert_eq!(explain["iss"], "issuer");
+        assert_eq!(explain["aud"][0], "aud1");
+    }
+
+    #[test]
+    fn multiple_keys_retry_only_on_signature_errors() {
+        let token = make_token();
+        let mut args = base_args();
+        args.explain = true;
+        let resolved = resolve_alg(None, &token).expect("resolve");
+        let candidates = |secrets: &[&str]| {
+            KeySource::Multiple(
+                secrets
+                    .iter()
+                    .map(|s| (VerifyKey::from_secret(s.as_bytes()), format!("k-{s}")))
+                    .collect(),
+                "vault".to_string(),
+            )
+        };
+
+        let verified =
+            verify_with_source(&args, &token, resolved, candidates(&["wrong", "secret"]))
+                .expect("second key verifies");
+        assert_eq!(verified.key_label, "vault");
+        let explain = &verified.to_json()["explain"];
+        assert_eq!(explain["key_source"], "vault");
+        assert_eq!(explain["attempts"][0]["result"], "signature_mismatch");
+        assert_eq!(explain["attempts"][1]["key"], "k-secret");
+        assert_eq!(explain["attempts"][1]["result"], "verified");
+
+        let err = verify_with_source(&args, &token, resolved, candidates(&["wrong", "other"]))
+            .expect_err("no key matches");
+        assert_eq!(err.exit_code(), 11);
+        assert!(err
+            .message
+            .contains("did not match any of 2 candidate keys"));
+        assert_eq!(
+            err.details.as_ref().unwrap()["attempts"][1]["key"],
+            "k-other"
+        );
+
+        args.iss = Some("someone-else".to_string());
+        let err = verify_with_source(&args, &token, resolved, candidates(&["secret", "wrong"]))
+            .expect_err("claim failure is final");
+        assert_eq!(err.exit_code(), 12);
+        assert!(err.message.contains("signature matched key 'k-secret'"));
+        let details = err.details.expect("details");
+        assert_eq!(details["matched_key"], "k-secret");
+        assert_eq!(details["attempts"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn expired_token_reports_the_matching_key() {
+        let token = jwt_ops::encode_token(
+            &Header::new(Algorithm::HS256),
+            &json!({ "sub": "tester", "exp": 1 }),
+            &EncodingKey::from_secret(b"secret"),
+        )
+        .expect("encode token");
+        let mut args = base_args();
+        args.explain = true;
+        let resolved = resolve_alg(None, &token).expect("resolve");
+        let source = KeySource::Multiple(
+            vec![
+                (VerifyKey::from_secret(b"wrong"), "old".to_string()),
+                (VerifyKey::from_secret(b"secret"), "current".to_string()),
+            ],
+            "vault".to_string(),
+        );
+        let err = verify_with_source(&args, &token, resolved, source).expect_err("expired");
+        assert_eq!(err.exit_code(), 12);
+        let details = err.details.expect("details");
+        assert_eq!(details["reason"], "expired");
+        assert_eq!(details["matched_key"], "current");
+        assert_eq!(details["attempts"][0]["result"], "signature_mismatch");
+        assert_eq!(details["attempts"][1]["result"], "expired");
+    }
+}
@@ -0,0 +1,121 @@
+use crate::claim_plugin;
+use crate::cli::AlgFamily;
+use crate::error::{AppError, AppResult};
+use crate::jwt_ops;
+use crate::vault::{FamilyPolicy, ProjectEntry};
+use jsonwebtoken::Algorithm;
+use serde_json::{json, Value};
+
+/// Applies the project's rules for the token's algorithm family. Families without rules pass.
+pub(super) fn enforce(
+    project: &ProjectEntry,
+    alg: Algorithm,
+    token: &str,
+    claims: &Value,
+) -> AppResult<()> {
+    let family = AlgFamily::of(alg).as_str();
+    let Some(rules) = project.policy.get(family) else {
+        return Ok(());
+    };
+    let header_segment = token.split('.').next().unwrap_or_default();
+    let header = jwt_ops::decode_segment_json(header_segment, "header")?;
+    let violations = violations(rules, &header, claims);
+    if violations.is_empty() {
+        return Ok(());
+    }
+    Err(AppError::invalid_claims(format!(
+        "project {} policy for {family} tokens: {}",
+        project.name,
+        violations.join("; ")
+    ))
+    .with_details(json!({
+        "reason": "policy",
+        "project": project.name,
+        "family": family,
+        "violations": violations,
+    })))
+}
+
+/// Runs the project's WASM module, if any, and fails with the violations its `jwt_validate`
+/// export reports.
+pub(super) fn enforce_wasm_module(
+    project: &ProjectEntry,
+    token: &str,
+    claims: &Value,
+) -> AppResult<()> {
+    let Some(module) = &project.wasm_module else {
+        return Ok(());
+    };
+    let header_segment = token.split('.').next().unwrap_or_default();
+    let header = jwt_ops::decode_segment_json(header_segment, "header")?;
+    let violations = claim_plugin::validate(module, &header, claims)?;
+    if violations.is_empty() {
+        return Ok(());
+    }
+    Err(AppError::invalid_claims(format!(
+        "project {} wasm module: {}",
+        project.name,
+        violations.join("; ")
+    ))
+    .with_details(json!({
+        "reason": "wasm_module",
+        "project": project.name,
+        "module": module,
+        "violations": violations,
+    })))
+}
+
+fn violations(rules: &FamilyPolicy, header: &Value, claims: &Value) -> Vec<String> {
+    let present = |object: &Value, name: &str| object.get(name).is_some_and(|v| !v.is_null());
+    let mut out = Vec::new();
+    for name in &rules.required_headers {
+        if !present(header, name) {
+            out.push(format!("missing header {name}"));
+        }
+    }
+    for name in &rules.required_claims {
+        if !present(claims, name) {
+            out.push(format!("missing claim {name}"));
+        }
+    }
+    if !rules.allowed_issuers.is_empty() {
+        match claims.get("iss").and_then(Value::as_str) {
+            Some(iss) if rules.allowed_issuers.iter().any(|allowed| allowed == iss) => {}
+            Some(iss) => out.push(format!("issuer '{iss}' is not allowed")),
+            None => out.push("missing claim iss (issuer allow-list is set)".to_string()),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_missing_headers_claims_and_disallowed_issuers() {
+        let rules = FamilyPolicy {
+            required_headers: vec!["kid".to_string(), "x5t".to_string()],
+            required_claims: vec!["sub".to_string()],
+            allowed_issuers: vec!["https://idp.internal".to_string()],
+        };
+        let header = json!({ "alg": "HS256", "kid": "k1" });
+        let ok = json!({ "sub": "svc", "iss": "https://idp.internal" });
+        assert_eq!(violations(&rules, &header, &ok), ["missing header x5t"]);
+
+        let header = json!({ "alg": "HS256", "kid": "k1", "x5t": "abc" });
+        assert!(violations(&rules, &header, &ok).is_empty());
+        assert_eq!(
+            violations(
+                &rules,
+                &header,
+                &json!({ "sub": null, "iss": "https://evil" })
+            ),
+            ["missing claim sub", "issuer 'https://evil' is not allowed"]
+        );
+        assert_eq!(
+            violations(&rules, &header, &json!({ "sub": "svc" })),
+            ["missing claim iss (issuer allow-list is set)"]
+        );
+    }
+}
@@ -0,0 +1,180 @@
+use crate::cli::{App, Command};
+use crate::error::{AppError, AppResult};
+use clap::error::ErrorKind;
+use clap::{CommandFactory, FromArgMatches};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// File `jwt-tester init` writes; commands run anywhere below it pick up its defaults.
+pub const WORKSPACE_FILE: &str = ".jwt-tester";
+
+/// Contents of a `.jwt-tester` file (JSON).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Workspace {
+    /// Vault project (name or id) used when a command is not given `--project` or a key.
+    pub project: String,
+    /// Default `encode --alg`, as accepted on the command line.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alg: Option<String>,
+    /// Default `encode` claims; a relative `@file` is resolved from the workspace directory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub claims: Option<String>,
+}
+
+/// A workspace file and the directory it applies to.
+#[derive(Debug, Clone)]
+pub struct Loaded {
+    pub dir: PathBuf,
+    pub workspace: Workspace,
+}
+
+/// The nearest `.jwt-tester` in `start` or one of its parents, like `.git` or `.envrc`.
+pub fn find(start: &Path) -> AppResult<Option<Loaded>> {
+    for dir in start.ancestors() {
+        let path = dir.join(WORKSPACE_FILE);
+        if !path.is_file() {
+            continue;
+        }
+        let raw = std::fs::read_to_string(&path).map_err(|e| {
+            AppError::invalid_claims(format!("failed to read {}: {e}", path.display()))
+        })?;
+        let workspace: Workspace = serde_json::from_str(&raw).map_err(|e| {
+            AppError::invalid_claims(format!("invalid workspace file {}: {e}", path.display()))
+        })?;
+        return Ok(Some(Loaded {
+            dir: dir.to_path_buf(),
+            workspace,
+        }));
+    }
+    Ok(None)
+}
+
+/// Parses the command line. With a workspace, arguments it can supply stop being required:
+/// every required `--project` and `encode --alg` default to the workspace values.
+pub fn parse_app(loaded: Option<&Loaded>) -> App {
+    let mut command = cli_command(loaded);
+    let mut matches = command.get_matches_mut();
+    let app = App::from_arg_matches_mut(&mut matches).unwrap_or_else(|err| err.exit());
+    match (&app.command, app.rpc) {
+        (None, false) => command
+            .error(
+                ErrorKind::MissingSubcommand,
+                "a subcommand is required unless --rpc is given",
+            )
+            .exit(),
+        (Some(_), true) => command
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--rpc reads its commands from stdin and cannot be combined with a subcommand",
+            )
+            .exit(),
+        _ => app,
+    }
+}
+
+/// The clap command `parse_app` uses; `--rpc` parses each request's arguments with it too.
+pub fn cli_command(loaded: Option<&Loaded>) -> clap::Command {
+    let command = App::command();
+    match loaded {
+        Some(loaded) => with_defaults(command, &loaded.workspace),
+        None => command,
+    }
+}
+
+fn with_defaults(mut command: clap::Command, workspace: &Workspace) -> clap::Command {
+    let subcommands: Vec<String> = command
+        .get_subcommands()
+        .map(|sub| sub.get_name().to_string())
+        .collect();
+    for name in subcommands {
+        command = command.mut_subcommand(name, |sub| with_defaults(sub, workspace));
+    }
+    let required_project = command
+        .get_arguments()
+        .any(|arg| arg.get_id() == "project" && arg.is_required_set());
+    if required_project {
+        let project = leak(&workspace.project);
+        command = command.mut_arg("project", |arg| arg.required(false).default_value(project));
+    }
+    if let (Some(alg), "encode") = (&workspace.alg, command.get_name()) {
+        let alg = leak(alg);
+        command = command.mut_arg("alg", |arg| arg.required(false).default_value(alg));
+    }
+    command
+}
+
+/// Clap keeps default values for the life of the command; there is one parse per process.
+fn leak(value: &str) -> &'static str {
+    Box::leak(value.to_string().into_boxed_str())
+}
+
+/// Fills optional arguments from the workspace. The project only applies when no other key
+/// source was given, so `--secret`, `--key` and `--jwks` keep working inside a workspace.
+pub fn apply(command: &mut Command, loaded: &Loaded) {
+    let workspace = &loaded.workspace;
+    match command {
+        Command::Encode(args) => {
+            if args.project.is_none() && args.secret.is_none() && args.key.is_none() {
+                args.project = Some(workspace.project.clone());
+            }
+            if args.claims.is_none() {
+                args.claims = workspace
+                    .claims
+                    .as_deref()
+                    .map(|claims| resolve_claims(&loaded.dir, claims));
+            }
+        }
+        Command::Verify(args) => {
+            let verify = &mut args.verify;
+            if verify.project.is_none()
+                && verify.secret.is_none()
+                && verify.key.is_none()
+                && verify.jwks.is_empty()
+            {
+                verify.project = Some(workspace.project.clone());
+            }
+        }
+        _ => {}
+    }
+}
+
+fn resolve_claims(dir: &Path, claims: &str) -> String {
+    match claims.strip_prefix('@') {
+        Some(path) if Path::new(path).is_relative() => {
+            format!("@{}", dir.join(path).display())
+        }
+        _ => claims.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn find_walks_up_to_the_nearest_workspace() {
+        let root = TempDir::new().unwrap();
+        let nested = root.path().join("a").join("b");
+        std::fs::create_dir_all(&nested).unwrap();
+        assert!(find(&nested).unwrap().is_none());
+
+        std::fs::write(
+            root.path().join(WORKSPACE_FILE),
+            r#"{"project":"demo","claims":"@claims.json"}"#,
+        )
+        .unwrap();
+        let loaded = find(&nested).unwrap().expect("workspace");
+        assert_eq!(loaded.dir, root.path());
+        assert_eq!(loaded.workspace.project, "demo");
+        assert_eq!(
+            resolve_claims(&loaded.dir, "@claims.json"),
+            format!("@{}", root.path().join("claims.json").display())
+        );
+        assert_eq!(resolve_claims(&loaded.dir, "{}"), "{}");
+
+        std::fs::write(root.path().join(WORKSPACE_FILE), r#"{"projct":"demo"}"#).unwrap();
+        assert!(find(&nested).is_err());
+    }
+}
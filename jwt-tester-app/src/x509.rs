@@ -0,0 +1,329 @@
+//! Just enough DER to summarize an X.509 chain (RFC 5280) and pull out the leaf's public key.
+//! Nothing here validates signatures or trust; callers only compare keys and thumbprints.
+
+use crate::error::{AppError, AppResult};
+use crate::jwt_ops::VerifyKey;
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+const TAG_SEQUENCE: u8 = 0x30;
+const TAG_SET: u8 = 0x31;
+const TAG_OID: u8 = 0x06;
+const TAG_BIT_STRING: u8 = 0x03;
+const TAG_UTC_TIME: u8 = 0x17;
+const TAG_GENERALIZED_TIME: u8 = 0x18;
+const TAG_EXPLICIT_VERSION: u8 = 0xA0;
+
+const OID_RSA: &[u8] = &[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x01, 0x01];
+const OID_EC: &[u8] = &[0x2A, 0x86, 0x48, 0xCE, 0x3D, 0x02, 0x01];
+const OID_P256: &[u8] = &[0x2A, 0x86, 0x48, 0xCE, 0x3D, 0x03, 0x01, 0x07];
+const OID_P384: &[u8] = &[0x2B, 0x81, 0x04, 0x00, 0x22];
+const OID_SECP256K1: &[u8] = &[0x2B, 0x81, 0x04, 0x00, 0x0A];
+const OID_ED25519: &[u8] = &[0x2B, 0x65, 0x70];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyType {
+    Rsa,
+    P256,
+    P384,
+    Secp256k1,
+    Ed25519,
+    Other,
+}
+
+impl KeyType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            KeyType::Rsa => "RSA",
+            KeyType::P256 => "EC P-256",
+            KeyType::P384 => "EC P-384",
+            KeyType::Secp256k1 => "EC secp256k1",
+            KeyType::Ed25519 => "Ed25519",
+            KeyType::Other => "unknown",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Certificate {
+    pub der: Vec<u8>,
+    pub subject: String,
+    pub issuer: String,
+    subject_der: Vec<u8>,
+    issuer_der: Vec<u8>,
+    pub not_before: i64,
+    pub not_after: i64,
+    pub key_type: KeyType,
+    /// The `subjectPublicKey` bits: PKCS#1 for RSA, the SEC1 point for EC, raw bytes for Ed25519.
+    public_key: Vec<u8>,
+}
+
+impl Certificate {
+    /// SHA-256 of the DER, base64url: the value an `x5t#S256` header carries.
+    pub fn thumbprint(&self) -> String {
+        URL_SAFE_NO_PAD.encode(Sha256::digest(&self.der))
+    }
+
+    pub fn self_signed(&self) -> bool {
+        self.subject_der == self.issuer_der
+    }
+
+    /// True when `issuer` names the certificate that should have signed this one.
+    pub fn issued_by(&self, issuer: &Certificate) -> bool {
+        self.issuer_der == issuer.subject_der
+    }
+
+    pub fn verify_key(&self) -> Option<VerifyKey> {
+        match self.key_type {
+            KeyType::Rsa => Some(VerifyKey::from_rsa_der(&self.public_key)),
+            KeyType::P256 | KeyType::P384 => Some(VerifyKey::from_ec_der(&self.public_key)),
+            KeyType::Ed25519 => Some(VerifyKey::from_ed_der(&self.public_key)),
+            KeyType::Secp256k1 | KeyType::Other => None,
+        }
+    }
+
+    pub fn to_json(&self, now: i64) -> Value {
+        json!({
+            "subject": self.subject,
+            "issuer": self.issuer,
+            "not_before": format_unix(self.not_before),
+            "not_after": format_unix(self.not_after),
+            "expired": self.not_after < now,
+            "key_type": self.key_type.as_str(),
+            "self_signed": self.self_signed(),
+            "x5t#S256": self.thumbprint(),
+        })
+    }
+}
+
+/// Reads every `CERTIFICATE` block of a PEM bundle, or a single DER certificate.
+pub fn parse_chain(bytes: &[u8]) -> AppResult<Vec<Certificate>> {
+    if bytes.first() == Some(&TAG_SEQUENCE) {
+        return Ok(vec![parse_certificate(bytes)?]);
+    }
+    let text = std::str::from_utf8(bytes)
+        .map_err(|_| AppError::invalid_key("certificate chain is neither PEM nor DER"))?;
+    let mut certs = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("-----BEGIN CERTIFICATE-----") {
+        let body = &rest[start + "-----BEGIN CERTIFICATE-----".len()..];
+        let end = body
+            .find("-----END CERTIFICATE-----")
+            .ok_or_else(|| AppError::invalid_key("unterminated PEM certificate"))?;
+        let b64: String = body[..end].split_whitespace().collect();
+        let der = STANDARD
+            .decode(b64)
+            .map_err(|e| AppError::invalid_key(format!("invalid PEM certificate: {e}")))?;
+        certs.push(parse_certificate(&der)?);
+        rest = &body[end..];
+    }
+    if certs.is_empty() {
+        return Err(AppError::invalid_key("no PEM certificates found"));
+    }
+    Ok(certs)
+}
+
+/// Decodes one `x5c` entry (standard base64 DER, RFC 7515 §4.1.6).
+pub fn parse_x5c_entry(entry: &str) -> AppResult<Certificate> {
+    let der = STANDARD
+        .decode(entry)
+        .map_err(|e| AppError::invalid_key(format!("invalid x5c entry: {e}")))?;
+    parse_certificate(&der)
+}
+
+pub fn parse_certificate(der: &[u8]) -> AppResult<Certificate> {
+    let (cert, _) = read_tlv(der, TAG_SEQUENCE)?;
+    let (tbs, _) = read_tlv(cert.body, TAG_SEQUENCE)?;
+
+    let mut rest = tbs.body;
+    if rest.first() == Some(&TAG_EXPLICIT_VERSION) {
+        rest = read_any(rest)?.1;
+    }
+    rest = read_any(rest)?.1; // serialNumber
+    rest = read_tlv(rest, TAG_SEQUENCE)?.1; // signature algorithm
+    let (issuer, rest) = read_tlv(rest, TAG_SEQUENCE)?;
+    let (validity, rest) = read_tlv(rest, TAG_SEQUENCE)?;
+    let (subject, rest) = read_tlv(rest, TAG_SEQUENCE)?;
+    let (spki, _) = read_tlv(rest, TAG_SEQUENCE)?;
+
+    let (not_before, after_rest) = read_any(validity.body)?;
+    let (not_after, _) = read_any(after_rest)?;
+
+    let (algorithm, key_rest) = read_tlv(spki.body, TAG_SEQUENCE)?;
+    let (bits, _) = read_tlv(key_rest, TAG_BIT_STRING)?;
+    let public_key = bits
+        .body
+        .split_first()
+        .map(|(_, key)| key.to_vec())
+        .unwrap_or_default();
+
+    Ok(Certificate {
+        der: cert.raw.to_vec(),
+        subject: render_name(subject.body),
+        issuer: render_name(issuer.body),
+        subject_der: subject.raw.to_vec(),
+        issuer_der: issuer.raw.to_vec(),
+        not_before: parse_time(&not_before)?,
+        not_after: parse_time(&not_after)?,
+        key_type: key_type(algorithm.body)?,
+        public_key,
+    })
+}
+
+struct Tlv<'a> {
+    tag: u8,
+    body: &'a [u8],
+    raw: &'a [u8],
+}
+
+fn read_tlv(input: &[u8], tag: u8) -> AppResult<(Tlv<'_>, &[u8])> {
+    let (tlv, rest) = read_any(input)?;
+    if tlv.tag != tag {
+        return Err(malformed());
+    }
+    Ok((tlv, rest))
+}
+
+fn read_any(input: &[u8]) -> AppResult<(Tlv<'_>, &[u8])> {
+    let (&tag, rest) = input.split_first().ok_or_else(malformed)?;
+    let (&first, rest) = rest.split_first().ok_or_else(malformed)?;
+    let (len, rest) = if first < 0x80 {
+        (first as usize, rest)
+    } else {
+        let count = (first & 0x7F) as usize;
+        if count == 0 || count > 4 || rest.len() < count {
+            return Err(malformed());
+        }
+        let len = rest[..count]
+            .iter()
+            .fold(0usize, |acc, byte| (acc << 8) | *byte as usize);
+        (len, &rest[count..])
+    };
+    if rest.len() < len {
+        return Err(malformed());
+    }
+    let header_len = input.len() - rest.len();
+    Ok((
+        Tlv {
+            tag,
+            body: &rest[..len],
+            raw: &input[..header_len + len],
+        },
+        &rest[len..],
+    ))
+}
+
+fn malformed() -> AppError {
+    AppError::invalid_key("malformed X.509 certificate")
+}
+
+fn key_type(algorithm: &[u8]) -> AppResult<KeyType> {
+    let (oid, params) = read_tlv(algorithm, TAG_OID)?;
+    Ok(match oid.body {
+        OID_RSA => KeyType::Rsa,
+        OID_ED25519 => KeyType::Ed25519,
+        OID_EC => match read_tlv(params, TAG_OID).map(|(curve, _)| curve.body) {
+            Ok(OID_P256) => KeyType::P256,
+            Ok(OID_P384) => KeyType::P384,
+            Ok(OID_SECP256K1) => KeyType::Secp256k1,
+            _ => KeyType::Other,
+        },
+        _ => KeyType::Other,
+    })
+}
+
+/// `CN=..., O=...` for the attributes people recognize; others are skipped.
+fn render_name(mut rdns: &[u8]) -> String {
+    let mut parts = Vec::new();
+    while let Ok((rdn, rest)) = read_tlv(rdns, TAG_SET) {
+        let mut attrs = rdn.body;
+        while let Ok((attr, next)) = read_tlv(attrs, TAG_SEQUENCE) {
+            if let Ok((oid, value)) = read_tlv(attr.body, TAG_OID) {
+                let label = match oid.body {
+                    [0x55, 0x04, 0x03] => Some("CN"),
+                    [0x55, 0x04, 0x06] => Some("C"),
+                    [0x55, 0x04, 0x07] => Some("L"),
+                    [0x55, 0x04, 0x08] => Some("ST"),
+                    [0x55, 0x04, 0x0A] => Some("O"),
+                    [0x55, 0x04, 0x0B] => Some("OU"),
+                    _ => None,
+                };
+                if let (Some(label), Ok((value, _))) = (label, read_any(value)) {
+                    parts.push(format!("{label}={}", String::from_utf8_lossy(value.body)));
+                }
+            }
+            attrs = next;
+        }
+        rdns = rest;
+    }
+    parts.join(", ")
+}
+
+/// UTCTime (`YYMMDDHHMMSSZ`, years 1950-2049) or GeneralizedTime (`YYYYMMDDHHMMSSZ`).
+fn parse_time(tlv: &Tlv<'_>) -> AppResult<i64> {
+    let text = std::str::from_utf8(tlv.body).map_err(|_| malformed())?;
+    let digits = text.strip_suffix('Z').ok_or_else(malformed)?;
+    let (year, rest) = match tlv.tag {
+        TAG_UTC_TIME if digits.len() == 12 => {
+            let yy: i32 = digits[..2].parse().map_err(|_| malformed())?;
+            (if yy >= 50 { 1900 + yy } else { 2000 + yy }, &digits[2..])
+        }
+        TAG_GENERALIZED_TIME if digits.len() == 14 => {
+            (digits[..4].parse().map_err(|_| malformed())?, &digits[4..])
+        }
+        _ => return Err(malformed()),
+    };
+    let field = |idx: usize| -> AppResult<u8> {
+        rest[idx * 2..idx * 2 + 2].parse().map_err(|_| malformed())
+    };
+    let month = time::Month::try_from(field(0)?).map_err(|_| malformed())?;
+    let date = time::Date::from_calendar_date(year, month, field(1)?).map_err(|_| malformed())?;
+    let clock = time::Time::from_hms(field(2)?, field(3)?, field(4)?).map_err(|_| malformed())?;
+    Ok(time::PrimitiveDateTime::new(date, clock)
+        .assume_utc()
+        .unix_timestamp())
+}
+
+fn format_unix(ts: i64) -> String {
+    time::OffsetDateTime::from_unix_timestamp(ts)
+        .ok()
+        .and_then(|dt| {
+            dt.format(&time::format_description::well_known::Rfc3339)
+                .ok()
+        })
+        .unwrap_or_else(|| ts.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CERT_PEM: &str = include_str!("../tests/fixtures/ec256_cert.pem");
+
+    #[test]
+    fn parses_self_signed_fixture() {
+        let chain = parse_chain(CERT_PEM.as_bytes()).expect("parse chain");
+        assert_eq!(chain.len(), 1);
+        let cert = &chain[0];
+        assert_eq!(cert.subject, "CN=jwt-tester fixture");
+        assert!(cert.self_signed());
+        assert!(cert.issued_by(cert));
+        assert_eq!(cert.key_type, KeyType::P256);
+        assert!(cert.not_before < cert.not_after);
+        assert_eq!(cert.public_key.len(), 65);
+        assert_eq!(
+            parse_certificate(&cert.der).unwrap().thumbprint(),
+            cert.thumbprint()
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_der() {
+        let chain = parse_chain(CERT_PEM.as_bytes()).unwrap();
+        let der = &chain[0].der;
+        assert!(parse_certificate(&der[..der.len() / 2]).is_err());
+        assert!(parse_chain(b"not a certificate").is_err());
+    }
+}
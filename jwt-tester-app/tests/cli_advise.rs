@@ -0,0 +1,80 @@
+mod common;
+use common::{at_path, encode_token, fixture_path, TestVault};
+
+#[test]
+fn advise_plans_hmac_to_es256_with_estimates_and_checklist() {
+    let vault = TestVault::new();
+    vault.run_json(&["vault", "project", "add", "alpha"]);
+    vault.run_json(&[
+        "vault",
+        "key",
+        "add",
+        "--project",
+        "alpha",
+        "--name",
+        "shared",
+        "--kind",
+        "hmac",
+        "--secret",
+        &at_path(&fixture_path("hmac.key")),
+    ]);
+    let token = encode_token(&[
+        "encode",
+        "--alg",
+        "hs256",
+        "--secret",
+        &at_path(&fixture_path("hmac.key")),
+        "--aud",
+        "orders",
+        "--aud",
+        "billing",
+        "--iat",
+        "--exp",
+        "+1h",
+    ]);
+
+    let out = vault.run_json(&[
+        "advise",
+        "--project",
+        "alpha",
+        "--target-alg",
+        "es256",
+        "--iterations",
+        "3",
+        &token,
+    ]);
+    let data = &out["data"];
+    assert_eq!(data["current_alg"], "HS256");
+    assert_eq!(data["target_alg"], "ES256");
+    assert_eq!(data["lifetime_secs"], 3600);
+    assert_eq!(data["findings"][0]["id"], "shared-secret");
+    assert_eq!(data["findings"][0]["priority"], "high");
+    let algs: Vec<&str> = data["estimates"]
+        .as_array()
+        .expect("estimates")
+        .iter()
+        .filter_map(|e| e["alg"].as_str())
+        .collect();
+    assert_eq!(algs, ["HS256", "ES256"]);
+    assert_eq!(data["estimates"][1]["signature_bytes"], 86);
+    let checklist = data["checklist"].as_array().expect("checklist");
+    assert!(checklist[0]
+        .as_str()
+        .unwrap()
+        .contains("--project alpha --name signing-es256 --kind ec"));
+    assert!(checklist
+        .iter()
+        .any(|step| step.as_str().unwrap().contains("for 1h so tokens")));
+
+    vault.assert_exit(
+        &[
+            "advise",
+            "--project",
+            "alpha",
+            "--target-alg",
+            "hs256",
+            &token,
+        ],
+        12,
+    );
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use common::{assert_exit, at_path, encode_token, fixture_path, run_json};
+
+#[test]
+fn resigned_token_verifies_with_the_printed_public_key() {
+    let token = encode_token(&[
+        "encode",
+        "--alg",
+        "rs256",
+        "--key",
+        &at_path(&fixture_path("rsa_private.pem")),
+        "--kid",
+        "prod-signing-1",
+        "--exp",
+        "+1h",
+        r#"{"sub":"alice","email":"alice@corp.example","roles":["admin"]}"#,
+    ]);
+
+    let out = run_json(&["anonymize", "--resign", "--seed", "issue-42", &token]);
+    let result = &out["data"]["tokens"][0];
+    assert_eq!(result["signature"], "resigned");
+    assert_eq!(result["replaced"], serde_json::json!(["email", "sub"]));
+    assert_eq!(out["data"]["time_shift_secs"], 0);
+    let anonymized = result["token"].as_str().expect("token");
+
+    let decoded = run_json(&["decode", anonymized]);
+    let payload = &decoded["data"]["payload"];
+    assert_eq!(payload["roles"], serde_json::json!(["admin"]));
+    assert_eq!(payload["sub"].as_str().map(str::len), Some(5));
+    assert_ne!(payload["sub"], "alice");
+    assert_ne!(decoded["data"]["header"]["kid"], "prod-signing-1");
+
+    let dir = tempfile::tempdir().expect("temp dir");
+    let public = dir.path().join("public.pem");
+    std::fs::write(
+        &public,
+        out["data"]["keys"][0]["public_key"].as_str().expect("pem"),
+    )
+    .expect("write key");
+    let verified = run_json(&[
+        "verify",
+        "--alg",
+        "rs256",
+        "--key",
+        &at_path(&public),
+        anonymized,
+    ]);
+    assert_eq!(verified["data"]["valid"], true);
+
+    // Without --resign the signature is random and nothing verifies it.
+    let out = run_json(&["anonymize", "--seed", "issue-42", &token]);
+    let unsigned = out["data"]["tokens"][0]["token"].as_str().expect("token");
+    assert_eq!(unsigned.len(), anonymized.len());
+    assert_exit(
+        &[
+            "verify",
+            "--alg",
+            "rs256",
+            "--key",
+            &at_path(&public),
+            unsigned,
+        ],
+        11,
+    );
+}
@@ -1,6 +1,6 @@
 mod common;
 
-use common::{at_path, encode_token, fixture_path, run_json};
+use common::{assert_exit, at_path, encode_token, fixture_path, run_json};
 
 fn rsa_roundtrip(alg: &str) {
     let priv_key = fixture_path("rsa_private.pem");
@@ -44,6 +44,15 @@ fn rs256_verify_infers_alg_from_header() {
     assert_eq!(out["data"]["valid"], true);
 }
 
+#[test]
+fn rs256_verify_with_private_key_pem_uses_its_public_half() {
+    let priv_key = at_path(&fixture_path("rsa_private.pem"));
+    let token = encode_token(&["encode", "--alg", "rs256", "--key", &priv_key]);
+
+    let out = run_json(&["verify", "--key", &priv_key, &token]);
+    assert_eq!(out["data"]["valid"], true);
+}
+
 #[test]
 fn rs384_encode_verify_pem() {
     rsa_roundtrip("rs384");
@@ -249,3 +258,232 @@ fn eddsa_verify_infers_alg_from_header() {
     ]);
     assert_eq!(out["data"]["valid"], true);
 }
+
+fn ssh_key_roundtrip(alg: &str, ssh_fixture: &str, public_fixture: &str) {
+    let ssh_key = fixture_path(ssh_fixture);
+    let pub_key = fixture_path(public_fixture);
+
+    let token = encode_token(&[
+        "encode",
+        "--alg",
+        alg,
+        "--key",
+        &format!("ssh:{}", ssh_key.display()),
+        "--exp",
+        "+1h",
+    ]);
+
+    let out = run_json(&["verify", "--alg", alg, "--key", &at_path(&pub_key), &token]);
+    assert_eq!(out["data"]["valid"], true);
+}
+
+#[test]
+fn eddsa_encode_with_openssh_key() {
+    ssh_key_roundtrip("eddsa", "ed25519_openssh", "ed25519_public.pem");
+}
+
+#[test]
+fn rs256_encode_with_openssh_key() {
+    ssh_key_roundtrip("rs256", "rsa_openssh", "rsa_public.pem");
+}
+
+#[test]
+fn encrypted_openssh_key_reads_passphrase_from_env() {
+    let ssh_key = fixture_path("ed25519_openssh_encrypted");
+    let output = assert_cmd::cargo::cargo_bin_cmd!()
+        .arg("--json")
+        .env("JWT_TESTER_SSH_PASSPHRASE", "fixture-pass")
+        .args([
+            "encode",
+            "--alg",
+            "eddsa",
+            "--key",
+            &format!("ssh:{}", ssh_key.display()),
+            "--exp",
+            "+1h",
+        ])
+        .output()
+        .expect("encode");
+    assert!(output.status.success(), "encode failed: {output:?}");
+}
+
+#[test]
+fn openssh_key_rejects_mismatched_alg() {
+    let ssh_key = fixture_path("ed25519_openssh");
+    assert_exit(
+        &[
+            "encode",
+            "--alg",
+            "rs256",
+            "--key",
+            &format!("ssh:{}", ssh_key.display()),
+        ],
+        13,
+    );
+}
+
+#[test]
+fn request_object_preset_roundtrips_with_profile() {
+    let priv_key = fixture_path("rsa_private.pem");
+    let pub_key = fixture_path("rsa_public.pem");
+    let token = encode_token(&[
+        "encode",
+        "--alg",
+        "rs256",
+        "--key",
+        &at_path(&priv_key),
+        "--preset",
+        "request-object",
+        "--client-id",
+        "client-1",
+        "--aud",
+        "https://as.example",
+        "--redirect-uri",
+        "https://client.example/cb",
+        "--scope",
+        "openid profile",
+    ]);
+
+    let decoded = run_json(&["decode", &token]);
+    assert_eq!(decoded["data"]["header"]["typ"], "oauth-authz-req+jwt");
+    let claims = &decoded["data"]["payload"];
+    assert_eq!(claims["iss"], "client-1");
+    assert_eq!(claims["response_type"], "code");
+    assert_eq!(claims["redirect_uri"], "https://client.example/cb");
+    assert!(claims["exp"].is_i64());
+
+    let out = run_json(&[
+        "verify",
+        "--key",
+        &at_path(&pub_key),
+        "--profile",
+        "request-object",
+        "--client-id",
+        "client-1",
+        &token,
+    ]);
+    assert_eq!(out["data"]["profile"], "request-object");
+
+    let plain = encode_token(&[
+        "encode",
+        "--alg",
+        "rs256",
+        "--key",
+        &at_path(&priv_key),
+        "--claim",
+        "client_id=client-1",
+        "--aud",
+        "https://as.example",
+    ]);
+    assert_exit(
+        &[
+            "verify",
+            "--key",
+            &at_path(&pub_key),
+            "--profile",
+            "request-object",
+            &plain,
+        ],
+        10,
+    );
+    assert_exit(
+        &[
+            "encode",
+            "--alg",
+            "rs256",
+            "--key",
+            &at_path(&priv_key),
+            "--preset",
+            "request-object",
+            "--client-id",
+            "client-1",
+        ],
+        12,
+    );
+}
+
+#[test]
+fn acme_preset_fetches_nonce_and_prints_flattened_jws() {
+    use std::io::{Read, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind");
+    let nonce_url = format!("http://{}/new-nonce", listener.local_addr().unwrap());
+    let server = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().expect("accept");
+        let mut request = [0; 1024];
+        let read = stream.read(&mut request).expect("read");
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nReplay-Nonce: nonce-1\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+            .expect("write");
+        String::from_utf8_lossy(&request[..read]).to_string()
+    });
+
+    let priv_key = fixture_path("ec256_private.pem");
+    let pub_key = fixture_path("ec256_public.pem");
+    let out = run_json(&[
+        "encode",
+        "--alg",
+        "es256",
+        "--key",
+        &at_path(&priv_key),
+        "--preset",
+        "acme",
+        "--nonce-url",
+        &nonce_url,
+        "--header-param",
+        "url=https://acme.example/new-order",
+        "--kid",
+        "https://acme.example/acct/1",
+        r#"{"identifiers":[{"type":"dns","value":"example.com"}]}"#,
+    ]);
+    assert!(server
+        .join()
+        .expect("server")
+        .starts_with("HEAD /new-nonce"));
+    let jws = &out["data"]["jws"];
+    let token = out["data"]["token"].as_str().expect("token");
+    assert_eq!(
+        token,
+        format!(
+            "{}.{}.{}",
+            jws["protected"].as_str().unwrap(),
+            jws["payload"].as_str().unwrap(),
+            jws["signature"].as_str().unwrap()
+        )
+    );
+
+    let decoded = run_json(&["decode", token]);
+    let header = &decoded["data"]["header"];
+    assert_eq!(header["nonce"], "nonce-1");
+    assert_eq!(header["url"], "https://acme.example/new-order");
+    assert_eq!(header["kid"], "https://acme.example/acct/1");
+    assert!(header.get("typ").is_none());
+    assert!(decoded["data"]["payload"].get("iat").is_none());
+
+    run_json(&[
+        "verify",
+        "--alg",
+        "es256",
+        "--key",
+        &at_path(&pub_key),
+        token,
+    ]);
+
+    // Without --kid or a jwk header the request could not identify the account.
+    assert_exit(
+        &[
+            "encode",
+            "--alg",
+            "es256",
+            "--key",
+            &at_path(&priv_key),
+            "--preset",
+            "acme",
+            "--header-param",
+            "nonce=n",
+            "--header-param",
+            "url=https://acme.example/new-order",
+        ],
+        12,
+    );
+}
@@ -1,8 +1,32 @@
 mod common;
 
 use common::{assert_exit, at_path, encode_token, fixture_path, run_json};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::thread;
 use tempfile::NamedTempFile;
 
+/// Serves `body` to every GET until the test process exits.
+fn serve_file(body: String) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let mut reader = BufReader::new(stream);
+            let mut line = String::new();
+            while reader.read_line(&mut line).is_ok_and(|n| n > 0) && line != "\r\n" {
+                line.clear();
+            }
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            let _ = reader.get_mut().write_all(response.as_bytes());
+        }
+    });
+    base
+}
+
 #[test]
 fn decode_includes_dates() {
     let secret = fixture_path("hmac.key");
@@ -22,6 +46,48 @@ fn decode_includes_dates() {
     assert!(out["data"]["dates"]["exp"]["raw"].is_i64());
 }
 
+#[test]
+fn claim_namespace_prefixes_custom_claims_and_decode_groups_them() {
+    let secret = fixture_path("hmac.key");
+    let token = encode_token(&[
+        "encode",
+        "--alg",
+        "hs256",
+        "--secret",
+        &at_path(&secret),
+        "--claim-namespace",
+        "https://example.com",
+        "--claim",
+        "roles=[\"admin\"]",
+        "--claim",
+        "email=user@example.com",
+    ]);
+
+    let out = run_json(&["decode", &token]);
+    let payload = &out["data"]["payload"];
+    assert_eq!(payload["https://example.com/roles"][0], "admin");
+    assert_eq!(payload["email"], "user@example.com");
+    assert_eq!(
+        out["data"]["namespaced_claims"]["https://example.com/"]["roles"][0],
+        "admin"
+    );
+
+    assert_exit(
+        &[
+            "encode",
+            "--alg",
+            "hs256",
+            "--secret",
+            &at_path(&secret),
+            "--claim-namespace",
+            "example.com",
+            "--claim",
+            "roles=admin",
+        ],
+        12,
+    );
+}
+
 #[test]
 fn inspect_shows_segments() {
     let secret = fixture_path("hmac.key");
@@ -37,6 +103,30 @@ fn inspect_shows_segments() {
 
     let out = run_json(&["inspect", "--show-segments", &token]);
     assert_eq!(out["data"]["segments"].as_array().unwrap().len(), 3);
+    assert_eq!(out["data"]["type"], "jws");
+}
+
+#[test]
+fn inspect_classifies_jwe_and_unsecured_tokens() {
+    // {"alg":"RSA-OAEP","enc":"A256GCM","kid":"enc1"}
+    let jwe = "eyJhbGciOiJSU0EtT0FFUCIsImVuYyI6IkEyNTZHQ00iLCJraWQiOiJlbmMxIn0.a2V5.aXY.Y2lwaGVydGV4dA.dGFn";
+    let out = run_json(&["inspect", jwe]);
+    assert_eq!(out["data"]["type"], "jwe");
+    assert_eq!(out["data"]["summary"]["enc"], "A256GCM");
+    assert_eq!(out["data"]["summary"]["kid"], "enc1");
+    assert_eq!(out["data"]["summary"]["sizes"]["ciphertext_len"], 14);
+    assert!(out["data"]["payload"].is_null());
+
+    // {"alg":"none","typ":"JWT"} . {"sub":"anon"}
+    let unsecured = "eyJhbGciOiJub25lIiwidHlwIjoiSldUIn0.eyJzdWIiOiJhbm9uIn0";
+    for token in [unsecured.to_string(), format!("{unsecured}.")] {
+        let out = run_json(&["inspect", &token]);
+        assert_eq!(out["data"]["type"], "unsecured");
+        assert_eq!(out["data"]["summary"]["alg"], "none");
+        assert_eq!(out["data"]["payload"]["sub"], "anon");
+    }
+
+    assert_exit(&["inspect", "a.b.c.d"], 10);
 }
 
 #[test]
@@ -125,3 +215,521 @@ fn decode_out_writes_json() {
 fn decode_rejects_invalid_token() {
     assert_exit(&["decode", "not-a-token"], 10);
 }
+
+#[test]
+fn decode_and_verify_accept_redirect_urls_and_header_lines() {
+    let secret = fixture_path("hmac.key");
+    let token = encode_token(&[
+        "encode",
+        "--alg",
+        "hs256",
+        "--secret",
+        &at_path(&secret),
+        "--sub",
+        "url-user",
+        "--exp",
+        "+1h",
+    ]);
+
+    let url = format!("https://app.test/callback#state=xyz&id_token={token}&token_type=Bearer");
+    let out = run_json(&["decode", &url]);
+    assert_eq!(out["data"]["payload"]["sub"], "url-user");
+
+    let header = format!("Authorization: Bearer {token}");
+    let out = run_json(&[
+        "verify",
+        "--alg",
+        "hs256",
+        "--secret",
+        &at_path(&secret),
+        &header,
+    ]);
+    assert_eq!(out["data"]["valid"], true);
+
+    let cookie = format!("Cookie: theme=dark; session={token}");
+    let out = run_json(&["inspect", &cookie]);
+    assert_eq!(out["data"]["payload"]["sub"], "url-user");
+
+    assert_exit(&["decode", "https://app.test/callback?code=abc"], 10);
+}
+
+#[test]
+fn analyze_diffs_permissions_between_tokens() {
+    let secret = at_path(&fixture_path("hmac.key"));
+    let before = encode_token(&[
+        "encode",
+        "--alg",
+        "hs256",
+        "--secret",
+        &secret,
+        "--claim",
+        "scope=read write",
+        "--claim",
+        r#"realm_access={"roles":["user"]}"#,
+    ]);
+    let after = encode_token(&[
+        "encode",
+        "--alg",
+        "hs256",
+        "--secret",
+        &secret,
+        "--claim",
+        "scope=read",
+        "--claim",
+        r#"realm_access={"roles":["user","admin"]}"#,
+    ]);
+
+    let out = run_json(&["analyze", &before]);
+    assert_eq!(out["data"]["scopes"], serde_json::json!(["read", "write"]));
+    assert_eq!(out["data"]["sources"][1], "realm_access.roles");
+
+    let out = run_json(&["analyze", &before, "--diff", &after]);
+    let diff = &out["data"]["diff"];
+    assert_eq!(
+        diff["added"],
+        serde_json::json!([{ "kind": "role", "value": "admin" }])
+    );
+    assert_eq!(
+        diff["removed"],
+        serde_json::json!([{ "kind": "scope", "value": "write" }])
+    );
+    assert_eq!(diff["unchanged"], 2);
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!()
+        .args(["analyze", &before, "--diff", &after])
+        .output()
+        .expect("analyze");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("- scope write\n+ role admin"),
+        "stdout was: {stdout}"
+    );
+}
+
+#[test]
+fn lint_scores_tokens_against_rfc8725() {
+    let secret = at_path(&fixture_path("hmac.key"));
+    let good = encode_token(&[
+        "encode",
+        "--alg",
+        "hs256",
+        "--secret",
+        &secret,
+        "--typ",
+        "at+jwt",
+        "--iss",
+        "https://idp.test",
+        "--aud",
+        "api",
+        "--exp",
+        "+15m",
+    ]);
+    let out = run_json(&["lint", "--rfc8725", "--expect-alg", "hs256", &good]);
+    assert_eq!(out["data"]["score"], 100);
+    assert_eq!(out["data"]["findings"], serde_json::json!([]));
+
+    assert_exit(
+        &[
+            "lint",
+            "--rfc8725",
+            "--expect-alg",
+            "hs256",
+            "--cross-boundary",
+            &good,
+        ],
+        12,
+    );
+    assert_exit(&["lint", "--rfc8725", "--expect-alg", "rs256", &good], 12);
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!()
+        .args(["--json", "lint", "--rfc8725", "eyJhbGciOiJub25lIn0.e30."])
+        .output()
+        .expect("run lint");
+    assert_eq!(output.status.code(), Some(12));
+    let body: serde_json::Value = serde_json::from_slice(&output.stdout).expect("json");
+    let rules: Vec<_> = body["data"]["findings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|f| f["rule"].as_str().unwrap().to_string())
+        .collect();
+    assert!(rules.contains(&"alg-none".to_string()));
+    assert!(rules.contains(&"lifetime".to_string()));
+
+    assert_exit(&["lint", &good], 10);
+}
+
+#[test]
+fn verify_and_decode_warn_about_near_miss_claim_names_and_types() {
+    let secret = at_path(&fixture_path("hmac.key"));
+    let token = encode_token(&[
+        "encode",
+        "--alg",
+        "hs256",
+        "--secret",
+        &secret,
+        "--no-iat",
+        r#"{"Iss":"https://idp.test","audience":"api","sub":42}"#,
+    ]);
+
+    for args in [
+        vec!["verify", "--secret", secret.as_str(), token.as_str()],
+        vec!["decode", token.as_str()],
+    ] {
+        let out = run_json(&args);
+        let warnings: Vec<(&str, &str)> = out["data"]["warnings"]
+            .as_array()
+            .expect("warnings array")
+            .iter()
+            .map(|w| (w["claim"].as_str().unwrap(), w["rule"].as_str().unwrap()))
+            .collect();
+        assert_eq!(
+            warnings,
+            [
+                ("Iss", "claim-name"),
+                ("audience", "claim-name"),
+                ("sub", "claim-type")
+            ],
+            "{args:?}"
+        );
+    }
+
+    let clean = encode_token(&["encode", "--alg", "hs256", "--secret", &secret, "{}"]);
+    let out = run_json(&["decode", &clean]);
+    assert_eq!(out["data"]["warnings"], serde_json::json!([]));
+}
+
+#[test]
+fn lint_claims_reports_positions_and_exits_12_on_errors() {
+    let mut file = NamedTempFile::new().expect("temp file");
+    write!(
+        file,
+        "{{\n  \"iss\": \"https://idp.test\",\n  \"aud\": 7,\n  \"expr\": 4102444800\n}}"
+    )
+    .expect("write claims");
+    let path = at_path(file.path());
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!()
+        .args(["--json", "lint-claims", "--profile", "oidc", &path])
+        .output()
+        .expect("run lint-claims");
+    assert_eq!(output.status.code(), Some(12));
+    let body: serde_json::Value = serde_json::from_slice(&output.stdout).expect("json");
+    let found: Vec<(u64, u64, &str)> = body["data"]["diagnostics"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|d| d["rule"] != "required")
+        .map(|d| {
+            (
+                d["line"].as_u64().unwrap(),
+                d["column"].as_u64().unwrap(),
+                d["rule"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(found, [(3, 10, "claim-type"), (4, 3, "claim-name")]);
+    assert_eq!(body["data"]["warnings"], 1);
+
+    let out = run_json(&[
+        "lint-claims",
+        "--alg",
+        "rs256",
+        r#"{"iss":"a","sub":"b","aud":"c","exp":4102444800,"iat":1700000000}"#,
+    ]);
+    assert_eq!(out["data"]["ok"], true);
+    assert_eq!(out["data"]["diagnostics"], serde_json::json!([]));
+}
+
+#[test]
+fn inspect_sizes_reports_claims_and_budget() {
+    let secret = at_path(&fixture_path("hmac.key"));
+    let token = encode_token(&[
+        "encode",
+        "--alg",
+        "hs256",
+        "--secret",
+        &secret,
+        "--claim",
+        "sub=user",
+        "--claim",
+        "organization_identifier=acme",
+    ]);
+
+    let out = run_json(&["inspect", "--sizes", &token]);
+    let report = &out["data"]["size_report"];
+    assert_eq!(report["claims"][0]["claim"], "organization_identifier");
+    assert!(report["budget"].is_null());
+    assert!(report["suggestions"][0]
+        .as_str()
+        .unwrap()
+        .contains("organization_identifier"));
+
+    let out = run_json(&["inspect", "--budget", "64", &token]);
+    let budget = &out["data"]["size_report"]["budget"];
+    assert_eq!(budget["limit"], 64);
+    assert_eq!(budget["over_by"], token.len() - 64);
+
+    let out = run_json(&["inspect", &token]);
+    assert!(out["data"].get("size_report").is_none());
+}
+
+#[test]
+fn inspect_fits_in_checks_cookie_and_header_limits() {
+    let secret = at_path(&fixture_path("hmac.key"));
+    let token = encode_token(&["encode", "--alg", "hs256", "--secret", &secret]);
+
+    let out = run_json(&[
+        "inspect",
+        "--fits-in",
+        "cookie",
+        "--fits-in",
+        "header",
+        &token,
+    ]);
+    let checks = out["data"]["fits_in"].as_array().unwrap();
+    assert_eq!(checks[0]["target"], "cookie");
+    assert_eq!(checks[0]["limit"], 4096);
+    assert_eq!(checks[0]["bytes"], "token=".len() + token.len());
+    assert_eq!(
+        checks[1]["bytes"],
+        "Authorization: Bearer ".len() + token.len()
+    );
+    assert!(checks.iter().all(|c| c["fits"] == true));
+
+    let tight = format!("cookie:{}", token.len() + 8);
+    let output = assert_cmd::cargo::cargo_bin_cmd!()
+        .args([
+            "--json",
+            "inspect",
+            "--fits-in",
+            &tight,
+            "--prefix",
+            "Bearer ",
+            &token,
+        ])
+        .output()
+        .expect("run inspect");
+    assert_eq!(output.status.code(), Some(12));
+    let body: serde_json::Value = serde_json::from_slice(&output.stdout).expect("json");
+    assert_eq!(body["data"]["fits_in"][0]["encoding_overhead"], 2);
+    assert_eq!(body["data"]["fits_in"][0]["fits"], false);
+
+    assert_exit(&["inspect", "--fits-in", "url", &token], 12);
+}
+
+#[test]
+fn encode_zip_deflates_and_decode_inflates() {
+    let secret = at_path(&fixture_path("hmac.key"));
+    let token = encode_token(&[
+        "encode", "--alg", "hs256", "--secret", &secret, "--zip", "DEF", "--claim", "sub=user",
+    ]);
+
+    let out = run_json(&["decode", &token]);
+    assert_eq!(out["data"]["header"]["zip"], "DEF");
+    assert_eq!(out["data"]["payload"]["sub"], "user");
+    assert_eq!(out["data"]["compressed"], true);
+
+    let out = run_json(&["inspect", &token]);
+    assert_eq!(out["data"]["summary"]["compressed"], true);
+
+    let out = run_json(&["verify", "--alg", "hs256", "--secret", &secret, &token]);
+    assert_eq!(out["data"]["claims"]["sub"], "user");
+}
+
+#[test]
+fn inspect_lists_header_urls_without_fetching() {
+    let token = encode_token(&[
+        "encode",
+        "--alg",
+        "es256",
+        "--key",
+        &at_path(&fixture_path("ec256_private.pem")),
+        "--header-param",
+        "jku=https://attacker.example/jwks.json",
+    ]);
+    let out = run_json(&["inspect", &token]);
+    let urls = &out["data"]["header_urls"];
+    assert!(urls["warning"].as_str().unwrap().contains("out of band"));
+    assert_eq!(urls["urls"][0]["header"], "jku");
+    assert_eq!(urls["urls"][0]["status"], "not_fetched");
+
+    // Fetching needs an allow-list, and URLs outside it are refused.
+    assert_exit(&["inspect", "--fetch-header-urls", &token], 2);
+    let out = run_json(&[
+        "inspect",
+        "--fetch-header-urls",
+        "--allow-url",
+        "https://idp.example.com/",
+        &token,
+    ]);
+    assert_eq!(out["data"]["header_urls"]["urls"][0]["status"], "refused");
+}
+
+#[test]
+fn inspect_fetches_x5u_chain_and_checks_signature() {
+    let cert = std::fs::read_to_string(fixture_path("ec256_cert.pem")).unwrap();
+    let base = serve_file(cert);
+    let x5u = format!("{base}/certs/chain.pem");
+    let token = encode_token(&[
+        "encode",
+        "--alg",
+        "es256",
+        "--key",
+        &at_path(&fixture_path("ec256_private.pem")),
+        "--header-param",
+        &format!("x5u={x5u}"),
+    ]);
+    let out = run_json(&[
+        "inspect",
+        "--fetch-header-urls",
+        "--allow-url",
+        &format!("{base}/certs/"),
+        &token,
+    ]);
+    let entry = &out["data"]["header_urls"]["urls"][0];
+    assert_eq!(entry["status"], "fetched", "{entry}");
+    assert_eq!(entry["chain"][0]["subject"], "CN=jwt-tester fixture");
+    assert_eq!(entry["chain"][0]["key_type"], "EC P-256");
+    assert_eq!(entry["checks"][0]["check"], "signature");
+    assert_eq!(entry["checks"][0]["ok"], true);
+
+    // A different key signed this one, so the fetched certificate does not match.
+    let forged = encode_token(&[
+        "encode",
+        "--alg",
+        "es384",
+        "--key",
+        &at_path(&fixture_path("ec384_private.pem")),
+        "--header-param",
+        &format!("x5u={x5u}"),
+    ]);
+    let out = run_json(&[
+        "inspect",
+        "--fetch-header-urls",
+        "--allow-url",
+        &format!("{base}/certs/"),
+        &forged,
+    ]);
+    assert_eq!(
+        out["data"]["header_urls"]["urls"][0]["checks"][0]["ok"],
+        false
+    );
+}
+
+#[test]
+fn armored_tokens_decode_after_being_written_out() {
+    let out = run_json(&[
+        "encode",
+        "--alg",
+        "hs256",
+        "--secret",
+        "secret",
+        "--armor",
+        r#"{"sub":"lab-device"}"#,
+    ]);
+    let token = out["data"]["token"].as_str().expect("token");
+    let block = out["data"]["armor"].as_str().expect("armor");
+    assert!(block.starts_with("-----BEGIN JWT-TESTER TOKEN-----\n"));
+
+    let mut file = NamedTempFile::new().expect("temp file");
+    file.write_all(block.as_bytes()).expect("write armor");
+    let decoded = run_json(&["decode", &at_path(file.path())]);
+    assert_eq!(decoded["data"]["payload"]["sub"], "lab-device");
+
+    let typo = block.replacen(&token[..8], &token[..8].to_ascii_lowercase(), 1);
+    std::fs::write(file.path(), typo).expect("write typo");
+    assert_exit(&["decode", &at_path(file.path())], 10);
+}
+
+#[cfg(unix)]
+#[test]
+fn decode_from_qr_reads_the_token_scanned_by_zbarimg() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let out = run_json(&[
+        "encode",
+        "--alg",
+        "hs256",
+        "--secret",
+        "secret",
+        "--qr",
+        r#"{"sub":"lab-device"}"#,
+    ]);
+    let token = out["data"]["token"].as_str().expect("token");
+    assert!(out["data"]["qr"].as_str().expect("qr").contains('▀'));
+
+    let dir = tempfile::tempdir().expect("temp dir");
+    let image = dir.path().join("token.png");
+    std::fs::write(&image, b"not really a png").expect("write image");
+    let scanner = dir.path().join("zbarimg");
+    std::fs::write(&scanner, format!("#!/bin/sh\necho '{token}'\n")).expect("write scanner");
+    std::fs::set_permissions(&scanner, std::fs::Permissions::from_mode(0o755)).expect("chmod");
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!()
+        .env("JWT_TESTER_ZBARIMG", &scanner)
+        .args(["--json", "decode", "--from-qr", &at_path(&image)])
+        .output()
+        .expect("run decode");
+    assert!(output.status.success(), "{output:?}");
+    let decoded: serde_json::Value = serde_json::from_slice(&output.stdout).expect("json");
+    assert_eq!(decoded["data"]["payload"]["sub"], "lab-device");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn sandboxed_decode_and_inspect_read_the_token_before_locking_down() {
+    let token = encode_token(&[
+        "encode",
+        "--alg",
+        "hs256",
+        "--secret",
+        "secret",
+        r#"{"sub":"hostile"}"#,
+    ]);
+    let mut file = NamedTempFile::new().expect("temp file");
+    write!(file, "{token}").expect("write token");
+
+    let out = run_json(&["decode", "--sandbox", &at_path(file.path())]);
+    assert_eq!(out["data"]["payload"]["sub"], "hostile");
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!()
+        .args(["--json", "inspect", "--sandbox", "--sizes", "-"])
+        .write_stdin(token.clone())
+        .output()
+        .expect("run inspect");
+    assert!(output.status.success(), "{output:?}");
+    let inspected: serde_json::Value = serde_json::from_slice(&output.stdout).expect("json");
+    assert_eq!(inspected["data"]["payload"]["sub"], "hostile");
+}
+
+#[test]
+fn sandbox_refuses_work_that_needs_files_or_the_network() {
+    let token = encode_token(&["encode", "--alg", "hs256", "--secret", "secret", "{}"]);
+    let dir = tempfile::tempdir().expect("temp dir");
+    let summary = dir.path().join("summary.json");
+
+    assert_exit(&["decode", "--sandbox", "--secret", "secret", &token], 14);
+    assert_exit(
+        &[
+            "--summary-file",
+            summary.to_str().expect("utf-8 path"),
+            "inspect",
+            "--sandbox",
+            &token,
+        ],
+        14,
+    );
+    assert_exit(&["--notify-done", "decode", "--sandbox", &token], 14);
+    assert_exit(
+        &[
+            "inspect",
+            "--sandbox",
+            "--fetch-header-urls",
+            "--allow-url",
+            "https://example.com/",
+            &token,
+        ],
+        2,
+    );
+}
@@ -0,0 +1,70 @@
+mod common;
+
+use common::TestVault;
+
+#[test]
+fn examples_list_names_recipes_and_parameters() {
+    let vault = TestVault::new();
+    let out = vault.run_json(&["examples", "list"]);
+    let recipes = out["data"]["recipes"].as_array().expect("recipes");
+    let names: Vec<&str> = recipes.iter().filter_map(|r| r["name"].as_str()).collect();
+    assert_eq!(
+        names,
+        ["rs256-rotation", "expired-token", "jwks-kid-mismatch"]
+    );
+    assert_eq!(recipes[1]["params"][0]["name"], "age");
+    assert_eq!(recipes[1]["params"][0]["default"], "1h");
+}
+
+#[test]
+fn examples_run_every_recipe_and_show_the_commands() {
+    let vault = TestVault::new();
+    let out = vault.run_json(&["examples", "run"]);
+    assert_eq!(out["data"]["failed"], 0, "{out}");
+    let recipes = out["data"]["recipes"].as_array().expect("recipes");
+    assert_eq!(recipes.len(), 3);
+
+    let expired = &recipes[1]["steps"];
+    assert_eq!(expired[3]["exit_code"], 12);
+    assert_eq!(expired[3]["error"]["code"], "INVALID_CLAIMS");
+    assert!(expired[3]["command"]
+        .as_str()
+        .unwrap()
+        .starts_with("jwt-tester verify --project demo --leeway-secs 30 ey"));
+    assert_eq!(expired[4]["data"]["valid"], true);
+
+    let mismatch = recipes[2]["steps"].as_array().expect("steps");
+    assert_eq!(mismatch.last().unwrap()["exit_code"], 13);
+}
+
+#[test]
+fn examples_run_applies_parameters_and_prints_commands_as_text() {
+    let vault = TestVault::new();
+    let output = vault
+        .cmd()
+        .args([
+            "examples",
+            "run",
+            "jwks-kid-mismatch",
+            "--set",
+            "stale_kid=old-idp",
+        ])
+        .output()
+        .expect("run examples");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("$ jwt-tester vault jwks add --project api --name idp '{\"keys\""));
+    assert!(stdout.contains("$ jwt-tester encode --project idp --kid old-idp --alg es256"));
+    assert!(stdout.contains("# A token with kid old-idp does not"));
+    assert!(stdout.trim_end().ends_with("PASS jwks-kid-mismatch"));
+}
+
+#[test]
+fn examples_run_rejects_unknown_recipes_and_parameters() {
+    let vault = TestVault::new();
+    vault.assert_exit(&["examples", "run", "nope"], 13);
+    vault.assert_exit(
+        &["examples", "run", "expired-token", "--set", "bits=4096"],
+        13,
+    );
+}
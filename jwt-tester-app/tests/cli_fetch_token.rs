@@ -0,0 +1,216 @@
+mod common;
+
+use common::{encode_token, TestVault};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::mpsc;
+use std::thread;
+
+/// Serves the given JSON bodies in order, one per request, and hands back each raw request.
+fn mock_endpoint(responses: Vec<(u16, String)>) -> (String, mpsc::Receiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+    let url = format!("http://{}/token", listener.local_addr().unwrap());
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for (status, body) in responses {
+            let (stream, _) = listener.accept().expect("accept");
+            let mut reader = BufReader::new(stream);
+            let mut request = String::new();
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).expect("read line");
+                if let Some(len) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                    content_length = len.trim().parse().unwrap_or(0);
+                }
+                request.push_str(&line);
+                if line == "\r\n" {
+                    break;
+                }
+            }
+            let mut form = vec![0; content_length];
+            reader.read_exact(&mut form).expect("read body");
+            request.push_str(&String::from_utf8_lossy(&form));
+            tx.send(request).expect("send request");
+
+            let response = format!(
+                "HTTP/1.1 {status} X\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            reader
+                .get_mut()
+                .write_all(response.as_bytes())
+                .expect("write");
+        }
+    });
+    (url, rx)
+}
+
+#[test]
+fn fetch_token_client_credentials_decodes_and_stores_tokens() {
+    let access = encode_token(&[
+        "encode", "--alg", "hs256", "--secret", "idp", "--sub", "svc", "--exp", "+1h",
+    ]);
+    let body = format!(
+        r#"{{"access_token":"{access}","refresh_token":"opaque-refresh","token_type":"Bearer","expires_in":3600}}"#
+    );
+    let (url, requests) = mock_endpoint(vec![(200, body.clone()), (200, body)]);
+
+    let vault = TestVault::new();
+    vault.run_json(&["vault", "project", "add", "idp"]);
+    let args = [
+        "fetch-token",
+        "--token-url",
+        &url,
+        "--client-id",
+        "app",
+        "--client-secret",
+        "s3cret",
+        "--scope",
+        "read",
+        "--project",
+        "idp",
+    ];
+    let out = vault.run_json(&args);
+    assert_eq!(
+        out["data"]["tokens"]["access_token"]["payload"]["sub"],
+        "svc"
+    );
+    assert_eq!(out["data"]["tokens"]["refresh_token"]["jwt"], false);
+    assert_eq!(out["data"]["response"]["expires_in"], 3600);
+    assert_eq!(out["data"]["stored"][0]["name"], "app-access");
+
+    let request = requests.recv().expect("request");
+    assert!(request.contains("grant_type=client_credentials"));
+    assert!(request.contains("scope=read"));
+    assert!(request.contains("Basic YXBwOnMzY3JldA=="));
+    assert!(!request.contains("client_secret="));
+
+    // Fetching again replaces the stored tokens instead of adding duplicates.
+    vault.run_json(&args);
+    let listed = vault.run_json(&["vault", "token", "list", "--project", "idp"]);
+    assert_eq!(listed["data"]["tokens"].as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn fetch_token_reports_oauth_errors() {
+    let (url, _requests) = mock_endpoint(vec![(
+        401,
+        r#"{"error":"invalid_client","error_description":"unknown client"}"#.to_string(),
+    )]);
+    let output = assert_cmd::cargo::cargo_bin_cmd!()
+        .args([
+            "fetch-token",
+            "--token-url",
+            &url,
+            "--client-id",
+            "app",
+            "--client-secret",
+            "wrong",
+            "--client-auth",
+            "post",
+        ])
+        .output()
+        .expect("run");
+    assert_eq!(output.status.code(), Some(13));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("invalid_client (unknown client)"));
+}
+
+#[test]
+fn fetch_token_device_code_polls_until_approved() {
+    let access = encode_token(&[
+        "encode", "--alg", "hs256", "--secret", "idp", "--sub", "dev",
+    ]);
+    let (url, requests) = mock_endpoint(vec![
+        (
+            200,
+            r#"{"device_code":"dc-1","user_code":"ABCD","verification_uri":"https://idp/device","interval":1,"expires_in":30}"#
+                .to_string(),
+        ),
+        (400, r#"{"error":"authorization_pending"}"#.to_string()),
+        (200, format!(r#"{{"access_token":"{access}","token_type":"Bearer"}}"#)),
+    ]);
+    let output = assert_cmd::cargo::cargo_bin_cmd!()
+        .args([
+            "--json",
+            "fetch-token",
+            "--grant",
+            "device-code",
+            "--device-url",
+            &url,
+            "--token-url",
+            &url,
+            "--client-id",
+            "cli",
+        ])
+        .output()
+        .expect("run");
+    assert!(output.status.success(), "{output:?}");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("enter code ABCD"));
+    let out: serde_json::Value = serde_json::from_slice(&output.stdout).expect("json");
+    assert_eq!(
+        out["data"]["tokens"]["access_token"]["payload"]["sub"],
+        "dev"
+    );
+
+    let authorization = requests.recv().expect("device request");
+    assert!(authorization.contains("client_id=cli"));
+    assert!(!authorization.contains("grant_type"));
+    let poll = requests.recv().expect("poll");
+    assert!(poll.contains("device_code=dc-1"));
+}
+
+#[test]
+fn fetch_token_refresh_tracks_rotation() {
+    let access = encode_token(&[
+        "encode", "--alg", "hs256", "--secret", "idp", "--sub", "svc",
+    ]);
+    let issued = |refresh: &str| {
+        format!(
+            r#"{{"access_token":"{access}","refresh_token":"{refresh}","token_type":"Bearer"}}"#
+        )
+    };
+    let (url, requests) = mock_endpoint(vec![
+        (200, issued("refresh-1")),
+        (200, issued("refresh-2")),
+        (400, r#"{"error":"invalid_grant"}"#.to_string()),
+        (200, issued("refresh-2")),
+    ]);
+
+    let vault = TestVault::new();
+    vault.run_json(&["vault", "project", "add", "idp"]);
+    let fetch = |extra: &[&str]| {
+        let mut args = vec![
+            "fetch-token",
+            "--token-url",
+            &url,
+            "--client-id",
+            "app",
+            "--project",
+            "idp",
+        ];
+        args.extend_from_slice(extra);
+        vault.run_json(&args)
+    };
+    fetch(&[]);
+    requests.recv().expect("initial grant");
+
+    let out = fetch(&["--refresh", "app", "--check-rotation"]);
+    assert_eq!(out["data"]["refresh"]["source"], "app-refresh");
+    assert_eq!(out["data"]["refresh"]["rotated"], true);
+    assert_eq!(out["data"]["refresh"]["old_token_rejected"], true);
+    let refresh = requests.recv().expect("refresh grant");
+    assert!(refresh.contains("grant_type=refresh_token"));
+    assert!(refresh.contains("refresh_token=refresh-1"));
+    let replay = requests.recv().expect("replay");
+    assert!(replay.contains("refresh_token=refresh-1"));
+
+    // The rotated token replaced the stored one, and an unchanged token is not a rotation.
+    let out = fetch(&["--refresh", "app-refresh"]);
+    assert_eq!(out["data"]["refresh"]["rotated"], false);
+    assert!(out["data"]["refresh"]["old_token_rejected"].is_null());
+    assert!(requests
+        .recv()
+        .expect("second refresh")
+        .contains("refresh_token=refresh-2"));
+}
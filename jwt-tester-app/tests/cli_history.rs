@@ -0,0 +1,60 @@
+mod common;
+
+use common::TestVault;
+
+fn run_with_history(vault: &TestVault, args: &[&str]) -> serde_json::Value {
+    let output = vault
+        .cmd()
+        .env("JWT_TESTER_HISTORY", "5")
+        .arg("--json")
+        .args(args)
+        .output()
+        .expect("failed to run jwt-tester");
+    serde_json::from_slice(&output.stdout).expect("invalid JSON output")
+}
+
+#[test]
+fn history_records_encode_and_verify_without_secrets() {
+    let vault = TestVault::new();
+    let secret = "history-test-secret";
+
+    let encoded = run_with_history(
+        &vault,
+        &[
+            "encode", "--alg", "hs256", "--secret", secret, "--sub", "alice", "--exp", "+1h",
+        ],
+    );
+    let token = encoded["data"]["token"]
+        .as_str()
+        .expect("token")
+        .to_string();
+    let _ = run_with_history(
+        &vault,
+        &["verify", "--alg", "hs256", "--secret", "wrong", &token],
+    );
+
+    let listed = run_with_history(&vault, &["history", "list"]);
+    let entries = listed["data"]["entries"].as_array().expect("entries");
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0]["operation"], "verify");
+    assert_eq!(entries[0]["ok"], false);
+    assert_eq!(entries[1]["operation"], "encode");
+    assert_eq!(entries[1]["params"]["sub"], "alice");
+    assert_eq!(entries[1]["params"]["secret"], "<inline>");
+    assert!(entries[1]["result"].as_str().unwrap().len() < token.len());
+    assert!(!listed.to_string().contains(secret));
+
+    let cleared = vault.run_json(&["history", "clear"]);
+    assert_eq!(cleared["data"]["removed"], 2);
+}
+
+#[test]
+fn history_is_off_by_default() {
+    let vault = TestVault::new();
+    let _ = vault.run_json(&[
+        "encode", "--alg", "hs256", "--secret", "secret", "--exp", "+1h",
+    ]);
+    let listed = vault.run_json(&["history", "list"]);
+    assert_eq!(listed["data"]["enabled"], false);
+    assert!(listed["data"]["entries"].as_array().unwrap().is_empty());
+}
@@ -257,3 +257,163 @@ fn keep_payload_order_preserves_input_order() {
     assert!(payload_sorted.find("\"a\"").unwrap() < payload_sorted.find("\"b\"").unwrap());
     assert!(payload_kept.find("\"b\"").unwrap() < payload_kept.find("\"a\"").unwrap());
 }
+
+#[test]
+fn direct_secret_does_not_touch_data_dir() {
+    let temp = tempfile::TempDir::new().expect("temp dir");
+    let data_dir = temp.path().join("vault-data");
+    let data_dir_arg = data_dir.display().to_string();
+    let secret = fixture_path("hmac.key");
+
+    let token = encode_token(&[
+        "--data-dir",
+        &data_dir_arg,
+        "encode",
+        "--alg",
+        "hs256",
+        "--secret",
+        &at_path(&secret),
+        "--exp",
+        "+1h",
+    ]);
+    let out = run_json(&[
+        "--data-dir",
+        &data_dir_arg,
+        "verify",
+        "--alg",
+        "hs256",
+        "--secret",
+        &at_path(&secret),
+        &token,
+    ]);
+    assert_eq!(out["data"]["valid"], true);
+    assert!(
+        !data_dir.exists(),
+        "direct key invocations must not open the vault"
+    );
+}
+
+#[test]
+fn verify_sweep_reports_validity_transitions() {
+    let secret = at_path(&fixture_path("hmac.key"));
+    let token = encode_token(&[
+        "encode", "--alg", "hs256", "--secret", &secret, "--nbf", "1000", "--exp", "2000",
+    ]);
+
+    // Long expired, yet the sweep still verifies the signature and reports the window.
+    let out = run_json(&[
+        "verify",
+        "--secret",
+        &secret,
+        "--leeway-secs",
+        "0",
+        "--sweep",
+        "0..3000:500s",
+        &token,
+    ]);
+    let sweep = &out["data"]["sweep"];
+    assert_eq!(sweep["points"], 7);
+    assert_eq!(sweep["valid_from"], 1000);
+    assert_eq!(sweep["valid_until"], 2000);
+    assert_eq!(sweep["transitions"][0]["at"], 1000);
+    assert_eq!(sweep["transitions"][1]["at"], 2500);
+    assert_eq!(sweep["transitions"][1]["to"], "expired");
+
+    assert_exit(
+        &[
+            "verify",
+            "--secret",
+            "wrong-secret",
+            "--sweep",
+            "0..3000:500s",
+            &token,
+        ],
+        11,
+    );
+    assert_exit(
+        &["verify", "--secret", &secret, "--sweep", "0..3000", &token],
+        12,
+    );
+}
+
+#[test]
+fn millisecond_timestamps_are_normalized_unless_seconds_are_forced() {
+    let secret = at_path(&fixture_path("hmac.key"));
+    // 2001-09-09 in milliseconds: long expired once read as seconds-since-epoch.
+    let token = encode_token(&[
+        "encode",
+        "--alg",
+        "hs256",
+        "--secret",
+        &secret,
+        "--no-iat",
+        "--exp",
+        "1000000000000",
+    ]);
+
+    assert_exit(&["verify", "--secret", &secret, &token], 12);
+    let out = run_json(&["verify", "--secret", &secret, "--timestamps", "s", &token]);
+    assert_eq!(out["data"]["valid"], true);
+
+    let out = run_json(&["decode", "--date", "utc", &token]);
+    assert_eq!(out["data"]["payload"]["exp"], 1000000000000_i64);
+    assert_eq!(
+        out["data"]["normalized_timestamps"]["exp"],
+        1000000000000_i64
+    );
+    assert_eq!(out["data"]["dates"]["exp"]["raw"], 1000000000);
+    let out = run_json(&["decode", "--timestamps", "s", &token]);
+    assert!(out["data"].get("normalized_timestamps").is_none());
+
+    let future = encode_token(&[
+        "encode",
+        "--alg",
+        "hs256",
+        "--secret",
+        &secret,
+        "--exp",
+        "4102444800000",
+    ]);
+    let out = run_json(&["verify", "--secret", &secret, &future]);
+    assert_eq!(out["data"]["claims"]["exp"], 4102444800_i64);
+    assert_eq!(
+        out["data"]["normalized_timestamps"]["exp"],
+        4102444800000_i64
+    );
+}
+
+#[test]
+fn encode_mimic_rebuilds_a_captured_token_with_overrides() {
+    let theirs = at_path(&fixture_path("hmac_alt.key"));
+    let ours = at_path(&fixture_path("hmac.key"));
+    let captured = encode_token(&[
+        "encode",
+        "--alg",
+        "hs256",
+        "--secret",
+        &theirs,
+        "--kid",
+        "prod-2024",
+        "--typ",
+        "at+jwt",
+        "--header-param",
+        "tenant=acme",
+        "--keep-payload-order",
+        r#"{"sub":"user","scope":"read","exp":1}"#,
+    ]);
+
+    let token = encode_token(&[
+        "encode", "--alg", "hs256", "--secret", &ours, "--mimic", &captured, "--exp", "+1h",
+    ]);
+    let verified = run_json(&["verify", "--secret", &ours, &token]);
+    assert_eq!(verified["data"]["valid"], true);
+    let decoded = run_json(&["decode", &token]);
+    let header = &decoded["data"]["header"];
+    assert_eq!(header["typ"], "at+jwt");
+    assert_eq!(header["tenant"], "acme");
+    assert!(header.get("kid").is_none());
+    let payload = decoded["data"]["payload"].as_object().expect("payload");
+    let names: Vec<&String> = payload.keys().collect();
+    assert_eq!(names, ["sub", "scope", "exp"]);
+    assert!(payload["exp"].as_i64().unwrap() > 1);
+}
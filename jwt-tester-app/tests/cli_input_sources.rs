@@ -97,3 +97,91 @@ fn encode_claims_from_env_and_env_secret() {
     let json = parse_json(&output);
     assert!(json["data"]["valid"].as_bool().unwrap_or(false));
 }
+
+#[test]
+fn encode_builds_audiences_from_a_file_and_pattern() {
+    let dir = tempfile::TempDir::new().expect("temp dir");
+    let services = dir.path().join("services.txt");
+    std::fs::write(&services, "# mesh\nledger\npayments\n\nledger\n").expect("write services");
+    let secret = at_path(&fixture_path("hmac.key"));
+
+    let token = encode_token(&[
+        "encode",
+        "--alg",
+        "hs256",
+        "--secret",
+        &secret,
+        "--aud",
+        "gateway",
+        "--aud-from-file",
+        &at_path(&services),
+        "--aud-pattern",
+        "spiffe://cluster/ns/%s",
+    ]);
+    let output = assert_cmd::cargo::cargo_bin_cmd!()
+        .args(["--json", "decode", &token])
+        .output()
+        .expect("decode");
+    let json = parse_json(&output);
+    assert_eq!(
+        json["data"]["payload"]["aud"],
+        serde_json::json!([
+            "gateway",
+            "spiffe://cluster/ns/ledger",
+            "spiffe://cluster/ns/payments"
+        ])
+    );
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!()
+        .args(["--json", "verify", "--secret", &secret])
+        .args(["--aud", "spiffe://cluster/ns/payments", &token])
+        .output()
+        .expect("verify");
+    assert!(output.status.success(), "verify failed: {output:?}");
+}
+
+#[test]
+fn verify_and_decode_pull_the_token_out_of_a_json_response() {
+    let secret = at_path(&fixture_path("hmac.key"));
+    let token = encode_token(&[
+        "encode", "--alg", "hs256", "--secret", &secret, "--exp", "+1h",
+    ]);
+    let response = format!(
+        r#"{{"token_type":"Bearer","expires_in":3600,"access_token":"{token}","extra":[{{"jwt":"{token}"}}]}}"#
+    );
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!()
+        .arg("--json")
+        .args([
+            "verify",
+            "--secret",
+            &secret,
+            "--token-path",
+            "$.access_token",
+            "-",
+        ])
+        .write_stdin(response.as_bytes())
+        .output()
+        .expect("verify");
+    assert!(output.status.success(), "verify failed: {output:?}");
+    assert_eq!(parse_json(&output)["data"]["valid"], true);
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!()
+        .arg("--json")
+        .args(["decode", "--token-path", "extra[0].jwt", "-"])
+        .write_stdin(response.as_bytes())
+        .output()
+        .expect("decode");
+    assert!(output.status.success(), "decode failed: {output:?}");
+    assert!(parse_json(&output)["data"]["payload"]["exp"].is_number());
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!()
+        .arg("--json")
+        .args(["decode", "--token-path", "$.id_token", "-"])
+        .write_stdin(response.as_bytes())
+        .output()
+        .expect("decode");
+    assert_eq!(output.status.code(), Some(10));
+    let message = parse_json(&output)["error"]["message"].to_string();
+    assert!(message.contains("'id_token' not found"), "{message}");
+}
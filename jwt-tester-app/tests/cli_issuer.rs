@@ -0,0 +1,314 @@
+#![cfg(feature = "ui")]
+
+mod common;
+
+use common::TestVault;
+use serde_json::Value;
+use std::io::{BufRead, BufReader};
+use std::process::Child;
+
+/// Kills the issuer even when an assertion fails first.
+struct Issuer(Child);
+
+impl Drop for Issuer {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+fn start_issuer(vault: &TestVault, args: &[&str]) -> (Issuer, Value) {
+    let mut child = vault.spawn(&[&["--json", "issuer", "serve"], args].concat());
+    let stdout = child.stdout.take().expect("stdout");
+    let issuer = Issuer(child);
+    let mut line = String::new();
+    BufReader::new(stdout)
+        .read_line(&mut line)
+        .expect("read startup line");
+    let started: Value = serde_json::from_str(&line).expect("startup JSON");
+    (issuer, started["data"].clone())
+}
+
+#[test]
+fn issuer_serves_discovery_jwks_and_templated_tokens() {
+    let vault = TestVault::new();
+    vault.run_json(&["vault", "project", "add", "idp"]);
+    vault.run_json(&[
+        "vault",
+        "key",
+        "generate",
+        "--project",
+        "idp",
+        "--kind",
+        "ec",
+        "--name",
+        "signer",
+        "--kid",
+        "idp-1",
+    ]);
+    vault.run_json(&[
+        "vault",
+        "key",
+        "generate",
+        "--project",
+        "idp",
+        "--kind",
+        "hmac",
+        "--name",
+        "shared",
+    ]);
+    let clients = r#"{
+        "svc": {
+            "secret": "s3cret",
+            "audience": "api://orders",
+            "expires_in": "10m",
+            "claims": { "tenant": "{{ params.tenant }}", "roles": ["{{ client_id }}-reader"] }
+        }
+    }"#;
+    let (_issuer, started) = start_issuer(
+        &vault,
+        &[
+            "--project",
+            "idp",
+            "--key-name",
+            "signer",
+            "--clients",
+            clients,
+        ],
+    );
+    assert_eq!(started["alg"], "ES256");
+    assert_eq!(started["kid"], "idp-1");
+    let base = started["url"].as_str().expect("url");
+    let token_url = started["token_endpoint"].as_str().expect("token endpoint");
+
+    let discovery: Value = ureq::get(&format!("{base}/.well-known/openid-configuration"))
+        .call()
+        .expect("discovery")
+        .into_json()
+        .expect("discovery JSON");
+    assert_eq!(discovery["issuer"], base);
+    assert_eq!(discovery["token_endpoint"], token_url);
+
+    let jwks: Value = ureq::get(&format!("{base}/jwks.json"))
+        .call()
+        .expect("jwks")
+        .into_json()
+        .expect("jwks JSON");
+    let keys = jwks["keys"].as_array().expect("keys");
+    assert_eq!(keys.len(), 1, "HMAC keys are not published: {jwks}");
+    assert_eq!(keys[0]["kid"], "idp-1");
+    assert_eq!(keys[0]["alg"], "ES256");
+    assert!(keys[0].get("d").is_none());
+
+    let fetched = vault.run_json(&[
+        "fetch-token",
+        "--token-url",
+        token_url,
+        "--client-id",
+        "svc",
+        "--client-secret",
+        "s3cret",
+        "--scope",
+        "orders:read",
+        "--param",
+        "tenant=acme",
+    ]);
+    assert_eq!(fetched["data"]["response"]["expires_in"], 600);
+    let token = fetched["data"]["tokens"]["access_token"]["token"]
+        .as_str()
+        .expect("access token")
+        .to_string();
+
+    let verified = vault.run_json(&[
+        "verify",
+        "--jwks",
+        &format!("{base}/jwks.json"),
+        "--iss",
+        base,
+        "--aud",
+        "api://orders",
+        &token,
+    ]);
+    let claims = &verified["data"]["claims"];
+    assert_eq!(claims["sub"], "svc");
+    assert_eq!(claims["scope"], "orders:read");
+    assert_eq!(claims["tenant"], "acme");
+    assert_eq!(claims["roles"][0], "svc-reader");
+
+    let rejected = ureq::post(token_url).send_form(&[
+        ("grant_type", "client_credentials"),
+        ("client_id", "svc"),
+        ("client_secret", "wrong"),
+    ]);
+    match rejected {
+        Err(ureq::Error::Status(401, response)) => {
+            let body: Value = response.into_json().expect("error JSON");
+            assert_eq!(body["error"], "invalid_client");
+        }
+        other => panic!("expected 401 invalid_client, got {other:?}"),
+    }
+    match ureq::post(token_url).send_form(&[("grant_type", "password"), ("client_id", "svc")]) {
+        Err(ureq::Error::Status(400, response)) => {
+            let body: Value = response.into_json().expect("error JSON");
+            assert_eq!(body["error"], "unsupported_grant_type");
+        }
+        other => panic!("expected 400 unsupported_grant_type, got {other:?}"),
+    }
+}
+
+#[test]
+fn issuer_rejects_unusable_configuration_before_serving() {
+    let vault = TestVault::new();
+    vault.run_json(&["vault", "project", "add", "idp"]);
+    vault.run_json(&[
+        "vault",
+        "key",
+        "generate",
+        "--project",
+        "idp",
+        "--kind",
+        "hmac",
+    ]);
+    vault.assert_exit(
+        &[
+            "issuer",
+            "serve",
+            "--project",
+            "idp",
+            "--clients",
+            r#"{"svc": {"claims": {"sub": "{{ broken"}}}"#,
+        ],
+        12,
+    );
+    vault.assert_exit(
+        &["issuer", "serve", "--project", "idp", "--alg", "rs256"],
+        13,
+    );
+    vault.assert_exit(
+        &["issuer", "serve", "--project", "idp", "--host", "0.0.0.0"],
+        13,
+    );
+}
+
+fn post_token(token_url: &str) -> Result<Value, (u16, Value)> {
+    match ureq::post(token_url)
+        .send_form(&[("grant_type", "client_credentials"), ("client_id", "svc")])
+    {
+        Ok(response) => Ok(response.into_json().expect("token JSON")),
+        Err(ureq::Error::Status(status, response)) => {
+            Err((status, response.into_json().expect("error JSON")))
+        }
+        Err(err) => panic!("token request failed: {err}"),
+    }
+}
+
+#[test]
+fn issuer_test_controls_skew_clock_force_errors_and_rotate() {
+    let vault = TestVault::new();
+    vault.run_json(&["vault", "project", "add", "idp"]);
+    vault.run_json(&[
+        "vault",
+        "key",
+        "generate",
+        "--project",
+        "idp",
+        "--kind",
+        "ec",
+        "--kid",
+        "idp-1",
+    ]);
+    let (_issuer, started) = start_issuer(
+        &vault,
+        &[
+            "--project",
+            "idp",
+            "--clock-offset",
+            "-2h",
+            "--force-error",
+            "temporarily_unavailable",
+        ],
+    );
+    let token_url = started["token_endpoint"].as_str().expect("token endpoint");
+    let behavior_url = started["admin"]["behavior"].as_str().expect("behavior");
+    let rotate_url = started["admin"]["rotate"].as_str().expect("rotate");
+    let bearer = format!(
+        "Bearer {}",
+        started["admin"]["token"].as_str().expect("admin token")
+    );
+    assert_eq!(started["behavior"]["clock_offset_secs"], -7200);
+
+    let (status, body) = post_token(token_url).expect_err("forced error");
+    assert_eq!(
+        (status, body["error"].as_str()),
+        (503, Some("temporarily_unavailable"))
+    );
+
+    match ureq::post(behavior_url).send_json(serde_json::json!({ "error": null })) {
+        Err(ureq::Error::Status(401, _)) => {}
+        other => panic!("expected 401 without the admin token, got {other:?}"),
+    }
+    let behavior: Value = ureq::post(behavior_url)
+        .set("Authorization", &bearer)
+        .send_json(serde_json::json!({ "error": "invalid_client", "times": 1 }))
+        .expect("set behavior")
+        .into_json()
+        .expect("behavior JSON");
+    assert_eq!(behavior["error_times"], 1);
+    let (status, body) = post_token(token_url).expect_err("one more forced error");
+    assert_eq!(
+        (status, body["error"].as_str()),
+        (401, Some("invalid_client"))
+    );
+
+    let issued = post_token(token_url).expect("errors ran out");
+    let token = issued["access_token"].as_str().expect("access token");
+    let decoded = vault.run_json(&["decode", token]);
+    let payload = &decoded["data"]["payload"];
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    let iat = payload["iat"].as_i64().expect("iat");
+    assert!(
+        (now - 7200 - iat).abs() < 60,
+        "iat {iat} is not two hours back"
+    );
+    assert!(
+        payload["exp"].as_i64().expect("exp") < now,
+        "token is already expired"
+    );
+    assert_eq!(decoded["data"]["header"]["kid"], "idp-1");
+
+    let rotated: Value = ureq::post(rotate_url)
+        .set("Authorization", &bearer)
+        .call()
+        .expect("rotate")
+        .into_json()
+        .expect("rotate JSON");
+    assert_eq!(rotated["previous_kid"], "idp-1");
+    let new_kid = rotated["kid"].as_str().expect("new kid");
+    assert_ne!(new_kid, "idp-1");
+    let issued = post_token(token_url).expect("token after rotation");
+    let decoded = vault.run_json(&["decode", issued["access_token"].as_str().unwrap()]);
+    assert_eq!(decoded["data"]["header"]["kid"], new_kid);
+
+    ureq::post(behavior_url)
+        .set("Authorization", &bearer)
+        .send_json(serde_json::json!({ "delay": "1s" }))
+        .expect("set delay");
+    let started_at = std::time::Instant::now();
+    let jwks: Value = ureq::get(started["jwks_uri"].as_str().unwrap())
+        .call()
+        .expect("jwks")
+        .into_json()
+        .expect("jwks JSON");
+    assert!(started_at.elapsed() >= std::time::Duration::from_secs(1));
+    let kids: Vec<&str> = jwks["keys"]
+        .as_array()
+        .expect("keys")
+        .iter()
+        .filter_map(|key| key["kid"].as_str())
+        .collect();
+    assert_eq!(kids.len(), 2, "old and new keys are both published: {jwks}");
+    assert!(kids.contains(&"idp-1") && kids.contains(&new_kid));
+}
@@ -118,3 +118,172 @@ fn decode_verifies_with_jwks() {
     assert_eq!(out["data"]["verified"], true);
     assert_eq!(out["data"]["verification"]["valid"], true);
 }
+
+#[test]
+fn jwks_sources_are_tried_in_order() {
+    let priv_key = fixture_path("rsa_private.pem");
+    let current = fixture_path("jwks_single.json");
+    let previous = at_path(&fixture_path("jwks.json"));
+    let token = encode_token(&[
+        "encode",
+        "--alg",
+        "rs256",
+        "--key",
+        &at_path(&priv_key),
+        "--kid",
+        "rsa1",
+    ]);
+
+    // The first URL is unreachable and the current set lacks rsa1; the previous set has it.
+    let out = run_json(&[
+        "verify",
+        "--jwks",
+        "http://127.0.0.1:9/jwks.json",
+        "--jwks",
+        &at_path(&current),
+        "--jwks",
+        &previous,
+        "--explain",
+        &token,
+    ]);
+    assert_eq!(out["data"]["valid"], true);
+    assert_eq!(
+        out["data"]["explain"]["key_source"],
+        format!("jwks:{previous}")
+    );
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!()
+        .args([
+            "--json",
+            "verify",
+            "--jwks",
+            &at_path(&current),
+            "--jwks",
+            "{\"keys\":[]}",
+            &token,
+        ])
+        .output()
+        .expect("verify");
+    assert_eq!(output.status.code(), Some(13));
+    let out: serde_json::Value = serde_json::from_slice(&output.stdout).expect("json");
+    let message = out["error"]["message"].as_str().unwrap();
+    assert!(
+        message.contains("<inline>: JWKS contains no keys"),
+        "{message}"
+    );
+}
+
+#[test]
+fn offline_refuses_remote_jwks() {
+    let output = assert_cmd::cargo::cargo_bin_cmd!()
+        .args([
+            "--json",
+            "--offline",
+            "verify",
+            "--jwks",
+            "http://127.0.0.1:9/jwks.json",
+            "--jwks",
+            &at_path(&fixture_path("jwks.json")),
+            &encode_token(&["encode", "--alg", "hs256", "--secret", "s", "--kid", "rsa1"]),
+        ])
+        .output()
+        .expect("verify");
+    assert_eq!(output.status.code(), Some(15));
+    let out: serde_json::Value = serde_json::from_slice(&output.stdout).expect("json");
+    assert_eq!(out["error"]["code"], "OFFLINE");
+}
+
+#[test]
+fn unknown_kid_is_negatively_cached() {
+    use std::io::{Read, Write};
+
+    let jwks = std::fs::read(fixture_path("jwks_single.json")).expect("jwks");
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind");
+    let url = format!("http://{}/jwks.json", listener.local_addr().unwrap());
+    // Serves a single request; a second fetch would be refused.
+    let server = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().expect("accept");
+        let mut request = [0; 1024];
+        let _ = stream.read(&mut request).expect("read");
+        let head = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            jwks.len()
+        );
+        stream.write_all(head.as_bytes()).expect("write");
+        stream.write_all(&jwks).expect("write");
+    });
+
+    let data_dir = tempfile::tempdir().expect("tempdir");
+    let token = encode_token(&[
+        "encode",
+        "--alg",
+        "rs256",
+        "--key",
+        &at_path(&fixture_path("rsa_private.pem")),
+        "--kid",
+        "rotated-away",
+    ]);
+    let verify = |offline: bool| {
+        let mut cmd = assert_cmd::cargo::cargo_bin_cmd!();
+        cmd.arg("--json").arg("--data-dir").arg(data_dir.path());
+        if offline {
+            cmd.arg("--offline");
+        }
+        let output = cmd
+            .args(["verify", "--jwks", &url, &token])
+            .output()
+            .expect("verify");
+        let out: serde_json::Value = serde_json::from_slice(&output.stdout).expect("json");
+        (output.status.code(), out["error"]["message"].to_string())
+    };
+
+    let (code, message) = verify(false);
+    assert_eq!(code, Some(13), "{message}");
+    server.join().expect("server");
+
+    // The miss is remembered, so neither a second run nor an offline one touches the network.
+    for offline in [false, true] {
+        let (code, message) = verify(offline);
+        assert_eq!(code, Some(13), "{message}");
+        assert!(message.contains("cached"), "{message}");
+    }
+}
+
+#[test]
+fn tenant_config_routes_tokens_by_issuer() {
+    let dir = tempfile::TempDir::new().expect("temp dir");
+    std::fs::create_dir(dir.path().join("keys")).expect("keys dir");
+    std::fs::copy(fixture_path("jwks.json"), dir.path().join("keys/acme.json")).expect("copy");
+    let config = dir.path().join("tenants.yaml");
+    std::fs::write(
+        &config,
+        "tenants:\n  - name: acme\n    issuer: https://acme.example.com\n    audiences: [gateway]\n    jwks: '@keys/acme.json'\n    algorithms: [RS256]\n",
+    )
+    .expect("write config");
+    let config = at_path(&config);
+    let priv_key = at_path(&fixture_path("rsa_private.pem"));
+    let rs256 = |claims: &str| {
+        encode_token(&[
+            "encode", "--alg", "rs256", "--key", &priv_key, "--kid", "rsa1", "--exp", "+1h", claims,
+        ])
+    };
+
+    let token = rs256(r#"{"iss":"https://acme.example.com","aud":"gateway"}"#);
+    let out = run_json(&["verify", "--tenant-config", &config, &token]);
+    assert_eq!(out["data"]["valid"], true);
+    assert_eq!(out["data"]["tenant"]["name"], "acme");
+
+    let wrong_aud = rs256(r#"{"iss":"https://acme.example.com","aud":"billing"}"#);
+    assert_exit(&["verify", "--tenant-config", &config, &wrong_aud], 12);
+    let unknown = rs256(r#"{"iss":"https://globex.example.com","aud":"gateway"}"#);
+    assert_exit(&["verify", "--tenant-config", &config, &unknown], 12);
+    let hs256 = encode_token(&[
+        "encode",
+        "--alg",
+        "hs256",
+        "--secret",
+        "s3cret",
+        r#"{"iss":"https://acme.example.com","aud":"gateway"}"#,
+    ]);
+    assert_exit(&["verify", "--tenant-config", &config, &hs256], 10);
+}
@@ -0,0 +1,113 @@
+mod common;
+
+use common::{encode_token, TestVault};
+use serde_json::Value;
+use std::io::{BufRead, BufReader};
+
+#[test]
+fn monitor_once_reports_expiring_vault_and_file_tokens() {
+    let vault = TestVault::new();
+    let dir = tempfile::tempdir().expect("tempdir");
+    let later = dir.path().join("later.jwt");
+    std::fs::write(
+        &later,
+        encode_token(&["encode", "--alg", "hs256", "--secret", "s", "--exp", "+2h"]),
+    )
+    .expect("write token");
+    let soon = encode_token(&["encode", "--alg", "hs256", "--secret", "s", "--exp", "+5m"]);
+
+    vault.run_json(&["vault", "project", "add", "shared"]);
+    vault.run_json(&[
+        "vault",
+        "token",
+        "add",
+        "--project",
+        "shared",
+        "--name",
+        "ci",
+        "--token",
+        &soon,
+    ]);
+
+    let later_spec = format!("@{}", later.display());
+    let output = vault
+        .cmd()
+        .args([
+            "--json",
+            "monitor",
+            "--once",
+            "--project",
+            "shared",
+            &later_spec,
+        ])
+        .output()
+        .expect("run monitor");
+    assert_eq!(output.status.code(), Some(12));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).expect("json");
+    let tokens = report["data"]["tokens"].as_array().expect("tokens");
+    assert_eq!(report["data"]["alerts"], 1);
+    assert_eq!(tokens[0]["source"], later_spec);
+    assert_eq!(tokens[0]["status"], "valid");
+    assert_eq!(tokens[1]["source"], "vault:shared/ci");
+    assert_eq!(tokens[1]["status"], "expiring");
+
+    let quiet = vault.run_json(&["monitor", "--once", "--warn-before", "1m", &later_spec]);
+    assert_eq!(quiet["data"]["alerts"], 0);
+}
+
+#[test]
+fn monitor_requires_something_to_watch() {
+    common::assert_exit(&["monitor", "--once"], 10);
+}
+
+#[test]
+fn monitor_rejects_an_unknown_project_at_startup() {
+    let vault = TestVault::new();
+    vault.assert_exit(&["monitor", "--project", "missing", "--interval", "1s"], 13);
+}
+
+#[test]
+fn watch_reports_a_failed_check_and_keeps_polling() {
+    let vault = TestVault::new();
+    let soon = encode_token(&["encode", "--alg", "hs256", "--secret", "s", "--exp", "+5m"]);
+    vault.run_json(&["vault", "project", "add", "shared"]);
+    vault.run_json(&[
+        "vault",
+        "token",
+        "add",
+        "--project",
+        "shared",
+        "--name",
+        "ci",
+        "--token",
+        &soon,
+    ]);
+
+    let mut child = vault.spawn(&[
+        "--json",
+        "monitor",
+        "--project",
+        "shared",
+        "--interval",
+        "100ms",
+    ]);
+    let mut stdout = BufReader::new(child.stdout.take().expect("stdout"));
+    let mut next = || {
+        let mut line = String::new();
+        stdout.read_line(&mut line).expect("read line");
+        serde_json::from_str::<Value>(&line).expect("json line")
+    };
+    assert_eq!(next()["data"]["status"], "expiring");
+
+    // The database stops being readable between two checks.
+    std::fs::write(vault.data_dir().join("vault.sqlite3"), b"not a database").expect("corrupt");
+    let first = next();
+    let second = next();
+    let still_running = child.try_wait().expect("try_wait").is_none();
+    child.kill().ok();
+    child.wait().ok();
+
+    assert!(still_running);
+    assert_eq!(first["ok"], false, "{first}");
+    assert_eq!(second["ok"], false, "{second}");
+}
@@ -0,0 +1,206 @@
+#![cfg(feature = "ui")]
+
+mod common;
+
+use common::TestVault;
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::process::Child;
+use tempfile::TempDir;
+
+/// Kills the UI even when an assertion fails first.
+struct Ui(Child);
+
+impl Drop for Ui {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+struct UiClient {
+    base: String,
+    cookie: String,
+    csrf: String,
+}
+
+impl UiClient {
+    fn connect(base: &str) -> Self {
+        let response = ureq::get(&format!("{base}api/csrf")).call().expect("csrf");
+        let cookie = response
+            .header("set-cookie")
+            .and_then(|value| value.split(';').next())
+            .expect("session cookie")
+            .to_string();
+        let body: Value = response.into_json().expect("csrf JSON");
+        UiClient {
+            base: base.to_string(),
+            cookie,
+            csrf: body["csrf"].as_str().expect("csrf token").to_string(),
+        }
+    }
+
+    fn post(&self, endpoint: &str, body: Value) -> (u16, Value) {
+        let result = ureq::post(&format!("{}api/jwt/{endpoint}", self.base))
+            .set("Cookie", &self.cookie)
+            .set("x-csrf-token", &self.csrf)
+            .send_json(body);
+        match result {
+            Ok(response) => (response.status(), response.into_json().expect("JSON")),
+            Err(ureq::Error::Status(status, response)) => {
+                (status, response.into_json().expect("error JSON"))
+            }
+            Err(err) => panic!("UI request failed: {err}"),
+        }
+    }
+}
+
+fn start_ui(vault: &TestVault, assets: &Path, session: &Path) -> (Ui, String) {
+    let mut child = vault.spawn_with_env(
+        &["--json", "ui", "--record", session.to_str().unwrap()],
+        &[("JWT_TESTER_UI_ASSETS_DIR", assets)],
+    );
+    let stdout = child.stdout.take().expect("stdout");
+    let ui = Ui(child);
+    let mut line = String::new();
+    BufReader::new(stdout)
+        .read_line(&mut line)
+        .expect("read startup line");
+    let started: Value = serde_json::from_str(&line).expect("startup JSON");
+    let url = started["data"]["url"].as_str().expect("url").to_string();
+    (ui, url)
+}
+
+fn replay(vault: &TestVault, session: &Path, extra: &[&str]) -> (Option<i32>, Value) {
+    let output = vault
+        .cmd()
+        .args(["--json", "replay", session.to_str().unwrap()])
+        .args(extra)
+        .output()
+        .expect("run replay");
+    let body = serde_json::from_slice(&output.stdout).expect("replay JSON");
+    (output.status.code(), body)
+}
+
+#[test]
+fn ui_session_is_recorded_redacted_and_replays_through_the_cli() {
+    let vault = TestVault::new();
+    vault.run_json(&["vault", "project", "add", "demo"]);
+    vault.run_json(&[
+        "vault",
+        "key",
+        "generate",
+        "--project",
+        "demo",
+        "--kind",
+        "hmac",
+        "--name",
+        "signing",
+    ]);
+    let dir = TempDir::new().expect("temp dir");
+    let assets = dir.path().join("assets");
+    std::fs::create_dir(&assets).unwrap();
+    std::fs::write(assets.join("index.html"), "<html></html>").unwrap();
+    let session = dir.path().join("session.jsonl");
+
+    let (ui, url) = start_ui(&vault, &assets, &session);
+    let client = UiClient::connect(&url);
+    let (status, encoded) = client.post(
+        "encode",
+        json!({ "project": "demo", "alg": "HS256", "claims": "{\"sub\":\"alice\"}", "exp": "+10m" }),
+    );
+    assert_eq!(status, 200, "{encoded}");
+    let token = encoded["data"]["token"]
+        .as_str()
+        .expect("token")
+        .to_string();
+    let (status, _) = client.post("verify", json!({ "project": "demo", "token": token }));
+    assert_eq!(status, 200);
+    let tampered = format!("{}AAAA", &token[..token.len() - 4]);
+    let (status, rejected) = client.post("verify", json!({ "project": "demo", "token": tampered }));
+    assert_eq!(
+        (status, rejected["code"].as_str()),
+        (401, Some("INVALID_SIGNATURE"))
+    );
+    let (status, _) = client.post("inspect", json!({ "token": token, "date": "utc" }));
+    assert_eq!(status, 200);
+    drop(ui);
+
+    let recorded = std::fs::read_to_string(&session).expect("session file");
+    let entries: Vec<Value> = recorded
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("entry JSON"))
+        .collect();
+    let endpoints: Vec<&str> = entries
+        .iter()
+        .map(|entry| entry["endpoint"].as_str().unwrap())
+        .collect();
+    assert_eq!(endpoints, ["encode", "verify", "verify", "inspect"]);
+    let minted = entries[0]["response"]["data"]["token"].as_str().unwrap();
+    assert!(minted.ends_with(".<redacted>"), "{minted}");
+
+    let (code, report) = replay(&vault, &session, &[]);
+    assert_eq!(code, Some(0), "{report}");
+    assert_eq!(report["data"]["matched"], 4);
+    assert_eq!(
+        report["data"]["entries"][2]["replayed"],
+        "INVALID_SIGNATURE"
+    );
+    let command = report["data"]["entries"][0]["command"].as_str().unwrap();
+    assert!(
+        command.starts_with("jwt-tester encode --project=demo --alg=hs256"),
+        "{command}"
+    );
+
+    // A UI success that the CLI now rejects is reported as a mismatch.
+    let mut edited = entries[2].clone();
+    edited["status"] = json!(200);
+    std::fs::write(&session, format!("{recorded}{edited}\n")).unwrap();
+    let (code, report) = replay(&vault, &session, &["--entry", "5"]);
+    assert_eq!(code, Some(12), "{report}");
+    assert_eq!(report["data"]["mismatched"], 1);
+    assert_eq!(report["data"]["entries"][0]["recorded"], "ok");
+}
+
+#[cfg(unix)]
+#[test]
+fn ctrl_c_stops_replay_and_reports_the_entries_already_replayed() {
+    let vault = TestVault::new();
+    let token = common::encode_token(&[
+        "encode",
+        "--alg",
+        "hs256",
+        "--secret",
+        "secret",
+        r#"{"sub":"alice"}"#,
+    ]);
+    let entry = json!({
+        "endpoint": "inspect",
+        "request": { "token": token },
+        "status": 200,
+        "response": { "ok": true },
+    });
+    let dir = TempDir::new().expect("temp dir");
+    let session = dir.path().join("session.jsonl");
+    std::fs::write(&session, format!("{entry}\n").repeat(200)).unwrap();
+
+    let mut child = vault.spawn(&["--json-lines", "replay", session.to_str().unwrap()]);
+    let mut stdout = BufReader::new(child.stdout.take().expect("stdout"));
+    let mut first = String::new();
+    stdout.read_line(&mut first).expect("first record");
+    let interrupted = std::process::Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .expect("run kill");
+    assert!(interrupted.success());
+
+    let lines: Vec<String> = stdout.lines().map(|line| line.expect("line")).collect();
+    let status = child.wait().expect("wait for replay");
+    assert_eq!(status.code(), Some(130));
+    let result: Value = serde_json::from_str(lines.last().expect("result line")).unwrap();
+    assert_eq!(result["data"]["cancelled"], true);
+    let replayed = result["data"]["replayed"].as_u64().unwrap();
+    assert!((1..200).contains(&replayed), "{result}");
+    assert_eq!(replayed as usize, lines.len());
+}
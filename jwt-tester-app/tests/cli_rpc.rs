@@ -0,0 +1,97 @@
+mod common;
+
+use common::TestVault;
+use serde_json::{json, Value};
+
+fn rpc(vault: &TestVault, requests: &[Value]) -> Vec<Value> {
+    let input: String = requests.iter().map(|req| format!("{req}\n")).collect();
+    let output = vault
+        .cmd()
+        .arg("--rpc")
+        .write_stdin(input)
+        .output()
+        .expect("run jwt-tester --rpc");
+    assert_eq!(output.status.code(), Some(0));
+    String::from_utf8(output.stdout)
+        .expect("utf8")
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("response is one JSON line"))
+        .collect()
+}
+
+fn request(id: u64, method: &str, params: &[&str]) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+}
+
+#[test]
+fn rpc_serves_encode_verify_decode_and_vault_queries_from_one_process() {
+    let vault = TestVault::new();
+    vault.run_json(&["vault", "project", "add", "demo"]);
+    vault.run_json(&[
+        "vault",
+        "key",
+        "generate",
+        "--project",
+        "demo",
+        "--kind",
+        "hmac",
+        "--name",
+        "signing",
+    ]);
+
+    let encoded = rpc(
+        &vault,
+        &[request(
+            1,
+            "encode",
+            &["--alg", "hs256", "--project", "demo", r#"{"sub":"alice"}"#],
+        )],
+    );
+    assert_eq!(encoded[0]["id"], 1);
+    let token = encoded[0]["result"]["token"]
+        .as_str()
+        .expect("token")
+        .to_string();
+
+    let responses = rpc(
+        &vault,
+        &[
+            request(2, "verify", &["--project", "demo", &token]),
+            request(3, "decode", &[&token]),
+            request(4, "vault", &["key", "list", "--project", "demo"]),
+            request(5, "verify", &["--secret", "wrong", &token]),
+            request(6, "verify", &["--secret", "-", &token]),
+            request(7, "vault", &["project", "delete", "--name", "demo"]),
+            request(8, "ui", &[]),
+            json!({ "jsonrpc": "2.0", "method": "decode", "params": [&token] }),
+        ],
+    );
+    assert_eq!(responses.len(), 7, "notifications get no response");
+    assert_eq!(responses[0]["result"]["claims"]["sub"], "alice");
+    assert_eq!(responses[1]["result"]["payload"]["sub"], "alice");
+    assert_eq!(responses[2]["result"]["keys"][0]["name"], "signing");
+
+    let failed = &responses[3]["error"];
+    assert_eq!(failed["code"], -32000);
+    assert_eq!(failed["data"]["code"], "INVALID_SIGNATURE");
+    assert_eq!(failed["data"]["exit_code"], 11);
+    assert_eq!(
+        responses[4]["error"]["code"], -32602,
+        "stdin is the transport"
+    );
+    assert_eq!(
+        responses[5]["error"]["code"], -32602,
+        "vault writes stay on the CLI"
+    );
+    assert_eq!(responses[6]["error"]["code"], -32601);
+
+    let projects = vault.run_json(&["vault", "project", "list"]);
+    assert_eq!(projects["data"]["projects"][0]["name"], "demo");
+}
+
+#[test]
+fn rpc_and_a_subcommand_are_mutually_exclusive() {
+    let vault = TestVault::new();
+    vault.assert_exit(&["--rpc", "decode", "x"], 2);
+    vault.assert_exit(&["--json"], 2);
+}
@@ -0,0 +1,188 @@
+#![cfg(unix)]
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use blake2::{Blake2b512, Digest};
+use ed25519_dalek::{Signer, SigningKey};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::path::Path;
+use std::thread;
+
+const KEY_ID: [u8; 8] = [1, 2, 3, 4, 5, 6, 7, 8];
+
+fn signing_key() -> SigningKey {
+    SigningKey::from_bytes(&[7; 32])
+}
+
+fn minisign_public_key() -> String {
+    let mut bin = b"Ed".to_vec();
+    bin.extend(KEY_ID);
+    bin.extend(signing_key().verifying_key().to_bytes());
+    STANDARD.encode(bin)
+}
+
+fn asset_name() -> String {
+    format!(
+        "jwt-tester-{}-{}",
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    )
+}
+
+/// Signature for the `v99.0.0` release that `mock_releases` serves.
+fn minisign_signature(data: &[u8]) -> Vec<u8> {
+    minisign_signature_for(data, "v99.0.0")
+}
+
+/// Pre-hashed minisign signature (`ED`) as written by
+/// `minisign -S -t "timestamp:0 file:<asset> tag:<tag>"`.
+fn minisign_signature_for(data: &[u8], tag: &str) -> Vec<u8> {
+    let key = signing_key();
+    let signature = key.sign(&Blake2b512::digest(data)).to_bytes();
+    let trusted = format!("timestamp:0\tfile:{}\ttag:{tag}", asset_name());
+    let mut global = signature.to_vec();
+    global.extend(trusted.as_bytes());
+    let mut bin = b"ED".to_vec();
+    bin.extend(KEY_ID);
+    bin.extend(signature);
+    format!(
+        "untrusted comment: test\n{}\ntrusted comment: {trusted}\n{}\n",
+        STANDARD.encode(bin),
+        STANDARD.encode(key.sign(&global).to_bytes())
+    )
+    .into_bytes()
+}
+
+/// Serves a GitHub-style `releases/latest` document and its assets until the test exits.
+fn mock_releases(binary: Vec<u8>, signature: Vec<u8>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let asset = asset_name();
+    let release = json!({
+        "tag_name": "v99.0.0",
+        "assets": [
+            { "name": asset, "browser_download_url": format!("{base}/download/bin") },
+            { "name": format!("{asset}.minisig"), "browser_download_url": format!("{base}/download/sig") },
+        ],
+    });
+    let mut routes: HashMap<String, Vec<u8>> = HashMap::new();
+    routes.insert(
+        "/repos/jMerta/jwt-tester/releases/latest".to_string(),
+        release.to_string().into_bytes(),
+    );
+    routes.insert("/download/bin".to_string(), binary);
+    routes.insert("/download/sig".to_string(), signature);
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut reader = BufReader::new(stream.expect("accept"));
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).expect("read");
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).expect("read");
+                if line == "\r\n" || line.is_empty() {
+                    break;
+                }
+            }
+            let path = request_line.split_whitespace().nth(1).unwrap_or_default();
+            let (status, body) = match routes.get(path) {
+                Some(body) => (200, body.clone()),
+                None => (404, b"{}".to_vec()),
+            };
+            let head = format!(
+                "HTTP/1.1 {status} X\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                body.len()
+            );
+            let stream = reader.get_mut();
+            stream.write_all(head.as_bytes()).expect("write");
+            stream.write_all(&body).expect("write");
+        }
+    });
+    base
+}
+
+fn installed_copy(dir: &Path) -> std::path::PathBuf {
+    let exe = dir.join("jwt-tester");
+    std::fs::copy(assert_cmd::cargo::cargo_bin!("jwt-tester"), &exe).expect("copy binary");
+    exe
+}
+
+fn self_update(exe: &Path, api: &str, extra: &[&str]) -> (Option<i32>, Value) {
+    let output = std::process::Command::new(exe)
+        .args(["--json", "self-update", "--api-url", api, "--public-key"])
+        .arg(minisign_public_key())
+        .args(extra)
+        .output()
+        .expect("self-update");
+    let value = serde_json::from_slice(&output.stdout).expect("json output");
+    (output.status.code(), value)
+}
+
+#[test]
+fn self_update_installs_signed_release() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let exe = installed_copy(dir.path());
+    let new_binary = std::fs::read(&exe).expect("read binary");
+    let api = mock_releases(new_binary.clone(), minisign_signature(&new_binary));
+
+    let (code, out) = self_update(&exe, &api, &["--check"]);
+    assert_eq!(code, Some(0), "{out}");
+    assert_eq!(out["data"]["update_available"], true);
+    assert_eq!(out["data"]["updated"], false);
+
+    let (code, out) = self_update(&exe, &api, &[]);
+    assert_eq!(code, Some(0), "{out}");
+    assert_eq!(out["data"]["updated"], true);
+    assert_eq!(out["data"]["latest"], "v99.0.0");
+    assert!(std::process::Command::new(&exe)
+        .arg("--version")
+        .status()
+        .expect("run updated binary")
+        .success());
+}
+
+#[test]
+fn self_update_rejects_bad_signatures_and_rolls_back_broken_binaries() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let exe = installed_copy(dir.path());
+    let original = std::fs::read(&exe).expect("read binary");
+
+    let tampered = b"#!/bin/sh\nexit 0\n".to_vec();
+    let api = mock_releases(tampered, minisign_signature(b"something else"));
+    let (code, out) = self_update(&exe, &api, &[]);
+    assert_eq!(code, Some(11), "{out}");
+    assert_eq!(out["error"]["code"], "INVALID_SIGNATURE");
+    assert_eq!(std::fs::read(&exe).expect("read binary"), original);
+
+    // A genuine signature, but for an older release: the tag it names must match.
+    let api = mock_releases(
+        original.clone(),
+        minisign_signature_for(&original, "v0.0.1"),
+    );
+    let (code, out) = self_update(&exe, &api, &[]);
+    assert_eq!(code, Some(11), "{out}");
+    assert!(out["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("tag:v0.0.1"));
+    assert_eq!(std::fs::read(&exe).expect("read binary"), original);
+
+    let broken = b"#!/bin/sh\nexit 3\n".to_vec();
+    let api = mock_releases(broken.clone(), minisign_signature(&broken));
+    let (code, out) = self_update(&exe, &api, &[]);
+    assert_eq!(code, Some(14), "{out}");
+    assert!(out["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("restored the previous version"));
+    assert_eq!(std::fs::read(&exe).expect("read binary"), original);
+    let leftovers: Vec<_> = std::fs::read_dir(dir.path())
+        .expect("read dir")
+        .map(|entry| entry.expect("entry").file_name())
+        .collect();
+    assert_eq!(leftovers.len(), 1, "{leftovers:?}");
+}
@@ -0,0 +1,57 @@
+#![cfg(feature = "ui")]
+
+mod common;
+
+use common::TestVault;
+
+#[test]
+fn serve_requires_grpc_and_refuses_remote_hosts() {
+    let vault = TestVault::new();
+    vault.assert_exit(&["serve"], 2);
+    vault.assert_exit(&["serve", "--grpc", "--host", "0.0.0.0"], 13);
+}
+
+#[cfg(feature = "grpc")]
+#[test]
+fn serve_grpc_prints_its_address_and_services() {
+    use std::io::{BufRead, BufReader};
+
+    let vault = TestVault::new();
+    let mut child = vault.spawn(&["--json", "serve", "--grpc"]);
+    let stdout = child.stdout.take().expect("stdout");
+    let mut line = String::new();
+    let read = BufReader::new(stdout).read_line(&mut line);
+    let _ = child.kill();
+    let _ = child.wait();
+    read.expect("read startup line");
+
+    let started: serde_json::Value = serde_json::from_str(&line).expect("startup JSON");
+    let url = started["data"]["url"].as_str().expect("url");
+    assert!(url.starts_with("http://127.0.0.1:"), "{url}");
+    assert_eq!(
+        started["data"]["services"],
+        serde_json::json!([
+            "jwt_tester.v1.Encode",
+            "jwt_tester.v1.Verify",
+            "jwt_tester.v1.Inspect",
+            "jwt_tester.v1.Vault"
+        ])
+    );
+}
+
+#[cfg(not(feature = "grpc"))]
+#[test]
+fn serve_grpc_needs_the_grpc_feature() {
+    let vault = TestVault::new();
+    let output = vault
+        .cmd()
+        .args(["--json", "serve", "--grpc"])
+        .output()
+        .expect("run serve");
+    assert_eq!(output.status.code(), Some(14));
+    let err: serde_json::Value = serde_json::from_slice(&output.stdout).expect("json");
+    assert!(err["error"]["message"]
+        .as_str()
+        .expect("message")
+        .contains("`grpc` feature"));
+}
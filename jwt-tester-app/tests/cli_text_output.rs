@@ -1,5 +1,5 @@
 mod common;
-use common::{at_path, fixture_path, TestVault};
+use common::{at_path, encode_token, fixture_path, run_json, TestVault};
 
 #[test]
 fn text_output_prints_token() {
@@ -50,12 +50,336 @@ fn text_error_respects_no_color_and_verbose() {
 }
 
 #[test]
-fn text_error_uses_color_by_default() {
+fn text_error_uses_color_when_forced() {
     let output = assert_cmd::cargo::cargo_bin_cmd!()
         .args(["decode", "not-a-token"])
+        .env("CLICOLOR_FORCE", "1")
+        .env_remove("NO_COLOR")
         .output()
         .expect("decode");
     assert!(!output.status.success());
     let stderr = String::from_utf8_lossy(&output.stderr);
     assert!(stderr.contains("\u{1b}[31m"));
+
+    // Piped output is not a terminal, so colors are off unless forced.
+    let output = assert_cmd::cargo::cargo_bin_cmd!()
+        .args(["decode", "not-a-token"])
+        .env_remove("CLICOLOR_FORCE")
+        .output()
+        .expect("decode");
+    assert!(!String::from_utf8_lossy(&output.stderr).contains('\u{1b}'));
+}
+
+#[test]
+fn text_output_colors_statuses_by_theme() {
+    let secret = at_path(&fixture_path("hmac.key"));
+    let expired = encode_token(&["encode", "--alg", "hs256", "--secret", &secret, "--exp=-1h"]);
+    let run = |args: &[&str], env: &[(&str, &str)]| {
+        let mut cmd = assert_cmd::cargo::cargo_bin_cmd!();
+        cmd.args(args)
+            .env("CLICOLOR_FORCE", "1")
+            .env_remove("NO_COLOR");
+        for (name, value) in env {
+            cmd.env(name, value);
+        }
+        let output = cmd.output().expect("run");
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    let stdout = run(&["decode", "--date=utc", &expired], &[]);
+    assert!(
+        stdout.starts_with("\u{1b}[33mUNVERIFIED\u{1b}[0m"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("\u{1b}[31mexp: "), "{stdout}");
+
+    let stdout = run(
+        &["--theme", "bright", "inspect", "--date=utc", &expired],
+        &[],
+    );
+    assert!(stdout.contains("\u{1b}[93mUNVERIFIED"), "{stdout}");
+    assert!(stdout.contains("\u{1b}[91mexp: "), "{stdout}");
+
+    let stdout = run(&["--theme", "none", "decode", &expired], &[]);
+    assert!(!stdout.contains('\u{1b}'), "{stdout}");
+    let stdout = run(&["decode", &expired], &[("NO_COLOR", "1")]);
+    assert!(!stdout.contains('\u{1b}'), "{stdout}");
+}
+
+#[test]
+fn verify_print_claims_shows_selected_claims() {
+    let secret = at_path(&fixture_path("hmac.key"));
+    let token = encode_token(&[
+        "encode",
+        "--alg",
+        "hs256",
+        "--secret",
+        &secret,
+        "--sub",
+        "user-1",
+        "--claim",
+        "scope=read write",
+        "--claim",
+        "profile={\"tier\":\"gold\"}",
+    ]);
+    let args = [
+        "verify",
+        "--secret",
+        &secret,
+        "--print-claims",
+        "sub,scope,profile.tier,exp",
+        &token,
+    ];
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!()
+        .args(args)
+        .output()
+        .expect("verify");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("sub           user-1"),
+        "stdout was: {stdout}"
+    );
+    assert!(
+        stdout.contains("profile.tier  gold"),
+        "stdout was: {stdout}"
+    );
+    assert!(
+        stdout.contains("exp           (missing)"),
+        "stdout was: {stdout}"
+    );
+    assert!(!stdout.contains("\"tier\""), "stdout was: {stdout}");
+
+    let out = run_json(&args);
+    assert_eq!(out["data"]["selected_claims"]["scope"], "read write");
+    assert!(out["data"]["selected_claims"]["exp"].is_null());
+    assert_eq!(out["data"]["claims"]["sub"], "user-1");
+}
+
+#[test]
+fn template_renders_command_data() {
+    let secret = at_path(&fixture_path("hmac.key"));
+    let render = |args: &[&str]| {
+        let output = assert_cmd::cargo::cargo_bin_cmd!()
+            .args(args)
+            .output()
+            .expect("run");
+        assert!(output.status.success(), "{output:?}");
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    };
+
+    let encoded = render(&[
+        "--template",
+        "{{ claims.sub }} {{ header.alg }}",
+        "encode",
+        "--alg",
+        "hs256",
+        "--secret",
+        &secret,
+        "--sub",
+        "user-1",
+    ]);
+    assert_eq!(encoded, "user-1 HS256");
+
+    let token = encode_token(&[
+        "encode", "--alg", "hs256", "--secret", &secret, "--sub", "user-1", "--exp", "+1h",
+    ]);
+    let template = "{{ claims.sub }} expires {{ dates.exp.relative }}";
+    let verified = render(&[
+        "--template",
+        template,
+        "verify",
+        "--secret",
+        &secret,
+        &token,
+    ]);
+    assert!(verified.starts_with("user-1 expires in "), "{verified}");
+    let decoded = render(&["--json", "--template", template, "decode", &token]);
+    assert!(decoded.starts_with("user-1 expires in "), "{decoded}");
+
+    let vault = TestVault::new();
+    vault.run_json(&["vault", "project", "add", "alpha"]);
+    vault.run_json(&["vault", "project", "add", "beta"]);
+    let output = vault
+        .cmd()
+        .args([
+            "--template",
+            "{% for p in projects %}{{ p.name }};{% endfor %}",
+            "vault",
+            "project",
+            "list",
+        ])
+        .output()
+        .expect("list");
+    // Listing order follows creation time, which can tie within a second.
+    let mut names: Vec<String> = String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .split_terminator(';')
+        .map(str::to_string)
+        .collect();
+    names.sort();
+    assert_eq!(names, ["alpha", "beta"]);
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!()
+        .args(["--template", "{{ claims.sub", "decode", &token])
+        .output()
+        .expect("decode");
+    assert_eq!(output.status.code(), Some(17));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("invalid --template"));
+
+    // A template that parses but fails against the data is reported instead of the result.
+    let output = assert_cmd::cargo::cargo_bin_cmd!()
+        .args(["--template", "{{ claims.sub + 1 }}", "decode", &token])
+        .output()
+        .expect("decode");
+    assert_eq!(output.status.code(), Some(17));
+    assert!(output.stdout.is_empty(), "{output:?}");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("failed to render --template"));
+}
+
+#[test]
+fn summary_file_records_success_and_failure() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let summary_path = dir.path().join("summary.json");
+    let summary_arg = summary_path.to_string_lossy().to_string();
+    let secret = at_path(&fixture_path("hmac.key"));
+    let token = encode_token(&["encode", "--alg", "hs256", "--secret", &secret]);
+    let read_summary = || -> serde_json::Value {
+        serde_json::from_str(&std::fs::read_to_string(&summary_path).expect("summary"))
+            .expect("summary json")
+    };
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!()
+        .args([
+            "--summary-file",
+            &summary_arg,
+            "verify",
+            "--secret",
+            &secret,
+            &token,
+        ])
+        .output()
+        .expect("verify");
+    assert!(output.status.success());
+    let summary = read_summary();
+    assert_eq!(summary["status"], "ok");
+    assert_eq!(summary["exit_code"], 0);
+    assert_eq!(summary["command"], "verify");
+    assert!(summary["key_source"]
+        .as_str()
+        .unwrap()
+        .starts_with("secret:@"));
+    assert!(summary["duration_ms"].is_u64());
+    assert!(summary["error"].is_null());
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!()
+        .args([
+            "--summary-file",
+            &summary_arg,
+            "verify",
+            "--secret",
+            "wrong-secret",
+            &token,
+        ])
+        .output()
+        .expect("verify");
+    assert_eq!(output.status.code(), Some(11));
+    let summary = read_summary();
+    assert_eq!(summary["status"], "error");
+    assert_eq!(summary["exit_code"], 11);
+    assert_eq!(summary["error"]["code"], "INVALID_SIGNATURE");
+    assert_eq!(summary["key_source"], "secret:<inline>");
+}
+
+#[test]
+fn output_sinks_mirror_the_json_result() {
+    use std::io::{BufRead, BufReader, Read, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind");
+    let webhook = format!("http://{}/results", listener.local_addr().unwrap());
+    let received = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().expect("accept");
+        let mut reader = BufReader::new(stream);
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).expect("read line");
+            if let Some(len) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                content_length = len.trim().parse().unwrap_or(0);
+            }
+            if line == "\r\n" {
+                break;
+            }
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).expect("read body");
+        reader
+            .get_mut()
+            .write_all(b"HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n")
+            .expect("write");
+        serde_json::from_slice::<serde_json::Value>(&body).expect("webhook json")
+    });
+
+    let dir = tempfile::TempDir::new().expect("temp dir");
+    let result_path = dir.path().join("result.json");
+    let secret = at_path(&fixture_path("hmac.key"));
+    let token = encode_token(&["encode", "--alg", "hs256", "--secret", &secret]);
+    let output = assert_cmd::cargo::cargo_bin_cmd!()
+        .args([
+            "--output-file",
+            &result_path.to_string_lossy(),
+            "--output-webhook",
+            &webhook,
+            "verify",
+            "--secret",
+            "wrong-secret",
+            &token,
+        ])
+        .output()
+        .expect("verify");
+    assert_eq!(output.status.code(), Some(11));
+    // Text mode on stdout/stderr, JSON in the sinks.
+    assert!(String::from_utf8_lossy(&output.stderr).contains("InvalidSignature"));
+
+    let file: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&result_path).expect("result file"))
+            .expect("result json");
+    assert_eq!(file["ok"], false);
+    assert_eq!(file["error"]["code"], "INVALID_SIGNATURE");
+    assert_eq!(received.join().expect("webhook"), file);
+}
+
+#[test]
+fn output_sinks_never_receive_revealed_key_material() {
+    let vault = TestVault::new();
+    vault.run_json(&["vault", "project", "add", "alpha"]);
+    let result_path = vault.data_dir().join("result.json");
+    let output = vault
+        .cmd()
+        .args([
+            "--json",
+            "--output-file",
+            &result_path.to_string_lossy(),
+            "vault",
+            "key",
+            "generate",
+            "--project",
+            "alpha",
+            "--kind",
+            "eddsa",
+            "--reveal",
+        ])
+        .output()
+        .expect("generate");
+    assert!(output.status.success());
+    let printed: serde_json::Value = serde_json::from_slice(&output.stdout).expect("stdout json");
+    let material = printed["data"]["material"].as_str().expect("material");
+    assert!(material.contains("PRIVATE KEY"));
+
+    let written = std::fs::read_to_string(&result_path).expect("result file");
+    assert!(!written.contains("PRIVATE KEY"), "{written}");
+    let file: serde_json::Value = serde_json::from_str(&written).expect("result json");
+    assert_eq!(file["data"]["material"], "<redacted>");
+    assert_eq!(file["data"]["key"]["id"], printed["data"]["key"]["id"]);
 }
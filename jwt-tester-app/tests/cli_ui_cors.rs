@@ -0,0 +1,103 @@
+#![cfg(feature = "ui")]
+
+mod common;
+
+use common::TestVault;
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::process::Child;
+use tempfile::TempDir;
+
+const FOREIGN_ORIGIN: &str = "https://tools.example.com";
+
+/// Kills the UI even when an assertion fails first.
+struct Ui(Child);
+
+impl Drop for Ui {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+fn start_ui(vault: &TestVault, assets: &Path, extra: &[&str]) -> (Ui, String) {
+    let mut args = vec!["--json", "ui"];
+    args.extend_from_slice(extra);
+    let mut child = vault.spawn_with_env(&args, &[("JWT_TESTER_UI_ASSETS_DIR", assets)]);
+    let stdout = child.stdout.take().expect("stdout");
+    let ui = Ui(child);
+    let mut line = String::new();
+    BufReader::new(stdout)
+        .read_line(&mut line)
+        .expect("read startup line");
+    let started: Value = serde_json::from_str(&line).expect("startup JSON");
+    let url = started["data"]["url"].as_str().expect("url").to_string();
+    (ui, url)
+}
+
+/// Sends a request the way a page on another origin would: no session cookie, no CSRF token.
+fn foreign(method: &str, url: &str, body: Option<Value>) -> (u16, Option<String>) {
+    let request = ureq::request(method, url).set("Origin", FOREIGN_ORIGIN);
+    let result = match body {
+        Some(body) => request.send_json(body),
+        None => request.call(),
+    };
+    let response = match result {
+        Ok(response) | Err(ureq::Error::Status(_, response)) => response,
+        Err(err) => panic!("UI request failed: {err}"),
+    };
+    let allow_origin = response
+        .header("access-control-allow-origin")
+        .map(str::to_string);
+    (response.status(), allow_origin)
+}
+
+#[test]
+fn allow_cors_inspect_opens_only_the_inspect_endpoint() {
+    let vault = TestVault::new();
+    let dir = TempDir::new().expect("temp dir");
+    let assets = dir.path().join("assets");
+    std::fs::create_dir(&assets).unwrap();
+    std::fs::write(assets.join("index.html"), "<html></html>").unwrap();
+    let token = common::encode_token(&[
+        "encode",
+        "--alg",
+        "hs256",
+        "--secret",
+        "secret",
+        r#"{"sub":"alice"}"#,
+    ]);
+    let inspect = json!({ "token": token });
+
+    let (ui, url) = start_ui(&vault, &assets, &[]);
+    let (status, allow_origin) = foreign(
+        "POST",
+        &format!("{url}api/jwt/inspect"),
+        Some(inspect.clone()),
+    );
+    assert_eq!((status, allow_origin), (403, None));
+    drop(ui);
+
+    let (_ui, url) = start_ui(&vault, &assets, &["--allow-cors-inspect"]);
+    let (status, allow_origin) = foreign("OPTIONS", &format!("{url}api/jwt/inspect"), None);
+    assert_eq!((status, allow_origin.as_deref()), (204, Some("*")));
+    let (status, allow_origin) = foreign("POST", &format!("{url}api/jwt/inspect"), Some(inspect));
+    assert_eq!((status, allow_origin.as_deref()), (200, Some("*")));
+
+    let (status, allow_origin) = foreign(
+        "POST",
+        &format!("{url}api/jwt/verify"),
+        Some(json!({ "project": "demo", "token": token })),
+    );
+    assert_eq!((status, allow_origin), (403, None));
+    let (status, _) = foreign(
+        "POST",
+        &format!("{url}api/vault/projects"),
+        Some(json!({ "name": "evil" })),
+    );
+    assert_eq!(status, 403);
+    let (status, allow_origin) = foreign("OPTIONS", &format!("{url}api/jwt/encode"), None);
+    assert_ne!(status, 204);
+    assert_eq!(allow_origin, None);
+}
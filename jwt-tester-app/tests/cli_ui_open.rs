@@ -0,0 +1,88 @@
+#![cfg(all(feature = "ui", unix))]
+
+mod common;
+
+use common::TestVault;
+use serde_json::Value;
+use std::io::{BufRead, BufReader};
+use std::os::unix::fs::PermissionsExt;
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+#[test]
+fn open_launches_browser_with_the_ui_url_once_it_listens() {
+    let vault = TestVault::new();
+    let dir = TempDir::new().expect("temp dir");
+    let assets = dir.path().join("assets");
+    std::fs::create_dir(&assets).unwrap();
+    std::fs::write(assets.join("index.html"), "<html></html>").unwrap();
+    let opened = dir.path().join("opened.txt");
+    let browser = dir.path().join("browser.sh");
+    std::fs::write(
+        &browser,
+        format!(
+            "#!/bin/sh\nprintf '%s' \"$1\" > '{0}.tmp' && mv '{0}.tmp' '{0}'\n",
+            opened.display()
+        ),
+    )
+    .unwrap();
+    std::fs::set_permissions(&browser, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let mut child = vault.spawn_with_env(
+        &["--json", "ui", "--open", "--qr"],
+        &[("JWT_TESTER_UI_ASSETS_DIR", &assets), ("BROWSER", &browser)],
+    );
+    let stdout = child.stdout.take().expect("stdout");
+    let mut line = String::new();
+    BufReader::new(stdout)
+        .read_line(&mut line)
+        .expect("read startup line");
+    let started: Value = serde_json::from_str(&line).expect("startup JSON, QR stays off stdout");
+    let url = started["data"]["url"].as_str().expect("url").to_string();
+
+    let deadline = Instant::now() + Duration::from_secs(20);
+    while !opened.exists() && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+    assert_eq!(std::fs::read_to_string(&opened).expect("browser ran"), url);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn register_file_association_writes_a_desktop_entry_for_jwt_and_jwks() {
+    let vault = TestVault::new();
+    let dir = TempDir::new().expect("temp dir");
+    let data = dir.path().join("data");
+    let run = |extra: &[&str]| -> Value {
+        let output = vault
+            .cmd()
+            .env("HOME", dir.path())
+            .env("XDG_DATA_HOME", &data)
+            .env("XDG_CONFIG_HOME", dir.path().join("config"))
+            .args(["--json", "inspect", "--register-file-association"])
+            .args(extra)
+            .output()
+            .expect("run jwt-tester");
+        assert!(output.status.success(), "{output:?}");
+        serde_json::from_slice(&output.stdout).expect("json")
+    };
+
+    let planned = run(&["--dry-run"]);
+    assert_eq!(planned["data"]["registered"], false);
+    let desktop = data.join("applications").join("jwt-tester.desktop");
+    assert_eq!(
+        planned["data"]["files"][0]["path"].as_str(),
+        desktop.to_str()
+    );
+    assert!(!desktop.exists());
+
+    let done = run(&[]);
+    assert_eq!(done["data"]["registered"], true);
+    let entry = std::fs::read_to_string(&desktop).expect("desktop entry");
+    assert!(entry.contains("ui --open-file %u"), "{entry}");
+    assert!(entry.contains("x-scheme-handler/jwt-tester;"), "{entry}");
+    let mime = std::fs::read_to_string(data.join("mime/packages/jwt-tester.xml")).expect("mime");
+    assert!(mime.contains("*.jwt") && mime.contains("*.jwks"));
+}
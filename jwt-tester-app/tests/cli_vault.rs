@@ -97,6 +97,40 @@ fn vault_roundtrip_and_key_selection() {
     ]);
     assert_eq!(verified_alt["data"]["valid"], true);
 
+    let expired_alt = vault.run_json(&[
+        "encode",
+        "--project",
+        "alpha",
+        "--alg",
+        "hs256",
+        "--key-name",
+        "secondary",
+        "--exp=-1h",
+    ]);
+    let expired_alt = expired_alt["data"]["token"].as_str().unwrap().to_string();
+    let output = vault
+        .cmd()
+        .args([
+            "--json",
+            "verify",
+            "--project",
+            "alpha",
+            "--alg",
+            "hs256",
+            "--try-all-keys",
+            "--explain",
+            &expired_alt,
+        ])
+        .output()
+        .expect("run verify");
+    assert_eq!(output.status.code(), Some(12));
+    let err: serde_json::Value = serde_json::from_slice(&output.stdout).expect("json error");
+    let details = &err["error"]["details"];
+    assert_eq!(details["reason"], "expired");
+    assert_eq!(details["matched_key"], "secondary");
+    let attempts = details["attempts"].as_array().expect("attempts");
+    assert_eq!(attempts.last().unwrap()["result"], "expired");
+
     let beta = vault.run_json(&["vault", "project", "add", "beta"]);
     let beta_id = beta["data"]["project"]["id"].as_str().unwrap().to_string();
 
@@ -140,8 +174,18 @@ fn vault_roundtrip_and_key_selection() {
         13,
     );
 
-    let _ = vault.run_json(&["vault", "project", "delete", project_id]);
-    let _ = vault.run_json(&["vault", "project", "delete", &beta_id]);
+    // Without a terminal to ask on, destructive commands refuse unless --yes is given.
+    let refused = vault
+        .cmd()
+        .args(["vault", "project", "delete", project_id])
+        .assert()
+        .failure()
+        .code(13);
+    let stderr = String::from_utf8_lossy(&refused.get_output().stderr).to_string();
+    assert!(stderr.contains("--yes"), "{stderr}");
+
+    let _ = vault.run_json(&["vault", "project", "delete", "--yes", project_id]);
+    let _ = vault.run_json(&["vault", "project", "delete", "--yes", &beta_id]);
 }
 
 #[test]
@@ -251,7 +295,7 @@ fn vault_metadata_and_kid_selection() {
         13,
     );
 
-    let _ = vault.run_json(&["vault", "project", "delete", &project_id]);
+    let _ = vault.run_json(&["vault", "project", "delete", "--yes", &project_id]);
 }
 
 #[test]
@@ -314,3 +358,1240 @@ fn vault_key_generate_rsa_no_reveal() {
     assert!(generated["data"].get("material").is_none());
     assert_eq!(generated["data"]["key"]["kind"].as_str().unwrap(), "rsa");
 }
+
+#[test]
+fn vault_key_generate_reports_public_jwk_for_new_curves() {
+    let vault = TestVault::new();
+    let _ = vault.run_json(&["vault", "project", "add", "alpha"]);
+
+    let ed448 = vault.run_json(&[
+        "vault",
+        "key",
+        "generate",
+        "--project",
+        "alpha",
+        "--kind",
+        "eddsa",
+        "--ed-curve",
+        "Ed448",
+        "--kid",
+        "ed448-1",
+    ]);
+    let jwk = &ed448["data"]["public_jwk"];
+    assert_eq!(jwk["crv"].as_str(), Some("Ed448"));
+    assert_eq!(jwk["kid"].as_str(), Some("ed448-1"));
+    assert!(ed448["data"]["public_pem"]
+        .as_str()
+        .unwrap()
+        .contains("BEGIN PUBLIC KEY"));
+
+    let k1 = vault.run_json(&[
+        "vault",
+        "key",
+        "generate",
+        "--project",
+        "alpha",
+        "--kind",
+        "ec",
+        "--ec-curve",
+        "secp256k1",
+    ]);
+    assert_eq!(k1["data"]["public_jwk"]["alg"].as_str(), Some("ES256K"));
+
+    let enc = vault.run_json(&[
+        "vault",
+        "key",
+        "generate",
+        "--project",
+        "alpha",
+        "--kind",
+        "rsa",
+        "--key-use",
+        "enc",
+    ]);
+    assert_eq!(enc["data"]["public_jwk"]["use"].as_str(), Some("enc"));
+
+    vault.assert_exit(
+        &[
+            "vault",
+            "key",
+            "generate",
+            "--project",
+            "alpha",
+            "--kind",
+            "ec",
+            "--key-use",
+            "enc",
+        ],
+        13,
+    );
+}
+
+#[test]
+fn vault_listings_render_table_csv_and_json_lines() {
+    let vault = TestVault::new();
+    vault.run_json(&["vault", "project", "add", "alpha", "--tag", "dev"]);
+    vault.run_json(&[
+        "vault",
+        "project",
+        "add",
+        "beta, inc",
+        "--description",
+        "second",
+    ]);
+
+    let list = |format: &str, columns: &str| {
+        let output = vault
+            .cmd()
+            .args([
+                "vault",
+                "project",
+                "list",
+                "--format",
+                format,
+                "--columns",
+                columns,
+            ])
+            .output()
+            .expect("list projects");
+        assert!(output.status.success(), "{output:?}");
+        String::from_utf8_lossy(&output.stdout)
+            .trim_end()
+            .to_string()
+    };
+
+    let table = list("table", "name,tags,description");
+    let lines: Vec<&str> = table.lines().collect();
+    assert_eq!(lines[0], "NAME       TAGS  DESCRIPTION");
+    assert!(lines.contains(&"alpha      dev   -"), "table was: {table}");
+
+    let csv = list("csv", "name,description");
+    assert!(csv.starts_with("name,description\n"), "csv was: {csv}");
+    assert!(csv.contains("\"beta, inc\",second"), "csv was: {csv}");
+
+    let json_lines = list("json-lines", "name,tags");
+    let rows: Vec<serde_json::Value> = json_lines
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("json line"))
+        .collect();
+    assert_eq!(rows.len(), 2);
+    assert!(rows
+        .iter()
+        .any(|row| row == &serde_json::json!({ "name": "alpha", "tags": ["dev"] })));
+
+    vault.assert_exit(
+        &[
+            "vault",
+            "project",
+            "list",
+            "--format",
+            "csv",
+            "--columns",
+            "secret",
+        ],
+        13,
+    );
+}
+
+#[test]
+fn json_lines_mode_prints_one_record_per_line_then_the_result() {
+    let vault = TestVault::new();
+    vault.run_json(&["vault", "project", "add", "alpha"]);
+    vault.run_json(&["vault", "project", "add", "beta"]);
+
+    let output = vault
+        .cmd()
+        .args(["--json-lines", "vault", "project", "list"])
+        .output()
+        .expect("list projects");
+    assert!(output.status.success(), "{output:?}");
+    let lines: Vec<serde_json::Value> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("json line"))
+        .collect();
+    assert_eq!(lines.len(), 3);
+    // Projects list newest first, and both can share a creation second, so compare names only.
+    let mut names: Vec<&str> = lines[..2]
+        .iter()
+        .map(|line| line["name"].as_str().expect("project name"))
+        .collect();
+    names.sort_unstable();
+    assert_eq!(names, ["alpha", "beta"]);
+    assert_eq!(lines[2], serde_json::json!({ "ok": true, "data": {} }));
+
+    let output = vault
+        .cmd()
+        .args(["--json-lines", "vault", "project", "show", "missing"])
+        .output()
+        .expect("show project");
+    assert_eq!(output.status.code(), Some(13));
+    let error: serde_json::Value = serde_json::from_slice(&output.stdout).expect("error line");
+    assert_eq!(error["error"]["code"], "NOT_FOUND");
+}
+
+#[test]
+fn stored_jwks_resolves_kid_for_project_verification() {
+    let vault = TestVault::new();
+    let priv_key = at_path(&fixture_path("rsa_private.pem"));
+    let _ = vault.run_json(&["vault", "project", "add", "issuer"]);
+
+    let added = vault.run_json(&[
+        "vault",
+        "jwks",
+        "add",
+        "--project",
+        "issuer",
+        "--name",
+        "published",
+        &at_path(&fixture_path("jwks.json")),
+    ]);
+    let jwks_id = added["data"]["jwks"]["id"].as_str().expect("jwks id");
+    assert!(added["data"]["jwks"]["kids"]
+        .as_array()
+        .unwrap()
+        .contains(&"rsa1".into()));
+
+    let listed = vault.run_json(&["vault", "jwks", "list", "--project", "issuer"]);
+    assert_eq!(listed["data"]["jwks"].as_array().unwrap().len(), 1);
+    let shown = vault.run_json(&[
+        "vault",
+        "jwks",
+        "show",
+        "--project",
+        "issuer",
+        "--name",
+        "published",
+    ]);
+    assert!(shown["data"]["document"]["keys"].is_array());
+
+    let token = common::encode_token(&[
+        "encode", "--alg", "rs256", "--key", &priv_key, "--kid", "rsa1",
+    ]);
+    let verified = vault.run_json(&["verify", "--project", "issuer", "--explain", &token]);
+    assert_eq!(verified["data"]["valid"], true);
+    assert_eq!(
+        verified["data"]["explain"]["key_source"],
+        "vault-jwks:published"
+    );
+
+    let unknown = common::encode_token(&[
+        "encode", "--alg", "rs256", "--key", &priv_key, "--kid", "other",
+    ]);
+    vault.assert_exit(&["verify", "--project", "issuer", &unknown], 13);
+
+    let _ = vault.run_json(&["vault", "jwks", "delete", jwks_id]);
+    vault.assert_exit(&["verify", "--project", "issuer", &token], 13);
+}
+
+#[test]
+fn stored_jwks_refreshes_from_source_url() {
+    use std::io::{Read, Write};
+
+    let jwks = std::fs::read(fixture_path("jwks.json")).expect("jwks");
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind");
+    let url = format!("http://{}/jwks.json", listener.local_addr().unwrap());
+    // One fetch for --auto-refresh and one for `vault jwks refresh`.
+    let server = std::thread::spawn(move || {
+        for _ in 0..2 {
+            let (mut stream, _) = listener.accept().expect("accept");
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request).expect("read");
+            let head = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                jwks.len()
+            );
+            stream.write_all(head.as_bytes()).expect("write");
+            stream.write_all(&jwks).expect("write");
+        }
+    });
+
+    let vault = TestVault::new();
+    let _ = vault.run_json(&["vault", "project", "add", "issuer"]);
+    let _ = vault.run_json(&[
+        "vault",
+        "jwks",
+        "add",
+        "--project",
+        "issuer",
+        "--name",
+        "published",
+        "--source-url",
+        &url,
+        r#"{"keys":[{"kty":"oct","kid":"retired","k":"c2VjcmV0"}]}"#,
+    ]);
+
+    let token = common::encode_token(&[
+        "encode",
+        "--alg",
+        "rs256",
+        "--key",
+        &at_path(&fixture_path("rsa_private.pem")),
+        "--kid",
+        "rsa1",
+    ]);
+    vault.assert_exit(&["verify", "--project", "issuer", &token], 13);
+
+    let verified = vault.run_json(&[
+        "verify",
+        "--project",
+        "issuer",
+        "--auto-refresh",
+        "--explain",
+        &token,
+    ]);
+    assert_eq!(verified["data"]["valid"], true);
+    assert_eq!(
+        verified["data"]["explain"]["key_source"],
+        "vault-jwks:published"
+    );
+
+    // The refreshed document was stored, so plain verification works now.
+    let verified = vault.run_json(&["verify", "--project", "issuer", &token]);
+    assert_eq!(verified["data"]["valid"], true);
+
+    let refreshed = vault.run_json(&["vault", "jwks", "refresh", "--project", "issuer"]);
+    let result = &refreshed["data"]["results"][0];
+    assert_eq!(result["refreshed"], true);
+    assert_eq!(result["changed"], false);
+    server.join().expect("server");
+}
+
+#[test]
+fn verify_integrity_reports_missing_keychain_entries() {
+    let vault = TestVault::new();
+    let secret = fixture_path("hmac.key");
+    let rsa = fixture_path("rsa_private.pem");
+    vault.run_json(&["vault", "project", "add", "alpha"]);
+    for (name, kind, path) in [("hmac", "hmac", &secret), ("rsa", "rsa", &rsa)] {
+        vault.run_json(&[
+            "vault",
+            "key",
+            "add",
+            "--project",
+            "alpha",
+            "--name",
+            name,
+            "--kind",
+            kind,
+            "--secret",
+            &at_path(path),
+        ]);
+    }
+
+    let report = vault.run_json(&["vault", "verify-integrity"]);
+    assert_eq!(report["data"]["ok"], true);
+    assert_eq!(report["data"]["checked"]["keys"], 2);
+
+    vault.assert_exit(
+        &[
+            "vault",
+            "key",
+            "add",
+            "--project",
+            "alpha",
+            "--name",
+            "broken",
+            "--kind",
+            "ec",
+            "--secret",
+            &at_path(&secret),
+        ],
+        13,
+    );
+
+    for entry in std::fs::read_dir(vault.keychain_dir()).expect("keychain dir") {
+        std::fs::remove_file(entry.expect("entry").path()).expect("remove entry");
+    }
+    let output = vault
+        .cmd()
+        .args(["--json", "vault", "verify-integrity"])
+        .output()
+        .expect("run verify-integrity");
+    assert_eq!(output.status.code(), Some(13));
+    let body: serde_json::Value = serde_json::from_slice(&output.stdout).expect("json");
+    let problems = body["error"]["details"]["problems"].as_array().unwrap();
+    assert_eq!(problems.len(), 2);
+    assert!(problems.iter().all(|p| p["problem"] == "missing_secret"));
+}
+
+#[test]
+fn relocate_moves_database_and_file_keychain_then_verifies() {
+    let vault = TestVault::new();
+    vault.run_json(&["vault", "project", "add", "alpha"]);
+    vault.run_json(&[
+        "vault",
+        "key",
+        "generate",
+        "--project",
+        "alpha",
+        "--kind",
+        "hmac",
+        "--name",
+        "k",
+    ]);
+    let target = TempDir::new().expect("temp dir");
+    let to = target.path().join("moved");
+    let to_arg = to.to_str().unwrap();
+
+    let copied = vault.run_json(&["vault", "relocate", "--to", to_arg, "--keep-source"]);
+    assert_eq!(copied["data"]["relocated"]["source_removed"], false);
+    assert!(vault.data_dir().join("vault.sqlite3").exists());
+    vault.assert_exit(&["vault", "relocate", "--to", to_arg, "--yes"], 13);
+    std::fs::remove_dir_all(&to).unwrap();
+
+    let moved = vault.run_json(&["vault", "relocate", "--to", to_arg, "--yes"]);
+    let report = &moved["data"]["relocated"];
+    assert_eq!(report["source_removed"], true);
+    assert!(report["keychain_dir"]
+        .as_str()
+        .unwrap()
+        .ends_with("keychain"));
+    assert!(!vault.data_dir().join("vault.sqlite3").exists());
+    assert!(!vault.keychain_dir().exists());
+
+    let output = vault
+        .cmd_at(&to)
+        .args(["--json", "vault", "verify-integrity"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    let keys: serde_json::Value = serde_json::from_slice(
+        &vault
+            .cmd_at(&to)
+            .args(["--json", "vault", "key", "list", "--project", "alpha"])
+            .output()
+            .unwrap()
+            .stdout,
+    )
+    .unwrap();
+    assert_eq!(keys["data"]["keys"][0]["name"], "k");
+}
+
+#[test]
+fn bench_keychain_reports_percentiles_and_leaves_no_entries() {
+    let vault = TestVault::new();
+    let report = vault.run_json(&["vault", "bench-keychain", "--iterations", "3"]);
+    let bench = &report["data"]["bench"];
+    assert_eq!(bench["backend"], "file");
+    assert_eq!(bench["iterations"], 3);
+    for op in ["set", "get", "delete"] {
+        let p50 = bench[op]["p50_ms"].as_f64().unwrap();
+        assert!(p50 <= bench[op]["max_ms"].as_f64().unwrap(), "{bench}");
+    }
+    let left = std::fs::read_dir(vault.keychain_dir()).unwrap().count();
+    assert_eq!(left, 0);
+
+    vault.assert_exit(&["vault", "bench-keychain", "--iterations", "0"], 13);
+}
+
+#[test]
+fn stats_counts_entries_per_project_and_sizes_storage() {
+    let vault = TestVault::new();
+    vault.run_json(&["vault", "project", "add", "alpha"]);
+    vault.run_json(&["vault", "project", "add", "empty"]);
+    for kind in ["hmac", "hmac", "ec"] {
+        vault.run_json(&[
+            "vault",
+            "key",
+            "generate",
+            "--project",
+            "alpha",
+            "--kind",
+            kind,
+        ]);
+    }
+    vault.run_json(&[
+        "vault",
+        "token",
+        "add",
+        "--project",
+        "alpha",
+        "--name",
+        "t",
+        "--token",
+        "a.b.c",
+    ]);
+
+    let stats = vault.run_json(&["vault", "stats"])["data"]["stats"].clone();
+    let alpha = &stats["projects"][0];
+    assert_eq!(alpha["name"], "alpha");
+    assert_eq!(alpha["keys"], 3);
+    assert_eq!(alpha["keys_by_kind"]["hmac"], 2);
+    assert_eq!(alpha["tokens"], 1);
+    assert!(alpha["oldest"].as_i64() <= alpha["newest"].as_i64());
+    assert_eq!(stats["projects"][1]["keys"], 0);
+    assert!(stats["projects"][1]["oldest"].is_null());
+    assert_eq!(stats["totals"]["projects"], 2);
+    assert_eq!(stats["totals"]["keys_by_kind"]["ec"], 1);
+    let storage = &stats["storage"];
+    assert_eq!(storage["keychain_backend"], "file");
+    assert!(storage["db_bytes"].as_u64().unwrap() > 0);
+    assert!(storage["keychain_bytes"].as_u64().unwrap() > 0);
+
+    let output = vault.cmd().args(["vault", "stats"]).output().unwrap();
+    let text = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        text.contains("total: 2 project(s), 3 key(s) (ec=1, hmac=2)"),
+        "{text}"
+    );
+}
+
+#[test]
+fn key_add_validates_material_against_kind() {
+    let vault = TestVault::new();
+    vault.run_json(&["vault", "project", "add", "alpha"]);
+    let rsa_public = at_path(&fixture_path("rsa_public.pem"));
+    let rsa_private = at_path(&fixture_path("rsa_private.pem"));
+    let ec_private = at_path(&fixture_path("ec256_private.pem"));
+    let hmac = at_path(&fixture_path("hmac.key"));
+    let add = ["vault", "key", "add", "--project", "alpha"];
+
+    vault.assert_exit(
+        &[&add[..], &["--kind", "rsa", "--secret", &rsa_public]].concat(),
+        13,
+    );
+    vault.assert_exit(
+        &[&add[..], &["--kind", "rsa", "--secret", &ec_private]].concat(),
+        13,
+    );
+    vault.assert_exit(
+        &[&add[..], &["--secret", &hmac, "--min-hmac-bytes", "4096"]].concat(),
+        13,
+    );
+
+    let public = vault.run_json(
+        &[
+            &add[..],
+            &["--kind", "rsa", "--secret", &rsa_public, "--allow-public"],
+        ]
+        .concat(),
+    );
+    let private =
+        vault.run_json(&[&add[..], &["--kind", "rsa", "--secret", &rsa_private]].concat());
+    let fingerprint = private["data"]["key"]["fingerprint"]
+        .as_str()
+        .expect("fingerprint");
+    assert!(fingerprint.starts_with("sha256:"));
+    assert_eq!(public["data"]["key"]["fingerprint"], fingerprint);
+
+    let hmac = vault.run_json(&[&add[..], &["--secret", &hmac]].concat());
+    assert!(hmac["data"]["key"].get("fingerprint").is_none());
+}
+
+#[test]
+fn encode_and_verify_select_key_by_fingerprint() {
+    let vault = TestVault::new();
+    vault.run_json(&["vault", "project", "add", "alpha"]);
+    let mut fingerprints = Vec::new();
+    for (kind, pem) in [
+        ("eddsa", "ed25519_private.pem"),
+        ("ec", "ec256_private.pem"),
+    ] {
+        let added = vault.run_json(&[
+            "vault",
+            "key",
+            "add",
+            "--project",
+            "alpha",
+            "--name",
+            kind,
+            "--kind",
+            kind,
+            "--secret",
+            &at_path(&fixture_path(pem)),
+        ]);
+        fingerprints.push(
+            added["data"]["key"]["fingerprint"]
+                .as_str()
+                .unwrap()
+                .to_string(),
+        );
+    }
+    assert_ne!(fingerprints[0], fingerprints[1]);
+
+    let listed = vault.run_json(&["vault", "key", "list", "--project", "alpha"]);
+    let listed: Vec<_> = listed["data"]["keys"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|k| k["fingerprint"].as_str().unwrap().to_string())
+        .collect();
+    assert!(listed.contains(&fingerprints[1]));
+
+    let token = vault.run_json(&[
+        "encode",
+        "--project",
+        "alpha",
+        "--alg",
+        "es256",
+        "--key-fingerprint",
+        &fingerprints[1],
+    ]);
+    let token = token["data"]["token"].as_str().unwrap().to_string();
+
+    let short = &fingerprints[1]["sha256:".len().."sha256:".len() + 12];
+    let verified = vault.run_json(&[
+        "verify",
+        "--project",
+        "alpha",
+        "--alg",
+        "es256",
+        "--key-fingerprint",
+        short,
+        &token,
+    ]);
+    assert_eq!(verified["data"]["valid"], true);
+    vault.assert_exit(
+        &[
+            "verify",
+            "--project",
+            "alpha",
+            "--alg",
+            "es256",
+            "--key-fingerprint",
+            &fingerprints[0],
+            &token,
+        ],
+        13,
+    );
+}
+
+#[test]
+fn keys_import_from_env_and_dotenv() {
+    let vault = TestVault::new();
+    vault.run_json(&["vault", "project", "add", "alpha"]);
+
+    let output = vault
+        .cmd()
+        .env("SERVICE_JWT_SECRET", "env-secret-value")
+        .args([
+            "--json",
+            "vault",
+            "key",
+            "add",
+            "--project",
+            "alpha",
+            "--from-env",
+            "SERVICE_JWT_SECRET",
+        ])
+        .output()
+        .expect("run key add");
+    assert!(output.status.success());
+    let added: serde_json::Value = serde_json::from_slice(&output.stdout).expect("json");
+    assert_eq!(added["data"]["key"]["name"], "SERVICE_JWT_SECRET");
+
+    let dir = TempDir::new().expect("temp dir");
+    let dotenv = dir.path().join(".env");
+    std::fs::write(
+        &dotenv,
+        "BILLING_JWT_SECRET=billing-secret\n\
+         export ORDERS_JWT_SECRET=\"orders-secret\"\n\
+         SERVICE_JWT_SECRET=duplicate\n\
+         EMPTY_JWT_SECRET=\n\
+         DATABASE_URL=postgres://localhost\n",
+    )
+    .expect("write .env");
+    let dotenv = dotenv.to_string_lossy().to_string();
+    let imported = vault.run_json(&[
+        "vault",
+        "key",
+        "import",
+        "--project",
+        "alpha",
+        "--dotenv",
+        &dotenv,
+        "--pattern",
+        "*_JWT_SECRET",
+    ]);
+    let names: Vec<&str> = imported["data"]["imported"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|k| k["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["BILLING_JWT_SECRET", "ORDERS_JWT_SECRET"]);
+    let skipped = imported["data"]["skipped"].as_array().unwrap();
+    assert_eq!(skipped.len(), 2);
+    assert_eq!(skipped[0]["name"], "SERVICE_JWT_SECRET");
+
+    vault.assert_exit(
+        &[
+            "vault",
+            "key",
+            "import",
+            "--project",
+            "alpha",
+            "--dotenv",
+            &dotenv,
+            "--pattern",
+            "NOPE_*",
+        ],
+        13,
+    );
+}
+
+#[test]
+fn project_policy_requires_headers_and_issuers_per_family() {
+    let vault = TestVault::new();
+    let secret = fixture_path("hmac.key");
+    vault.run_json(&["vault", "project", "add", "alpha"]);
+    vault.run_json(&[
+        "vault",
+        "key",
+        "add",
+        "--project",
+        "alpha",
+        "--kid",
+        "k1",
+        "--secret",
+        &at_path(&secret),
+    ]);
+    let set = vault.run_json(&[
+        "vault",
+        "project",
+        "set-policy",
+        "--project",
+        "alpha",
+        "--family",
+        "hs",
+        "--require-header",
+        "kid",
+        "--allow-issuer",
+        "https://idp.internal",
+    ]);
+    assert_eq!(set["data"]["family"], "HS");
+    assert_eq!(set["data"]["policy"]["required_headers"][0], "kid");
+
+    let encode = |extra: &[&str]| {
+        let mut args = vec![
+            "encode",
+            "--project",
+            "alpha",
+            "--alg",
+            "hs256",
+            "--exp",
+            "+1h",
+        ];
+        args.extend_from_slice(extra);
+        vault.run_json(&args)["data"]["token"]
+            .as_str()
+            .expect("token")
+            .to_string()
+    };
+    let bare = encode(&["--iss", "https://idp.internal"]);
+    let output = vault
+        .cmd()
+        .args(["--json", "verify", "--project", "alpha", &bare])
+        .output()
+        .expect("run verify");
+    assert_eq!(output.status.code(), Some(12));
+    let err: serde_json::Value = serde_json::from_slice(&output.stdout).expect("json");
+    assert_eq!(
+        err["error"]["details"]["violations"],
+        serde_json::json!(["missing header kid"])
+    );
+
+    let foreign = encode(&["--kid", "k1", "--iss", "https://elsewhere"]);
+    vault.assert_exit(&["verify", "--project", "alpha", &foreign], 12);
+
+    let good = encode(&["--kid", "k1", "--iss", "https://idp.internal"]);
+    let verified = vault.run_json(&["verify", "--project", "alpha", &good]);
+    assert_eq!(verified["data"]["valid"], true);
+
+    let shown = vault.run_json(&["vault", "project", "show", "alpha"]);
+    assert_eq!(
+        shown["data"]["project"]["policy"]["HS"]["allowed_issuers"][0],
+        "https://idp.internal"
+    );
+
+    vault.run_json(&[
+        "vault",
+        "project",
+        "set-policy",
+        "--project",
+        "alpha",
+        "--family",
+        "hs",
+        "--clear",
+    ]);
+    vault.run_json(&["verify", "--project", "alpha", &bare]);
+}
+
+/// A module with fixed answers: the claims below, `[]` from a passing and one violation from a
+/// failing `jwt_validate`.
+#[cfg(feature = "wasm")]
+fn write_wasm_module(dir: &TempDir, name: &str, validate_result: i64) -> String {
+    let path = dir.path().join(name);
+    let wat = format!(
+        r#"(module
+            (memory (export "memory") 1)
+            (data (i32.const 0) "{{\"sub\":\"svc\",\"tenant\":\"acme\",\"exp\":4102444800}}")
+            (data (i32.const 128) "[\"tenant acme is suspended\"]")
+            (data (i32.const 192) "[]")
+            (func (export "alloc") (param i32) (result i32) i32.const 1024)
+            (func (export "jwt_transform") (param i32 i32) (result i64) i64.const 46)
+            (func (export "jwt_validate") (param i32 i32) (result i64) i64.const {validate_result}))"#
+    );
+    std::fs::write(&path, wat).expect("write module");
+    path.to_string_lossy().into_owned()
+}
+
+#[cfg(feature = "wasm")]
+#[test]
+fn project_wasm_module_transforms_on_encode_and_validates_on_verify() {
+    let vault = TestVault::new();
+    let dir = TempDir::new().expect("temp dir");
+    let secret = at_path(&fixture_path("hmac.key"));
+    let passing = write_wasm_module(&dir, "pass.wat", (192 << 32) | 2);
+    let failing = write_wasm_module(&dir, "fail.wat", (128 << 32) | 28);
+    for project in ["alpha", "beta"] {
+        vault.run_json(&["vault", "project", "add", project]);
+    }
+    vault.run_json(&[
+        "vault",
+        "key",
+        "add",
+        "--project",
+        "alpha",
+        "--secret",
+        &secret,
+    ]);
+    let set = vault.run_json(&[
+        "vault",
+        "project",
+        "set-wasm-module",
+        "--project",
+        "alpha",
+        "--path",
+        &passing,
+    ]);
+    assert_eq!(
+        set["data"]["hooks"],
+        serde_json::json!(["jwt_transform", "jwt_validate"])
+    );
+    vault.run_json(&[
+        "vault",
+        "project",
+        "set-wasm-module",
+        "--project",
+        "beta",
+        "--path",
+        &failing,
+    ]);
+
+    let encoded = vault.run_json(&[
+        "encode",
+        "--project",
+        "alpha",
+        "--alg",
+        "hs256",
+        r#"{"sub":"user"}"#,
+    ]);
+    let token = encoded["data"]["token"].as_str().expect("token");
+    let decoded = vault.run_json(&["decode", token]);
+    assert_eq!(decoded["data"]["payload"]["sub"], "svc");
+    assert_eq!(decoded["data"]["payload"]["tenant"], "acme");
+
+    let verified = vault.run_json(&["verify", "--project", "alpha", token]);
+    assert_eq!(verified["data"]["valid"], true);
+
+    let output = vault
+        .cmd()
+        .args([
+            "--json",
+            "verify",
+            "--secret",
+            &secret,
+            "--project",
+            "beta",
+            token,
+        ])
+        .output()
+        .expect("run verify");
+    assert_eq!(output.status.code(), Some(12));
+    let err: serde_json::Value = serde_json::from_slice(&output.stdout).expect("json");
+    assert_eq!(err["error"]["details"]["reason"], "wasm_module");
+    assert_eq!(
+        err["error"]["details"]["violations"],
+        serde_json::json!(["tenant acme is suspended"])
+    );
+
+    vault.run_json(&[
+        "vault",
+        "project",
+        "set-wasm-module",
+        "--project",
+        "beta",
+        "--clear",
+    ]);
+    vault.run_json(&["verify", "--secret", &secret, "--project", "beta", token]);
+}
+
+#[cfg(not(feature = "wasm"))]
+#[test]
+fn project_wasm_module_needs_the_wasm_feature() {
+    let vault = TestVault::new();
+    let dir = TempDir::new().expect("temp dir");
+    let module = dir.path().join("plugin.wasm");
+    std::fs::write(&module, b"\0asm\x01\0\0\0").expect("write module");
+    vault.run_json(&["vault", "project", "add", "alpha"]);
+    let output = vault
+        .cmd()
+        .args([
+            "--json",
+            "vault",
+            "project",
+            "set-wasm-module",
+            "--project",
+            "alpha",
+            "--path",
+            module.to_str().expect("utf-8 path"),
+        ])
+        .output()
+        .expect("run vault");
+    assert_eq!(output.status.code(), Some(13));
+    let err: serde_json::Value = serde_json::from_slice(&output.stdout).expect("json");
+    let message = err["error"]["message"].as_str().expect("message");
+    assert!(message.contains("`wasm` feature"), "{message}");
+    let shown = vault.run_json(&["vault", "project", "show", "alpha"]);
+    assert!(shown["data"]["project"].get("wasm_module").is_none());
+}
+
+#[test]
+fn vault_webhooks_receive_signed_key_events() {
+    use std::io::{BufRead, BufReader, Read, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind");
+    let url = format!("http://{}/inventory", listener.local_addr().unwrap());
+    // key.added and key.rotated; the token is added last and is not subscribed to.
+    let server = std::thread::spawn(move || {
+        let mut received = Vec::new();
+        for _ in 0..2 {
+            let (stream, _) = listener.accept().expect("accept");
+            let mut reader = BufReader::new(stream);
+            let mut headers = Vec::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).expect("read header");
+                if line.trim().is_empty() {
+                    break;
+                }
+                headers.push(line.trim().to_ascii_lowercase());
+            }
+            let length: usize = headers
+                .iter()
+                .find_map(|h| h.strip_prefix("content-length: "))
+                .and_then(|v| v.parse().ok())
+                .expect("content length");
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).expect("read body");
+            reader
+                .get_mut()
+                .write_all(b"HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n")
+                .expect("write");
+            let body: serde_json::Value = serde_json::from_slice(&body).expect("json body");
+            received.push((headers, body));
+        }
+        received
+    });
+
+    let vault = TestVault::new();
+    let _ = vault.run_json(&["vault", "project", "add", "alpha"]);
+    let added = vault.run_json(&[
+        "vault",
+        "webhook",
+        "add",
+        "--project",
+        "alpha",
+        "--url",
+        &url,
+        "--event",
+        "key.added",
+        "--event",
+        "key.rotated",
+    ]);
+    let secret = added["data"]["secret"].as_str().expect("generated secret");
+    assert_eq!(secret.len(), 64);
+    let listed = vault.run_json(&["vault", "webhook", "list", "--project", "alpha"]);
+    assert_eq!(listed["data"]["webhooks"][0]["url"], url.as_str());
+    assert!(listed["data"]["webhooks"][0].get("secret").is_none());
+
+    let key = vault.run_json(&[
+        "vault",
+        "key",
+        "add",
+        "--project",
+        "alpha",
+        "--name",
+        "primary",
+        "--kind",
+        "hmac",
+        "--secret",
+        &at_path(&fixture_path("hmac.key")),
+    ]);
+    let _ = vault.run_json(&[
+        "vault",
+        "project",
+        "set-default-key",
+        "--project",
+        "alpha",
+        "--key-name",
+        "primary",
+    ]);
+    let _ = vault.run_json(&[
+        "vault",
+        "token",
+        "add",
+        "--project",
+        "alpha",
+        "--name",
+        "t",
+        "--token",
+        "a.b.c",
+    ]);
+
+    let received = server.join().expect("server");
+    let key_id = key["data"]["key"]["id"].as_str().unwrap();
+    for ((headers, body), event) in received.iter().zip(["key.added", "key.rotated"]) {
+        assert!(headers.contains(&format!("x-jwt-tester-event: {event}")));
+        let signature = headers
+            .iter()
+            .find_map(|h| h.strip_prefix("x-jwt-tester-signature: sha256="))
+            .expect("signature header");
+        assert_eq!(signature.len(), 64);
+        assert_eq!(body["event"], event);
+        assert_eq!(body["project"]["name"], "alpha");
+        assert_eq!(body["key"]["id"], key_id);
+        assert!(!body.to_string().contains(secret));
+    }
+    assert_eq!(received[1].1["previous_key_id"], serde_json::Value::Null);
+
+    let hook_id = listed["data"]["webhooks"][0]["id"].as_str().unwrap();
+    let _ = vault.run_json(&["vault", "webhook", "delete", hook_id]);
+    vault.assert_exit(&["vault", "webhook", "delete", hook_id], 13);
+}
+
+#[test]
+fn revoked_tokens_fail_verify_with_check_revocation() {
+    let vault = TestVault::new();
+    let _ = vault.run_json(&["vault", "project", "add", "alpha"]);
+    let _ = vault.run_json(&[
+        "vault",
+        "key",
+        "add",
+        "--project",
+        "alpha",
+        "--name",
+        "primary",
+        "--kind",
+        "hmac",
+        "--secret",
+        &at_path(&fixture_path("hmac.key")),
+    ]);
+    let encode = |claims: &str| {
+        let out = vault.run_json(&[
+            "encode",
+            "--project",
+            "alpha",
+            "--alg",
+            "hs256",
+            "--exp",
+            "+1h",
+            claims,
+        ]);
+        out["data"]["token"].as_str().unwrap().to_string()
+    };
+    let stored = encode(r#"{"sub":"alice"}"#);
+    let by_jti = encode(r#"{"sub":"bob","jti":"session-2"}"#);
+    let active = encode(r#"{"sub":"carol","jti":"session-3"}"#);
+
+    let added = vault.run_json(&[
+        "vault",
+        "token",
+        "add",
+        "--project",
+        "alpha",
+        "--name",
+        "alice",
+        "--token",
+        &stored,
+    ]);
+    let token_id = added["data"]["token"]["id"].as_str().unwrap();
+    let revoked = vault.run_json(&["vault", "token", "revoke", token_id]);
+    assert_eq!(revoked["data"]["revocation"]["token_id"], token_id);
+    assert_eq!(
+        revoked["data"]["revocation"]["token_hash"]
+            .as_str()
+            .unwrap()
+            .len(),
+        64
+    );
+    let _ = vault.run_json(&[
+        "vault",
+        "token",
+        "revoke",
+        "--project",
+        "alpha",
+        "session-2",
+    ]);
+    let again = vault.run_json(&[
+        "vault",
+        "token",
+        "revoke",
+        "--project",
+        "alpha",
+        "session-2",
+    ]);
+    let listed = vault.run_json(&["vault", "token", "revoked", "--project", "alpha"]);
+    assert_eq!(listed["data"]["revocations"].as_array().unwrap().len(), 2);
+    assert_eq!(
+        listed["data"]["revocations"][1]["id"],
+        again["data"]["revocation"]["id"]
+    );
+
+    let verify = ["verify", "--project", "alpha", "--check-revocation"];
+    for (token, matched) in [(&stored, "token_hash"), (&by_jti, "jti")] {
+        let output = vault
+            .cmd()
+            .arg("--json")
+            .args(verify)
+            .arg(token)
+            .output()
+            .expect("run verify");
+        assert_eq!(output.status.code(), Some(16));
+        let body: serde_json::Value = serde_json::from_slice(&output.stdout).expect("json");
+        assert_eq!(body["error"]["code"], "TOKEN_REVOKED");
+        assert_eq!(body["error"]["details"]["matched"], matched);
+    }
+    let ok = vault.run_json(&[&verify[..], &[active.as_str()]].concat());
+    assert_eq!(ok["data"]["valid"], true);
+    // Without the flag the list is not consulted.
+    let ok = vault.run_json(&["verify", "--project", "alpha", &stored]);
+    assert_eq!(ok["data"]["valid"], true);
+
+    vault.assert_exit(&["vault", "token", "revoke", "not-a-token-id"], 13);
+    vault.assert_exit(
+        &["verify", "--check-revocation", "--secret", "x", &active],
+        2,
+    );
+}
+
+#[test]
+fn key_usage_keeps_verify_only_keys_out_of_encode() {
+    let vault = TestVault::new();
+    vault.run_json(&["vault", "project", "add", "alpha"]);
+    let add = ["vault", "key", "add", "--project", "alpha", "--kind", "rsa"];
+    let public = vault.run_json(
+        &[
+            &add[..],
+            &[
+                "--name",
+                "prod-public",
+                "--secret",
+                &at_path(&fixture_path("rsa_public.pem")),
+                "--allow-public",
+            ],
+        ]
+        .concat(),
+    );
+    assert_eq!(public["data"]["key"]["usage"], "verify");
+    vault.run_json(
+        &[
+            &add[..],
+            &[
+                "--name",
+                "signer",
+                "--secret",
+                &at_path(&fixture_path("rsa_private.pem")),
+            ],
+        ]
+        .concat(),
+    );
+
+    // The public key is skipped, so the only key that may sign is picked.
+    let encode = ["encode", "--alg", "rs256", "--project", "alpha"];
+    let token = vault.run_json(&encode)["data"]["token"]
+        .as_str()
+        .expect("token")
+        .to_string();
+    vault.run_json(&[
+        "verify",
+        "--alg",
+        "rs256",
+        "--project",
+        "alpha",
+        "--key-name",
+        "prod-public",
+        &token,
+    ]);
+    vault.assert_exit(&[&encode[..], &["--key-name", "prod-public"]].concat(), 13);
+
+    let set_usage = [
+        "vault",
+        "key",
+        "set-usage",
+        "--project",
+        "alpha",
+        "--key-name",
+    ];
+    vault.assert_exit(&[&set_usage[..], &["prod-public", "both"]].concat(), 13);
+    vault.run_json(&[&set_usage[..], &["signer", "verify"]].concat());
+    vault.assert_exit(&encode, 13);
+}
+
+#[test]
+fn platform_keys_store_a_sign_only_keystore_reference() {
+    let vault = TestVault::new();
+    vault.run_json(&["vault", "project", "add", "alpha"]);
+    let add = [
+        "vault",
+        "key",
+        "add",
+        "--project",
+        "alpha",
+        "--kind",
+        "platform",
+    ];
+
+    vault.assert_exit(&[&add[..], &["--secret", "ssh-agent"]].concat(), 13);
+    vault.assert_exit(
+        &[&add[..], &["--secret", "cng:jwt-dev", "--usage", "both"]].concat(),
+        13,
+    );
+    let key = vault.run_json(&[&add[..], &["--secret", "cng:jwt-dev"]].concat());
+    assert_eq!(key["data"]["key"]["kind"], "platform");
+    assert_eq!(key["data"]["key"]["usage"], "sign");
+    let key_id = key["data"]["key"]["id"].as_str().expect("key id");
+    vault.assert_exit(
+        &[
+            "vault",
+            "key",
+            "set-usage",
+            "--project",
+            "alpha",
+            "--key-id",
+            key_id,
+            "both",
+        ],
+        13,
+    );
+
+    // Secure Enclave keys are P-256, so an RSA key is refused before touching any keystore, and
+    // a failed creation stores nothing.
+    vault.assert_exit(
+        &[
+            "vault",
+            "key",
+            "create-platform",
+            "--project",
+            "alpha",
+            "--ref",
+            "secure-enclave:jwt-dev",
+            "--alg",
+            "rs256",
+        ],
+        13,
+    );
+    let keys = vault.run_json(&["vault", "key", "list", "--project", "alpha"]);
+    assert_eq!(keys["data"]["keys"].as_array().expect("keys").len(), 1);
+}
@@ -112,7 +112,7 @@ fn vault_export_import_roundtrip() {
     let verified = imported.run_json(&["verify", "--project", "alpha", "--alg", "hs256", &token]);
     assert_eq!(verified["data"]["valid"], true);
 
-    let _ = imported.run_json(&["vault", "project", "delete", &project_id]);
+    let _ = imported.run_json(&["vault", "project", "delete", "--yes", &project_id]);
 }
 
 #[test]
@@ -180,5 +180,168 @@ fn vault_import_rejects_wrong_passphrase_and_non_empty() {
         "--passphrase",
         "passphrase",
         "--replace",
+        "--yes",
     ]);
 }
+
+#[test]
+fn vault_export_single_project() {
+    let vault = TestVault::new();
+    let secret = fixture_path("hmac.key");
+
+    for project in ["alpha", "beta"] {
+        let _ = vault.run_json(&["vault", "project", "add", project]);
+        let _ = vault.run_json(&[
+            "vault",
+            "key",
+            "add",
+            "--project",
+            project,
+            "--name",
+            "primary",
+            "--kind",
+            "hmac",
+            "--secret",
+            &at_path(&secret),
+        ]);
+    }
+
+    let dir = tempfile::TempDir::new().expect("temp dir");
+    let out_path = dir.path().join("beta.json");
+    let _ = vault.run_json(&[
+        "vault",
+        "export",
+        "--project",
+        "beta",
+        "--passphrase",
+        "passphrase",
+        "--out",
+        out_path.to_str().unwrap(),
+    ]);
+
+    let listing = vault.run_json(&[
+        "vault",
+        "import",
+        "--bundle",
+        &at_path(&out_path),
+        "--passphrase",
+        "passphrase",
+        "--list",
+    ]);
+    assert_eq!(listing["data"]["manifest"]["counts"]["projects"], 1);
+    assert_eq!(listing["data"]["manifest"]["counts"]["keys"], 1);
+
+    vault.assert_exit(
+        &[
+            "vault",
+            "export",
+            "--project",
+            "missing",
+            "--passphrase",
+            "passphrase",
+        ],
+        13,
+    );
+}
+
+#[test]
+fn vault_seed_loads_bundle_into_in_memory_vault() {
+    let vault = TestVault::new();
+    vault.run_json(&["vault", "project", "add", "ci"]);
+    vault.run_json(&[
+        "vault",
+        "key",
+        "generate",
+        "--project",
+        "ci",
+        "--kind",
+        "hmac",
+        "--name",
+        "signing",
+    ]);
+    let dir = tempfile::TempDir::new().expect("temp dir");
+    let bundle = dir.path().join("seed.json");
+    vault.run_json(&[
+        "vault",
+        "export",
+        "--passphrase",
+        "seed-pass",
+        "--out",
+        bundle.to_str().unwrap(),
+    ]);
+
+    let data_dir = tempfile::TempDir::new().expect("temp dir");
+    let seeded = |args: &[&str]| {
+        let output = assert_cmd::cargo::cargo_bin_cmd!()
+            .env("SEED_PASS", "seed-pass")
+            .args(["--json", "--data-dir", data_dir.path().to_str().unwrap()])
+            .args(["--vault-seed", &at_path(&bundle)])
+            .args(["--vault-seed-passphrase", "env:SEED_PASS"])
+            .args(args)
+            .output()
+            .expect("run jwt-tester");
+        assert!(output.status.success(), "{output:?}");
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).expect("json")
+    };
+    let encoded = seeded(&[
+        "encode",
+        "--alg",
+        "hs256",
+        "--project",
+        "ci",
+        r#"{"sub":"ci"}"#,
+    ]);
+    let token = encoded["data"]["token"].as_str().unwrap();
+    let verified = seeded(&["verify", "--project", "ci", token]);
+    assert_eq!(verified["data"]["claims"]["sub"], "ci");
+    assert_eq!(
+        std::fs::read_dir(data_dir.path()).unwrap().count(),
+        0,
+        "the seeded vault never touches the data dir"
+    );
+
+    common::assert_exit(
+        &[
+            "--vault-seed",
+            &at_path(&bundle),
+            "--vault-seed-passphrase",
+            "wrong",
+            "vault",
+            "project",
+            "list",
+        ],
+        13,
+    );
+}
+
+#[test]
+fn non_exportable_projects_keep_keys_out_of_exports_and_reveals() {
+    let vault = TestVault::new();
+    vault.run_json(&["vault", "project", "add", "lab", "--non-exportable"]);
+    let generate = ["vault", "key", "generate", "--project", "lab", "--reveal"];
+    vault.assert_exit(&generate, 13);
+    let generated = vault.run_json(&[&generate[..], &["--include-non-exportable"]].concat());
+    assert!(generated["data"]["material"].is_string());
+
+    let export = vault.run_json(&["vault", "export", "--passphrase", "pw"]);
+    let skipped = export["data"]["skipped_keys"].as_array().expect("skipped");
+    assert_eq!(skipped.len(), 1);
+    assert_eq!(skipped[0]["id"], generated["data"]["key"]["id"]);
+
+    vault.run_json(&[
+        "vault",
+        "project",
+        "set-exportable",
+        "--project",
+        "lab",
+        "--exportable",
+        "true",
+    ]);
+    let export = vault.run_json(&["vault", "export", "--passphrase", "pw"]);
+    assert_eq!(export["data"]["skipped_keys"], serde_json::json!([]));
+
+    let audit = vault.run_json(&["vault", "audit"]);
+    let entries = audit["data"]["entries"].as_array().expect("entries");
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0]["action"], "reveal.non_exportable");
+}
@@ -1,5 +1,5 @@
 mod common;
-use common::{at_path, encode_token, fixture_path, run_json};
+use common::{assert_exit, at_path, encode_token, fixture_path, run_json, TestVault};
 
 #[test]
 fn verify_explain_includes_inferred_flag() {
@@ -20,3 +20,304 @@ fn verify_explain_includes_inferred_flag() {
         .as_bool()
         .unwrap_or(false));
 }
+
+#[test]
+fn verify_reports_every_claim_violation_and_explain_lists_checks() {
+    let secret = at_path(&fixture_path("hmac.key"));
+    let token = encode_token(&[
+        "encode",
+        "--alg",
+        "hs256",
+        "--secret",
+        &secret,
+        "--exp=-1h",
+        "--sub",
+        "user",
+        "--aud",
+        "api",
+    ]);
+    let verify = [
+        "verify",
+        "--secret",
+        &secret,
+        "--sub",
+        "user",
+        "--aud",
+        "web",
+        "--require",
+        "role",
+    ];
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!()
+        .arg("--json")
+        .args(verify)
+        .arg(&token)
+        .output()
+        .expect("verify");
+    assert_eq!(output.status.code(), Some(12));
+    let err: serde_json::Value = serde_json::from_slice(&output.stdout).expect("json");
+    let details = &err["error"]["details"];
+    let failed: Vec<&str> = details["violations"]
+        .as_array()
+        .expect("violations")
+        .iter()
+        .filter_map(|v| v["claim"].as_str())
+        .collect();
+    assert_eq!(failed, ["exp", "aud", "role"]);
+    assert_eq!(details["reason"], "expired");
+    assert!(details.get("checks").is_none());
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!()
+        .args(verify)
+        .args(["--explain", &token])
+        .env_remove("CLICOLOR_FORCE")
+        .output()
+        .expect("verify --explain");
+    assert_eq!(output.status.code(), Some(12));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("  FAIL exp: expired"), "{stderr}");
+    assert!(stderr.contains("  PASS sub: is \"user\""), "{stderr}");
+    assert!(
+        stderr.contains("  FAIL aud: is \"api\", expected \"web\""),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains("  FAIL role: missing required claim: role"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn verify_continue_on_claim_failure_prints_claims_with_violations() {
+    let secret = at_path(&fixture_path("hmac.key"));
+    let token = encode_token(&[
+        "encode",
+        "--alg",
+        "hs256",
+        "--secret",
+        &secret,
+        "--exp=-1h",
+        "--sub",
+        "user",
+        "--aud",
+        "api",
+    ]);
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!()
+        .args(["--json", "verify", "--secret", &secret, "--aud", "web"])
+        .args(["--continue-on-claim-failure", &token])
+        .output()
+        .expect("verify");
+    assert_eq!(output.status.code(), Some(12));
+    let out: serde_json::Value = serde_json::from_slice(&output.stdout).expect("json");
+    let data = &out["data"];
+    assert_eq!(data["valid"], false);
+    assert_eq!(data["signature_valid"], true);
+    assert_eq!(data["claims"]["sub"], "user");
+    let failed: Vec<&str> = data["violations"]
+        .as_array()
+        .expect("violations")
+        .iter()
+        .filter_map(|v| v["claim"].as_str())
+        .collect();
+    assert_eq!(failed, ["exp", "aud"]);
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!()
+        .args([
+            "verify",
+            "--secret",
+            &secret,
+            "--continue-on-claim-failure",
+            &token,
+        ])
+        .env_remove("CLICOLOR_FORCE")
+        .output()
+        .expect("verify text");
+    assert_eq!(output.status.code(), Some(12));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.starts_with("INVALID (signature OK; claims failed)"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("  FAIL exp: expired"), "{stdout}");
+    assert!(stdout.contains("\"sub\": \"user\""), "{stdout}");
+
+    // A bad signature is still an error; there are no trustworthy claims to show.
+    assert_cmd::cargo::cargo_bin_cmd!()
+        .args([
+            "verify",
+            "--secret",
+            "other-secret",
+            "--continue-on-claim-failure",
+            &token,
+        ])
+        .assert()
+        .failure()
+        .code(11);
+}
+
+#[test]
+fn step_up_claims_are_encoded_and_checked_by_verify() {
+    let secret = at_path(&fixture_path("hmac.key"));
+    let encoded = run_json(&[
+        "encode",
+        "--alg",
+        "hs256",
+        "--secret",
+        &secret,
+        "--acr",
+        "phrh",
+        "--amr",
+        "pwd",
+        "--amr",
+        "hwk",
+        "--auth-time=-10m",
+    ]);
+    let token = encoded["data"]["token"].as_str().expect("token");
+    let decoded = run_json(&["decode", token]);
+    assert_eq!(decoded["data"]["payload"]["acr"], "phrh");
+    assert_eq!(
+        decoded["data"]["payload"]["amr"],
+        serde_json::json!(["pwd", "hwk"])
+    );
+
+    let out = run_json(&[
+        "verify",
+        "--secret",
+        &secret,
+        "--require-acr",
+        "phr",
+        "--require-acr",
+        "phrh",
+        "--max-auth-age",
+        "15m",
+        token,
+    ]);
+    assert_eq!(out["data"]["valid"], true);
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!()
+        .args(["--json", "verify", "--secret", &secret])
+        .args(["--require-acr", "phr", "--max-auth-age", "5m", token])
+        .output()
+        .expect("verify");
+    assert_eq!(output.status.code(), Some(12));
+    let err: serde_json::Value = serde_json::from_slice(&output.stdout).expect("json");
+    let reasons: Vec<&str> = err["error"]["details"]["violations"]
+        .as_array()
+        .expect("violations")
+        .iter()
+        .filter_map(|v| v["reason"].as_str())
+        .collect();
+    assert_eq!(reasons, ["mismatch", "auth_too_old"]);
+
+    assert_exit(
+        &[
+            "encode", "--alg", "hs256", "--secret", &secret, "--amr", "password",
+        ],
+        12,
+    );
+    assert_exit(
+        &[
+            "encode",
+            "--alg",
+            "hs256",
+            "--secret",
+            &secret,
+            "--auth-time=+1h",
+        ],
+        12,
+    );
+}
+
+#[test]
+fn verify_explain_lists_each_project_key_decision_on_failure() {
+    let vault = TestVault::new();
+    vault.run_json(&["vault", "project", "add", "alpha"]);
+    for (name, file) in [("primary", "hmac.key"), ("secondary", "hmac_alt.key")] {
+        vault.run_json(&[
+            "vault",
+            "key",
+            "add",
+            "--project",
+            "alpha",
+            "--name",
+            name,
+            "--kind",
+            "hmac",
+            "--secret",
+            &at_path(&fixture_path(file)),
+        ]);
+    }
+    vault.run_json(&[
+        "vault",
+        "project",
+        "set-default-key",
+        "--project",
+        "alpha",
+        "--key-name",
+        "primary",
+    ]);
+    let token = encode_token(&[
+        "encode",
+        "--alg",
+        "hs256",
+        "--secret",
+        &at_path(&fixture_path("hmac_alt.key")),
+        "--exp",
+        "+1h",
+    ]);
+    let verify = [
+        "verify",
+        "--project",
+        "alpha",
+        "--alg",
+        "hs256",
+        "--explain",
+    ];
+
+    let output = vault
+        .cmd()
+        .arg("--json")
+        .args(verify)
+        .arg(&token)
+        .output()
+        .expect("verify --explain");
+    assert_eq!(output.status.code(), Some(11));
+    let err: serde_json::Value = serde_json::from_slice(&output.stdout).expect("json");
+    let explain = &err["error"]["details"]["explain"];
+    let decisions: Vec<(&str, &str)> = explain["decisions"]
+        .as_array()
+        .expect("decisions")
+        .iter()
+        .map(|d| {
+            (
+                d["candidate"].as_str().unwrap_or_default(),
+                d["decision"].as_str().unwrap_or_default(),
+            )
+        })
+        .collect();
+    assert!(decisions.contains(&("primary", "used")), "{explain}");
+    assert!(decisions.contains(&("secondary", "skipped")), "{explain}");
+    let steps: Vec<&str> = explain["steps"]
+        .as_array()
+        .expect("steps")
+        .iter()
+        .filter_map(|s| s["step"].as_str())
+        .collect();
+    assert_eq!(steps, ["resolve_alg", "resolve_key", "verify"]);
+    assert_eq!(explain["steps"][2]["ok"], false);
+
+    let output = vault
+        .cmd()
+        .args(verify)
+        .arg(&token)
+        .env_remove("CLICOLOR_FORCE")
+        .output()
+        .expect("verify --explain");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("key decisions:"), "{stderr}");
+    assert!(
+        stderr.contains("skipped vault 'secondary': not the project default key"),
+        "{stderr}"
+    );
+}
@@ -0,0 +1,64 @@
+mod common;
+use common::{at_path, encode_token, fixture_path, run_json};
+use tempfile::TempDir;
+
+#[test]
+fn verify_script_reports_every_failed_rule() {
+    let secret = at_path(&fixture_path("hmac.key"));
+    let token = encode_token(&[
+        "encode",
+        "--alg",
+        "hs256",
+        "--secret",
+        &secret,
+        "--kid",
+        "k1",
+        "--exp",
+        "+2h",
+        r#"{"sub":"svc","scope":"read write"}"#,
+    ]);
+    let dir = TempDir::new().expect("temp dir");
+    let rules = dir.path().join("rules.rhai");
+    std::fs::write(
+        &rules,
+        r#"
+        rule(header.kid == "k1", "unexpected kid");
+        rule(claims.scope.split(" ").contains("admin"), "admin scope required");
+        rule(claims.exp - now <= 3600, "tokens may live one hour at most");
+        "#,
+    )
+    .expect("write rules");
+    let rules = rules.to_str().expect("utf-8 path");
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!()
+        .args([
+            "--json", "verify", "--secret", &secret, "--script", rules, &token,
+        ])
+        .output()
+        .expect("run verify");
+    assert_eq!(output.status.code(), Some(12));
+    let err: serde_json::Value = serde_json::from_slice(&output.stdout).expect("json");
+    assert_eq!(err["error"]["details"]["reason"], "script");
+    assert_eq!(err["error"]["details"]["rules"], 3);
+    assert_eq!(
+        err["error"]["details"]["violations"],
+        serde_json::json!(["admin scope required", "tokens may live one hour at most"])
+    );
+
+    let passing = dir.path().join("passing.rhai");
+    std::fs::write(
+        &passing,
+        r#"rule(claims.sub == "svc", "service tokens only");"#,
+    )
+    .expect("write rules");
+    let out = run_json(&[
+        "verify",
+        "--secret",
+        &secret,
+        "--script",
+        passing.to_str().expect("utf-8 path"),
+        &token,
+    ]);
+    assert_eq!(out["data"]["valid"], true);
+    assert_eq!(out["data"]["script"]["rules"], 1);
+}
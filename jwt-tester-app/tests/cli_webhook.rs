@@ -0,0 +1,72 @@
+mod common;
+
+use common::{at_path, encode_token, fixture_path, run_json};
+
+const BODY: &str = "{\"event\":\"paid\",\"amount\":42}";
+
+fn signed_header(hash: &str) -> String {
+    let key = fixture_path("ec256_private.pem");
+    let token = encode_token(&[
+        "encode",
+        "--alg",
+        "es256",
+        "--key",
+        &at_path(&key),
+        "--iat",
+        "--claim",
+        &format!("request_body_sha256={hash}"),
+    ]);
+    format!("Plaid-Verification: {token}")
+}
+
+fn body_hash() -> String {
+    use sha2::Digest;
+    hex::encode(sha2::Sha256::digest(BODY.as_bytes()))
+}
+
+#[test]
+fn webhook_verify_checks_body_hash_claim() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let body = dir.path().join("body.json");
+    std::fs::write(&body, BODY).expect("write body");
+    let public = fixture_path("ec256_public.pem");
+    let header = signed_header(&body_hash());
+
+    let out = run_json(&[
+        "webhook",
+        "verify",
+        "--key",
+        &at_path(&public),
+        "--body",
+        body.to_str().unwrap(),
+        "--signature",
+        &header,
+        "--max-age",
+        "5m",
+    ]);
+    assert_eq!(out["data"]["body_hash"]["encoding"], "hex");
+    assert_eq!(out["data"]["body_len"], BODY.len());
+
+    std::fs::write(&body, format!("{BODY}\n")).expect("write body");
+    let output = assert_cmd::cargo::cargo_bin_cmd!()
+        .args([
+            "--json",
+            "webhook",
+            "verify",
+            "--key",
+            &at_path(&public),
+            "--body",
+            body.to_str().unwrap(),
+            "--signature",
+            &header,
+        ])
+        .output()
+        .expect("run");
+    assert_eq!(output.status.code(), Some(12));
+    let err: serde_json::Value = serde_json::from_slice(&output.stdout).expect("json");
+    assert_eq!(err["error"]["details"]["expected"], body_hash());
+    assert!(err["error"]["details"]["hint"]
+        .as_str()
+        .unwrap()
+        .contains("trailing"));
+}
@@ -0,0 +1,83 @@
+mod common;
+
+use common::TestVault;
+use serde_json::Value;
+use std::path::Path;
+use tempfile::TempDir;
+
+fn run_in(vault: &TestVault, dir: &Path, args: &[&str]) -> (Option<i32>, Value) {
+    let output = vault
+        .cmd()
+        .current_dir(dir)
+        .arg("--json")
+        .args(args)
+        .output()
+        .expect("run jwt-tester");
+    let body = serde_json::from_slice(&output.stdout).unwrap_or(Value::Null);
+    (output.status.code(), body)
+}
+
+#[test]
+fn init_links_a_directory_tree_to_a_project() {
+    let vault = TestVault::new();
+    vault.run_json(&["vault", "project", "add", "demo"]);
+    vault.run_json(&[
+        "vault",
+        "key",
+        "generate",
+        "--project",
+        "demo",
+        "--kind",
+        "hmac",
+        "--name",
+        "signing",
+    ]);
+    let root = TempDir::new().expect("temp dir");
+    std::fs::write(root.path().join("claims.json"), r#"{"sub":"alice"}"#).unwrap();
+    let nested = root.path().join("services").join("api");
+    std::fs::create_dir_all(&nested).unwrap();
+
+    let (code, body) = run_in(
+        &vault,
+        root.path(),
+        &[
+            "init",
+            "--project",
+            "demo",
+            "--alg",
+            "HS256",
+            "--claims",
+            "@claims.json",
+        ],
+    );
+    assert_eq!(code, Some(0), "{body}");
+    assert_eq!(body["data"]["workspace"]["alg"], "hs256");
+    let written: Value =
+        serde_json::from_str(&std::fs::read_to_string(root.path().join(".jwt-tester")).unwrap())
+            .unwrap();
+    assert_eq!(written["project"], "demo");
+
+    // No --project, --alg or claims: all three come from the workspace two levels up.
+    let (code, encoded) = run_in(&vault, &nested, &["encode", "--no-iat"]);
+    assert_eq!(code, Some(0), "{encoded}");
+    let token = encoded["data"]["token"].as_str().expect("token");
+    let (code, verified) = run_in(&vault, &nested, &["verify", token]);
+    assert_eq!(code, Some(0), "{verified}");
+    assert_eq!(verified["data"]["claims"]["sub"], "alice");
+    let (code, keys) = run_in(&vault, &nested, &["vault", "key", "list"]);
+    assert_eq!(code, Some(0), "{keys}");
+    assert_eq!(keys["data"]["keys"][0]["name"], "signing");
+
+    // A direct key still wins over the workspace project.
+    let (code, _) = run_in(&vault, &nested, &["verify", "--secret", "wrong", token]);
+    assert_eq!(code, Some(11));
+
+    let (code, _) = run_in(&vault, root.path(), &["init", "--project", "demo"]);
+    assert_eq!(code, Some(12));
+    let (code, _) = run_in(
+        &vault,
+        root.path(),
+        &["init", "--project", "missing", "--force"],
+    );
+    assert_eq!(code, Some(13));
+}
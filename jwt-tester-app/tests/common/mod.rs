@@ -68,9 +68,29 @@ impl TestVault {
     }
 
     pub fn cmd(&self) -> Command {
-        let mut cmd = assert_cmd::cargo::cargo_bin_cmd!();
+        Command::from_std(self.std_cmd())
+    }
+
+    /// A command against this vault's keychain settings but another data directory, with the
+    /// file keychain in `<data_dir>/keychain` (where `vault relocate` puts it).
+    pub fn cmd_at(&self, data_dir: &Path) -> Command {
+        let mut cmd = self.std_cmd_at(data_dir);
+        cmd.env("JWT_TESTER_KEYCHAIN_DIR", data_dir.join("keychain"));
+        Command::from_std(cmd)
+    }
+
+    pub fn data_dir(&self) -> &Path {
+        self.dir.path()
+    }
+
+    fn std_cmd(&self) -> std::process::Command {
+        self.std_cmd_at(self.dir.path())
+    }
+
+    fn std_cmd_at(&self, data_dir: &Path) -> std::process::Command {
+        let mut cmd = std::process::Command::new(assert_cmd::cargo::cargo_bin!("jwt-tester"));
         cmd.arg("--data-dir")
-            .arg(self.dir.path())
+            .arg(data_dir)
             .env("JWT_TESTER_KEYCHAIN_SERVICE", &self.service)
             .env("JWT_TESTER_KEYCHAIN_BACKEND", "file")
             .env("JWT_TESTER_KEYCHAIN_PASSPHRASE", &self.passphrase)
@@ -80,6 +100,22 @@ impl TestVault {
         cmd
     }
 
+    /// Starts a long-running command (a server) against this vault with stdout piped. The caller
+    /// kills the child.
+    pub fn spawn(&self, args: &[&str]) -> std::process::Child {
+        self.spawn_with_env(args, &[])
+    }
+
+    pub fn spawn_with_env(&self, args: &[&str], env: &[(&str, &Path)]) -> std::process::Child {
+        self.std_cmd()
+            .args(args)
+            .envs(env.iter().copied())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::null())
+            .spawn()
+            .expect("failed to spawn jwt-tester")
+    }
+
     pub fn run_json(&self, args: &[&str]) -> Value {
         let output = self
             .cmd()
@@ -96,6 +132,10 @@ impl TestVault {
         serde_json::from_slice(&output.stdout).expect("invalid JSON output")
     }
 
+    pub fn keychain_dir(&self) -> &Path {
+        &self.keychain_dir
+    }
+
     pub fn assert_exit(&self, args: &[&str], code: i32) {
         self.cmd().args(args).assert().failure().code(code);
     }
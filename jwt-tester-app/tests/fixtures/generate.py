@@ -1,10 +1,12 @@
 from __future__ import annotations
 
 import base64
+import datetime
 import json
 from pathlib import Path
 
-from cryptography.hazmat.primitives import serialization
+from cryptography import x509
+from cryptography.hazmat.primitives import hashes, serialization
 from cryptography.hazmat.primitives.asymmetric import ec, ed25519, rsa
 
 ROOT = Path(__file__).resolve().parent
@@ -52,6 +54,14 @@ write_bytes(ROOT / "rsa_private.pem", rsa_private_pem)
 write_bytes(ROOT / "rsa_public.pem", rsa_public_pem)
 write_bytes(ROOT / "rsa_private.der", rsa_private_der)
 write_bytes(ROOT / "rsa_public.der", rsa_public_der)
+write_bytes(
+    ROOT / "rsa_openssh",
+    rsa_key.private_bytes(
+        serialization.Encoding.PEM,
+        serialization.PrivateFormat.OpenSSH,
+        serialization.NoEncryption(),
+    ),
+)
 
 # EC P-256
 _ec_key = ec.generate_private_key(ec.SECP256R1())
@@ -78,6 +88,22 @@ write_bytes(ROOT / "ec256_public.pem", ec_public_pem)
 write_bytes(ROOT / "ec256_private.der", ec_private_der)
 write_bytes(ROOT / "ec256_public.der", ec_public_der)
 
+# Self-signed certificate for the P-256 key (x5u/x5c tests); valid for a century so the
+# notAfter is a GeneralizedTime.
+_name = x509.Name([x509.NameAttribute(x509.NameOID.COMMON_NAME, "jwt-tester fixture")])
+_now = datetime.datetime.now(datetime.timezone.utc)
+ec_cert = (
+    x509.CertificateBuilder()
+    .subject_name(_name)
+    .issuer_name(_name)
+    .public_key(_ec_key.public_key())
+    .serial_number(x509.random_serial_number())
+    .not_valid_before(_now)
+    .not_valid_after(_now + datetime.timedelta(days=36500))
+    .sign(_ec_key, hashes.SHA256())
+)
+write_bytes(ROOT / "ec256_cert.pem", ec_cert.public_bytes(serialization.Encoding.PEM))
+
 # EC P-384
 _ec384_key = ec.generate_private_key(ec.SECP384R1())
 ec384_private_pem = _ec384_key.private_bytes(
@@ -127,6 +153,16 @@ write_bytes(ROOT / "ed25519_private.pem", ed_private_pem)
 write_bytes(ROOT / "ed25519_public.pem", ed_public_pem)
 write_bytes(ROOT / "ed25519_private.der", ed_private_der)
 write_bytes(ROOT / "ed25519_public.der", ed_public_der)
+# OpenSSH format; ed25519_openssh_encrypted is this key re-encrypted with
+# `ssh-keygen -p -N fixture-pass -f <copy>` (cryptography needs bcrypt for that).
+write_bytes(
+    ROOT / "ed25519_openssh",
+    ed_key.private_bytes(
+        serialization.Encoding.PEM,
+        serialization.PrivateFormat.OpenSSH,
+        serialization.NoEncryption(),
+    ),
+)
 
 # JWKS (RSA + HMAC)
 rsa_numbers = rsa_key.public_key().public_numbers()
@@ -55,6 +55,7 @@ export default function App() {
 
   // View State
   const [currentView, setCurrentView] = useState("dashboard");
+  const [openedFile, setOpenedFile] = useState(null);
 
   const persistDefaultProject = useCallback((projectId) => {
     const nextId = projectId || null;
@@ -128,6 +129,16 @@ export default function App() {
 
   useEffect(() => {
     loadVault();
+    // Set when the UI was started by double-clicking a .jwt/.jwks file.
+    api("/api/opened-file")
+      .then((res) => {
+        if (res && res.data) {
+          setOpenedFile(res.data);
+          setCurrentView("inspector");
+          setStatus(`Opened ${res.data.name}`);
+        }
+      })
+      .catch(() => {});
   }, []); // Initial load
 
   const selectedProject = useMemo(
@@ -177,7 +188,7 @@ export default function App() {
       case "builder":
         return <TokenBuilder {...commonProps} />;
       case "inspector":
-        return <TokenInspector setStatus={setStatus} />;
+        return <TokenInspector setStatus={setStatus} openedFile={openedFile} />;
       case "verifier":
         return <TokenVerifier {...commonProps} />;
       case "settings":
@@ -32,6 +32,10 @@ export async function api(path, options = {}) {
     ...(options.headers || {}),
   };
   const res = await fetch(path, { ...options, headers });
+  const rotated = res.headers && res.headers.get("X-CSRF-Token");
+  if (rotated) {
+    cachedCsrf = rotated;
+  }
   const body = await res.json().catch(() => null);
   if (!res.ok) {
     const msg = body && body.error ? body.error : `HTTP ${res.status}`;
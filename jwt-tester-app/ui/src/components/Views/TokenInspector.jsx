@@ -1,11 +1,23 @@
 import React, { useState } from "react";
 import { api } from "../../api.js";
 
-export function TokenInspector({ setStatus }) {
-    const [token, setToken] = useState("");
+// A file passed to `jwt-tester ui --open-file`: a token fills the input, a JWKS is shown as-is.
+const openedOutput = (openedFile) => {
+    if (openedFile?.kind !== "jwks") return "";
+    try {
+        return JSON.stringify(JSON.parse(openedFile.content), null, 2);
+    } catch (err) {
+        return openedFile.content;
+    }
+};
+
+export function TokenInspector({ setStatus, openedFile }) {
+    const [token, setToken] = useState(() =>
+        openedFile?.kind === "jwt" ? openedFile.content : ""
+    );
     const [dateMode, setDateMode] = useState("");
     const [showSegments, setShowSegments] = useState(false);
-    const [output, setOutput] = useState("");
+    const [output, setOutput] = useState(() => openedOutput(openedFile));
 
     const handleInspect = async () => {
         if (!token.trim()) {
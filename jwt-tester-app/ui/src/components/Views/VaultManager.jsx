@@ -45,6 +45,8 @@ function AddKeyModal({ isOpen, onClose, projectId, onRefresh, setStatus, setGene
     const [hmacBytes, setHmacBytes] = useState("32");
     const [rsaBits, setRsaBits] = useState("2048");
     const [ecCurve, setEcCurve] = useState("P-256");
+    const [edCurve, setEdCurve] = useState("Ed25519");
+    const [rsaUse, setRsaUse] = useState("sig");
 
     useEffect(() => {
         if (!isOpen) {
@@ -118,10 +120,14 @@ function AddKeyModal({ isOpen, onClose, projectId, onRefresh, setStatus, setGene
                 return;
             }
             payload.rsa_bits = bits;
+            payload.key_use = rsaUse;
         }
         if (kind === "ec") {
             payload.ec_curve = ecCurve;
         }
+        if (kind === "eddsa") {
+            payload.ed_curve = edCurve;
+        }
 
         const res = await api("/api/vault/keys/generate", {
             method: "POST",
@@ -134,6 +140,8 @@ function AddKeyModal({ isOpen, onClose, projectId, onRefresh, setStatus, setGene
                       key: res.data.key,
                       material: res.data.material,
                       format: res.data.format || "pem",
+                      publicPem: res.data.public_pem || null,
+                      publicJwk: res.data.public_jwk || null,
                   }
                 : null
         );
@@ -258,19 +266,33 @@ function AddKeyModal({ isOpen, onClose, projectId, onRefresh, setStatus, setGene
                             </select>
                         </label>
                     )}
+                    {kind === "rsa" && (
+                        <label className="field">
+                            <span>Key Use</span>
+                            <select value={rsaUse} onChange={(e) => setRsaUse(e.target.value)}>
+                                <option value="sig">Signing (sig)</option>
+                                <option value="enc">Encryption (enc, RSA-OAEP)</option>
+                            </select>
+                        </label>
+                    )}
                     {kind === "ec" && (
                         <label className="field">
                             <span>Curve</span>
                             <select value={ecCurve} onChange={(e) => setEcCurve(e.target.value)}>
                                 <option value="P-256">P-256</option>
                                 <option value="P-384">P-384</option>
+                                <option value="secp256k1">secp256k1 (ES256K)</option>
                             </select>
                         </label>
                     )}
                     {kind === "eddsa" && (
-                        <div style={{ padding: '0.5rem 0', color: 'var(--text-muted)' }}>
-                            Algorithm: Ed25519
-                        </div>
+                        <label className="field">
+                            <span>Curve</span>
+                            <select value={edCurve} onChange={(e) => setEdCurve(e.target.value)}>
+                                <option value="Ed25519">Ed25519</option>
+                                <option value="Ed448">Ed448</option>
+                            </select>
+                        </label>
                     )}
                     {kind === "jwks" && (
                         <div style={{ padding: '0.5rem 0', color: 'var(--error)' }}>
@@ -433,6 +455,7 @@ function KeysSection({ projectId, keys, defaultKeyId, onRefresh, setStatus, setG
                                             </span>
                                             <span className="mono">{key.id.slice(0, 8)}</span>
                                             {key.kid && <span>• kid: {key.kid}</span>}
+                                            {key.fingerprint && <span className="mono" title={key.fingerprint}>• {key.fingerprint.slice(0, 19)}</span>}
                                         </div>
                                         {key.tags?.length > 0 && (
                                              <div className="list-sub" style={{ marginTop: '0.25rem' }}>
@@ -810,6 +833,28 @@ export function VaultManager({
                                 ••••••••••••••••••••••••••••••
                             </div>
                         )}
+                        {generated.publicJwk && (
+                            <label className="field" style={{ marginTop: '1rem' }}>
+                                <span>Public JWK</span>
+                                <textarea
+                                    readOnly
+                                    value={JSON.stringify(generated.publicJwk, null, 2)}
+                                    rows={6}
+                                    style={{ fontFamily: "monospace", fontSize: "0.85rem", width: '100%' }}
+                                />
+                            </label>
+                        )}
+                        {generated.publicPem && (
+                            <label className="field">
+                                <span>Public Key (PEM)</span>
+                                <textarea
+                                    readOnly
+                                    value={generated.publicPem}
+                                    rows={4}
+                                    style={{ fontFamily: "monospace", fontSize: "0.85rem", width: '100%' }}
+                                />
+                            </label>
+                        )}
                     </div>
                 </div>
             )}
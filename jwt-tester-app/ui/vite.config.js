@@ -16,10 +16,15 @@ export default defineConfig({
     globals: true,
   },
   server: {
+    host: "127.0.0.1",
+    // `jwt-tester ui --dev` picks a free port and passes it here; never drift to another one.
+    port: Number(process.env.JWT_TESTER_UI_DEV_PORT) || 5173,
+    strictPort: true,
     proxy: {
       "/api": {
         target: process.env.JWT_TESTER_API_URL || "http://127.0.0.1:3000",
         changeOrigin: true,
+        ws: false,
       },
     },
   },